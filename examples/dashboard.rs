@@ -0,0 +1,138 @@
+#![deny(warnings)]
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, ascii::FONT_6X10, MonoTextStyle},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use embedded_hal::delay::DelayNs;
+use epd_waveshare::{
+    color::Color,
+    epd2in9bc::{Display2in9bc, Epd2in9bc},
+    graphics::VarDisplay,
+    policy::{RefreshHint, RefreshKind, RefreshPolicy},
+    prelude::*,
+};
+use linux_embedded_hal::{
+    spidev::{self, SpidevOptions},
+    sysfs_gpio::Direction,
+    Delay, SPIError, SpidevDevice, SysfsPin,
+};
+
+// activate spi, gpio in raspi-config
+// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
+// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
+//
+// A weather station style dashboard on the 2.9" B/C HAT, with the same pin layout as the
+// epd2in13bc example: a red header and a black body are refreshed in three colors, the
+// clock below is refreshed quickly once a minute. The refresh policy forces a full
+// refresh after 10 quick ones to clean up the ghosting. The booster is turned off in
+// between the refreshes, after an hour the display is put to sleep.
+
+/// Window of the clock, x and width are multiples of 8
+const CLOCK: Rectangle = Rectangle::new(Point::new(16, 120), Size::new(96, 32));
+
+fn main() -> Result<(), epd_waveshare::Error<SPIError>> {
+    let busy = SysfsPin::new(24); // GPIO 24, board J-18
+    busy.export().expect("busy export");
+    while !busy.is_exported() {}
+    busy.set_direction(Direction::In).expect("busy Direction");
+
+    let dc = SysfsPin::new(25); // GPIO 25, board J-22
+    dc.export().expect("dc export");
+    while !dc.is_exported() {}
+    dc.set_direction(Direction::Out).expect("dc Direction");
+
+    let rst = SysfsPin::new(17); // GPIO 17, board J-11
+    rst.export().expect("rst export");
+    while !rst.is_exported() {}
+    rst.set_direction(Direction::Out).expect("rst Direction");
+
+    let mut spi = SpidevDevice::open("/dev/spidev0.0").expect("spidev directory");
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(4_000_000)
+        .mode(spidev::SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options).expect("spi configuration");
+
+    let mut delay = Delay {};
+
+    let mut epd =
+        Epd2in9bc::new(&mut spi, busy, dc, rst, &mut delay, None).expect("eink initalize error");
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+
+    let mut policy = RefreshPolicy::new().with_max_quick_refreshes(10);
+    let (x, y) = (CLOCK.top_left.x as u32, CLOCK.top_left.y as u32);
+    let (width, height) = (CLOCK.size.width, CLOCK.size.height);
+    let mut old_clock = [0u8; 96 / 8 * 32];
+    let mut new_clock = [0u8; 96 / 8 * 32];
+
+    for minute in 0..60 {
+        match policy.next_refresh(RefreshHint::new(u64::from(minute) * 60)) {
+            RefreshKind::Full => {
+                println!("{}: full refresh", minute);
+                let (bw, chromatic) = render(minute);
+                epd.update_color_frame(&mut spi, &mut delay, bw.buffer(), chromatic.buffer())?;
+                epd.display_frame(&mut spi, &mut delay)?;
+            }
+            RefreshKind::Quick => {
+                println!("{}: clock", minute);
+                render_clock(&mut old_clock, minute - 1);
+                render_clock(&mut new_clock, minute);
+                epd.update_partial_old_frame(
+                    &mut spi, &mut delay, &old_clock, x, y, width, height,
+                )?;
+                epd.update_partial_new_frame(
+                    &mut spi, &mut delay, &new_clock, x, y, width, height,
+                )?;
+                epd.display_partial_frame(&mut spi, &mut delay, x, y, width, height)?;
+            }
+        }
+        epd.power_off(&mut spi, &mut delay)?;
+        delay.delay_ms(60_000);
+    }
+
+    println!("Finished - going to sleep");
+    epd.sleep(&mut spi, &mut delay)
+}
+
+fn draw_clock<D: DrawTarget<Color = Color>>(target: &mut D, origin: Point, minute: u32) {
+    let style = MonoTextStyle::new(&FONT_10X20, Color::Black);
+    let time = format!("12:{:02}", minute);
+    let _ =
+        Text::with_baseline(&time, origin + Point::new(8, 6), style, Baseline::Top).draw(target);
+}
+
+/// Both planes of the whole layout, black on the chromatic plane shows up red
+fn render(minute: u32) -> (Display2in9bc, Display2in9bc) {
+    let mut bw = Display2in9bc::default();
+    let mut chromatic = Display2in9bc::default();
+    bw.clear(Color::White).ok();
+    chromatic.clear(Color::White).ok();
+
+    let _ = Rectangle::new(Point::zero(), Size::new(128, 32))
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(&mut chromatic);
+    let header = MonoTextStyle::new(&FONT_10X20, Color::White);
+    let _ = Text::with_baseline("Weather", Point::new(8, 6), header, Baseline::Top)
+        .draw(&mut chromatic);
+
+    let body = MonoTextStyle::new(&FONT_6X10, Color::Black);
+    let _ =
+        Text::with_baseline("21 C  sunny", Point::new(8, 48), body, Baseline::Top).draw(&mut bw);
+    let _ =
+        Text::with_baseline("48 %  1013 hPa", Point::new(8, 64), body, Baseline::Top).draw(&mut bw);
+    draw_clock(&mut bw, CLOCK.top_left, minute);
+
+    (bw, chromatic)
+}
+
+/// The clock alone, in the buffer of its window
+fn render_clock(buffer: &mut [u8], minute: u32) {
+    let mut window = VarDisplay::<Color>::new(CLOCK.size.width, CLOCK.size.height, buffer, false)
+        .expect("clock buffer");
+    window.clear(Color::White).ok();
+    draw_clock(&mut window, Point::zero(), minute);
+}
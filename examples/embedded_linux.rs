@@ -0,0 +1,44 @@
+#![deny(warnings)]
+
+//! Raspberry Pi with the Waveshare e-Paper HAT and a 4.2" display
+//!
+//! Run with `cargo run --example embedded_linux --features linux` (as root for sysfs).
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyle},
+    prelude::*,
+    text::Text,
+};
+use embedded_hal::delay::DelayNs;
+use epd_waveshare::{color::*, epd4in2::*, graphics::VarDisplay, linux::*, prelude::*};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut spi = spidev_default("/dev/spidev0.0")?;
+    let pins = LinuxEpdPins::waveshare_hat().export()?;
+    let mut delay = Delay;
+    let mut epd = Epd4in2::new(&mut spi, pins.busy, pins.dc, pins.rst, &mut delay, None)?;
+
+    epd.clear_frame(&mut spi, &mut delay)?;
+    epd.display_frame(&mut spi, &mut delay)?;
+
+    let mut display = Display4in2::default();
+    display.clear(Color::White).ok();
+    let style = MonoTextStyle::new(&FONT_10X20, Color::Black);
+    Text::new("Hello from linux!", Point::new(20, 40), style).draw(&mut display)?;
+    epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+
+    // count up in a 80x40 window with quick partial refreshes
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+    let mut buffer = [0u8; 80 / 8 * 40];
+    for i in 0..5 {
+        let mut window = VarDisplay::<Color>::new(80, 40, &mut buffer, false).expect("buffer size");
+        window.clear(Color::White).ok();
+        Text::new(&format!("{i}"), Point::new(30, 28), style).draw(&mut window)?;
+        epd.update_partial_frame(&mut spi, &mut delay, window.buffer(), 160, 120, 80, 40)?;
+        epd.display_frame(&mut spi, &mut delay)?;
+        delay.delay_ms(1_000);
+    }
+
+    epd.sleep(&mut spi, &mut delay)?;
+    Ok(())
+}
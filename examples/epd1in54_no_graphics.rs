@@ -12,10 +12,10 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), SPIError> {
+fn main() -> Result<(), epd_waveshare::Error<SPIError>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
-    let mut spi = SpidevDevice::open("/dev/spidev0.0")?;
+    let mut spi = SpidevDevice::open("/dev/spidev0.0").map_err(epd_waveshare::Error::spi)?;
     let options = SpidevOptions::new()
         .bits_per_word(8)
         .max_speed_hz(4_000_000)
@@ -66,7 +66,7 @@ fn main() -> Result<(), SPIError> {
 
     // Speeddemo
     epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
-    let small_buffer = [Color::Black.get_byte_value(); 32]; //16x16
+    let small_buffer = [Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 32]; //16x16
     let number_of_runs = 1;
     for i in 0..number_of_runs {
         let offset = i * 8 % 150;
@@ -87,13 +87,15 @@ fn main() -> Result<(), SPIError> {
     epd.display_frame(&mut spi, &mut delay)?;
 
     // Draw some squares
-    let small_buffer = [Color::Black.get_byte_value(); 3200]; //160x160
+    let small_buffer =
+        [Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 3200]; //160x160
     epd.update_partial_frame(&mut spi, &mut delay, &small_buffer, 20, 20, 160, 160)?;
 
-    let small_buffer = [Color::White.get_byte_value(); 800]; //80x80
+    let small_buffer =
+        [Color::White.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 800]; //80x80
     epd.update_partial_frame(&mut spi, &mut delay, &small_buffer, 60, 60, 80, 80)?;
 
-    let small_buffer = [Color::Black.get_byte_value(); 8]; //8x8
+    let small_buffer = [Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 8]; //8x8
     epd.update_partial_frame(&mut spi, &mut delay, &small_buffer, 96, 96, 8, 8)?;
 
     // Display updated frame
@@ -1,5 +1,4 @@
 #![deny(warnings)]
-#![deny(warnings)]
 
 use embedded_graphics::{
     mono_font::MonoTextStyleBuilder,
@@ -24,7 +23,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), SPIError> {
+fn main() -> Result<(), epd_waveshare::Error<SPIError>> {
     // Configure SPI
     // Settings are taken from
     let mut spi = SpidevDevice::open("/dev/spidev0.0").expect("spidev directory");
@@ -69,7 +68,8 @@ fn main() -> Result<(), SPIError> {
 
     let (x, y, width, height) = (50, 50, 250, 250);
 
-    let mut buffer = [epd4in2::DEFAULT_BACKGROUND_COLOR.get_byte_value(); 62500]; //250*250
+    let mut buffer = [epd4in2::DEFAULT_BACKGROUND_COLOR
+        .fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 62500]; //250*250
     let mut display = VarDisplay::new(width, height, &mut buffer, false).unwrap();
     display.set_rotation(DisplayRotation::Rotate0);
     draw_text(&mut display, "Rotate 0!", 5, 50);
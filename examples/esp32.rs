@@ -0,0 +1,89 @@
+#![deny(warnings)]
+
+//! Recommended settings for ESP32 boards running esp-hal
+//!
+//! The SPI DMA of the ESP32 can't transfer more than 32 KB at once, while a
+//! 7.5" frame is 48000 bytes, so the writes have to be split with
+//! `set_max_transfer_size`. Polling the busy pin without a delay for the
+//! several seconds a refresh takes trips the task watchdog, so keep the idle
+//! loop delay (the last argument of `new`) above zero.
+//!
+//! The driver only needs the embedded-hal traits, so `run` below is generic.
+//! With esp-hal the peripherals are created like this (adapt the pins to your
+//! wiring):
+//!
+//! ```text
+//! let peripherals = esp_hal::init(esp_hal::Config::default());
+//! let spi = Spi::new(peripherals.SPI2, spi::master::Config::default()
+//!         .with_frequency(4.MHz())
+//!         .with_mode(spi::Mode::_0))?
+//!     .with_sck(peripherals.GPIO18)
+//!     .with_mosi(peripherals.GPIO23)
+//!     .with_dma(peripherals.DMA_SPI2)
+//!     .with_buffers(dma_rx_buf, dma_tx_buf);
+//! let cs = Output::new(peripherals.GPIO5, Level::High);
+//! let mut spi = ExclusiveDevice::new(spi, cs, Delay::new())?;
+//! let busy = Input::new(peripherals.GPIO4, Pull::None);
+//! let dc = Output::new(peripherals.GPIO17, Level::Low);
+//! let rst = Output::new(peripherals.GPIO16, Level::High);
+//! run(&mut spi, busy, dc, rst, &mut Delay::new())?;
+//! ```
+
+use embedded_graphics::{
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle},
+};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+use epd_waveshare::{
+    color::*,
+    epd7in5_v2::{Display7in5, Epd7in5},
+    prelude::*,
+};
+
+/// Largest chunk the ESP32 SPI DMA transfers at once, stay well below its 32 KB limit
+const MAX_TRANSFER_SIZE: usize = 4096;
+
+/// Sleep 10ms between busy pin reads, giving other tasks (and the watchdog) a chance to run
+const BUSY_DELAY_US: u32 = 10_000;
+
+fn main() {
+    // Nothing to drive on the host, `run` is meant to be called from the esp-hal
+    // entry point as shown above
+    println!("flash this example to an ESP32, see the module docs for the setup");
+}
+
+#[allow(dead_code)]
+fn run<SPI, BUSY, DC, RST, DELAY>(
+    spi: &mut SPI,
+    busy: BUSY,
+    dc: DC,
+    rst: RST,
+    delay: &mut DELAY,
+) -> Result<(), epd_waveshare::Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    // 48 KB are too much for the main task stack, keep the buffer in a static
+    static mut DISPLAY: Display7in5 = Display7in5::new();
+
+    let mut epd = Epd7in5::new(spi, busy, dc, rst, delay, Some(BUSY_DELAY_US))?;
+    epd.set_max_transfer_size(Some(MAX_TRANSFER_SIZE));
+
+    // SAFETY: `run` is only called once from the entry point
+    let display = unsafe { &mut *core::ptr::addr_of_mut!(DISPLAY) };
+    display.clear(Color::White).ok();
+    let _ = Circle::new(Point::new(300, 140), 200)
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(display);
+
+    epd.update_and_display_frame(spi, display.buffer(), delay)?;
+    epd.sleep(spi, delay)
+}
@@ -0,0 +1,85 @@
+#![deny(warnings)]
+
+//! Raspberry Pi with the Waveshare e-Paper HAT, the panel is picked at runtime
+//!
+//! Run with `cargo run --example linux_any_panel --features linux,alloc -- 7in5_v2`
+//! (as root for sysfs). The frame buffer is a [`DynDisplay`] sized for the selected panel.
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyle},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+use epd_waveshare::{
+    color::*,
+    epd2in9_v2::Epd2in9,
+    epd4in2::Epd4in2,
+    epd7in5_v2::Epd7in5,
+    graphics::{DynDisplay, FlushableDisplay},
+    linux::*,
+    prelude::*,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let panel = std::env::args().nth(1).unwrap_or_else(|| "4in2".into());
+    let mut spi = spidev_default("/dev/spidev0.0")?;
+    let pins = LinuxEpdPins::waveshare_hat().export()?;
+    let mut delay = Delay;
+    let (busy, dc, rst) = (pins.busy, pins.dc, pins.rst);
+
+    match panel.as_str() {
+        "2in9_v2" => {
+            let epd = Epd2in9::new(&mut spi, busy, dc, rst, &mut delay, None)?;
+            show(epd, &mut spi, &mut delay, &panel)
+        }
+        "4in2" => {
+            let epd = Epd4in2::new(&mut spi, busy, dc, rst, &mut delay, None)?;
+            show(epd, &mut spi, &mut delay, &panel)
+        }
+        "7in5_v2" => {
+            let epd = Epd7in5::new(&mut spi, busy, dc, rst, &mut delay, None)?;
+            show(epd, &mut spi, &mut delay, &panel)
+        }
+        _ => Err(format!("unknown panel {panel}, expected 2in9_v2, 4in2 or 7in5_v2").into()),
+    }
+}
+
+/// Draws the same scene on any black/white panel
+fn show<SPI, BUSY, DC, RST, DELAY, EPD>(
+    mut epd: EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    SPI: SpiDevice,
+    SPI::Error: std::error::Error + 'static,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    let mut display = DynDisplay::<Color>::new(EPD::WIDTH, EPD::HEIGHT, false);
+    display.set_rotation(DisplayRotation::Rotate90);
+    display.clear(Color::White).ok();
+
+    let size = display.size();
+    Rectangle::new(Point::zero(), size)
+        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 4))
+        .draw(&mut display)?;
+    let style = MonoTextStyle::new(&FONT_10X20, Color::Black);
+    let text = format!("{name}: {}x{}", size.width, size.height);
+    Text::new(&text, Point::new(12, 30), style).draw(&mut display)?;
+
+    display.flush(&mut epd, spi, delay)?;
+    epd.display_frame(spi, delay)?;
+    epd.sleep(spi, delay)?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+#![deny(warnings)]
+
+//! Draws an RGB image onto a tri-color frame of the 4.2" b/c size and previews it on the
+//! b/w 4.2" driver
+//!
+//! The image is embedded as raw RGB888 pixels to keep the example free of further
+//! dependencies. A `tinybmp::Bmp<Rgb888>` (or `tinytga::Tga<Rgb888>`) is wrapped the same
+//! way:
+//!
+//! ```text
+//! let bmp = Bmp::<Rgb888>::from_slice(include_bytes!("logo.bmp")).unwrap();
+//! let image = QuantizedImage::<_, TriColor>::new(&bmp, Quantizer::default());
+//! ```
+//!
+//! On the host the quantized frame is printed, `run` shows how to send it to a panel.
+
+use embedded_graphics::{
+    image::{Image, ImageRaw},
+    pixelcolor::Rgb888,
+    prelude::*,
+};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+use epd_waveshare::{
+    buffer_len,
+    color::*,
+    epd4in2::{Epd4in2, HEIGHT, WIDTH},
+    graphics::{ChromaticAs, Display},
+    images::{QuantizedImage, Quantizer},
+    prelude::*,
+};
+
+const LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// The frame of a 4.2" b/c panel
+type Display4in2bc = Display<WIDTH, HEIGHT, false, { LEN * 2 }, TriColor>;
+
+/// 8x8 pixels: a red heart with a dark outline on a light gray background
+fn logo() -> [u8; 8 * 8 * 3] {
+    const ROWS: [&str; 8] = [
+        "..oo.oo.", ".oRRoRRo", ".oRRRRRo", ".oRRRRRo", "..oRRRo.", "...oRo..", "....o...",
+        "........",
+    ];
+    let mut data = [0; 8 * 8 * 3];
+    for (pixel, c) in data.chunks_exact_mut(3).zip(ROWS.concat().chars()) {
+        pixel.copy_from_slice(match c {
+            'R' => &[220, 30, 40],
+            'o' => &[40, 30, 30],
+            _ => &[210, 210, 210],
+        });
+    }
+    data
+}
+
+fn draw(display: &mut Display4in2bc) {
+    let data = logo();
+    let raw = ImageRaw::<Rgb888>::new(&data, 8);
+    let image = QuantizedImage::<_, TriColor>::new(&raw, Quantizer::default());
+    display.clear(TriColor::White).ok();
+    Image::new(&image, Point::new(196, 146)).draw(display).ok();
+}
+
+fn main() {
+    let mut display = Display4in2bc::default();
+    draw(&mut display);
+    for y in 146..154 {
+        let row: String = (196..204)
+            .map(|x| match display.get_pixel(x, y) {
+                Some(TriColor::Chromatic) => 'R',
+                Some(TriColor::Black) => '#',
+                _ => '.',
+            })
+            .collect();
+        println!("{row}");
+    }
+}
+
+#[allow(dead_code)]
+fn run<SPI, BUSY, DC, RST, DELAY>(
+    spi: &mut SPI,
+    busy: BUSY,
+    dc: DC,
+    rst: RST,
+    delay: &mut DELAY,
+) -> Result<(), epd_waveshare::Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    let mut display = Display4in2bc::default();
+    draw(&mut display);
+
+    // red is dithered on the b/w panel until the b/c one arrives
+    let mut preview = [0u8; LEN];
+    display.to_bw_buffer(&mut preview, ChromaticAs::Dithered);
+    let mut epd = Epd4in2::new(spi, busy, dc, rst, delay, None)?;
+    epd.update_and_display_frame(spi, &preview, delay)?;
+    epd.sleep(spi, delay)
+}
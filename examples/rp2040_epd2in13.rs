@@ -0,0 +1,138 @@
+#![deny(warnings)]
+
+//! Setup of the Pico-ePaper-2.13 (V3) on a Raspberry Pi Pico with rp2040-hal
+//!
+//! The display sits on SPI1 of the Pico: CLK GP10, DIN GP11, CS GP9, DC GP8,
+//! RST GP12 and BUSY GP13. The rp2040-hal `Spi` is a bus, the drivers need a
+//! `SpiDevice` including the chip select, which `ExclusiveDevice` of
+//! embedded-hal-bus provides. The pins work as they are: their error type is
+//! `Infallible` and the drivers ignore pin errors anyway. The SysTick delay of
+//! cortex-m 0.7 only has a `delay_us(u32)` of its own, `delay::DelayUsFn` of the
+//! `delay-adapter` feature turns it into the `DelayNs` the drivers need.
+//!
+//! The driver only needs the embedded-hal traits, so `run` below is generic.
+//! With rp2040-hal 0.10 the peripherals are created like this:
+//!
+//! ```text
+//! let mut pac = pac::Peripherals::take().unwrap();
+//! let core = pac::CorePeripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(pac.WATCHDOG);
+//! let clocks = init_clocks_and_plls(XTAL_FREQ_HZ, pac.XOSC, pac.CLOCKS, pac.PLL_SYS,
+//!     pac.PLL_USB, &mut pac.RESETS, &mut watchdog).ok().unwrap();
+//! let sio = Sio::new(pac.SIO);
+//! let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//!
+//! let sclk = pins.gpio10.into_function::<FunctionSpi>();
+//! let mosi = pins.gpio11.into_function::<FunctionSpi>();
+//! let spi_bus = Spi::<_, _, _, 8>::new(pac.SPI1, (mosi, sclk)).init(
+//!     &mut pac.RESETS, clocks.peripheral_clock.freq(), 4.MHz(), embedded_hal::spi::MODE_0);
+//! let cs = pins.gpio9.into_push_pull_output_in_state(PinState::High);
+//! let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+//! let mut spi = ExclusiveDevice::new(spi_bus, cs, timer).unwrap();
+//!
+//! let dc = pins.gpio8.into_push_pull_output();
+//! let rst = pins.gpio12.into_push_pull_output();
+//! let busy = pins.gpio13.into_pull_up_input();
+//!
+//! let mut systick = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+//! run(&mut spi, busy, dc, rst, |us| systick.delay_us(us)).unwrap();
+//! ```
+//!
+//! The rp2040-hal `Timer` implements `DelayNs` as well and can be passed to the
+//! drivers directly, the adapter is for firmware built around the SysTick delay.
+//!
+//! Without a Pico at hand, check the generic part on the host with
+//! `cargo check --example rp2040_epd2in13 --features delay-adapter`, and the crate
+//! for the Pico with
+//! `cargo check --lib --target thumbv6m-none-eabi --no-default-features --features delay-adapter,graphics,epd2in13_v3`
+//! (after `rustup target add thumbv6m-none-eabi`).
+
+use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
+    prelude::*,
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+use epd_waveshare::{
+    color::*,
+    delay::DelayUsFn,
+    epd2in13_v2::{Display2in13, Epd2in13},
+    graphics::DisplayRotation,
+    prelude::*,
+};
+
+/// Minutes shown by the clock before the display goes to sleep
+const CLOCK_MINUTES: u32 = 10;
+
+fn main() {
+    // Nothing to drive on the host, `run` is meant to be called from the rp2040-hal
+    // entry point as shown above
+    println!("flash this example to a Raspberry Pi Pico, see the module docs for the setup");
+}
+
+#[allow(dead_code)]
+fn run<SPI, BUSY, DC, RST, F>(
+    spi: &mut SPI,
+    busy: BUSY,
+    dc: DC,
+    rst: RST,
+    delay_us: F,
+) -> Result<(), epd_waveshare::Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    F: FnMut(u32),
+{
+    let mut delay = DelayUsFn::new(delay_us);
+    let mut epd = Epd2in13::new(spi, busy, dc, rst, &mut delay, None)?;
+
+    // a full refresh to start from a clean panel
+    let mut display = Display2in13::default();
+    display.set_rotation(DisplayRotation::Rotate90);
+    display.clear(Color::White).ok();
+    draw_text(&mut display, "Pico-ePaper-2.13", 5, 5);
+    epd.update_and_display_frame(spi, display.buffer(), &mut delay)?;
+
+    // the clock only changes a few pixels, the quick LUT refreshes them without
+    // flashing the whole panel
+    epd.set_refresh(spi, &mut delay, RefreshLut::Quick)?;
+    for minute in 0..CLOCK_MINUTES {
+        let time = clock_text(minute / 60, minute % 60);
+        draw_text(&mut display, core::str::from_utf8(&time).unwrap(), 5, 50);
+        epd.update_and_display_frame(spi, display.buffer(), &mut delay)?;
+        delay.delay_ms(60_000);
+    }
+
+    // back to the full LUT before sleeping, which clears the ghosting of the quick ones
+    epd.set_refresh(spi, &mut delay, RefreshLut::Full)?;
+    epd.update_and_display_frame(spi, display.buffer(), &mut delay)?;
+    epd.sleep(spi, &mut delay)
+}
+
+fn draw_text(display: &mut Display2in13, text: &str, x: i32, y: i32) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&embedded_graphics::mono_font::ascii::FONT_10X20)
+        .text_color(Color::Black)
+        .background_color(Color::White)
+        .build();
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+    let _ = Text::with_text_style(text, Point::new(x, y), style, text_style).draw(display);
+}
+
+/// `hh:mm` without an allocator, the Pico has no heap by default
+fn clock_text(hours: u32, minutes: u32) -> [u8; 5] {
+    let digit = |value: u32| b'0' + (value % 10) as u8;
+    [
+        digit(hours / 10),
+        digit(hours),
+        b':',
+        digit(minutes / 10),
+        digit(minutes),
+    ]
+}
@@ -0,0 +1,81 @@
+/*
+ * C interface of epd-waveshare, built with the `ffi` cargo feature
+ *
+ * Kept in sync with src/ffi.rs by hand, see there for the documentation.
+ */
+
+#ifndef EPD_WAVESHARE_H
+#define EPD_WAVESHARE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Return codes */
+#define EPD_OK 0
+#define EPD_ERR_NULL (-1)
+#define EPD_ERR_SPI (-2)
+#define EPD_ERR_NEEDS_RESET (-3)
+#define EPD_ERR_BUFFER_LEN (-4)
+#define EPD_ERR_OTHER (-5)
+
+/* Pins passed to the pin callbacks */
+#define EPD_PIN_BUSY 0
+#define EPD_PIN_DC 1
+#define EPD_PIN_RST 2
+
+/* Frame buffer sizes in bytes */
+#define EPD4IN2_BUFFER_LEN 15000
+#define EPD2IN13_BUFFER_LEN 4000
+
+/* Handle sizes in bytes */
+#define EPD4IN2_HANDLE_SIZE 512
+#define EPD2IN13_HANDLE_SIZE 512
+
+typedef struct {
+    /* passed to every callback */
+    void *ctx;
+    /* writes len bytes with the chip select asserted, returns 0 or a negative error */
+    int32_t (*spi_write)(void *ctx, const uint8_t *data, size_t len);
+    /* sets EPD_PIN_DC or EPD_PIN_RST, returns 0 or a negative error */
+    int32_t (*pin_set)(void *ctx, uint8_t pin, bool high);
+    /* reads EPD_PIN_BUSY, returns 1 for high, 0 for low or a negative error */
+    int32_t (*pin_get)(void *ctx, uint8_t pin);
+    /* waits for at least us microseconds */
+    void (*delay_us)(void *ctx, uint32_t us);
+} epd_callbacks_t;
+
+/* Storage for the drivers, owned by the caller and filled by the _new functions */
+typedef struct {
+    _Alignas(8) uint8_t opaque[EPD4IN2_HANDLE_SIZE];
+} epd4in2_t;
+
+typedef struct {
+    _Alignas(8) uint8_t opaque[EPD2IN13_HANDLE_SIZE];
+} epd2in13_t;
+
+/* 4.2" */
+int32_t epd4in2_new(epd4in2_t *handle, const epd_callbacks_t *callbacks);
+int32_t epd4in2_update_frame(epd4in2_t *handle, const uint8_t *buffer, size_t len);
+int32_t epd4in2_display_frame(epd4in2_t *handle);
+int32_t epd4in2_clear(epd4in2_t *handle);
+int32_t epd4in2_sleep(epd4in2_t *handle);
+int32_t epd4in2_wake_up(epd4in2_t *handle);
+
+/* 2.13" V2/V3 */
+int32_t epd2in13_new(epd2in13_t *handle, const epd_callbacks_t *callbacks);
+int32_t epd2in13_update_frame(epd2in13_t *handle, const uint8_t *buffer, size_t len);
+int32_t epd2in13_display_frame(epd2in13_t *handle);
+int32_t epd2in13_clear(epd2in13_t *handle);
+int32_t epd2in13_sleep(epd2in13_t *handle);
+int32_t epd2in13_wake_up(epd2in13_t *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EPD_WAVESHARE_H */
@@ -0,0 +1,437 @@
+//! Showing a sequence of frames in a window of the panel
+//!
+//! [`Animator`] cycles through the frames of a small animation (e.g. a weather icon),
+//! refreshing only its window with [`RegionRefresh::refresh_region_with_lut`]. The
+//! frames are shown with the quick LUT, a [`RefreshPolicy`] inserts a full refresh of
+//! the window to clean up the ghosting, by default at the start of every cycle.
+//!
+//! The crate has no clock: the caller drives the animation by calling
+//! [`Animator::tick`] with the current time in ticks of any unit, the next frame is
+//! only shown once the frame interval passed. A late tick delays the following
+//! frames instead of catching up with several refreshes in a row.
+//!
+//! The frames cover the window, with rows of [line_bytes(width)](crate::line_bytes)
+//! bytes in native (unrotated) panel coordinates. They are either stored up front
+//! or rendered on demand into a scratch buffer with [`RenderedFrames`].
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::Rectangle};
+//!use epd_waveshare::{animation::*, epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let millis_since_boot = || 0;
+//!
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// 4 frames of a 32x32 icon, drawn into the scratch buffer when they are due
+//!let mut scratch = [0xFF; 32 / 8 * 32];
+//!let frames = RenderedFrames::new(4, &mut scratch, |index, buffer: &mut [u8]| {
+//!    buffer.fill(0xFF);
+//!    buffer[index * 4] = 0x00;
+//!});
+//!
+//!// a frame every 2 s, a full refresh every other cycle
+//!let icon = Rectangle::new(Point::new(16, 16), Size::new(32, 32));
+//!let mut animator = Animator::new(frames, icon, 2_000).with_full_refresh_every(2);
+//!
+//!loop {
+//!    animator.tick(&mut epd, &mut spi, &mut delay, millis_since_boot())?;
+//!#   break;
+//!}
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::error::Error;
+use crate::policy::{RefreshHint, RefreshKind, RefreshPolicy};
+use crate::traits::RegionRefresh;
+
+/// Frames of an animation, see [crate::animation]
+pub trait FrameSource {
+    /// Number of frames, at least 1
+    fn frame_count(&self) -> usize;
+
+    /// Frame `index`, less than [`frame_count`](FrameSource::frame_count)
+    fn frame(&mut self, index: usize) -> &[u8];
+}
+
+impl<const N: usize> FrameSource for [&[u8]; N] {
+    fn frame_count(&self) -> usize {
+        N
+    }
+
+    fn frame(&mut self, index: usize) -> &[u8] {
+        self[index]
+    }
+}
+
+impl FrameSource for &[&[u8]] {
+    fn frame_count(&self) -> usize {
+        self.len()
+    }
+
+    fn frame(&mut self, index: usize) -> &[u8] {
+        self[index]
+    }
+}
+
+/// Frames rendered on demand into a scratch buffer
+///
+/// Needs the RAM of a single frame only, `render` is called with the index of the
+/// frame and the scratch buffer whenever the frame is due.
+pub struct RenderedFrames<'a, F> {
+    count: usize,
+    scratch: &'a mut [u8],
+    render: F,
+}
+
+impl<'a, F> RenderedFrames<'a, F>
+where
+    F: FnMut(usize, &mut [u8]),
+{
+    /// `count` frames drawn by `render` into `scratch`
+    pub fn new(count: usize, scratch: &'a mut [u8], render: F) -> Self {
+        RenderedFrames {
+            count,
+            scratch,
+            render,
+        }
+    }
+}
+
+impl<F> FrameSource for RenderedFrames<'_, F>
+where
+    F: FnMut(usize, &mut [u8]),
+{
+    fn frame_count(&self) -> usize {
+        self.count
+    }
+
+    fn frame(&mut self, index: usize) -> &[u8] {
+        (self.render)(index, self.scratch);
+        self.scratch
+    }
+}
+
+/// A frame shown by [`Animator::tick`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationStep {
+    /// Index of the frame
+    pub frame: usize,
+    /// Kind of the refresh of the window
+    pub kind: RefreshKind,
+}
+
+/// Shows the frames of an animation one after another in a window, see
+/// [crate::animation]
+pub struct Animator<S> {
+    frames: S,
+    area: Rectangle,
+    interval: u64,
+    policy: RefreshPolicy,
+    next_frame: usize,
+    /// time the next frame is due, `None` before the first one
+    due: Option<u64>,
+}
+
+impl<S> Animator<S>
+where
+    S: FrameSource,
+{
+    /// Animation of `frames` in the window `area`, a frame every `interval` ticks
+    ///
+    /// The window is refreshed fully at the start of every cycle, see
+    /// [`with_full_refresh_every`](Animator::with_full_refresh_every).
+    ///
+    /// # Panics
+    ///
+    /// If `frames` is empty.
+    pub fn new(frames: S, area: Rectangle, interval: u64) -> Self {
+        assert!(frames.frame_count() > 0, "an animation needs a frame");
+        Animator {
+            frames,
+            area,
+            interval,
+            policy: RefreshPolicy::new(),
+            next_frame: 0,
+            due: None,
+        }
+        .with_full_refresh_every(1)
+    }
+
+    /// Refreshes the window fully at the start of every `cycles`-th cycle, the other
+    /// frames are refreshed quickly
+    ///
+    /// 0 refreshes every frame fully.
+    pub fn with_full_refresh_every(self, cycles: u32) -> Self {
+        let count = u32::try_from(self.frames.frame_count()).unwrap_or(u32::MAX);
+        let quick = cycles.saturating_mul(count).saturating_sub(1);
+        self.with_policy(RefreshPolicy::new().with_max_quick_refreshes(quick))
+    }
+
+    /// Decides the kind of every refresh with `policy` instead, e.g. to refresh fully
+    /// after some time
+    ///
+    /// The policy gets the time passed to [`tick`](Animator::tick).
+    pub fn with_policy(mut self, policy: RefreshPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Index of the frame shown next
+    pub fn next_frame(&self) -> usize {
+        self.next_frame
+    }
+
+    /// Time the next frame is due, `None` if it is shown by the next tick
+    pub fn due(&self) -> Option<u64> {
+        self.due
+    }
+
+    /// Starts over with the first frame at the next tick, with a full refresh
+    pub fn restart(&mut self) {
+        self.next_frame = 0;
+        self.due = None;
+        self.policy.force_full();
+    }
+
+    /// Shows the next frame if it is due at `now`
+    ///
+    /// Returns the frame shown, `None` if it isn't time for it yet. After an error
+    /// the same frame is shown again by the next tick, with a full refresh as the
+    /// content of the window is unknown.
+    pub fn tick<SPI, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        now: u64,
+    ) -> Result<Option<AnimationStep>, Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        EPD: RegionRefresh<SPI, BUSY, DC, RST, DELAY>,
+    {
+        if self.due.is_some_and(|due| now < due) {
+            return Ok(None);
+        }
+
+        let kind = self.policy.next_refresh(RefreshHint::new(now));
+        let frame = self.next_frame;
+        let buffer = self.frames.frame(frame);
+        if let Err(error) = epd.refresh_region_with_lut(spi, delay, buffer, self.area, kind.into())
+        {
+            self.policy.force_full();
+            return Err(error);
+        }
+
+        self.next_frame = (frame + 1) % self.frames.frame_count();
+        // keeps the cadence, unless the tick was more than an interval late
+        let due = self.due.unwrap_or(now).saturating_add(self.interval);
+        self.due = Some(if due > now {
+            due
+        } else {
+            now.saturating_add(self.interval)
+        });
+        Ok(Some(AnimationStep { frame, kind }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::mock::{Bus, BusyPin, DcPin, Delay, Pin, Spi};
+    use crate::traits::RefreshLut;
+    use embedded_graphics_core::prelude::*;
+    use std::vec::Vec;
+
+    const ICON: Rectangle = Rectangle::new(Point::new(8, 4), Size::new(16, 2));
+
+    /// Driver recording the first byte of every frame and the LUT it was shown with
+    #[derive(Default)]
+    struct Recorder {
+        refreshes: Vec<(u8, RefreshLut)>,
+        fail: bool,
+    }
+
+    impl RegionRefresh<Spi, BusyPin, DcPin, Pin, Delay> for Recorder {
+        fn refresh_region_with_lut(
+            &mut self,
+            _spi: &mut Spi,
+            _delay: &mut Delay,
+            buffer: &[u8],
+            area: Rectangle,
+            lut: RefreshLut,
+        ) -> Result<(), Error<embedded_hal::spi::ErrorKind>> {
+            assert_eq!(area, ICON);
+            if self.fail {
+                return Err(Error::InvalidWindow);
+            }
+            self.refreshes.push((buffer[0], lut));
+            Ok(())
+        }
+    }
+
+    fn frames() -> [&'static [u8]; 3] {
+        [&[0; 4], &[1; 4], &[2; 4]]
+    }
+
+    #[test]
+    fn paces_the_frames() {
+        let mut spi = Bus::new().spi();
+        let mut delay = Delay::new();
+        let mut epd = Recorder::default();
+        let mut animator = Animator::new(frames(), ICON, 10);
+
+        let mut shown = Vec::new();
+        for now in [0, 5, 9, 10, 12, 20, 45, 50, 54, 55] {
+            let step = animator.tick(&mut epd, &mut spi, &mut delay, now).unwrap();
+            shown.push(step.map(|step| step.frame));
+        }
+        // 45 is late, the next frame follows an interval later instead of right away
+        assert_eq!(
+            shown,
+            [
+                Some(0),
+                None,
+                None,
+                Some(1),
+                None,
+                Some(2),
+                Some(0),
+                None,
+                None,
+                Some(1)
+            ]
+        );
+        assert_eq!(animator.due(), Some(65));
+        assert_eq!(animator.next_frame(), 2);
+    }
+
+    #[test]
+    fn full_refresh_every_cycles() {
+        let mut spi = Bus::new().spi();
+        let mut delay = Delay::new();
+        let mut epd = Recorder::default();
+        let mut animator = Animator::new(frames(), ICON, 1).with_full_refresh_every(2);
+
+        for now in 0..8 {
+            animator.tick(&mut epd, &mut spi, &mut delay, now).unwrap();
+        }
+        let (full, quick) = (RefreshLut::Full, RefreshLut::Quick);
+        assert_eq!(
+            epd.refreshes,
+            [
+                (0, full),
+                (1, quick),
+                (2, quick),
+                (0, quick),
+                (1, quick),
+                (2, quick),
+                (0, full),
+                (1, quick)
+            ]
+        );
+
+        // a restart begins with the first frame and a full refresh
+        animator.restart();
+        epd.refreshes.clear();
+        animator.tick(&mut epd, &mut spi, &mut delay, 8).unwrap();
+        assert_eq!(epd.refreshes, [(0, full)]);
+    }
+
+    #[test]
+    fn error_repeats_the_frame_fully() {
+        let mut spi = Bus::new().spi();
+        let mut delay = Delay::new();
+        let mut epd = Recorder::default();
+        let mut animator = Animator::new(frames(), ICON, 1);
+
+        animator.tick(&mut epd, &mut spi, &mut delay, 0).unwrap();
+        epd.fail = true;
+        assert_eq!(
+            animator.tick(&mut epd, &mut spi, &mut delay, 1),
+            Err(Error::InvalidWindow)
+        );
+        epd.fail = false;
+        let step = animator.tick(&mut epd, &mut spi, &mut delay, 1).unwrap();
+        assert_eq!(
+            step,
+            Some(AnimationStep {
+                frame: 1,
+                kind: RefreshKind::Full
+            })
+        );
+    }
+
+    #[test]
+    fn rendered_frames() {
+        let mut scratch = [0; 4];
+        let mut frames = RenderedFrames::new(2, &mut scratch, |index, buffer: &mut [u8]| {
+            buffer.fill(index as u8 + 10)
+        });
+        assert_eq!(frames.frame_count(), 2);
+        assert_eq!(frames.frame(1), [11; 4]);
+        assert_eq!(frames.frame(0), [10; 4]);
+    }
+
+    #[cfg(feature = "epd4in2")]
+    #[test]
+    fn animates_a_window_of_the_4in2() {
+        use crate::epd4in2::{command::Command, Epd4in2};
+        use crate::traits::{DriverControl, WaveshareDisplay};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        let mut animator = Animator::new(frames(), ICON, 1);
+
+        let (lut, refresh) = (Command::LutForVcom as u8, Command::DisplayRefresh as u8);
+        let mut tick = |now| {
+            bus.clear();
+            let step = animator.tick(&mut epd, &mut spi, &mut delay, now).unwrap();
+            assert_eq!(bus.count(refresh), 1);
+            (step.unwrap().kind, bus.count(lut))
+        };
+        // the full LUT is only uploaded, and the quick one restored, for the first frame
+        assert_eq!(tick(0), (RefreshKind::Full, 2));
+        assert_eq!(tick(1), (RefreshKind::Quick, 0));
+        assert_eq!(tick(2), (RefreshKind::Quick, 0));
+        assert_eq!(tick(3), (RefreshKind::Full, 2));
+        assert_eq!(epd.refresh_counters().partial, 4);
+    }
+}
@@ -0,0 +1,444 @@
+//! Operating displays without a connected busy pin
+//!
+//! The controllers signal with the busy pin when they can accept the next
+//! command. If the pin isn't connected, the drivers supporting
+//! [`BusyMode::FixedDelay`] can instead wait the worst case duration of the
+//! operation which was started last (init, full refresh, partial refresh or
+//! going to sleep), using [`DummyInputPin`] as busy pin.
+//!
+//! Only Epd4in2, Epd2in9 V2 and Epd2in13 V2/V3 support it so far, they have
+//! `new_with_busy_mode` and `set_busy_mode`. All other drivers always read the
+//! busy pin.
+//!
+//! This comes with some costs:
+//!
+//! - it is slower, every wait takes the worst case time, even if the controller
+//!   was finished long ago
+//! - faults go unnoticed: a panel stuck in busy (e.g. because of a low supply
+//!   voltage or a missing booster) isn't detected, the following commands are
+//!   silently dropped by the controller
+//! - the worst case times are taken from the specifications at room
+//!   temperature, a cold panel refreshes slower
+//!
+//! Supported by [Epd4in2](crate::epd4in2::Epd4in2),
+//! [Epd2in9 V2](crate::epd2in9_v2::Epd2in9) and
+//! [Epd2in13 V2/V3](crate::epd2in13_v2::Epd2in13).
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{busy::DummyInputPin, epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd4in2::new_with_busy_mode(
+//!    &mut spi,
+//!    DummyInputPin,
+//!    dc,
+//!    rst,
+//!    &mut delay,
+//!    None,
+//!    BusyMode::FixedDelay,
+//!)?;
+//!epd.clear_frame(&mut spi, &mut delay)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # Glitches on the busy line
+//!
+//! A busy line going through an optocoupler or a long cable can read idle for a
+//! moment during a refresh. The wait then returns early and the next command
+//! corrupts the refresh. With `set_idle_debounce(n)`, available on every driver,
+//! a wait only returns after `n` consecutive idle readings, which are `delay_us`
+//! (given to `new`) apart. The default of 1 keeps the single reading.
+//!
+//! # Inverted busy lines
+//!
+//! Some clone modules signal busy with the opposite level of the genuine Waveshare
+//! part, the waits then return right away and every refresh is corrupted. Every
+//! driver can be switched to the other level with `set_busy_polarity`,
+//! `busy_polarity` returns the one in use. `new` already initialised the display with
+//! the default level, so `wake_up` it again afterwards. The self test detects the
+//! polarity from the level the pin settles at after the reset, see
+//! [`SelfTestReport::busy_polarity`].
+//!
+//! # Monitoring refresh times
+//!
+//! Panels refresh slower as they age, long before they fail. `display_frame_checked`,
+//! available on every driver, refreshes like `display_frame` and returns a
+//! [`RefreshReport`] with the time the panel was busy, measured by counting the polls
+//! of the busy pin. It is compared with the typical and maximum time of the
+//! specification, the `REFRESH_LIMITS` of the driver modules. Glitches are only seen
+//! with an idle debounce of more than 1, without one the wait ends at the first idle
+//! reading.
+//!
+//! # Self test
+//!
+//! Most problems with a new display are wiring mistakes, which show up as a wait that
+//! never returns or a blank screen. `self_test`, available on every driver, checks
+//! the reaction of the busy pin to a hardware reset and to a command which keeps the
+//! controller busy for a moment (power on on UC81xx, software reset on SSD16xx
+//! controllers). It returns a [`SelfTestReport`] with the result of every check and
+//! the [`LikelyFault`]. Stuck busy lines are given up on after [`SELF_TEST_TIMEOUT_US`],
+//! so the test never hangs. The drivers of the 1.54" V2, 2.13" V2/V3 and 2.9" V2 can
+//! also write and read back some RAM with `self_test_read_back`, which needs the data
+//! line of the controller connected to MISO like `read_user_id`.
+//!
+//! The test resets the controller, afterwards the driver needs a `recover` (or
+//! `wake_up`). It needs [`BusyMode::Pin`] and a `delay_us` of 0 is fine, the pin is
+//! polled every [`SELF_TEST_POLL_US`].
+
+//!
+//! # Cancelling a refresh
+//!
+//! A refresh takes seconds and `display_frame` only returns when it is done.
+//! `display_frame_cancellable`, available on every driver, starts the refresh the
+//! same way but calls `cancel` before every poll of the busy pin (and every
+//! millisecond with [`BusyMode::FixedDelay`]) and gives up waiting as soon as it
+//! returns true, e.g. when a button was pressed. It returns
+//! [`WaitOutcome::Cancelled`] then.
+//!
+//! Cancelling is not an error: the driver isn't poisoned and the power state it
+//! tracks stays the one of the running refresh. The refresh keeps running, the steps
+//! the driver takes after it (e.g. powering the booster off) are skipped. `is_busy`
+//! tells when it is done, the next operation waits for it anyway (a later
+//! `wait_until_idle` does the same), and `abort_refresh` stops it right away.
+
+use core::convert::Infallible;
+use embedded_hal::digital::{ErrorType, InputPin};
+
+/// How a wait for the controller ended, see
+/// [cancelling a refresh](crate::busy#cancelling-a-refresh)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use]
+pub enum WaitOutcome {
+    /// The controller is idle, the refresh is finished
+    Idle,
+    /// The cancel hook returned true first, the refresh is still running
+    Cancelled,
+}
+
+/// How the drivers wait for the controller to be ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BusyMode {
+    /// Poll the busy pin
+    #[default]
+    Pin,
+    /// Ignore the busy pin and wait the worst case time of the last started operation
+    ///
+    /// Supported by Epd4in2, Epd2in9 V2 and Epd2in13 V2/V3 only.
+    FixedDelay,
+}
+
+/// Level of the busy pin while the controller is busy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusyPolarity {
+    /// Busy while the pin is high, e.g. the SSD16xx controllers
+    ActiveHigh,
+    /// Busy while the pin is low, e.g. the UC81xx controllers
+    ActiveLow,
+}
+
+impl BusyPolarity {
+    pub(crate) const fn from_busy_low(is_busy_low: bool) -> Self {
+        if is_busy_low {
+            BusyPolarity::ActiveLow
+        } else {
+            BusyPolarity::ActiveHigh
+        }
+    }
+
+    pub(crate) const fn is_busy_low(self) -> bool {
+        matches!(self, BusyPolarity::ActiveLow)
+    }
+}
+
+/// Busy pin for boards where it isn't connected, never reads as busy
+///
+/// Use it together with [`BusyMode::FixedDelay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DummyInputPin;
+
+impl ErrorType for DummyInputPin {
+    type Error = Infallible;
+}
+
+impl InputPin for DummyInputPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+/// Operation keeping the controller busy, decides how long to wait with [`BusyMode::FixedDelay`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    /// Reset, power on and loading the OTP
+    Init,
+    /// Refresh with the full LUT
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    FullRefresh,
+    /// Refresh with the quick/partial LUT
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    PartialRefresh,
+    /// Power off before deep sleep
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    Sleep,
+}
+
+/// Worst case durations in µs of the operations of a controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct BusyTimings {
+    pub init: u32,
+    pub full_refresh: u32,
+    pub partial_refresh: u32,
+    pub sleep: u32,
+    /// Any other command, e.g. writing the LUT
+    pub command: u32,
+}
+
+impl BusyTimings {
+    /// Worst case duration of `operation`, or of a simple command for `None`
+    pub(crate) fn duration(&self, operation: Option<Operation>) -> u32 {
+        match operation {
+            Some(Operation::Init) => self.init,
+            Some(Operation::FullRefresh) => self.full_refresh,
+            Some(Operation::PartialRefresh) => self.partial_refresh,
+            Some(Operation::Sleep) => self.sleep,
+            None => self.command,
+        }
+    }
+}
+
+/// Typical and maximum refresh times of a panel in µs, from its specification
+///
+/// The times are given at room temperature, a cold panel refreshes slower. Panels
+/// without a quick refresh use the full refresh times for it as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshLimits {
+    /// Typical duration of a full refresh
+    pub full_typical_us: u32,
+    /// Longest duration of a full refresh
+    pub full_max_us: u32,
+    /// Typical duration of a quick refresh
+    pub quick_typical_us: u32,
+    /// Longest duration of a quick refresh
+    pub quick_max_us: u32,
+}
+
+impl RefreshLimits {
+    /// Limits of a panel with full refreshes only
+    pub const fn new(typical_us: u32, max_us: u32) -> Self {
+        Self {
+            full_typical_us: typical_us,
+            full_max_us: max_us,
+            quick_typical_us: typical_us,
+            quick_max_us: max_us,
+        }
+    }
+
+    /// Sets the limits of quick refreshes
+    pub const fn with_quick(self, typical_us: u32, max_us: u32) -> Self {
+        Self {
+            quick_typical_us: typical_us,
+            quick_max_us: max_us,
+            ..self
+        }
+    }
+}
+
+/// Measured busy time of a refresh, returned by `display_frame_checked` of the drivers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RefreshReport {
+    /// Number of polls of the busy pin reading busy
+    pub busy_polls: u32,
+    /// `busy_polls` times the poll interval (`delay_us` given to `new`)
+    ///
+    /// The time of the polls themselves isn't included, so this is a lower bound. Always
+    /// 0 with an interval of 0 or with [`BusyMode::FixedDelay`], where nothing is measured.
+    pub busy_us: u32,
+    /// Whether the refresh took longer than the typical time of the specification
+    pub over_typical: bool,
+    /// Whether the refresh took longer than the maximum time of the specification
+    pub over_max: bool,
+    /// Number of times the busy line read idle and busy again within a wait
+    pub glitches: u32,
+}
+
+impl RefreshReport {
+    /// Compares `busy_polls` polls `interval_us` apart with the `limits` of a quick or
+    /// full refresh
+    pub(crate) fn new(
+        busy_polls: u32,
+        interval_us: u32,
+        glitches: u32,
+        limits: &RefreshLimits,
+        quick: bool,
+    ) -> Self {
+        let (typical, max) = if quick {
+            (limits.quick_typical_us, limits.quick_max_us)
+        } else {
+            (limits.full_typical_us, limits.full_max_us)
+        };
+        let busy_us = busy_polls.saturating_mul(interval_us);
+        Self {
+            busy_polls,
+            busy_us,
+            over_typical: busy_us > typical,
+            over_max: busy_us > max,
+            glitches,
+        }
+    }
+}
+
+/// Interval of the busy pin polls of the self test in µs
+pub const SELF_TEST_POLL_US: u32 = 100;
+/// Time the controller gets to become busy after a reset or command in µs
+pub const SELF_TEST_ASSERT_US: u32 = 10_000;
+/// Time the controller gets to become idle again in µs, longer means a stuck busy line
+pub const SELF_TEST_TIMEOUT_US: u32 = 5_000_000;
+/// Bytes written to the RAM and read back by `self_test_read_back`
+#[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+pub(crate) const SELF_TEST_PATTERN: [u8; 8] = [0x00, 0xFF, 0x55, 0xAA, 0x0F, 0xF0, 0x3C, 0xC3];
+
+/// Likely wiring mistake found by the self test, see [`SelfTestReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LikelyFault {
+    /// The busy pin always reads high: it isn't connected (floating or pulled up), is
+    /// connected to the wrong pin (e.g. swapped with DC) or the controller hangs
+    BusyStuckHigh,
+    /// The busy pin always reads low, for the same reasons as [`LikelyFault::BusyStuckHigh`]
+    BusyStuckLow,
+    /// The controller reacts to commands but not to the hardware reset, the reset line
+    /// isn't connected
+    NoResetResponse,
+    /// The controller reacts to the hardware reset but not to commands: DC isn't
+    /// connected, or MOSI, SCK or CS are wrong
+    NoCommandResponse,
+    /// The RAM read back differs from what was written: the data line of the controller
+    /// isn't connected to MISO, or MOSI and MISO are swapped
+    RamMismatch,
+    /// The busy pin settles at the busy level after the reset: the module signals busy
+    /// with the opposite level, see [`SelfTestReport::busy_polarity`]
+    InvertedBusyPolarity,
+}
+
+/// Reaction of the busy pin to a reset or command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BusyCheck {
+    /// Whether the pin read busy within [`SELF_TEST_ASSERT_US`]
+    pub asserted: bool,
+    /// Whether the pin read idle again within [`SELF_TEST_TIMEOUT_US`]
+    pub released: bool,
+    /// Time from the reset or command until the pin read idle again, in steps of
+    /// [`SELF_TEST_POLL_US`]
+    pub busy_us: u32,
+}
+
+impl BusyCheck {
+    /// Whether the controller became busy and idle again
+    pub fn passed(&self) -> bool {
+        self.asserted && self.released
+    }
+}
+
+/// Result of the `self_test` of the drivers, see [crate::busy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Reaction to the hardware reset, `busy_us` is the reset to idle time
+    pub reset: BusyCheck,
+    /// Reaction to the command keeping the controller busy
+    pub command: BusyCheck,
+    /// Whether the RAM read back matched, `None` if it wasn't checked
+    pub ram_read_back: Option<bool>,
+    /// Polarity of the busy pin detected from the level it settled at after the reset,
+    /// `None` if the pin never changed its level
+    pub busy_polarity: Option<BusyPolarity>,
+    /// The likely wiring mistake, `None` if every check passed
+    pub fault: Option<LikelyFault>,
+}
+
+impl SelfTestReport {
+    /// Diagnoses the checks of a controller signalling busy with a low level if
+    /// `is_busy_low`
+    pub(crate) fn new(reset: BusyCheck, command: BusyCheck, is_busy_low: bool) -> Self {
+        let stuck = |busy_level_high: bool| {
+            if busy_level_high {
+                LikelyFault::BusyStuckHigh
+            } else {
+                LikelyFault::BusyStuckLow
+            }
+        };
+        let fault =
+            if (reset.asserted && !reset.released) || (command.asserted && !command.released) {
+                // stuck at the busy level
+                Some(stuck(!is_busy_low))
+            } else if !reset.asserted && !command.asserted {
+                // stuck at the idle level
+                Some(stuck(is_busy_low))
+            } else if !reset.asserted {
+                Some(LikelyFault::NoResetResponse)
+            } else if !command.asserted {
+                Some(LikelyFault::NoCommandResponse)
+            } else {
+                None
+            };
+        Self {
+            reset,
+            command,
+            ram_read_back: None,
+            busy_polarity: None,
+            fault,
+        }
+    }
+
+    /// Adds the `detected` polarity, a different one than `configured` replaces the
+    /// fault found with the wrong polarity
+    pub(crate) fn with_busy_polarity(
+        self,
+        detected: Option<BusyPolarity>,
+        configured: BusyPolarity,
+    ) -> Self {
+        let inverted = detected.is_some_and(|polarity| polarity != configured);
+        Self {
+            busy_polarity: detected,
+            fault: if inverted {
+                Some(LikelyFault::InvertedBusyPolarity)
+            } else {
+                self.fault
+            },
+            ..self
+        }
+    }
+
+    /// Adds the result of the RAM read back
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn with_read_back(self, matched: bool) -> Self {
+        Self {
+            ram_read_back: Some(matched),
+            fault: self
+                .fault
+                .or((!matched).then_some(LikelyFault::RamMismatch)),
+            ..self
+        }
+    }
+
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.fault.is_none()
+    }
+}
@@ -0,0 +1,297 @@
+//! Picking the quick LUT timing for a single panel
+//!
+//! Panels of the same type differ slightly, a quick LUT tuned on one of them may leave
+//! ghosting on another. [`sweep_quick_lut`] runs a quick refresh cycle with each of a
+//! few candidate LUTs, e.g. the one of the driver with longer or shorter phases
+//! ([`CustomLut::scale_frames`]), and measures how long the panel stays busy. The
+//! fastest candidate finishing within the maximum quick refresh time of the
+//! specification wins: the busy time is a proxy for the margin the controller has
+//! to complete the waveform.
+//!
+//! The sweep takes a few seconds, so it is meant to run once, e.g. in production or
+//! on the first boot, with the index of the winner stored in non-volatile memory:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{calibration::*, epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# struct Settings { quick_lut: Option<u8> }
+//!# impl Settings { fn save(&self) {} }
+//!# let mut settings = Settings { quick_lut: None };
+//!
+//!// the busy time is measured in steps of the poll interval, 10 ms here
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, Some(10_000))?;
+//!
+//!let candidates = [
+//!    QUICK_LUT.scale_frames(80),
+//!    QUICK_LUT,
+//!    QUICK_LUT.scale_frames(125),
+//!];
+//!let index = match settings.quick_lut {
+//!    Some(index) => usize::from(index),
+//!    None => {
+//!        let mut scratch = [0; SCRATCH_LEN];
+//!        let index =
+//!            sweep_quick_lut(&mut epd, &mut spi, &mut delay, &candidates, &mut scratch)?
+//!                // none finished in time, stay with the LUT of the driver
+//!                .unwrap_or(1);
+//!        settings.quick_lut = Some(index as u8);
+//!        settings.save();
+//!        index
+//!    }
+//!};
+//!
+//!// on every boot
+//!epd.set_custom_lut(&mut spi, &mut delay, &candidates[index])?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! The measurement needs the busy pin, with [BusyMode::FixedDelay](crate::busy::BusyMode)
+//! or a poll interval of 0 every candidate measures 0 µs and the first one wins.
+//!
+//! Implemented for the UC8176 of the 4.2" display, whose quick LUT lives in registers
+//! of the controller.
+
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::busy::RefreshReport;
+use crate::error::Error;
+use crate::line_bytes;
+use crate::traits::{QuickRefresh, WaveshareDisplay};
+
+/// Width of the window refreshed by [`sweep_quick_lut`], at the top left corner
+pub const WINDOW_WIDTH: u32 = 32;
+/// Height of the window refreshed by [`sweep_quick_lut`]
+pub const WINDOW_HEIGHT: u32 = 16;
+/// Length of the scratch buffer of [`sweep_quick_lut`], the test pattern and its inverse
+pub const SCRATCH_LEN: usize = 2 * (line_bytes(WINDOW_WIDTH) * WINDOW_HEIGHT) as usize;
+
+/// Waveform of a UC81xx controller, the tables of its five LUT registers
+///
+/// Each table consists of groups of 6 bytes: the levels of the four phases, the
+/// number of frames of each phase and how often the group repeats. The VCOM table has
+/// two more bytes at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomLut {
+    /// VCOM levels (`LutForVcom`)
+    pub vcom: [u8; 44],
+    /// Pixels staying white (`LutWhiteToWhite`)
+    pub ww: [u8; 42],
+    /// Pixels turning white (`LutBlackToWhite`)
+    pub bw: [u8; 42],
+    /// Pixels turning black (`LutWhiteToBlack`)
+    pub wb: [u8; 42],
+    /// Pixels staying black (`LutBlackToBlack`)
+    pub bb: [u8; 42],
+}
+
+impl CustomLut {
+    /// The LUT with the frames of every phase scaled by `percent`, e.g. 80 for phases
+    /// 20 % shorter
+    ///
+    /// Phases which are used keep at least one frame, at most 255.
+    pub fn scale_frames(&self, percent: u16) -> Self {
+        let mut lut = *self;
+        scale_table(&mut lut.vcom, percent);
+        for table in [&mut lut.ww, &mut lut.bw, &mut lut.wb, &mut lut.bb] {
+            scale_table(table, percent);
+        }
+        lut
+    }
+}
+
+/// Scales the frame counts in the groups of `table`, the trailing bytes of the VCOM
+/// table aren't part of a group
+fn scale_table(table: &mut [u8], percent: u16) {
+    for group in table.chunks_exact_mut(6) {
+        for frames in &mut group[1..5] {
+            if *frames > 0 {
+                let scaled = u32::from(*frames) * u32::from(percent) / 100;
+                *frames = scaled.clamp(1, 255) as u8;
+            }
+        }
+    }
+}
+
+/// Drivers whose quick LUT can be replaced, see [crate::calibration]
+pub trait QuickLutCalibration<SPI, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY> + QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Uploads `lut` and selects it for the following quick refreshes
+    ///
+    /// [`refresh_lut`](WaveshareDisplay::refresh_lut) reports the quick
+    /// LUT afterwards. The next `set_lut` or `wake_up` uploads the LUT of the driver
+    /// again, so this has to be repeated after them.
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &CustomLut,
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Same as [`display_new_frame`](QuickRefresh::display_new_frame), but waits for
+    /// the refresh and reports how long the panel was busy, like the
+    /// `display_frame_checked` of the driver
+    fn display_new_frame_checked(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<RefreshReport, Error<SPI::Error>>;
+}
+
+/// Runs a quick refresh cycle with each of the `candidates` and returns the index of
+/// the fastest one finishing within the maximum quick refresh time of the panel
+///
+/// For every candidate a checkerboard in the window of [`WINDOW_WIDTH`] x
+/// [`WINDOW_HEIGHT`] pixels at the top left corner is refreshed to its inverse and
+/// back. The slower of both refreshes counts, equally fast candidates keep the order
+/// of `candidates`. `scratch` holds the patterns and needs [`SCRATCH_LEN`] bytes,
+/// otherwise [`Error::InvalidDataLength`] is returned before anything is sent.
+///
+/// Returns `None` if no candidate finished in time. Afterwards the LUT selected
+/// before is uploaded again and the window shows the checkerboard, the next full
+/// refresh clears it. After an error the LUT of the failed candidate may still be
+/// loaded.
+pub fn sweep_quick_lut<E, SPI, BUSY, DC, RST, DELAY>(
+    epd: &mut E,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    candidates: &[CustomLut],
+    scratch: &mut [u8],
+) -> Result<Option<usize>, Error<SPI::Error>>
+where
+    E: QuickLutCalibration<SPI, BUSY, DC, RST, DELAY>,
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    if scratch.len() < SCRATCH_LEN {
+        return Err(Error::InvalidDataLength);
+    }
+    let (pattern, inverse) = scratch[..SCRATCH_LEN].split_at_mut(SCRATCH_LEN / 2);
+    checkerboard(pattern, inverse);
+
+    let previous = epd.refresh_lut();
+    let mut best: Option<(usize, u32)> = None;
+    for (index, lut) in candidates.iter().enumerate() {
+        epd.set_custom_lut(spi, delay, lut)?;
+        let there = refresh_window(epd, spi, delay, inverse, pattern)?;
+        let back = refresh_window(epd, spi, delay, pattern, inverse)?;
+        let busy_us =
+            (!there.over_max && !back.over_max).then_some(there.busy_us.max(back.busy_us));
+        best = fastest(best, index, busy_us);
+    }
+    epd.set_lut(spi, delay, previous)?;
+    Ok(best.map(|(index, _)| index))
+}
+
+/// Refreshes the calibration window from `old` to `new`
+fn refresh_window<E, SPI, BUSY, DC, RST, DELAY>(
+    epd: &mut E,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    old: &[u8],
+    new: &[u8],
+) -> Result<RefreshReport, Error<SPI::Error>>
+where
+    E: QuickLutCalibration<SPI, BUSY, DC, RST, DELAY>,
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    epd.update_partial_old_frame(spi, delay, old, 0, 0, WINDOW_WIDTH, WINDOW_HEIGHT)?;
+    epd.update_partial_new_frame(spi, delay, new, 0, 0, WINDOW_WIDTH, WINDOW_HEIGHT)?;
+    epd.display_new_frame_checked(spi, delay)
+}
+
+/// The better of the `best` candidate so far and candidate `index`, which took
+/// `busy_us` or didn't finish in time (`None`)
+fn fastest(best: Option<(usize, u32)>, index: usize, busy_us: Option<u32>) -> Option<(usize, u32)> {
+    match (best, busy_us) {
+        (Some((_, best_us)), Some(busy_us)) if busy_us < best_us => Some((index, busy_us)),
+        (None, Some(busy_us)) => Some((index, busy_us)),
+        _ => best,
+    }
+}
+
+/// A checkerboard of single pixels in the layout of the window, and its inverse
+fn checkerboard(pattern: &mut [u8], inverse: &mut [u8]) {
+    let row_bytes = line_bytes(WINDOW_WIDTH) as usize;
+    for (row, (pattern, inverse)) in pattern
+        .chunks_exact_mut(row_bytes)
+        .zip(inverse.chunks_exact_mut(row_bytes))
+        .enumerate()
+    {
+        let byte = if row % 2 == 0 { 0xAA } else { 0x55 };
+        pattern.fill(byte);
+        inverse.fill(!byte);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fastest_in_time_wins() {
+        // too slow, 1.0 s, 0.6 s, 0.6 s again
+        let best = [None, Some(1_000_000), Some(600_000), Some(600_000)]
+            .into_iter()
+            .enumerate()
+            .fold(None, |best, (index, busy_us)| fastest(best, index, busy_us));
+        assert_eq!(best, Some((2, 600_000)));
+        assert_eq!(fastest(None, 0, None), None);
+    }
+
+    #[test]
+    fn scaled_frames() {
+        let mut lut = CustomLut {
+            vcom: [0; 44],
+            ww: [0; 42],
+            bw: [0; 42],
+            wb: [0; 42],
+            bb: [0; 42],
+        };
+        lut.vcom[..6].copy_from_slice(&[0x00, 0x0E, 0x01, 0x00, 0xC8, 0x01]);
+        lut.vcom[42..].copy_from_slice(&[0x10, 0x20]);
+        lut.bb[6..12].copy_from_slice(&[0x50, 0x0A, 0x00, 0x00, 0x00, 0x02]);
+
+        let shorter = lut.scale_frames(50);
+        // unused phases stay off, used ones keep a frame
+        assert_eq!(shorter.vcom[..6], [0x00, 0x07, 0x01, 0x00, 0x64, 0x01]);
+        assert_eq!(shorter.vcom[42..], [0x10, 0x20]);
+        assert_eq!(shorter.bb[6..12], [0x50, 0x05, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(lut.scale_frames(200).vcom[4], 0xFF);
+    }
+
+    #[test]
+    fn checkerboard_pattern() {
+        let mut scratch = [0; SCRATCH_LEN];
+        let (pattern, inverse) = scratch.split_at_mut(SCRATCH_LEN / 2);
+        checkerboard(pattern, inverse);
+        assert_eq!(
+            pattern[..8],
+            [0xAA, 0xAA, 0xAA, 0xAA, 0x55, 0x55, 0x55, 0x55]
+        );
+        assert!(pattern.iter().zip(inverse.iter()).all(|(p, i)| *p == !i));
+    }
+}
@@ -0,0 +1,194 @@
+//! CRC-16 checksum over the data sent to the EPD
+//!
+//! With the `checksum` feature every driver keeps a running checksum over the
+//! data bytes it sends after a command (including the bytes synthesized for fills
+//! like `clear_frame`). After an `update_frame` it is the checksum of the frame:
+//! commands a driver sends after the frame data, e.g. to end the transmission, and
+//! the status commands of busy waits keep it, and drivers sending the frame in
+//! several windows checksum the whole frame. So [`crc16`] over the source image can be
+//! compared against the driver's `last_transfer_checksum()` or `take_checksum()` to
+//! detect corruption between render and transfer. Drivers converting the frame, e.g.
+//! to more bits per pixel, checksum the bytes as the controller received them.
+//! Without the feature the drivers don't spend any time on it.
+//!
+//! Tri-color controllers receive the frame as two planes with a command each, the
+//! checksum of the last transfer only covers the chromatic one (the black/white one
+//! after an `update_frame` of the Epd2in66b, which fills the chromatic plane with a
+//! pattern command). `plane_checksum()`
+//! keeps the checksum of the data last sent to each plane, so both can be checked.
+//!
+//! The algorithm is CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF,
+//! no reflection, no final xor).
+
+#[cfg(feature = "checksum")]
+use crate::color::Plane;
+
+/// Initial value of the checksum
+pub const CRC16_INIT: u16 = 0xFFFF;
+
+const POLY: u16 = 0x1021;
+
+/// Lookup table for one nibble, keeps the per byte cost low without spending 512 bytes
+const NIBBLE_TABLE: [u16; 16] = {
+    let mut table = [0u16; 16];
+    let mut i = 0;
+    while i < 16 {
+        let mut crc = (i as u16) << 12;
+        let mut bit = 0;
+        while bit < 4 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLY
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Feeds a single byte into a running checksum
+#[inline]
+pub const fn crc16_update(crc: u16, byte: u8) -> u16 {
+    let crc = (crc << 4) ^ NIBBLE_TABLE[(((crc >> 12) as u8) ^ (byte >> 4)) as usize];
+    (crc << 4) ^ NIBBLE_TABLE[(((crc >> 12) as u8) ^ (byte & 0x0F)) as usize]
+}
+
+/// Computes the checksum over `data`
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter()
+        .fold(CRC16_INIT, |crc, &byte| crc16_update(crc, byte))
+}
+
+/// Running checksum of a driver, over the data of the last command and per plane
+#[cfg(feature = "checksum")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TransferChecksum {
+    /// Over the data sent since the last command
+    current: u16,
+    /// Of the data last sent to the black/white and the chromatic plane
+    planes: [u16; 2],
+    /// Plane the data of the last command belongs to
+    plane: Option<Plane>,
+}
+
+#[cfg(feature = "checksum")]
+impl TransferChecksum {
+    pub(crate) const fn new() -> Self {
+        TransferChecksum {
+            current: CRC16_INIT,
+            planes: [CRC16_INIT; 2],
+            plane: None,
+        }
+    }
+
+    /// Starts over for the data of a new command, which belongs to `plane` if any
+    pub(crate) fn start(&mut self, plane: Option<Plane>) {
+        self.current = CRC16_INIT;
+        self.plane = plane;
+        if let Some(plane) = plane {
+            self.planes[plane as usize] = CRC16_INIT;
+        }
+    }
+
+    pub(crate) fn feed(&mut self, data: &[u8]) {
+        self.current = data
+            .iter()
+            .fold(self.current, |crc, &byte| crc16_update(crc, byte));
+        self.sync_plane();
+    }
+
+    pub(crate) fn feed_repeated(&mut self, val: u8, repetitions: u32) {
+        for _ in 0..repetitions {
+            self.current = crc16_update(self.current, val);
+        }
+        self.sync_plane();
+    }
+
+    fn sync_plane(&mut self) {
+        if let Some(plane) = self.plane {
+            self.planes[plane as usize] = self.current;
+        }
+    }
+
+    pub(crate) fn current(&self) -> u16 {
+        self.current
+    }
+
+    /// Overrides the checksum, e.g. to keep the one of a frame across a trailing command
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_current(&mut self, checksum: u16) {
+        self.current = checksum;
+    }
+
+    pub(crate) fn plane(&self, plane: Plane) -> u16 {
+        self.planes[plane as usize]
+    }
+
+    /// Returns the checksum of the last transfer and resets all of them
+    pub(crate) fn take(&mut self) -> u16 {
+        let checksum = self.current;
+        *self = TransferChecksum::new();
+        checksum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // straightforward bitwise implementation as reference
+    fn reference(data: &[u8]) -> u16 {
+        let mut crc = 0xFFFFu16;
+        for &byte in data {
+            crc ^= (byte as u16) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 != 0 {
+                    (crc << 1) ^ 0x1021
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[]), CRC16_INIT);
+    }
+
+    #[test]
+    fn matches_reference() {
+        let frame: [u8; 300] = core::array::from_fn(|i| (i * 7 + i / 3) as u8);
+        assert_eq!(crc16(&frame), reference(&frame));
+        assert_eq!(crc16(&[0xFF; 5000]), reference(&[0xFF; 5000]));
+        assert_eq!(crc16(&[0x00; 5000]), reference(&[0x00; 5000]));
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn planes() {
+        let mut checksum = TransferChecksum::new();
+        checksum.start(Some(Plane::BlackWhite));
+        checksum.feed(&[0x12, 0x34]);
+        checksum.feed_repeated(0xFF, 3);
+        // a parameter in between doesn't touch the planes
+        checksum.start(None);
+        checksum.feed(&[0x01]);
+        checksum.start(Some(Plane::Chromatic));
+        checksum.feed(&[0x00; 4]);
+
+        assert_eq!(
+            checksum.plane(Plane::BlackWhite),
+            crc16(&[0x12, 0x34, 0xFF, 0xFF, 0xFF])
+        );
+        assert_eq!(checksum.plane(Plane::Chromatic), crc16(&[0x00; 4]));
+        assert_eq!(checksum.take(), crc16(&[0x00; 4]));
+        assert_eq!(checksum.current(), CRC16_INIT);
+        assert_eq!(checksum.plane(Plane::BlackWhite), CRC16_INIT);
+    }
+}
@@ -44,6 +44,23 @@ pub enum TriColor {
     Chromatic,
 }
 
+/// How tri-color displays draw [TriColor::Chromatic], see `set_chromatic_policy` of the
+/// tri-color displays of [crate::graphics]
+///
+/// The chromatic particles of the b/w/r panels fade years before the black ones. The
+/// fallbacks keep a UI drawn with chromatic content legible on such a panel without
+/// touching the drawing code, the chromatic plane stays empty then.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ChromaticPolicy {
+    /// Chromatic is drawn into the chromatic plane
+    #[default]
+    Normal,
+    /// Chromatic is drawn as black, into the black/white plane
+    RenderAsBlack,
+    /// Chromatic drawing is ignored, the pixels keep what was there before
+    Drop,
+}
+
 /// For the 5in65 7 Color Display
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum OctColor {
@@ -65,6 +82,76 @@ pub enum OctColor {
     HiZ = 0x07,
 }
 
+/// For the 4 color displays with 2 bits per pixel in a single buffer (e.g. 2in36g)
+///
+/// The discriminants are the 2 bit values the controller expects.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuadColor {
+    /// Black Color
+    Black = 0b00,
+    /// White Color
+    White = 0b01,
+    /// Yellow Color
+    Yellow = 0b10,
+    /// Red Color
+    Red = 0b11,
+}
+
+/// One of the one bit per pixel buffers of a display
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Plane {
+    /// The black/white data (also the "old" data of the b/w controllers)
+    BlackWhite,
+    /// The chromatic (red or yellow) data of the tri-color displays
+    Chromatic,
+}
+
+/// Meaning of a set bit in a [`Plane`] as expected by a controller
+///
+/// Every driver has one constant for each of its planes, e.g. `BW_CONVENTION`
+/// and `CHROMATIC_CONVENTION`. Use [`Color::fill_byte`] and [`Color::to_plane_bit`]
+/// (or the [`TriColor`] counterparts) with them instead of inverting bytes by hand.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaneConvention {
+    /// Black/white plane, 1 is white and 0 is black
+    OneIsWhite,
+    /// Black/white plane, 1 is black and 0 is white
+    OneIsBlack,
+    /// Chromatic plane, 1 is chromatic and 0 lets the black/white plane show
+    OneIsChromatic,
+    /// Chromatic plane, 0 is chromatic and 1 lets the black/white plane show
+    OneIsNotChromatic,
+}
+
+impl PlaneConvention {
+    /// The plane this convention is meant for
+    pub const fn plane(self) -> Plane {
+        match self {
+            PlaneConvention::OneIsWhite | PlaneConvention::OneIsBlack => Plane::BlackWhite,
+            PlaneConvention::OneIsChromatic | PlaneConvention::OneIsNotChromatic => {
+                Plane::Chromatic
+            }
+        }
+    }
+
+    /// Bit for a pixel which is white (black/white plane) or chromatic (chromatic plane)
+    const fn bit(self, white_or_chromatic: bool) -> bool {
+        match self {
+            PlaneConvention::OneIsWhite | PlaneConvention::OneIsChromatic => white_or_chromatic,
+            PlaneConvention::OneIsBlack | PlaneConvention::OneIsNotChromatic => !white_or_chromatic,
+        }
+    }
+}
+
+/// A full byte of `bit`
+const fn fill(bit: bool) -> u8 {
+    if bit {
+        0xFF
+    } else {
+        0x00
+    }
+}
+
 /// Color trait for use in `Display`s
 pub trait ColorType {
     /// Number of bit used to represent this color type in a single buffer.
@@ -78,14 +165,25 @@ pub trait ColorType {
     /// Return the data used to set a pixel color
     ///
     /// * bwrbit is used to tell the value of the unused bit when a chromatic
-    /// color is set (TriColor only as for now)
+    ///   color is set (TriColor only as for now)
     /// * pos is the pixel position in the line, used to know which pixels must be set
     ///
     /// Return values are :
     /// * .0 is the mask used to exclude this pixel from the byte (eg: 0x7F in BiColor)
     /// * .1 are the bits used to set the color in the byte (eg: 0x80 in BiColor)
-    ///      this is u16 because we set 2 bytes in case of split buffer
+    ///   this is u16 because we set 2 bytes in case of split buffer
     fn bitmask(&self, bwrbit: bool, pos: u32) -> (u8, u16);
+
+    /// The color to draw under `policy`, `None` if nothing is drawn
+    ///
+    /// Only [TriColor] has a chromatic color to replace, other colors are kept.
+    fn with_chromatic_policy(self, policy: ChromaticPolicy) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = policy;
+        Some(self)
+    }
 }
 
 impl ColorType for Color {
@@ -118,18 +216,35 @@ impl ColorType for TriColor {
             ),
         }
     }
+
+    fn with_chromatic_policy(self, policy: ChromaticPolicy) -> Option<Self> {
+        match (self, policy) {
+            (TriColor::Chromatic, ChromaticPolicy::RenderAsBlack) => Some(TriColor::Black),
+            (TriColor::Chromatic, ChromaticPolicy::Drop) => None,
+            (color, _) => Some(color),
+        }
+    }
 }
 
 impl ColorType for OctColor {
     const BITS_PER_PIXEL_PER_BUFFER: usize = 4;
     const BUFFER_COUNT: usize = 1;
     fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
-        let mask = !(0xF0 >> (pos % 2));
+        let mask = !(0xF0 >> (pos % 2 * 4));
         let bits = self.get_nibble() as u16;
         (mask, if pos % 2 == 1 { bits } else { bits << 4 })
     }
 }
 
+impl ColorType for QuadColor {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        let shift = 6 - 2 * (pos % 4);
+        (!(0b11 << shift), (self.get_bits() as u16) << shift)
+    }
+}
+
 #[cfg(feature = "graphics")]
 impl From<BinaryColor> for OctColor {
     fn from(b: BinaryColor) -> OctColor {
@@ -240,10 +355,125 @@ impl OctColor {
         }
     }
 }
+
+impl QuadColor {
+    /// Gets the 2 bit representation of the Color as needed by the display
+    pub fn get_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Gets a full byte of 4 pixels of this color
+    pub fn get_byte_value(self) -> u8 {
+        self.get_bits() * 0b0101_0101
+    }
+
+    /// Converts four colors into a single byte for the Display, the first one is the leftmost
+    pub fn colors_byte(colors: [QuadColor; 4]) -> u8 {
+        colors
+            .iter()
+            .fold(0, |byte, color| byte << 2 | color.get_bits())
+    }
+
+    /// Takes the lower 2 bits and converts them to a QuadColor
+    pub fn from_bits(bits: u8) -> QuadColor {
+        match bits & 0b11 {
+            0b00 => QuadColor::Black,
+            0b01 => QuadColor::White,
+            0b10 => QuadColor::Yellow,
+            _ => QuadColor::Red,
+        }
+    }
+
+    /// Splits a byte into its four colors, the leftmost pixel first
+    pub fn split_byte(byte: u8) -> [QuadColor; 4] {
+        [6, 4, 2, 0].map(|shift| QuadColor::from_bits(byte >> shift))
+    }
+
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            QuadColor::Black => (0x00, 0x00, 0x00),
+            QuadColor::White => (0xff, 0xff, 0xff),
+            QuadColor::Yellow => (0xff, 0xff, 0x00),
+            QuadColor::Red => (0xff, 0x00, 0x00),
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl PixelColor for QuadColor {
+    type Raw = embedded_graphics_core::pixelcolor::raw::RawU2;
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::raw::RawU2> for QuadColor {
+    fn from(b: embedded_graphics_core::pixelcolor::raw::RawU2) -> Self {
+        use embedded_graphics_core::prelude::RawData;
+        QuadColor::from_bits(b.into_inner())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for QuadColor {
+    fn from(b: BinaryColor) -> QuadColor {
+        match b {
+            BinaryColor::On => QuadColor::Black,
+            BinaryColor::Off => QuadColor::White,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<QuadColor> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(b: QuadColor) -> Self {
+        let (r, g, b) = b.rgb();
+        Self::new(r, g, b)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for QuadColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> QuadColor {
+        use embedded_graphics_core::prelude::RgbColor;
+        // pick the nearest color
+        [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ]
+        .into_iter()
+        .min_by_key(|c| {
+            let (r, g, b) = c.rgb();
+            (i32::from(r) - i32::from(p.r())).pow(2)
+                + (i32::from(g) - i32::from(p.g())).pow(2)
+                + (i32::from(b) - i32::from(p.b())).pow(2)
+        })
+        .unwrap_or(QuadColor::White)
+    }
+}
+
 //TODO: Rename get_bit_value to bit() and get_byte_value to byte() ?
 
 impl Color {
+    /// Bit of this color in `plane`, encoded with `convention`
+    ///
+    /// Nothing is chromatic, so the chromatic plane is the same for both colors.
+    pub fn to_plane_bit(self, plane: Plane, convention: PlaneConvention) -> bool {
+        debug_assert_eq!(plane, convention.plane());
+        match plane {
+            Plane::BlackWhite => convention.bit(self == Color::White),
+            Plane::Chromatic => convention.bit(false),
+        }
+    }
+
+    /// A full byte of this color in `plane`, encoded with `convention`
+    pub fn fill_byte(self, plane: Plane, convention: PlaneConvention) -> u8 {
+        fill(self.to_plane_bit(plane, convention))
+    }
+
     /// Get the color encoding of the color for one bit
+    #[deprecated(since = "0.6.0", note = "use `to_plane_bit` with the plane convention")]
     pub fn get_bit_value(self) -> u8 {
         match self {
             Color::White => 1u8,
@@ -252,6 +482,7 @@ impl Color {
     }
 
     /// Gets a full byte of black or white pixels
+    #[deprecated(since = "0.6.0", note = "use `fill_byte` with the plane convention")]
     pub fn get_byte_value(self) -> u8 {
         match self {
             Color::White => 0xff,
@@ -303,6 +534,16 @@ impl From<BinaryColor> for Color {
     }
 }
 
+#[cfg(feature = "graphics")]
+impl From<Color> for BinaryColor {
+    fn from(color: Color) -> BinaryColor {
+        match color {
+            Color::Black => BinaryColor::On,
+            Color::White => BinaryColor::Off,
+        }
+    }
+}
+
 #[cfg(feature = "graphics")]
 impl From<embedded_graphics_core::pixelcolor::Rgb888> for Color {
     fn from(rgb: embedded_graphics_core::pixelcolor::Rgb888) -> Self {
@@ -334,7 +575,24 @@ impl From<Color> for embedded_graphics_core::pixelcolor::Rgb888 {
 }
 
 impl TriColor {
+    /// Bit of this color in `plane`, encoded with `convention`
+    ///
+    /// Chromatic counts as black in the black/white plane.
+    pub fn to_plane_bit(self, plane: Plane, convention: PlaneConvention) -> bool {
+        debug_assert_eq!(plane, convention.plane());
+        match plane {
+            Plane::BlackWhite => convention.bit(self == TriColor::White),
+            Plane::Chromatic => convention.bit(self == TriColor::Chromatic),
+        }
+    }
+
+    /// A full byte of this color in `plane`, encoded with `convention`
+    pub fn fill_byte(self, plane: Plane, convention: PlaneConvention) -> u8 {
+        fill(self.to_plane_bit(plane, convention))
+    }
+
     /// Get the color encoding of the color for one bit
+    #[deprecated(since = "0.6.0", note = "use `to_plane_bit` with the plane convention")]
     pub fn get_bit_value(self) -> u8 {
         match self {
             TriColor::White => 1u8,
@@ -343,6 +601,7 @@ impl TriColor {
     }
 
     /// Gets a full byte of black or white pixels
+    #[deprecated(since = "0.6.0", note = "use `fill_byte` with the plane convention")]
     pub fn get_byte_value(self) -> u8 {
         match self {
             TriColor::White => 0xff,
@@ -405,7 +664,7 @@ mod tests {
     // test all values aside from 0 and 1 which all should panic
     #[test]
     fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
+        for val in 2..=u8::MAX {
             extern crate std;
             let result = std::panic::catch_unwind(|| Color::from(val));
             assert!(result.is_err());
@@ -413,17 +672,78 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn u8_conversion_black() {
         assert_eq!(Color::from(Color::Black.get_bit_value()), Color::Black);
         assert_eq!(Color::from(0u8).get_bit_value(), 0u8);
     }
 
     #[test]
+    #[allow(deprecated)]
     fn u8_conversion_white() {
         assert_eq!(Color::from(Color::White.get_bit_value()), Color::White);
         assert_eq!(Color::from(1u8).get_bit_value(), 1u8);
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn plane_bytes_match_the_old_values() {
+        use PlaneConvention::*;
+        for color in [Color::Black, Color::White] {
+            assert_eq!(
+                color.fill_byte(Plane::BlackWhite, OneIsWhite),
+                color.get_byte_value()
+            );
+            assert_eq!(
+                color.fill_byte(Plane::BlackWhite, OneIsBlack),
+                !color.get_byte_value()
+            );
+            assert_eq!(
+                u8::from(color.to_plane_bit(Plane::BlackWhite, OneIsWhite)),
+                color.get_bit_value()
+            );
+        }
+        for color in [TriColor::Black, TriColor::White, TriColor::Chromatic] {
+            assert_eq!(
+                color.fill_byte(Plane::BlackWhite, OneIsWhite),
+                color.get_byte_value()
+            );
+            assert_eq!(
+                u8::from(color.to_plane_bit(Plane::BlackWhite, OneIsWhite)),
+                color.get_bit_value()
+            );
+        }
+    }
+
+    #[test]
+    fn chromatic_plane() {
+        use PlaneConvention::*;
+        assert_eq!(
+            TriColor::Chromatic.fill_byte(Plane::Chromatic, OneIsChromatic),
+            0xFF
+        );
+        assert_eq!(
+            TriColor::Chromatic.fill_byte(Plane::Chromatic, OneIsNotChromatic),
+            0x00
+        );
+        for color in [TriColor::Black, TriColor::White] {
+            assert_eq!(color.fill_byte(Plane::Chromatic, OneIsChromatic), 0x00);
+            assert_eq!(color.fill_byte(Plane::Chromatic, OneIsNotChromatic), 0xFF);
+        }
+        // a b/w color never shows up in the chromatic plane
+        for color in [Color::Black, Color::White] {
+            assert!(!color.to_plane_bit(Plane::Chromatic, OneIsChromatic));
+            assert!(color.to_plane_bit(Plane::Chromatic, OneIsNotChromatic));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn convention_of_the_wrong_plane() {
+        Color::White.fill_byte(Plane::Chromatic, PlaneConvention::OneIsWhite);
+    }
+
     #[test]
     fn test_oct() {
         let left = OctColor::Red;
@@ -433,4 +753,42 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn quad_color_bits() {
+        // values from the Waveshare 2in36g driver, swapped values show up as swapped colors
+        assert_eq!(QuadColor::Black.get_bits(), 0b00);
+        assert_eq!(QuadColor::White.get_bits(), 0b01);
+        assert_eq!(QuadColor::Yellow.get_bits(), 0b10);
+        assert_eq!(QuadColor::Red.get_bits(), 0b11);
+        assert_eq!(QuadColor::White.get_byte_value(), 0x55);
+        assert_eq!(QuadColor::Red.get_byte_value(), 0xFF);
+
+        let colors = [
+            QuadColor::Red,
+            QuadColor::Black,
+            QuadColor::Yellow,
+            QuadColor::White,
+        ];
+        assert_eq!(QuadColor::colors_byte(colors), 0b11_00_10_01);
+        assert_eq!(QuadColor::split_byte(0b11_00_10_01), colors);
+        for bits in 0..4 {
+            assert_eq!(QuadColor::from_bits(bits).get_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn quad_color_bitmask() {
+        // pixels are packed MSB first
+        assert_eq!(QuadColor::Red.bitmask(false, 0), (0b0011_1111, 0b1100_0000));
+        assert_eq!(
+            QuadColor::Yellow.bitmask(false, 1),
+            (0b1100_1111, 0b0010_0000)
+        );
+        assert_eq!(
+            QuadColor::White.bitmask(false, 6),
+            (0b1111_0011, 0b0000_0100)
+        );
+        assert_eq!(QuadColor::Red.bitmask(false, 7), (0b1111_1100, 0b0000_0011));
+    }
 }
@@ -0,0 +1,653 @@
+//! Recording the operations sent to a display for replaying them later
+//!
+//! [CommandLog] wraps a driver and records every successful operation passed to it
+//! as an [EpdOp], e.g. to reproduce a field report on a display on the desk or to run
+//! a demo against a mock without the hardware. Frames aren't copied into the log,
+//! they are identified by their length and [CRC-16](crate::checksum::crc16), see
+//! [FrameId]. [replay] sends a log to another display, taking the frames from a
+//! [FrameStore] of the original frame data.
+//!
+//! The log holds the last `N` operations: once it is full, the oldest one is
+//! dropped for every new one. The creation of the driver isn't recorded, replay
+//! against an initialised display.
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{command_log::*, epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let mut other_epd = Epd4in2::new(&mut spi, busy_in.clone(), dc.clone(), rst.clone(), &mut delay, None)?;
+//!# let buffer = [0u8; 400 / 8 * 300];
+//!
+//!let epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = CommandLog::<_, 16>::wrap(epd);
+//!epd.update_and_display_frame(&mut spi, &buffer, &mut delay)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!
+//!// the same again on another display
+//!let frames = [&buffer[..]];
+//!replay(epd.log(), &FrameStore::new(&frames), &mut other_epd, &mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::checksum::{crc16, crc16_update, CRC16_INIT};
+use crate::color::Color;
+use crate::error::Error;
+use crate::traits::{RefreshLut, WaveshareDisplay};
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+/// Identifies the frame data of an operation without keeping a copy of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameId {
+    /// Length of the frame data in bytes
+    pub len: usize,
+    /// [CRC-16](crate::checksum::crc16) of the frame data
+    pub crc: u16,
+}
+
+impl FrameId {
+    /// The id of `data`
+    pub fn of(data: &[u8]) -> Self {
+        FrameId {
+            len: data.len(),
+            crc: crc16(data),
+        }
+    }
+
+    /// The id of the frame joined from `parts`, the same as [FrameId::of] the joined buffer
+    pub fn of_parts(parts: &[&[u8]]) -> Self {
+        FrameId {
+            len: parts.iter().map(|part| part.len()).sum(),
+            crc: parts
+                .iter()
+                .flat_map(|part| part.iter())
+                .fold(CRC16_INIT, |crc, &byte| crc16_update(crc, byte)),
+        }
+    }
+}
+
+/// An operation of [WaveshareDisplay] recorded by a [CommandLog]
+///
+/// Operations taking frame data refer to it by its [FrameId].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpdOp {
+    /// [sleep](WaveshareDisplay::sleep)
+    Sleep,
+    /// [wake_up](WaveshareDisplay::wake_up)
+    WakeUp,
+    /// [recover](WaveshareDisplay::recover)
+    Recover,
+    /// [abort_refresh](WaveshareDisplay::abort_refresh)
+    AbortRefresh,
+    /// [set_background_color](WaveshareDisplay::set_background_color)
+    SetBackgroundColor(Color),
+    /// [update_frame](WaveshareDisplay::update_frame)
+    UpdateFrame(FrameId),
+    /// [update_frame_with_progress](WaveshareDisplay::update_frame_with_progress), replayed
+    /// without a progress callback
+    UpdateFrameWithProgress(FrameId),
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame)
+    UpdatePartialFrame {
+        /// The window data
+        frame: FrameId,
+        /// Left edge of the window
+        x: u32,
+        /// Top edge of the window
+        y: u32,
+        /// Width of the window
+        width: u32,
+        /// Height of the window
+        height: u32,
+    },
+    /// [display_frame](WaveshareDisplay::display_frame)
+    DisplayFrame,
+    /// [update_and_display_frame](WaveshareDisplay::update_and_display_frame)
+    UpdateAndDisplayFrame(FrameId),
+    /// [clear_frame](WaveshareDisplay::clear_frame)
+    ClearFrame,
+    /// [clear_region](WaveshareDisplay::clear_region)
+    ClearRegion {
+        /// Left edge of the window
+        x: u32,
+        /// Top edge of the window
+        y: u32,
+        /// Width of the window
+        width: u32,
+        /// Height of the window
+        height: u32,
+    },
+    /// [set_lut](WaveshareDisplay::set_lut)
+    SetLut(Option<RefreshLut>),
+    /// [wait_until_idle](WaveshareDisplay::wait_until_idle)
+    WaitUntilIdle,
+    /// [display_frame_with](WaveshareDisplay::display_frame_with)
+    DisplayFrameWith(RefreshLut),
+    /// [update_and_display_frame_with](WaveshareDisplay::update_and_display_frame_with)
+    UpdateAndDisplayFrameWith(FrameId, RefreshLut),
+}
+
+impl EpdOp {
+    /// The frame data the operation sends, if any
+    pub fn frame(&self) -> Option<FrameId> {
+        match *self {
+            EpdOp::UpdateFrame(frame)
+            | EpdOp::UpdateFrameWithProgress(frame)
+            | EpdOp::UpdatePartialFrame { frame, .. }
+            | EpdOp::UpdateAndDisplayFrame(frame)
+            | EpdOp::UpdateAndDisplayFrameWith(frame, _) => Some(frame),
+            _ => None,
+        }
+    }
+}
+
+/// The frame data for a [replay], looked up by [FrameId]
+pub struct FrameStore<'a> {
+    frames: &'a [&'a [u8]],
+}
+
+impl<'a> FrameStore<'a> {
+    /// A store of `frames`, full frames and partial windows alike
+    pub fn new(frames: &'a [&'a [u8]]) -> Self {
+        FrameStore { frames }
+    }
+
+    /// The frame with the id `id`, the first one if several match
+    pub fn get(&self, id: FrameId) -> Option<&'a [u8]> {
+        self.frames
+            .iter()
+            .copied()
+            .find(|frame| frame.len() == id.len && crc16(frame) == id.crc)
+    }
+}
+
+/// A driver recording the operations sent to it, see the [module documentation](self)
+///
+/// `N` is the number of operations kept, the oldest ones are dropped first.
+pub struct CommandLog<E, const N: usize> {
+    epd: E,
+    ops: [EpdOp; N],
+    len: usize,
+    dropped: usize,
+}
+
+impl<E, const N: usize> CommandLog<E, N> {
+    /// Wraps `epd`, starting with an empty log
+    pub fn wrap(epd: E) -> Self {
+        Self {
+            epd,
+            ops: [EpdOp::Sleep; N],
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The recorded operations, oldest first
+    pub fn log(&self) -> &[EpdOp] {
+        &self.ops[..self.len]
+    }
+
+    /// Number of operations dropped from the log since it was cleared
+    ///
+    /// A log replayed after dropping operations starts in the middle of the sequence.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Empties the log
+    pub fn clear_log(&mut self) {
+        self.len = 0;
+        self.dropped = 0;
+    }
+
+    /// The wrapped driver
+    pub fn inner(&self) -> &E {
+        &self.epd
+    }
+
+    /// The wrapped driver, operations through it aren't recorded
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.epd
+    }
+
+    /// Returns the wrapped driver, dropping the log
+    pub fn into_inner(self) -> E {
+        self.epd
+    }
+
+    fn record(&mut self, op: EpdOp) {
+        if N == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.len == N {
+            // keeps the log in order for `log()`, the logs are short
+            self.ops.copy_within(1.., 0);
+            self.len -= 1;
+            self.dropped += 1;
+        }
+        self.ops[self.len] = op;
+        self.len += 1;
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, E, const N: usize> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for CommandLog<E, N>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    E: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = E::WIDTH;
+    const HEIGHT: u32 = E::HEIGHT;
+    const BUFFER_LEN: usize = E::BUFFER_LEN;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        E::new(spi, busy, dc, rst, delay, delay_us).map(Self::wrap)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.sleep(spi, delay)?;
+        self.record(EpdOp::Sleep);
+        Ok(())
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.wake_up(spi, delay)?;
+        self.record(EpdOp::WakeUp);
+        Ok(())
+    }
+
+    fn recover(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.recover(spi, delay)?;
+        self.record(EpdOp::Recover);
+        Ok(())
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.abort_refresh(spi, delay)?;
+        self.record(EpdOp::AbortRefresh);
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.epd.set_background_color(color);
+        self.record(EpdOp::SetBackgroundColor(color));
+    }
+
+    fn background_color(&self) -> &Color {
+        self.epd.background_color()
+    }
+
+    fn width(&self) -> u32 {
+        self.epd.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.epd.height()
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_frame(spi, buffer, delay)?;
+        self.record(EpdOp::UpdateFrame(FrameId::of(buffer)));
+        Ok(())
+    }
+
+    fn update_frame_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        progress: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_frame_with_progress(spi, buffer, delay, progress)?;
+        self.record(EpdOp::UpdateFrameWithProgress(FrameId::of(buffer)));
+        Ok(())
+    }
+
+    /// Recorded as an [EpdOp::UpdateFrame] of the joined parts
+    fn update_frame_vectored(
+        &mut self,
+        spi: &mut SPI,
+        parts: &[&[u8]],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_frame_vectored(spi, parts, delay)?;
+        self.record(EpdOp::UpdateFrame(FrameId::of_parts(parts)));
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_partial_frame(spi, delay, buffer, x, y, width, height)?;
+        self.record(EpdOp::UpdatePartialFrame {
+            frame: FrameId::of(buffer),
+            x,
+            y,
+            width,
+            height,
+        });
+        Ok(())
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.display_frame(spi, delay)?;
+        self.record(EpdOp::DisplayFrame);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_and_display_frame(spi, buffer, delay)?;
+        self.record(EpdOp::UpdateAndDisplayFrame(FrameId::of(buffer)));
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.clear_frame(spi, delay)?;
+        self.record(EpdOp::ClearFrame);
+        Ok(())
+    }
+
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.clear_region(spi, delay, x, y, width, height)?;
+        self.record(EpdOp::ClearRegion {
+            x,
+            y,
+            width,
+            height,
+        });
+        Ok(())
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.set_lut(spi, delay, refresh_rate)?;
+        self.record(EpdOp::SetLut(refresh_rate));
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.wait_until_idle(spi, delay)?;
+        self.record(EpdOp::WaitUntilIdle);
+        Ok(())
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        self.epd.refresh_lut()
+    }
+
+    fn display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.display_frame_with(spi, delay, lut)?;
+        self.record(EpdOp::DisplayFrameWith(lut));
+        Ok(())
+    }
+
+    fn update_and_display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_and_display_frame_with(spi, buffer, delay, lut)?;
+        self.record(EpdOp::UpdateAndDisplayFrameWith(FrameId::of(buffer), lut));
+        Ok(())
+    }
+}
+
+/// Runs the operations of `log` on `epd`, taking their frame data from `frames`
+///
+/// Returns [`Error::UnknownFrame`] without sending anything if `frames` misses the
+/// data of an operation. Stops at the first failing operation.
+pub fn replay<SPI, BUSY, DC, RST, DELAY, E>(
+    log: &[EpdOp],
+    frames: &FrameStore<'_>,
+    epd: &mut E,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    E: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    let frame = |id: FrameId| frames.get(id).ok_or(Error::UnknownFrame);
+    for op in log {
+        if let Some(id) = op.frame() {
+            frame(id)?;
+        }
+    }
+
+    for &op in log {
+        match op {
+            EpdOp::Sleep => epd.sleep(spi, delay)?,
+            EpdOp::WakeUp => epd.wake_up(spi, delay)?,
+            EpdOp::Recover => epd.recover(spi, delay)?,
+            EpdOp::AbortRefresh => epd.abort_refresh(spi, delay)?,
+            EpdOp::SetBackgroundColor(color) => epd.set_background_color(color),
+            EpdOp::UpdateFrame(id) => epd.update_frame(spi, frame(id)?, delay)?,
+            EpdOp::UpdateFrameWithProgress(id) => {
+                epd.update_frame_with_progress(spi, frame(id)?, delay, |_, _| {})?
+            }
+            EpdOp::UpdatePartialFrame {
+                frame: id,
+                x,
+                y,
+                width,
+                height,
+            } => epd.update_partial_frame(spi, delay, frame(id)?, x, y, width, height)?,
+            EpdOp::DisplayFrame => epd.display_frame(spi, delay)?,
+            EpdOp::UpdateAndDisplayFrame(id) => {
+                epd.update_and_display_frame(spi, frame(id)?, delay)?
+            }
+            EpdOp::ClearFrame => epd.clear_frame(spi, delay)?,
+            EpdOp::ClearRegion {
+                x,
+                y,
+                width,
+                height,
+            } => epd.clear_region(spi, delay, x, y, width, height)?,
+            EpdOp::SetLut(lut) => epd.set_lut(spi, delay, lut)?,
+            EpdOp::WaitUntilIdle => epd.wait_until_idle(spi, delay)?,
+            EpdOp::DisplayFrameWith(lut) => epd.display_frame_with(spi, delay, lut)?,
+            EpdOp::UpdateAndDisplayFrameWith(id, lut) => {
+                epd.update_and_display_frame_with(spi, frame(id)?, delay, lut)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "epd4in2"))]
+mod tests {
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::mock::{Bus, Delay, Pin};
+
+    const LEN: usize = 400 / 8 * 300;
+
+    #[test]
+    fn oldest_operations_are_dropped() {
+        let mut log = CommandLog::<(), 3>::wrap(());
+        for op in [
+            EpdOp::WakeUp,
+            EpdOp::ClearFrame,
+            EpdOp::DisplayFrame,
+            EpdOp::Sleep,
+        ] {
+            log.record(op);
+        }
+        assert_eq!(
+            log.log(),
+            [EpdOp::ClearFrame, EpdOp::DisplayFrame, EpdOp::Sleep]
+        );
+        assert_eq!(log.dropped(), 1);
+    }
+
+    #[test]
+    fn replay_sends_the_same_sequence() {
+        let frame = [0x3C; LEN];
+        let window = [0xA5; 2 * 4];
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut epd = CommandLog::<_, 16>::wrap(epd);
+        bus.clear();
+        epd.set_background_color(Color::Black);
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        epd.update_partial_frame(&mut spi, &mut delay, &window, 64, 20, 16, 4)
+            .unwrap();
+        epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert_eq!(epd.log().len(), 7);
+
+        let other = Bus::new();
+        let mut other_spi = other.spi();
+        let mut other_epd = Epd4in2::new(
+            &mut other_spi,
+            other.busy(true),
+            other.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        other.clear();
+        let frames = [&frame[..], &window[..]];
+        replay(
+            epd.log(),
+            &FrameStore::new(&frames),
+            &mut other_epd,
+            &mut other_spi,
+            &mut delay,
+        )
+        .unwrap();
+        assert_eq!(other.transfers(), bus.transfers());
+    }
+
+    #[test]
+    fn vectored_frame_is_logged_joined() {
+        let frame: [u8; LEN] = core::array::from_fn(|i| i as u8);
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut epd = CommandLog::<_, 4>::wrap(epd);
+        epd.update_frame_vectored(&mut spi, &[&frame[..7], &frame[7..]], &mut delay)
+            .unwrap();
+        assert_eq!(epd.log(), [EpdOp::UpdateFrame(FrameId::of(&frame))]);
+    }
+
+    #[test]
+    fn missing_frame_sends_nothing() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+        let log = [
+            EpdOp::ClearFrame,
+            EpdOp::UpdateFrame(FrameId::of(&[0; LEN])),
+        ];
+        let result = replay(&log, &FrameStore::new(&[]), &mut epd, &mut spi, &mut delay);
+        assert_eq!(result, Err(Error::UnknownFrame));
+        assert!(bus.transfers().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Adapter for delays which only offer a microsecond delay, e.g. `cortex_m::delay::Delay`
+//!
+//! Only available with the `delay-adapter` feature. The drivers need an
+//! embedded-hal 1.0 [DelayNs], while the SysTick delay of cortex-m 0.7 (used by
+//! most rp2040-hal and other cortex-m examples) only implements the traits of
+//! embedded-hal 0.2 and its own `delay_us(u32)`. [DelayUsFn] wraps a closure
+//! calling such a microsecond delay:
+//!
+//! ```text
+//! let mut systick = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+//! let mut delay = DelayUsFn::new(|us| systick.delay_us(us));
+//! let mut epd = Epd2in13::new(&mut spi, busy, dc, rst, &mut delay, None)?;
+//! ```
+//!
+//! With rp2040-hal the `hal::Timer` implements [DelayNs] itself and needs no adapter.
+
+use embedded_hal::delay::DelayNs;
+
+/// Longest delay in milliseconds passed on in one call, `u32::MAX` microseconds
+const MAX_MS_PER_CALL: u32 = u32::MAX / 1_000;
+
+/// [DelayNs] on a closure delaying for the given number of microseconds
+///
+/// Delays in nanoseconds are rounded up to whole microseconds, so no delay is
+/// shorter than requested.
+pub struct DelayUsFn<F: FnMut(u32)> {
+    delay_us: F,
+}
+
+impl<F: FnMut(u32)> DelayUsFn<F> {
+    /// Wraps `delay_us`, which has to delay for at least the microseconds it is passed
+    pub fn new(delay_us: F) -> Self {
+        DelayUsFn { delay_us }
+    }
+
+    /// Returns the wrapped closure
+    pub fn into_inner(self) -> F {
+        self.delay_us
+    }
+}
+
+impl<F: FnMut(u32)> DelayNs for DelayUsFn<F> {
+    fn delay_ns(&mut self, ns: u32) {
+        (self.delay_us)(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        (self.delay_us)(us);
+    }
+
+    fn delay_ms(&mut self, mut ms: u32) {
+        while ms > 0 {
+            let step = ms.min(MAX_MS_PER_CALL);
+            (self.delay_us)(step * 1_000);
+            ms -= step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delays(run: impl FnOnce(&mut DelayUsFn<&mut dyn FnMut(u32)>)) -> [u64; 2] {
+        let mut total = [0u64; 2];
+        let mut record = |us: u32| {
+            total[0] += u64::from(us);
+            total[1] += 1;
+        };
+        run(&mut DelayUsFn::new(&mut record));
+        total
+    }
+
+    #[test]
+    fn nanoseconds_round_up() {
+        assert_eq!(delays(|delay| delay.delay_ns(1)), [1, 1]);
+        assert_eq!(delays(|delay| delay.delay_ns(1_000)), [1, 1]);
+        assert_eq!(delays(|delay| delay.delay_ns(1_001)), [2, 1]);
+        assert_eq!(delays(|delay| delay.delay_ns(0)), [0, 1]);
+    }
+
+    #[test]
+    fn microseconds_pass_through() {
+        assert_eq!(delays(|delay| delay.delay_us(10_000)), [10_000, 1]);
+        assert_eq!(
+            delays(|delay| delay.delay_us(u32::MAX)),
+            [u64::from(u32::MAX), 1]
+        );
+    }
+
+    #[test]
+    fn long_milliseconds_are_split() {
+        assert_eq!(delays(|delay| delay.delay_ms(200)), [200_000, 1]);
+        // beyond u32::MAX microseconds, about 71 minutes
+        assert_eq!(
+            delays(|delay| delay.delay_ms(u32::MAX)),
+            [u64::from(u32::MAX) * 1_000, 1_001]
+        );
+    }
+}
@@ -0,0 +1,604 @@
+//! A simple Driver for the Waveshare 13.3" (K) E-Ink Display via SPI
+//!
+//! 960x680 pixels, black and white (White = 1/0xFF, Black = 0/0x00). The SSD1677 class
+//! controller addresses both axes in pixels with 10 bits, sent as the low byte followed
+//! by bits 8 and 9 (e.g. `[0x00, 0x02]` for x = 512).
+//!
+//! # RAM
+//!
+//! A full frame takes 81600 bytes, more than many microcontrollers have. Rather than
+//! [Display13in3k], draw the frame in bands of a few rows with a
+//! [VarDisplay](crate::graphics::VarDisplay) and send every band with
+//! [update_partial_frame](WaveshareDisplay::update_partial_frame), then refresh once:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Circle, PrimitiveStyle}};
+//!use epd_waveshare::{buffer_len, color::Color, epd13in3k::*, graphics::VarDisplay, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd13in3k::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// 40 rows take 4800 bytes
+//!const BAND: u32 = 40;
+//!let mut buffer = [0u8; buffer_len(WIDTH as usize, BAND as usize)];
+//!for top in (0..HEIGHT).step_by(BAND as usize) {
+//!    let mut band = VarDisplay::<Color>::new(WIDTH, BAND, &mut buffer, false).unwrap();
+//!    let _ = band.clear(Color::White);
+//!    // draw the whole scene in panel coordinates, only the band is kept
+//!    let _ = Circle::new(Point::new(280, 140), 400)
+//!        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 8))
+//!        .draw(&mut band.translated(Point::new(0, -(top as i32))));
+//!    epd.update_partial_frame(&mut spi, &mut delay, band.buffer(), 0, top, WIDTH, BAND)?;
+//!}
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// quick refreshes of a window, x and the width need to be multiples of 8
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!let clock = [0x00; 120 / 8 * 48];
+//!epd.update_partial_frame(&mut spi, &mut delay, &clock, 816, 16, 120, 48)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/13.3inch_e-Paper_HAT_(K))
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd13in3k.py)
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::{Operation, RefreshLimits};
+use crate::color::{Color, Plane, PlaneConvention};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+use crate::update_sequence::UpdateSequence;
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::{buffer_len, line_bytes};
+
+/// Full size buffer for use with the 13.3" (K) EPD
+///
+/// <div class="warning">This takes 81600 bytes of RAM, see the <a
+/// href="index.html#ram">module documentation</a> for drawing in bands instead.</div>
+#[cfg(feature = "graphics")]
+pub type Display13in3k = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 680;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd13in3k",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(6_000_000, 9_000_000).with_quick(1_000_000, 2_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Display update sequence of a full refresh
+const FULL_UPDATE: u8 = 0xF7;
+/// Display update sequence of a partial refresh (display mode 2)
+const PARTIAL_UPDATE: u8 = 0xFF;
+/// Border waveform of full refreshes
+const FULL_BORDER: u8 = 0x05;
+/// Border waveform of partial refreshes, the border is left alone
+const PARTIAL_BORDER: u8 = 0x80;
+
+/// EPD13in3k driver
+pub struct Epd13in3k<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd13in3k {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.cmd_with_data(spi, Command::SoftStart, &[0xAE, 0xC7, 0xC3, 0xC0, 0x80])?;
+
+        // 680 gates, the last one is sent with 10 bits as well
+        let [low, high] = coordinate(HEIGHT - 1);
+        self.cmd_with_data(spi, Command::DriverOutputControl, &[low, high, 0x00])?;
+
+        // x and y increment
+        self.cmd_with_data(spi, Command::DataEntry, &[0x03])?;
+
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::VbdControl, &[self.border()])?;
+
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        let quick = self.refresh == RefreshLut::Quick;
+        if !quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        let update =
+            self.interface
+                .update_sequence_or(if quick { PARTIAL_UPDATE } else { FULL_UPDATE });
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])?;
+        self.command(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(quick);
+        self.interface.start_operation(if quick {
+            Operation::PartialRefresh
+        } else {
+            Operation::FullRefresh
+        });
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleep)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0x03])?;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_window(spi, delay, frame, 0, 0, WIDTH, HEIGHT)
+    }
+
+    /// Writes `buffer` into the window at `x`, `y`, which need to be multiples of 8
+    /// horizontally
+    ///
+    /// With the full LUT the old frame RAM is updated as well, so that the next quick
+    /// refresh starts from what is shown.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_window(spi, delay, &mut FrameData::new(buffer), x, y, width, height)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        let pixel_count = line_bytes(WIDTH) * HEIGHT;
+        let background_color_byte = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.command(spi, Command::WriteRamBw)?;
+        self.interface
+            .data_x_times(spi, background_color_byte, pixel_count)?;
+
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRamRed)?;
+        self.interface
+            .data_x_times(spi, background_color_byte, pixel_count)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Selects full (default) or quick refreshes, the waveforms are loaded from the OTP
+    /// of the controller
+    ///
+    /// Quick refreshes use display mode 2, which only drives the pixels differing from
+    /// the old frame RAM and leaves the border alone.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        let refresh = match refresh_rate {
+            Some(RefreshLut::Quick) => RefreshLut::Quick,
+            Some(RefreshLut::Full | RefreshLut::Custom(_)) | None => RefreshLut::Full,
+        };
+        self.refresh = refresh;
+        self.cmd_with_data(spi, Command::VbdControl, &[self.border()])
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes the window of [update_partial_frame](WaveshareDisplay::update_partial_frame)
+    /// from `frame`
+    #[allow(clippy::too_many_arguments)]
+    fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        frame: &mut FrameData<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        if !x.is_multiple_of(8) || !width.is_multiple_of(8) {
+            return Err(Error::InvalidWindow);
+        }
+        assert!((line_bytes(width) * height) as usize == frame.len());
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRamBw, frame, 0..frame.len())?;
+
+        if self.refresh != RefreshLut::Quick {
+            self.set_counters(spi, x, y)?;
+            self.interface
+                .cmd_with_frame(spi, Command::WriteRamRed, frame, 0..frame.len())?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Sets the RAM window to `width` x `height` pixels at `x`, `y` and moves the
+    /// address counters to its start
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let [x_start, x_start_high] = coordinate(x);
+        let [x_end, x_end_high] = coordinate(x + width - 1);
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[x_start, x_start_high, x_end, x_end_high],
+        )?;
+        let [y_start, y_start_high] = coordinate(y);
+        let [y_end, y_end_high] = coordinate(y + height - 1);
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYStartEnd,
+            &[y_start, y_start_high, y_end, y_end_high],
+        )?;
+        self.set_counters(spi, x, y)
+    }
+
+    /// Border waveform of the selected refresh
+    fn border(&self) -> u8 {
+        if self.refresh == RefreshLut::Quick {
+            PARTIAL_BORDER
+        } else {
+            FULL_BORDER
+        }
+    }
+
+    fn set_counters(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::SetRamXAc, &coordinate(x))?;
+        self.cmd_with_data(spi, Command::SetRamYAc, &coordinate(y))
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+}
+
+/// 10 bit RAM address, the low byte first and then bits 8 and 9
+fn coordinate(value: u32) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8 & 0x03]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 680);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn coordinate_encoding() {
+        assert_eq!(coordinate(0), [0x00, 0x00]);
+        assert_eq!(coordinate(511), [0xFF, 0x01]);
+        assert_eq!(coordinate(512), [0x00, 0x02]);
+        assert_eq!(coordinate(WIDTH - 1), [0xBF, 0x03]);
+        assert_eq!(coordinate(HEIGHT - 1), [0xA7, 0x02]);
+    }
+
+    #[test]
+    fn partial_window_above_512() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd13in3k::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        bus.clear();
+
+        // x 520..=647 and y 600..=679
+        let buffer = [0xA5; 128 / 8 * 80];
+        epd.update_partial_frame(&mut spi, &mut delay, &buffer, 520, 600, 128, 80)
+            .unwrap();
+        assert_eq!(
+            bus.data_after(Command::SetRamXStartEnd as u8).unwrap(),
+            [0x08, 0x02, 0x87, 0x02]
+        );
+        assert_eq!(
+            bus.data_after(Command::SetRamYStartEnd as u8).unwrap(),
+            [0x58, 0x02, 0xA7, 0x02]
+        );
+        assert_eq!(
+            bus.data_after(Command::SetRamXAc as u8).unwrap(),
+            [0x08, 0x02]
+        );
+        assert_eq!(
+            bus.data_after(Command::SetRamYAc as u8).unwrap(),
+            [0x58, 0x02]
+        );
+        assert_eq!(bus.data_after(Command::WriteRamBw as u8).unwrap(), buffer);
+        // the old frame is kept for the comparison of the quick refresh
+        assert_eq!(bus.count(Command::WriteRamRed as u8), 0);
+
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.data_after(Command::DisplayUpdateControl2 as u8)
+                .unwrap(),
+            [PARTIAL_UPDATE]
+        );
+
+        // the full LUT writes both RAMs
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Full))
+            .unwrap();
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut delay, &buffer, 832, 0, 128, 80)
+            .unwrap();
+        assert_eq!(
+            bus.data_after(Command::SetRamXStartEnd as u8).unwrap(),
+            [0x40, 0x03, 0xBF, 0x03]
+        );
+        assert_eq!(bus.data_after(Command::WriteRamRed as u8).unwrap(), buffer);
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamXAc as u8, 1).unwrap(),
+            [0x40, 0x03]
+        );
+    }
+}
@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -48,25 +48,51 @@
 pub const WIDTH: u32 = 200;
 /// Height of the display
 pub const HEIGHT: u32 = 200;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd1in54",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(2_000_000, 4_000_000).with_quick(300_000, 1_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 //const DPI: u16 = 184;
 const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::reset::ResetConfig;
 use crate::type_a::{
     command::Command,
     constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
 };
+use crate::update_sequence::UpdateSequence;
 
-use crate::color::Color;
+use crate::busy::RefreshLimits;
+use crate::color::{Color, Plane, PlaneConvention};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RamBank, RefreshLut, WaveshareDisplay,
+};
 
-use crate::buffer_len;
 use crate::interface::DisplayInterface;
+use crate::{buffer_len, line_bytes};
 
 /// Full size buffer for use with the 1in54b EPD
 #[cfg(feature = "graphics")]
@@ -86,9 +112,13 @@ pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Windows written by [Epd1in54::write_ram_window] wait for
+    /// [Epd1in54::refresh_written]
+    ram_windows: bool,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
@@ -96,8 +126,34 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd1in54 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            ram_windows: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.ram_windows = false;
+        self.interface.reset(delay, RESET_CONFIG);
 
         // 3 Databytes:
         // A[7:0]
@@ -152,6 +208,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -167,25 +226,22 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-
-        let mut epd = Epd1in54 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
@@ -199,11 +255,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+            .cmd_with_frame(spi, Command::WriteRam, frame, 0..frame.len())?;
         Ok(())
     }
 
@@ -217,7 +285,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
         self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, delay, x, y)?;
@@ -227,18 +299,14 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
         self.wait_until_idle(spi, delay)?;
-        // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
-        //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])?;
-
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        // MASTER Activation should not be interupted to avoid currption of panel images
-        // therefore a terminate command is send
-        self.interface.cmd(spi, Command::Nop)?;
-        Ok(())
+        self.refresh(spi, false)
     }
 
     fn update_and_display_frame(
@@ -246,22 +314,26 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self
+            .background_color
+            .fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
         Ok(())
     }
 
@@ -278,20 +350,33 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
+            RefreshLut::Full | RefreshLut::Custom(_) => {
+                self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE)
+            }
             RefreshLut::Quick => self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE),
-        }
+        }?;
+        self.interface.restore_operation(operation);
+        Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
@@ -302,11 +387,121 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` of the RAM `bank` without refreshing
+    ///
+    /// Any number of windows can be written, [`refresh_written`](Self::refresh_written)
+    /// then shows all of them with a single refresh. This is a lot faster than
+    /// refreshing after every window. Until then frame updates, refreshes and clears
+    /// return [`Error::PartialSessionActive`].
+    ///
+    /// `x` and `width` should be multiples of 8. Returns [`Error::InvalidWindow`]
+    /// without sending anything if the window is empty or doesn't fit on the panel.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        bank: RamBank,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+        let command = match bank {
+            RamBank::New => Command::WriteRam,
+            RamBank::Old => Command::WriteRam2,
+        };
+        self.interface.cmd_with_data(spi, command, buffer)?;
+        self.ram_windows = true;
+        Ok(())
+    }
+
+    /// Shows the windows written by [`write_ram_window`](Self::write_ram_window) with a
+    /// single refresh
+    ///
+    /// `mode` selects the LUT of this refresh, the one selected with `set_lut` is
+    /// uploaded again afterwards if they differ. Returns [`Error::NoPartialSession`] if
+    /// no window was written.
+    pub fn refresh_written(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        if !self.ram_windows {
+            return Err(Error::NoPartialSession);
+        }
+        self.ram_windows = false;
+        if mode != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        let previous = self.refresh;
+        if mode != previous {
+            self.set_lut(spi, delay, Some(mode))?;
+        }
+        self.wait_until_idle(spi, delay)?;
+        let result = self.refresh(spi, true);
+        if mode != previous {
+            // the LUT must not change while the refresh is still running
+            self.wait_until_idle(spi, delay)?;
+            self.set_lut(spi, delay, Some(previous))?;
+        }
+        result
+    }
+
+    /// Starts the refresh with the current LUT, `windows` counts it as partial one
+    fn refresh(&mut self, spi: &mut SPI, windows: bool) -> Result<(), Error<SPI::Error>> {
+        // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
+        //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
+        let sequence = self.interface.update_sequence_or(0xC4);
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        if windows {
+            self.interface.count_partial_refresh();
+        } else {
+            self.interface
+                .count_refresh(self.refresh == RefreshLut::Quick);
+        }
+        // MASTER Activation should not be interupted to avoid currption of panel images
+        // therefore a terminate command is send
+        self.interface.cmd(spi, Command::Nop)?;
+        Ok(())
+    }
+
+    fn ensure_no_session(&self) -> Result<(), Error<SPI::Error>> {
+        if self.ram_windows {
+            return Err(Error::PartialSessionActive);
+        }
+        Ok(())
+    }
+
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -322,10 +517,10 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -355,7 +550,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -376,7 +571,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         assert!(buffer.len() == 30);
 
@@ -389,6 +584,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Bus, Delay, Pin};
 
     #[test]
     fn epd_size() {
@@ -396,4 +592,83 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn ram_windows_share_one_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd1in54::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            epd.refresh_written(&mut spi, &mut delay, RefreshLut::Full),
+            Err(Error::NoPartialSession)
+        );
+
+        bus.clear();
+        for (i, y) in [8, 40, 150].into_iter().enumerate() {
+            epd.write_ram_window(
+                &mut spi,
+                &mut delay,
+                &[i as u8; 4],
+                16,
+                y,
+                16,
+                2,
+                RamBank::New,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            epd.update_frame(&mut spi, &[0; 5000], &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        assert_eq!(
+            epd.write_ram_window(&mut spi, &mut delay, &[0; 4], 192, 0, 16, 2, RamBank::New),
+            Err(Error::InvalidWindow)
+        );
+        epd.refresh_written(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+
+        let x_window = Command::SetRamXAddressStartEndPosition as u8;
+        let y_window = Command::SetRamYAddressStartEndPosition as u8;
+        assert_eq!(bus.count(x_window), 3);
+        assert_eq!(bus.data_after_nth(x_window, 0).unwrap(), [2, 3]);
+        assert_eq!(bus.data_after_nth(y_window, 2).unwrap(), [150, 0, 151, 0]);
+        assert_eq!(
+            bus.data_after_nth(Command::WriteRam as u8, 1).unwrap(),
+            [1; 4]
+        );
+        assert_eq!(bus.count(Command::MasterActivation as u8), 1);
+        assert_eq!(bus.count(Command::WriteLutRegister as u8), 0);
+        assert_eq!(epd.refresh_counters().partial, 1);
+
+        // a different mode is loaded for the refresh only
+        bus.clear();
+        epd.write_ram_window(&mut spi, &mut delay, &[0; 4], 0, 0, 16, 2, RamBank::Old)
+            .unwrap();
+        epd.refresh_written(&mut spi, &mut delay, RefreshLut::Quick)
+            .unwrap();
+        assert_eq!(bus.count(Command::WriteRam2 as u8), 1);
+        assert_eq!(bus.count(Command::MasterActivation as u8), 1);
+        assert_eq!(
+            bus.data_after_nth(Command::WriteLutRegister as u8, 0)
+                .unwrap(),
+            LUT_PARTIAL_UPDATE
+        );
+        assert_eq!(
+            bus.data_after_nth(Command::WriteLutRegister as u8, 1)
+                .unwrap(),
+            LUT_FULL_UPDATE
+        );
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Full));
+        epd.update_frame(&mut spi, &[0; 5000], &mut delay).unwrap();
+    }
 }
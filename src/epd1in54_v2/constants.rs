@@ -1,3 +1,5 @@
+use crate::color::PlaneConvention;
+
 #[rustfmt::skip]
 // Original Waveforms from Waveshare for 1in54_V2
 pub(crate) const LUT_FULL_UPDATE: [u8; 159] =[
@@ -44,3 +46,6 @@ pub(crate) const LUT_PARTIAL_UPDATE: [u8; 159] =[
     0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x0, 0x0, 0x0, 
     0x02, 0x17, 0x41, 0xB0, 0x32, 0x28, 
 ];
+
+/// Bit sense of the black/white plane
+pub(crate) const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
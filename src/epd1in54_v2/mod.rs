@@ -6,26 +6,67 @@
 pub const WIDTH: u32 = 200;
 /// Height of the display
 pub const HEIGHT: u32 = 200;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd1in54_v2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(2_000_000, 4_000_000).with_quick(300_000, 1_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Border waveform of full refreshes
+const BORDER_FULL: u8 = 0x01;
+/// Border waveform during partial refreshes, the border flashes with the full one
+const BORDER_PARTIAL: u8 = 0x80;
+
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::{RefreshLimits, SelfTestReport, SELF_TEST_PATTERN};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::reset::ResetConfig;
+use crate::ssd16xx;
 use crate::type_a::command::Command;
+use crate::update_sequence::UpdateSequence;
+use crate::{buffer_len, line_bytes};
 
 mod constants;
-use crate::epd1in54_v2::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+use crate::epd1in54_v2::constants::{BW_CONVENTION, LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 
-use crate::color::Color;
+use crate::color::{Color, Plane};
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
 
 use crate::interface::DisplayInterface;
+use crate::vcom;
 
+/// Full size buffer for use with the 1in54 V2 EPD
+///
+/// Same layout as `Display1in54` of the first version.
 #[cfg(feature = "graphics")]
-pub use crate::epd1in54::Display1in54;
+pub type Display1in54 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
 
 /// Epd1in54 driver
 pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
@@ -36,9 +77,16 @@ pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
 
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Black/white RAM shown inverted
+    inverted: bool,
+    /// The old frame RAM holds the shown frame, copied there by the last quick refresh
+    old_frame_shown: bool,
+    /// The border waveform of a quick refresh is still set, its wait was cancelled
+    border_partial: bool,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
@@ -46,8 +94,37 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd1in54 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            inverted: false,
+            old_frame_shown: false,
+            border_partial: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.old_frame_shown = false;
+        self.border_partial = false;
+        self.interface.reset(delay, RESET_CONFIG);
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
         self.wait_until_idle(spi, delay)?;
@@ -56,15 +133,20 @@ where
         // A[7:0]
         // 0.. A[8]
         // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+        // A = Height of Screen, B = 0x01 (TB=1: gates scanned from the bottom)
         self.interface.cmd_with_data(
             spi,
             Command::DriverOutputControl,
-            &[(HEIGHT - 1) as u8, 0x0, 0x00],
+            &[(HEIGHT - 1) as u8, 0x0, 0x01],
         )?;
 
+        // x increments, y decrements: together with TB=1 the image isn't mirrored,
+        // see set_ram_area
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x1])?;
+
         self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x3])?;
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_FULL])?;
 
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -79,12 +161,36 @@ where
 
         self.set_ram_counter(spi, delay, 0, 0)?;
 
+        if self.inverted {
+            self.set_inverted(spi, true)?;
+        }
+
         //Initialize the lookup table with a refresh waveform
         self.set_lut(spi, delay, None)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let partial = self.prepare_display_frame()?;
+        self.start_refresh(spi, delay, partial)
+    }
+
+    fn finish_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.border_partial {
+            self.restore_border(spi)?;
+        }
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -97,6 +203,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -112,25 +221,22 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-
-        let mut epd = Epd1in54 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
@@ -142,11 +248,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+            .cmd_with_frame(spi, Command::WriteRam, frame, 0..frame.len())?;
         Ok(())
     }
 
@@ -160,31 +277,15 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
-        self.set_ram_counter(spi, delay, x, y)?;
-
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        Ok(())
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_ram(spi, delay, Command::WriteRam, buffer, x, y, width, height)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        if self.refresh == RefreshLut::Full {
-            self.interface
-                .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
-        } else if self.refresh == RefreshLut::Quick {
-            self.interface
-                .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xCF])?;
-        }
-
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        // MASTER Activation should not be interupted to avoid currption of panel images
-        // therefore a terminate command is send
-        self.interface.cmd(spi, Command::Nop)?;
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let partial = self.prepare_display_frame()?;
+        self.refresh(spi, delay, partial)
     }
 
     fn update_and_display_frame(
@@ -192,28 +293,45 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.wait_until_idle(spi, delay)?;
+        self.old_frame_shown = false;
         self.use_full_frame(spi, delay)?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self
+            .background_color
+            .fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
         self.interface.cmd(spi, Command::WriteRam2)?;
         self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
         Ok(())
     }
 
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        QuickRefresh::clear_partial_frame(self, spi, delay, x, y, width, height)
+    }
+
     fn set_background_color(&mut self, background_color: Color) {
         self.background_color = background_color;
     }
@@ -227,12 +345,15 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
+            RefreshLut::Full | RefreshLut::Custom(_) => {
+                self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE)
+            }
             RefreshLut::Quick => self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE),
         }?;
 
@@ -243,8 +364,6 @@ where
                 Command::WriteOtpSelection,
                 &[0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0],
             )?;
-            self.interface
-                .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xc0])?;
             self.interface.cmd(spi, Command::MasterActivation)?;
@@ -252,13 +371,22 @@ where
             // therefore a terminate command is send
             self.interface.cmd(spi, Command::Nop)?;
         }
+        self.interface.restore_operation(operation);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
@@ -269,11 +397,150 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Full refresh with the short waveform of a hot panel, about twice as fast but
+    /// with a slightly lower contrast, see
+    /// [fast full refreshes](crate::update_sequence#fast-full-refreshes)
+    pub fn display_frame_fast(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.wait_until_idle(spi, delay)?;
+        if self.border_partial {
+            self.restore_border(spi)?;
+        }
+        ssd16xx::display_frame_fast(&mut self.interface, spi, delay, IS_BUSY_LOW)?;
+        self.old_frame_shown = false;
+        // the OTP waveform replaced the LUT and the voltages of the driver
+        self.set_lut(spi, delay, None)
+    }
+
+    /// Checks the refresh guard for [display_frame](WaveshareDisplay::display_frame),
+    /// returns whether the refresh is a quick one
+    fn prepare_display_frame(&mut self) -> Result<bool, Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        Ok(self.refresh == RefreshLut::Quick)
+    }
+
+    /// [self_test](DriverControl::self_test) which also writes some bytes to the RAM and reads
+    /// them back
+    ///
+    /// Needs the data line of the controller connected to MISO, see `read_user_id`.
+    /// The read back is skipped if the busy line already failed.
+    pub fn self_test_read_back(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        let report = self.self_test(spi, delay)?;
+        if !report.passed() {
+            return Ok(report);
+        }
+        self.init(spi, delay)?;
+        self.set_ram_counter(spi, delay, 0, 0)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, &SELF_TEST_PATTERN)?;
+        self.set_ram_counter(spi, delay, 0, 0)?;
+        self.interface.cmd(spi, Command::ReadRam)?;
+        // the first byte is a dummy
+        let mut read = [0; SELF_TEST_PATTERN.len() + 1];
+        let result = self.interface.read(spi, &mut read);
+        self.interface.poison();
+        result?;
+        Ok(report.with_read_back(read[1..] == SELF_TEST_PATTERN))
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd1in54::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::ssd16xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd1in54::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::ssd16xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Shows the black/white RAM inverted, starting with the next refresh
+    ///
+    /// This only changes the RAM content option of the controller, so it is free:
+    /// the frame doesn't need to be transmitted again. The setting is kept over
+    /// [`wake_up`](WaveshareDisplay::wake_up). For a software inversion, which needs a
+    /// retransmit, see `Display::invert`.
+    pub fn set_inverted(&mut self, spi: &mut SPI, invert: bool) -> Result<(), Error<SPI::Error>> {
+        self.inverted = invert;
+        // RAM content option of the black/white RAM, 0b1000 inverts it
+        let option = if invert { 0x08 } else { 0x00 };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[option, 0x00])
+    }
+
+    /// Whether the black/white RAM is shown inverted
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -289,10 +556,10 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y && end_y < HEIGHT);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -302,6 +569,10 @@ where
             &[(start_x >> 3) as u8, (end_x >> 3) as u8],
         )?;
 
+        // y decrements through the RAM, so the window starts at its bottom RAM row:
+        // image row y is RAM row HEIGHT - 1 - y
+        let (start_y, end_y) = (HEIGHT - 1 - start_y, HEIGHT - 1 - end_y);
+
         // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
         self.interface.cmd_with_data(
             spi,
@@ -322,13 +593,15 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
             .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
+        // image row y is RAM row HEIGHT - 1 - y, see set_ram_area
+        let y = HEIGHT - 1 - y;
         // 2 Databytes: A[7:0] & 0..A[8]
         self.interface.cmd_with_data(
             spi,
@@ -338,12 +611,89 @@ where
         Ok(())
     }
 
+    /// Starts a refresh, during a partial one the border is kept from flashing
+    fn refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        partial: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_refresh(spi, delay, partial)?;
+        if partial {
+            // the border waveform must not change while the refresh is running
+            self.wait_until_idle(spi, delay)?;
+            self.restore_border(spi)?;
+        }
+        Ok(())
+    }
+
+    /// Starts a refresh without waiting for it, after a quick refresh the border
+    /// waveform has to be restored with [Self::restore_border] once it is done
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        partial: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        if partial {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_PARTIAL])?;
+            self.border_partial = true;
+        } else if self.border_partial {
+            self.restore_border(spi)?;
+        }
+        let sequence = self
+            .interface
+            .update_sequence_or(if partial { 0xCF } else { 0xC7 });
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(partial);
+        // MASTER Activation should not be interupted to avoid currption of panel images
+        // therefore a terminate command is send
+        self.interface.cmd(spi, Command::Nop)?;
+
+        // display mode 2 of a partial refresh copies the new frame into the old frame RAM
+        self.old_frame_shown = partial;
+        Ok(())
+    }
+
+    fn restore_border(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_FULL])?;
+        self.border_partial = false;
+        Ok(())
+    }
+
+    /// Writes `buffer` into the window at (x, y) of `ram`
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_ram(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        ram: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+
+        self.interface.cmd_with_data(spi, ram, buffer)
+    }
+
     fn set_lut_helper(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         assert!(buffer.len() == 159);
 
@@ -363,16 +713,143 @@ where
             Command::SourceDrivingVoltage,
             &[buffer[155], buffer[156], buffer[157]],
         )?;
+        let vcom = self.interface.vcom_or(buffer[158]);
         self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[buffer[158]])?;
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])?;
 
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes the frame currently shown into the RAM the controller compares against
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.use_full_frame(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)?;
+        self.old_frame_shown = true;
+        Ok(())
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Partial refresh with the border kept from flashing, select the
+    /// [quick LUT](RefreshLut::Quick) first
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.refresh(spi, delay, true)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    /// Only waits for the copy done by the last quick refresh to finish
+    fn promote_displayed_frame_to_old(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        Ok(self.old_frame_shown)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_ram(spi, delay, Command::WriteRam2, buffer, x, y, width, height)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_ram(spi, delay, Command::WriteRam, buffer, x, y, width, height)
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        let color = self
+            .background_color
+            .fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        self.old_frame_shown = false;
+        for ram in [Command::WriteRam, Command::WriteRam2] {
+            self.wait_until_idle(spi, delay)?;
+            self.set_ram_area(spi, delay, x, y, x + width - 1, y + height - 1)?;
+            self.set_ram_counter(spi, delay, x, y)?;
+            self.interface.cmd(spi, ram)?;
+            self.interface
+                .data_x_times(spi, color, line_bytes(width) * height)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Bus, Delay, Pin};
 
     #[test]
     fn epd_size() {
@@ -380,4 +857,126 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    fn epd(
+        bus: &Bus,
+        spi: &mut crate::mock::Spi,
+        delay: &mut Delay,
+    ) -> Epd1in54<crate::mock::Spi, crate::mock::BusyPin, crate::mock::DcPin, Pin, Delay> {
+        Epd1in54::new(spi, bus.busy(false), bus.dc(), Pin::new(), delay, None).unwrap()
+    }
+
+    #[test]
+    fn border_brackets_partial_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+        assert_eq!(bus.data_after(0x3C).unwrap(), [BORDER_FULL]);
+
+        bus.clear();
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x3C, 0x22, 0x20, 0xFF, 0x3C]);
+        assert_eq!(bus.data_after_nth(0x3C, 0).unwrap(), [BORDER_PARTIAL]);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xCF]);
+        assert_eq!(bus.data_after_nth(0x3C, 1).unwrap(), [BORDER_FULL]);
+        assert!(bus.waited_after(0xFF));
+
+        // full refreshes leave the border alone
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x22, 0x20, 0xFF]);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC7]);
+
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(0x3C), 2);
+    }
+
+    #[test]
+    fn fast_refresh_restores_temperature_and_lut() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+
+        bus.clear();
+        epd.display_frame_fast(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.commands(),
+            [0x1A, 0x22, 0x20, 0x22, 0x20, 0x22, 0x20, 0x32, 0x3F, 0x03, 0x04, 0x2C]
+        );
+        assert_eq!(bus.data_after(0x1A).unwrap(), ssd16xx::FAST_TEMPERATURE);
+        // the LUT for the fake temperature, the refresh and the real temperature
+        assert_eq!(bus.data_after_nth(0x22, 0).unwrap(), [0x91]);
+        assert_eq!(bus.data_after_nth(0x22, 1).unwrap(), [0xC7]);
+        assert_eq!(bus.data_after_nth(0x22, 2).unwrap(), [0xA1]);
+        assert_eq!(bus.data_after(0x32).unwrap(), LUT_FULL_UPDATE[..153]);
+        assert_eq!(epd.refresh_counters().full, 1);
+
+        // the following refresh uses the LUT of the driver again
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x22, 0x20, 0xFF]);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC7]);
+    }
+
+    #[test]
+    fn partial_refresh_keeps_the_shown_frame() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+        assert!(!epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        assert!(epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert!(!epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+    }
+
+    #[test]
+    fn partial_window_y_descends() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+
+        bus.clear();
+        let buffer = [0xAA; 2 * 8];
+        epd.update_partial_frame(&mut spi, &mut delay, &buffer, 8, 16, 16, 8)
+            .unwrap();
+        assert_eq!(bus.data_after(0x44).unwrap(), [1, 2]);
+        // rows 16..=23 are RAM rows 183 down to 176
+        assert_eq!(bus.data_after(0x45).unwrap(), [183, 0, 176, 0]);
+        assert_eq!(bus.data_after(0x4E).unwrap(), [1]);
+        assert_eq!(bus.data_after(0x4F).unwrap(), [183, 0]);
+        assert_eq!(bus.data_after(0x24).unwrap(), buffer);
+
+        // the full frame spans all RAM rows from the top one down
+        bus.clear();
+        epd.update_frame(&mut spi, &[0; 5000], &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x45).unwrap(), [199, 0, 0, 0]);
+        assert_eq!(bus.data_after(0x4F).unwrap(), [199, 0]);
+        assert_eq!(bus.data_after(0x11), None);
+    }
+
+    #[test]
+    fn init_scans_bottom_up() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        epd(&bus, &mut spi, &mut delay);
+        assert_eq!(bus.data_after(0x01).unwrap(), [199, 0, 0x01]);
+        assert_eq!(bus.data_after(0x11).unwrap(), [0x01]);
+    }
 }
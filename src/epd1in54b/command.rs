@@ -1,9 +1,10 @@
 //! SPI Commands for the Waveshare 1.54" red E-Ink Display
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     PanelSetting = 0x00,
 
     PowerSetting = 0x01,
@@ -37,4 +38,52 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff | Command::PowerOn | Command::DisplayRefresh => DataLen::Exact(0),
+            Command::PanelSetting
+            | Command::PllControl
+            | Command::TemperatureSensorSelection
+            | Command::VcomAndDataIntervalSetting
+            | Command::VcmDcSetting
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::LutForVcom
+            | Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutG0
+            | Command::LutG1
+            | Command::LutRedVcom
+            | Command::LutRed0
+            | Command::LutRed1 => DataLen::Exact(15),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::DataStartTransmission1 => Some(Plane::BlackWhite),
+            Command::DataStartTransmission2 => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutRedVcom.expected_data_len().accepts(15));
+        assert!(!Command::LutRedVcom.expected_data_len().accepts(42));
+        assert!(Command::PowerSetting.expected_data_len().accepts(4));
+        assert!(!Command::PowerSetting.expected_data_len().accepts(6));
+        assert!(Command::DataStartTransmission2
+            .expected_data_len()
+            .accepts(5000));
+    }
 }
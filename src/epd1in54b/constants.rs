@@ -1,3 +1,5 @@
+use crate::color::PlaneConvention;
+
 pub(crate) const LUT_VCOM0: &[u8] = &[
     0x0E, 0x14, 0x01, 0x0A, 0x06, 0x04, 0x0A, 0x0A, 0x0F, 0x03, 0x03, 0x0C, 0x06, 0x0A, 0x00,
 ];
@@ -29,3 +31,8 @@ pub(crate) const LUT_RED0: &[u8] = &[
 pub(crate) const LUT_RED1: &[u8] = &[
     0x03, 0x1D, 0x01, 0x01, 0x08, 0x23, 0x37, 0x37, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
+
+/// Bit sense of the black/white plane
+pub(crate) const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane
+pub(crate) const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsNotChromatic;
@@ -1,11 +1,25 @@
 //! A simple Driver for the Waveshare 1.54" (B) E-Ink Display via SPI
+//!
+//! # Data format
+//!
+//! The controller takes the black/white plane with 2 bits per pixel (`00` black,
+//! `11` white) and the red plane with the usual 1 bit per pixel. [Display1in54b]
+//! keeps both planes at 1 bit per pixel (10000 bytes) and the driver expands the
+//! black/white plane while transmitting it, so storing the expanded plane doesn't
+//! cost another 5000 bytes of RAM, at the cost of twice the bytes on the bus.
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
+use crate::vcom;
 
 //The Lookup Tables for the Display
 mod constants;
@@ -15,26 +29,45 @@ use crate::epd1in54b::constants::*;
 pub const WIDTH: u32 = 200;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 200;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd1in54b",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: false,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(8_000_000, 12_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::{Color, Plane};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, line_bytes};
 
 /// Full size buffer for use with the 1in54b EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
+///
+/// Pass [bw_buffer](crate::graphics::Display::bw_buffer) and
+/// [chromatic_buffer](crate::graphics::Display::chromatic_buffer) to
+/// `update_color_frame`, the black/white plane is expanded while transmitting it.
 #[cfg(feature = "graphics")]
 pub type Display1in54b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    true,
+    { crate::tri_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    crate::color::TriColor,
 >;
 
 /// Epd1in54b driver
@@ -52,8 +85,31 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd1in54b {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
 
         // set the power settings
         self.interface
@@ -79,7 +135,8 @@ where
         // set resolution
         self.send_resolution(spi)?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0E])?;
+        let vcom = self.interface.vcom_or(0x0E);
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
 
         self.set_lut(spi, delay, None)?;
 
@@ -104,9 +161,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     fn update_achromatic_frame(
@@ -114,27 +189,35 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.send_resolution(spi)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-
-        for b in black {
-            let expanded = expand_bits(*b);
-            self.interface.data(spi, &expanded)?;
-        }
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
         Ok(())
     }
 }
@@ -149,6 +232,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -156,18 +242,12 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd1in54b { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -187,10 +267,16 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, color: Color) {
         self.color = color;
     }
@@ -212,23 +298,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.send_resolution(spi)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
 
-        for b in buffer {
-            // Two bits per pixel
-            let expanded = expand_bits(*b);
-            self.interface.data(spi, &expanded)?;
-        }
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, frame)?;
 
         //NOTE: Example code has a delay here
 
         // Clear the read layer
-        let color = self.color.get_byte_value();
-        let nbits = WIDTH * (HEIGHT / 8);
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        let nbits = line_bytes(WIDTH) * HEIGHT;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, nbits)?;
@@ -247,13 +334,19 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         unimplemented!()
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
         Ok(())
     }
 
@@ -262,29 +355,30 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
         // Clear the black
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         // Uses 2 bits per pixel
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface
-            .data_x_times(spi, color, 2 * (WIDTH / 8 * HEIGHT))?;
+            .data_x_times(spi, color, 2 * line_bytes(WIDTH) * HEIGHT)?;
 
         // Clear the red
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
         Ok(())
     }
 
@@ -293,7 +387,8 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         self.interface
             .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
         self.interface
@@ -309,10 +404,15 @@ where
         self.interface
             .cmd_with_data(spi, Command::LutRed1, LUT_RED1)?;
 
+        self.interface.restore_operation(operation);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
@@ -326,12 +426,80 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.send_resolution(spi)?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+
+        for chunk in black.chunks(0..black.len()) {
+            for b in chunk {
+                // Two bits per pixel
+                let expanded = expand_bits(*b);
+                self.interface.data(spi, &expanded)?;
+            }
+            black.sent(chunk.len());
+        }
+        Ok(())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_frame(spi, chromatic, 0..chromatic.len())?;
+        Ok(())
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd1in54b::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd1in54b::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
     }
 
     fn cmd_with_data(
@@ -339,22 +507,24 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::ResolutionSetting)?;
-
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[w as u8, (h >> 8) as u8, h as u8],
+        )
     }
 }
 
+/// Expands 8 pixels of 1 bit to 2 bits each, the first pixel stays in the highest bits
+/// of the first byte
 fn expand_bits(bits: u8) -> [u8; 2] {
     let mut x = bits as u16;
 
@@ -369,6 +539,7 @@ fn expand_bits(bits: u8) -> [u8; 2] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Bus, Delay, Pin};
 
     #[test]
     fn epd_size() {
@@ -376,4 +547,54 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn expand_pixel_pairs() {
+        // white is 1, every pixel becomes a pair of equal bits
+        assert_eq!(expand_bits(0xFF), [0xFF, 0xFF]);
+        assert_eq!(expand_bits(0x00), [0x00, 0x00]);
+        // the first pixel ends up in the highest bits
+        assert_eq!(expand_bits(0b1000_0000), [0b1100_0000, 0x00]);
+        assert_eq!(expand_bits(0b0000_0001), [0x00, 0b0000_0011]);
+        // white, black, white, white, black, black, white, black
+        assert_eq!(expand_bits(0b1011_0010), [0b1100_1111, 0b0000_1100]);
+        assert_eq!(expand_bits(0b0101_0101), [0b0011_0011, 0b0011_0011]);
+    }
+
+    #[test]
+    fn color_frame_planes() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd1in54b::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        let plane = line_bytes(WIDTH) as usize * HEIGHT as usize;
+        let mut black = [0xFF; 5000];
+        black[0] = 0b1011_0010;
+        let mut chromatic = [0xFF; 5000];
+        chromatic[1] = 0x0F;
+        assert_eq!(black.len(), plane);
+        epd.update_color_frame(&mut spi, &mut delay, &black, &chromatic)
+            .unwrap();
+
+        let bw = bus
+            .data_after(Command::DataStartTransmission1 as u8)
+            .unwrap();
+        assert_eq!(bw.len(), 2 * plane);
+        assert_eq!(bw[..3], [0b1100_1111, 0b0000_1100, 0xFF]);
+        assert_eq!(
+            bus.data_after(Command::DataStartTransmission2 as u8)
+                .unwrap(),
+            chromatic
+        );
+    }
 }
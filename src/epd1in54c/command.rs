@@ -1,9 +1,10 @@
 //! SPI Commands for the Waveshare 1.54" C yellow E-Ink Display
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     PanelSetting = 0x00,
 
     PowerSetting = 0x01,
@@ -35,4 +36,45 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff | Command::PowerOn | Command::DisplayRefresh => DataLen::Exact(0),
+            Command::DeepSleep
+            | Command::PllControl
+            | Command::TemperatureSensorSelection
+            | Command::VcomAndDataIntervalSetting
+            | Command::VcmDcSetting
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::PanelSetting => DataLen::Exact(2),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::PowerSetting => DataLen::Max(5),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::DataStartTransmission1 => Some(Plane::BlackWhite),
+            Command::DataStartTransmission2 => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::PanelSetting.expected_data_len().accepts(2));
+        assert!(!Command::PanelSetting.expected_data_len().accepts(1));
+        assert!(Command::ResolutionSetting.expected_data_len().accepts(3));
+        assert!(!Command::ResolutionSetting.expected_data_len().accepts(4));
+        assert!(Command::DataStartTransmission1
+            .expected_data_len()
+            .accepts(2888));
+    }
 }
@@ -2,26 +2,51 @@
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd1in54 in pixels
 pub const WIDTH: u32 = 152;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 152;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd1in54c",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: false,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 const IS_BUSY_LOW: bool = true;
-const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsNotChromatic;
+const NUM_DISPLAY_BITS: u32 = line_bytes(WIDTH) * HEIGHT;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::{Color, Plane, PlaneConvention};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, line_bytes};
 
 /// Full size buffer for use with the 1in54c EPD
 /// TODO this should be a TriColor, but let's keep it as is at first
@@ -49,12 +74,35 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd1in54c {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
         // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_1in54c.c
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
@@ -74,6 +122,18 @@ where
 
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -91,9 +151,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     fn update_achromatic_frame(
@@ -101,11 +179,8 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
-
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     fn update_chromatic_frame(
@@ -113,10 +188,27 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
 
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
         Ok(())
     }
 }
@@ -131,6 +223,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -138,18 +233,12 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd1in54c { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
 
         self.command(spi, Command::PowerOff)?;
@@ -159,10 +248,16 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, color: Color) {
         self.color = color;
     }
@@ -184,11 +279,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, buffer)?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, frame)?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
 
         self.command(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
@@ -206,15 +312,16 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         unimplemented!()
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -222,22 +329,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.wait_until_idle(spi, delay)?;
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
         // Clear the black
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.command(spi, Command::DataStartTransmission1)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
         // Clear the chromatic
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.command(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
@@ -249,11 +357,16 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
@@ -267,12 +380,46 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd_with_frame(
+            spi,
+            Command::DataStartTransmission1,
+            black,
+            0..black.len(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd_with_frame(
+            spi,
+            Command::DataStartTransmission2,
+            chromatic,
+            0..chromatic.len(),
+        )?;
+
+        Ok(())
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
     }
 
     fn cmd_with_data(
@@ -280,27 +427,31 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::ResolutionSetting)?;
-
-        // | D7 | D6 | D5 | D4 | D3 | D2 | D1 | D0 |
-        // |       HRES[7:3]        |  0 |  0 |  0 |
-        self.send_data(spi, &[(w as u8) & 0b1111_1000])?;
-        // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
-        // |  - |  - |  - |  - |  - |  - |  - | VRES[8] |
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
-        // |                  VRES[7:0]                 |
-        // Specification shows C/D is zero while sending the last byte,
-        // but upstream code does not implement it like that. So for now
-        // we follow upstream code.
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[
+                // | D7 | D6 | D5 | D4 | D3 | D2 | D1 | D0 |
+                // |       HRES[7:3]        |  0 |  0 |  0 |
+                (w as u8) & 0b1111_1000,
+                // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
+                // |  - |  - |  - |  - |  - |  - |  - | VRES[8] |
+                (w >> 8) as u8,
+                // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
+                // |                  VRES[7:0]                 |
+                // Specification shows C/D is zero while sending the last byte,
+                // but upstream code does not implement it like that. So for now
+                // we follow upstream code.
+                h as u8,
+            ],
+        )
     }
 }
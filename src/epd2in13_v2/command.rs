@@ -1,6 +1,6 @@
 //! SPI Commands for the Waveshare 2.13" v2
 
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 extern crate bit_field;
 use bit_field::BitField;
@@ -10,7 +10,7 @@ use bit_field::BitField;
 /// For more infos about the addresses and what they are doing look into the pdfs
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     DriverOutputControl = 0x01,
     GateDrivingVoltageCtrl = 0x03,
     SourceDrivingVoltageCtrl = 0x04,
@@ -35,6 +35,7 @@ pub(crate) enum Command {
     ProgramVcomOpt = 0x2A,
     WriteVcomRegister = 0x2C,
     OtpRegisterRead = 0x2D,
+    UserIdRead = 0x2E,
     StatusBitRead = 0x2F,
     ProgramWsOtp = 0x30,
     LoadWsOtp = 0x31,
@@ -89,7 +90,6 @@ impl DriverOutput {
 ///  | | `------------- load temp
 ///  | `--------------- enable clock
 ///  `----------------- enable analog
-
 pub(crate) struct DisplayUpdateControl2(pub u8);
 #[allow(dead_code)]
 impl DisplayUpdateControl2 {
@@ -112,6 +112,11 @@ impl DisplayUpdateControl2 {
         self
     }
 
+    pub fn display_mode_2(mut self) -> Self {
+        self.0.set_bit(3, true);
+        self
+    }
+
     pub fn load_lut(mut self) -> Self {
         self.0.set_bit(4, true);
         self
@@ -273,4 +278,71 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::SwReset
+            | Command::MasterActivation
+            | Command::VcomSense
+            | Command::ProgramVcomOpt
+            | Command::ProgramWsOtp
+            | Command::LoadWsOtp
+            | Command::ProgramOtpSelection
+            | Command::Nop => DataLen::Exact(0),
+            Command::GateDrivingVoltageCtrl
+            | Command::DeepSleepMode
+            | Command::DataEntryModeSetting
+            | Command::HvReadyDetection
+            | Command::VciDetection
+            | Command::DisplayUpdateControl2
+            | Command::VcomSenseDuration
+            | Command::WriteVcomRegister
+            | Command::SetDummyLinePeriod
+            | Command::SetGateLineWidth
+            | Command::BorderWaveformControl
+            | Command::ReadRamOption
+            | Command::SetRamXAddressCounter
+            | Command::AutoWriteRedRamRegularPattern
+            | Command::AutoWriteBwRamRegularPattern
+            | Command::SetAnalogBlockControl
+            | Command::SetDigitalBlockControl => DataLen::Exact(1),
+            Command::GateScanStartPosition
+            | Command::TemperatureSensorControlWrite
+            | Command::SetRamXAddressStartEndPosition
+            | Command::SetRamYAddressCounter => DataLen::Exact(2),
+            Command::DriverOutputControl | Command::SourceDrivingVoltageCtrl => DataLen::Exact(3),
+            Command::SetRamYAddressStartEndPosition => DataLen::Exact(4),
+            Command::DisplayUpdateControl1 => DataLen::Max(2),
+            Command::TemperatureSensorExtControlWrite => DataLen::Max(3),
+            Command::BoosterSoftStartControl => DataLen::Max(4),
+            Command::WriteOtpSelection => DataLen::Max(10),
+            // 70 bytes on the V2, the V3 sends the voltages following its 153 byte LUT along
+            Command::WriteLutRegister => DataLen::Max(159),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::WriteLutRegister.expected_data_len().accepts(70));
+        assert!(!Command::WriteLutRegister.expected_data_len().accepts(160));
+        assert!(Command::DisplayUpdateControl1
+            .expected_data_len()
+            .accepts(2));
+        assert!(!Command::DisplayUpdateControl1
+            .expected_data_len()
+            .accepts(3));
+        assert!(Command::SetRamYAddressCounter
+            .expected_data_len()
+            .accepts(2));
+        assert!(!Command::SetRamYAddressCounter
+            .expected_data_len()
+            .accepts(1));
+    }
 }
@@ -1,6 +1,7 @@
-#[rustfmt::skip]
+use crate::color::PlaneConvention;
 
 #[cfg(feature = "epd2in13_v2")]
+#[rustfmt::skip]
 // Original Waveforms from Waveshare
 pub(crate) const LUT_FULL_UPDATE: [u8; 70] =[
     0x80,0x60,0x40,0x00,0x00,0x00,0x00,             // LUT0: BB:     VS 0 ~7
@@ -84,3 +85,6 @@ pub(crate) const LUT_FULL_UPDATE: [u8; 159] =[
 	0x22,0x22,0x22,0x22,0x22,0x22,0x0,0x0,0x0,		
 	0x22,0x17,0x41,0x0,0x32,0x36
 ];
+
+/// Bit sense of the black/white plane
+pub(crate) const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
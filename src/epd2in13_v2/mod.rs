@@ -21,10 +21,22 @@ use embedded_hal::{
     spi::SpiDevice,
 };
 
-use crate::buffer_len;
-use crate::color::Color;
+use crate::busy::{
+    BusyMode, BusyTimings, Operation, RefreshLimits, SelfTestReport, SELF_TEST_PATTERN,
+};
+use crate::color::{Color, Plane};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::reset::ResetConfig;
+use crate::ssd16xx;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RamBank, RefreshLut, WaveshareDisplay,
+};
+use crate::update_sequence::UpdateSequence;
+use crate::vcom;
+use crate::voltage::DrivingVoltages;
+use crate::{buffer_len, line_bytes};
 
 pub(crate) mod command;
 use self::command::{
@@ -35,7 +47,7 @@ use self::command::{
 
 pub(crate) mod constants;
 
-use self::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+use self::constants::{BW_CONVENTION, LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 #[cfg(all(feature = "epd2in13_v2", feature = "epd2in13_v3"))]
 compile_error!(
     "feature \"epd2in13_v2\" and feature \"epd2in13_v3\" cannot be enabled at the same time"
@@ -60,12 +72,50 @@ pub const WIDTH: u32 = 122;
 
 /// Height of the display
 pub const HEIGHT: u32 = 250;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in13_v2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Refresh with the waveform stored in the OTP of the controller, display mode 1
+///
+/// Takes the temperature into account like the full refresh, but doesn't
+/// need the LUT uploaded by the driver. Use it with [Epd2in13::set_refresh].
+pub const REFRESH_OTP_MODE_1: RefreshLut = RefreshLut::Custom(0);
+
+/// Refresh with the waveform stored in the OTP of the controller, display mode 2
+///
+/// The faster waveform of the OTP, on the V3 with less flashing than mode 1.
+pub const REFRESH_OTP_MODE_2: RefreshLut = RefreshLut::Custom(1);
+
+/// Worst case times for [BusyMode::FixedDelay], from the specification with some margin
+const BUSY_TIMINGS: BusyTimings = BusyTimings {
+    init: 100_000,
+    full_refresh: 3_000_000,
+    partial_refresh: 500_000,
+    sleep: 10_000,
+    command: 10_000,
+};
+
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(2_000_000, BUSY_TIMINGS.full_refresh)
+    .with_quick(300_000, BUSY_TIMINGS.partial_refresh);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+
 /// Epd2in13 (V2 & V3) driver
 ///
 /// To use this driver for V2 of the display, feature \"epd2in13_v3\" needs to be disabled and feature \"epd2in13_v2\" enabled.
@@ -78,6 +128,11 @@ pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY> {
     /// Background Color
     background_color: Color,
     refresh: RefreshLut,
+    /// Black/white RAM shown inverted
+    inverted: bool,
+    /// Windows written by [Epd2in13::write_ram_window] wait for
+    /// [Epd2in13::refresh_written]
+    ram_windows: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -89,9 +144,37 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in13 {
+            interface,
+            sleep_mode: DeepSleepMode::Mode1,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            inverted: false,
+            ram_windows: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.ram_windows = false;
         // HW reset
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         if self.refresh == RefreshLut::Quick {
             self.set_vcom_register(spi, (-9).vcom())?;
@@ -109,6 +192,7 @@ where
                 DisplayUpdateControl2::new().enable_analog().enable_clock(),
             )?;
             self.command(spi, Command::MasterActivation)?;
+            self.interface.start_operation(Operation::Init);
             self.wait_until_idle(spi, delay)?;
 
             self.set_border_waveform(
@@ -122,6 +206,7 @@ where
         } else {
             self.wait_until_idle(spi, delay)?;
             self.command(spi, Command::SwReset)?;
+            self.interface.start_operation(Operation::Init);
             self.wait_until_idle(spi, delay)?;
 
             self.set_driver_output(
@@ -155,12 +240,18 @@ where
 
             self.set_vcom_register(spi, (-21).vcom())?;
 
-            self.set_gate_driving_voltage(spi, 190.gate_driving_decivolt())?;
+            let [vgh, vsh1, vsh2, vsl] = self.interface.ssd16xx_voltages_or([
+                190.gate_driving_decivolt().0,
+                150.source_driving_decivolt().0,
+                50.source_driving_decivolt().0,
+                (-150).source_driving_decivolt().0,
+            ]);
+            self.set_gate_driving_voltage(spi, GateDrivingVoltage(vgh))?;
             self.set_source_driving_voltage(
                 spi,
-                150.source_driving_decivolt(),
-                50.source_driving_decivolt(),
-                (-150).source_driving_decivolt(),
+                SourceDrivingVoltage(vsh1),
+                SourceDrivingVoltage(vsh2),
+                SourceDrivingVoltage(vsl),
             )?;
 
             self.set_gate_line_width(spi, 10)?;
@@ -168,9 +259,26 @@ where
             self.set_lut(spi, delay, Some(self.refresh))?;
         }
 
+        if self.inverted {
+            self.set_inverted(spi, true)?;
+        }
+
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        self.start_refresh(spi, self.refresh, false)
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -183,6 +291,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -190,23 +301,22 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let mut epd = Epd2in13 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
-            sleep_mode: DeepSleepMode::Mode1,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_busy_mode(spi, busy, dc, rst, delay, delay_us, BusyMode::Pin)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
 
         // All sample code enables and disables analog/clocks...
@@ -219,6 +329,7 @@ where
                 .disable_clock(),
         )?;
         self.command(spi, Command::MasterActivation)?;
+        self.interface.start_operation(Operation::Sleep);
 
         self.set_sleep_mode(spi, self.sleep_mode)?;
         Ok(())
@@ -229,19 +340,33 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.ensure_no_session()?;
+        assert!(frame.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
 
-        self.cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRam, frame, 0..frame.len())?;
 
-        if self.refresh == RefreshLut::Full {
+        if self.refresh != RefreshLut::Quick {
             // Always keep the base buffer equal to current if not doing partial refresh.
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
             self.set_ram_address_counters(spi, delay, 0, 0)?;
 
-            self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
+            self.interface
+                .cmd_with_frame(spi, Command::WriteRamRed, frame, 0..frame.len())?;
         }
         Ok(())
     }
@@ -258,8 +383,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!((width * height / 8) as usize == buffer.len());
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
+        assert!((line_bytes(width) * height) as usize == buffer.len());
 
         // This should not be used when doing partial refresh. The RAM_RED must
         // be updated with the last buffer having been displayed. Doing partial
@@ -267,14 +396,14 @@ where
         // RAM content). Using this function will most probably make the actual
         // display incorrect as the controler will compare with something
         // incorrect.
-        assert!(self.refresh == RefreshLut::Full);
+        assert!(self.refresh != RefreshLut::Quick);
 
         self.set_ram_area(spi, x, y, x + width, y + height)?;
         self.set_ram_address_counters(spi, delay, x, y)?;
 
         self.cmd_with_data(spi, Command::WriteRam, buffer)?;
 
-        if self.refresh == RefreshLut::Full {
+        if self.refresh != RefreshLut::Quick {
             // Always keep the base buffer equals to current if not doing partial refresh.
             self.set_ram_area(spi, x, y, x + width, y + height)?;
             self.set_ram_address_counters(spi, delay, x, y)?;
@@ -287,24 +416,9 @@ where
 
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        if self.refresh == RefreshLut::Full {
-            self.set_display_update_control_2(
-                spi,
-                DisplayUpdateControl2::new()
-                    .enable_clock()
-                    .enable_analog()
-                    .display()
-                    .disable_analog()
-                    .disable_clock(),
-            )?;
-        } else {
-            self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())?;
-        }
-        self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -312,7 +426,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
@@ -322,8 +436,12 @@ where
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        let color = self.background_color.get_byte_value();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.ensure_no_session()?;
+        let color = self
+            .background_color
+            .fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
@@ -336,7 +454,7 @@ where
         )?;
 
         // Always keep the base buffer equals to current if not doing partial refresh.
-        if self.refresh == RefreshLut::Full {
+        if self.refresh != RefreshLut::Quick {
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
             self.set_ram_address_counters(spi, delay, 0, 0)?;
 
@@ -371,16 +489,25 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
             Some(RefreshLut::Quick) => &LUT_PARTIAL_UPDATE,
+            // loaded from OTP with every refresh
+            Some(REFRESH_OTP_MODE_1 | REFRESH_OTP_MODE_2) => return Ok(()),
+            Some(RefreshLut::Full | RefreshLut::Custom(_)) | None => &LUT_FULL_UPDATE,
         };
 
-        self.cmd_with_data(spi, Command::WriteLutRegister, buffer)
+        let operation = self.interface.set_operation(error::Operation::Lut);
+        self.cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
+        self.interface.restore_operation(operation);
+        Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
@@ -394,6 +521,183 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Replaces the gate and source driving voltages sent by `init`/`wake_up`, `None`
+    /// restores the ones of the driver, see [crate::voltage]
+    ///
+    /// Takes effect with the next `wake_up` with the full LUT, the quick refreshes keep
+    /// the voltages sent last. Returns [Error::InvalidVoltage] without changing anything
+    /// for voltages the controller doesn't support.
+    pub fn set_driving_voltages(
+        &mut self,
+        voltages: Option<DrivingVoltages>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(voltages) = voltages {
+            voltages
+                .ssd16xx
+                .registers()
+                .map_err(Error::InvalidVoltage)?;
+        }
+        self.interface.set_driving_voltages(voltages);
+        Ok(())
+    }
+
+    /// Full refresh with the short waveform of a hot panel, about twice as fast but
+    /// with a slightly lower contrast, see
+    /// [fast full refreshes](crate::update_sequence#fast-full-refreshes)
+    pub fn display_frame_fast(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.wait_until_idle(spi, delay)?;
+        ssd16xx::display_frame_fast(&mut self.interface, spi, delay, IS_BUSY_LOW)?;
+        // the OTP waveform replaced the LUT and the voltages of the driver, like a
+        // switch with `set_refresh` this needs the configuration again
+        self.init(spi, delay)
+    }
+
+    /// [self_test](DriverControl::self_test) which also writes some bytes to the RAM and reads
+    /// them back
+    ///
+    /// Needs the data line of the controller connected to MISO, see `read_user_id`.
+    /// The read back is skipped if the busy line already failed.
+    pub fn self_test_read_back(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        let report = self.self_test(spi, delay)?;
+        if !report.passed() {
+            return Ok(report);
+        }
+        self.init(spi, delay)?;
+        self.set_ram_address_counters(spi, delay, 0, 0)?;
+        self.cmd_with_data(spi, Command::WriteRam, &SELF_TEST_PATTERN)?;
+        self.set_ram_address_counters(spi, delay, 0, 0)?;
+        self.command(spi, Command::ReadRam)?;
+        // the first byte is a dummy
+        let mut read = [0; SELF_TEST_PATTERN.len() + 1];
+        let result = self.interface.read(spi, &mut read);
+        self.interface.poison();
+        result?;
+        Ok(report.with_read_back(read[1..] == SELF_TEST_PATTERN))
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in13::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::ssd16xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in13::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::ssd16xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Same as [new](WaveshareDisplay::new), but selects how to wait for the display
+    ///
+    /// With [BusyMode::FixedDelay] already the initialisation doesn't use the busy
+    /// pin, which can then be a [DummyInputPin](crate::busy::DummyInputPin).
+    /// See [crate::busy] for the drawbacks.
+    pub fn new_with_busy_mode(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        busy_mode: BusyMode,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    /// Selects whether to poll the busy pin or to wait worst case times, see [crate::busy]
+    pub fn set_busy_mode(&mut self, busy_mode: BusyMode) {
+        self.interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+    }
+
+    /// Current busy mode
+    pub fn busy_mode(&self) -> BusyMode {
+        self.interface.busy_mode()
+    }
+
+    /// Shows the black/white RAM inverted, starting with the next refresh
+    ///
+    /// This only changes the RAM content option of the controller, so it is free:
+    /// the frame doesn't need to be transmitted again. The setting is kept over
+    /// [`wake_up`](WaveshareDisplay::wake_up). For a software inversion, which needs a
+    /// retransmit, see `Display::invert`.
+    pub fn set_inverted(&mut self, spi: &mut SPI, invert: bool) -> Result<(), Error<SPI::Error>> {
+        self.inverted = invert;
+        // RAM content option of the black/white RAM, 0b1000 inverts it
+        let option = if invert { 0x08 } else { 0x00 };
+        self.cmd_with_data(spi, Command::DisplayUpdateControl1, &[option, 0x00])
+    }
+
+    /// Whether the black/white RAM is shown inverted
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
     /// When using partial refresh, the controller uses the provided buffer for
     /// comparison with new buffer.
     pub fn set_partial_base_buffer(
@@ -401,7 +705,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, delay, 0, 0)?;
@@ -410,6 +714,129 @@ where
         Ok(())
     }
 
+    /// Writes `buffer` into the window at `x`, `y` of the RAM `bank` without refreshing
+    ///
+    /// Any number of windows can be written, [`refresh_written`](Self::refresh_written)
+    /// then shows all of them with a single refresh. This is a lot faster than
+    /// refreshing after every window. Until then frame updates, refreshes and clears
+    /// return [`Error::PartialSessionActive`].
+    ///
+    /// Quick refreshes show the difference to the [old](RamBank::Old) RAM, which they
+    /// don't update, see [`set_partial_base_buffer`](Self::set_partial_base_buffer).
+    ///
+    /// `x` and `width` should be multiples of 8. Returns [`Error::InvalidWindow`]
+    /// without sending anything if the window is empty or doesn't fit on the panel.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        bank: RamBank,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_address_counters(spi, delay, x, y)?;
+        let command = match bank {
+            RamBank::New => Command::WriteRam,
+            RamBank::Old => Command::WriteRamRed,
+        };
+        self.cmd_with_data(spi, command, buffer)?;
+        self.ram_windows = true;
+        Ok(())
+    }
+
+    /// Shows the windows written by [`write_ram_window`](Self::write_ram_window) with a
+    /// single refresh
+    ///
+    /// `mode` selects the waveform of this refresh, the LUT of the mode selected with
+    /// [`set_refresh`](Self::set_refresh) is uploaded again afterwards if they differ.
+    /// Returns [`Error::NoPartialSession`] if no window was written.
+    pub fn refresh_written(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        if !self.ram_windows {
+            return Err(Error::NoPartialSession);
+        }
+        self.ram_windows = false;
+        if mode != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        if mode != self.refresh {
+            self.set_lut(spi, delay, Some(mode))?;
+        }
+        self.refresh(spi, delay, mode, true)?;
+        if mode != self.refresh {
+            self.set_lut(spi, delay, Some(self.refresh))?;
+        }
+        Ok(())
+    }
+
+    /// Refreshes with the waveform of `lut` and waits for the end, `windows` counts it
+    /// as partial one
+    fn refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: RefreshLut,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_refresh(spi, lut, windows)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Starts a refresh with `lut` without waiting for it
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        lut: RefreshLut,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        let full = DisplayUpdateControl2::new()
+            .enable_clock()
+            .enable_analog()
+            .display()
+            .disable_analog()
+            .disable_clock();
+        let control = match lut {
+            RefreshLut::Quick => DisplayUpdateControl2::new().display(),
+            REFRESH_OTP_MODE_1 => full.load_temp().load_lut(),
+            REFRESH_OTP_MODE_2 => full.load_temp().load_lut().display_mode_2(),
+            RefreshLut::Full | RefreshLut::Custom(_) => full,
+        };
+        let control = DisplayUpdateControl2(self.interface.update_sequence_or(control.0));
+        self.set_display_update_control_2(spi, control)?;
+        self.command(spi, Command::MasterActivation)?;
+        if windows {
+            self.interface.count_partial_refresh();
+        } else {
+            self.interface.count_refresh(lut == RefreshLut::Quick);
+        }
+        self.interface.start_operation(match lut {
+            RefreshLut::Quick => Operation::PartialRefresh,
+            _ => Operation::FullRefresh,
+        });
+        Ok(())
+    }
+
+    fn ensure_no_session(&self) -> Result<(), Error<SPI::Error>> {
+        if self.ram_windows {
+            return Err(Error::PartialSessionActive);
+        }
+        Ok(())
+    }
+
     /// Selects which sleep mode will be used when triggering the deep sleep.
     pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
         self.sleep_mode = mode;
@@ -417,12 +844,15 @@ where
 
     /// Sets the refresh mode. When changing mode, the screen will be
     /// re-initialized accordingly.
+    ///
+    /// Besides the full and quick LUTs of the driver, the waveforms in the OTP
+    /// of the controller are available as [REFRESH_OTP_MODE_1] and [REFRESH_OTP_MODE_2].
     pub fn set_refresh(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh: RefreshLut,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if self.refresh != refresh {
             self.refresh = refresh;
             self.init(spi, delay)?;
@@ -434,7 +864,7 @@ where
         &mut self,
         spi: &mut SPI,
         start: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(start <= 295);
         self.cmd_with_data(
             spi,
@@ -447,7 +877,7 @@ where
         &mut self,
         spi: &mut SPI,
         borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::BorderWaveformControl,
@@ -455,15 +885,16 @@ where
         )
     }
 
-    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), SPI::Error> {
-        self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom.0])
+    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), Error<SPI::Error>> {
+        let vcom = self.interface.vcom_or(vcom.0);
+        self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
     }
 
     fn set_gate_driving_voltage(
         &mut self,
         spi: &mut SPI,
         voltage: GateDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[voltage.0])
     }
 
@@ -471,12 +902,12 @@ where
         &mut self,
         spi: &mut SPI,
         number_of_lines: u8,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(number_of_lines <= 127);
         self.cmd_with_data(spi, Command::SetDummyLinePeriod, &[number_of_lines])
     }
 
-    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), SPI::Error> {
+    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::SetGateLineWidth, &[width & 0x0F])
     }
 
@@ -487,7 +918,7 @@ where
         vsh1: SourceDrivingVoltage,
         vsh2: SourceDrivingVoltage,
         vsl: SourceDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SourceDrivingVoltageCtrl,
@@ -501,16 +932,24 @@ where
         &mut self,
         spi: &mut SPI,
         value: DisplayUpdateControl2,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[value.0])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
+    fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+        output: DriverOutput,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
     }
 
@@ -521,7 +960,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -534,7 +973,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -560,7 +999,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
@@ -572,7 +1011,7 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -581,7 +1020,7 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 }
@@ -589,6 +1028,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::busy::DummyInputPin;
+    use crate::mock::{Bus, Delay, Pin};
+    extern crate std;
+    use std::vec::Vec;
 
     #[test]
     fn epd_size() {
@@ -596,4 +1039,408 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn set_inverted() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(bus.count(0x21), 0);
+
+        bus.clear();
+        epd.set_inverted(&mut spi, true).unwrap();
+        assert_eq!(bus.commands(), [0x21]);
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x08, 0x00]);
+        assert!(epd.is_inverted());
+
+        // the controller forgets it on reset, init restores it
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x08, 0x00]);
+
+        bus.clear();
+        epd.set_inverted(&mut spi, false).unwrap();
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x00, 0x00]);
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(0x21), 0);
+    }
+
+    #[test]
+    fn fixed_delay_waits_per_operation() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let delays = Delay::new();
+        let mut delay = delays.clone();
+        let mut epd = Epd2in13::new_with_busy_mode(
+            &mut spi,
+            DummyInputPin,
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+            BusyMode::FixedDelay,
+        )
+        .unwrap();
+        // after the hardware and the software reset
+        assert_eq!(
+            delays.delays()[3..5],
+            [BUSY_TIMINGS.init, BUSY_TIMINGS.init]
+        );
+
+        let start = delays.delays().len();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(delays.delays()[start..], [BUSY_TIMINGS.full_refresh]);
+
+        epd.set_refresh(&mut spi, &mut delay, RefreshLut::Quick)
+            .unwrap();
+        let start = delays.delays().len();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(delays.delays()[start..], [BUSY_TIMINGS.partial_refresh]);
+
+        // the dummy pin never reads as busy, polling it doesn't wait at all
+        epd.set_busy_mode(BusyMode::Pin);
+        let start = delays.delays().len();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(delays.delays().len(), start);
+    }
+
+    #[test]
+    fn refresh_modes() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        for (refresh, control, uploads_lut) in [
+            (RefreshLut::Quick, 0x04, true),
+            (REFRESH_OTP_MODE_1, 0xF7, false),
+            (REFRESH_OTP_MODE_2, 0xFF, false),
+            // unknown indices fall back to the full LUT
+            (RefreshLut::Custom(7), 0xC7, true),
+            (RefreshLut::Full, 0xC7, true),
+        ] {
+            bus.clear();
+            epd.set_refresh(&mut spi, &mut delay, refresh).unwrap();
+            assert_eq!(bus.commands().contains(&0x32), uploads_lut);
+
+            bus.clear();
+            epd.display_frame(&mut spi, &mut delay).unwrap();
+            assert_eq!(bus.commands(), [0x22, 0x20]);
+            assert_eq!(bus.data_after(0x22).unwrap(), [control]);
+        }
+    }
+
+    #[test]
+    fn update_sequence_presets() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        for (sequence, control) in [
+            (UpdateSequence::FULL_WITH_TEMP, 0xF7),
+            (UpdateSequence::FAST_NO_TEMP, 0xD7),
+            (UpdateSequence::DISPLAY_ONLY, 0xC7),
+        ] {
+            epd.set_update_sequence(Some(sequence)).unwrap();
+            // replaces the sequence of the quick refresh as well
+            for refresh in [RefreshLut::Full, RefreshLut::Quick] {
+                epd.set_refresh(&mut spi, &mut delay, refresh).unwrap();
+                bus.clear();
+                epd.display_frame(&mut spi, &mut delay).unwrap();
+                assert_eq!(bus.data_after(0x22).unwrap(), [control]);
+            }
+        }
+
+        // invalid sequences keep the previous one
+        let analog_without_clock = UpdateSequence::ENABLE_ANALOG | UpdateSequence::DISPLAY;
+        assert_eq!(
+            epd.set_update_sequence(Some(analog_without_clock)),
+            Err(Error::InvalidUpdateSequence)
+        );
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC7]);
+
+        // back to the sequence of the driver
+        epd.set_update_sequence(None).unwrap();
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x22).unwrap(), [0x04]);
+    }
+
+    #[test]
+    fn update_frame_with_progress_reports_every_kilobyte() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        let buffer = [0x5A; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        bus.clear();
+        epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+        let plain = bus.transfers();
+
+        let mut reports = Vec::new();
+        bus.clear();
+        epd.update_frame_with_progress(&mut spi, &buffer, &mut delay, |sent, total| {
+            reports.push((sent, total))
+        })
+        .unwrap();
+
+        // both RAMs are written, the progress follows the first one
+        assert_eq!(
+            reports,
+            [(1024, 4000), (2048, 4000), (3072, 4000), (4000, 4000)]
+        );
+        assert_eq!(bus.transfers(), plain);
+    }
+
+    #[test]
+    fn clear_frame_fills_padded_rows() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        // 122 pixels wide, the last byte of every row holds only 2 pixels
+        assert_eq!(line_bytes(WIDTH), 16);
+        bus.clear();
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x24).unwrap().len(), 16 * 250);
+        assert_eq!(bus.data_after(0x26).unwrap().len(), 16 * 250);
+    }
+
+    #[test]
+    fn read_user_id() {
+        use crate::mock::Transfer;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        let id = [0x57, 0x53, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        bus.reply(&id);
+        let mut out = [0; 10];
+        epd.read_user_id(&mut spi, &mut delay, &mut out).unwrap();
+        assert_eq!(out, id);
+        assert_eq!(
+            bus.transfers(),
+            [
+                Transfer::Command(Command::UserIdRead as u8),
+                Transfer::Read(id.to_vec())
+            ]
+        );
+
+        // the DC pin is switched back for the following command
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands().last(), Some(&(Command::DeepSleepMode as u8)));
+    }
+
+    #[test]
+    fn fast_refresh_configures_again() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        bus.clear();
+        epd.display_frame_fast(&mut spi, &mut delay).unwrap();
+        let temperature = Command::TemperatureSensorControlWrite as u8;
+        let control = Command::DisplayUpdateControl2 as u8;
+        assert_eq!(bus.commands()[0], temperature);
+        assert_eq!(
+            bus.data_after(temperature).unwrap(),
+            ssd16xx::FAST_TEMPERATURE
+        );
+        assert_eq!(bus.data_after_nth(control, 0).unwrap(), [0x91]);
+        assert_eq!(bus.data_after_nth(control, 1).unwrap(), [0xC7]);
+        assert_eq!(bus.data_after_nth(control, 2).unwrap(), [0xA1]);
+        // the LUT and the voltages of the driver replace the OTP waveform
+        assert_eq!(
+            bus.data_after(Command::WriteLutRegister as u8).unwrap(),
+            LUT_FULL_UPDATE
+        );
+        assert_eq!(bus.count(Command::GateDrivingVoltageCtrl as u8), 1);
+
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(temperature), 0);
+        assert_eq!(bus.data_after(control).unwrap(), [0xC7]);
+    }
+
+    #[test]
+    fn ram_windows_share_one_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        bus.clear();
+        for (i, y) in [8, 40, 200].into_iter().enumerate() {
+            epd.write_ram_window(
+                &mut spi,
+                &mut delay,
+                &[i as u8; 4],
+                16,
+                y,
+                16,
+                2,
+                RamBank::New,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        epd.refresh_written(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+
+        let x_window = Command::SetRamXAddressStartEndPosition as u8;
+        assert_eq!(bus.count(x_window), 3);
+        assert_eq!(bus.data_after_nth(x_window, 2).unwrap(), [2, 3]);
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamYAddressStartEndPosition as u8, 2)
+                .unwrap(),
+            [200, 0, 201, 0]
+        );
+        assert_eq!(bus.count(Command::MasterActivation as u8), 1);
+        assert_eq!(bus.count(Command::WriteLutRegister as u8), 0);
+        assert_eq!(
+            epd.refresh_written(&mut spi, &mut delay, RefreshLut::Full),
+            Err(Error::NoPartialSession)
+        );
+
+        // the quick LUT is only used for this refresh
+        bus.clear();
+        epd.write_ram_window(&mut spi, &mut delay, &[0; 4], 0, 0, 16, 2, RamBank::Old)
+            .unwrap();
+        epd.refresh_written(&mut spi, &mut delay, RefreshLut::Quick)
+            .unwrap();
+        assert_eq!(bus.count(Command::WriteRamRed as u8), 1);
+        assert_eq!(bus.count(Command::MasterActivation as u8), 1);
+        assert_eq!(
+            bus.data_after(Command::DisplayUpdateControl2 as u8)
+                .unwrap(),
+            [DisplayUpdateControl2::new().display().0]
+        );
+        let lut = Command::WriteLutRegister as u8;
+        assert_eq!(bus.data_after_nth(lut, 0).unwrap(), LUT_PARTIAL_UPDATE);
+        assert_eq!(bus.data_after_nth(lut, 1).unwrap(), LUT_FULL_UPDATE);
+        assert_eq!(epd.refresh_counters().partial, 2);
+    }
+
+    #[test]
+    fn driving_voltages_applied_by_wake_up() {
+        use crate::voltage::{Rail, VoltagePreset};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let gate = Command::GateDrivingVoltageCtrl as u8;
+        let source = Command::SourceDrivingVoltageCtrl as u8;
+        assert_eq!(bus.data_after(gate).unwrap(), [0x15]);
+        assert_eq!(bus.data_after(source).unwrap(), [0x41, 0xA8, 0x32]);
+
+        epd.set_driving_voltages(Some(VoltagePreset::ColdWeather.into()))
+            .unwrap();
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(gate).unwrap(), [0x19]);
+        assert_eq!(bus.data_after(source).unwrap(), [0x50, 0xA8, 0x3E]);
+
+        // refused without forgetting the voltages set before
+        let mut voltages = DrivingVoltages::default();
+        voltages.ssd16xx.vsl = -19_000;
+        assert_eq!(
+            epd.set_driving_voltages(Some(voltages)),
+            Err(Error::InvalidVoltage(Rail::Vsl))
+        );
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(gate).unwrap(), [0x19]);
+
+        epd.set_driving_voltages(None).unwrap();
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(gate).unwrap(), [0x15]);
+    }
 }
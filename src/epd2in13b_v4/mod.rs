@@ -0,0 +1,715 @@
+//! A driver for the Waveshare 2.13" (B) V4 E-Ink Display (black, white and red) via SPI
+//!
+//! 122x250 pixels with an SSD1680 controller. Unlike the older 2.13" (B/C) modules
+//! (see [crate::epd2in13bc]) the frame is written into two RAMs with SSD16xx commands,
+//! rows take 16 bytes with the last 6 bits unused.
+//!
+//! # Red RAM
+//!
+//! The Waveshare examples set the red RAM to be shown inverted, 0 is red there. This
+//! driver shows it as is, 1 is red, so the chromatic plane of [Display2in13b] is sent
+//! unchanged. Red takes precedence over the black/white RAM.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in13b_v4::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd2in13b::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!let mut display = Display2in13b::default();
+//!display.set_rotation(DisplayRotation::Rotate90);
+//!let _ = display.clear(TriColor::White);
+//!let _ = Line::new(Point::new(0, 60), Point::new(249, 60))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 4))
+//!    .draw(&mut display);
+//!
+//!epd.update_color_frame(&mut spi, &mut delay, display.bw_buffer(), display.chromatic_buffer())?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// a black 16x8 window in the top left corner of the unrotated panel
+//!epd.update_partial_color_frame(&mut spi, &mut delay, Some(&[0x00; 16]), Some(&[0x00; 16]), 0, 0, 16, 8)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(B)_Manual)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in13b_V4.py)
+//! - [SSD1680 Datasheet](https://www.waveshare.com/w/upload/b/b3/SSD1680_Datasheet.pdf)
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::{Operation, RefreshLimits};
+use crate::color::{Plane, PlaneConvention, TriColor};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::{buffer_len, line_bytes};
+
+/// Full size buffer for use with the 2.13" (B) V4 EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) * 2 },
+    TriColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 122;
+/// Height of the display
+pub const HEIGHT: u32 = 250;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in13b_v4",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane, with the red RAM shown as is
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsChromatic;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Both RAMs shown as is, the sources S8 to S167 are used
+const RAM_CONTENT_NORMAL: [u8; 2] = [0x00, 0x80];
+/// Border waveform following the white LUT
+const BORDER_WAVEFORM: u8 = 0x05;
+
+/// Epd2in13b V4 driver
+pub struct Epd2in13b<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: TriColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in13b {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // 250 gates, scanned from G0 upwards
+        let [low, high] = gate(HEIGHT - 1);
+        self.cmd_with_data(spi, Command::DriverOutputControl, &[low, high, 0x00])?;
+
+        // x and y increment
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_WAVEFORM])?;
+
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+
+        self.cmd_with_data(spi, Command::DisplayUpdateControl1, &RAM_CONTENT_NORMAL)?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.command(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(false);
+        self.interface.start_operation(Operation::FullRefresh);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
+    }
+
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
+    }
+
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface.cmd(spi, Command::WriteRamRed)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = TriColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize) * 2;
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        Ok(())
+    }
+
+    /// Writes the black/white plane, the red RAM is cleared
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, frame)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRamRed)?;
+        self.interface
+            .data_x_times(spi, background, line_bytes(WIDTH) * HEIGHT)
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` of the black/white RAM, x needs to be
+    /// a multiple of 8
+    ///
+    /// See [`Epd2in13b::update_partial_color_frame`] for the red RAM.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_color_frame(spi, delay, Some(buffer), None, x, y, width, height)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        let pixel_count = line_bytes(WIDTH) * HEIGHT;
+        let bw = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        let chromatic = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.command(spi, Command::WriteRamBlackWhite)?;
+        self.interface.data_x_times(spi, bw, pixel_count)?;
+
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRamRed)?;
+        self.interface.data_x_times(spi, chromatic, pixel_count)
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// The controller only has the full refresh of the OTP for three colors
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRamBlackWhite, black, 0..black.len())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRamRed, chromatic, 0..chromatic.len())
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Updates the planes of a window, planes passed as `None` are left untouched
+    ///
+    /// Both buffers cover the window of `width` x `height` pixels at (x, y), with rows
+    /// of [line_bytes(width)](crate::line_bytes) bytes. x needs to be a multiple of 8.
+    /// The changes are shown by the next [`display_frame`](WaveshareDisplay::display_frame).
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: Option<&[u8]>,
+        chromatic: Option<&[u8]>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        assert!(x.is_multiple_of(8));
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        if let Some(black) = black {
+            assert!((line_bytes(width) * height) as usize == black.len());
+            self.cmd_with_data(spi, Command::WriteRamBlackWhite, black)?;
+        }
+        if let Some(chromatic) = chromatic {
+            assert!((line_bytes(width) * height) as usize == chromatic.len());
+            self.set_counters(spi, x, y)?;
+            self.cmd_with_data(spi, Command::WriteRamRed, chromatic)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the RAM window to `width` x `height` pixels at `x`, `y` and moves the
+    /// address counters to its start
+    ///
+    /// Horizontally the controller counts bytes, a row of the panel ends in the 16th.
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(x / 8) as u8, ((x + width - 1) / 8) as u8],
+        )?;
+        let [y_start, y_start_high] = gate(y);
+        let [y_end, y_end_high] = gate(y + height - 1);
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[y_start, y_start_high, y_end, y_end_high],
+        )?;
+        self.set_counters(spi, x, y)
+    }
+
+    fn set_counters(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x / 8) as u8])?;
+        self.cmd_with_data(spi, Command::SetRamYAddressCounter, &gate(y))
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+}
+
+/// 9 bit gate address, the low byte first and then bit 8
+fn gate(value: u32) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8 & 0x01]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(line_bytes(WIDTH), 16);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        Epd2in13b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            bus.commands(),
+            [0x12, 0x01, 0x11, 0x44, 0x45, 0x4E, 0x4F, 0x3C, 0x18, 0x21]
+        );
+        assert_eq!(bus.data_after(0x01).unwrap(), [0xF9, 0x00, 0x00]);
+        // 122 pixels end in the 16th byte of a row
+        assert_eq!(bus.data_after(0x44).unwrap(), [0x00, 0x0F]);
+        assert_eq!(bus.data_after(0x45).unwrap(), [0x00, 0x00, 0xF9, 0x00]);
+        // the red RAM is not inverted, unlike in the Waveshare examples
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x00, 0x80]);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn plane_encoding() {
+        use embedded_graphics_core::{prelude::*, Pixel};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        let mut display = Display2in13b::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::Black));
+        display.set_pixel(Pixel(Point::new(9, 0), TriColor::Chromatic));
+
+        bus.clear();
+        epd.update_color_frame(
+            &mut spi,
+            &mut delay,
+            display.bw_buffer(),
+            display.chromatic_buffer(),
+        )
+        .unwrap();
+
+        let bw = bus.data_after(Command::WriteRamBlackWhite as u8).unwrap();
+        assert_eq!(bw.len(), 4000);
+        assert_eq!(bw[..2], [0x7F, 0xFF]);
+        assert!(bw[2..].iter().all(|&b| b == 0xFF));
+        let red = bus.data_after(Command::WriteRamRed as u8).unwrap();
+        assert_eq!(red.len(), 4000);
+        assert_eq!(red[..2], [0x00, 0x40]);
+        assert!(red[2..].iter().all(|&b| b == 0x00));
+    }
+
+    #[test]
+    fn partial_window() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        // x 8..=27 and y 200..=249, rows of 3 bytes
+        let black = [0x0F; 3 * 50];
+        let red = [0xF0; 3 * 50];
+        epd.update_partial_color_frame(
+            &mut spi,
+            &mut delay,
+            Some(&black),
+            Some(&red),
+            8,
+            200,
+            20,
+            50,
+        )
+        .unwrap();
+        assert_eq!(
+            bus.data_after(Command::SetRamXAddressStartEndPosition as u8)
+                .unwrap(),
+            [0x01, 0x03]
+        );
+        assert_eq!(
+            bus.data_after(Command::SetRamYAddressStartEndPosition as u8)
+                .unwrap(),
+            [0xC8, 0x00, 0xF9, 0x00]
+        );
+        // the counters are moved back to the window for the red RAM
+        assert_eq!(bus.count(Command::SetRamXAddressCounter as u8), 2);
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamYAddressCounter as u8, 1)
+                .unwrap(),
+            [0xC8, 0x00]
+        );
+        assert_eq!(
+            bus.data_after(Command::WriteRamBlackWhite as u8).unwrap(),
+            black
+        );
+        assert_eq!(bus.data_after(Command::WriteRamRed as u8).unwrap(), red);
+
+        // only the black/white RAM through the trait
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut delay, &black, 8, 200, 20, 50)
+            .unwrap();
+        assert_eq!(bus.count(Command::WriteRamRed as u8), 0);
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle, PrimitiveStyleBuilder}};
 //!use epd_waveshare::{epd2in13bc::*, prelude::*};
 //!#
@@ -52,22 +52,48 @@
 //!```
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
+use crate::vcom;
 
 /// Width of epd2in13bc in pixels
 pub const WIDTH: u32 = 104;
 /// Height of epd2in13bc in pixels
 pub const HEIGHT: u32 = 212;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in13bc",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default background color (white) of epd2in13bc display
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 
 /// Number of bits for b/w buffer and same for chromatic buffer
-const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+const NUM_DISPLAY_BITS: u32 = line_bytes(WIDTH) * HEIGHT;
 
 const IS_BUSY_LOW: bool = true;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsNotChromatic;
 const VCOM_DATA_INTERVAL: u8 = 0x07;
 const WHITE_BORDER: u8 = 0x70;
 const BLACK_BORDER: u8 = 0x30;
@@ -75,11 +101,11 @@ const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::TriColor;
+use crate::color::{Plane, PlaneConvention, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{line_bytes, tri_buffer_len};
 
 /// Full size buffer for use with the 2.13" b/c EPD
 #[cfg(feature = "graphics")]
@@ -87,7 +113,7 @@ pub type Display2in13bc = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     true,
-    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    { tri_buffer_len(WIDTH as usize, HEIGHT as usize) },
     TriColor,
 >;
 
@@ -106,10 +132,33 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in13bc {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // start the booster
         self.interface
@@ -132,12 +181,25 @@ where
         // set resolution
         self.send_resolution(spi)?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])?;
+        let vcom = self.interface.vcom_or(0x0A);
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
 
         self.wait_until_idle(spi, delay)?;
 
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -155,9 +217,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(NUM_DISPLAY_BITS as usize)?;
+        chromatic.check_len(NUM_DISPLAY_BITS as usize)?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     /// Update only the black/white data of the display.
@@ -166,12 +246,10 @@ where
     fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data(spi, black)?;
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     /// Update only chromatic data of the display.
@@ -182,9 +260,26 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
@@ -201,6 +296,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = tri_buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -208,18 +306,12 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in13bc { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -236,10 +328,16 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, color: TriColor) {
         self.color = color;
     }
@@ -261,13 +359,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(NUM_DISPLAY_BITS as usize)?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        self.interface.data(spi, buffer)?;
+        self.interface.data_frame(spi, frame, 0..frame.len())?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
@@ -286,15 +395,16 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.command(spi, Command::DisplayRefresh)?;
-
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -302,24 +412,25 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.send_resolution(spi)?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
         // Clear the black
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
         // Clear the chromatic
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
         self.wait_until_idle(spi, delay)?;
@@ -331,11 +442,16 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
@@ -349,12 +465,71 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_frame(spi, black, 0..black.len())?;
+        Ok(())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_frame(spi, chromatic, 0..chromatic.len())?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in13bc::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in13bc::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
     }
 
     fn cmd_with_data(
@@ -362,23 +537,27 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::ResolutionSetting)?;
-
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[w as u8, (h >> 8) as u8, h as u8],
+        )
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), Error<SPI::Error>> {
         let border = match color {
             TriColor::Black => BLACK_BORDER,
             TriColor::White => WHITE_BORDER,
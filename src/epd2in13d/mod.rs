@@ -0,0 +1,906 @@
+//! A simple Driver for the Waveshare 2.13" (D) flexible E-Ink Display via SPI
+//!
+//! The flexible substrate keeps ghosting from too many partial refreshes, so the
+//! driver forces a full refresh after a configurable number of quick refreshes,
+//! see [`Epd2in13d::set_partial_refresh_limit`].
+//!
+//! - [Datasheet](https://www.waveshare.com/w/upload/b/b3/2.13inch-e-paper-d-Specification.pdf)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13d.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in13d.py)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::{RefreshLimits, WaitOutcome};
+use crate::color::{Color, Plane};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
+#[cfg(feature = "graphics")]
+use crate::traits::{partial_window, RegionRefresh};
+use crate::vcom;
+use crate::voltage::DrivingVoltages;
+use crate::{buffer_len, line_bytes};
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::primitives::Rectangle;
+
+pub(crate) mod command;
+use self::command::Command;
+
+mod constants;
+use self::constants::*;
+
+/// Full size buffer for use with the 2in13d EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13d = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 104;
+/// Height of the display
+pub const HEIGHT: u32 = 212;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in13d",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(2_000_000, 4_000_000).with_quick(300_000, 1_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+/// Quick refreshes after which a full refresh is forced by default
+pub const DEFAULT_PARTIAL_REFRESH_LIMIT: u8 = 5;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = false;
+const NUM_DISPLAY_BYTES: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
+
+/// Epd2in13d driver
+pub struct Epd2in13d<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Quick refreshes since the last full refresh
+    partial_refreshes: u8,
+    /// Quick refreshes after which a full refresh is forced
+    partial_refresh_limit: Option<u8>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in13d {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            partial_refreshes: 0,
+            partial_refresh_limit: Some(DEFAULT_PARTIAL_REFRESH_LIMIT),
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+
+        // VDS_EN, VDG_EN, VCOM_HV, VGHL_LV, VDH, VDL, VDHR
+        let power_setting = self
+            .interface
+            .uc81xx_power_setting_or([0x03, 0x00, 0x2b, 0x2b, 0x03]);
+        self.interface
+            .cmd_with_data(spi, Command::PowerSetting, &power_setting)?;
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+        self.interface.cmd(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // LUT from register, b/w mode, VCOM to 0V fast
+        self.interface
+            .cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0e])?;
+        // 3a 100HZ | 29 150Hz | 39 200HZ | 31 171HZ
+        self.interface
+            .cmd_with_data(spi, Command::PllControl, &[0x3a])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+        let vcom = self.interface.vcom_or(0x28);
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
+
+        self.set_lut(spi, delay, None)
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        self.start_refresh(spi, delay, self.refresh == RefreshLut::Quick)
+    }
+
+    fn wait_until_idle_cancellable(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        self.interface.wait_until_idle_with_cmd_cancellable(
+            spi,
+            delay,
+            IS_BUSY_LOW,
+            Command::GetStatus,
+            cancel,
+        )
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        // floating border while sleeping
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
+        self.interface.cmd(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.wait_until_idle(spi, delay)?;
+        // the old frame is the inverted background, like after clear_frame
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, !color, NUM_DISPLAY_BYTES)?;
+        self.interface
+            .cmd_with_frame(spi, Command::DataStartTransmission2, frame, 0..frame.len())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.set_partial_window(spi, x, y, width, height)?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.interface.cmd(spi, Command::PartialOut)
+    }
+
+    /// Refreshes with the selected LUT, a quick refresh becomes a full one when the
+    /// partial refresh limit is reached
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.wait_until_idle(spi, delay)?;
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, !color, NUM_DISPLAY_BYTES)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BYTES)
+    }
+
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        QuickRefresh::clear_partial_frame(self, spi, delay, x, y, width, height)
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        match self.refresh {
+            RefreshLut::Full | RefreshLut::Custom(_) => self.set_full_reg(spi),
+            RefreshLut::Quick => self.set_part_reg(spi),
+        }?;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// Quick refresh, unless the partial refresh limit is reached
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.refresh(spi, delay, true)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.set_partial_window(spi, x, y, width, height)?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
+        self.interface.cmd(spi, Command::PartialOut)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        let bytes = line_bytes(width) * height;
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.set_partial_window(spi, x, y, width, height)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, !color, bytes)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, bytes)?;
+        self.interface.cmd(spi, Command::PartialOut)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<SPI, BUSY, DC, RST, DELAY> RegionRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13d<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// The LUT is uploaded before every refresh anyway, so only this refresh uses `lut`
+    /// and nothing has to be restored. A quick refresh still counts towards the
+    /// partial refresh limit.
+    fn refresh_region_with_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        let (x, y, width, height) = partial_window(&area, WIDTH, HEIGHT)?;
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)?;
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        let quick = lut == RefreshLut::Quick;
+        if !quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        self.refresh(spi, delay, quick)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13d<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Replaces the driving voltages of `PowerSetting` sent by `init`/`wake_up`, `None`
+    /// restores the ones of the driver, see [crate::voltage]
+    ///
+    /// Takes effect with the next `wake_up`. Returns [Error::InvalidVoltage] without
+    /// changing anything for voltages the controller doesn't support.
+    pub fn set_driving_voltages(
+        &mut self,
+        voltages: Option<DrivingVoltages>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(voltages) = voltages {
+            voltages.uc81xx.registers().map_err(Error::InvalidVoltage)?;
+        }
+        self.interface.set_driving_voltages(voltages);
+        Ok(())
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in13d::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in13d::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Sets after how many quick refreshes the next one is replaced by a full refresh
+    ///
+    /// Defaults to [`DEFAULT_PARTIAL_REFRESH_LIMIT`]. With `None` the driver never forces
+    /// a full refresh, the application has to schedule them itself to avoid permanent
+    /// ghosting.
+    pub fn set_partial_refresh_limit(&mut self, limit: Option<u8>) {
+        self.partial_refresh_limit = limit;
+    }
+
+    /// Quick refreshes after which a full refresh is forced
+    pub fn partial_refresh_limit(&self) -> Option<u8> {
+        self.partial_refresh_limit
+    }
+
+    /// Quick refreshes since the last full refresh
+    ///
+    /// Applications can use this to do the (flashing) full refresh at a convenient
+    /// moment before the driver forces it.
+    pub fn partial_refreshes(&self) -> u8 {
+        self.partial_refreshes
+    }
+
+    /// Whether the next quick refresh will be a full one
+    pub fn full_refresh_due(&self) -> bool {
+        self.partial_refresh_limit
+            .is_some_and(|limit| self.partial_refreshes >= limit)
+    }
+
+    fn refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        quick: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_refresh(spi, delay, quick)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Starts a refresh without waiting for it
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        quick: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        let quick = quick && !self.full_refresh_due();
+        if quick {
+            self.set_part_reg(spi)?;
+            self.partial_refreshes = self.partial_refreshes.saturating_add(1);
+        } else {
+            self.set_full_reg(spi)?;
+            self.partial_refreshes = 0;
+        }
+        self.interface.cmd(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(quick);
+        // at least 200µs are needed before the busy state is valid
+        delay.delay_us(10_000);
+        Ok(())
+    }
+
+    fn set_full_reg(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let vcom = self.interface.vcom_or(0x28);
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
+        self.set_lut_helper(spi, &LUT_VCOMDC, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
+    }
+
+    fn set_part_reg(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
+        self.set_lut_helper(spi, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1)
+    }
+
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+        self.interface
+            .cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)
+    }
+
+    fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        // x has to start on a byte and end right before the next one
+        let x_start = x & !0x07;
+        let x_end = (x + width - 1) | 0x07;
+        let y_end = y + height - 1;
+        self.interface.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                x_start as u8,
+                x_end as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // scan inside and outside of the window
+                0x28,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+    use crate::policy::RefreshCounters;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 104);
+        assert_eq!(HEIGHT, 212);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn forced_full_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13d::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_partial_refresh_limit(Some(3));
+        let frame = [0xAA; NUM_DISPLAY_BYTES as usize];
+
+        for n in 1..=3 {
+            bus.clear();
+            epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+                .unwrap();
+            assert_eq!(bus.data_after(0x20).unwrap(), LUT_VCOM1);
+            assert_eq!(bus.count(0x12), 1);
+            assert_eq!(epd.partial_refreshes(), n);
+        }
+        assert!(epd.full_refresh_due());
+
+        // the 4th one flashes with the full LUTs
+        bus.clear();
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert_eq!(bus.data_after(0x20).unwrap(), LUT_VCOMDC);
+        assert_eq!(bus.data_after(0x24).unwrap(), LUT_BB);
+        assert_eq!(bus.count(0x12), 1);
+        assert_eq!(epd.partial_refreshes(), 0);
+
+        bus.clear();
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x20).unwrap(), LUT_VCOM1);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        // without a limit quick refreshes stay quick
+        epd.set_partial_refresh_limit(None);
+        for _ in 0..10 {
+            epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        }
+        bus.clear();
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x20).unwrap(), LUT_VCOM1);
+        assert_eq!(epd.partial_refreshes(), 12);
+    }
+
+    #[test]
+    fn display_frame_follows_lut() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13d::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        // full refreshes don't count
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(epd.partial_refreshes(), 0);
+
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x20).unwrap(), LUT_VCOM1);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        // sleep uses the floating border setting
+        bus.clear();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x50).unwrap(), [0xf7]);
+        assert_eq!(bus.data_after(0x07).unwrap(), [0xA5]);
+    }
+
+    #[test]
+    fn refresh_counters_follow_partial_limit() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13d::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_partial_refresh_limit(Some(1));
+
+        // the second quick refresh is a full one because of the limit
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        epd.display_new_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            epd.refresh_counters(),
+            RefreshCounters {
+                full: 1,
+                quick: 1,
+                partial: 0
+            }
+        );
+    }
+
+    #[test]
+    fn region_refresh_uploads_one_lut_per_refresh() {
+        use embedded_graphics_core::prelude::{Point, Size};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13d::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let area = Rectangle::new(Point::new(8, 10), Size::new(16, 4));
+        for (lut, table) in [
+            (RefreshLut::Quick, LUT_VCOM1),
+            (RefreshLut::Full, LUT_VCOMDC),
+            (RefreshLut::Quick, LUT_VCOM1),
+        ] {
+            bus.clear();
+            epd.refresh_region_with_lut(&mut spi, &mut delay, &[0; 8], area, lut)
+                .unwrap();
+            let commands = bus.commands();
+            let position =
+                |command: Command| commands.iter().position(|c| *c == command as u8).unwrap();
+            assert!(position(Command::PartialOut) < position(Command::LutForVcom));
+            assert!(position(Command::LutForVcom) < position(Command::DisplayRefresh));
+            assert_eq!(bus.count(Command::LutForVcom as u8), 1);
+            assert_eq!(bus.data_after(Command::LutForVcom as u8).unwrap(), table);
+        }
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Full));
+        assert_eq!(epd.partial_refreshes(), 1);
+    }
+
+    #[test]
+    fn driving_voltages_applied_by_wake_up() {
+        use crate::voltage::Rail;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13d::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let power_setting = Command::PowerSetting as u8;
+        assert_eq!(
+            bus.data_after(power_setting).unwrap(),
+            [0x03, 0x00, 0x2b, 0x2b, 0x03]
+        );
+
+        let mut voltages = DrivingVoltages::default();
+        voltages.uc81xx.vgh = 14_000;
+        voltages.uc81xx.vdh = 10_000;
+        voltages.uc81xx.vdl = -10_000;
+        epd.set_driving_voltages(Some(voltages)).unwrap();
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.data_after(power_setting).unwrap(),
+            [0x03, 0x02, 0x26, 0x26, 0x03]
+        );
+
+        voltages.uc81xx.vdhr = 11_200;
+        assert_eq!(
+            epd.set_driving_voltages(Some(voltages)),
+            Err(Error::InvalidVoltage(Rail::Vdhr))
+        );
+    }
+}
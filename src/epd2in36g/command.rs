@@ -0,0 +1,118 @@
+//! SPI Commands for the Waveshare 2.36" (G) E-Ink Display
+
+use crate::traits::{self, DataLen};
+
+/// Epd2in36g commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// The vendor specific commands are undocumented, their values are taken from the
+/// Waveshare driver.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction,
+    /// booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Turns off charge pump, T-con, source driver, gate driver, VCOM and temperature
+    /// sensor. Register data is kept.
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    ///
+    /// BUSY becomes "1" once the power on sequence is complete.
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The only parameter is a check code, the command is executed if it is 0xA5.
+    DeepSleep = 0x07,
+
+    /// Starts the transmission of the 2 bit per pixel frame into the SRAM
+    DataStartTransmission = 0x10,
+
+    /// Refreshes the display according to SRAM data and LUT.
+    ///
+    /// The refresh of the four color panel takes considerably longer than of b/w panels.
+    DisplayRefresh = 0x12,
+
+    /// Selects the temperature sensor
+    TemperatureSensorSelection = 0x41,
+
+    /// Sets border output and data polarity
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// Sets the non-overlap periods of the gate and source outputs
+    TconSetting = 0x60,
+
+    /// Sets the display resolution
+    ResolutionSetting = 0x61,
+
+    /// Vendor specific, precedes the configuration and is sent twice during init
+    Header = 0x66,
+
+    /// Vendor specific, 0x01 before and 0x00 after a frame transmission
+    FrameWrite = 0x68,
+
+    /// Vendor specific
+    PowerSaving = 0x84,
+
+    /// Vendor specific
+    ExtendedSettingB0 = 0xB0,
+
+    /// Vendor specific
+    ExtendedSettingF0 = 0xF0,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOn => DataLen::Exact(0),
+            Command::PowerOff
+            | Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::DisplayRefresh
+            | Command::TemperatureSensorSelection
+            | Command::VcomAndDataIntervalSetting
+            | Command::FrameWrite
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::PanelSetting | Command::TconSetting => DataLen::Exact(2),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::Header => DataLen::Max(4),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::FrameWrite.address(), 0x68);
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::ResolutionSetting.expected_data_len().accepts(3));
+        assert!(!Command::ResolutionSetting.expected_data_len().accepts(4));
+        assert!(Command::Header.expected_data_len().accepts(2));
+        assert!(!Command::Header.expected_data_len().accepts(5));
+    }
+}
@@ -0,0 +1,463 @@
+//! A simple Driver for the Waveshare 2.36" (G) 4 color E-Ink Display via SPI
+//!
+//! Each pixel is encoded in 2 bits of a single buffer, see [`QuadColor`].
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/2.36inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in36g.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in36g.py)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::RefreshLimits;
+use crate::color::QuadColor;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::buffer_len;
+
+/// Full size buffer for use with the 2in36g EPD
+#[cfg(feature = "graphics")]
+pub type Display2in36g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize * 2, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 168;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in36g",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 4,
+    partial: false,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(16_000_000, 24_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Epd2in36g driver
+///
+pub struct Epd2in36g<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in36g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in36g {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.cmd_with_data(spi, Command::Header, &[0x49, 0x55, 0x13, 0x5D])?;
+        self.cmd_with_data(spi, Command::Header, &[0x49, 0x55])?;
+        self.cmd_with_data(spi, Command::ExtendedSettingB0, &[0x03])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x4F, 0x6B])?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::ExtendedSettingF0,
+            &[0xF6, 0x0D, 0x00, 0x00, 0x00],
+        )?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xCF, 0xDE, 0x0F])?;
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x00])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x30])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x0C, 0x05])?;
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x01])?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        // the refresh takes a lot longer than on b/w displays, the booster is
+        // turned off afterwards like in the vendor driver
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x01])?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
+
+    fn finish_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in36g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = QuadColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize * 2, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize * 2, HEIGHT as usize))?;
+        self.start_frame(spi, delay)?;
+        self.interface.data_frame(spi, frame, 0..frame.len())?;
+        self.end_frame(spi)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        unimplemented!();
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+        self.finish_display_frame(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        let bg = self.color.get_byte_value();
+        self.start_frame(spi, delay)?;
+        self.interface.data_x_times(spi, bg, WIDTH / 4 * HEIGHT)?;
+        self.end_frame(spi)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        unimplemented!();
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in36g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    /// Powers on and starts the transmission of a full frame
+    fn start_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::FrameWrite, &[0x01])?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)
+    }
+
+    fn end_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // keep the checksum of the frame intact
+        #[cfg(feature = "checksum")]
+        let checksum = self.interface.checksum();
+        self.cmd_with_data(spi, Command::FrameWrite, &[0x00])?;
+        #[cfg(feature = "checksum")]
+        self.interface.set_checksum(checksum);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::busy::WaitOutcome;
+    #[cfg(feature = "checksum")]
+    use crate::checksum::crc16;
+    use crate::mock::{Bus, Delay, Pin};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 168);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[test]
+    fn frame_upload() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in36g::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        let frame = [0x1B; (WIDTH / 4 * HEIGHT) as usize];
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert_eq!(
+            bus.commands(),
+            [
+                Command::FrameWrite as u8,
+                Command::PowerOn as u8,
+                Command::DataStartTransmission as u8,
+                Command::FrameWrite as u8,
+                Command::DisplayRefresh as u8,
+                Command::PowerOff as u8,
+            ]
+        );
+        assert!(bus.waited_after(Command::PowerOn as u8));
+        assert_eq!(
+            bus.data_after(Command::DataStartTransmission as u8)
+                .unwrap(),
+            frame
+        );
+        assert_eq!(
+            bus.data_after(Command::DisplayRefresh as u8).unwrap(),
+            [0x01]
+        );
+
+        bus.clear();
+        epd.set_background_color(QuadColor::Yellow);
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.data_after(Command::DataStartTransmission as u8)
+                .unwrap(),
+            [0xAA; (WIDTH / 4 * HEIGHT) as usize]
+        );
+        // the trailing command doesn't spoil the checksum of the frame
+        #[cfg(feature = "checksum")]
+        assert_eq!(
+            epd.last_transfer_checksum(),
+            crc16(&[0xAA; (WIDTH / 4 * HEIGHT) as usize])
+        );
+    }
+
+    #[test]
+    fn cancelled_refresh_skips_the_power_off() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let busy = bus.busy(true);
+        let mut epd = Epd2in36g::new(
+            &mut spi,
+            busy.clone(),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        busy.push(&[false; 4]);
+        let polls = core::cell::Cell::new(0);
+        let cancel = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 2
+        };
+        assert_eq!(
+            epd.display_frame_cancellable(&mut spi, &mut delay, &cancel),
+            Ok(WaitOutcome::Cancelled)
+        );
+        assert_eq!(bus.commands(), [Command::DisplayRefresh as u8]);
+        assert!(epd.is_busy());
+
+        epd.wait_until_idle(&mut spi, &mut delay).unwrap();
+        assert!(!epd.is_busy());
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_packing() {
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in36g::default();
+        assert_eq!(display.buffer().len(), (WIDTH / 4 * HEIGHT) as usize);
+        display.clear(QuadColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), QuadColor::Red));
+        display.set_pixel(Pixel(Point::new(2, 0), QuadColor::Yellow));
+        display.set_pixel(Pixel(Point::new(7, 1), QuadColor::Black));
+        let line = (WIDTH / 4) as usize;
+        assert_eq!(display.buffer()[0], 0b11_01_10_01);
+        assert_eq!(display.buffer()[1], 0x55);
+        assert_eq!(display.buffer()[line + 1], 0b01_01_01_00);
+    }
+}
@@ -1,10 +1,11 @@
 #![allow(dead_code)]
 //! SPI Commands for the SSD1675B driver chip
 
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     DriverOutputControl = 0x01,
     GateDrivingVoltageControl = 0x02,
     SourceDrivingVoltageControl = 0x04,
@@ -65,6 +66,61 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::Reset
+            | Command::MasterActivation
+            | Command::SenseVCOM
+            | Command::ProgramOTPVCOM
+            | Command::ProgramOTPWaveformSetting
+            | Command::LoadOTPWaveformSetting
+            | Command::CalculateCRC
+            | Command::ProgramOTPSelection
+            | Command::Nop => DataLen::Exact(0),
+            Command::GateDrivingVoltageControl
+            | Command::DeepSleepMode
+            | Command::DataEntryMode
+            | Command::HVReadyDetection
+            | Command::VCIDetection
+            | Command::TemperatureSensorSelection
+            | Command::DisplayUpdateControl2
+            | Command::VCOMSenseDuration
+            | Command::WriteVCOMRegister
+            | Command::SetDummyLinePeriod
+            | Command::SetGateLineWidth
+            | Command::BorderWaveformControl
+            | Command::RAMReadOption
+            | Command::SetXAddressCounter
+            | Command::RedRAMTestPattern
+            | Command::BlackWhiteRAMTestPattern
+            | Command::SetAnalogBlockControl
+            | Command::SetDigitalBlockControl => DataLen::Exact(1),
+            Command::GateScanStartPosition
+            | Command::SetXAddressRange
+            | Command::SetYAddressCounter => DataLen::Exact(2),
+            Command::DriverOutputControl | Command::SourceDrivingVoltageControl => {
+                DataLen::Exact(3)
+            }
+            Command::SetYAddressRange => DataLen::Exact(4),
+            Command::WriteRegisterForDisplayOption | Command::WriteRegisterForUserID => {
+                DataLen::Exact(10)
+            }
+            Command::WriteTemperatureRegister | Command::DisplayUpdateControl1 => DataLen::Max(2),
+            Command::ExternalTemperatureSensorWrite => DataLen::Max(3),
+            Command::BoosterSoftstartControl => DataLen::Max(4),
+            Command::WriteLUTRegister => DataLen::Max(153),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::WriteBlackWhiteRAM => Some(Plane::BlackWhite),
+            Command::WriteRedRAM => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
 }
 
 pub(crate) enum DataEntrySign {
@@ -115,3 +171,17 @@ pub(crate) enum StartWith {
     Zero = 0x00,
     One = 0x80,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::WriteLUTRegister.expected_data_len().accepts(153));
+        assert!(!Command::WriteLUTRegister.expected_data_len().accepts(159));
+        assert!(Command::SetYAddressRange.expected_data_len().accepts(4));
+        assert!(!Command::SetYAddressRange.expected_data_len().accepts(2));
+    }
+}
@@ -172,10 +172,15 @@ use embedded_hal::{
     spi::SpiDevice,
 };
 
-use crate::color::TriColor;
+use crate::busy::RefreshLimits;
+use crate::color::{Plane, PlaneConvention, TriColor};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 pub(crate) mod command;
@@ -186,11 +191,30 @@ use crate::buffer_len;
 pub const WIDTH: u32 = 152;
 /// Display width in pixels
 pub const HEIGHT: u32 = 296;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in66b",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
 
+const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Bit sense of the red RAM
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsChromatic;
 
 /// White, display this during long-term storage
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 
 /// A Display buffer configured with our extent and color depth.
 #[cfg(feature = "graphics")]
@@ -206,6 +230,8 @@ pub type Display2in66b = crate::graphics::Display<
 pub struct Epd2in66b<SPI, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     background: TriColor,
+    /// Black/white RAM shown inverted
+    inverted: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -217,22 +243,53 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::Reset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in66b {
+            interface,
+            background: DEFAULT_BACKGROUND_COLOR,
+            inverted: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // We follow the sequence of the Pi-Pico hat example code.
         self.hw_reset(delay)?;
         self.sw_reset(spi, delay)?;
         self.data_entry_mode(spi, DataEntryRow::XMinor, DataEntrySign::IncYIncX)?;
         self.set_display_window(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-        self.update_control1(
-            spi,
-            WriteMode::Normal,
-            WriteMode::Normal,
-            OutputSource::S8ToS167,
-        )?;
+        self.set_inverted(spi, self.inverted)?;
         self.set_cursor(spi, 0, 0)?;
 
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -250,31 +307,67 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.set_cursor(spi, 0, 0)?;
-        self.interface.cmd(spi, Command::WriteBlackWhiteRAM)?;
-        self.interface.data(spi, black)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
         self.set_cursor(spi, 0, 0)?;
         self.interface.cmd(spi, Command::WriteRedRAM)?;
-        self.interface.data(spi, chromatic)
+        let background = self
+            .background
+            .fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
+        Ok(())
     }
 }
 
@@ -288,6 +381,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize) * 2;
 
     fn new(
         spi: &mut SPI,
@@ -296,19 +392,15 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized,
     {
-        let mut epd = Self {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
-            background: DEFAULT_BACKGROUND_COLOR,
-        };
-        epd.init(spi, delay)?;
-        Ok(epd)
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.interface.cmd_with_data(
             spi,
             Command::DeepSleepMode,
@@ -316,10 +408,16 @@ where
         )
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::Reset, Command::DeepSleepMode)
+    }
+
     fn set_background_color(&mut self, color: Self::DisplayColor) {
         self.background = color;
     }
@@ -341,10 +439,28 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.set_cursor(spi, 0, 0)?;
-        self.update_achromatic_frame(spi, delay, buffer)?;
-        self.red_pattern(spi, delay, PatW::W160, PatH::H296, StartWith::Zero) // do NOT consider background here since red overrides other colors
+        self.write_achromatic(spi, delay, frame)?;
+        // the pattern filling the chromatic plane keeps the checksum of the frame intact
+        #[cfg(feature = "checksum")]
+        let checksum = self.interface.checksum();
+        let (_, red) = self.background_phases();
+        self.red_pattern(spi, delay, PatW::W160, PatH::H296, red)?;
+        #[cfg(feature = "checksum")]
+        self.interface.set_checksum(checksum);
+        Ok(())
     }
 
     fn update_partial_frame(
@@ -356,15 +472,18 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         self.set_display_window(spi, x, y, x + width, y + height)?;
         self.set_cursor(spi, x, y)?;
         self.update_achromatic_frame(spi, delay, buffer)?;
         self.set_display_window(spi, 0, 0, WIDTH, HEIGHT)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::MasterActivation)?;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
         self.wait_until_idle(delay)
     }
 
@@ -373,17 +492,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        let (white, red) = match self.background {
-            TriColor::Black => (StartWith::Zero, StartWith::Zero),
-            TriColor::White => (StartWith::One, StartWith::Zero),
-            TriColor::Chromatic => (StartWith::Zero, StartWith::One),
-        };
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        let (white, red) = self.background_phases();
         self.black_white_pattern(spi, delay, PatW::W160, PatH::H296, white)?;
         self.red_pattern(spi, delay, PatW::W160, PatH::H296, red)
     }
@@ -393,11 +509,16 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(delay)
     }
 }
@@ -411,16 +532,80 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn wait_until_idle(&mut self, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, false);
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.set_cursor(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteBlackWhiteRAM)?;
+        self.interface.data_frame(spi, black, 0..black.len())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.set_cursor(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteRedRAM)?;
+        self.interface
+            .data_frame(spi, chromatic, 0..chromatic.len())
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(delay)?;
+        self.interface.cmd(spi, Command::ReadOTPUserId)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Shows the black/white RAM inverted, starting with the next refresh
+    ///
+    /// This only changes the RAM content option of the controller, so it is free:
+    /// the frame doesn't need to be transmitted again. The setting is kept over
+    /// [`wake_up`](WaveshareDisplay::wake_up). For a software inversion, which needs a
+    /// retransmit, see `Display::invert`.
+    pub fn set_inverted(&mut self, spi: &mut SPI, invert: bool) -> Result<(), Error<SPI::Error>> {
+        self.inverted = invert;
+        let bw_mode = if invert {
+            WriteMode::Invert
+        } else {
+            WriteMode::Normal
+        };
+        self.update_control1(spi, WriteMode::Normal, bw_mode, OutputSource::S8ToS167)
+    }
+
+    /// Whether the black/white RAM is shown inverted
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    fn wait_until_idle(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
-    fn hw_reset(&mut self, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn hw_reset(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // The initial delay is taken from other code here, the 2 ms comes from the SSD1675B datasheet.
-        self.interface.reset(delay, 20_000, 2_000);
+        self.interface.reset(delay, RESET_CONFIG);
         self.wait_until_idle(delay)
     }
-    fn sw_reset(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sw_reset(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, Command::Reset)?;
         self.wait_until_idle(delay)
     }
@@ -429,7 +614,7 @@ where
         spi: &mut SPI,
         row: DataEntryRow,
         sign: DataEntrySign,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
             .cmd_with_data(spi, Command::DataEntryMode, &[row as u8 | sign as u8])
     }
@@ -440,7 +625,7 @@ where
         ystart: u32,
         xend: u32,
         yend: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::SetXAddressRange,
@@ -463,7 +648,7 @@ where
         red_mode: WriteMode,
         bw_mode: WriteMode,
         source: OutputSource,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::DisplayUpdateControl1,
@@ -471,7 +656,7 @@ where
         )
     }
 
-    fn set_cursor(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), SPI::Error> {
+    fn set_cursor(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::SetXAddressCounter,
@@ -491,7 +676,7 @@ where
         w: PatW,
         h: PatH,
         phase: StartWith,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::BlackWhiteRAMTestPattern,
@@ -499,6 +684,15 @@ where
         )?;
         self.wait_until_idle(delay)
     }
+    /// Test pattern phases of the black/white and the red RAM filling both with the background
+    fn background_phases(&self) -> (StartWith, StartWith) {
+        match self.background {
+            TriColor::Black => (StartWith::Zero, StartWith::Zero),
+            TriColor::White => (StartWith::One, StartWith::Zero),
+            TriColor::Chromatic => (StartWith::Zero, StartWith::One),
+        }
+    }
+
     fn red_pattern(
         &mut self,
         spi: &mut SPI,
@@ -506,7 +700,7 @@ where
         w: PatW,
         h: PatH,
         phase: StartWith,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(
             spi,
             Command::RedRAMTestPattern,
@@ -515,3 +709,70 @@ where
         self.wait_until_idle(delay)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+
+    #[test]
+    fn set_inverted() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in66b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x00, 0x80]);
+
+        // only the black/white RAM is inverted
+        bus.clear();
+        epd.set_inverted(&mut spi, true).unwrap();
+        assert_eq!(bus.commands(), [0x21]);
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x08, 0x80]);
+
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x08, 0x80]);
+    }
+
+    #[test]
+    fn background_patterns() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in66b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let buffer = [0xFF; (WIDTH / 8 * HEIGHT) as usize];
+
+        // the whole RAMs in the phase of the background, 0x80 fills with ones
+        for (color, black_white, red) in [
+            (TriColor::White, 0xE5, 0x65),
+            (TriColor::Black, 0x65, 0x65),
+            (TriColor::Chromatic, 0x65, 0xE5),
+        ] {
+            epd.set_background_color(color);
+            bus.clear();
+            epd.clear_frame(&mut spi, &mut delay).unwrap();
+            assert_eq!(bus.data_after(0x47).unwrap(), [black_white]);
+            assert_eq!(bus.data_after(0x46).unwrap(), [red]);
+
+            bus.clear();
+            epd.update_frame(&mut spi, &buffer, &mut delay).unwrap();
+            assert_eq!(bus.data_after(0x46).unwrap(), [red]);
+        }
+    }
+}
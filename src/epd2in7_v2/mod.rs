@@ -0,0 +1,805 @@
+//! A driver for the Waveshare 2.7" V2 E-Ink Display via SPI
+//!
+//! 176x264 pixels with an SSD1680 controller. The V2 module replaced the IL91874 based
+//! 2.7" one, whose commands it ignores. The frame is written with SSD16xx commands,
+//! rows take 22 bytes.
+//!
+//! # Refresh
+//!
+//! A full refresh takes about 3 s. With [RefreshLut::Quick] selected `display_frame`
+//! refreshes quickly from the difference to the old frame RAM. The quick refresh copies
+//! the new frame into it, with the full LUT `update_frame` and `update_partial_frame`
+//! write both RAMs, so the first quick refresh after a full one starts from the shown
+//! frame.
+//!
+//! # 4 gray levels
+//!
+//! [`Epd2in7::update_frame_gray`] uploads the 4-gray LUT of the Waveshare examples and
+//! sends a frame with 2 bits per pixel, shown by the next `display_frame`. The levels
+//! are split into both RAMs, which the LUT drives together. Quick refreshes don't work
+//! with it, the next black/white update, `clear_frame` or `set_lut` initialises the
+//! controller again.
+//!
+//! # Example for the 2.7" V2 E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in7_v2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in7::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in7_v2::default();
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 263))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Four stripes of the gray levels, 2 bits per pixel from black (0) to white (3)
+//!let mut gray = [0u8; GRAY_BUFFER_LEN];
+//!for (i, byte) in gray.iter_mut().enumerate() {
+//!    *byte = [0x00, 0x55, 0xAA, 0xFF][i * 4 / GRAY_BUFFER_LEN];
+//!}
+//!epd.update_frame_gray(&mut spi, &mut delay, &gray)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT_Manual)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in7_V2.c)
+//! - [SSD1680 Datasheet](https://www.waveshare.com/w/upload/b/b3/SSD1680_Datasheet.pdf)
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::{Operation, RefreshLimits};
+use crate::color::{Color, Plane, PlaneConvention};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::{buffer_len, line_bytes};
+
+/// Full size buffer for use with the 2.7" V2 EPD
+#[cfg(feature = "graphics")]
+#[allow(non_camel_case_types)]
+pub type Display2in7_v2 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 176;
+/// Height of the display
+pub const HEIGHT: u32 = 264;
+/// Length of a frame for [`Epd2in7::update_frame_gray`], 4 pixels per byte
+pub const GRAY_BUFFER_LEN: usize = (WIDTH * HEIGHT / 4) as usize;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in7_v2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(3_000_000, 5_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Border waveform following the white LUT
+const BORDER_WAVEFORM_FULL: u8 = 0x05;
+/// Border held at VCOM during quick refreshes, from the Waveshare examples
+const BORDER_WAVEFORM_QUICK: u8 = 0x80;
+/// Border waveform of the 4-gray LUT
+const BORDER_WAVEFORM_GRAY: u8 = 0x00;
+
+/// Clock, analog, temperature, OTP LUT and display mode 1
+const SEQUENCE_FULL: u8 = 0xF7;
+/// Clock, analog, temperature, OTP LUT and display mode 2
+const SEQUENCE_QUICK: u8 = 0xFF;
+/// Clock, analog and display mode 1 with the uploaded LUT
+const SEQUENCE_GRAY: u8 = 0xC7;
+
+/// Waveform of the 4 gray levels from the Waveshare examples: 153 bytes for
+/// `WriteLutRegister`, then the LUT end option, the gate voltage, the three source
+/// voltages and VCOM
+pub const LUT_4_GRAY: [u8; 159] = [
+    0x40, 0x48, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L0
+    0x08, 0x48, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L1
+    0x02, 0x48, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L2
+    0x20, 0x48, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L3
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L4
+    0x0A, 0x19, 0x00, 0x03, 0x08, 0x00, 0x00, // TP, SR, RP of group 0
+    0x14, 0x01, 0x00, 0x14, 0x01, 0x00, 0x03, // group 1
+    0x0A, 0x03, 0x00, 0x08, 0x19, 0x00, 0x00, // group 2
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // group 3
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 4
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 5
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 6
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 7
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 8
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 9
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 10
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group 11
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x00, 0x00, 0x00, // FR, XON
+    0x22, // EOPT
+    0x17, // VGH
+    0x41, 0x00, 0x32, // VSH1, VSH2, VSL
+    0x1C, // VCOM
+];
+
+/// Epd2in7 V2 driver
+pub struct Epd2in7<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Whether the 4-gray LUT replaced the one of the OTP
+    gray: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in7 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gray: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.gray = false;
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::SwReset)?;
+        self.interface.start_operation(Operation::Init);
+        self.wait_until_idle(spi, delay)?;
+
+        // 264 gates, scanned from G0 upwards
+        let [low, high] = gate(HEIGHT - 1);
+        self.cmd_with_data(spi, Command::DriverOutputControl, &[low, high, 0x00])?;
+
+        // x and y increment
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[self.border()])?;
+
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        let quick = self.is_quick();
+        if !quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        let sequence = if self.gray {
+            SEQUENCE_GRAY
+        } else if quick {
+            SEQUENCE_QUICK
+        } else {
+            SEQUENCE_FULL
+        };
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.command(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(quick);
+        self.interface.start_operation(if quick {
+            Operation::PartialRefresh
+        } else {
+            Operation::FullRefresh
+        });
+        Ok(())
+    }
+
+    fn is_quick_refresh(&self) -> bool {
+        self.is_quick()
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in7<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.gray = false;
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.start_operation(Operation::Sleep);
+        Ok(())
+    }
+
+    /// Writes the frame into the new frame RAM, with the full LUT into the old frame
+    /// RAM as well
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_window(spi, delay, frame, 0, 0, WIDTH, HEIGHT)
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` like `update_frame`, x needs to be a
+    /// multiple of 8
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        assert!(x.is_multiple_of(8));
+        assert!((line_bytes(width) * height) as usize == buffer.len());
+        self.write_window(spi, delay, &mut FrameData::new(buffer), x, y, width, height)
+    }
+
+    /// Full refresh, or a quick one with [RefreshLut::Quick], and one with the 4-gray
+    /// LUT after [`Epd2in7::update_frame_gray`]
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.leave_gray(spi, delay)?;
+        let pixel_count = line_bytes(WIDTH) * HEIGHT;
+        let bw = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.command(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, bw, pixel_count)?;
+
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, bw, pixel_count)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Both refreshes use the waveforms of the OTP, only the border differs
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        if self.gray {
+            return self.init(spi, delay);
+        }
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[self.border()])
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in7<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Uploads the 4-gray LUT and writes a frame with 4 gray levels, shown by the next
+    /// [display_frame](WaveshareDisplay::display_frame)
+    ///
+    /// `buffer` holds [GRAY_BUFFER_LEN] bytes with 2 bits per pixel, the leftmost pixel
+    /// in the highest bits and rows of 44 bytes. 0 is black and 3 is white, the levels
+    /// in between are dark and light gray. The black/white functions initialise the
+    /// controller again, see [4 gray levels](self#4-gray-levels).
+    pub fn update_frame_gray(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        assert_eq!(buffer.len(), GRAY_BUFFER_LEN);
+        self.wait_until_idle(spi, delay)?;
+        if !self.gray {
+            self.load_gray_waveform(spi)?;
+        }
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.write_gray_plane(spi, Command::WriteRam, buffer, 0)?;
+        self.set_counters(spi, 0, 0)?;
+        self.write_gray_plane(spi, Command::WriteRam2, buffer, 1)
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Whether the next refresh is a quick one
+    fn is_quick(&self) -> bool {
+        !self.gray && self.refresh == RefreshLut::Quick
+    }
+
+    /// Border waveform of the selected LUT
+    fn border(&self) -> u8 {
+        match self.refresh {
+            RefreshLut::Quick => BORDER_WAVEFORM_QUICK,
+            _ => BORDER_WAVEFORM_FULL,
+        }
+    }
+
+    /// Initialises the controller again if the 4-gray LUT is loaded
+    fn leave_gray(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        if self.gray {
+            self.init(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Uploads the 4-gray LUT with its voltages, from `EPD_2IN7_V2_Init_4GRAY`
+    fn load_gray_waveform(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::AnalogBlockControl, &[0x54])?;
+        self.cmd_with_data(spi, Command::DigitalBlockControl, &[0x3B])?;
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_WAVEFORM_GRAY])?;
+        self.cmd_with_data(spi, Command::WriteVcomRegister, &LUT_4_GRAY[158..])?;
+        self.cmd_with_data(spi, Command::WriteLutRegisterEnd, &LUT_4_GRAY[153..154])?;
+        self.cmd_with_data(
+            spi,
+            Command::GateDrivingVoltageControl,
+            &LUT_4_GRAY[154..155],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SourceDrivingVoltageControl,
+            &LUT_4_GRAY[155..158],
+        )?;
+        self.cmd_with_data(spi, Command::WriteLutRegister, &LUT_4_GRAY[..153])?;
+        self.gray = true;
+        Ok(())
+    }
+
+    /// Sends one bit of the gray levels in `buffer` as plane after `command`, a row at
+    /// a time
+    fn write_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        bit: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut row = [0u8; line_bytes(WIDTH) as usize];
+        self.command(spi, command)?;
+        for gray_row in buffer.chunks_exact(2 * row.len()) {
+            for (byte, gray) in row.iter_mut().zip(gray_row.chunks_exact(2)) {
+                *byte = gray_plane_byte([gray[0], gray[1]], bit);
+            }
+            self.interface.data_combined(spi, &row)?;
+        }
+        self.interface.flush(spi)
+    }
+
+    /// Writes `buffer` into the window of the new frame RAM, with the full LUT into the
+    /// old frame RAM as well
+    #[allow(clippy::too_many_arguments)]
+    fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        frame: &mut FrameData<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.leave_gray(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRam, frame, 0..frame.len())?;
+        if self.refresh != RefreshLut::Quick {
+            self.set_counters(spi, x, y)?;
+            self.interface
+                .cmd_with_frame(spi, Command::WriteRam2, frame, 0..frame.len())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the RAM window to `width` x `height` pixels at `x`, `y` and moves the
+    /// address counters to its start
+    ///
+    /// Horizontally the controller counts bytes, a row of the panel ends in the 22nd.
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(x / 8) as u8, ((x + width - 1) / 8) as u8],
+        )?;
+        let [y_start, y_start_high] = gate(y);
+        let [y_end, y_end_high] = gate(y + height - 1);
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[y_start, y_start_high, y_end, y_end_high],
+        )?;
+        self.set_counters(spi, x, y)
+    }
+
+    fn set_counters(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x / 8) as u8])?;
+        self.cmd_with_data(spi, Command::SetRamYAddressCounter, &gate(y))
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+}
+
+/// 9 bit gate address, the low byte first and then bit 8
+fn gate(value: u32) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8 & 0x01]
+}
+
+/// One byte of a RAM plane from 8 pixels with 2 bits each, as in `EPD_2IN7_V2_4GrayDisplay`
+///
+/// The plane bit is set where `bit` of the level is clear, so white (3) clears both
+/// planes and black (0) sets both.
+fn gray_plane_byte(gray: [u8; 2], bit: u8) -> u8 {
+    (0..8).fold(0, |byte, pixel| {
+        let level = gray[pixel / 4] >> (6 - 2 * (pixel % 4));
+        byte | (u8::from(level >> bit & 1 == 0) << (7 - pixel))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay};
+    use crate::mock::{BusyPin, DcPin, Pin, Spi};
+
+    type Epd = Epd2in7<Spi, BusyPin, DcPin, Pin, Delay>;
+
+    fn new_epd(bus: &Bus, spi: &mut Spi, delay: &mut Delay) -> Epd {
+        Epd2in7::new(spi, bus.busy(false), bus.dc(), Pin::new(), delay, None).unwrap()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 176);
+        assert_eq!(HEIGHT, 264);
+        assert_eq!(line_bytes(WIDTH), 22);
+        assert_eq!(
+            GRAY_BUFFER_LEN,
+            2 * buffer_len(WIDTH as usize, HEIGHT as usize)
+        );
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        new_epd(&bus, &mut spi, &mut delay);
+        assert_eq!(
+            bus.commands(),
+            [0x12, 0x01, 0x11, 0x44, 0x45, 0x4E, 0x4F, 0x3C, 0x18]
+        );
+        // 264 gates, the 9th bit in the second byte
+        assert_eq!(bus.data_after(0x01).unwrap(), [0x07, 0x01, 0x00]);
+        // 176 pixels fill 22 bytes of a row
+        assert_eq!(bus.data_after(0x44).unwrap(), [0x00, 0x15]);
+        assert_eq!(bus.data_after(0x45).unwrap(), [0x00, 0x00, 0x07, 0x01]);
+        assert_eq!(bus.data_after(0x3C).unwrap(), [BORDER_WAVEFORM_FULL]);
+    }
+
+    #[test]
+    fn gray_levels_split_into_planes() {
+        // white clears both planes, black sets both
+        assert_eq!(gray_plane_byte([0xFF, 0xFF], 0), 0x00);
+        assert_eq!(gray_plane_byte([0xFF, 0xFF], 1), 0x00);
+        assert_eq!(gray_plane_byte([0x00, 0x00], 0), 0xFF);
+        assert_eq!(gray_plane_byte([0x00, 0x00], 1), 0xFF);
+        // light gray (2) only in the first RAM, dark gray (1) only in the second
+        assert_eq!(gray_plane_byte([0xAA, 0xAA], 0), 0xFF);
+        assert_eq!(gray_plane_byte([0xAA, 0xAA], 1), 0x00);
+        assert_eq!(gray_plane_byte([0x55, 0x55], 0), 0x00);
+        assert_eq!(gray_plane_byte([0x55, 0x55], 1), 0xFF);
+        // the leftmost pixel in the highest bits
+        assert_eq!(gray_plane_byte([0x3F, 0xFF], 0), 0x80);
+        assert_eq!(gray_plane_byte([0xFF, 0xFD], 1), 0x01);
+    }
+
+    #[test]
+    fn gray_sequence() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = new_epd(&bus, &mut spi, &mut delay);
+
+        // the top half black, the bottom half light gray
+        let mut gray = [0xAA; GRAY_BUFFER_LEN];
+        gray[..GRAY_BUFFER_LEN / 2].fill(0x00);
+        bus.clear();
+        epd.update_frame_gray(&mut spi, &mut delay, &gray).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.commands(),
+            [
+                0x74, 0x7E, 0x3C, 0x2C, 0x3F, 0x03, 0x04, 0x32, 0x44, 0x45, 0x4E, 0x4F, 0x24, 0x4E,
+                0x4F, 0x26, 0x22, 0x20
+            ]
+        );
+        assert_eq!(bus.data_after(0x32).unwrap(), LUT_4_GRAY[..153]);
+        assert_eq!(bus.data_after(0x3F).unwrap(), [0x22]);
+        assert_eq!(bus.data_after(0x03).unwrap(), [0x17]);
+        assert_eq!(bus.data_after(0x04).unwrap(), [0x41, 0x00, 0x32]);
+        assert_eq!(bus.data_after(0x2C).unwrap(), [0x1C]);
+        assert_eq!(bus.data_after(0x3C).unwrap(), [BORDER_WAVEFORM_GRAY]);
+        let half = buffer_len(WIDTH as usize, HEIGHT as usize) / 2;
+        let first = bus.data_after(0x24).unwrap();
+        assert_eq!(first.len(), 2 * half);
+        assert!(first.iter().all(|&b| b == 0xFF));
+        let second = bus.data_after(0x26).unwrap();
+        assert!(second[..half].iter().all(|&b| b == 0xFF));
+        assert!(second[half..].iter().all(|&b| b == 0x00));
+        assert_eq!(bus.data_after(0x22).unwrap(), [SEQUENCE_GRAY]);
+
+        // the LUT stays loaded for the next gray frame
+        bus.clear();
+        epd.update_frame_gray(&mut spi, &mut delay, &gray).unwrap();
+        assert_eq!(bus.count(0x32), 0);
+
+        // a black/white frame initialises the controller again
+        bus.clear();
+        epd.update_and_display_frame(&mut spi, &[0xFF; 5808], &mut delay)
+            .unwrap();
+        assert_eq!(bus.count(0x12), 1);
+        assert_eq!(bus.data_after(0x22).unwrap(), [SEQUENCE_FULL]);
+    }
+
+    #[test]
+    fn quick_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = new_epd(&bus, &mut spi, &mut delay);
+
+        // the full LUT writes the base frame into both RAMs
+        bus.clear();
+        epd.update_and_display_frame(&mut spi, &[0xF0; 5808], &mut delay)
+            .unwrap();
+        assert_eq!(bus.data_after(0x26).unwrap(), [0xF0; 5808]);
+        assert_eq!(bus.data_after(0x22).unwrap(), [SEQUENCE_FULL]);
+
+        bus.clear();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        assert_eq!(bus.data_after(0x3C).unwrap(), [BORDER_WAVEFORM_QUICK]);
+
+        bus.clear();
+        epd.update_and_display_frame(&mut spi, &[0x0F; 5808], &mut delay)
+            .unwrap();
+        assert_eq!(bus.count(0x26), 0);
+        assert_eq!(bus.data_after(0x24).unwrap(), [0x0F; 5808]);
+        assert_eq!(bus.data_after(0x22).unwrap(), [SEQUENCE_QUICK]);
+        assert_eq!(epd.refresh_counters().quick, 1);
+    }
+
+    #[test]
+    fn partial_window() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = new_epd(&bus, &mut spi, &mut delay);
+        bus.clear();
+
+        // x 16..=39 and y 250..=259 past the 8 bit gates, rows of 3 bytes
+        let window = [0x0F; 3 * 10];
+        epd.update_partial_frame(&mut spi, &mut delay, &window, 16, 250, 24, 10)
+            .unwrap();
+        assert_eq!(bus.data_after(0x44).unwrap(), [0x02, 0x04]);
+        assert_eq!(bus.data_after(0x45).unwrap(), [0xFA, 0x00, 0x03, 0x01]);
+        assert_eq!(bus.data_after_nth(0x4F, 1).unwrap(), [0xFA, 0x00]);
+        assert_eq!(bus.data_after(0x24).unwrap(), window);
+        assert_eq!(bus.data_after(0x26).unwrap(), window);
+
+        bus.clear();
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut delay, &window, 160, 250, 24, 10),
+            Err(Error::InvalidWindow)
+        );
+        assert!(bus.commands().is_empty());
+    }
+}
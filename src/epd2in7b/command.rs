@@ -1,12 +1,13 @@
 //! SPI Commands for the Waveshare 2.7" B 3 color E-Ink Display
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 /// EPD2IN7B commands
 ///
 /// More information can be found in the [specification](https://www.waveshare.com/w/upload/d/d8/2.7inch-e-paper-b-specification.pdf)
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift direction, booster switch, soft reset
     PanelSetting = 0x00,
     /// Selecting internal and external power
@@ -122,6 +123,44 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff
+            | Command::PowerOn
+            | Command::PowerOnMeasure
+            | Command::DataStop
+            | Command::DisplayRefresh
+            | Command::ActiveProgramming => DataLen::Exact(0),
+            Command::PanelSetting
+            | Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::PllControl
+            | Command::VcomAndDataIntervalSetting
+            | Command::LowPowerDetection
+            | Command::TconSetting
+            | Command::VcmDcSetting
+            | Command::ProgramMode => DataLen::Exact(1),
+            Command::PowerOptimization => DataLen::Exact(2),
+            Command::BoosterSoftStart => DataLen::Exact(3),
+            Command::ResolutionSetting => DataLen::Exact(4),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::LutForVcom => DataLen::Exact(44),
+            Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutWhiteToBlack
+            | Command::LutBlackToBlack => DataLen::Exact(42),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::DataStartTransmission1 => Some(Plane::BlackWhite),
+            Command::DataStartTransmission2 => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -134,4 +173,14 @@ mod tests {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutForVcom.expected_data_len().accepts(44));
+        assert!(!Command::LutForVcom.expected_data_len().accepts(42));
+        assert!(Command::PowerOptimization.expected_data_len().accepts(2));
+        assert!(!Command::PowerOptimization.expected_data_len().accepts(1));
+        assert!(Command::ResolutionSetting.expected_data_len().accepts(4));
+        assert!(!Command::ResolutionSetting.expected_data_len().accepts(3));
+    }
 }
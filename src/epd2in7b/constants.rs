@@ -1,3 +1,5 @@
+use crate::color::PlaneConvention;
+
 #[rustfmt::skip]
 pub(crate) const LUT_VCOM_DC: [u8; 44] = [
 0x00, 0x00,
@@ -53,3 +55,8 @@ pub(crate) const LUT_WB: [u8; 42] =[
 0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
 0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
 ];
+
+/// Bit sense of the black/white plane on the wire, `send_buffer_helper` inverts the buffers
+pub(crate) const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsBlack;
+/// Bit sense of the chromatic plane on the wire
+pub(crate) const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsChromatic;
@@ -4,10 +4,16 @@
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
+use crate::vcom;
 
 // The Lookup Tables for the Display
 mod constants;
@@ -17,16 +23,32 @@ use crate::epd2in7b::constants::*;
 pub const WIDTH: u32 = 176;
 /// Height of the display
 pub const HEIGHT: u32 = 264;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in7b",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::{Color, Plane};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, line_bytes};
 
 /// Full size buffer for use with the 2in7B EPD
 /// TODO this should be a TriColor, but let's keep it as is at first
@@ -56,9 +78,32 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in7b {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // power on
         self.command(spi, Command::PowerOn)?;
@@ -96,8 +141,9 @@ where
         self.interface
             .cmd_with_data(spi, Command::PowerOptimization, &[0x73, 0x41])?;
 
+        let vcom = self.interface.vcom_or(0x12);
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
 
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x87])?;
@@ -110,6 +156,18 @@ where
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -122,6 +180,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -129,22 +190,22 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in7b { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
@@ -160,17 +221,29 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.send_buffer_helper(spi, buffer)?;
+        self.send_buffer_helper(spi, frame)?;
 
         // Clear chromatic layer since we won't be using it here
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.interface
-            .data_x_times(spi, !self.color.get_byte_value(), WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
 
-        self.interface.cmd(spi, Command::DataStop)?;
+        self.data_stop(spi)?;
         Ok(())
     }
 
@@ -183,27 +256,24 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
-            .cmd(spi, Command::PartialDataStartTransmission1)?;
-
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialDataStartTransmission1,
+            &partial_window(x, y, width, height),
+        )?;
         self.wait_until_idle(spi, delay)?;
 
-        self.send_buffer_helper(spi, buffer)?;
+        self.send_buffer_helper(spi, &mut FrameData::new(buffer))?;
 
-        self.interface.cmd(spi, Command::DataStop)
+        self.data_stop(spi)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.command(spi, Command::DisplayRefresh)?;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
@@ -213,26 +283,27 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.wait_until_idle(spi, delay)?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
 
-        self.interface.cmd(spi, Command::DataStop)?;
+        self.data_stop(spi)?;
 
+        let color_value = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
-        self.interface.cmd(spi, Command::DataStop)?;
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
+        self.data_stop(spi)?;
         Ok(())
     }
 
@@ -257,17 +328,23 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)?;
         self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)?;
         self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW)?;
         self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB)?;
         self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB)?;
+        self.interface.restore_operation(operation);
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
@@ -288,9 +365,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     /// Update only the black/white data of the display.
@@ -299,14 +394,10 @@ where
     fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         achromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-
-        self.send_buffer_helper(spi, achromatic)?;
-
-        self.interface.cmd(spi, Command::DataStop)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(achromatic))
     }
 
     /// Update only chromatic data of the display.
@@ -317,12 +408,30 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
 
-        self.send_buffer_helper(spi, chromatic)?;
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        let (before, after) = fill;
+        self.interface.data_x_times(spi, background, before)?;
+        self.send_buffer_helper(spi, &mut FrameData::new(chromatic.data))?;
+        self.interface.data_x_times(spi, background, after)?;
 
-        self.interface.cmd(spi, Command::DataStop)?;
+        self.data_stop(spi)?;
         self.wait_until_idle(spi, delay)?;
 
         Ok(())
@@ -337,29 +446,127 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+
+        self.send_buffer_helper(spi, black)?;
+
+        self.data_stop(spi)
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+
+        self.send_buffer_helper(spi, chromatic)?;
+
+        self.data_stop(spi)?;
+        self.wait_until_idle(spi, delay)?;
+
+        Ok(())
+    }
+
+    /// Updates the frame and starts the refresh without waiting for it to finish
+    ///
+    /// Unlike [WaveshareDisplay::update_and_display_frame] this returns while the
+    /// panel is still refreshing, poll [DriverControl::is_busy] before sending anything else.
+    pub fn update_and_begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.begin_display_frame(spi, delay)
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in7b::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in7b::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
-    fn send_buffer_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn send_buffer_helper(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
         // Based on the waveshare implementation, all data for color values is flipped. This helper
         // method makes that transmission easier
-        for b in buffer.iter() {
-            self.send_data(spi, &[!b])?;
+        for chunk in frame.chunks(0..frame.len()) {
+            for b in chunk {
+                self.send_data(spi, &[!b])?;
+            }
+            frame.sent(chunk.len());
         }
         Ok(())
     }
 
+    /// Ends the transmission of a plane, keeping the checksum of its data intact
+    fn data_stop(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        #[cfg(feature = "checksum")]
+        let checksum = self.interface.checksum();
+        self.interface.cmd(spi, Command::DataStop)?;
+        #[cfg(feature = "checksum")]
+        self.interface.set_checksum(checksum);
+        Ok(())
+    }
+
     fn cmd_with_data(
         &mut self,
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
@@ -372,16 +579,16 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.command(spi, Command::PartialDisplayRefresh)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialDisplayRefresh,
+            &partial_window(x, y, width, height),
+        )?;
+        self.interface.count_partial_refresh();
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
@@ -397,17 +604,15 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
-            .cmd(spi, Command::PartialDataStartTransmission1)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialDataStartTransmission1,
+            &partial_window(x, y, width, height),
+        )?;
         self.wait_until_idle(spi, delay)?;
 
         for b in achromatic.iter() {
@@ -429,17 +634,15 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
-            .cmd(spi, Command::PartialDataStartTransmission2)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        self.send_data(spi, &[(x & 0xf8) as u8])?;
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[(y & 0xff) as u8])?;
-        self.send_data(spi, &[(width >> 8) as u8])?;
-        self.send_data(spi, &[(width & 0xf8) as u8])?;
-        self.send_data(spi, &[(height >> 8) as u8])?;
-        self.send_data(spi, &[(height & 0xff) as u8])?;
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialDataStartTransmission2,
+            &partial_window(x, y, width, height),
+        )?;
         self.wait_until_idle(spi, delay)?;
 
         for b in chromatic.iter() {
@@ -451,9 +654,25 @@ where
     }
 }
 
+/// Parameters of the partial commands for the window at (x, y), x and the width are
+/// rounded down to multiples of 8
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> [u8; 8] {
+    [
+        (x >> 8) as u8,
+        (x & 0xf8) as u8,
+        (y >> 8) as u8,
+        (y & 0xff) as u8,
+        (width >> 8) as u8,
+        (width & 0xf8) as u8,
+        (height >> 8) as u8,
+        (height & 0xff) as u8,
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Bus, Delay, Pin};
 
     #[test]
     fn epd_size() {
@@ -461,4 +680,72 @@ mod tests {
         assert_eq!(HEIGHT, 264);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn update_and_display_frame_matches_separate_calls() {
+        let record = |combined: bool| {
+            let bus = Bus::new();
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let mut epd = Epd2in7b::new(
+                &mut spi,
+                bus.busy(true),
+                bus.dc(),
+                Pin::new(),
+                &mut delay,
+                None,
+            )
+            .unwrap();
+            epd.set_refresh_guard(1);
+            epd.note_time(1_000);
+            bus.clear();
+            if combined {
+                epd.update_and_display_frame(&mut spi, &[0xAA; 4], &mut delay)
+                    .unwrap();
+            } else {
+                epd.update_frame(&mut spi, &[0xAA; 4], &mut delay).unwrap();
+                epd.display_frame(&mut spi, &mut delay).unwrap();
+            }
+            let trace = (bus.transfers(), bus.busy_reads());
+
+            // the combined call is refused by the refresh guard as well
+            assert_eq!(
+                epd.update_and_display_frame(&mut spi, &[0xAA; 4], &mut delay),
+                Err(Error::RefreshTooSoon)
+            );
+            trace
+        };
+        assert_eq!(record(true), record(false));
+    }
+
+    #[test]
+    fn update_and_display_frame_waits_for_the_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in7b::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+        epd.update_and_display_frame(&mut spi, &[0xAA; 4], &mut delay)
+            .unwrap();
+        assert!(bus.waited_after(Command::DisplayRefresh as u8));
+
+        bus.clear();
+        epd.update_and_begin_display_frame(&mut spi, &[0xAA; 4], &mut delay)
+            .unwrap();
+        assert_eq!(
+            bus.commands().last(),
+            Some(&(Command::DisplayRefresh as u8))
+        );
+        assert!(!bus.waited_after(Command::DisplayRefresh as u8));
+        assert!(!epd.is_busy());
+        assert_eq!(epd.refresh_counters().full, 2);
+    }
 }
@@ -0,0 +1,485 @@
+//! Either version of the Waveshare 2.9" E-Ink Display
+//!
+//! The V1 (IL3820, [crate::epd2in9]) and the V2 (SSD1680, [crate::epd2in9_v2]) have
+//! the same resolution, buffer layout and origin, and both support quick refreshes.
+//! [Epd2in9Any] wraps the driver of either one, so an application can treat both the
+//! same after creating the right one, e.g. from a hardware revision pin.
+//!
+//! Only available with both the `epd2in9` and the `epd2in9_v2` feature.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd2in9_any::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let is_v2 = true;
+//!
+//!let mut epd = if is_v2 {
+//!    Epd2in9Any::new_v2(&mut spi, busy_in, dc, rst, &mut delay, None)?
+//!} else {
+//!    Epd2in9Any::new_v1(&mut spi, busy_in, dc, rst, &mut delay, None)?
+//!};
+//!
+//!let display = Display2in9::default();
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// quick refreshes on both versions
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!epd.update_old_frame(&mut spi, display.buffer(), &mut delay)?;
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::epd2in9::Epd2in9 as Epd2in9V1;
+use crate::epd2in9_v2::Epd2in9 as Epd2in9V2;
+use crate::error::Error;
+use crate::traits::{PeriodicUpdateOptions, QuickRefresh, RefreshLut, WaveshareDisplay};
+
+pub use crate::epd2in9_v2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+
+/// Display with Fullsize buffer for both versions of the 2in9 EPD
+#[cfg(feature = "graphics")]
+pub use crate::epd2in9_v2::Display2in9;
+
+/// Driver of either version of the 2.9" display
+pub enum Epd2in9Any<SPI, BUSY, DC, RST, DELAY> {
+    /// The V1 with the IL3820 controller
+    V1(Epd2in9V1<SPI, BUSY, DC, RST, DELAY>),
+    /// The V2 with the SSD1680 controller
+    V2(Epd2in9V2<SPI, BUSY, DC, RST, DELAY>),
+}
+
+/// Calls the same code on the driver of either version
+macro_rules! dispatch {
+    ($self:expr, $epd:ident => $body:expr) => {
+        match $self {
+            Epd2in9Any::V1($epd) => $body,
+            Epd2in9Any::V2($epd) => $body,
+        }
+    };
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9Any<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates the driver of the V1
+    pub fn new_v1(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Epd2in9V1::new(spi, busy, dc, rst, delay, delay_us).map(Self::V1)
+    }
+
+    /// Creates the driver of the V2
+    pub fn new_v2(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Epd2in9V2::new(spi, busy, dc, rst, delay, delay_us).map(Self::V2)
+    }
+
+    /// Whether this drives the V2
+    pub fn is_v2(&self) -> bool {
+        matches!(self, Self::V2(_))
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> From<Epd2in9V1<SPI, BUSY, DC, RST, DELAY>>
+    for Epd2in9Any<SPI, BUSY, DC, RST, DELAY>
+{
+    fn from(epd: Epd2in9V1<SPI, BUSY, DC, RST, DELAY>) -> Self {
+        Self::V1(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> From<Epd2in9V2<SPI, BUSY, DC, RST, DELAY>>
+    for Epd2in9Any<SPI, BUSY, DC, RST, DELAY>
+{
+    fn from(epd: Epd2in9V2<SPI, BUSY, DC, RST, DELAY>) -> Self {
+        Self::V2(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9Any<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = crate::buffer_len(WIDTH as usize, HEIGHT as usize);
+
+    /// Creates the driver of the V2, use [Epd2in9Any::new_v1] for the V1
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_v2(spi, busy, dc, rst, delay, delay_us)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.sleep(spi, delay))
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.wake_up(spi, delay))
+    }
+
+    fn recover(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.recover(spi, delay))
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.abort_refresh(spi, delay))
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        dispatch!(self, epd => epd.set_background_color(color))
+    }
+
+    fn background_color(&self) -> &Color {
+        dispatch!(self, epd => epd.background_color())
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_frame(spi, buffer, delay))
+    }
+
+    fn update_frame_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        progress: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_frame_with_progress(spi, buffer, delay, progress))
+    }
+
+    fn update_frame_vectored(
+        &mut self,
+        spi: &mut SPI,
+        parts: &[&[u8]],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_frame_vectored(spi, parts, delay))
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_partial_frame(spi, delay, buffer, x, y, width, height))
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.display_frame(spi, delay))
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_and_display_frame(spi, buffer, delay))
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.clear_frame(spi, delay))
+    }
+
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.clear_region(spi, delay, x, y, width, height))
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.set_lut(spi, delay, refresh_rate))
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.wait_until_idle(spi, delay))
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        dispatch!(self, epd => epd.refresh_lut())
+    }
+
+    fn periodic_update(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        opts: PeriodicUpdateOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.periodic_update(spi, delay, buffer, opts))
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9Any<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_old_frame(spi, buffer, delay))
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_new_frame(spi, buffer, delay))
+    }
+
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.display_new_frame(spi, delay))
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_and_display_new_frame(spi, buffer, delay))
+    }
+
+    fn promote_displayed_frame_to_old(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, Error<SPI::Error>> {
+        dispatch!(self, epd => epd.promote_displayed_frame_to_old(spi, delay))
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_partial_old_frame(spi, delay, buffer, x, y, width, height))
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.update_partial_new_frame(spi, delay, buffer, x, y, width, height))
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        dispatch!(self, epd => epd.clear_partial_frame(spi, delay, x, y, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9::Epd2in9;
+    use crate::graphics::Display;
+    use crate::mock::{Bus, Delay, Pin};
+    use crate::type_a::command::Command;
+    use embedded_graphics_core::prelude::*;
+
+    #[test]
+    fn both_versions_share_the_display() {
+        // the same buffer type of both modules
+        let mut display: crate::epd2in9::Display2in9 = Display2in9::default();
+        let _: &Display<WIDTH, HEIGHT, false, { 16 * 296 }, Color> = &display;
+        display.clear(Color::White).unwrap();
+        Pixel(Point::new(0, 0), Color::Black)
+            .draw(&mut display)
+            .unwrap();
+        let frame = display.buffer();
+
+        // both controllers count up from the same corner and get the pixel as first bit
+        for v2 in [false, true] {
+            let bus = Bus::new();
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let (busy, dc) = (bus.busy(false), bus.dc());
+            let mut epd = if v2 {
+                Epd2in9Any::new_v2(&mut spi, busy, dc, Pin::new(), &mut delay, None)
+            } else {
+                Epd2in9Any::new_v1(&mut spi, busy, dc, Pin::new(), &mut delay, None)
+            }
+            .unwrap();
+            assert_eq!(epd.is_v2(), v2);
+            assert_eq!(
+                bus.data_after(Command::DriverOutputControl as u8).unwrap(),
+                [0x27, 0x01, 0x00]
+            );
+            assert_eq!(
+                bus.data_after(Command::DataEntryModeSetting as u8).unwrap(),
+                [0x03]
+            );
+
+            bus.clear();
+            epd.update_and_display_frame(&mut spi, frame, &mut delay)
+                .unwrap();
+            if !v2 {
+                assert_eq!(
+                    bus.data_after(Command::SetRamXAddressCounter as u8)
+                        .unwrap(),
+                    [0x00]
+                );
+                assert_eq!(
+                    bus.data_after(Command::SetRamYAddressCounter as u8)
+                        .unwrap(),
+                    [0x00, 0x00]
+                );
+            }
+            let ram = bus.data_after(Command::WriteRam as u8).unwrap();
+            assert_eq!(ram[0], 0x7F);
+            assert!(ram[1..].iter().all(|&byte| byte == 0xFF));
+        }
+    }
+
+    #[test]
+    fn quick_refresh_on_both_versions() {
+        let frame = [0xFF; 4736];
+        for v2 in [false, true] {
+            let bus = Bus::new();
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let (busy, dc) = (bus.busy(false), bus.dc());
+            let mut epd: Epd2in9Any<_, _, _, _, _> = if v2 {
+                crate::epd2in9_v2::Epd2in9::new(&mut spi, busy, dc, Pin::new(), &mut delay, None)
+                    .unwrap()
+                    .into()
+            } else {
+                Epd2in9::new(&mut spi, busy, dc, Pin::new(), &mut delay, None)
+                    .unwrap()
+                    .into()
+            };
+
+            epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+                .unwrap();
+            assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+            epd.update_old_frame(&mut spi, &frame, &mut delay).unwrap();
+            epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+                .unwrap();
+            // a quick refresh with the quick LUT
+            let luts = bus.count(Command::WriteLutRegister as u8);
+            let lut = bus
+                .data_after_nth(Command::WriteLutRegister as u8, luts - 1)
+                .unwrap();
+            let (activation, lut_len) = if v2 { (0x0F, 159) } else { (0xC4, 30) };
+            assert_eq!(lut.len(), lut_len);
+            let activations = bus.count(Command::DisplayUpdateControl2 as u8);
+            assert_eq!(
+                bus.data_after_nth(Command::DisplayUpdateControl2 as u8, activations - 1)
+                    .unwrap(),
+                [activation]
+            );
+            assert!(epd
+                .promote_displayed_frame_to_old(&mut spi, &mut delay)
+                .unwrap());
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Async driver of the 2.9" V2 display, see [AsyncWaveshareDisplay]
+//!
+//! It sends the same sequences as the full refreshes of [Epd2in9](super::Epd2in9) and
+//! shares its commands and waveform.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    DEFAULT_BACKGROUND_COLOR, HEIGHT, IS_BUSY_LOW, RESET_CONFIG, SINGLE_BYTE_WRITE, WIDTH, WS_20_30,
+};
+use crate::color::Color;
+use crate::error::{self, Error};
+use crate::interface_async::AsyncDisplayInterface;
+use crate::traits::AsyncWaveshareDisplay;
+use crate::type_a::command::Command;
+
+/// Async Epd2in9 (V2) driver
+///
+/// Refreshes with the full waveform, the quick refreshes and partial updates of
+/// [Epd2in9](super::Epd2in9) aren't available yet.
+pub struct Epd2in9Async<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9Async<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    async fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG).await;
+
+        self.wait_until_idle(spi, delay).await?;
+        self.interface.cmd(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi, delay).await?;
+
+        // height of the screen (0x127), GD, SM and TB = 0
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])
+            .await?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+
+        // the whole RAM, x is positioned in bytes
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressStartEndPosition,
+                &[0, ((WIDTH - 1) >> 3) as u8],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressStartEndPosition,
+                &[0, 0, (HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8],
+            )
+            .await?;
+
+        // black/white RAM not inverted
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])
+            .await?;
+
+        // start from the beginning
+        self.wait_until_idle(spi, delay).await?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0, 0])
+            .await?;
+
+        self.wait_until_idle(spi, delay).await?;
+
+        self.load_full_waveform(spi, delay).await
+    }
+
+    /// Uploads the LUT, driving voltages and VCOM of full refreshes
+    async fn load_full_waveform(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &WS_20_30[0..153])
+            .await?;
+        self.wait_until_idle(spi, delay).await?;
+        for (command, range) in [
+            (Command::WriteLutRegisterEnd, 153..154),
+            (Command::GateDrivingVoltage, 154..155),
+            (Command::SourceDrivingVoltage, 155..158),
+            (Command::WriteVcomRegister, 158..159),
+        ] {
+            self.interface
+                .cmd_with_data(spi, command, &WS_20_30[range])
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9Async<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut epd = Epd2in9Async {
+            interface: AsyncDisplayInterface::new(busy, dc, rst),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+        epd.init(spi, delay).await?;
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay).await?;
+        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay).await
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await
+    }
+
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.wait_until_idle(spi, delay).await?;
+        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])
+            .await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi, delay).await
+    }
+
+    async fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW).await;
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -58,11 +58,40 @@
 pub const WIDTH: u32 = 128;
 /// Height of epd2in9 in pixels
 pub const HEIGHT: u32 = 296;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in9_v2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Worst case times for [BusyMode::FixedDelay], from the specification with some margin
+const BUSY_TIMINGS: BusyTimings = BusyTimings {
+    init: 100_000,
+    full_refresh: 4_000_000,
+    partial_refresh: 600_000,
+    sleep: 10_000,
+    command: 10_000,
+};
+
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(3_000_000, BUSY_TIMINGS.full_refresh)
+    .with_quick(300_000, BUSY_TIMINGS.partial_refresh);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+
 const LUT_PARTIAL_2IN9: [u8; 159] = [
     0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x80, 0x0, 0x0, 0x0, 0x0,
     0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
@@ -89,15 +118,30 @@ const WS_20_30: [u8; 159] = [
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::{
+    BusyMode, BusyTimings, Operation, RefreshLimits, SelfTestReport, SELF_TEST_PATTERN,
+};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::reset::ResetConfig;
+use crate::ssd16xx;
 use crate::type_a::command::Command;
+use crate::update_sequence::UpdateSequence;
+use crate::voltage::DrivingVoltages;
 
-use crate::color::Color;
+use crate::color::{Color, Plane, PlaneConvention};
 
 use crate::traits::*;
 
-use crate::buffer_len;
 use crate::interface::DisplayInterface;
 use crate::traits::QuickRefresh;
+use crate::vcom;
+use crate::{buffer_len, line_bytes};
+
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use self::asynch::Epd2in9Async;
 
 /// Display with Fullsize buffer for use with the 2in9 EPD V2
 #[cfg(feature = "graphics")]
@@ -118,9 +162,21 @@ pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Black/white RAM shown inverted
+    inverted: bool,
+    /// The old frame RAM holds the shown frame, copied there by the last quick refresh
+    old_frame_shown: bool,
+    /// Whether the controller is in deep sleep, it only keeps its RAM then
+    asleep: bool,
+    /// Whether the LUT of quick refreshes replaced the one of full refreshes
+    quick_waveform_loaded: bool,
+    /// Windows written by [Epd2in9::write_ram_window] wait for
+    /// [Epd2in9::refresh_written]
+    ram_windows: bool,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
@@ -128,11 +184,44 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 2_000);
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in9 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            inverted: false,
+            old_frame_shown: false,
+            asleep: false,
+            quick_waveform_loaded: false,
+            ram_windows: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.old_frame_shown = false;
+        self.asleep = false;
+        self.ram_windows = false;
+        self.interface.reset(delay, RESET_CONFIG);
 
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
+        self.interface.start_operation(Operation::Init);
         self.wait_until_idle(spi, delay)?;
 
         // 3 Databytes:
@@ -150,26 +239,165 @@ where
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])?;
+        self.set_inverted(spi, self.inverted)?;
 
         self.set_ram_counter(spi, delay, 0, 0)?;
 
         self.wait_until_idle(spi, delay)?;
 
+        self.load_full_waveform(spi, delay)
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        if self.refresh == RefreshLut::Quick {
+            self.load_quick_waveform(spi, delay)?;
+            return self.start_quick_frame(spi, delay, false);
+        }
+        self.start_full_frame(spi, delay, false)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Uploads the LUT, driving voltages and VCOM of full refreshes
+    fn load_full_waveform(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         // set LUT by host
         self.set_lut_helper(spi, delay, &WS_20_30[0..153])?;
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegisterEnd, &WS_20_30[153..154])?;
+        let [vgh, vsh1, vsh2, vsl] = self.interface.ssd16xx_voltages_or([
+            WS_20_30[154],
+            WS_20_30[155],
+            WS_20_30[156],
+            WS_20_30[157],
+        ]);
         self.interface
-            .cmd_with_data(spi, Command::GateDrivingVoltage, &WS_20_30[154..155])?;
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &[vgh])?;
         self.interface
-            .cmd_with_data(spi, Command::SourceDrivingVoltage, &WS_20_30[155..158])?;
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &[vsh1, vsh2, vsl])?;
+        let vcom = self.interface.vcom_or(WS_20_30[158]);
         self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &WS_20_30[158..159])?;
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])?;
+        self.quick_waveform_loaded = false;
 
         Ok(())
     }
+
+    /// Resets the controller, which keeps its RAM, and uploads the LUT of quick refreshes
+    fn load_quick_waveform(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.set_lut_helper(spi, delay, &LUT_PARTIAL_2IN9)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteOtpSelection,
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.start_operation(Operation::Init);
+
+        self.wait_until_idle(spi, delay)?;
+        self.quick_waveform_loaded = true;
+
+        self.use_full_frame(spi, delay)
+    }
+
+    /// Refreshes quickly from the old frame RAM, `windows` counts it as partial one
+    fn display_quick_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_quick_frame(spi, delay, windows)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Starts the refresh of [Self::display_quick_frame] without waiting for it
+    fn start_quick_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        if windows {
+            self.interface.count_partial_refresh();
+        } else {
+            self.interface.count_refresh(true);
+        }
+        self.interface.start_operation(Operation::PartialRefresh);
+        // display mode 2 copies the new frame into the old frame RAM
+        self.old_frame_shown = true;
+        Ok(())
+    }
+
+    /// Refreshes with the LUT of full refreshes, `windows` counts it as partial one
+    fn display_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_full_frame(spi, delay, windows)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Starts the refresh of [Self::display_full_frame] without waiting for it
+    fn start_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        windows: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.wait_until_idle(spi, delay)?;
+        if self.quick_waveform_loaded {
+            self.load_full_waveform(spi, delay)?;
+            self.use_full_frame(spi, delay)?;
+        }
+        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+        let sequence = self.interface.update_sequence_or(0xC7);
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        if windows {
+            self.interface.count_partial_refresh();
+        } else {
+            self.interface.count_refresh(false);
+        }
+        self.interface.start_operation(Operation::FullRefresh);
+        self.old_frame_shown = false;
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -182,6 +410,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -197,41 +428,53 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-
-        let mut epd = Epd2in9 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_busy_mode(spi, busy, dc, rst, delay, delay_us, BusyMode::Pin)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.start_operation(Operation::Sleep);
+        self.asleep = true;
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)?;
         Ok(())
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
-        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRam, frame, 0..frame.len())
     }
 
     fn update_partial_frame(
@@ -243,7 +486,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
         //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
         self.wait_until_idle(spi, delay)?;
         self.set_ram_area(spi, x, y, x + width, y + height)?;
@@ -255,14 +502,14 @@ where
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    ///
+    /// With the quick LUT selected this is a quick refresh from the frame in the old
+    /// frame RAM, like [QuickRefresh::display_new_frame]. It holds the shown frame after
+    /// a quick refresh, before the first one it has to be sent with
+    /// [QuickRefresh::update_old_frame].
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -270,23 +517,29 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
+        self.old_frame_shown = false;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self
+            .background_color
+            .fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)?;
         self.interface.cmd(spi, Command::WriteRam2)?;
-        self.interface.data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+        self.interface
+            .data_x_times(spi, color, line_bytes(WIDTH) * HEIGHT)
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -302,17 +555,74 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+
+    /// The SSD1680 keeps its RAM in deep sleep mode 1, but the reset needed to wake it
+    /// up restores the default registers and LUT.
+    ///
+    /// A quick refresh loads its LUT after a reset anyway and only needs the shown frame
+    /// in the old frame RAM, so it works after deep sleep as well. Full periodic updates
+    /// write the frame into both RAMs for this, the first quick one after a new driver or
+    /// an update without a retained configuration is a full one. With a retained
+    /// configuration and without deep sleep a full refresh only uploads its LUT again,
+    /// replaced by the quick refreshes. The controller powers down by itself after a
+    /// refresh.
+    fn periodic_update(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        opts: PeriodicUpdateOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.ensure_no_session()?;
+        if !opts.assume_config_retained {
+            self.interface.set_operation(error::Operation::WakeUp);
+            self.init(spi, delay)?;
+        }
+        if !opts.full_refresh && self.old_frame_shown {
+            self.update_and_display_new_frame(spi, buffer, delay)?;
+        } else {
+            if self.asleep {
+                self.interface.set_operation(error::Operation::WakeUp);
+                self.init(spi, delay)?;
+            } else if opts.assume_config_retained {
+                self.interface.set_operation(error::Operation::Lut);
+                self.load_full_waveform(spi, delay)?;
+                self.use_full_frame(spi, delay)?;
+            }
+            self.interface.set_operation(error::Operation::UpdateFrame);
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam2, buffer)?;
+            self.set_ram_counter(spi, delay, 0, 0)?;
+            self.update_frame(spi, buffer, delay)?;
+            self.interface.set_operation(error::Operation::DisplayFrame);
+            self.display_full_frame(spi, delay, false)?;
+            self.old_frame_shown = true;
+        }
+        if opts.deep_sleep_after {
+            self.sleep(spi, delay)?;
+        }
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
@@ -323,7 +633,267 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn use_full_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Replaces the gate and source driving voltages sent by `init`/`wake_up`, `None`
+    /// restores the ones of the driver, see [crate::voltage]
+    ///
+    /// Takes effect with the next `wake_up`, the quick refreshes keep the voltages of
+    /// their waveform. Returns [Error::InvalidVoltage] without changing anything for
+    /// voltages the controller doesn't support.
+    pub fn set_driving_voltages(
+        &mut self,
+        voltages: Option<DrivingVoltages>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(voltages) = voltages {
+            voltages
+                .ssd16xx
+                .registers()
+                .map_err(Error::InvalidVoltage)?;
+        }
+        self.interface.set_driving_voltages(voltages);
+        Ok(())
+    }
+
+    /// Full refresh with the short waveform of a hot panel, about twice as fast but
+    /// with a slightly lower contrast, see
+    /// [fast full refreshes](crate::update_sequence#fast-full-refreshes)
+    pub fn display_frame_fast(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.wait_until_idle(spi, delay)?;
+        if self.quick_waveform_loaded {
+            self.use_full_frame(spi, delay)?;
+        }
+        ssd16xx::display_frame_fast(&mut self.interface, spi, delay, IS_BUSY_LOW)?;
+        self.old_frame_shown = false;
+        // the OTP waveform replaced the LUT and the voltages of the driver
+        self.load_full_waveform(spi, delay)
+    }
+
+    /// [self_test](DriverControl::self_test) which also writes some bytes to the RAM and reads
+    /// them back
+    ///
+    /// Needs the data line of the controller connected to MISO, see `read_user_id`.
+    /// The read back is skipped if the busy line already failed.
+    pub fn self_test_read_back(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        let report = self.self_test(spi, delay)?;
+        if !report.passed() {
+            return Ok(report);
+        }
+        self.init(spi, delay)?;
+        self.set_ram_counter(spi, delay, 0, 0)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, &SELF_TEST_PATTERN)?;
+        self.set_ram_counter(spi, delay, 0, 0)?;
+        self.interface.cmd(spi, Command::ReadRam)?;
+        // the first byte is a dummy
+        let mut read = [0; SELF_TEST_PATTERN.len() + 1];
+        let result = self.interface.read(spi, &mut read);
+        self.interface.poison();
+        result?;
+        Ok(report.with_read_back(read[1..] == SELF_TEST_PATTERN))
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in9::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::ssd16xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in9::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::ssd16xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Same as [new](WaveshareDisplay::new), but selects how to wait for the display
+    ///
+    /// With [BusyMode::FixedDelay] already the initialisation doesn't use the busy
+    /// pin, which can then be a [DummyInputPin](crate::busy::DummyInputPin).
+    /// See [crate::busy] for the drawbacks.
+    pub fn new_with_busy_mode(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        busy_mode: BusyMode,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Selects whether to poll the busy pin or to wait worst case times, see [crate::busy]
+    pub fn set_busy_mode(&mut self, busy_mode: BusyMode) {
+        self.interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+    }
+
+    /// Current busy mode
+    pub fn busy_mode(&self) -> BusyMode {
+        self.interface.busy_mode()
+    }
+
+    /// Shows the black/white RAM inverted, starting with the next refresh
+    ///
+    /// This only changes the RAM content option of the controller, so it is free:
+    /// the frame doesn't need to be transmitted again. The setting is kept over
+    /// [`wake_up`](WaveshareDisplay::wake_up). For a software inversion, which needs a
+    /// retransmit, see `Display::invert`.
+    pub fn set_inverted(&mut self, spi: &mut SPI, invert: bool) -> Result<(), Error<SPI::Error>> {
+        self.inverted = invert;
+        // RAM content option of the black/white RAM, 0b1000 inverts it
+        let option = if invert { 0x08 } else { 0x00 };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[option, 0x80])
+    }
+
+    /// Whether the black/white RAM is shown inverted
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` of the RAM `bank` without refreshing
+    ///
+    /// Any number of windows can be written, [`refresh_written`](Self::refresh_written)
+    /// then shows all of them with a single refresh. This is a lot faster than
+    /// refreshing after every window. Until then frame updates, refreshes and clears
+    /// return [`Error::PartialSessionActive`].
+    ///
+    /// `x` and `width` should be multiples of 8. Returns [`Error::InvalidWindow`]
+    /// without sending anything if the window is empty or doesn't fit on the panel.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        bank: RamBank,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, delay, x, y)?;
+        let command = match bank {
+            RamBank::New => Command::WriteRam,
+            RamBank::Old => {
+                self.old_frame_shown = false;
+                Command::WriteRam2
+            }
+        };
+        self.interface.cmd_with_data(spi, command, buffer)?;
+        self.ram_windows = true;
+        Ok(())
+    }
+
+    /// Shows the windows written by [`write_ram_window`](Self::write_ram_window) with a
+    /// single refresh
+    ///
+    /// A quick `mode` refreshes from the [old](RamBank::Old) RAM after loading the LUT
+    /// of quick refreshes, which activates the controller once more. Returns
+    /// [`Error::NoPartialSession`] if no window was written.
+    pub fn refresh_written(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        if !self.ram_windows {
+            return Err(Error::NoPartialSession);
+        }
+        self.ram_windows = false;
+        if mode == RefreshLut::Quick {
+            self.load_quick_waveform(spi, delay)?;
+            self.display_quick_frame(spi, delay, true)
+        } else {
+            self.display_full_frame(spi, delay, true)
+        }
+    }
+
+    fn ensure_no_session(&self) -> Result<(), Error<SPI::Error>> {
+        if self.ram_windows {
+            return Err(Error::PartialSessionActive);
+        }
+        Ok(())
+    }
+
+    fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -338,9 +908,9 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -369,12 +939,12 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[x as u8])?;
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
         // 2 Databytes: A[7:0] & 0..A[8]
         self.interface.cmd_with_data(
@@ -391,7 +961,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
@@ -400,6 +970,35 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> RowStreaming<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_frame_rows<F: FnMut(u32, &mut [u8])>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        row: &mut [u8],
+        mut fill_row: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_no_session()?;
+        debug_assert_eq!(row.len(), line_bytes(WIDTH) as usize);
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        for y in 0..HEIGHT {
+            fill_row(y, row);
+            self.interface.data_combined(spi, row)?;
+        }
+        self.interface.flush(spi)
+    }
+}
+
 impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
     for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
@@ -415,10 +1014,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
         self.interface
-            .cmd_with_data(spi, Command::WriteRam2, buffer)
+            .cmd_with_data(spi, Command::WriteRam2, buffer)?;
+        self.old_frame_shown = true;
+        Ok(())
     }
 
     /// To be used immediately after `update_old_frame`.
@@ -427,39 +1030,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface.reset(delay, 10_000, 2_000);
-
-        self.set_lut_helper(spi, delay, &LUT_PARTIAL_2IN9)?;
-        self.interface.cmd_with_data(
-            spi,
-            Command::WriteOtpSelection,
-            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
-        )?;
-        self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-
-        self.wait_until_idle(spi, delay)?;
-
-        self.use_full_frame(spi, delay)?;
-
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_no_session()?;
+        self.load_quick_waveform(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)?;
         Ok(())
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        self.display_quick_frame(spi, delay, false)
     }
 
     /// Updates and displays the new frame.
@@ -468,12 +1056,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_new_frame(spi, delay)?;
         Ok(())
     }
 
+    /// Only waits for the copy done by the last quick refresh to finish
+    fn promote_displayed_frame_to_old(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_no_session()?;
+        self.wait_until_idle(spi, delay)?;
+        Ok(self.old_frame_shown)
+    }
+
     /// Partial quick refresh not supported yet
     #[allow(unused)]
     fn update_partial_old_frame(
@@ -485,7 +1085,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         //TODO supported by display?
         unimplemented!()
     }
@@ -501,7 +1104,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         //TODO supported by display?
         unimplemented!()
     }
@@ -516,7 +1122,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         //TODO supported by display?
         unimplemented!()
     }
@@ -525,6 +1134,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::busy::LikelyFault;
+    use crate::mock::{Bus, BusyPin, Delay, Pin, Transfer};
 
     #[test]
     fn epd_size() {
@@ -532,4 +1143,448 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn vcom_override() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let vcom = Command::WriteVcomRegister as u8;
+
+        // the value of the LUT by default
+        Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(bus.data_after(vcom).unwrap(), [WS_20_30[158]]);
+
+        bus.clear();
+        let mut epd = Epd2in9::new_with_vcom(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+            -2050,
+        )
+        .unwrap();
+        assert_eq!(bus.data_after(vcom).unwrap(), [0x52]);
+
+        bus.clear();
+        epd.set_vcom(&mut spi, 0).unwrap();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(vcom), 2);
+        assert_eq!(bus.data_after_nth(vcom, 1).unwrap(), [0x08]);
+    }
+
+    #[test]
+    fn fast_refresh_restores_temperature_and_waveform() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+
+        bus.clear();
+        epd.display_frame_fast(&mut spi, &mut delay).unwrap();
+        let temperature = Command::TemperatureSensorControl as u8;
+        let control = Command::DisplayUpdateControl2 as u8;
+        // the whole RAM after the quick refresh
+        assert_eq!(
+            bus.commands()[0],
+            Command::SetRamXAddressStartEndPosition as u8
+        );
+        assert_eq!(
+            bus.data_after(temperature).unwrap(),
+            ssd16xx::FAST_TEMPERATURE
+        );
+        assert_eq!(bus.data_after_nth(control, 0).unwrap(), [0x91]);
+        assert_eq!(bus.data_after_nth(control, 1).unwrap(), [0xC7]);
+        assert_eq!(bus.data_after_nth(control, 2).unwrap(), [0xA1]);
+        assert_eq!(
+            bus.data_after(Command::WriteLutRegister as u8).unwrap(),
+            WS_20_30[..153]
+        );
+
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(temperature), 0);
+        assert_eq!(bus.data_after(control).unwrap(), [0xC7]);
+    }
+
+    #[test]
+    fn abort_refresh_needs_reinit() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+
+        // software reset, then deep sleep without polling the busy pin
+        bus.clear();
+        epd.abort_refresh(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.commands(),
+            [Command::SwReset as u8, Command::DeepSleepMode as u8]
+        );
+        assert_eq!(
+            bus.data_after(Command::DeepSleepMode as u8).unwrap(),
+            [0x01]
+        );
+        assert!(!bus.waited_after(Command::SwReset as u8));
+
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame, &mut delay),
+            Err(Error::NeedsReset(error::Context::new(
+                error::Operation::UpdateFrame
+            )))
+        );
+        assert_eq!(bus.commands().len(), 2);
+
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+    }
+
+    #[test]
+    fn display_frame_checked_waits_once_for_the_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let busy = BusyPin::new(false);
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            busy.clone(),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        let reads = busy.reads();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        let display_reads = busy.reads() - reads;
+
+        // the wait for the previous operation, then the ones of display_frame itself
+        epd.note_time(u64::from(MIN_REFRESH_INTERVAL));
+        let reads = busy.reads();
+        epd.display_frame_checked(&mut spi, &mut delay).unwrap();
+        assert_eq!(busy.reads() - reads, display_reads + 1);
+    }
+
+    #[test]
+    fn clear_region_unsupported() {
+        use embedded_graphics_core::prelude::{Point, Size};
+        use embedded_graphics_core::primitives::Rectangle;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        bus.clear();
+        assert_eq!(
+            epd.clear_region(&mut spi, &mut delay, 0, 0, 16, 16),
+            Err(Error::Unsupported)
+        );
+        let keep = Rectangle::new(Point::new(0, 0), Size::new(16, 16));
+        assert_eq!(
+            epd.clear_frame_except(&mut spi, &mut delay, keep, &frame),
+            Err(Error::Unsupported)
+        );
+        assert!(bus.transfers().is_empty());
+    }
+
+    #[test]
+    fn quick_refresh_reuses_the_shown_frame() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        // a full refresh doesn't copy the frame, the first quick refresh needs the old one
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert!(!epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+        epd.update_old_frame(&mut spi, &frame, &mut delay).unwrap();
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+
+        bus.clear();
+        assert!(epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert_eq!(bus.count(0x26), 0);
+        assert_eq!(bus.data_after(0x24).unwrap().len(), 4736);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC0]);
+        assert_eq!(bus.data_after_nth(0x22, 1).unwrap(), [0x0F]);
+
+        // clearing writes the old frame RAM
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        assert!(!epd
+            .promote_displayed_frame_to_old(&mut spi, &mut delay)
+            .unwrap());
+    }
+
+    #[test]
+    fn read_user_id() {
+        use crate::mock::Transfer;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        let id = [0x57, 0x53, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        bus.reply(&id);
+        let mut out = [0; 10];
+        epd.read_user_id(&mut spi, &mut delay, &mut out).unwrap();
+        assert_eq!(out, id);
+        assert_eq!(
+            bus.transfers(),
+            [
+                Transfer::Command(Command::UserIdRead as u8),
+                Transfer::Read(id.to_vec())
+            ]
+        );
+
+        // the DC pin is switched back for the following command
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands().last(), Some(&(Command::DeepSleepMode as u8)));
+    }
+
+    #[test]
+    fn periodic_update_sequences() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let rst = Pin::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            rst.clone(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0xAA; 4736];
+        let quick = PeriodicUpdateOptions {
+            assume_config_retained: true,
+            ..Default::default()
+        };
+        let sw_reset = Command::SwReset as u8;
+        let old_ram = Command::WriteRam2 as u8;
+
+        // the first one is a full refresh, also filling the old frame RAM
+        bus.clear();
+        epd.periodic_update(&mut spi, &mut delay, &frame, quick)
+            .unwrap();
+        assert_eq!(
+            bus.commands(),
+            [
+                0x32, 0x3F, 0x03, 0x04, 0x2C, 0x44, 0x45, 0x4E, 0x4F, 0x26, 0x4E, 0x4F, 0x24, 0x22,
+                0x20
+            ]
+        );
+        assert_eq!(bus.data_after(old_ram).unwrap(), frame);
+
+        // quick refreshes from the retained RAM, also after deep sleep
+        let resets = rst.levels().len();
+        for _ in 0..2 {
+            bus.clear();
+            let opts = PeriodicUpdateOptions {
+                deep_sleep_after: true,
+                ..quick
+            };
+            epd.periodic_update(&mut spi, &mut delay, &frame, opts)
+                .unwrap();
+            assert_eq!(bus.data_after(0x32).unwrap(), LUT_PARTIAL_2IN9);
+            assert_eq!(bus.count(sw_reset), 0);
+            assert_eq!(bus.count(old_ram), 0);
+            assert_eq!(bus.data_after(0x22).unwrap(), [0xC0]);
+            assert_eq!(bus.data_after_nth(0x22, 1).unwrap(), [0x0F]);
+            assert_eq!(bus.commands().last(), Some(&0x10));
+        }
+        assert!(rst.levels().len() > resets);
+
+        // a full refresh after deep sleep initialises again
+        bus.clear();
+        let full = PeriodicUpdateOptions {
+            full_refresh: true,
+            ..quick
+        };
+        epd.periodic_update(&mut spi, &mut delay, &frame, full)
+            .unwrap();
+        assert_eq!(bus.count(sw_reset), 1);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC7]);
+
+        // nothing retained, the quick refresh needs the old frame again
+        bus.clear();
+        epd.periodic_update(&mut spi, &mut delay, &frame, Default::default())
+            .unwrap();
+        assert_eq!(bus.count(sw_reset), 1);
+        assert_eq!(bus.data_after(old_ram).unwrap(), frame);
+        assert_eq!(bus.data_after(0x22).unwrap(), [0xC7]);
+    }
+
+    #[test]
+    fn self_test_read_back() {
+        let run = |reply: &[u8]| {
+            let bus = Bus::new();
+            let busy = BusyPin::new(false);
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let mut epd = Epd2in9::new(
+                &mut spi,
+                busy.clone(),
+                bus.dc(),
+                Pin::new(),
+                &mut delay,
+                None,
+            )
+            .unwrap();
+            // idle during the reset, then busy after the reset and the command
+            busy.push(&[false; 20]);
+            busy.push(&[true, false, true, false]);
+            bus.clear();
+            bus.reply(reply);
+            let report = epd.self_test_read_back(&mut spi, &mut delay).unwrap();
+            assert_eq!(
+                bus.data_after(Command::WriteRam as u8).unwrap(),
+                SELF_TEST_PATTERN
+            );
+            assert!(bus.transfers().ends_with(&[
+                Transfer::Command(Command::ReadRam as u8),
+                Transfer::Read(reply.to_vec())
+            ]));
+            report
+        };
+
+        let mut reply = [0xFF; 9];
+        reply[1..].copy_from_slice(&SELF_TEST_PATTERN);
+        let report = run(&reply);
+        assert_eq!(report.ram_read_back, Some(true));
+        assert!(report.passed());
+
+        // MISO not connected
+        let report = run(&[0; 9]);
+        assert_eq!(report.ram_read_back, Some(false));
+        assert_eq!(report.fault, Some(LikelyFault::RamMismatch));
+    }
+
+    #[test]
+    fn ram_windows_share_one_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        bus.clear();
+        for (i, y) in [8, 40, 280].into_iter().enumerate() {
+            epd.write_ram_window(
+                &mut spi,
+                &mut delay,
+                &[i as u8; 4],
+                16,
+                y,
+                16,
+                2,
+                RamBank::New,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            epd.clear_frame(&mut spi, &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        epd.refresh_written(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+
+        assert_eq!(bus.count(Command::SetRamXAddressStartEndPosition as u8), 3);
+        // the counter is positioned in bytes like the window
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamXAddressCounter as u8, 0)
+                .unwrap(),
+            [2]
+        );
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamYAddressStartEndPosition as u8, 2)
+                .unwrap(),
+            [24, 1, 25, 1]
+        );
+        assert_eq!(bus.count(Command::MasterActivation as u8), 1);
+        assert_eq!(
+            bus.data_after(Command::DisplayUpdateControl2 as u8)
+                .unwrap(),
+            [0xC7]
+        );
+        assert_eq!(epd.refresh_counters().partial, 1);
+        assert_eq!(epd.refresh_counters().full, 0);
+    }
 }
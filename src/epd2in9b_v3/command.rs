@@ -0,0 +1,108 @@
+//! SPI Commands for the Waveshare 2.9" (B) V3 E-Ink Display
+
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
+
+/// Epd2in9b V3 commands, an SSD1680 controller
+///
+/// For more infos about the addresses and what they are doing look into the pdfs
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum Command {
+    /// Number of gates (lines) minus one and the scanning order
+    DriverOutputControl = 0x01,
+    GateDrivingVoltageControl = 0x03,
+    SourceDrivingVoltageControl = 0x04,
+    /// Deep sleep mode control, 0x01 keeps the RAM
+    DeepSleepMode = 0x10,
+    /// Define data entry sequence
+    DataEntryModeSetting = 0x11,
+    /// Resets the commands and parameters to their defaults, BUSY is high meanwhile.
+    /// The RAM is unaffected.
+    SwReset = 0x12,
+    /// Temperature sensor selection, 0x80 for the internal one
+    TemperatureSensorControl = 0x18,
+    /// Runs the display update sequence selected with DisplayUpdateControl2
+    MasterActivation = 0x20,
+    /// RAM content options of the black/white and the red RAM and the source range
+    DisplayUpdateControl1 = 0x21,
+    DisplayUpdateControl2 = 0x22,
+    /// Black/white RAM, 1 is white
+    WriteRamBlackWhite = 0x24,
+    /// Red RAM
+    WriteRamRed = 0x26,
+    ReadRam = 0x27,
+    UserIdRead = 0x2E,
+    StatusBitRead = 0x2F,
+    BorderWaveformControl = 0x3C,
+    /// Start and end of the RAM window in bytes
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Start and end of the RAM window in gates, 9 bits each
+    SetRamYAddressStartEndPosition = 0x45,
+    SetRamXAddressCounter = 0x4E,
+    SetRamYAddressCounter = 0x4F,
+    Nop = 0x7F,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::SwReset | Command::MasterActivation | Command::Nop => DataLen::Exact(0),
+            Command::GateDrivingVoltageControl
+            | Command::DeepSleepMode
+            | Command::DataEntryModeSetting
+            | Command::TemperatureSensorControl
+            | Command::DisplayUpdateControl2
+            | Command::BorderWaveformControl
+            | Command::SetRamXAddressCounter => DataLen::Exact(1),
+            Command::SetRamXAddressStartEndPosition | Command::SetRamYAddressCounter => {
+                DataLen::Exact(2)
+            }
+            Command::DriverOutputControl | Command::SourceDrivingVoltageControl => {
+                DataLen::Exact(3)
+            }
+            Command::SetRamYAddressStartEndPosition => DataLen::Exact(4),
+            Command::DisplayUpdateControl1 => DataLen::Max(2),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::WriteRamBlackWhite => Some(Plane::BlackWhite),
+            Command::WriteRamRed => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::DriverOutputControl.address(), 0x01);
+        assert_eq!(Command::WriteRamRed.address(), 0x26);
+        assert_eq!(Command::SetRamYAddressCounter.address(), 0x4F);
+        assert_eq!(Command::Nop.address(), 0x7F);
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::DriverOutputControl.expected_data_len().accepts(3));
+        assert!(!Command::DriverOutputControl.expected_data_len().accepts(2));
+        assert!(Command::SetRamXAddressStartEndPosition
+            .expected_data_len()
+            .accepts(2));
+        assert!(!Command::SetRamXAddressStartEndPosition
+            .expected_data_len()
+            .accepts(4));
+    }
+}
@@ -0,0 +1,823 @@
+//! A driver for the Waveshare 2.9" (B) V3 E-Ink Display (black, white and red) via SPI
+//!
+//! 128x296 pixels with an SSD1680 controller. The module replaced the IL0373 based
+//! 2.9" (B/C) one (see [crate::epd2in9bc]) and stays white with that driver. The frame
+//! is written into two RAMs with SSD16xx commands, rows take 16 bytes.
+//!
+//! # Red RAM
+//!
+//! Like on [crate::epd2in13b_v4] the red RAM is shown as is, 1 is red, so the chromatic
+//! plane of [Display2in9b_v3] is sent unchanged. Red takes precedence over the
+//! black/white RAM.
+//!
+//! # Refresh
+//!
+//! A refresh of the three colors takes about 15 s, the driver waits on the busy line
+//! until it is done. Windows written with [`Epd2in9b::update_partial_color_frame`]
+//! are shown by the next full refresh, the OTP of the panel has no quick waveform
+//! for three colors.
+//!
+//! # Example for the 2.9" (B) V3 E-Ink Display
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in9b_v3::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in9b::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// One display holds both planes
+//!let mut display = Display2in9b_v3::default();
+//!let _ = display.clear(TriColor::White);
+//!
+//!// A black line
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+//!    .draw(&mut display);
+//!
+//!// A red line
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_color_frame(&mut spi, &mut delay, display.bw_buffer(), display.chromatic_buffer())?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [Waveshare Wiki](https://www.waveshare.com/wiki/2.9inch_e-Paper_Module_(B)_Manual)
+//! - [SSD1680 Datasheet](https://www.waveshare.com/w/upload/b/b3/SSD1680_Datasheet.pdf)
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::{Operation, RefreshLimits};
+use crate::color::{Plane, PlaneConvention, TriColor};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::{buffer_len, line_bytes};
+
+/// Full size buffer for use with the 2.9" (B) V3 EPD
+#[cfg(feature = "graphics")]
+#[allow(non_camel_case_types)]
+pub type Display2in9b_v3 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) * 2 },
+    TriColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 128;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in9b_v3",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane, with the red RAM shown as is
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsChromatic;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Both RAMs shown as is, the sources S8 to S167 are used
+const RAM_CONTENT_NORMAL: [u8; 2] = [0x00, 0x80];
+/// Border waveform following the white LUT
+const BORDER_WAVEFORM: u8 = 0x05;
+
+/// Epd2in9b V3 driver
+pub struct Epd2in9b<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: TriColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in9b {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        // 296 gates, scanned from G0 upwards
+        let [low, high] = gate(HEIGHT - 1);
+        self.cmd_with_data(spi, Command::DriverOutputControl, &[low, high, 0x00])?;
+
+        // x and y increment
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_WAVEFORM])?;
+
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+
+        self.cmd_with_data(spi, Command::DisplayUpdateControl1, &RAM_CONTENT_NORMAL)?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.command(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(false);
+        self.interface.start_operation(Operation::FullRefresh);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
+    }
+
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
+    }
+
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface.cmd(spi, Command::WriteRamRed)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = TriColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize) * 2;
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        Ok(())
+    }
+
+    /// Writes the black/white plane, the red RAM is cleared
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, frame)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRamRed)?;
+        self.interface
+            .data_x_times(spi, background, line_bytes(WIDTH) * HEIGHT)
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` of the black/white RAM, x needs to be
+    /// a multiple of 8
+    ///
+    /// See [`Epd2in9b::update_partial_color_frame`] for the red RAM.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_color_frame(spi, delay, Some(buffer), None, x, y, width, height)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        let pixel_count = line_bytes(WIDTH) * HEIGHT;
+        let bw = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        let chromatic = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.command(spi, Command::WriteRamBlackWhite)?;
+        self.interface.data_x_times(spi, bw, pixel_count)?;
+
+        self.set_counters(spi, 0, 0)?;
+        self.command(spi, Command::WriteRamRed)?;
+        self.interface.data_x_times(spi, chromatic, pixel_count)
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// The controller only has the full refresh of the OTP for three colors
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRamBlackWhite, black, 0..black.len())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, 0, 0, WIDTH, HEIGHT)?;
+        self.interface
+            .cmd_with_frame(spi, Command::WriteRamRed, chromatic, 0..chromatic.len())
+    }
+
+    /// Reads the 10 byte user ID from the OTP of the controller
+    ///
+    /// Lets the firmware tell panel batches (e.g. with different waveforms) apart. The
+    /// controller answers on its data line, which has to be connected to MISO as well,
+    /// the Waveshare boards only connect it to MOSI. Not available in deep sleep.
+    pub fn read_user_id(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        out: &mut [u8; 10],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::UserIdRead)?;
+        self.interface.read(spi, out)
+    }
+
+    /// Updates the planes of a window, planes passed as `None` are left untouched
+    ///
+    /// Both buffers cover the window of `width` x `height` pixels at (x, y), with rows
+    /// of [line_bytes(width)](crate::line_bytes) bytes. x needs to be a multiple of 8.
+    /// The changes are shown by the next [`display_frame`](WaveshareDisplay::display_frame).
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: Option<&[u8]>,
+        chromatic: Option<&[u8]>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        assert!(x.is_multiple_of(8));
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+
+        self.wait_until_idle(spi, delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        if let Some(black) = black {
+            assert!((line_bytes(width) * height) as usize == black.len());
+            self.cmd_with_data(spi, Command::WriteRamBlackWhite, black)?;
+        }
+        if let Some(chromatic) = chromatic {
+            assert!((line_bytes(width) * height) as usize == chromatic.len());
+            self.set_counters(spi, x, y)?;
+            self.cmd_with_data(spi, Command::WriteRamRed, chromatic)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the RAM window to `width` x `height` pixels at `x`, `y` and moves the
+    /// address counters to its start
+    ///
+    /// Horizontally the controller counts bytes, a row of the panel ends in the 16th.
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(x / 8) as u8, ((x + width - 1) / 8) as u8],
+        )?;
+        let [y_start, y_start_high] = gate(y);
+        let [y_end, y_end_high] = gate(y + height - 1);
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[y_start, y_start_high, y_end, y_end_high],
+        )?;
+        self.set_counters(spi, x, y)
+    }
+
+    fn set_counters(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x / 8) as u8])?;
+        self.cmd_with_data(spi, Command::SetRamYAddressCounter, &gate(y))
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+}
+
+/// 9 bit gate address, the low byte first and then bit 8
+fn gate(value: u32) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8 & 0x01]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(line_bytes(WIDTH), 16);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        Epd2in9b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            bus.commands(),
+            [0x12, 0x01, 0x11, 0x44, 0x45, 0x4E, 0x4F, 0x3C, 0x18, 0x21]
+        );
+        // 296 gates, the 9th bit in the second byte
+        assert_eq!(bus.data_after(0x01).unwrap(), [0x27, 0x01, 0x00]);
+        // 128 pixels fill 16 bytes of a row
+        assert_eq!(bus.data_after(0x44).unwrap(), [0x00, 0x0F]);
+        assert_eq!(bus.data_after(0x45).unwrap(), [0x00, 0x00, 0x27, 0x01]);
+        // the red RAM is not inverted
+        assert_eq!(bus.data_after(0x21).unwrap(), [0x00, 0x80]);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn plane_encoding() {
+        use embedded_graphics_core::{prelude::*, Pixel};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        let mut display = Display2in9b_v3::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::Black));
+        display.set_pixel(Pixel(Point::new(9, 0), TriColor::Chromatic));
+
+        bus.clear();
+        epd.update_color_frame(
+            &mut spi,
+            &mut delay,
+            display.bw_buffer(),
+            display.chromatic_buffer(),
+        )
+        .unwrap();
+
+        let bw = bus.data_after(Command::WriteRamBlackWhite as u8).unwrap();
+        assert_eq!(bw.len(), 4736);
+        assert_eq!(bw[..2], [0x7F, 0xFF]);
+        assert!(bw[2..].iter().all(|&b| b == 0xFF));
+        let red = bus.data_after(Command::WriteRamRed as u8).unwrap();
+        assert_eq!(red.len(), 4736);
+        assert_eq!(red[..2], [0x00, 0x40]);
+        assert!(red[2..].iter().all(|&b| b == 0x00));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn banded_frame_matches_full_planes() {
+        use crate::graphics::PartialChromaticDisplay;
+        use embedded_graphics::{
+            prelude::*,
+            primitives::{PrimitiveStyle, Rectangle},
+        };
+
+        let record = |banded: bool| {
+            let bus = Bus::new();
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let mut epd = Epd2in9b::new(
+                &mut spi,
+                bus.busy(false),
+                bus.dc(),
+                Pin::new(),
+                &mut delay,
+                None,
+            )
+            .unwrap();
+            // red only in the rows 40..80
+            let banner = Rectangle::new(Point::new(4, 41), Size::new(100, 30))
+                .into_styled(PrimitiveStyle::with_fill(TriColor::Chromatic));
+            let frame = Rectangle::new(Point::new(2, 2), Size::new(120, 290))
+                .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1));
+            bus.clear();
+            if banded {
+                let mut display = PartialChromaticDisplay::<
+                    WIDTH,
+                    HEIGHT,
+                    40,
+                    false,
+                    { buffer_len(128, 336) },
+                >::new(40);
+                display.clear(TriColor::White).unwrap();
+                banner.draw(&mut display).unwrap();
+                frame.draw(&mut display).unwrap();
+                let band = display.chromatic_buffer();
+                assert_eq!(band.fill_lens(WIDTH, HEIGHT), Some((640, 3456)));
+                epd.update_color_frame_banded(&mut spi, &mut delay, display.bw_buffer(), band)
+                    .unwrap();
+            } else {
+                let mut display = Display2in9b_v3::default();
+                display.clear(TriColor::White).unwrap();
+                banner.draw(&mut display).unwrap();
+                frame.draw(&mut display).unwrap();
+                epd.update_color_frame(
+                    &mut spi,
+                    &mut delay,
+                    display.bw_buffer(),
+                    display.chromatic_buffer(),
+                )
+                .unwrap();
+            }
+            bus
+        };
+
+        let (full, banded) = (record(false), record(true));
+        assert_eq!(banded.commands(), full.commands());
+        let red = banded.data_after(Command::WriteRamRed as u8).unwrap();
+        assert_eq!(red.len(), 4736);
+        assert_eq!(red, full.data_after(Command::WriteRamRed as u8).unwrap());
+        assert_eq!(
+            banded.data_after(Command::WriteRamBlackWhite as u8),
+            full.data_after(Command::WriteRamBlackWhite as u8)
+        );
+
+        // a band beyond the panel is refused before anything is sent
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+        let band = ChromaticBand {
+            data: &[0; 16 * 40],
+            start_row: 257,
+        };
+        assert_eq!(
+            epd.update_color_frame_banded(&mut spi, &mut delay, &[0xFF; 4736], band),
+            Err(Error::InvalidWindow)
+        );
+        assert!(bus.commands().is_empty());
+    }
+
+    #[test]
+    fn partial_window() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in9b::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        // x 8..=27 and y 260..=289 past the 8 bit gates, rows of 3 bytes
+        let black = [0x0F; 3 * 30];
+        let red = [0xF0; 3 * 30];
+        epd.update_partial_color_frame(
+            &mut spi,
+            &mut delay,
+            Some(&black),
+            Some(&red),
+            8,
+            260,
+            20,
+            30,
+        )
+        .unwrap();
+        assert_eq!(
+            bus.data_after(Command::SetRamXAddressStartEndPosition as u8)
+                .unwrap(),
+            [0x01, 0x03]
+        );
+        assert_eq!(
+            bus.data_after(Command::SetRamYAddressStartEndPosition as u8)
+                .unwrap(),
+            [0x04, 0x01, 0x21, 0x01]
+        );
+        // the counters are moved back to the window for the red RAM
+        assert_eq!(bus.count(Command::SetRamXAddressCounter as u8), 2);
+        assert_eq!(
+            bus.data_after_nth(Command::SetRamYAddressCounter as u8, 1)
+                .unwrap(),
+            [0x04, 0x01]
+        );
+        assert_eq!(
+            bus.data_after(Command::WriteRamBlackWhite as u8).unwrap(),
+            black
+        );
+        assert_eq!(bus.data_after(Command::WriteRamRed as u8).unwrap(), red);
+
+        // only the black/white RAM through the trait
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut delay, &black, 8, 260, 20, 30)
+            .unwrap();
+        assert_eq!(bus.count(Command::WriteRamRed as u8), 0);
+    }
+}
@@ -1,9 +1,10 @@
 //! SPI Commands for the Waveshare 2.9" (B/C) E-Ink Display
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     PanelSetting = 0x00,
 
     PowerSetting = 0x01,
@@ -27,6 +28,9 @@ pub(crate) enum Command {
     VcomAndDataIntervalSetting = 0x50,
     ResolutionSetting = 0x61,
     VcmDcSetting = 0x82,
+    PartialWindow = 0x90,
+    PartialIn = 0x91,
+    PartialOut = 0x92,
     PowerSaving = 0xE3,
 }
 
@@ -35,4 +39,57 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff
+            | Command::PowerOn
+            | Command::DisplayRefresh
+            | Command::PartialIn
+            | Command::PartialOut => DataLen::Exact(0),
+            Command::PanelSetting
+            | Command::DeepSleep
+            | Command::PllControl
+            | Command::TemperatureSensorSelection
+            | Command::VcomAndDataIntervalSetting
+            | Command::VcmDcSetting
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::PartialWindow => DataLen::Exact(7),
+            Command::LutForVcom => DataLen::Exact(44),
+            Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutWhiteToBlack
+            | Command::LutBlackToBlack => DataLen::Exact(42),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::DataStartTransmission1 => Some(Plane::BlackWhite),
+            Command::DataStartTransmission2 => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutForVcom.expected_data_len().accepts(44));
+        assert!(!Command::LutForVcom.expected_data_len().accepts(42));
+        assert!(Command::ResolutionSetting.expected_data_len().accepts(3));
+        assert!(!Command::ResolutionSetting.expected_data_len().accepts(4));
+        assert!(Command::PowerSetting.expected_data_len().accepts(4));
+        assert!(!Command::PowerSetting.expected_data_len().accepts(6));
+        assert!(Command::DataStartTransmission1
+            .expected_data_len()
+            .accepts(4736));
+    }
 }
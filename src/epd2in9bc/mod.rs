@@ -1,10 +1,12 @@
 //! A simple Driver for the Waveshare 2.9" (B/C) E-Ink Display via SPI
 //!
+//! The SSD1680 based 2.9" (B) V3 module needs [crate::epd2in9b_v3] instead.
+//!
 //! # Example for the 2.9" E-Ink Display
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -56,21 +58,51 @@
 //!```
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, QuickRefresh, RefreshLut,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
+use crate::vcom;
 
 /// Width of epd2in9bc in pixels
 pub const WIDTH: u32 = 128;
 /// Height of epd2in9bc in pixels
 pub const HEIGHT: u32 = 296;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in9bc",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default background color (white) of epd2in9bc display
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(15_000_000, 20_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 
-const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+const NUM_DISPLAY_BITS: u32 = line_bytes(WIDTH) * HEIGHT;
 
 const IS_BUSY_LOW: bool = true;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsNotChromatic;
+/// Tri-color mode with the LUT from the OTP
+const PANEL_SETTING: u8 = 0x8F;
+/// b/w mode with the LUT from the registers, for quick refreshes
+const QUICK_PANEL_SETTING: u8 = 0xBF;
 const VCOM_DATA_INTERVAL: u8 = 0x07;
 const WHITE_BORDER: u8 = 0x70;
 const BLACK_BORDER: u8 = 0x30;
@@ -78,11 +110,15 @@ const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::{Color, TriColor};
+use crate::color::{Color, Plane, PlaneConvention, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+
+mod constants;
+use self::constants::*;
+use crate::uc81xx::{self, Coordinates, Frame, PartialWindow};
+use crate::{buffer_len, line_bytes};
 
 /// Full size buffer for use with the 2in9b/c EPD
 /// TODO this should be a TriColor, but let's keep it as is at first
@@ -99,6 +135,14 @@ pub type Display2in9bc = crate::graphics::Display<
 pub struct Epd2in9bc<SPI, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     color: Color,
+    /// Border bits of VcomAndDataIntervalSetting, restored after partial updates
+    border: u8,
+    /// LUT of window refreshes and `display_new_frame`
+    refresh: RefreshLut,
+    /// The controller is in the b/w mode with the quick LUT
+    quick_lut_loaded: bool,
+    /// The booster is on, see [`Epd2in9bc::power_off`]
+    powered: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -110,10 +154,37 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in9bc {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            border: WHITE_BORDER,
+            refresh: RefreshLut::Full,
+            quick_lut_loaded: false,
+            powered: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // start the booster
         self.interface
@@ -123,10 +194,13 @@ where
         self.command(spi, Command::PowerOn)?;
         delay.delay_us(5000);
         self.wait_until_idle(spi, delay)?;
+        self.powered = true;
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x8F])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[PANEL_SETTING])?;
+        self.quick_lut_loaded = false;
 
+        self.border = WHITE_BORDER;
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
@@ -136,12 +210,27 @@ where
         // set resolution
         self.send_resolution(spi)?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])?;
+        let vcom = self.interface.vcom_or(0x0A);
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
 
         self.wait_until_idle(spi, delay)?;
 
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.ensure_powered(spi, delay)?;
+        self.load_lut(spi, false)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -159,9 +248,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        chromatic.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     /// Update only the black/white data of the display.
@@ -170,12 +277,10 @@ where
     fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data(spi, black)?;
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     /// Update only chromatic data of the display.
@@ -186,9 +291,26 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
@@ -205,6 +327,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -212,18 +337,12 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in9bc { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -232,6 +351,7 @@ where
         )?;
 
         self.command(spi, Command::PowerOff)?;
+        self.powered = false;
         // The example STM code from Github has a wait after PowerOff
         self.wait_until_idle(spi, delay)?;
 
@@ -240,10 +360,17 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.powered = false;
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, color: Color) {
         self.color = color;
     }
@@ -265,13 +392,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        self.interface.data(spi, buffer)?;
+        self.interface.data_frame(spi, frame, 0..frame.len())?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
@@ -280,7 +418,9 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Updates the black/white data of a window, the chromatic data is kept
+    ///
+    /// See [`Epd2in9bc::update_partial_color_frame`].
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -290,15 +430,19 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.update_partial_color_frame(spi, delay, Some(buffer), None, x, y, width, height)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.command(spi, Command::DisplayRefresh)?;
-
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    /// Refreshes the whole display in three colors, also with the quick LUT selected
+    ///
+    /// After quick refreshes both planes have to be sent again, in the b/w mode the
+    /// chromatic RAM holds the new b/w frame.
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -306,45 +450,187 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.send_resolution(spi)?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
         // Clear the black
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
         // Clear the chromatic
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        let color = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
 
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        QuickRefresh::clear_partial_frame(self, spi, delay, x, y, width, height)
+    }
+
+    /// Selects the LUT of [`Epd2in9bc::display_partial_frame`] and of the
+    /// [QuickRefresh] refreshes, `display_frame` always uses the full one
+    ///
+    /// The LUT is uploaded with the next refresh needing it.
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
         _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
 }
 
+/// Quick refreshes in b/w, e.g. of a clock in a layout drawn in three colors before
+///
+/// The controller is switched to its b/w mode, where the black/white RAM holds the old
+/// frame and the chromatic RAM the new one. Only pixels changing between both are
+/// driven, so chromatic pixels outside of the refreshed window or unchanged in both
+/// frames keep their color. The b/w mode overwrites the chromatic RAM though, send both
+/// planes again before the next (tri-color) [`display_frame`](WaveshareDisplay::display_frame).
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        uc81xx::write_frame(&mut self.interface, spi, Frame::Old, buffer)
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        uc81xx::write_frame(&mut self.interface, spi, Frame::New, buffer)
+    }
+
+    /// Quick refresh of the whole display, see [`Epd2in9bc::display_partial_frame`] for a
+    /// window
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.load_lut(spi, true)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(true);
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_partial_color_frame(spi, delay, Some(buffer), None, x, y, width, height)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_partial_color_frame(spi, delay, None, Some(buffer), x, y, width, height)
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        let bytes = line_bytes(width) * height;
+        self.enter_partial(spi, x, y, width, height)?;
+        uc81xx::fill_frame(&mut self.interface, spi, Frame::Old, color, bytes)?;
+        uc81xx::fill_frame(&mut self.interface, spi, Frame::New, color, bytes)?;
+        self.leave_partial(spi)
+    }
+}
+
 impl<SPI, BUSY, DC, RST, DELAY> Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
@@ -353,12 +639,71 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_frame(spi, black, 0..black.len())?;
+        Ok(())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_frame(spi, chromatic, 0..chromatic.len())?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd2in9bc::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd2in9bc::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
     }
 
     fn cmd_with_data(
@@ -366,32 +711,350 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::ResolutionSetting)?;
-
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[w as u8, (h >> 8) as u8, h as u8],
+        )
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), Error<SPI::Error>> {
         let border = match color {
             TriColor::Black => BLACK_BORDER,
             TriColor::White => WHITE_BORDER,
             TriColor::Chromatic => CHROMATIC_BORDER,
         };
+        self.border = border;
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
             &[border | VCOM_DATA_INTERVAL],
         )
     }
+
+    /// Updates the planes of a window, planes passed as `None` are left untouched
+    ///
+    /// Both buffers cover the window of `width` x `height` pixels at (x, y), with rows
+    /// of [line_bytes(width)](crate::line_bytes) bytes. x is rounded down to a multiple
+    /// of 8. Show the changes with [`Epd2in9bc::display_partial_frame`] or a full
+    /// [`display_frame`](WaveshareDisplay::display_frame).
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: Option<&[u8]>,
+        chromatic: Option<&[u8]>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.enter_partial(spi, x, y, width, height)?;
+        if let Some(black) = black {
+            uc81xx::write_frame(&mut self.interface, spi, Frame::Old, black)?;
+        }
+        if let Some(chromatic) = chromatic {
+            uc81xx::write_frame(&mut self.interface, spi, Frame::New, chromatic)?;
+        }
+        self.leave_partial(spi)
+    }
+
+    /// Refreshes only the window at (x, y), the rest of the display is left as is
+    ///
+    /// With the quick LUT selected by `set_lut` the window is refreshed in b/w from the
+    /// old frame to the new frame, see [QuickRefresh], otherwise in three colors.
+    pub fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.load_lut(spi, self.refresh == RefreshLut::Quick)?;
+        self.enter_partial(spi, x, y, width, height)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_partial_refresh();
+        self.wait_until_idle(spi, delay)?;
+        self.leave_partial(spi)
+    }
+
+    /// Selects the window and lets the border float, otherwise it flickers
+    /// with every partial update
+    fn enter_partial(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[FLOATING_BORDER | VCOM_DATA_INTERVAL],
+        )?;
+        uc81xx::enter_partial(
+            &mut self.interface,
+            spi,
+            &partial_window(x, y, width, height),
+        )
+    }
+
+    /// Leaves the partial mode and restores the border
+    fn leave_partial(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        uc81xx::leave_partial(&mut self.interface, spi)?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[self.border | VCOM_DATA_INTERVAL],
+        )
+    }
+
+    /// Turns the booster off while keeping the panel configuration
+    ///
+    /// Between frequent refreshes (e.g. once a minute) this is preferable to
+    /// [`sleep`](WaveshareDisplay::sleep), the controller resumes without a reset and
+    /// keeps the frames in its RAM. The next refresh powers the booster on again by
+    /// itself.
+    pub fn power_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::PowerOff)?;
+        self.powered = false;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    /// Turns the booster on again after [`power_off`](Self::power_off)
+    pub fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_us(5000);
+        self.wait_until_idle(spi, delay)?;
+        self.powered = true;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    fn ensure_powered(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.powered {
+            self.power_on(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Switches between the tri-color mode with the LUT from the OTP and the b/w mode
+    /// with the quick LUT, nothing is sent if the mode doesn't change
+    fn load_lut(&mut self, spi: &mut SPI, quick: bool) -> Result<(), Error<SPI::Error>> {
+        if quick == self.quick_lut_loaded {
+            return Ok(());
+        }
+        if quick {
+            self.cmd_with_data(spi, Command::PanelSetting, &[QUICK_PANEL_SETTING])?;
+            self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_QUICK)?;
+            self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW_QUICK)?;
+            self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW_QUICK)?;
+            self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB_QUICK)?;
+            self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB_QUICK)?;
+        } else {
+            self.cmd_with_data(spi, Command::PanelSetting, &[PANEL_SETTING])?;
+        }
+        self.quick_lut_loaded = quick;
+        Ok(())
+    }
+}
+
+/// Data of PartialWindow for the window at (x, y)
+///
+/// Unlike the 4.2" the horizontal positions take one byte each (bits 7:3, the end
+/// ends with 0b111), and the vertical ones are 9 bits split into two bytes.
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> PartialWindow {
+    PartialWindow::new(Coordinates::Byte, x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, BusyPin, DcPin, Delay, Pin, Spi};
+
+    fn epd(
+        bus: &Bus,
+        spi: &mut Spi,
+        delay: &mut Delay,
+    ) -> Epd2in9bc<Spi, BusyPin, DcPin, Pin, Delay> {
+        Epd2in9bc::new(spi, bus.busy(true), bus.dc(), Pin::new(), delay, None).unwrap()
+    }
+
+    #[test]
+    fn partial_window_encoding() {
+        // y ends at 279, above 8 bits
+        assert_eq!(
+            partial_window(120, 200, 8, 80).params(),
+            [0x78, 0x7F, 0x00, 0xC8, 0x01, 0x17, 0x01]
+        );
+        // x is rounded down to the byte
+        assert_eq!(
+            partial_window(13, 0, 16, 1).params(),
+            [8, 23, 0, 0, 0, 0, 0x01]
+        );
+    }
+
+    #[test]
+    fn banded_chromatic_plane_is_filled_with_background() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+
+        bus.clear();
+        let band = ChromaticBand {
+            data: &[0x5A; 16 * 20],
+            start_row: 100,
+        };
+        epd.update_color_frame_banded(&mut spi, &mut delay, &[0xFF; 4736], band)
+            .unwrap();
+        let chromatic = bus.data_after(0x13).unwrap();
+        // as many bytes as the full plane, 0 is chromatic on this panel
+        assert_eq!(chromatic.len(), 4736);
+        assert!(chromatic[..1600].iter().all(|&b| b == 0xFF));
+        assert!(chromatic[1600..1920].iter().all(|&b| b == 0x5A));
+        assert!(chromatic[1920..].iter().all(|&b| b == 0xFF));
+        assert_eq!(bus.data_after(0x10).unwrap(), [0xFF; 4736]);
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn plane_checksums() {
+        use crate::checksum::crc16;
+        use crate::traits::DriverControl;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+
+        let black: [u8; 4736] = core::array::from_fn(|i| (i % 251) as u8);
+        let chromatic: [u8; 4736] = core::array::from_fn(|i| (i % 13) as u8);
+        epd.update_color_frame(&mut spi, &mut delay, &black, &chromatic)
+            .unwrap();
+        assert_eq!(epd.plane_checksum(Plane::BlackWhite), crc16(&black));
+        assert_eq!(epd.plane_checksum(Plane::Chromatic), crc16(&chromatic));
+        assert_eq!(epd.last_transfer_checksum(), crc16(&chromatic));
+
+        // the plane the display shows as black/white is covered by update_frame too
+        epd.update_frame(&mut spi, &black, &mut delay).unwrap();
+        assert_eq!(epd.plane_checksum(Plane::BlackWhite), crc16(&black));
+        assert_eq!(epd.take_checksum(), crc16(&[0xFF; 4736]));
+        assert_eq!(
+            epd.plane_checksum(Plane::BlackWhite),
+            crate::checksum::CRC16_INIT
+        );
+    }
+
+    #[test]
+    fn partial_update_sends_only_the_given_plane() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+        epd.set_border_color(&mut spi, TriColor::Chromatic).unwrap();
+
+        bus.clear();
+        let black = [0xAB; 80];
+        epd.update_partial_frame(&mut spi, &mut delay, &black, 120, 200, 8, 80)
+            .unwrap();
+        assert_eq!(bus.commands(), [0x50, 0x91, 0x90, 0x10, 0x92, 0x50]);
+        assert_eq!(
+            bus.data_after(0x90).unwrap(),
+            [0x78, 0x7F, 0x00, 0xC8, 0x01, 0x17, 0x01]
+        );
+        assert_eq!(bus.data_after(0x10).unwrap(), black);
+        // floating border during the partial mode, restored afterwards
+        assert_eq!(
+            bus.data_after_nth(0x50, 0).unwrap(),
+            [FLOATING_BORDER | VCOM_DATA_INTERVAL]
+        );
+        assert_eq!(
+            bus.data_after_nth(0x50, 1).unwrap(),
+            [CHROMATIC_BORDER | VCOM_DATA_INTERVAL]
+        );
+
+        bus.clear();
+        let chromatic = [0x0F; 80];
+        epd.update_partial_color_frame(
+            &mut spi,
+            &mut delay,
+            None,
+            Some(&chromatic),
+            120,
+            200,
+            8,
+            80,
+        )
+        .unwrap();
+        assert_eq!(bus.count(0x10), 0);
+        assert_eq!(bus.data_after(0x13).unwrap(), chromatic);
+
+        bus.clear();
+        epd.display_partial_frame(&mut spi, &mut delay, 120, 200, 8, 80)
+            .unwrap();
+        assert_eq!(bus.commands(), [0x50, 0x91, 0x90, 0x12, 0x92, 0x50]);
+        assert!(bus.waited_after(0x12));
+    }
+
+    #[test]
+    fn quick_lut_is_loaded_once() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = epd(&bus, &mut spi, &mut delay);
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+
+        bus.clear();
+        for _ in 0..2 {
+            epd.display_partial_frame(&mut spi, &mut delay, 0, 0, 8, 8)
+                .unwrap();
+        }
+        assert_eq!(bus.data_after(0x00).unwrap(), [QUICK_PANEL_SETTING]);
+        assert_eq!(bus.count(0x20), 1);
+
+        // back to three colors, the booster is turned on again after power_off
+        epd.power_off(&mut spi, &mut delay).unwrap();
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x04, 0x00, 0x12]);
+        assert_eq!(bus.data_after(0x00).unwrap(), [PANEL_SETTING]);
+    }
 }
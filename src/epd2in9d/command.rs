@@ -1,9 +1,9 @@
 //! SPI Commands for the Waveshare 2.9" FLEXIBLE E-PAPER DISPLAY
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     PanelSetting = 0x00,
     /// selecting internal and external power
     ///    self.send_data(0x03)?; //VDS_EN, VDG_EN
@@ -147,4 +147,57 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff
+            | Command::PowerOn
+            | Command::PowerOnMeasure
+            | Command::DataStop
+            | Command::DisplayRefresh
+            | Command::PartialIn
+            | Command::PartialOut
+            | Command::ActiveProgramming => DataLen::Exact(0),
+            Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::PllControl
+            | Command::TemperatureSensorSelection
+            | Command::TemperatureSensorWrite
+            | Command::VcomAndDataIntervalSetting
+            | Command::LowPowerDetection
+            | Command::TconSetting
+            | Command::AutoMeasurementVcom
+            | Command::VcmDcSetting
+            | Command::ProgramMode
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::PanelSetting => DataLen::Max(2),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::PartialWindow => DataLen::Exact(7),
+            Command::LutForVcom => DataLen::Exact(44),
+            Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutWhiteToBlack
+            | Command::LutBlackToBlack => DataLen::Exact(42),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutForVcom.expected_data_len().accepts(44));
+        assert!(!Command::LutForVcom.expected_data_len().accepts(42));
+        assert!(Command::LutBlackToBlack.expected_data_len().accepts(42));
+        assert!(!Command::LutBlackToBlack.expected_data_len().accepts(44));
+        assert!(Command::PanelSetting.expected_data_len().accepts(1));
+        assert!(!Command::PanelSetting.expected_data_len().accepts(3));
+        assert!(Command::PartialWindow.expected_data_len().accepts(7));
+        assert!(!Command::PartialWindow.expected_data_len().accepts(9));
+    }
 }
@@ -13,8 +13,15 @@ use embedded_hal::{
     spi::SpiDevice,
 };
 
+use crate::busy::RefreshLimits;
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+use crate::voltage::DrivingVoltages;
 
 //The Lookup Tables for the Display
 mod constants;
@@ -24,19 +31,37 @@ use crate::epd2in9d::constants::*;
 pub const WIDTH: u32 = 128;
 /// Height of Epd2in9d in pixels
 pub const HEIGHT: u32 = 296;
-/// EPD_ARRAY of Epd2in9d in pixels
-/// WIDTH / 8 * HEIGHT
-pub const EPD_ARRAY: u32 = 4736;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd2in9d",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
+/// EPD_ARRAY of Epd2in9d in bytes
+pub const EPD_ARRAY: u32 = line_bytes(WIDTH) * HEIGHT;
 /// Default Background Color (white)
-pub const DEFAULT_BACKGROUND_COLOR: Color = Color::Black;
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(2_000_000, 4_000_000).with_quick(300_000, 1_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
 
-use crate::color::Color;
+use crate::color::{Color, Plane, PlaneConvention};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, line_bytes};
 
 /// Display with Fullsize buffer for use with the 2in9 EPD D
 #[cfg(feature = "graphics")]
@@ -62,6 +87,8 @@ pub struct Epd2in9d<'a, SPI, BUSY, DC, RST, DELAY> {
     old_data: &'a [u8],
     // 标记是否局刷的状态
     is_partial_refresh: bool,
+    /// Whether the booster is running, see [`Epd2in9d::power_off`]
+    powered: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -73,8 +100,35 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 2_000);
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd2in9d {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            old_data: &[],
+            is_partial_refresh: false,
+            powered: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
 
         //panel setting
         //LUT from OTP，KW-BF   KWR-AF	BWROTP 0f	BWOTP 1f
@@ -85,8 +139,7 @@ where
         self.interface
             .cmd_with_data(spi, Command::ResolutionSetting, &[0x80, 0x01, 0x28])?;
 
-        self.interface.cmd(spi, Command::PowerOn)?;
-        self.wait_until_idle(spi, delay)?;
+        self.power_on(spi, delay)?;
 
         //VCOM AND DATA INTERVAL SETTING
         self.interface
@@ -94,6 +147,23 @@ where
 
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        self.ensure_powered(spi, delay)?;
+        self.interface.cmd(spi, Command::DisplayRefresh)?;
+        self.interface
+            .count_refresh(self.refresh == RefreshLut::Quick);
+        delay.delay_us(1_000);
+        Ok(())
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -106,6 +176,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -113,30 +186,17 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-        let old_data: &[u8] = &[];
-        let is_partial_refresh = false;
-
-        let mut epd = Epd2in9d {
-            interface,
-            color,
-            refresh: RefreshLut::Full,
-            old_data,
-            is_partial_refresh,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.is_partial_refresh = false;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
         self.interface.cmd(spi, Command::PowerOff)?;
+        self.powered = false;
         self.wait_until_idle(spi, delay)?;
         delay.delay_us(100_000);
         self.interface
@@ -145,11 +205,18 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)?;
         Ok(())
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.powered = false;
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, background_color: Color) {
         self.color = background_color;
     }
@@ -173,18 +240,37 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
         if self.is_partial_refresh {
             // Modify local refresh status if full refresh is performed.
             self.is_partial_refresh = false;
         }
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
 
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0xFF, EPD_ARRAY)?;
+        self.interface.data_x_times(spi, color, EPD_ARRAY)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.interface.cmd_with_frame(
+            spi,
+            Command::DataStartTransmission2,
+            frame,
+            0..frame.len(),
+        )?;
+        // a frame in parts isn't kept as the old data of the next partial update
+        let buffer = frame.as_slice().unwrap_or(&[]);
         self.old_data = unsafe { from_raw_parts(buffer.as_ptr(), buffer.len()) };
         Ok(())
     }
@@ -200,25 +286,31 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         if !self.is_partial_refresh {
             // Initialize only on first call
             self.set_part_reg(spi, delay)?;
             self.is_partial_refresh = true;
         }
+        self.ensure_powered(spi, delay)?;
         self.interface.cmd(spi, Command::PartialIn)?;
 
-        self.interface.cmd(spi, Command::PartialWindow)?;
-        self.interface.data(spi, &[(x - x % 8) as u8])?;
-        self.interface
-            .data(spi, &[(((x - x % 8) + width - 1) - 1) as u8])?;
-        self.interface.data(spi, &[(y / 256) as u8])?;
-        self.interface.data(spi, &[(y % 256) as u8])?;
-        self.interface
-            .data(spi, &[((y + height - 1) / 256) as u8])?;
-        self.interface
-            .data(spi, &[((y + height - 1) % 256 - 1) as u8])?;
-        self.interface.data(spi, &[0x28])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x - x % 8) as u8,
+                ((x - x % 8) + width - 1).saturating_sub(1) as u8,
+                (y / 256) as u8,
+                (y % 256) as u8,
+                ((y + height - 1) / 256) as u8,
+                ((y + height - 1) % 256).saturating_sub(1) as u8,
+                0x28,
+            ],
+        )?;
 
         self.interface
             .cmd_with_data(spi, Command::DataStartTransmission1, self.old_data)?;
@@ -231,11 +323,9 @@ where
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DisplayRefresh)?;
-        delay.delay_us(1_000);
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -243,18 +333,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.ensure_powered(spi, delay)?;
+        // the old frame is the inverted background, the new one the background
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0x00, EPD_ARRAY)?;
+        self.interface.data_x_times(spi, !color, EPD_ARRAY)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, 0xFF, EPD_ARRAY)?;
+        self.interface.data_x_times(spi, color, EPD_ARRAY)?;
 
         self.display_frame(spi, delay)?;
 
@@ -266,21 +360,31 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         self.set_lut_helper(
             spi, delay, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1,
         )?;
+        self.interface.restore_operation(operation);
 
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
@@ -291,13 +395,68 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    /// Wake Up Screen
+    /// Replaces the driving voltages of `PowerSetting` sent by `init`/`wake_up`, `None`
+    /// restores the ones of the driver, see [crate::voltage]
     ///
-    /// After the screen sleeps, it enters deep sleep mode. If you need to refresh the screen while in deep sleep mode, you must first execute awaken().
-    /// Wake the screen.
-    // fn awaken(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Takes effect with the next `wake_up`. Returns [Error::InvalidVoltage] without
+    /// changing anything for voltages the controller doesn't support.
+    pub fn set_driving_voltages(
+        &mut self,
+        voltages: Option<DrivingVoltages>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(voltages) = voltages {
+            voltages.uc81xx.registers().map_err(Error::InvalidVoltage)?;
+        }
+        self.interface.set_driving_voltages(voltages);
+        Ok(())
+    }
+
+    /// Turns the booster off while keeping the panel configuration
+    ///
+    /// Between frequent refreshes (e.g. once a minute) this is preferable to
+    /// [`sleep`](WaveshareDisplay::sleep): the controller draws around 50 µA
+    /// instead of about 1 µA in deep sleep, but it resumes instantly without a
+    /// reset or resending the panel and LUT settings.
+    ///
+    /// The next frame update powers the booster on again by itself.
+    pub fn power_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::PowerOff)?;
+        self.powered = false;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    /// Turns the booster on again after [`power_off`](Self::power_off)
+    pub fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.interface.cmd(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.powered = true;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    fn ensure_powered(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.powered {
+            self.power_on(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    // Wake Up Screen
+    //
+    // After the screen sleeps, it enters deep sleep mode. If you need to refresh the screen while in deep sleep mode, you must first execute awaken().
+    // Wake the screen.
+    // fn awaken(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
     //     // reset the device
-    //     self.interface.reset(delay, 20_000, 2_000);
+    //     self.interface.reset(delay, RESET_CONFIG);
     //     self.wait_until_idle(spi, delay)?;
 
     //     // panel setting
@@ -315,18 +474,18 @@ where
     //     Ok(())
     // }
 
-    fn set_part_reg(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_part_reg(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the EPD driver circuit
         //TODO: 这里在微雪的例程中反复刷新了3次，后面有显示问题再进行修改
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // Power settings
         //TODO: The data in the document is [0x03,0x00,0x2b,0x2b,0x09].
-        self.interface.cmd_with_data(
-            spi,
-            Command::PowerSetting,
-            &[0x03, 0x00, 0x2b, 0x2b, 0x03],
-        )?;
+        let power_setting = self
+            .interface
+            .uc81xx_power_setting_or([0x03, 0x00, 0x2b, 0x2b, 0x03]);
+        self.interface
+            .cmd_with_data(spi, Command::PowerSetting, &power_setting)?;
 
         // Soft start
         self.interface
@@ -363,6 +522,7 @@ where
         // Get the BUSY level, high to continue, low to wait for the screen to respond.
         //TODO: This is the recommended step in the documentation, but I've ignored it since I've seen other screens that don't wait.
         self.wait_until_idle(spi, delay)?;
+        self.powered = true;
 
         // vcom and data interval settings
         // self.interface
@@ -381,7 +541,7 @@ where
         lut_bw: &[u8],
         lut_wb: &[u8],
         lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let _ = delay;
         // LUT VCOM
         self.interface
@@ -0,0 +1,147 @@
+//! SPI Commands for the Waveshare 3.52" E-Ink Display
+
+use crate::traits::{self, DataLen};
+
+/// Epd3in52 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum Command {
+    /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0". This command will turn off charge pump,
+    /// T-con, source driver, gate driver, VCOM, and temperature sensor, but register
+    /// data will be kept until VDD becomes OFF.
+    PowerOff = 0x02,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The only parameter is a check code, the command is executed if it is 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting the old frame, compared with the new one by the
+    /// LUTs
+    DataStartTransmission1 = 0x10,
+
+    /// Stopping data transmission
+    DataStop = 0x11,
+
+    /// Refreshes the display according to SRAM data and LUT, without powering it
+    DisplayRefresh = 0x12,
+
+    /// This command starts transmitting the new frame
+    DataStartTransmission2 = 0x13,
+
+    /// Runs power on, refresh and power off in one go, the mode byte 0xA5 selects this
+    /// sequence
+    ///
+    /// BUSY stays "0" until the power off at the end of the sequence is complete.
+    AutoSequence = 0x17,
+
+    /// VCOM LUT, used with the LUTs from the registers only
+    LutForVcom = 0x20,
+
+    /// White to white LUT
+    LutWhiteToWhite = 0x21,
+
+    /// Black to white LUT
+    LutBlackToWhite = 0x22,
+
+    /// White to black LUT
+    LutWhiteToBlack = 0x23,
+
+    /// Black to black LUT
+    LutBlackToBlack = 0x24,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    ResolutionSetting = 0x61,
+
+    /// This command sets VCOM_DC value.
+    VcmDcSetting = 0x82,
+
+    /// Sets the source and VCOM output widths while the controller saves power
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff | Command::PowerOn | Command::DataStop | Command::DisplayRefresh => {
+                DataLen::Exact(0)
+            }
+            Command::DeepSleep
+            | Command::AutoSequence
+            | Command::PllControl
+            | Command::VcomAndDataIntervalSetting
+            | Command::TconSetting
+            | Command::VcmDcSetting
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::PanelSetting => DataLen::Exact(2),
+            Command::BoosterSoftStart | Command::ResolutionSetting => DataLen::Exact(3),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::LutForVcom => DataLen::Exact(56),
+            Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutWhiteToBlack
+            | Command::LutBlackToBlack => DataLen::Exact(42),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::AutoSequence.address(), 0x17);
+        assert_eq!(Command::PowerSaving.address(), 0xE3);
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutForVcom.expected_data_len().accepts(56));
+        assert!(!Command::LutForVcom.expected_data_len().accepts(44));
+        assert!(Command::LutWhiteToBlack.expected_data_len().accepts(42));
+        assert!(!Command::LutWhiteToBlack.expected_data_len().accepts(56));
+        assert!(Command::PanelSetting.expected_data_len().accepts(2));
+        assert!(!Command::PanelSetting.expected_data_len().accepts(1));
+    }
+}
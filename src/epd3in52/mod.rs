@@ -0,0 +1,449 @@
+//! A simple Driver for the Waveshare 3.52" E-Ink Display via SPI
+//!
+//! The controller refreshes in two phases: the waveform of the refresh is loaded into
+//! the LUT registers, then the auto sequence (power on, refresh, power off) is enabled
+//! with a mode byte. The busy pin behaves differently in both of them. Writing the LUT
+//! registers doesn't assert it, but the registers are only taken while the controller
+//! is idle, so the end of the previous sequence is waited for first. The auto sequence
+//! asserts it until the power off at its end, afterwards the panel needs another 200 ms
+//! like in the vendor driver.
+//!
+//! [RefreshLut::Full] refreshes with the GC waveform, [RefreshLut::Quick] with the faster
+//! DU waveform which leaves some ghosting behind. `set_lut` only selects the waveform, it
+//! is loaded with every refresh.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/3.52inch-e-paper-hat.htm)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_3in52.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd3in52.py)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::RefreshLimits;
+use crate::color::{Color, Plane, PlaneConvention};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+mod constants;
+use self::constants::*;
+
+use crate::{buffer_len, line_bytes};
+
+/// Full size buffer for use with the 3in52 EPD
+#[cfg(feature = "graphics")]
+pub type Display3in52 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 240;
+/// Height of the display
+pub const HEIGHT: u32 = 360;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd3in52",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: false,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(200_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits =
+    RefreshLimits::new(3_000_000, 5_000_000).with_quick(500_000, 1_500_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = true;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+const SINGLE_BYTE_WRITE: bool = true;
+/// Mode byte of [Command::AutoSequence] running power on, refresh and power off
+const AUTO_SEQUENCE: u8 = 0xA5;
+/// Time the panel needs after the busy pin was released at the end of a refresh
+const SETTLE_US: u32 = 200_000;
+
+/// Epd3in52 driver
+///
+pub struct Epd3in52<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Waveform of the next refresh
+    refresh: RefreshLut,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd3in52<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd3in52 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+
+        // LUTs from the registers, b/w mode, scanning up and shifting right
+        self.cmd_with_data(spi, Command::PanelSetting, &[0xFF, 0x01])?;
+
+        // VGH=20V, VGL=-20V, VDH=15V, VDL=-15V, VDHR=6.4V
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x10, 0x3F, 0x3F, 0x03])?;
+
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x37, 0x3D, 0x3D])?;
+
+        // S2G and G2S non-overlap periods of 12
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+
+        // VCOM_DC of -0.1V
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x07])?;
+
+        // 50 Hz frame rate
+        self.cmd_with_data(spi, Command::PllControl, &[0x09])?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x88])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+
+        // white border
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xB7])?;
+        Ok(())
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        let quick = self.refresh == RefreshLut::Quick;
+        if !quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        // phase 1: the LUT registers are only taken while the controller is idle
+        self.wait_until_idle(spi, delay)?;
+        self.load_lut(spi, quick)?;
+
+        // phase 2: busy until the power off at the end of the sequence
+        self.cmd_with_data(spi, Command::AutoSequence, &[AUTO_SEQUENCE])?;
+        self.interface.count_refresh(quick);
+        Ok(())
+    }
+
+    fn finish_display_frame(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        delay.delay_us(SETTLE_US);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd3in52<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        // the auto sequence already powered the controller off
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        Ok(())
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_frame(spi, Command::DataStartTransmission2, frame, 0..frame.len())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        unimplemented!()
+    }
+
+    /// Loads the waveform of the [selected LUT](WaveshareDisplay::set_lut) and runs the
+    /// auto sequence, see the [module docs](self) for the busy handling
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+        self.finish_display_frame(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.wait_until_idle(spi, delay)?;
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
+        Ok(())
+    }
+
+    /// Selects the waveform of the following refreshes, [RefreshLut::Full] (and
+    /// [RefreshLut::Custom]) for GC, [RefreshLut::Quick] for DU
+    ///
+    /// Nothing is sent, the waveform is loaded with every refresh.
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd3in52<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    /// Loads the DU waveform for quick refreshes, otherwise the GC one
+    fn load_lut(&mut self, spi: &mut SPI, quick: bool) -> Result<(), Error<SPI::Error>> {
+        let (vcom, ww, bw, wb, bb) = if quick {
+            (&LUT_VCOM_DU, &LUT_WW_DU, &LUT_BW_DU, &LUT_WB_DU, &LUT_BB_DU)
+        } else {
+            (&LUT_VCOM_GC, &LUT_WW_GC, &LUT_BW_GC, &LUT_WB_GC, &LUT_BB_GC)
+        };
+        self.cmd_with_data(spi, Command::LutForVcom, vcom)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, ww)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, bw)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, wb)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, bb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin, Transfer};
+    use crate::policy::RefreshCounters;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 240);
+        assert_eq!(HEIGHT, 360);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn busy_waits_of_both_phases() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd3in52::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0xAA; 10800];
+
+        for (lut, vcom) in [
+            (RefreshLut::Full, LUT_VCOM_GC),
+            (RefreshLut::Quick, LUT_VCOM_DU),
+        ] {
+            epd.set_lut(&mut spi, &mut delay, Some(lut)).unwrap();
+            epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+            bus.clear();
+            epd.display_frame(&mut spi, &mut delay).unwrap();
+
+            assert_eq!(
+                bus.commands(),
+                [0x20, 0x21, 0x22, 0x23, 0x24, Command::AutoSequence as u8]
+            );
+            assert_eq!(bus.data_after(0x20).unwrap(), vcom);
+            assert_eq!(
+                bus.data_after(Command::AutoSequence as u8).unwrap(),
+                [AUTO_SEQUENCE]
+            );
+            // one wait before the LUT registers, one after the auto sequence
+            let transfers = bus.transfers();
+            assert_eq!(transfers.first(), Some(&Transfer::Command(0x20)));
+            assert_eq!(bus.busy_reads(), [0, transfers.len()]);
+        }
+        assert_eq!(delay.delays().last(), Some(&SETTLE_US));
+        assert_eq!(
+            epd.refresh_counters(),
+            RefreshCounters {
+                full: 1,
+                quick: 1,
+                partial: 0
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! SPI Commands for the Waveshare 3.7" E-Ink Display
 
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 /// EPD3IN7 commands
 ///
@@ -11,25 +11,25 @@ use crate::traits;
 /// The description of the single commands is mostly taken from EDP3IN7 specification
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
-    ///
+pub enum Command {
+    /// Gate setting
     GateSetting = 0x01,
-    ///
+    /// Power off
     PowerOff = 0x02,
-    ///
+    /// Deep sleep (alternative)
     Sleep2 = 0x07,
-    ///
+    /// Gate driving voltage
     GateVoltage = 0x03,
-    ///
+    /// Source driving voltage
     GateVoltageSource = 0x04,
-    ///
+    /// Booster soft start control
     BoosterSoftStartControl = 0x0C,
     /// After this command initiated, the chip will enter Deep Sleep Mode,
     /// BUSY pad will keep output high.
     ///
     /// Note: To exit Deep Sleep Mode, User required to send HWRESET to the driver.
     DeepSleep = 0x10,
-    ///
+    /// Data entry mode setting
     DataEntrySequence = 0x11,
     /// This command resets commands and parameters to their S/W Reset default values,
     /// except Deep Sleep Mode.
@@ -55,12 +55,14 @@ pub(crate) enum Command {
     WriteRam = 0x24,
     /// This command writes VCOM register from MCU interface
     WriteVcomRegister = 0x2C,
+    /// Reads the 10 byte user ID stored in the OTP
+    UserIdRead = 0x2E,
     /// This command writes LUT register from MCU interface (105 bytes),
     /// which contains the content of VS [nx-LUT], TP #[nX], RP #[n]
     WriteLutRegister = 0x32,
-    ///
+    /// Display option
     DisplayOption = 0x37,
-    ///
+    /// Border waveform control
     BorderWaveformControl = 0x3C,
     /// This command specifies the start/end positions of the window address in the X direction,
     /// by an address unit of RAM.
@@ -68,15 +70,15 @@ pub(crate) enum Command {
     /// This command specifies the start/end positions of the window address in the Y direction,
     /// by an address unit of RAM.
     SetRamYAddressStartEndPosition = 0x45,
-    ///
+    /// Auto write red RAM for regular pattern
     AutoWriteRedRamRegularPattern = 0x46,
-    ///
+    /// Auto write black/white RAM for regular pattern
     AutoWriteBwRamRegularPattern = 0x47,
     /// This command makes the initial settings for the RAM X address in the address counter (AC)
     SetRamXAddressCounter = 0x4E,
     /// This command makes the initial settings for the RAM Y address in the address counter (AC)
     SetRamYAddressCounter = 0x4F,
-    ///
+    /// Sleep
     Sleep = 0x50,
 }
 
@@ -85,4 +87,51 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff | Command::SwReset | Command::DisplayUpdateSequence => {
+                DataLen::Exact(0)
+            }
+            Command::GateVoltage
+            | Command::Sleep2
+            | Command::DeepSleep
+            | Command::DataEntrySequence
+            | Command::TemperatureSensorSelection
+            | Command::DisplayUpdateSequenceSetting
+            | Command::WriteVcomRegister
+            | Command::BorderWaveformControl
+            | Command::AutoWriteRedRamRegularPattern
+            | Command::AutoWriteBwRamRegularPattern
+            | Command::Sleep => DataLen::Exact(1),
+            Command::SetRamXAddressCounter | Command::SetRamYAddressCounter => DataLen::Exact(2),
+            Command::GateSetting | Command::GateVoltageSource => DataLen::Exact(3),
+            Command::SetRamXAddressStartEndPosition | Command::SetRamYAddressStartEndPosition => {
+                DataLen::Exact(4)
+            }
+            Command::BoosterSoftStartControl => DataLen::Exact(5),
+            Command::DisplayOption => DataLen::Exact(10),
+            Command::WriteLutRegister => DataLen::Exact(105),
+            Command::TemperatureSensorWrite => DataLen::Max(2),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::WriteLutRegister.expected_data_len().accepts(105));
+        assert!(!Command::WriteLutRegister.expected_data_len().accepts(153));
+        assert!(Command::BoosterSoftStartControl
+            .expected_data_len()
+            .accepts(5));
+        assert!(!Command::BoosterSoftStartControl
+            .expected_data_len()
+            .accepts(4));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::color::PlaneConvention;
+
 // This LUT clears the whole display during updates.
 pub(crate) const LUT_1GRAY_GC: [u8; 105] = [
     0x2A, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //1
@@ -27,3 +29,6 @@ pub(crate) const LUT_1GRAY_DU: [u8; 105] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //10
     0x22, 0x22, 0x22, 0x22, 0x22,
 ];
+
+/// Bit sense of the black/white plane
+pub(crate) const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
@@ -0,0 +1,231 @@
+//! Async driver of the 4.2" display, see [AsyncWaveshareDisplay]
+//!
+//! It sends the same sequences as the full refreshes of [Epd4in2](super::Epd4in2) and
+//! shares its commands and LUTs.
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::command::Command;
+use super::constants::*;
+use super::{
+    DEFAULT_BACKGROUND_COLOR, HEIGHT, IS_BUSY_LOW, RESET_CONFIG, SINGLE_BYTE_WRITE, WIDTH,
+};
+use crate::color::{Color, Plane};
+use crate::error::{self, Error};
+use crate::interface_async::AsyncDisplayInterface;
+use crate::line_bytes;
+use crate::traits::AsyncWaveshareDisplay;
+
+/// Async Epd4in2 driver
+///
+/// Refreshes with the full LUT, the quick refreshes and partial updates of
+/// [Epd4in2](super::Epd4in2) aren't available yet.
+pub struct Epd4in2Async<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Whether the booster is running
+    powered: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd4in2Async<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    async fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG).await;
+
+        // set the power settings
+        self.interface
+            .cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b, 0xff])
+            .await?;
+
+        // start the booster
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])
+            .await?;
+
+        self.power_on(spi, delay).await?;
+
+        // set the panel settings
+        self.interface
+            .cmd_with_data(spi, Command::PanelSetting, &[0x3F])
+            .await?;
+
+        // 100 Hz, see Epd4in2
+        self.interface
+            .cmd_with_data(spi, Command::PllControl, &[0x3A])
+            .await?;
+
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::ResolutionSetting,
+                &[
+                    (WIDTH >> 8) as u8,
+                    WIDTH as u8,
+                    (HEIGHT >> 8) as u8,
+                    HEIGHT as u8,
+                ],
+            )
+            .await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])
+            .await?;
+
+        //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])
+            .await?;
+
+        self.set_lut(spi, delay).await?;
+
+        self.wait_until_idle(spi, delay).await
+    }
+
+    async fn power_on(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, Command::PowerOn).await?;
+        delay.delay_us(5000).await;
+        self.wait_until_idle(spi, delay).await?;
+        self.powered = true;
+        Ok(())
+    }
+
+    /// Uploads the LUT of full refreshes
+    async fn set_lut(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        self.wait_until_idle(spi, delay).await?;
+        for (command, lut) in [
+            (Command::LutForVcom, &LUT_VCOM0[..]),
+            (Command::LutWhiteToWhite, &LUT_WW[..]),
+            (Command::LutBlackToWhite, &LUT_BW[..]),
+            (Command::LutWhiteToBlack, &LUT_WB[..]),
+            (Command::LutBlackToBlack, &LUT_BB[..]),
+        ] {
+            self.interface.cmd_with_data(spi, command, lut).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2Async<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut epd = Epd4in2Async {
+            interface: AsyncDisplayInterface::new(busy, dc, rst),
+            color: DEFAULT_BACKGROUND_COLOR,
+            powered: false,
+        };
+        epd.init(spi, delay).await?;
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay).await?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])
+            .await?; //border floating
+        self.interface.cmd(spi, Command::VcmDcSetting).await?; // VCOM to 0V
+        self.interface.cmd(spi, Command::PanelSetting).await?;
+
+        self.interface.cmd(spi, Command::PowerSetting).await?; //VG&VS to 0V fast
+        self.interface.data(spi, &[0x00; 4]).await?;
+
+        self.interface.cmd(spi, Command::PowerOff).await?;
+        self.powered = false;
+        self.wait_until_idle(spi, delay).await?;
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+            .await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay).await?;
+        if !self.powered {
+            self.power_on(spi, delay).await?;
+        }
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+
+        self.interface
+            .cmd(spi, Command::DataStartTransmission1)
+            .await?;
+        self.interface
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)
+            .await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    /// Starts the refresh, like the blocking driver it doesn't wait for the end of it
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.wait_until_idle(spi, delay).await?;
+        if !self.powered {
+            self.power_on(spi, delay).await?;
+        }
+        self.interface.cmd(spi, Command::DisplayRefresh).await
+    }
+
+    async fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(IS_BUSY_LOW).await;
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 //! SPI Commands for the Waveshare 4.2" E-Ink Display
-use crate::traits;
+use crate::traits::{self, DataLen};
 /// EPD4IN2 commands
 ///
 /// Should rarely (never?) be needed directly.
@@ -9,7 +9,7 @@ use crate::traits;
 /// The description of the single commands is mostly taken from IL0398.pdf
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift direction, booster switch, soft reset
     /// One Byte of Data:
     ///     0x0F Red Mode, LUT from OTP
@@ -159,6 +159,42 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff
+            | Command::PowerOn
+            | Command::PowerOnMeasure
+            | Command::DataStop
+            | Command::DisplayRefresh
+            | Command::PartialIn
+            | Command::PartialOut
+            | Command::ActiveProgramming => DataLen::Exact(0),
+            Command::PanelSetting
+            | Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::PllControl
+            | Command::TemperatureSensorSelection
+            | Command::TemperatureSensorWrite
+            | Command::VcomAndDataIntervalSetting
+            | Command::LowPowerDetection
+            | Command::TconSetting
+            | Command::AutoMeasurementVcom
+            | Command::VcmDcSetting
+            | Command::ProgramMode
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::BoosterSoftStart => DataLen::Exact(3),
+            Command::ResolutionSetting => DataLen::Exact(4),
+            Command::PowerSetting => DataLen::Max(5),
+            Command::PartialWindow => DataLen::Exact(9),
+            Command::LutForVcom => DataLen::Exact(44),
+            Command::LutWhiteToWhite
+            | Command::LutBlackToWhite
+            | Command::LutWhiteToBlack
+            | Command::LutBlackToBlack => DataLen::Exact(42),
+            _ => DataLen::Variable,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -174,4 +210,17 @@ mod tests {
 
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::LutForVcom.expected_data_len().accepts(44));
+        assert!(!Command::LutForVcom.expected_data_len().accepts(42));
+        assert!(Command::LutWhiteToWhite.expected_data_len().accepts(42));
+        assert!(!Command::LutWhiteToWhite.expected_data_len().accepts(44));
+        assert!(Command::PartialWindow.expected_data_len().accepts(9));
+        assert!(!Command::PartialWindow.expected_data_len().accepts(7));
+        assert!(Command::DataStartTransmission2
+            .expected_data_len()
+            .accepts(15000));
+    }
 }
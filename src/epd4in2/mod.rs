@@ -9,7 +9,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -51,8 +51,24 @@
 
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
 
+#[cfg(feature = "calibration")]
+use crate::busy::RefreshReport;
+use crate::busy::{BusyMode, BusyTimings, Operation, RefreshLimits};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::reset::ResetConfig;
+#[cfg(feature = "calibration")]
+use crate::traits::DriverControl;
+#[cfg(feature = "graphics")]
+use crate::traits::RegionRefresh;
+use crate::traits::{
+    check_window, InternalWiAdditions, PeriodicUpdateOptions, QuickRefresh, RefreshLut,
+    RowStreaming, WaveshareDisplay,
+};
+use crate::vcom;
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::primitives::Rectangle;
 
 //The Lookup Tables for the Display
 mod constants;
@@ -62,16 +78,60 @@ use crate::epd4in2::constants::*;
 pub const WIDTH: u32 = 400;
 /// Height of the display
 pub const HEIGHT: u32 = 300;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd4in2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: true,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 10_000);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+/// Worst case times for [BusyMode::FixedDelay], from the specification with some margin
+const BUSY_TIMINGS: BusyTimings = BusyTimings {
+    init: 200_000,
+    full_refresh: 5_000_000,
+    partial_refresh: 1_500_000,
+    sleep: 200_000,
+    command: 10_000,
+};
+
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(4_000_000, BUSY_TIMINGS.full_refresh)
+    .with_quick(300_000, BUSY_TIMINGS.partial_refresh);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+
+use crate::color::{Color, Plane};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+pub use self::asynch::Epd4in2Async;
+use crate::uc81xx::{self, Coordinates, Frame, PartialWindow};
+use crate::{buffer_len, line_bytes};
+
+/// The quick LUT of the driver as starting point of a calibration, see
+/// [crate::calibration]
+#[cfg(feature = "calibration")]
+pub const QUICK_LUT: crate::calibration::CustomLut = crate::calibration::CustomLut {
+    vcom: LUT_VCOM0_QUICK,
+    ww: LUT_WW_QUICK,
+    bw: LUT_BW_QUICK,
+    wb: LUT_WB_QUICK,
+    bb: LUT_BB_QUICK,
+};
 
 /// Full size buffer for use with the 4in2 EPD
 #[cfg(feature = "graphics")]
@@ -92,6 +152,12 @@ pub struct Epd4in2<SPI, BUSY, DC, RST, DELAY> {
     color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Whether the booster is running, see [`Epd4in2::power_off`]
+    powered: bool,
+    /// Whether a partial update session is open, see [`Epd4in2::begin_partial`]
+    partial_session: bool,
+    /// Whether the controller is in deep sleep, it lost its configuration then
+    asleep: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -103,9 +169,38 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd4in2 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            powered: false,
+            partial_session: false,
+            asleep: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // reset the device
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, RESET_CONFIG);
+        self.partial_session = false;
+        self.asleep = false;
 
         // set the power settings
         self.interface.cmd_with_data(
@@ -119,9 +214,7 @@ where
             .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
 
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_us(5000);
-        self.wait_until_idle(spi, delay)?;
+        self.power_on(spi, delay)?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])?;
@@ -134,8 +227,9 @@ where
 
         self.send_resolution(spi)?;
 
+        let vcom = self.interface.vcom_or(0x12);
         self.interface
-            .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
 
         //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
         self.interface
@@ -158,6 +252,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -165,22 +262,12 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd4in2 {
-            interface,
-            color,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_busy_mode(spi, busy, dc, rst, delay, delay_us, BusyMode::Pin)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -193,16 +280,26 @@ where
         }
 
         self.command(spi, Command::PowerOff)?;
+        self.interface.start_operation(Operation::Sleep);
+        self.powered = false;
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.asleep = true;
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.powered = false;
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
     fn set_background_color(&mut self, color: Color) {
         self.color = color;
     }
@@ -224,16 +321,33 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
-        let color_value = self.color.get_byte_value();
+        self.ensure_powered(spi, delay)?;
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.interface.cmd_with_frame(
+            spi,
+            Command::DataStartTransmission2,
+            frame,
+            0..frame.len(),
+        )?;
         Ok(())
     }
 
@@ -246,29 +360,24 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
-        if buffer.len() as u32 != width / 8 * height {
+        self.ensure_powered(spi, delay)?;
+        if buffer.len() as u32 != line_bytes(width) * height {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
         self.command(spi, Command::PartialIn)?;
-        self.command(spi, Command::PartialWindow)?;
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
-
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[y as u8])?;
-
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])?;
-        self.send_data(spi, &[(y + height - 1) as u8])?;
-
-        self.send_data(spi, &[0x01])?; // Gates scan both inside and outside of the partial window. (default)
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            partial_window(x, y, width, height).params(),
+        )?;
 
         //TODO: handle dtm somehow
         let is_dtm1 = false;
@@ -284,10 +393,15 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.ensure_no_session()?;
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
         self.wait_until_idle(spi, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.ensure_powered(spi, delay)?;
+        self.refresh(spi, false)
     }
 
     fn update_and_display_frame(
@@ -295,39 +409,55 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
         self.send_resolution(spi)?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
         Ok(())
     }
 
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        QuickRefresh::clear_partial_frame(self, spi, delay, x, y, width, height)
+    }
+
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         match self.refresh {
-            RefreshLut::Full => {
+            RefreshLut::Full | RefreshLut::Custom(_) => {
                 self.set_lut_helper(spi, delay, &LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
             }
             RefreshLut::Quick => self.set_lut_helper(
@@ -339,13 +469,52 @@ where
                 &LUT_WB_QUICK,
                 &LUT_BB_QUICK,
             ),
-        }
+        }?;
+        self.interface.restore_operation(operation);
+        Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        Some(self.refresh)
+    }
+
+    /// The registers and LUTs of the UC8176 survive [`power_off`](Epd4in2::power_off),
+    /// so with a retained configuration the booster is only powered on for the update.
+    /// Deep sleep loses all of them, the display is initialised again after it.
+    /// Without deep sleep the booster is powered off after the refresh.
+    fn periodic_update(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        opts: PeriodicUpdateOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !opts.assume_config_retained || self.asleep {
+            self.interface.set_operation(error::Operation::WakeUp);
+            self.init(spi, delay)?;
+        }
+        let lut = if opts.full_refresh {
+            RefreshLut::Full
+        } else {
+            RefreshLut::Quick
+        };
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame_with(spi, delay, lut)?;
+        if opts.deep_sleep_after {
+            self.sleep(spi, delay)
+        } else {
+            self.power_off(spi, delay)
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> Epd4in2<SPI, BUSY, DC, RST, DELAY>
@@ -356,11 +525,231 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Sets the VCOM voltage printed on the panel in millivolts, e.g. `-2050` for `-2.05V`
+    ///
+    /// Voltages outside of the range of the controller are clamped, see [crate::vcom].
+    /// The value is sent right away and again by every `init`/`wake_up` instead of
+    /// the default. Use [`Epd4in2::new_with_vcom`] to already initialise with it.
+    pub fn set_vcom(&mut self, spi: &mut SPI, millivolts: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom::uc81xx_register(millivolts);
+        self.interface.set_vcom(Some(vcom));
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[vcom])
+    }
+
+    /// Creates the driver like `new`, initialised with the VCOM voltage of the panel
+    ///
+    /// See [`Epd4in2::set_vcom`].
+    pub fn new_with_vcom(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        millivolts: i16,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_vcom(Some(vcom::uc81xx_register(millivolts)));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Same as [new](WaveshareDisplay::new), but selects how to wait for the display
+    ///
+    /// With [BusyMode::FixedDelay] already the initialisation doesn't use the busy
+    /// pin, which can then be a [DummyInputPin](crate::busy::DummyInputPin).
+    /// See [crate::busy] for the drawbacks.
+    pub fn new_with_busy_mode(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        busy_mode: BusyMode,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// Selects whether to poll the busy pin or to wait worst case times, see [crate::busy]
+    pub fn set_busy_mode(&mut self, busy_mode: BusyMode) {
+        self.interface.set_busy_mode(busy_mode, BUSY_TIMINGS);
+    }
+
+    /// Current busy mode
+    pub fn busy_mode(&self) -> BusyMode {
+        self.interface.busy_mode()
+    }
+
+    /// Turns the booster off while keeping the panel configuration
+    ///
+    /// Between frequent refreshes (e.g. once a minute) this is preferable to
+    /// [`sleep`](WaveshareDisplay::sleep): the controller draws around 50 µA
+    /// instead of about 1 µA in deep sleep, but it resumes instantly without a
+    /// reset or resending the power, panel and LUT settings.
+    ///
+    /// The next frame update powers the booster on again by itself.
+    pub fn power_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::PowerOff)?;
+        self.interface.start_operation(Operation::Sleep);
+        self.powered = false;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    /// Turns the booster on again after [`power_off`](Self::power_off)
+    pub fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Power);
+        self.command(spi, Command::PowerOn)?;
+        self.interface.start_operation(Operation::Init);
+        delay.delay_us(5000);
+        self.wait_until_idle(spi, delay)?;
+        self.powered = true;
+        self.interface.restore_operation(operation);
+        Ok(())
+    }
+
+    /// Starts a partial update session
+    ///
+    /// Any number of windows can then be written with
+    /// [`write_window`](Self::write_window) and shown with a single refresh by
+    /// [`end_partial_and_display`](Self::end_partial_and_display). This is a lot
+    /// faster than refreshing after every small window.
+    ///
+    /// While the session is open all other frame updates return
+    /// [`Error::PartialSessionActive`].
+    pub fn begin_partial(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        self.ensure_no_session()?;
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.command(spi, Command::PartialIn)?;
+        self.partial_session = true;
+        Ok(())
+    }
+
+    /// Writes `buffer` into the window at `x`, `y` of a partial update session
+    ///
+    /// `x` and `width` should be multiples of 8. Returns [`Error::InvalidDataLength`]
+    /// unless `buffer` holds exactly `line_bytes(width) * height` bytes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        if buffer.len() as u32 != line_bytes(width) * height {
+            return Err(Error::InvalidDataLength);
+        }
+        if !self.partial_session {
+            return Err(Error::NoPartialSession);
+        }
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::PartialWindow)?;
+        self.shift_display(spi, x, y, width, height)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// Closes the partial update session and refreshes all written windows at once
+    pub fn end_partial_and_display(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        if !self.partial_session {
+            return Err(Error::NoPartialSession);
+        }
+        // a refused refresh leaves the session open, it can be retried
+        if self.refresh != RefreshLut::Quick {
+            self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        }
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.command(spi, Command::PartialOut)?;
+        self.refresh(spi, true)?;
+        self.partial_session = false;
+        Ok(())
+    }
+
+    /// Starts the refresh with the current LUT, `windows` counts it as partial one
+    fn refresh(&mut self, spi: &mut SPI, windows: bool) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::DisplayRefresh)?;
+        if windows {
+            self.interface.count_partial_refresh();
+        } else {
+            self.interface
+                .count_refresh(self.refresh == RefreshLut::Quick);
+        }
+        self.interface.start_operation(match self.refresh {
+            RefreshLut::Full | RefreshLut::Custom(_) => Operation::FullRefresh,
+            RefreshLut::Quick => Operation::PartialRefresh,
+        });
+        Ok(())
+    }
+
+    /// Leaves an open partial session without refreshing
+    #[cfg(feature = "graphics")]
+    fn close_session(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        if self.partial_session {
+            self.partial_session = false;
+            self.command(spi, Command::PartialOut)?;
+        }
+        Ok(())
+    }
+
+    fn ensure_no_session(&self) -> Result<(), Error<SPI::Error>> {
+        if self.partial_session {
+            return Err(Error::PartialSessionActive);
+        }
+        Ok(())
+    }
+
+    fn ensure_powered(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.powered {
+            self.power_on(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -369,19 +758,19 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::ResolutionSetting)?;
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -394,7 +783,7 @@ where
         lut_bw: &[u8],
         lut_wb: &[u8],
         lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         // LUT VCOM
         self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
@@ -415,6 +804,9 @@ where
 
     /// Helper function. Sets up the display to send pixel data to a custom
     /// starting point.
+    ///
+    /// Returns [`Error::InvalidWindow`] without sending anything for a window off the
+    /// panel.
     pub fn shift_display(
         &mut self,
         spi: &mut SPI,
@@ -422,23 +814,96 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.send_data(spi, &[(x >> 8) as u8])?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8])?;
-        self.send_data(spi, &[(tmp | 0x07) as u8])?;
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.interface
+            .data_block(spi, partial_window(x, y, width, height).params())
+    }
+}
 
-        self.send_data(spi, &[(y >> 8) as u8])?;
-        self.send_data(spi, &[y as u8])?;
+impl<SPI, BUSY, DC, RST, DELAY> RowStreaming<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn update_frame_rows<F: FnMut(u32, &mut [u8])>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        row: &mut [u8],
+        mut fill_row: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        debug_assert_eq!(row.len(), line_bytes(WIDTH) as usize);
+        self.ensure_no_session()?;
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
 
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])?;
-        self.send_data(spi, &[(y + height - 1) as u8])?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, line_bytes(WIDTH) * HEIGHT)?;
 
-        self.send_data(spi, &[0x01])?; // Gates scan both inside and outside of the partial window. (default)
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        for y in 0..HEIGHT {
+            fill_row(y, row);
+            self.interface.data_combined(spi, row)?;
+        }
+        self.interface.flush(spi)
+    }
+}
 
-        Ok(())
+#[cfg(feature = "graphics")]
+impl<SPI, BUSY, DC, RST, DELAY> RegionRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Discards the windows of an open partial session, which can't be shown with
+    /// another LUT anyway
+    fn refresh_region_with_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        let (x, y, width, height) = crate::traits::partial_window(&area, WIDTH, HEIGHT)?;
+        self.close_session(spi)?;
+        self.wait_until_idle(spi, delay)?;
+        let previous = self.refresh;
+        if previous != lut {
+            self.set_lut(spi, delay, Some(lut))?;
+        }
+        let result = self
+            .begin_partial(spi, delay)
+            .and_then(|()| self.write_window(spi, delay, buffer, x, y, width, height))
+            .and_then(|()| self.end_partial_and_display(spi, delay));
+        // the LUT must neither change inside the session nor during the refresh
+        let restore = self
+            .close_session(spi)
+            .and_then(|()| self.wait_until_idle(spi, delay))
+            .and_then(|()| {
+                if previous == lut {
+                    Ok(())
+                } else {
+                    self.set_lut(spi, delay, Some(previous))
+                }
+            });
+        result.and(restore)
     }
 }
 
@@ -457,14 +922,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-
-        self.interface.data(spi, buffer)?;
-
-        Ok(())
+        self.ensure_powered(spi, delay)?;
+        uc81xx::write_frame(&mut self.interface, spi, Frame::Old, buffer)
     }
 
     /// To be used immediately after `update_old_frame`.
@@ -473,20 +936,20 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
         self.wait_until_idle(spi, delay)?;
-        // self.send_resolution(spi)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
-
-        self.interface.data(spi, buffer)?;
-
-        Ok(())
+        uc81xx::write_frame(&mut self.interface, spi, Frame::New, buffer)
     }
 
     /// This is a wrapper around `display_frame` for using this device as a true
     /// `QuickRefresh` device.
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
         self.display_frame(spi, delay)
     }
 
@@ -499,11 +962,51 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
 
+    fn display_with_ghost_compensation(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        let inverted = scratch
+            .get_mut(..buffer.len())
+            .ok_or(Error::InvalidDataLength)?;
+        for (inverse, byte) in inverted.iter_mut().zip(buffer) {
+            *inverse = !byte;
+        }
+
+        self.ensure_no_session()?;
+        let previous = self.refresh;
+        self.set_lut_helper(
+            spi,
+            delay,
+            &LUT_VCOM0_COMPENSATION,
+            &LUT_WW_COMPENSATION,
+            &LUT_BW_COMPENSATION,
+            &LUT_WB_COMPENSATION,
+            &LUT_BB_COMPENSATION,
+        )?;
+        // both refreshes are timed like quick refreshes
+        self.refresh = RefreshLut::Quick;
+        self.update_old_frame(spi, buffer, delay)?;
+        self.update_new_frame(spi, inverted, delay)?;
+        self.display_frame(spi, delay)?;
+
+        self.set_lut(spi, delay, Some(RefreshLut::Quick))?;
+        self.update_old_frame(spi, inverted, delay)?;
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+
+        self.set_lut(spi, delay, Some(previous))
+    }
+
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
@@ -513,24 +1016,22 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
 
-        if buffer.len() as u32 != width / 8 * height {
+        if buffer.len() as u32 != line_bytes(width) * height {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
-        self.interface.cmd(spi, Command::PartialIn)?;
-        self.interface.cmd(spi, Command::PartialWindow)?;
-
-        self.shift_display(spi, x, y, width, height)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-
-        self.interface.data(spi, buffer)?;
-
-        Ok(())
+        let window = partial_window(x, y, width, height);
+        uc81xx::enter_partial(&mut self.interface, spi, &window)?;
+        uc81xx::write_frame(&mut self.interface, spi, Frame::Old, buffer)
     }
 
     /// Always call `update_partial_old_frame` before this, with buffer-updating code
@@ -544,21 +1045,19 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         self.wait_until_idle(spi, delay)?;
-        if buffer.len() as u32 != width / 8 * height {
+        if buffer.len() as u32 != line_bytes(width) * height {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
         self.shift_display(spi, x, y, width, height)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
-
-        self.interface.data(spi, buffer)?;
-
-        self.interface.cmd(spi, Command::PartialOut)?;
-        Ok(())
+        uc81xx::write_frame(&mut self.interface, spi, Frame::New, buffer)?;
+        uc81xx::leave_partial(&mut self.interface, spi)
     }
 
     fn clear_partial_frame(
@@ -569,33 +1068,75 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.ensure_no_session()?;
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
         self.send_resolution(spi)?;
 
-        let color_value = self.color.get_byte_value();
-
-        self.interface.cmd(spi, Command::PartialIn)?;
-        self.interface.cmd(spi, Command::PartialWindow)?;
-
-        self.shift_display(spi, x, y, width, height)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
-
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface
-            .data_x_times(spi, color_value, width / 8 * height)?;
+        let color_value = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        uc81xx::clear_partial_frames(
+            &mut self.interface,
+            spi,
+            &partial_window(x, y, width, height),
+            color_value,
+            line_bytes(width) * height,
+        )
+    }
+}
 
-        self.interface.cmd(spi, Command::PartialOut)?;
+#[cfg(feature = "calibration")]
+impl<SPI, BUSY, DC, RST, DELAY> crate::calibration::QuickLutCalibration<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &crate::calibration::CustomLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        let operation = self.interface.set_operation(error::Operation::Lut);
+        self.ensure_no_session()?;
+        self.set_lut_helper(spi, delay, &lut.vcom, &lut.ww, &lut.bw, &lut.wb, &lut.bb)?;
+        // timed and counted like the quick LUT it replaces
+        self.refresh = RefreshLut::Quick;
+        self.interface.restore_operation(operation);
         Ok(())
     }
+
+    fn display_new_frame_checked(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<RefreshReport, Error<SPI::Error>> {
+        self.display_frame_checked(spi, delay)
+    }
+}
+
+/// Parameters of PartialWindow for the window at (x, y)
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> PartialWindow {
+    PartialWindow::new(Coordinates::Word, x, y, width, height)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "checksum")]
+    use crate::checksum::{crc16, CRC16_INIT};
+    use crate::mock::{Bus, BusyPin, Delay, Pin};
+    use crate::policy::RefreshCounters;
+    use crate::traits::DriverControl;
+    extern crate std;
+    use std::vec::Vec;
 
     #[test]
     fn epd_size() {
@@ -603,4 +1144,1146 @@ mod tests {
         assert_eq!(HEIGHT, 300);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn transfer_checksum() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        let frame: [u8; 15000] = core::array::from_fn(|i| (i % 251) as u8);
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+        assert_eq!(epd.last_transfer_checksum(), crc16(&frame));
+        assert_eq!(epd.take_checksum(), crc16(&frame));
+        assert_eq!(epd.take_checksum(), CRC16_INIT);
+
+        // synthesized fill bytes are covered as well
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(epd.last_transfer_checksum(), crc16(&[0xFF; 15000]));
+    }
+
+    #[test]
+    fn update_after_power_off_powers_on() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        epd.power_off(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [Command::PowerOff as u8]);
+        bus.clear();
+
+        let frame = [0xAA; 15000];
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+        // no configuration is resent, only the booster is started again
+        assert_eq!(
+            bus.commands(),
+            [
+                Command::PowerOn as u8,
+                Command::DataStartTransmission1 as u8,
+                Command::DataStartTransmission2 as u8,
+            ]
+        );
+        assert!(bus.waited_after(Command::PowerOn as u8));
+        assert_eq!(
+            bus.data_after(Command::DataStartTransmission2 as u8)
+                .unwrap(),
+            frame
+        );
+
+        // already powered, nothing extra is sent
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [Command::DisplayRefresh as u8]);
+    }
+
+    #[test]
+    fn partial_window_in_one_cs_assertion() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        // single byte writes are on for this controller, resolution as one list
+        assert_eq!(bus.data_after(0x61).as_deref(), Some(&[1, 144, 1, 44][..]));
+
+        bus.clear();
+        epd.command(&mut spi, Command::PartialWindow).unwrap();
+        epd.shift_display(&mut spi, 42, 100, 24, 20).unwrap();
+        assert_eq!(bus.cs_assertions(), 2);
+        assert_eq!(
+            bus.data_after(0x90).as_deref(),
+            Some(&[0, 40, 0, 63, 0, 100, 0, 119, 0x01][..])
+        );
+    }
+
+    #[test]
+    fn clear_frame_except_keeps_window() {
+        use embedded_graphics_core::prelude::{Point, Size};
+        use embedded_graphics_core::primitives::Rectangle;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame: [u8; 50 * 300] = core::array::from_fn(|i| (i % 253) as u8);
+
+        // widened to 40..64, cleared in four windows and kept row by row
+        bus.clear();
+        let keep = Rectangle::new(Point::new(42, 100), Size::new(20, 3));
+        epd.clear_frame_except(&mut spi, &mut delay, keep, &frame)
+            .unwrap();
+        assert_eq!(bus.count(0x90), 4 + 3);
+        let windows = [
+            (0, 0, 400, 100),
+            (0, 103, 400, 197),
+            (0, 100, 40, 3),
+            (64, 100, 336, 3),
+        ];
+        for (n, (x, y, width, height)) in windows.into_iter().enumerate() {
+            assert_eq!(
+                bus.data_after_nth(0x90, n),
+                Some(partial_window(x, y, width, height).params().to_vec())
+            );
+            assert_eq!(
+                bus.data_after_nth(0x10, n).map(|data| data.len()),
+                Some(crate::line_bytes(width) as usize * height as usize)
+            );
+        }
+        for row in 0..3 {
+            assert_eq!(
+                bus.data_after_nth(0x90, 4 + row),
+                Some(
+                    partial_window(40, 100 + row as u32, 24, 1)
+                        .params()
+                        .to_vec()
+                )
+            );
+            let start = (100 + row) * 50 + 5;
+            assert_eq!(
+                bus.data_after_nth(0x13, 4 + row).as_deref(),
+                Some(&frame[start..start + 3])
+            );
+        }
+
+        // full rows go out at once
+        bus.clear();
+        let keep = Rectangle::new(Point::new(0, 0), Size::new(400, 20));
+        epd.clear_frame_except(&mut spi, &mut delay, keep, &frame)
+            .unwrap();
+        assert_eq!(bus.count(0x90), 2);
+        assert_eq!(bus.data_after_nth(0x13, 1).as_deref(), Some(&frame[..1000]));
+
+        // a frame short of the last row is refused before anything is sent
+        bus.clear();
+        assert_eq!(
+            epd.clear_frame_except(&mut spi, &mut delay, keep, &frame[..50 * 299]),
+            Err(Error::InvalidDataLength)
+        );
+        assert!(bus.transfers().is_empty());
+    }
+
+    #[test]
+    fn partial_rect_matches_loose_args() {
+        use embedded_graphics_core::prelude::{Point, Size};
+        use embedded_graphics_core::primitives::Rectangle;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let buffer = [0x5A; 3 * 20];
+        let area = Rectangle::new(Point::new(40, 100), Size::new(24, 20));
+
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut delay, &buffer, 40, 100, 24, 20)
+            .unwrap();
+        epd.update_partial_new_frame(&mut spi, &mut delay, &buffer, 40, 100, 24, 20)
+            .unwrap();
+        epd.clear_partial_frame(&mut spi, &mut delay, 40, 100, 24, 20)
+            .unwrap();
+        let loose = bus.transfers();
+
+        bus.clear();
+        epd.update_partial_frame_rect(&mut spi, &mut delay, &buffer, area)
+            .unwrap();
+        epd.update_partial_new_frame_rect(&mut spi, &mut delay, &buffer, area)
+            .unwrap();
+        epd.clear_partial_frame_rect(&mut spi, &mut delay, area)
+            .unwrap();
+        assert_eq!(bus.transfers(), loose);
+
+        // invalid windows are refused before anything is sent
+        bus.clear();
+        let negative = Rectangle::new(Point::new(-8, 0), Size::new(16, 1));
+        let outside = Rectangle::new(Point::new(392, 0), Size::new(16, 1));
+        assert_eq!(
+            epd.update_partial_frame_rect(&mut spi, &mut delay, &buffer, negative),
+            Err(Error::InvalidWindow)
+        );
+        assert_eq!(
+            epd.update_partial_old_frame_rect(&mut spi, &mut delay, &buffer, outside),
+            Err(Error::InvalidWindow)
+        );
+        assert_eq!(
+            epd.clear_partial_frame_rect(&mut spi, &mut delay, Rectangle::zero()),
+            Err(Error::InvalidWindow)
+        );
+        assert!(bus.transfers().is_empty());
+    }
+
+    #[test]
+    fn partial_session() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        assert_eq!(
+            epd.write_window(&mut spi, &mut delay, &[0; 2], 0, 0, 16, 1),
+            Err(Error::NoPartialSession)
+        );
+        epd.begin_partial(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            epd.begin_partial(&mut spi, &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        epd.write_window(&mut spi, &mut delay, &[0x11; 2], 8, 10, 16, 1)
+            .unwrap();
+        epd.write_window(&mut spi, &mut delay, &[0x22; 4], 264, 280, 8, 4)
+            .unwrap();
+        assert_eq!(
+            epd.update_frame(&mut spi, &[0; 15000], &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        // windows reaching beyond the panel or not matching the buffer are refused
+        assert_eq!(
+            epd.write_window(&mut spi, &mut delay, &[0x33; 3], 392, 299, 24, 1),
+            Err(Error::InvalidWindow)
+        );
+        assert_eq!(
+            epd.write_window(&mut spi, &mut delay, &[0x33; 2], 376, 299, 24, 1),
+            Err(Error::InvalidDataLength)
+        );
+        assert_eq!(
+            epd.write_window(&mut spi, &mut delay, &[0x33; 4], 376, 299, 24, 1),
+            Err(Error::InvalidDataLength)
+        );
+        epd.write_window(&mut spi, &mut delay, &[0x33; 3], 376, 299, 24, 1)
+            .unwrap();
+        epd.end_partial_and_display(&mut spi, &mut delay).unwrap();
+
+        let window = Command::PartialWindow as u8;
+        let dtm2 = Command::DataStartTransmission2 as u8;
+        assert_eq!(
+            bus.commands(),
+            [
+                Command::PartialIn as u8,
+                window,
+                dtm2,
+                window,
+                dtm2,
+                window,
+                dtm2,
+                Command::PartialOut as u8,
+                Command::DisplayRefresh as u8,
+            ]
+        );
+        assert_eq!(
+            bus.data_after_nth(window, 0).unwrap(),
+            [0x00, 0x08, 0x00, 0x17, 0x00, 10, 0x00, 10, 0x01]
+        );
+        assert_eq!(
+            bus.data_after_nth(window, 1).unwrap(),
+            [0x01, 0x08, 0x01, 0x0F, 0x01, 0x18, 0x01, 0x1B, 0x01]
+        );
+        assert_eq!(
+            bus.data_after_nth(window, 2).unwrap(),
+            [0x01, 0x78, 0x01, 0x8F, 0x01, 0x2B, 0x01, 0x2B, 0x01]
+        );
+        assert_eq!(bus.data_after_nth(dtm2, 1).unwrap(), [0x22; 4]);
+
+        // the session is closed, normal updates work again
+        assert_eq!(
+            epd.end_partial_and_display(&mut spi, &mut delay),
+            Err(Error::NoPartialSession)
+        );
+        epd.update_frame(&mut spi, &[0; 15000], &mut delay).unwrap();
+    }
+
+    #[test]
+    fn display_frame_with_restores_lut() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let before = bus.writes();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        let lut_upload = bus.writes() - before;
+        bus.clear();
+
+        let vcom = Command::LutForVcom as u8;
+        epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+        assert_eq!(bus.count(vcom), 2);
+        assert_eq!(bus.data_after_nth(vcom, 0).unwrap(), LUT_VCOM0);
+        assert_eq!(bus.data_after_nth(vcom, 1).unwrap(), LUT_VCOM0_QUICK);
+        assert_eq!(bus.count(Command::DisplayRefresh as u8), 1);
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+
+        // the requested LUT is already selected, nothing to upload
+        bus.clear();
+        epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Quick)
+            .unwrap();
+        assert_eq!(bus.commands(), [Command::DisplayRefresh as u8]);
+
+        // the selection is restored even if the refresh itself fails, the upload
+        // happens with the reinitialisation as the failed transfer poisons the driver
+        bus.clear();
+        bus.fail_after(lut_upload);
+        assert_eq!(
+            epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full),
+            Err(Error::Spi(
+                embedded_hal::spi::ErrorKind::Other,
+                error::Context::new(error::Operation::DisplayFrame)
+            ))
+        );
+        assert_eq!(bus.count(vcom), 1);
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+        epd.recover(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(vcom), 2);
+        assert_eq!(bus.data_after_nth(vcom, 1).unwrap(), LUT_VCOM0_QUICK);
+    }
+
+    #[test]
+    fn errors_carry_context() {
+        use embedded_hal::spi::ErrorKind;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        bus.fail_after(0);
+        assert_eq!(
+            Epd4in2::new(
+                &mut spi,
+                BusyPin::new(true),
+                bus.dc(),
+                Pin::new(),
+                &mut delay,
+                None,
+            )
+            .err(),
+            Some(Error::Spi(
+                ErrorKind::Other,
+                error::Context::new(error::Operation::Init)
+            ))
+        );
+
+        bus.clear();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_instance_id(3);
+        bus.fail_after(0);
+        let error = epd.sleep(&mut spi, &mut delay).unwrap_err();
+        let context = error::Context::new(error::Operation::Sleep).with_instance(3);
+        assert_eq!(error, Error::Spi(ErrorKind::Other, context));
+        assert_eq!(error.context(), Some(context));
+
+        // the id survives the reinitialisation
+        epd.recover(&mut spi, &mut delay).unwrap();
+        bus.fail_after(0);
+        assert_eq!(
+            epd.clear_frame(&mut spi, &mut delay),
+            Err(Error::Spi(
+                ErrorKind::Other,
+                error::Context::new(error::Operation::ClearFrame).with_instance(3)
+            ))
+        );
+        assert_eq!(Error::<ErrorKind>::RefreshTooSoon.context(), None);
+    }
+
+    #[test]
+    fn recover_after_failed_transfer() {
+        use embedded_hal::spi::ErrorKind;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let rst = Pin::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            rst.clone(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame: [u8; 15000] = core::array::from_fn(|i| (i % 13) as u8);
+
+        // fail in the middle of the frame
+        bus.fail_after(100);
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame, &mut delay),
+            Err(Error::Spi(
+                ErrorKind::Other,
+                error::Context::new(error::Operation::UpdateFrame)
+            ))
+        );
+        bus.clear();
+        let writes = bus.writes();
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::NeedsReset(error::Context::new(
+                error::Operation::DisplayFrame
+            )))
+        );
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame, &mut delay),
+            Err(Error::NeedsReset(error::Context::new(
+                error::Operation::UpdateFrame
+            )))
+        );
+        assert_eq!(bus.writes(), writes);
+
+        let resets = rst.levels().iter().filter(|&&high| !high).count();
+        epd.recover(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            rst.levels().iter().filter(|&&high| !high).count(),
+            resets + 1
+        );
+        assert_eq!(bus.count(Command::PanelSetting as u8), 1);
+
+        bus.clear();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert_eq!(
+            bus.data_after(Command::DataStartTransmission2 as u8)
+                .unwrap(),
+            frame
+        );
+        assert_eq!(bus.count(Command::DisplayRefresh as u8), 1);
+    }
+
+    #[test]
+    fn abort_refresh_needs_reinit() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let rst = Pin::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            rst.clone(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0x0F; 15000];
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+
+        // hardware reset and power off, without waiting for the running refresh
+        bus.clear();
+        let resets = rst.levels().iter().filter(|&&high| !high).count();
+        epd.abort_refresh(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            rst.levels().iter().filter(|&&high| !high).count(),
+            resets + 1
+        );
+        assert_eq!(bus.commands(), [Command::PowerOff as u8]);
+        assert!(!bus.waited_after(Command::PowerOff as u8));
+
+        // refused until initialised again, both start with powering the booster on
+        let needs_reset = Err(Error::NeedsReset(error::Context::new(
+            error::Operation::Power,
+        )));
+        assert_eq!(epd.update_frame(&mut spi, &frame, &mut delay), needs_reset);
+        assert_eq!(epd.display_frame(&mut spi, &mut delay), needs_reset);
+        assert_eq!(bus.commands(), [Command::PowerOff as u8]);
+
+        bus.clear();
+        epd.recover(&mut spi, &mut delay).unwrap();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        // powered on again before the refresh
+        assert_eq!(bus.count(Command::PowerOn as u8), 1);
+        assert_eq!(bus.count(Command::DisplayRefresh as u8), 1);
+    }
+
+    #[test]
+    fn fixed_delay_waits_per_operation() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let delays = Delay::new();
+        let mut delay = delays.clone();
+        // stuck busy, would never finish when polled
+        let busy = BusyPin::new(false);
+        let mut epd = Epd4in2::new_with_busy_mode(
+            &mut spi,
+            busy.clone(),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+            BusyMode::FixedDelay,
+        )
+        .unwrap();
+        // reset, power on and the waits before and after writing the LUT
+        assert_eq!(
+            delays.delays(),
+            [
+                10_000,
+                10_000,
+                200_000,
+                5_000,
+                BUSY_TIMINGS.init,
+                10_000,
+                10_000
+            ]
+        );
+
+        let mut waits = |f: &mut dyn FnMut(&mut Epd4in2<_, _, _, _, _>, &mut Delay)| {
+            let start = delays.delays().len();
+            f(&mut epd, &mut delay);
+            delays.delays()[start..].to_vec()
+        };
+        let frame = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        assert_eq!(
+            waits(&mut |epd, delay| epd.display_frame(&mut spi, delay).unwrap()),
+            [BUSY_TIMINGS.command]
+        );
+        assert_eq!(
+            waits(&mut |epd, delay| epd.update_frame(&mut spi, &frame, delay).unwrap()),
+            [BUSY_TIMINGS.full_refresh]
+        );
+        assert_eq!(
+            waits(&mut |epd, delay| {
+                epd.set_lut(&mut spi, delay, Some(RefreshLut::Quick))
+                    .unwrap();
+                epd.display_frame(&mut spi, delay).unwrap();
+            }),
+            [BUSY_TIMINGS.command, BUSY_TIMINGS.command]
+        );
+        assert_eq!(
+            waits(&mut |epd, delay| epd.sleep(&mut spi, delay).unwrap()),
+            [BUSY_TIMINGS.partial_refresh, BUSY_TIMINGS.sleep]
+        );
+        assert_eq!(busy.reads(), 0);
+    }
+
+    #[test]
+    fn vcom_override() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+
+        Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(bus.data_after(0x82).unwrap(), [0x12]);
+
+        bus.clear();
+        let mut epd = Epd4in2::new_with_vcom(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+            -2050,
+        )
+        .unwrap();
+        assert_eq!(bus.data_after(0x82).unwrap(), [0x27]);
+
+        // applied right away and again by the next init
+        bus.clear();
+        epd.set_vcom(&mut spi, -4000).unwrap();
+        assert_eq!(bus.data_after(0x82).unwrap(), [0x3A]);
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        bus.clear();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x82).unwrap(), [0x3A]);
+    }
+
+    #[test]
+    fn reset_config_override() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let rst = Pin::new();
+
+        let inverted = ResetConfig {
+            active_low: false,
+            ..RESET_CONFIG
+        };
+        let mut epd = Epd4in2::new_with_reset_config(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            rst.clone(),
+            &mut delay,
+            None,
+            inverted,
+        )
+        .unwrap();
+        assert_eq!(rst.levels(), [false, true, false]);
+        assert_eq!(epd.reset_config(), inverted);
+
+        // wake_up resets the same way
+        epd.set_reset_config(ResetConfig {
+            pulses: 2,
+            ..inverted
+        });
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        epd.wake_up(&mut spi, &mut delay).unwrap();
+        assert_eq!(rst.levels()[3..], [false, true, false, true, false]);
+    }
+
+    #[test]
+    fn ghost_compensation_shows_inverse_first() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+
+        let frame: [u8; 15000] = core::array::from_fn(|i| (i % 251) as u8);
+        let inverse = frame.map(|byte| !byte);
+        let mut scratch = [0; 15000];
+        epd.display_with_ghost_compensation(&mut spi, &mut delay, &frame, &mut scratch)
+            .unwrap();
+
+        // compensation LUT, inverse, quick LUT, real frame, previous (full) LUT
+        assert_eq!(
+            bus.commands(),
+            [
+                0x20, 0x21, 0x22, 0x23, 0x24, 0x10, 0x13, 0x12, 0x20, 0x21, 0x22, 0x23, 0x24, 0x10,
+                0x13, 0x12, 0x20, 0x21, 0x22, 0x23, 0x24,
+            ]
+        );
+        assert_eq!(bus.data_after_nth(0x20, 0).unwrap(), LUT_VCOM0_COMPENSATION);
+        assert_eq!(bus.data_after_nth(0x13, 0).unwrap(), inverse);
+        assert_eq!(bus.data_after_nth(0x20, 1).unwrap(), LUT_VCOM0_QUICK);
+        assert_eq!(bus.data_after_nth(0x10, 1).unwrap(), inverse);
+        assert_eq!(bus.data_after_nth(0x13, 1).unwrap(), frame);
+        assert_eq!(bus.data_after_nth(0x20, 2).unwrap(), LUT_VCOM0);
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Full));
+
+        // a quick LUT stays selected
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        bus.clear();
+        epd.display_with_ghost_compensation(&mut spi, &mut delay, &frame, &mut scratch)
+            .unwrap();
+        assert_eq!(bus.data_after_nth(0x20, 2).unwrap(), LUT_VCOM0_QUICK);
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+
+        // a short scratch buffer is refused without sending anything
+        bus.clear();
+        assert!(matches!(
+            epd.display_with_ghost_compensation(&mut spi, &mut delay, &frame, &mut scratch[1..]),
+            Err(Error::InvalidDataLength)
+        ));
+        assert!(bus.commands().is_empty());
+    }
+
+    #[test]
+    fn refresh_guard() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_refresh_guard(1);
+        epd.note_time(1_000);
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+
+        // refused before anything is sent
+        bus.clear();
+        epd.note_time(1_000 + u64::from(MIN_REFRESH_INTERVAL) - 1);
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::RefreshTooSoon)
+        );
+        assert!(bus.transfers().is_empty());
+
+        // the escape hatch refreshes anyway, but only once
+        epd.force_display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(0x12), 1);
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::RefreshTooSoon)
+        );
+
+        // quick refreshes aren't limited
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(0x12), 3);
+
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Full))
+            .unwrap();
+        epd.note_time(1_000 + 2 * u64::from(MIN_REFRESH_INTERVAL));
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(bus.count(0x12), 4);
+
+        // a refused partial session stays open and can be refreshed later
+        epd.begin_partial(&mut spi, &mut delay).unwrap();
+        epd.write_window(&mut spi, &mut delay, &[0; 2], 0, 0, 16, 1)
+            .unwrap();
+        bus.clear();
+        assert_eq!(
+            epd.end_partial_and_display(&mut spi, &mut delay),
+            Err(Error::RefreshTooSoon)
+        );
+        assert!(bus.transfers().is_empty());
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut delay),
+            Err(Error::PartialSessionActive)
+        );
+        epd.note_time(1_000 + 3 * u64::from(MIN_REFRESH_INTERVAL));
+        epd.end_partial_and_display(&mut spi, &mut delay).unwrap();
+        assert_eq!(
+            bus.commands(),
+            [Command::PartialOut as u8, Command::DisplayRefresh as u8]
+        );
+    }
+
+    #[test]
+    fn display_frame_checked_reports() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let busy = BusyPin::new(true);
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            busy.clone(),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            Some(100_000),
+        )
+        .unwrap();
+
+        // idle before the refresh, then busy for 4.5 s
+        busy.push(&[true]);
+        busy.push(&[false; 45]);
+        let report = epd.display_frame_checked(&mut spi, &mut delay).unwrap();
+        assert_eq!(report.busy_us, 4_500_000);
+        assert!(report.over_typical && !report.over_max);
+
+        // 0.5 s is fine for a full refresh but slow for a quick one
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        busy.push(&[true]);
+        busy.push(&[false; 5]);
+        let report = epd.display_frame_checked(&mut spi, &mut delay).unwrap();
+        assert_eq!(report.busy_polls, 5);
+        assert!(report.over_typical && !report.over_max);
+    }
+
+    #[test]
+    fn periodic_update_sequences() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0xAA; 15000];
+        let retained = PeriodicUpdateOptions {
+            assume_config_retained: true,
+            ..Default::default()
+        };
+        let lut = [0x20, 0x21, 0x22, 0x23, 0x24];
+
+        // only the frame, the quick LUT for this refresh and the power off
+        bus.clear();
+        epd.periodic_update(&mut spi, &mut delay, &frame, retained)
+            .unwrap();
+        let update = [&[0x10, 0x13][..], &lut, &[0x12], &lut, &[0x02]].concat();
+        assert_eq!(bus.commands(), update);
+
+        // the booster is powered on again
+        bus.clear();
+        epd.periodic_update(&mut spi, &mut delay, &frame, retained)
+            .unwrap();
+        assert_eq!(bus.commands(), [&[0x04][..], &update].concat());
+
+        // the selected LUT stays, the full one isn't uploaded twice
+        bus.clear();
+        let full = PeriodicUpdateOptions {
+            full_refresh: true,
+            deep_sleep_after: true,
+            ..retained
+        };
+        epd.periodic_update(&mut spi, &mut delay, &frame, full)
+            .unwrap();
+        let commands = bus.commands();
+        assert_eq!(commands[..4], [0x04, 0x10, 0x13, 0x12]);
+        assert_eq!(commands.last(), Some(&0x07));
+        assert_eq!(bus.count(0x20), 0);
+
+        // deep sleep lost the configuration, it is sent again
+        for opts in [retained, PeriodicUpdateOptions::default()] {
+            epd.sleep(&mut spi, &mut delay).unwrap();
+            bus.clear();
+            epd.periodic_update(&mut spi, &mut delay, &frame, opts)
+                .unwrap();
+            let commands = bus.commands();
+            assert_eq!(commands[..3], [0x01, 0x06, 0x04]);
+            assert_eq!(commands.last(), Some(&0x02));
+        }
+    }
+
+    #[test]
+    fn refresh_counters() {
+        use crate::policy::{RefreshHint, RefreshPolicy};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let frame = [0; 15000];
+        let counters = |full, quick, partial| RefreshCounters {
+            full,
+            quick,
+            partial,
+        };
+        assert_eq!(epd.refresh_counters(), RefreshCounters::default());
+
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        assert_eq!(epd.refresh_counters(), counters(2, 0, 0));
+
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut delay)
+            .unwrap();
+        epd.periodic_update(&mut spi, &mut delay, &frame, Default::default())
+            .unwrap();
+        assert_eq!(epd.refresh_counters(), counters(2, 2, 0));
+
+        // the first refresh of a policy is a full one
+        let mut policy = RefreshPolicy::new();
+        epd.display_with_policy(&mut spi, &mut delay, &mut policy, RefreshHint::new(0))
+            .unwrap();
+        epd.display_with_policy(&mut spi, &mut delay, &mut policy, RefreshHint::new(1))
+            .unwrap();
+        assert_eq!(epd.refresh_counters(), counters(3, 3, 0));
+
+        epd.begin_partial(&mut spi, &mut delay).unwrap();
+        epd.write_window(&mut spi, &mut delay, &[0; 2], 0, 0, 16, 1)
+            .unwrap();
+        epd.end_partial_and_display(&mut spi, &mut delay).unwrap();
+        assert_eq!(epd.refresh_counters(), counters(3, 3, 1));
+
+        // failed refreshes aren't counted
+        epd.set_refresh_guard(1);
+        epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full)
+            .unwrap();
+        assert_eq!(
+            epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full),
+            Err(Error::RefreshTooSoon)
+        );
+        assert_eq!(epd.refresh_counters(), counters(4, 3, 1));
+
+        // restored counters continue and saturate
+        epd.restore_refresh_counters(counters(u32::MAX, 7, 0));
+        epd.force_display_frame(&mut spi, &mut delay).unwrap();
+        assert_eq!(epd.refresh_counters(), counters(u32::MAX, 8, 0));
+        assert_eq!(epd.refresh_counters().total(), u32::MAX);
+    }
+
+    #[test]
+    fn update_and_display_frame_matches_separate_calls() {
+        let record = |combined: bool| {
+            let bus = Bus::new();
+            let mut spi = bus.spi();
+            let mut delay = Delay::new();
+            let mut epd = Epd4in2::new(
+                &mut spi,
+                bus.busy(true),
+                bus.dc(),
+                Pin::new(),
+                &mut delay,
+                None,
+            )
+            .unwrap();
+            epd.set_refresh_guard(1);
+            epd.note_time(1_000);
+            bus.clear();
+            if combined {
+                epd.update_and_display_frame(&mut spi, &[0xAA; 4], &mut delay)
+                    .unwrap();
+            } else {
+                epd.update_frame(&mut spi, &[0xAA; 4], &mut delay).unwrap();
+                epd.display_frame(&mut spi, &mut delay).unwrap();
+            }
+            let trace = (bus.transfers(), bus.busy_reads());
+
+            // the combined call is refused by the refresh guard as well
+            assert_eq!(
+                epd.update_and_display_frame(&mut spi, &[0xAA; 4], &mut delay),
+                Err(Error::RefreshTooSoon)
+            );
+            trace
+        };
+        assert_eq!(record(true), record(false));
+    }
+
+    #[test]
+    fn region_refresh_brackets_lut_and_session() {
+        use embedded_graphics_core::prelude::{Point, Size};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let clock = Rectangle::new(Point::new(0, 0), Size::new(64, 16));
+        let photo = Rectangle::new(Point::new(200, 100), Size::new(8, 2));
+        let (lut, part_in, part_out, refresh) = (
+            Command::LutForVcom as u8,
+            Command::PartialIn as u8,
+            Command::PartialOut as u8,
+            Command::DisplayRefresh as u8,
+        );
+        let sequence = |bus: &Bus| -> Vec<u8> {
+            bus.commands()
+                .into_iter()
+                .filter(|c| [lut, part_in, part_out, refresh].contains(c))
+                .collect()
+        };
+
+        // quick LUT for this refresh only, restored after the refresh finished
+        bus.clear();
+        epd.refresh_region_with_lut(&mut spi, &mut delay, &[0; 128], clock, RefreshLut::Quick)
+            .unwrap();
+        assert_eq!(sequence(&bus), [lut, part_in, part_out, refresh, lut]);
+        assert_eq!(bus.data_after_nth(lut, 0).unwrap(), LUT_VCOM0_QUICK);
+        assert_eq!(bus.data_after_nth(lut, 1).unwrap(), LUT_VCOM0);
+        assert!(bus.waited_after(refresh));
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Full));
+
+        // the selected LUT isn't uploaded again
+        bus.clear();
+        epd.refresh_region_with_lut(&mut spi, &mut delay, &[0; 2], photo, RefreshLut::Full)
+            .unwrap();
+        assert_eq!(sequence(&bus), [part_in, part_out, refresh]);
+        assert_eq!(
+            bus.data_after(Command::PartialWindow as u8).unwrap(),
+            [0, 200, 0, 207, 0, 100, 0, 101, 0x01]
+        );
+
+        // an open session is closed before the LUT upload
+        epd.begin_partial(&mut spi, &mut delay).unwrap();
+        bus.clear();
+        epd.refresh_region_with_lut(&mut spi, &mut delay, &[0; 128], clock, RefreshLut::Quick)
+            .unwrap();
+        assert_eq!(
+            sequence(&bus),
+            [part_out, lut, part_in, part_out, refresh, lut]
+        );
+        assert_eq!(epd.refresh_counters().partial, 3);
+
+        // invalid windows are refused before anything is sent
+        bus.clear();
+        let outside = Rectangle::new(Point::new(396, 0), Size::new(8, 1));
+        assert_eq!(
+            epd.refresh_region_with_lut(&mut spi, &mut delay, &[0; 1], outside, RefreshLut::Quick),
+            Err(Error::InvalidWindow)
+        );
+        assert!(bus.transfers().is_empty());
+    }
+
+    #[cfg(feature = "calibration")]
+    #[test]
+    fn quick_lut_sweep() {
+        use crate::calibration::{sweep_quick_lut, SCRATCH_LEN};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let busy = BusyPin::new(true);
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            busy.clone(),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            Some(100_000),
+        )
+        .unwrap();
+        bus.clear();
+
+        // busy polls of both refreshes of each candidate, 15 polls (1.5 s) at most
+        let candidates = [
+            QUICK_LUT,
+            QUICK_LUT.scale_frames(50),
+            QUICK_LUT.scale_frames(30),
+        ];
+        for (there, back) in [(10, 10), (5, 6), (3, 20)] {
+            // LUT upload, old and new frame, idle before and at the start of the refresh
+            busy.push(&[true; 5]);
+            busy.push(&std::vec![false; there]);
+            busy.push(&[true; 5]);
+            busy.push(&std::vec![false; back]);
+            busy.push(&[true]);
+        }
+        let mut scratch = [0; SCRATCH_LEN];
+        let index = sweep_quick_lut(&mut epd, &mut spi, &mut delay, &candidates, &mut scratch);
+        assert_eq!(index, Ok(Some(1)));
+
+        // every candidate refreshed twice, then the full LUT is back
+        assert_eq!(bus.count(0x12), 6);
+        assert_eq!(
+            bus.data_after_nth(0x20, 1).unwrap(),
+            candidates[1].vcom.to_vec()
+        );
+        assert_eq!(bus.data_after_nth(0x20, 3).unwrap(), LUT_VCOM0.to_vec());
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Full));
+
+        // all too slow
+        busy.push(&[true; 5]);
+        busy.push(&[false; 16]);
+        busy.push(&[true; 5]);
+        busy.push(&[false; 1]);
+        assert_eq!(
+            sweep_quick_lut(
+                &mut epd,
+                &mut spi,
+                &mut delay,
+                &candidates[..1],
+                &mut scratch
+            ),
+            Ok(None)
+        );
+        assert_eq!(
+            sweep_quick_lut(
+                &mut epd,
+                &mut spi,
+                &mut delay,
+                &candidates,
+                &mut scratch[1..]
+            ),
+            Err(Error::InvalidDataLength)
+        );
+    }
 }
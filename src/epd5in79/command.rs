@@ -0,0 +1,114 @@
+//! SPI Commands for the Waveshare 5.79" E-Ink Display
+
+use crate::traits::{self, DataLen};
+
+/// EPD5IN79 commands
+///
+/// The panel is driven by two cascaded SSD1683. Commands without the highest bit
+/// set go to the master (left half, or both for the common settings), the same
+/// commands with the highest bit set go to the slave (right half).
+///
+/// Should rarely (never?) be needed directly.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum Command {
+    /// Gate count and scanning direction
+    DriverOutputControl = 0x01,
+    /// Deep sleep, 0x01 keeps the RAM, 0x03 loses it
+    DeepSleepMode = 0x10,
+    /// Address increments of the master RAM
+    DataEntryModeSetting = 0x11,
+    /// Address increments of the slave RAM
+    SlaveDataEntryModeSetting = 0x91,
+    /// Resets all commands and parameters to their default values, except deep sleep
+    SwReset = 0x12,
+    /// Selects the internal temperature sensor
+    TemperatureSensorControl = 0x18,
+    /// Runs the sequence selected with `DisplayUpdateControl2`
+    MasterActivation = 0x20,
+    /// RAM content options
+    DisplayUpdateControl1 = 0x21,
+    /// Display update sequence, e.g. 0xF7 for a full refresh
+    DisplayUpdateControl2 = 0x22,
+    /// Writes the black/white RAM of the master
+    WriteRam = 0x24,
+    /// Writes the black/white RAM of the slave
+    SlaveWriteRam = 0xA4,
+    /// Writes the red (previous frame) RAM of the master
+    WriteRamRed = 0x26,
+    /// Writes the red (previous frame) RAM of the slave
+    SlaveWriteRamRed = 0xA6,
+    /// Border waveform control
+    BorderWaveformControl = 0x3C,
+    /// Start/end of the master RAM window in the X direction, in bytes
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Start/end of the slave RAM window in the X direction, in bytes
+    SlaveSetRamXAddressStartEndPosition = 0xC4,
+    /// Start/end of the master RAM window in the Y direction
+    SetRamYAddressStartEndPosition = 0x45,
+    /// Start/end of the slave RAM window in the Y direction
+    SlaveSetRamYAddressStartEndPosition = 0xC5,
+    /// Master RAM X address counter
+    SetRamXAddressCounter = 0x4E,
+    /// Slave RAM X address counter
+    SlaveSetRamXAddressCounter = 0xCE,
+    /// Master RAM Y address counter
+    SetRamYAddressCounter = 0x4F,
+    /// Slave RAM Y address counter
+    SlaveSetRamYAddressCounter = 0xCF,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::SwReset | Command::MasterActivation => DataLen::Exact(0),
+            Command::DeepSleepMode
+            | Command::DataEntryModeSetting
+            | Command::SlaveDataEntryModeSetting
+            | Command::TemperatureSensorControl
+            | Command::DisplayUpdateControl2
+            | Command::BorderWaveformControl
+            | Command::SetRamXAddressCounter
+            | Command::SlaveSetRamXAddressCounter => DataLen::Exact(1),
+            Command::SetRamXAddressStartEndPosition
+            | Command::SlaveSetRamXAddressStartEndPosition
+            | Command::SetRamYAddressCounter
+            | Command::SlaveSetRamYAddressCounter => DataLen::Exact(2),
+            Command::DriverOutputControl => DataLen::Exact(3),
+            Command::SetRamYAddressStartEndPosition
+            | Command::SlaveSetRamYAddressStartEndPosition => DataLen::Exact(4),
+            Command::DisplayUpdateControl1 => DataLen::Max(2),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::WriteRam.address(), 0x24);
+        assert_eq!(Command::SlaveWriteRam.address(), 0xA4);
+        assert_eq!(Command::SlaveSetRamXAddressStartEndPosition.address(), 0xC4);
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::SlaveSetRamYAddressStartEndPosition
+            .expected_data_len()
+            .accepts(4));
+        assert!(!Command::SlaveSetRamYAddressStartEndPosition
+            .expected_data_len()
+            .accepts(2));
+        assert!(Command::DriverOutputControl.expected_data_len().accepts(3));
+        assert!(!Command::DriverOutputControl.expected_data_len().accepts(4));
+    }
+}
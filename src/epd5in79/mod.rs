@@ -0,0 +1,585 @@
+//! A simple Driver for the Waveshare 5.79" E-Ink Display via SPI
+//!
+//! The panel is driven by two cascaded SSD1683 controllers: the master drives the
+//! left 400 pixels, the slave the right 392 pixels of every row. The driver splits
+//! the rows of a seamless 792x272 buffer (e.g. [`Display5in79`]) at the seam and
+//! writes each half to its controller, so the application never sees the split.
+//!
+//! - [Datasheet](https://www.waveshare.com/w/upload/5/5e/5.79inch_e-Paper_Specification.pdf)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in79.c)
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{
+//!    prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd5in79::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd5in79::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut display = Display5in79::default();
+//!
+//!// one line across both halves
+//!let _ = Line::new(Point::new(0, 136), Point::new(791, 136))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::RefreshLimits;
+use crate::color::{Color, Plane, PlaneConvention};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+use crate::traits::{
+    check_window, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+use crate::update_sequence::UpdateSequence;
+use crate::{buffer_len, line_bytes};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 5in79 EPD
+#[cfg(feature = "graphics")]
+pub type Display5in79 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd5in79",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 2,
+    partial: true,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(10_000, 2_000);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(3_000_000, 4_500_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
+const IS_BUSY_LOW: bool = false;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Pixels of a row driven by the master controller
+const MASTER_WIDTH: u32 = 400;
+/// Bytes of a row in the RAM of the master, 400 / 8
+const MASTER_ROW_BYTES: usize = MASTER_WIDTH as usize / 8;
+/// Bytes of a row in the RAM of the slave, 392 / 8 (not 50!)
+const SLAVE_ROW_BYTES: usize = (WIDTH - MASTER_WIDTH) as usize / 8;
+
+/// One of the two cascaded controllers
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Controller {
+    /// Left half
+    Master,
+    /// Right half
+    Slave,
+}
+
+/// Epd5in79 driver
+pub struct Epd5in79<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::SwReset;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd5in79 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, RESET_CONFIG);
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])?;
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
+
+        // x and y increment on both controllers, address counter updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+        self.interface
+            .cmd_with_data(spi, Command::SlaveDataEntryModeSetting, &[0x03])?;
+
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        let sequence = self.interface.update_sequence_or(0xF7);
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
+        self.wait_until_idle(spi, delay)?;
+        // deep sleep mode 1, keeps the RAM
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
+        self.init(spi, delay)
+    }
+
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .abort_with_sw_reset(spi, delay, Command::SwReset, Command::DeepSleepMode)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.wait_until_idle(spi, delay)?;
+        self.write_window(spi, frame, 0, 0, WIDTH, HEIGHT)
+    }
+
+    /// Writes a window of the frame, split at the seam between the controllers
+    ///
+    /// `x` and `width` need to be multiples of 8.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        self.wait_until_idle(spi, delay)?;
+        self.write_window(spi, &mut FrameData::new(buffer), x, y, width, height)
+    }
+
+    /// Full refresh of both halves at once, the master synchronises the slave
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
+        self.wait_until_idle(spi, delay)?;
+        let color = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
+        for (controller, row_bytes) in [
+            (Controller::Master, MASTER_ROW_BYTES),
+            (Controller::Slave, SLAVE_ROW_BYTES),
+        ] {
+            self.set_ram_area(spi, controller, 0, row_bytes - 1, 0, HEIGHT - 1)?;
+            self.interface.cmd(spi, controller.write_ram())?;
+            self.interface
+                .data_x_times(spi, color, (row_bytes as u32) * HEIGHT)?;
+        }
+        Ok(())
+    }
+
+    /// Only the full refresh waveform of the OTP is supported
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
+        Ok(())
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Replaces the display update sequence of the refreshes, `None` restores the
+    /// ones of the driver, see [crate::update_sequence]
+    ///
+    /// Returns [Error::InvalidUpdateSequence] without changing anything for a sequence
+    /// the controller can't run.
+    pub fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_update_sequence(sequence)
+    }
+
+    /// Writes the rows of `buffer` (`width` pixels each) to the window at `x`, `y`
+    ///
+    /// The bytes left of the seam go to the master, the others to the slave.
+    fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
+        let row_bytes = line_bytes(width) as usize;
+        if row_bytes == 0 || height == 0 {
+            return Ok(());
+        }
+        // byte columns of the full row covered by the window
+        let first = (x / 8) as usize;
+        let end = first + row_bytes;
+
+        let halves = [
+            (Controller::Master, first, end.min(MASTER_ROW_BYTES)),
+            (Controller::Slave, first.max(MASTER_ROW_BYTES), end),
+        ];
+        for (controller, start, stop) in halves {
+            if start >= stop {
+                continue;
+            }
+            let offset = match controller {
+                Controller::Master => 0,
+                Controller::Slave => MASTER_ROW_BYTES,
+            };
+            self.set_ram_area(
+                spi,
+                controller,
+                start - offset,
+                stop - offset - 1,
+                y,
+                y + height - 1,
+            )?;
+            self.interface.cmd(spi, controller.write_ram())?;
+            for row in (0..frame.len()).step_by(row_bytes).take(height as usize) {
+                self.interface
+                    .data_frame(spi, frame, row + start - first..row + stop - first)?;
+            }
+        }
+        // each controller got its half of the rows, the checksum is the one of the window
+        #[cfg(feature = "checksum")]
+        self.interface.set_checksum(frame.checksum());
+        Ok(())
+    }
+
+    /// Sets the RAM window of `controller` and moves its address counter to the start
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        controller: Controller,
+        x_start: usize,
+        x_end: usize,
+        y_start: u32,
+        y_end: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let (x_window, y_window, x_counter, y_counter) = match controller {
+            Controller::Master => (
+                Command::SetRamXAddressStartEndPosition,
+                Command::SetRamYAddressStartEndPosition,
+                Command::SetRamXAddressCounter,
+                Command::SetRamYAddressCounter,
+            ),
+            Controller::Slave => (
+                Command::SlaveSetRamXAddressStartEndPosition,
+                Command::SlaveSetRamYAddressStartEndPosition,
+                Command::SlaveSetRamXAddressCounter,
+                Command::SlaveSetRamYAddressCounter,
+            ),
+        };
+        self.interface
+            .cmd_with_data(spi, x_window, &[x_start as u8, x_end as u8])?;
+        self.interface.cmd_with_data(
+            spi,
+            y_window,
+            &[
+                y_start as u8,
+                (y_start >> 8) as u8,
+                y_end as u8,
+                (y_end >> 8) as u8,
+            ],
+        )?;
+        self.interface
+            .cmd_with_data(spi, x_counter, &[x_start as u8])?;
+        self.interface
+            .cmd_with_data(spi, y_counter, &[y_start as u8, (y_start >> 8) as u8])
+    }
+}
+
+impl Controller {
+    fn write_ram(self) -> Command {
+        match self {
+            Controller::Master => Command::WriteRam,
+            Controller::Slave => Command::SlaveWriteRam,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, Delay, Pin};
+
+    fn epd(
+        bus: &Bus,
+    ) -> Epd5in79<crate::mock::Spi, crate::mock::BusyPin, crate::mock::DcPin, Pin, Delay> {
+        let mut spi = bus.spi();
+        Epd5in79::new(
+            &mut spi,
+            bus.busy(false),
+            bus.dc(),
+            Pin::new(),
+            &mut Delay::new(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(MASTER_ROW_BYTES, 50);
+        assert_eq!(SLAVE_ROW_BYTES, 49);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn update_frame_strides() {
+        let bus = Bus::new();
+        let mut epd = epd(&bus);
+        let mut spi = bus.spi();
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| (i % 251) as u8);
+
+        bus.clear();
+        epd.update_frame(&mut spi, &frame, &mut Delay::new())
+            .unwrap();
+        assert_eq!(bus.data_after(0x44).unwrap(), [0, 49]);
+        assert_eq!(bus.data_after(0xC4).unwrap(), [0, 48]);
+        assert_eq!(bus.data_after(0x45).unwrap(), [0, 0, 0x0F, 0x01]);
+
+        let master = bus.data_after(0x24).unwrap();
+        let slave = bus.data_after(0xA4).unwrap();
+        assert_eq!(master.len(), 50 * 272);
+        assert_eq!(slave.len(), 49 * 272);
+        for (row, line) in frame.chunks(99).enumerate() {
+            assert_eq!(master[row * 50..][..50], line[..50]);
+            assert_eq!(slave[row * 49..][..49], line[50..]);
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn line_across_seam() {
+        use embedded_graphics::{
+            prelude::*,
+            primitives::{Line, PrimitiveStyle},
+        };
+
+        let bus = Bus::new();
+        let mut epd = epd(&bus);
+        let mut spi = bus.spi();
+        let mut display = Display5in79::default();
+        display.clear(Color::White).unwrap();
+        // pixels 396..=403 of row 10, the last 4 of the master and the first 4 of the slave
+        Line::new(Point::new(396, 10), Point::new(403, 10))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display)
+            .unwrap();
+
+        bus.clear();
+        epd.update_frame(&mut spi, display.buffer(), &mut Delay::new())
+            .unwrap();
+        let master = bus.data_after(0x24).unwrap();
+        let slave = bus.data_after(0xA4).unwrap();
+        assert_eq!(master[10 * 50 + 49], 0xF0);
+        assert_eq!(slave[10 * 49], 0x0F);
+        assert_eq!(master.iter().filter(|&&b| b != 0xFF).count(), 1);
+        assert_eq!(slave.iter().filter(|&&b| b != 0xFF).count(), 1);
+    }
+
+    #[test]
+    fn partial_window_across_seam() {
+        let bus = Bus::new();
+        let mut epd = epd(&bus);
+        let mut spi = bus.spi();
+        // 32 pixels starting 2 bytes left of the seam, 3 rows
+        let window: [u8; 12] = core::array::from_fn(|i| i as u8);
+
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut Delay::new(), &window, 384, 100, 32, 3)
+            .unwrap();
+        assert_eq!(bus.data_after(0x44).unwrap(), [48, 49]);
+        assert_eq!(bus.data_after(0x4E).unwrap(), [48]);
+        assert_eq!(bus.data_after(0xC4).unwrap(), [0, 1]);
+        assert_eq!(bus.data_after(0xCE).unwrap(), [0]);
+        assert_eq!(bus.data_after(0xC5).unwrap(), [100, 0, 102, 0]);
+        assert_eq!(bus.data_after(0x24).unwrap(), [0, 1, 4, 5, 8, 9]);
+        assert_eq!(bus.data_after(0xA4).unwrap(), [2, 3, 6, 7, 10, 11]);
+
+        // a window right of the seam only uses the slave
+        bus.clear();
+        epd.update_partial_frame(&mut spi, &mut Delay::new(), &window[..4], 408, 0, 16, 2)
+            .unwrap();
+        assert_eq!(bus.count(0x24), 0);
+        assert_eq!(bus.data_after(0xC4).unwrap(), [1, 2]);
+    }
+}
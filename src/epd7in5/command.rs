@@ -1,6 +1,6 @@
 //! SPI Commands for the Waveshare 7.5" E-Ink Display
 
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 /// Epd7in5 commands
 ///
@@ -9,7 +9,7 @@ use crate::traits;
 /// For more infos about the addresses and what they are doing look into the PDFs.
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift
     /// direction, booster switch, soft reset.
     PanelSetting = 0x00,
@@ -138,6 +138,29 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff | Command::PowerOn | Command::DataStop | Command::DisplayRefresh => {
+                DataLen::Exact(0)
+            }
+            Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::PllControl
+            | Command::TemperatureCalibration
+            | Command::VcomAndDataIntervalSetting
+            | Command::LowPowerDetection
+            | Command::TconSetting
+            | Command::AutoMeasurementVcom
+            | Command::VcmDcSetting
+            | Command::FlashMode => DataLen::Exact(1),
+            Command::PanelSetting => DataLen::Exact(2),
+            Command::BoosterSoftStart => DataLen::Exact(3),
+            Command::TconResolution => DataLen::Exact(4),
+            Command::PowerSetting => DataLen::Max(4),
+            _ => DataLen::Variable,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -150,4 +173,14 @@ mod tests {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::PanelSetting.expected_data_len().accepts(2));
+        assert!(!Command::PanelSetting.expected_data_len().accepts(1));
+        assert!(Command::BoosterSoftStart.expected_data_len().accepts(3));
+        assert!(!Command::BoosterSoftStart.expected_data_len().accepts(4));
+        assert!(Command::PowerSetting.expected_data_len().accepts(2));
+        assert!(!Command::PowerSetting.expected_data_len().accepts(5));
+    }
 }
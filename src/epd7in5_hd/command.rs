@@ -1,6 +1,6 @@
 //! SPI Commands for the Waveshare 7.5" E-Ink Display
 
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 /// EPD7in5 commands
 ///
@@ -10,7 +10,7 @@ use crate::traits;
 #[allow(dead_code)]
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     DriverOutputControl = 0x01,
 
     /// Set gate driving voltage
@@ -94,6 +94,9 @@ pub(crate) enum Command {
     /// Read Register for Display Option
     OtpRead = 0x2D,
 
+    /// Read the 10 byte user ID stored in the OTP
+    UserIdRead = 0x2E,
+
     /// CRC calculation command for OTP content validation
     CrcCalculation = 0x34,
 
@@ -143,6 +146,45 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::SwReset
+            | Command::MasterActivation
+            | Command::VcomSense
+            | Command::VcomProgramOtp
+            | Command::CrcCalculation
+            | Command::ProgramSelection
+            | Command::Nop => DataLen::Exact(0),
+            Command::GateDrivingVoltageControl
+            | Command::DeepSleep
+            | Command::DataEntry
+            | Command::HvReadyDetection
+            | Command::VciDetection
+            | Command::TemperatureSensorControl
+            | Command::DisplayUpdateControl2
+            | Command::VcomSenseDuration
+            | Command::VcomWrite
+            | Command::VbdControl
+            | Command::ReadRamOption
+            | Command::AutoWriteRed
+            | Command::AutoWriteBw => DataLen::Exact(1),
+            Command::GateScanStartPosition
+            | Command::TemperatureSensorWrite
+            | Command::VcomControl
+            | Command::SetRamXAc
+            | Command::SetRamYAc => DataLen::Exact(2),
+            Command::DriverOutputControl | Command::SourceDrivingVoltageControl => {
+                DataLen::Exact(3)
+            }
+            Command::SetRamXStartEnd | Command::SetRamYStartEnd => DataLen::Exact(4),
+            Command::SoftStart => DataLen::Exact(5),
+            Command::DisplayOptionWrite | Command::UserIdWrite => DataLen::Exact(10),
+            Command::DisplayUpdateControl1 => DataLen::Max(2),
+            Command::TemperatureSensorWriteExternal => DataLen::Max(3),
+            _ => DataLen::Variable,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -156,4 +198,14 @@ mod tests {
         assert_eq!(Command::SwReset.address(), 0x12);
         assert_eq!(Command::DisplayUpdateControl2.address(), 0x22);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::SoftStart.expected_data_len().accepts(5));
+        assert!(!Command::SoftStart.expected_data_len().accepts(4));
+        assert!(Command::SetRamXStartEnd.expected_data_len().accepts(4));
+        assert!(!Command::SetRamXStartEnd.expected_data_len().accepts(2));
+        assert!(Command::SetRamXAc.expected_data_len().accepts(2));
+        assert!(!Command::SetRamXAc.expected_data_len().accepts(1));
+    }
 }
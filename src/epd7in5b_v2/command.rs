@@ -1,6 +1,7 @@
 //! SPI Commands for the Waveshare 7.5"(B) V2 and V3 -Ink Display
 
-use crate::traits;
+use crate::color::Plane;
+use crate::traits::{self, DataLen};
 
 /// Epd7in5 commands
 ///
@@ -9,7 +10,7 @@ use crate::traits;
 /// For more infos about the addresses and what they are doing look into the PDFs.
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift
     /// direction, booster switch, soft reset.
     PanelSetting = 0x00,
@@ -151,6 +152,42 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOff
+            | Command::PowerOn
+            | Command::DataStop
+            | Command::DisplayRefresh
+            | Command::PartialIn
+            | Command::PartialOut => DataLen::Exact(0),
+            Command::PowerOffSequenceSetting
+            | Command::DeepSleep
+            | Command::DualSpi
+            | Command::PllControl
+            | Command::TemperatureCalibration
+            | Command::LowPowerDetection
+            | Command::TconSetting
+            | Command::AutoMeasurementVcom
+            | Command::VcmDcSetting => DataLen::Exact(1),
+            Command::PanelSetting => DataLen::Max(2),
+            Command::VcomAndDataIntervalSetting => DataLen::Exact(2),
+            Command::BoosterSoftStart | Command::TconResolution | Command::SpiFlashControl => {
+                DataLen::Exact(4)
+            }
+            Command::PowerSetting => DataLen::Max(5),
+            Command::PartialWindow => DataLen::Exact(9),
+            _ => DataLen::Variable,
+        }
+    }
+
+    fn plane(self) -> Option<Plane> {
+        match self {
+            Command::DataStartTransmission1 => Some(Plane::BlackWhite),
+            Command::DataStartTransmission2 => Some(Plane::Chromatic),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -163,4 +200,18 @@ mod tests {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::VcomAndDataIntervalSetting
+            .expected_data_len()
+            .accepts(2));
+        assert!(!Command::VcomAndDataIntervalSetting
+            .expected_data_len()
+            .accepts(1));
+        assert!(Command::TconResolution.expected_data_len().accepts(4));
+        assert!(!Command::TconResolution.expected_data_len().accepts(3));
+        assert!(Command::PowerSetting.expected_data_len().accepts(4));
+        assert!(!Command::PowerSetting.expected_data_len().accepts(6));
+    }
 }
@@ -9,6 +9,62 @@
 //! Important note for V2:
 //! Revision V2 has been released on 2019.11, the resolution is upgraded to 800×480, from 640×384 of V1.
 //! The hardware and interface of V2 are compatible with V1, however, the related software should be updated.
+//!
+//! The controller is powered off after every refresh, as the datasheet requires to
+//! avoid damaging the panel. It is powered on again by the next transfer.
+//!
+//! # Example
+//!
+//! A full [`Display7in5b`] needs 96 KB of RAM. With less memory the planes can be
+//! rendered in horizontal bands into a small [`VarDisplay`](crate::graphics::VarDisplay)
+//! and streamed with [`Epd7in5::begin_plane`] and [`Epd7in5::write_plane`]:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use embedded_graphics::{
+//!    prelude::*,
+//!    primitives::{Circle, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{color::*, epd7in5b_v2::*, graphics::VarDisplay, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let cs_pin = pin::Mock::new(&[]);
+//!# let busy_in = pin::Mock::new(&[]);
+//!# let dc = pin::Mock::new(&[]);
+//!# let rst = pin::Mock::new(&[]);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd7in5::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// 40 rows of both planes: 2 * 100 * 40 bytes instead of 96 KB
+//!const ROWS: u32 = 40;
+//!let mut buffer = [0u8; 2 * 100 * ROWS as usize];
+//!
+//!for plane in [Plane::BlackWhite, Plane::Chromatic] {
+//!    epd.begin_plane(&mut spi, &mut delay, plane)?;
+//!    for top in (0..HEIGHT).step_by(ROWS as usize) {
+//!        let mut band = VarDisplay::<TriColor>::new(WIDTH, ROWS, &mut buffer, false).unwrap();
+//!        band.clear(TriColor::White).ok();
+//!        // draw everything shifted up by the first row of the band
+//!        Circle::new(Point::new(300, 140 - top as i32), 200)
+//!            .into_styled(PrimitiveStyle::with_fill(TriColor::Chromatic))
+//!            .draw(&mut band)
+//!            .ok();
+//!        let data = match plane {
+//!            Plane::BlackWhite => band.bw_buffer(),
+//!            Plane::Chromatic => band.chromatic_buffer(),
+//!        };
+//!        epd.write_plane(&mut spi, data)?;
+//!    }
+//!    epd.end_plane(&mut spi)?;
+//!}
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
 
 use embedded_hal::{
     delay::DelayNs,
@@ -16,36 +72,68 @@ use embedded_hal::{
     spi::SpiDevice,
 };
 
-use crate::color::TriColor;
+use crate::busy::{RefreshLimits, WaitOutcome};
+use crate::color::{Plane, PlaneConvention, TriColor};
+use crate::error::{self, Error};
+use crate::frame::FrameData;
 use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    check_window, ChromaticBand, DriverControl, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{line_bytes, tri_buffer_len};
 
 /// Full size buffer for use with the 7in5b v2 EPD
+///
+/// This takes 96 KB, see the module documentation for rendering in bands instead.
 #[cfg(feature = "graphics")]
-pub type Display7in5 = crate::graphics::Display<
+pub type Display7in5b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    { tri_buffer_len(WIDTH as usize, HEIGHT as usize) },
     TriColor,
 >;
 
+/// Full size buffer for use with the 7in5b v2 EPD
+#[cfg(feature = "graphics")]
+#[deprecated(note = "use `Display7in5b` instead")]
+pub type Display7in5 = Display7in5b;
+
 /// Width of the display
 pub const WIDTH: u32 = 800;
 /// Height of the display
 pub const HEIGHT: u32 = 480;
+/// Size and refresh capabilities of the display, see [SUPPORTED_PANELS](crate::panels::SUPPORTED_PANELS)
+pub const CAPABILITIES: crate::panels::PanelInfo = crate::panels::PanelInfo {
+    name: "epd7in5b_v2",
+    width: WIDTH,
+    height: HEIGHT,
+    colors: 3,
+    partial: false,
+    quick_refresh: false,
+};
+/// Hardware reset sequence of the panel, see [crate::reset]
+pub const RESET_CONFIG: ResetConfig = ResetConfig::new(200_000, 2_000);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Typical and maximum refresh times, see [crate::busy::RefreshLimits]
+pub const REFRESH_LIMITS: RefreshLimits = RefreshLimits::new(20_000_000, 30_000_000);
+/// Shortest time between two full refreshes in seconds recommended for the lifetime of
+/// the panel, enforced by the refresh guard, see [crate::policy]
+pub const MIN_REFRESH_INTERVAL: u32 = 180;
 
 /// Number of bytes for b/w buffer and same for chromatic buffer bits
-const NUM_DISPLAY_BITS: usize = WIDTH as usize / 8 * HEIGHT as usize;
+const NUM_DISPLAY_BITS: usize = line_bytes(WIDTH) as usize * HEIGHT as usize;
 const IS_BUSY_LOW: bool = true;
+/// Bit sense of the black/white plane
+const BW_CONVENTION: PlaneConvention = PlaneConvention::OneIsWhite;
+/// Bit sense of the chromatic plane
+const CHROMATIC_CONVENTION: PlaneConvention = PlaneConvention::OneIsChromatic;
 const SINGLE_BYTE_WRITE: bool = false;
 
 /// Epd7in5 (V2) driver
@@ -55,6 +143,8 @@ pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: TriColor,
+    /// Whether the controller is powered on, it is powered off after every refresh
+    powered: bool,
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
@@ -66,10 +156,34 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    type Interface = DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+    type Command = Command;
+
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const RESET_CONFIG: ResetConfig = RESET_CONFIG;
+    const REFRESH_LIMITS: RefreshLimits = REFRESH_LIMITS;
+    const SELF_TEST_COMMAND: Command = Command::PowerOn;
+
+    fn from_interface(interface: Self::Interface) -> Self {
+        Epd7in5 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            powered: false,
+        }
+    }
+
+    fn interface(&self) -> &Self::Interface {
+        &self.interface
+    }
+
+    fn interface_mut(&mut self) -> &mut Self::Interface {
+        &mut self.interface
+    }
+
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
         // C driver does 200/2 original rust driver does 10/2
-        self.interface.reset(delay, 200_000, 2_000);
+        self.interface.reset(delay, RESET_CONFIG);
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
@@ -77,9 +191,8 @@ where
         // https://www.waveshare.com/w/upload/6/60/7.5inch_e-Paper_V2_Specification.pdf
 
         self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x07, 0x3F, 0x3F])?;
-        self.command(spi, Command::PowerOn)?;
-        // C driver adds a static 100ms delay here
-        self.wait_until_idle(spi, delay)?;
+        // C driver adds a static 100ms delay after the power on
+        self.power_on(spi, delay)?;
         // Done, but this is also the default
         // 0x1F = B/W mode ? doesnt seem to work
         self.cmd_with_data(spi, Command::PanelSetting, &[0x0F])?;
@@ -104,6 +217,43 @@ where
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::DisplayFrame);
+        self.interface.guard_refresh(MIN_REFRESH_INTERVAL)?;
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_refresh(false);
+        Ok(())
+    }
+
+    fn finish_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.power_off(spi, delay)
+    }
+
+    fn wait_until_idle_cancellable(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        self.interface.wait_until_idle_with_cmd_cancellable(
+            spi,
+            delay,
+            IS_BUSY_LOW,
+            Command::GetStatus,
+            cancel,
+        )
+    }
 }
 
 impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
@@ -121,9 +271,27 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.update_achromatic_frame(spi, delay, black)?;
-        self.update_chromatic_frame(spi, delay, chromatic)
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::new(black),
+            &mut FrameData::new(chromatic),
+        )
+    }
+
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        black.check_len(NUM_DISPLAY_BITS)?;
+        chromatic.check_len(NUM_DISPLAY_BITS)?;
+        self.write_achromatic(spi, delay, black)?;
+        self.write_chromatic(spi, delay, chromatic)
     }
 
     /// Update only the black/white data of the display.
@@ -132,13 +300,10 @@ where
     fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        _delay: &mut DELAY,
+        delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data(spi, black)?;
-        self.interface.cmd(spi, Command::DataStop)?;
-        Ok(())
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_achromatic(spi, delay, &mut FrameData::new(black))
     }
 
     /// Update only chromatic data of the display.
@@ -149,10 +314,28 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_chromatic(spi, delay, &mut FrameData::new(chromatic))
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let fill = chromatic
+            .fill_lens(WIDTH, HEIGHT)
+            .ok_or(Error::InvalidWindow)?;
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.update_achromatic_frame(spi, delay, black)?;
+        self.ensure_powered(spi, delay)?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
-        self.interface.cmd(spi, Command::DataStop)?;
+        let background = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
+        self.interface
+            .data_band(spi, chromatic.data, background, fill)?;
+        self.data_stop(spi)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
@@ -169,6 +352,9 @@ where
     DELAY: DelayNs,
 {
     type DisplayColor = TriColor;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = tri_buffer_len(WIDTH as usize, HEIGHT as usize);
     fn new(
         spi: &mut SPI,
         busy: BUSY,
@@ -176,25 +362,28 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd7in5 { interface, color };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_reset_config(spi, busy, dc, rst, delay, delay_us, RESET_CONFIG)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::WakeUp);
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.command(spi, Command::PowerOff)?;
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.powered = false;
+        self.interface
+            .abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Sleep);
         self.wait_until_idle(spi, delay)?;
+        // already done after the last refresh
+        if self.powered {
+            self.power_off(spi, delay)?;
+        }
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
         Ok(())
     }
@@ -204,20 +393,34 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::new(buffer), delay)
+    }
+
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        frame.check_len(tri_buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
         // (B) version sends one buffer for black and one for red
-        self.cmd_with_data(
+        self.interface.cmd_with_frame(
             spi,
             Command::DataStartTransmission1,
-            &buffer[..NUM_DISPLAY_BITS],
+            frame,
+            0..NUM_DISPLAY_BITS,
         )?;
-        self.cmd_with_data(
+        self.interface.cmd_with_frame(
             spi,
             Command::DataStartTransmission2,
-            &buffer[NUM_DISPLAY_BITS..],
+            frame,
+            NUM_DISPLAY_BITS..frame.len(),
         )?;
-        self.interface.cmd(spi, Command::DataStop)?;
+        self.data_stop(spi)?;
         Ok(())
     }
 
@@ -226,18 +429,25 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .set_operation(error::Operation::PartialUpdate);
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         unimplemented!()
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Refreshes the display and powers the controller off afterwards
+    ///
+    /// The datasheet requires the power off after every refresh to avoid damaging
+    /// the panel. A refresh takes about 20 s.
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
         self.wait_until_idle(spi, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.finish_display_frame(spi, delay)
     }
 
     fn update_and_display_frame(
@@ -245,26 +455,28 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+        self.display_frame(spi, delay)
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::ClearFrame);
         self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
         self.send_resolution(spi)?;
 
+        let bw = self.color.fill_byte(Plane::BlackWhite, BW_CONVENTION);
         self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0xFF, WIDTH / 8 * HEIGHT)?;
+        self.interface
+            .data_x_times(spi, bw, line_bytes(WIDTH) * HEIGHT)?;
 
+        let chromatic = self.color.fill_byte(Plane::Chromatic, CHROMATIC_CONVENTION);
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, 0x00, WIDTH / 8 * HEIGHT)?;
-
-        self.interface.cmd(spi, Command::DataStop)?;
-
-        self.command(spi, Command::DisplayRefresh)?;
+        self.interface
+            .data_x_times(spi, chromatic, line_bytes(WIDTH) * HEIGHT)?;
 
+        self.data_stop(spi)?;
         Ok(())
     }
 
@@ -289,12 +501,17 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::Lut);
         unimplemented!();
     }
 
     /// wait
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface
             .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)
     }
@@ -308,6 +525,39 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Sends the black/white plane, see [WaveshareThreeColorDisplay::update_achromatic_frame]
+    fn write_achromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_powered(spi, delay)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data_frame(spi, black, 0..black.len())?;
+        self.data_stop(spi)?;
+        Ok(())
+    }
+
+    /// Sends the chromatic plane, see [WaveshareThreeColorDisplay::update_chromatic_frame]
+    fn write_chromatic(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.ensure_powered(spi, delay)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_frame(spi, chromatic, 0..chromatic.len())?;
+        self.data_stop(spi)?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
+
     /// temporary replacement for missing delay in the trait to call wait_until_idle
     #[allow(clippy::too_many_arguments)]
     pub fn update_partial_frame2(
@@ -319,9 +569,11 @@ where
         width: u32,
         height: u32,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_window(x, y, width, height, WIDTH, HEIGHT)?;
         self.wait_until_idle(spi, delay)?;
-        if buffer.len() as u32 != width / 8 * height {
+        self.ensure_powered(spi, delay)?;
+        if buffer.len() as u32 != line_bytes(width) * height {
             //TODO panic or error
         }
 
@@ -349,17 +601,90 @@ where
         self.cmd_with_data(spi, Command::DataStartTransmission2, &buffer[half..])?;
 
         self.command(spi, Command::DisplayRefresh)?;
+        self.interface.count_partial_refresh();
         self.wait_until_idle(spi, delay)?;
 
         self.command(spi, Command::PartialOut)?;
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Starts streaming one plane of a full frame
+    ///
+    /// Send the rows of the plane from top to bottom with any number of
+    /// [`write_plane`](Self::write_plane) calls and finish with
+    /// [`end_plane`](Self::end_plane). Together with a small
+    /// [`VarDisplay`](crate::graphics::VarDisplay) this renders a frame without the
+    /// 96 KB of a full [`Display7in5b`], see the module documentation.
+    pub fn begin_plane(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        plane: Plane,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.wait_until_idle(spi, delay)?;
+        self.ensure_powered(spi, delay)?;
+        self.command(
+            spi,
+            match plane {
+                Plane::BlackWhite => Command::DataStartTransmission1,
+                Plane::Chromatic => Command::DataStartTransmission2,
+            },
+        )
+    }
+
+    /// Sends the next rows of the plane started with [`begin_plane`](Self::begin_plane)
+    pub fn write_plane(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.send_data(spi, data)
+    }
+
+    /// Finishes the plane started with [`begin_plane`](Self::begin_plane)
+    pub fn end_plane(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.interface.set_operation(error::Operation::UpdateFrame);
+        self.data_stop(spi)
+    }
+
+    fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.powered = true;
+        Ok(())
+    }
+
+    fn power_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)?;
+        self.powered = false;
+        Ok(())
+    }
+
+    fn ensure_powered(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.powered {
+            self.power_on(spi, delay)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the transmission of a plane, keeping the checksum of its data intact
+    fn data_stop(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        #[cfg(feature = "checksum")]
+        let checksum = self.interface.checksum();
+        self.interface.cmd(spi, Command::DataStop)?;
+        #[cfg(feature = "checksum")]
+        self.interface.set_checksum(checksum);
+        Ok(())
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -368,25 +693,26 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
-        self.command(spi, Command::TconResolution)?;
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Bus, BusyPin, Delay, Pin};
 
     #[test]
     fn epd_size() {
@@ -394,4 +720,39 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
     }
+
+    #[test]
+    fn power_off_after_refresh() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        // without the status polls
+        let commands = |bus: &Bus| bus.commands().into_iter().filter(|&c| c != 0x71);
+
+        bus.clear();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        // polled until the refresh is done before the power off
+        assert_eq!(bus.commands(), [0x71, 0x12, 0x71, 0x02, 0x71]);
+
+        // powered on again for the next transfer
+        bus.clear();
+        epd.update_color_frame(&mut spi, &mut delay, &[0xFF], &[0x00])
+            .unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        assert!(commands(&bus).eq([0x04, 0x10, 0x11, 0x13, 0x11, 0x12, 0x02]));
+
+        // no second power off before the deep sleep
+        bus.clear();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        assert!(commands(&bus).eq([0x07]));
+    }
 }
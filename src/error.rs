@@ -0,0 +1,189 @@
+//! Errors returned by the drivers
+
+use core::fmt;
+
+/// Driver operation during which an error occurred, see [`Context`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Creating the driver, which initialises the display
+    Init,
+    /// `wake_up` (or `recover`), initialising the display again
+    WakeUp,
+    /// Sending the display to sleep
+    Sleep,
+    /// Sending a frame
+    UpdateFrame,
+    /// Sending or refreshing a window of the display
+    PartialUpdate,
+    /// Refreshing the display
+    DisplayFrame,
+    /// Clearing the frame or a region of it
+    ClearFrame,
+    /// Selecting or uploading a LUT
+    Lut,
+    /// Powering the controller on or off between refreshes
+    Power,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Init => "init",
+            Operation::WakeUp => "wake up",
+            Operation::Sleep => "sleep",
+            Operation::UpdateFrame => "frame update",
+            Operation::PartialUpdate => "partial update",
+            Operation::DisplayFrame => "refresh",
+            Operation::ClearFrame => "clear",
+            Operation::Lut => "LUT selection",
+            Operation::Power => "power switching",
+        })
+    }
+}
+
+/// Where a transfer failed: the operation of the driver and the display
+///
+/// Tells several displays driven from one place apart without adding context at every
+/// call site. The instance is the id set with `set_instance_id` of the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Context {
+    /// Operation the driver was doing, `None` if unknown
+    pub operation: Option<Operation>,
+    /// Id of the display given to `set_instance_id`, `None` if none was set
+    pub instance: Option<u8>,
+}
+
+impl Context {
+    /// Context of `operation` without an instance id
+    pub const fn new(operation: Operation) -> Self {
+        Context {
+            operation: Some(operation),
+            instance: None,
+        }
+    }
+
+    /// Adds the id of the display
+    pub const fn with_instance(mut self, id: u8) -> Self {
+        self.instance = Some(id);
+        self
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(operation) = self.operation {
+            write!(f, " during {}", operation)?;
+        }
+        if let Some(id) = self.instance {
+            write!(f, " of display {}", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by the display drivers
+///
+/// `E` is the error type of the used spi device. Failed transfers carry the
+/// [`Context`] they happened in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error<E> {
+    /// The spi device failed to transfer a command or data
+    ///
+    /// The controller might have been left in the middle of a transfer, so the
+    /// driver refuses further transfers with [`Error::NeedsReset`] until it is
+    /// recovered with `recover` (or `wake_up`).
+    Spi(E, Context),
+    /// A previous transfer failed, the display needs to be reset and initialised again
+    NeedsReset(Context),
+    /// A partial update session is still open, it needs to be finished with
+    /// `end_partial_and_display` (or `refresh_written` on the SSD16xx drivers) first
+    PartialSessionActive,
+    /// No partial update session was started with `begin_partial` (or no window was
+    /// written with `write_ram_window`)
+    NoPartialSession,
+    /// The refresh guard refused a refresh, the minimum interval of the display
+    /// (`MIN_REFRESH_INTERVAL`) since the last one hasn't passed yet
+    ///
+    /// Nothing was sent to the display, see [crate::policy] for the guard.
+    RefreshTooSoon,
+    /// The window of a partial update is empty, starts at a negative coordinate or
+    /// reaches beyond the panel
+    ///
+    /// Nothing was sent to the display.
+    InvalidWindow,
+    /// The driver doesn't support the operation, e.g. clearing a part of the panel
+    ///
+    /// Nothing was sent to the display.
+    Unsupported,
+    /// The parameters of a command don't have the length the controller expects, e.g.
+    /// a custom LUT of the wrong size
+    ///
+    /// Nothing was sent to the display.
+    InvalidDataLength,
+    /// The display update sequence has steps the controller can't run, see
+    /// [crate::update_sequence]
+    ///
+    /// Nothing was sent to the display.
+    InvalidUpdateSequence,
+    /// A [replay](crate::command_log::replay) found no frame data for a recorded operation
+    ///
+    /// Nothing was sent to the display.
+    UnknownFrame,
+    /// A driving voltage is outside of the range of the controller or between two of
+    /// its steps, see [crate::voltage]
+    ///
+    /// Nothing was changed.
+    InvalidVoltage(crate::voltage::Rail),
+}
+
+impl<E> Error<E> {
+    /// A failed transfer of `error` without context, e.g. to convert the error of
+    /// setting up the spi device
+    pub const fn spi(error: E) -> Self {
+        Error::Spi(
+            error,
+            Context {
+                operation: None,
+                instance: None,
+            },
+        )
+    }
+
+    /// Context of a failed transfer, `None` for the other errors
+    pub fn context(&self) -> Option<Context> {
+        match self {
+            Error::Spi(_, context) | Error::NeedsReset(context) => Some(*context),
+            _ => None,
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Spi(e, context) => write!(f, "spi error{}: {:?}", context, e),
+            Error::NeedsReset(context) => write!(
+                f,
+                "display needs a reset after a failed transfer{}",
+                context
+            ),
+            Error::PartialSessionActive => f.write_str("partial update session still active"),
+            Error::NoPartialSession => f.write_str("no partial update session active"),
+            Error::RefreshTooSoon => f.write_str("refresh too soon after the last one"),
+            Error::InvalidWindow => f.write_str("partial update window outside of the panel"),
+            Error::Unsupported => f.write_str("operation not supported by the display"),
+            Error::InvalidDataLength => f.write_str("wrong number of command parameters"),
+            Error::InvalidUpdateSequence => f.write_str("invalid display update sequence"),
+            Error::UnknownFrame => f.write_str("no frame data for a recorded operation"),
+            Error::InvalidVoltage(rail) => {
+                write!(
+                    f,
+                    "driving voltage {:?} not supported by the controller",
+                    rail
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,568 @@
+//! C interface for the 4.2" and the 2.13" V2/V3 displays
+//!
+//! Only available with the `ffi` feature, which needs neither `std` nor `alloc`.
+//! The C declarations are in `include/epd_waveshare.h`. The functions are exported
+//! from a `staticlib` (or `cdylib`) crate depending on this one, which can be linked
+//! into the C firmware.
+//!
+//! The hardware is reached through the C function pointers of [`EpdCallbacks`]:
+//! spi writes, setting and reading the pins and delays. They get the `ctx` pointer
+//! of the callbacks as first argument, which is never touched on the Rust side.
+//!
+//! The driver lives in storage owned by the caller ([`Epd4in2Handle`],
+//! [`Epd2in13Handle`]), filled by `epd4in2_new` / `epd2in13_new`. Frame buffers stay
+//! owned by the caller as well, they are only read during the call.
+//!
+//! All functions return [`EPD_OK`] or one of the negative `EPD_ERR_*` codes.
+//!
+//! ```c
+//! epd4in2_t epd;
+//! epd_callbacks_t callbacks = { &board, spi_write, pin_set, pin_get, delay_us };
+//! if (epd4in2_new(&epd, &callbacks) == EPD_OK) {
+//!     epd4in2_update_frame(&epd, frame, EPD4IN2_BUFFER_LEN);
+//!     epd4in2_display_frame(&epd);
+//!     epd4in2_sleep(&epd);
+//! }
+//! ```
+
+use core::ffi::c_void;
+use core::mem::{align_of, size_of};
+use core::{ptr, slice};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{self, InputPin, OutputPin},
+    spi::{self, Operation, SpiDevice},
+};
+
+use crate::buffer_len;
+use crate::epd2in13_v2::{self, Epd2in13};
+use crate::epd4in2::{self, Epd4in2};
+use crate::error::Error;
+use crate::traits::WaveshareDisplay;
+
+/// The call succeeded
+pub const EPD_OK: i32 = 0;
+/// A handle, callback or buffer pointer was NULL
+pub const EPD_ERR_NULL: i32 = -1;
+/// A callback returned an error during a transfer
+pub const EPD_ERR_SPI: i32 = -2;
+/// A previous transfer failed, the display has to be woken up (or created) again
+pub const EPD_ERR_NEEDS_RESET: i32 = -3;
+/// The frame buffer doesn't have the size of the display
+pub const EPD_ERR_BUFFER_LEN: i32 = -4;
+/// Any other error of the driver
+pub const EPD_ERR_OTHER: i32 = -5;
+
+/// The busy pin, passed to `pin_get`
+pub const EPD_PIN_BUSY: u8 = 0;
+/// The data/command pin, passed to `pin_set`
+pub const EPD_PIN_DC: u8 = 1;
+/// The reset pin, passed to `pin_set`
+pub const EPD_PIN_RST: u8 = 2;
+
+/// Callbacks to the hardware of the C side
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EpdCallbacks {
+    /// Passed to every callback
+    pub ctx: *mut c_void,
+    /// Writes `len` bytes with the chip select asserted, returns 0 or a negative error
+    pub spi_write: unsafe extern "C" fn(ctx: *mut c_void, data: *const u8, len: usize) -> i32,
+    /// Sets one of the `EPD_PIN_*` output pins, returns 0 or a negative error
+    pub pin_set: unsafe extern "C" fn(ctx: *mut c_void, pin: u8, high: bool) -> i32,
+    /// Reads one of the `EPD_PIN_*` input pins, returns 1 for high, 0 for low or a
+    /// negative error
+    pub pin_get: unsafe extern "C" fn(ctx: *mut c_void, pin: u8) -> i32,
+    /// Waits for at least `us` microseconds
+    pub delay_us: unsafe extern "C" fn(ctx: *mut c_void, us: u32),
+}
+
+/// Negative return value of a callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallbackError(pub i32);
+
+impl spi::Error for CallbackError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl digital::Error for CallbackError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+fn check(ret: i32) -> Result<(), CallbackError> {
+    if ret < 0 {
+        Err(CallbackError(ret))
+    } else {
+        Ok(())
+    }
+}
+
+/// [`SpiDevice`] over [`EpdCallbacks::spi_write`], reads aren't supported
+#[derive(Debug, Clone, Copy)]
+pub struct CallbackSpi(EpdCallbacks);
+
+impl spi::ErrorType for CallbackSpi {
+    type Error = CallbackError;
+}
+
+impl SpiDevice for CallbackSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), CallbackError> {
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => {
+                    // SAFETY: the pointer and length come from a valid slice
+                    check(unsafe { (self.0.spi_write)(self.0.ctx, data.as_ptr(), data.len()) })?
+                }
+                Operation::DelayNs(ns) => CallbackDelay(self.0).delay_ns(*ns),
+                _ => return Err(CallbackError(EPD_ERR_OTHER)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// [`OutputPin`] and [`InputPin`] over [`EpdCallbacks::pin_set`] and
+/// [`EpdCallbacks::pin_get`]
+#[derive(Debug, Clone, Copy)]
+pub struct CallbackPin(EpdCallbacks, u8);
+
+impl digital::ErrorType for CallbackPin {
+    type Error = CallbackError;
+}
+
+impl OutputPin for CallbackPin {
+    fn set_low(&mut self) -> Result<(), CallbackError> {
+        // SAFETY: the callbacks were checked when the handle was created
+        check(unsafe { (self.0.pin_set)(self.0.ctx, self.1, false) })
+    }
+
+    fn set_high(&mut self) -> Result<(), CallbackError> {
+        // SAFETY: see set_low
+        check(unsafe { (self.0.pin_set)(self.0.ctx, self.1, true) })
+    }
+}
+
+impl InputPin for CallbackPin {
+    fn is_high(&mut self) -> Result<bool, CallbackError> {
+        // SAFETY: see set_low
+        let level = unsafe { (self.0.pin_get)(self.0.ctx, self.1) };
+        check(level).map(|_| level != 0)
+    }
+
+    fn is_low(&mut self) -> Result<bool, CallbackError> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// [`DelayNs`] over [`EpdCallbacks::delay_us`]
+#[derive(Debug, Clone, Copy)]
+pub struct CallbackDelay(EpdCallbacks);
+
+impl DelayNs for CallbackDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        // SAFETY: see CallbackPin::set_low
+        unsafe { (self.0.delay_us)(self.0.ctx, us) }
+    }
+}
+
+/// The driver and the callbacks it was created with, stored in a handle
+struct Slot<EPD> {
+    callbacks: EpdCallbacks,
+    epd: EPD,
+}
+
+/// Frame buffer size of the 4.2" display
+pub const EPD4IN2_BUFFER_LEN: usize = buffer_len(epd4in2::WIDTH as usize, epd4in2::HEIGHT as usize);
+/// Frame buffer size of the 2.13" V2/V3 display
+pub const EPD2IN13_BUFFER_LEN: usize =
+    buffer_len(epd2in13_v2::WIDTH as usize, epd2in13_v2::HEIGHT as usize);
+
+/// Size of [`Epd4in2Handle`] in bytes
+pub const EPD4IN2_HANDLE_SIZE: usize = 512;
+/// Size of [`Epd2in13Handle`] in bytes
+pub const EPD2IN13_HANDLE_SIZE: usize = 512;
+
+/// Storage for an [`Epd4in2`] driver, `epd4in2_t` in C
+#[repr(C, align(8))]
+pub struct Epd4in2Handle {
+    _opaque: [u8; EPD4IN2_HANDLE_SIZE],
+}
+
+/// Storage for an [`Epd2in13`] driver, `epd2in13_t` in C
+#[repr(C, align(8))]
+pub struct Epd2in13Handle {
+    _opaque: [u8; EPD2IN13_HANDLE_SIZE],
+}
+
+type Epd4in2Slot = Slot<Epd4in2<CallbackSpi, CallbackPin, CallbackPin, CallbackPin, CallbackDelay>>;
+type Epd2in13Slot =
+    Slot<Epd2in13<CallbackSpi, CallbackPin, CallbackPin, CallbackPin, CallbackDelay>>;
+
+const _: () = assert!(
+    size_of::<Epd4in2Slot>() <= EPD4IN2_HANDLE_SIZE && align_of::<Epd4in2Slot>() <= 8,
+    "Epd4in2Handle is too small for the driver"
+);
+const _: () = assert!(
+    size_of::<Epd2in13Slot>() <= EPD2IN13_HANDLE_SIZE && align_of::<Epd2in13Slot>() <= 8,
+    "Epd2in13Handle is too small for the driver"
+);
+
+fn error_code(error: Error<CallbackError>) -> i32 {
+    match error {
+        Error::Spi(..) => EPD_ERR_SPI,
+        Error::NeedsReset(_) => EPD_ERR_NEEDS_RESET,
+        _ => EPD_ERR_OTHER,
+    }
+}
+
+fn result_code(result: Result<(), Error<CallbackError>>) -> i32 {
+    result.map_or_else(error_code, |()| EPD_OK)
+}
+
+/// Creates the driver in `handle`, see [`WaveshareDisplay::new`]
+unsafe fn new<EPD>(handle: *mut Slot<EPD>, callbacks: *const EpdCallbacks) -> i32
+where
+    EPD: WaveshareDisplay<CallbackSpi, CallbackPin, CallbackPin, CallbackPin, CallbackDelay>,
+{
+    if handle.is_null() || callbacks.is_null() {
+        return EPD_ERR_NULL;
+    }
+    let callbacks = *callbacks;
+    let epd = EPD::new(
+        &mut CallbackSpi(callbacks),
+        CallbackPin(callbacks, EPD_PIN_BUSY),
+        CallbackPin(callbacks, EPD_PIN_DC),
+        CallbackPin(callbacks, EPD_PIN_RST),
+        &mut CallbackDelay(callbacks),
+        None,
+    );
+    match epd {
+        Ok(epd) => {
+            ptr::write(handle, Slot { callbacks, epd });
+            EPD_OK
+        }
+        Err(error) => error_code(error),
+    }
+}
+
+/// Runs `f` on the driver in `handle`
+unsafe fn with<EPD>(
+    handle: *mut Slot<EPD>,
+    f: impl FnOnce(&mut EPD, &mut CallbackSpi, &mut CallbackDelay) -> Result<(), Error<CallbackError>>,
+) -> i32 {
+    let Some(slot) = handle.as_mut() else {
+        return EPD_ERR_NULL;
+    };
+    let mut spi = CallbackSpi(slot.callbacks);
+    let mut delay = CallbackDelay(slot.callbacks);
+    result_code(f(&mut slot.epd, &mut spi, &mut delay))
+}
+
+/// Sends a frame of `len` bytes, which has to be `BUFFER_LEN` of the display
+unsafe fn update_frame<EPD>(handle: *mut Slot<EPD>, buffer: *const u8, len: usize) -> i32
+where
+    EPD: WaveshareDisplay<CallbackSpi, CallbackPin, CallbackPin, CallbackPin, CallbackDelay>,
+{
+    if buffer.is_null() {
+        return EPD_ERR_NULL;
+    }
+    if len != EPD::BUFFER_LEN {
+        return EPD_ERR_BUFFER_LEN;
+    }
+    let buffer = slice::from_raw_parts(buffer, len);
+    with(handle, |epd: &mut EPD, spi, delay| {
+        epd.update_frame(spi, buffer, delay)
+    })
+}
+
+/// Declares the C functions of a driver
+macro_rules! ffi_driver {
+    (
+        $epd:literal, $handle:ty, $slot:ty,
+        $new:ident, $update_frame:ident, $display_frame:ident, $clear:ident, $sleep:ident,
+        $wake_up:ident
+    ) => {
+        #[doc = concat!("Creates the ", $epd, " driver in `handle`")]
+        ///
+        /// Resets and initialises the display. On errors `handle` stays uninitialised.
+        ///
+        /// # Safety
+        ///
+        /// `handle` has to point to writable storage and `callbacks` to valid callbacks,
+        /// which are copied. They are called with `ctx` as long as the handle is used.
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(handle: *mut $handle, callbacks: *const EpdCallbacks) -> i32 {
+            new(handle.cast::<$slot>(), callbacks)
+        }
+
+        /// Sends a frame of `len` bytes to the display without showing it
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`handle` has to be created by `", stringify!($new), "` and `buffer` has to")]
+        /// point to `len` readable bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn $update_frame(
+            handle: *mut $handle,
+            buffer: *const u8,
+            len: usize,
+        ) -> i32 {
+            update_frame(handle.cast::<$slot>(), buffer, len)
+        }
+
+        /// Shows the frame sent last
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`handle` has to be created by `", stringify!($new), "`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $display_frame(handle: *mut $handle) -> i32 {
+            with(handle.cast::<$slot>(), |epd, spi, delay| {
+                epd.display_frame(spi, delay)
+            })
+        }
+
+        /// Clears the frame of the display to the background color without showing it
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`handle` has to be created by `", stringify!($new), "`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $clear(handle: *mut $handle) -> i32 {
+            with(handle.cast::<$slot>(), |epd, spi, delay| {
+                epd.clear_frame(spi, delay)
+            })
+        }
+
+        /// Puts the display into deep sleep
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`handle` has to be created by `", stringify!($new), "`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $sleep(handle: *mut $handle) -> i32 {
+            with(handle.cast::<$slot>(), |epd, spi, delay| epd.sleep(spi, delay))
+        }
+
+        /// Wakes the display up from deep sleep, also after [`EPD_ERR_NEEDS_RESET`]
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("`handle` has to be created by `", stringify!($new), "`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $wake_up(handle: *mut $handle) -> i32 {
+            with(handle.cast::<$slot>(), |epd, spi, delay| {
+                epd.wake_up(spi, delay)
+            })
+        }
+    };
+}
+
+ffi_driver!(
+    "4.2\"",
+    Epd4in2Handle,
+    Epd4in2Slot,
+    epd4in2_new,
+    epd4in2_update_frame,
+    epd4in2_display_frame,
+    epd4in2_clear,
+    epd4in2_sleep,
+    epd4in2_wake_up
+);
+
+ffi_driver!(
+    "2.13\" V2/V3",
+    Epd2in13Handle,
+    Epd2in13Slot,
+    epd2in13_new,
+    epd2in13_update_frame,
+    epd2in13_display_frame,
+    epd2in13_clear,
+    epd2in13_sleep,
+    epd2in13_wake_up
+);
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::mock::{Bus, BusyPin, DcPin, Delay, Pin, Spi};
+    use core::mem::MaybeUninit;
+
+    /// The C side of the tests, forwarding the callbacks to the mocks
+    struct Board {
+        spi: Spi,
+        busy: BusyPin,
+        dc: DcPin,
+        rst: Pin,
+        delay: Delay,
+    }
+
+    unsafe extern "C" fn spi_write(ctx: *mut c_void, data: *const u8, len: usize) -> i32 {
+        let board = &mut *ctx.cast::<Board>();
+        match board.spi.write(slice::from_raw_parts(data, len)) {
+            Ok(()) => 0,
+            Err(_) => -7,
+        }
+    }
+
+    unsafe extern "C" fn pin_set(ctx: *mut c_void, pin: u8, high: bool) -> i32 {
+        let board = &mut *ctx.cast::<Board>();
+        let pin: &mut dyn OutputPin<Error = _> = match pin {
+            EPD_PIN_DC => &mut board.dc,
+            EPD_PIN_RST => &mut board.rst,
+            _ => return -1,
+        };
+        pin.set_state(high.into()).unwrap();
+        0
+    }
+
+    unsafe extern "C" fn pin_get(ctx: *mut c_void, pin: u8) -> i32 {
+        let board = &mut *ctx.cast::<Board>();
+        match pin {
+            EPD_PIN_BUSY => board.busy.is_high().unwrap().into(),
+            _ => -1,
+        }
+    }
+
+    unsafe extern "C" fn delay_us(ctx: *mut c_void, us: u32) {
+        (*ctx.cast::<Board>()).delay.delay_us(us);
+    }
+
+    fn board(bus: &Bus, busy_level: bool) -> Board {
+        Board {
+            spi: bus.spi(),
+            busy: BusyPin::new(busy_level),
+            dc: bus.dc(),
+            rst: Pin::new(),
+            delay: Delay::new(),
+        }
+    }
+
+    fn callbacks(board: &mut Board) -> EpdCallbacks {
+        EpdCallbacks {
+            ctx: (board as *mut Board).cast(),
+            spi_write,
+            pin_set,
+            pin_get,
+            delay_us,
+        }
+    }
+
+    #[test]
+    fn header_constants() {
+        let header = include_str!("../include/epd_waveshare.h");
+        let defines = [
+            ("EPD_OK", EPD_OK as usize),
+            ("EPD_PIN_BUSY", EPD_PIN_BUSY.into()),
+            ("EPD_PIN_DC", EPD_PIN_DC.into()),
+            ("EPD_PIN_RST", EPD_PIN_RST.into()),
+            ("EPD4IN2_BUFFER_LEN", EPD4IN2_BUFFER_LEN),
+            ("EPD2IN13_BUFFER_LEN", EPD2IN13_BUFFER_LEN),
+            ("EPD4IN2_HANDLE_SIZE", EPD4IN2_HANDLE_SIZE),
+            ("EPD2IN13_HANDLE_SIZE", EPD2IN13_HANDLE_SIZE),
+        ];
+        for (name, value) in defines {
+            let line = std::format!("#define {name} {value}\n");
+            assert!(header.contains(&line), "{line}");
+        }
+        let errors = [
+            ("EPD_ERR_NULL", EPD_ERR_NULL),
+            ("EPD_ERR_SPI", EPD_ERR_SPI),
+            ("EPD_ERR_NEEDS_RESET", EPD_ERR_NEEDS_RESET),
+            ("EPD_ERR_BUFFER_LEN", EPD_ERR_BUFFER_LEN),
+            ("EPD_ERR_OTHER", EPD_ERR_OTHER),
+        ];
+        for (name, value) in errors {
+            let line = std::format!("#define {name} ({value})\n");
+            assert!(header.contains(&line), "{line}");
+        }
+    }
+
+    #[test]
+    fn epd4in2_matches_driver() {
+        let frame = [0x5A; EPD4IN2_BUFFER_LEN];
+
+        // the same calls on the driver directly
+        let expected = Bus::new();
+        let mut spi = expected.spi();
+        let mut delay = Delay::new();
+        let rst = Pin::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            expected.dc(),
+            rst.clone(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+        epd.display_frame(&mut spi, &mut delay).unwrap();
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        epd.sleep(&mut spi, &mut delay).unwrap();
+
+        let bus = Bus::new();
+        let mut board = board(&bus, true);
+        let callbacks = callbacks(&mut board);
+        let mut handle = MaybeUninit::<Epd4in2Handle>::uninit();
+        let handle = handle.as_mut_ptr();
+        unsafe {
+            assert_eq!(epd4in2_new(handle, &callbacks), EPD_OK);
+            assert_eq!(
+                epd4in2_update_frame(handle, frame.as_ptr(), frame.len()),
+                EPD_OK
+            );
+            assert_eq!(epd4in2_display_frame(handle), EPD_OK);
+            assert_eq!(epd4in2_clear(handle), EPD_OK);
+            assert_eq!(epd4in2_sleep(handle), EPD_OK);
+        }
+        assert_eq!(bus.transfers(), expected.transfers());
+        assert_eq!(board.rst.levels(), rst.levels());
+        assert_eq!(board.delay.delays(), delay.delays());
+    }
+
+    #[test]
+    fn epd2in13_error_codes() {
+        let bus = Bus::new();
+        let mut board = board(&bus, false);
+        let callbacks = callbacks(&mut board);
+        let mut handle = MaybeUninit::<Epd2in13Handle>::uninit();
+        let handle = handle.as_mut_ptr();
+        let frame = [0xFF; EPD2IN13_BUFFER_LEN];
+        unsafe {
+            assert_eq!(epd2in13_new(ptr::null_mut(), &callbacks), EPD_ERR_NULL);
+            assert_eq!(epd2in13_new(handle, ptr::null()), EPD_ERR_NULL);
+            assert_eq!(epd2in13_new(handle, &callbacks), EPD_OK);
+            assert_eq!(
+                epd2in13_update_frame(handle, frame.as_ptr(), frame.len() - 1),
+                EPD_ERR_BUFFER_LEN
+            );
+            assert_eq!(
+                epd2in13_update_frame(handle, ptr::null(), frame.len()),
+                EPD_ERR_NULL
+            );
+            assert_eq!(epd2in13_display_frame(ptr::null_mut()), EPD_ERR_NULL);
+
+            // a failing spi write poisons the driver until it is woken up again
+            bus.fail_after(0);
+            assert_eq!(
+                epd2in13_update_frame(handle, frame.as_ptr(), frame.len()),
+                EPD_ERR_SPI
+            );
+            assert_eq!(epd2in13_display_frame(handle), EPD_ERR_NEEDS_RESET);
+            assert_eq!(epd2in13_wake_up(handle), EPD_OK);
+            assert_eq!(epd2in13_clear(handle), EPD_OK);
+            assert_eq!(epd2in13_display_frame(handle), EPD_OK);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! A full frame handed to a driver as one buffer or in parts
+//!
+//! The drivers send every frame through a [FrameData], so
+//! [update_frame_with_progress](crate::traits::WaveshareDisplay::update_frame_with_progress)
+//! and [update_frame_vectored](crate::traits::WaveshareDisplay::update_frame_vectored)
+//! put the same bytes on the bus as [update_frame](crate::traits::WaveshareDisplay::update_frame)
+//! on every driver.
+
+#[cfg(feature = "checksum")]
+use crate::checksum::{crc16_update, CRC16_INIT};
+use crate::error::Error;
+use core::ops::Range;
+
+/// Bytes sent between two calls of the progress callback of a [FrameData]
+pub(crate) const PROGRESS_CHUNK_SIZE: usize = 1024;
+
+/// Frame data for a driver, one buffer or the parts of one, optionally reporting
+/// the progress of its transfer
+pub struct FrameData<'a> {
+    parts: Parts<'a>,
+    len: usize,
+    /// Set for frames in parts, which have to add up to a full frame
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    vectored: bool,
+    progress: Option<&'a mut dyn FnMut(usize, usize)>,
+    /// Bytes of the frame sent so far
+    sent: usize,
+    /// Last number of bytes passed to `progress`
+    reported: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Parts<'a> {
+    One(&'a [u8]),
+    Many(&'a [&'a [u8]]),
+}
+
+impl<'a> FrameData<'a> {
+    /// A frame in one buffer, as given to `update_frame`
+    pub(crate) fn new(buffer: &'a [u8]) -> Self {
+        FrameData::with_parts(Parts::One(buffer), buffer.len(), false)
+    }
+
+    /// A frame in `parts`, which are sent in order as one buffer
+    pub(crate) fn from_parts(parts: &'a [&'a [u8]]) -> Self {
+        let len = parts.iter().map(|part| part.len()).sum();
+        FrameData::with_parts(Parts::Many(parts), len, true)
+    }
+
+    fn with_parts(parts: Parts<'a>, len: usize, vectored: bool) -> Self {
+        FrameData {
+            parts,
+            len,
+            vectored,
+            progress: None,
+            sent: 0,
+            reported: None,
+        }
+    }
+
+    /// Calls `progress` with the bytes sent so far and the length of the frame, about
+    /// every [PROGRESS_CHUNK_SIZE] bytes
+    pub(crate) fn with_progress(mut self, progress: &'a mut dyn FnMut(usize, usize)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Length of the whole frame
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns [`Error::InvalidDataLength`] unless the parts of a vectored frame add up
+    /// to the `len` bytes of a full frame
+    ///
+    /// Every driver checks this before its first command, so nothing is sent for a
+    /// wrong frame. Frames in one buffer are sent like before, whatever their length.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn check_len<E>(&self, len: usize) -> Result<(), Error<E>> {
+        if self.vectored && self.len != len {
+            return Err(Error::InvalidDataLength);
+        }
+        Ok(())
+    }
+
+    /// CRC-16 over the whole frame in the order of its parts, see [crate::checksum]
+    #[cfg(feature = "checksum")]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn checksum(&self) -> u16 {
+        self.pieces(0..self.len)
+            .flatten()
+            .fold(CRC16_INIT, |crc, &byte| crc16_update(crc, byte))
+    }
+
+    /// The frame as one buffer, unless it is in several parts
+    pub(crate) fn as_slice(&self) -> Option<&'a [u8]> {
+        match self.parts {
+            Parts::One(buffer) => Some(buffer),
+            Parts::Many([part]) => Some(part),
+            Parts::Many(_) => None,
+        }
+    }
+
+    /// The bytes `range` of the frame if they are in one part
+    ///
+    /// Panics like slicing a buffer if `range` reaches beyond a frame in one buffer.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn slice(&self, range: Range<usize>) -> Option<&'a [u8]> {
+        if let Parts::One(buffer) = self.parts {
+            return Some(&buffer[range]);
+        }
+        let mut pieces = self.pieces(range);
+        match (pieces.next(), pieces.next()) {
+            (Some(piece), None) => Some(piece),
+            (None, _) => Some(&[]),
+            _ => None,
+        }
+    }
+
+    /// The bytes `range` of the frame, in as few slices as the parts allow
+    pub(crate) fn pieces(&self, range: Range<usize>) -> impl Iterator<Item = &'a [u8]> + Clone {
+        let (one, many, range): (Option<&'a [u8]>, &'a [&'a [u8]], _) = match self.parts {
+            Parts::One(buffer) => (Some(&buffer[range]), &[], 0..0),
+            Parts::Many(parts) => (None, parts, range),
+        };
+        let mut start = 0;
+        let many = many.iter().filter_map(move |part| {
+            let (part_start, part_end) = (start, start + part.len());
+            start = part_end;
+            let from = range.start.max(part_start);
+            let to = range.end.min(part_end);
+            (from < to).then(|| &part[from - part_start..to - part_start])
+        });
+        one.into_iter().chain(many)
+    }
+
+    /// Like [FrameData::pieces], in pieces of at most [PROGRESS_CHUNK_SIZE] bytes if the
+    /// progress is reported
+    ///
+    /// Send each piece and report it with [FrameData::sent].
+    pub(crate) fn chunks(&self, range: Range<usize>) -> impl Iterator<Item = &'a [u8]> {
+        let size = if self.progress.is_some() {
+            PROGRESS_CHUNK_SIZE
+        } else {
+            usize::MAX
+        };
+        self.pieces(range).flat_map(move |piece| piece.chunks(size))
+    }
+
+    /// Whether the progress is reported, senders split the frame with [FrameData::chunks] then
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn reports_progress(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    /// Counts `len` more bytes of the frame as sent and reports the progress once another
+    /// [PROGRESS_CHUNK_SIZE] bytes or the whole frame went out
+    ///
+    /// Drivers sending the frame more than once report the first transfer.
+    pub(crate) fn sent(&mut self, len: usize) {
+        let total = self.len;
+        self.sent = (self.sent + len).min(total);
+        if let Some(progress) = self.progress.as_mut() {
+            let reported = self.reported.unwrap_or(0);
+            if self.sent > reported
+                && (self.sent == total
+                    || self.sent / PROGRESS_CHUNK_SIZE > reported / PROGRESS_CHUNK_SIZE)
+            {
+                progress(self.sent, total);
+                self.reported = Some(self.sent);
+            }
+        }
+    }
+
+    /// Reports the end of the transfer unless it already was
+    pub(crate) fn finish(&mut self) {
+        let len = self.len;
+        if let Some(progress) = self.progress.as_mut() {
+            if self.reported != Some(len) {
+                progress(len, len);
+                self.reported = Some(len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::vec::Vec;
+
+    #[test]
+    fn pieces_across_parts() {
+        let data: [u8; 10] = core::array::from_fn(|i| i as u8);
+        let parts: [&[u8]; 4] = [&data[..3], &[], &data[3..4], &data[4..]];
+        let frame = FrameData::from_parts(&parts);
+        assert_eq!(frame.len(), 10);
+        for start in 0..10 {
+            for end in start..=10 {
+                let joined: Vec<u8> = frame.pieces(start..end).flatten().copied().collect();
+                assert_eq!(joined, data[start..end]);
+            }
+        }
+        assert_eq!(frame.slice(0..3), Some(&data[..3]));
+        assert_eq!(frame.slice(2..4), None);
+        assert_eq!(frame.slice(5..5), Some(&[][..]));
+        assert_eq!(frame.as_slice(), None);
+        assert!(frame.check_len::<()>(10).is_ok());
+        assert!(matches!(
+            frame.check_len::<()>(11),
+            Err(Error::InvalidDataLength)
+        ));
+        // frames in one buffer are sent whatever their length
+        assert!(FrameData::new(&data).check_len::<()>(11).is_ok());
+    }
+
+    #[test]
+    fn progress_is_monotonic() {
+        let data = [0u8; 3000];
+        let mut reports = Vec::new();
+        let mut progress = |sent, total| reports.push((sent, total));
+        let mut frame = FrameData::new(&data).with_progress(&mut progress);
+        let chunks: Vec<usize> = frame.chunks(0..3000).map(|chunk| chunk.len()).collect();
+        assert_eq!(chunks, [1024, 1024, 952]);
+        for len in chunks {
+            frame.sent(len);
+        }
+        // a second transfer of the same frame isn't reported again
+        frame.sent(1024);
+        frame.finish();
+        assert_eq!(reports, [(1024, 3000), (2048, 3000), (3000, 3000)]);
+    }
+
+    #[test]
+    fn small_pieces_are_reported_per_chunk() {
+        let data = [0u8; 2100];
+        let mut reports = Vec::new();
+        let mut progress = |sent, total| reports.push((sent, total));
+        let mut frame = FrameData::new(&data).with_progress(&mut progress);
+        for _ in 0..21 {
+            frame.sent(100);
+        }
+        frame.finish();
+        assert_eq!(reports, [(1100, 2100), (2100, 2100)]);
+    }
+
+    #[test]
+    fn empty_frame_reports_its_end() {
+        let mut reports = Vec::new();
+        let mut progress = |sent, total| reports.push((sent, total));
+        let mut frame = FrameData::new(&[]).with_progress(&mut progress);
+        frame.finish();
+        assert_eq!(reports, [(0, 0)]);
+    }
+}
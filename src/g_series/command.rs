@@ -0,0 +1,127 @@
+//! SPI Commands for the Waveshare G series 4 color E-Ink Displays
+
+use crate::traits::{self, DataLen};
+
+/// Commands of the G series controllers
+///
+/// Should rarely (never?) be needed directly.
+///
+/// The vendor specific commands are undocumented, their values are taken from the
+/// Waveshare drivers.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction,
+    /// booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// Turns off charge pump, T-con, source driver, gate driver, VCOM and temperature
+    /// sensor. Register data is kept.
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    ///
+    /// BUSY becomes "1" once the power on sequence is complete.
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The only parameter is a check code, the command is executed if it is 0xA5.
+    DeepSleep = 0x07,
+
+    /// Starts the transmission of the 2 bit per pixel frame into the SRAM
+    DataStartTransmission = 0x10,
+
+    /// Refreshes the display according to SRAM data and the 4 color OTP waveform.
+    DisplayRefresh = 0x12,
+
+    /// Sets the frame rate
+    PllControl = 0x30,
+
+    /// Vendor specific, the first command after the reset
+    ExtendedSetting4D = 0x4D,
+
+    /// Sets border output and data polarity
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// Sets the non-overlap periods of the gate and source outputs
+    TconSetting = 0x60,
+
+    /// Sets the display resolution
+    ResolutionSetting = 0x61,
+
+    /// Vendor specific
+    ExtendedSettingB4 = 0xB4,
+
+    /// Vendor specific
+    ExtendedSettingB5 = 0xB5,
+
+    /// Vendor specific
+    PowerSaving = 0xE3,
+
+    /// Vendor specific
+    ExtendedSettingE7 = 0xE7,
+
+    /// Vendor specific
+    ExtendedSettingE9 = 0xE9,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PowerOn => DataLen::Exact(0),
+            Command::PowerOff
+            | Command::DeepSleep
+            | Command::DisplayRefresh
+            | Command::PllControl
+            | Command::VcomAndDataIntervalSetting
+            | Command::PowerSaving => DataLen::Exact(1),
+            Command::PanelSetting | Command::TconSetting => DataLen::Exact(2),
+            Command::PowerOffSequenceSetting => DataLen::Exact(3),
+            Command::ResolutionSetting => DataLen::Exact(4),
+            Command::PowerSetting => DataLen::Max(6),
+            Command::BoosterSoftStart => DataLen::Max(7),
+            _ => DataLen::Variable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::ExtendedSetting4D.address(), 0x4D);
+    }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::ResolutionSetting.expected_data_len().accepts(4));
+        assert!(!Command::ResolutionSetting.expected_data_len().accepts(3));
+        assert!(Command::PowerOffSequenceSetting
+            .expected_data_len()
+            .accepts(3));
+        assert!(!Command::PowerOffSequenceSetting
+            .expected_data_len()
+            .accepts(1));
+        assert!(Command::ExtendedSetting4D.expected_data_len().accepts(1));
+    }
+}
@@ -0,0 +1,225 @@
+//! Shared parts of the Waveshare G series 4 color E-Ink Displays
+//!
+//! The 2.15" and 2.9" (G) panels use the same controller and 4 color OTP waveform,
+//! they only differ in their resolution. Each pixel is encoded in 2 bits of a single
+//! buffer, see [`QuadColor`]. Rows are padded to full bytes like on the b/w panels,
+//! so a frame is [`frame_len`] bytes long.
+//!
+//! # References
+//!
+//! - [Waveshare C driver 2.15" (G)](https://github.com/waveshare/e-Paper/blob/master/E-paper_Separate_Program/2in15_e-Paper_G/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in15g.c)
+//! - [Waveshare C driver 2.9" (G)](https://github.com/waveshare/e-Paper/blob/master/E-paper_Separate_Program/2in9_e-Paper_G/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in9g.c)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::buffer_len;
+use crate::color::QuadColor;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::reset::ResetConfig;
+
+pub(crate) mod command;
+use self::command::Command;
+
+pub(crate) const IS_BUSY_LOW: bool = true;
+pub(crate) const SINGLE_BYTE_WRITE: bool = false;
+/// Hardware reset sequence of all G series panels
+pub(crate) const RESET_CONFIG: ResetConfig = ResetConfig::new(20_000, 2_000);
+
+/// Connection interface of the G series drivers
+pub(crate) type Interface<SPI, BUSY, DC, RST, DELAY> =
+    DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>;
+
+/// Bytes of a full frame of `width` x `height` pixels with 2 bits per pixel
+///
+/// Every row is padded to a full byte, e.g. the 168 pixels of the 2.9" take 42 bytes.
+pub(crate) const fn frame_len(width: u32, height: u32) -> usize {
+    buffer_len(width as usize * 2, height as usize)
+}
+
+/// Resets the controller and sends the configuration shared by all G series panels
+pub(crate) fn init<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.reset(delay, RESET_CONFIG);
+    interface.wait_until_idle(delay, IS_BUSY_LOW);
+
+    interface.cmd_with_data(spi, Command::ExtendedSetting4D, &[0x78])?;
+    interface.cmd_with_data(spi, Command::PanelSetting, &[0x0F, 0x29])?;
+    interface.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x00])?;
+    interface.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x10, 0x54, 0x44])?;
+    interface.cmd_with_data(
+        spi,
+        Command::BoosterSoftStart,
+        &[0x05, 0x00, 0x3F, 0x0A, 0x25, 0x12, 0x1A],
+    )?;
+    interface.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
+    interface.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x02])?;
+    interface.cmd_with_data(
+        spi,
+        Command::ResolutionSetting,
+        &[
+            (width >> 8) as u8,
+            width as u8,
+            (height >> 8) as u8,
+            height as u8,
+        ],
+    )?;
+    interface.cmd_with_data(spi, Command::ExtendedSettingE7, &[0x1C])?;
+    interface.cmd_with_data(spi, Command::PowerSaving, &[0x22])?;
+    interface.cmd_with_data(spi, Command::ExtendedSettingB4, &[0xD0])?;
+    interface.cmd_with_data(spi, Command::ExtendedSettingB5, &[0x03])?;
+    interface.cmd_with_data(spi, Command::ExtendedSettingE9, &[0x01])?;
+    interface.cmd_with_data(spi, Command::PllControl, &[0x08])?;
+
+    interface.cmd(spi, Command::PowerOn)?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW);
+    Ok(())
+}
+
+/// Starts the transmission of a full frame
+pub(crate) fn start_frame<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.wait_until_idle(delay, IS_BUSY_LOW);
+    interface.cmd(spi, Command::DataStartTransmission)
+}
+
+/// Fills a full frame of `width` x `height` pixels with `color`
+pub(crate) fn clear_frame<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    width: u32,
+    height: u32,
+    color: QuadColor,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    start_frame(interface, spi, delay)?;
+    interface.data_x_times(spi, color.get_byte_value(), frame_len(width, height) as u32)
+}
+
+/// Starts the refresh with the frame in the SRAM without waiting for it
+pub(crate) fn begin_display_frame<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+    interface.count_refresh(false);
+    Ok(())
+}
+
+/// Powers off and sends the controller into deep sleep
+pub(crate) fn sleep<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.wait_until_idle(delay, IS_BUSY_LOW);
+    interface.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+    interface.wait_until_idle(delay, IS_BUSY_LOW);
+    interface.cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+}
+
+/// Aborts a refresh with a hardware reset, see [`crate::traits::WaveshareDisplay::abort_refresh`]
+pub(crate) fn abort_refresh<SPI, BUSY, DC, RST, DELAY>(
+    interface: &mut Interface<SPI, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.abort_with_reset(spi, delay, RESET_CONFIG, Command::PowerOff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_len_pads_rows() {
+        // 2.15" and 2.9"
+        assert_eq!(frame_len(160, 296), 40 * 296);
+        assert_eq!(frame_len(168, 384), 42 * 384);
+        // 3 pixels are still a full byte
+        assert_eq!(frame_len(3, 2), 2);
+        assert_eq!(frame_len(5, 2), 4);
+    }
+
+    /// Draws the corners of the display and checks the packed bytes
+    #[cfg(feature = "graphics")]
+    fn check_packing<const W: u32, const H: u32, const LEN: usize>() {
+        use crate::graphics::Display;
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display::<W, H, false, LEN, QuadColor>::default();
+        assert_eq!(display.buffer().len(), frame_len(W, H));
+        display.clear(QuadColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), QuadColor::Red));
+        display.set_pixel(Pixel(Point::new(W as i32 - 1, 0), QuadColor::Black));
+        display.set_pixel(Pixel(Point::new(1, 1), QuadColor::Yellow));
+
+        let line = frame_len(W, 1);
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b11_01_01_01);
+        assert_eq!(buffer[line - 1], 0b01_01_01_00);
+        assert_eq!(buffer[line], 0b01_10_01_01);
+        assert!(buffer[line + 1..].iter().all(|&byte| byte == 0x55));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_packing() {
+        check_packing::<160, 296, { frame_len(160, 296) }>();
+        check_packing::<168, 384, { frame_len(168, 384) }>();
+    }
+}
@@ -1,8 +1,23 @@
 //! Graphics Support for EPDs
 
-use crate::color::{ColorType, TriColor};
+use crate::color::{ChromaticPolicy, Color, ColorType, TriColor};
+use crate::error::Error;
+use crate::traits::{
+    ChromaticBand, QuickRefresh, RowStreaming, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
 use core::marker::PhantomData;
-use embedded_graphics_core::prelude::*;
+#[cfg(feature = "text")]
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle},
+    text::{Baseline, Text},
+    Drawable,
+};
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
 
 /// Display rotation, only 90° increments supported
 #[derive(Clone, Copy, Default)]
@@ -18,10 +33,107 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
+/// Size of a `width` x `height` panel in the coordinates of `rotation`
+fn rotated_size(width: u32, height: u32, rotation: DisplayRotation) -> Size {
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(width, height),
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(height, width),
+    }
+}
+
+/// Horizontal alignment, see [DisplayLayout::aligned_origin]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HAlign {
+    /// Aligned to the left edge
+    #[default]
+    Left,
+    /// Centered, rounded to the left
+    Center,
+    /// Aligned to the right edge
+    Right,
+}
+
+/// Vertical alignment, see [DisplayLayout::aligned_origin]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VAlign {
+    /// Aligned to the top edge
+    #[default]
+    Top,
+    /// Centered, rounded to the top
+    Center,
+    /// Aligned to the bottom edge
+    Bottom,
+}
+
+/// Top left corner of `content` aligned in `area`
+///
+/// Content larger than the area starts at its top left corner.
+fn align_in(area: &Rectangle, content: Size, h: HAlign, v: VAlign) -> Point {
+    let free = area.size.saturating_sub(content);
+    let x = match h {
+        HAlign::Left => 0,
+        HAlign::Center => free.width / 2,
+        HAlign::Right => free.width,
+    };
+    let y = match v {
+        VAlign::Top => 0,
+        VAlign::Center => free.height / 2,
+        VAlign::Bottom => free.height,
+    };
+    area.top_left + Point::new(x as i32, y as i32)
+}
+
+/// Placement of content in the coordinates of the current rotation
+///
+/// Implemented for every draw target with a size, e.g. [Display], [VarDisplay] and
+/// [StaticDisplay], whose size already takes the [rotation](DisplayRotation) into
+/// account. So a 296x128 panel is 128 pixels wide with `Rotate90`.
+///
+#[cfg_attr(feature = "epd2in9", doc = "```rust")]
+#[cfg_attr(not(feature = "epd2in9"), doc = "```ignore")]
+/// use embedded_graphics::prelude::*;
+/// use epd_waveshare::{epd2in9::Display2in9, graphics::*};
+///
+/// let mut display = Display2in9::default();
+/// display.set_rotation(DisplayRotation::Rotate90);
+/// assert_eq!(display.drawable_size(), Size::new(296, 128));
+/// assert_eq!(display.centered_origin(Size::new(96, 20)), Point::new(100, 54));
+/// ```
+pub trait DisplayLayout: OriginDimensions {
+    /// Size in the coordinates of the current rotation
+    fn drawable_size(&self) -> Size {
+        self.size()
+    }
+
+    /// Top left corner for `content` to be centered on the display
+    fn centered_origin(&self, content: Size) -> Point {
+        self.aligned_origin(content, HAlign::Center, VAlign::Center)
+    }
+
+    /// Top left corner for `content` to be aligned on the display
+    ///
+    /// Content larger than the display is clamped to the top left corner in the
+    /// larger dimension.
+    fn aligned_origin(&self, content: Size, h: HAlign, v: VAlign) -> Point {
+        align_in(&Rectangle::new(Point::zero(), self.size()), content, h, v)
+    }
+}
+
+impl<T: OriginDimensions> DisplayLayout for T {}
+
 /// count the number of bytes per line knowing that it may contains padding bits
 const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
-    // round to upper 8 bit count
-    (width as usize * bits_per_pixel + 7) / 8
+    (width as usize * bits_per_pixel).div_ceil(8)
+}
+
+/// Length of a native row of one buffer plane, at least 1 to chunk an empty buffer
+const fn row_stride<COLOR: ColorType>(width: u32) -> usize {
+    let stride = line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+    if stride == 0 {
+        1
+    } else {
+        stride
+    }
 }
 
 /// Display bffer used for drawing with embedded graphics
@@ -30,10 +142,10 @@ const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
 /// - WIDTH: width in pixel when display is not rotated
 /// - HEIGHT: height in pixel when display is not rotated
 /// - BWRBIT: mandatory value of the B/W when chromatic bit is set, can be any value for non
-///           tricolor epd
+///   tricolor epd
 /// - COLOR: color type used by the target display
 /// - BYTECOUNT: This is redundant with prvious data and should be removed when const generic
-///              expressions are stabilized
+///   expressions are stabilized
 ///
 /// More on BWRBIT:
 ///
@@ -55,6 +167,7 @@ pub struct Display<
 > {
     buffer: [u8; BYTECOUNT],
     rotation: DisplayRotation,
+    policy: ChromaticPolicy,
     _color: PhantomData<COLOR>,
 }
 
@@ -76,12 +189,7 @@ impl<
     // inline is necessary here to allow heap allocation via Box on stack limited programs
     #[inline(always)]
     fn default() -> Self {
-        Self {
-            // default color must be 0 for every bit in a pixel to make this work everywere
-            buffer: [0u8; BYTECOUNT],
-            rotation: DisplayRotation::default(),
-            _color: PhantomData,
-        }
+        Self::new()
     }
 }
 
@@ -106,6 +214,28 @@ impl<
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_solid(
+                &mut self.buffer,
+                WIDTH,
+                HEIGHT,
+                self.rotation,
+                BWRBIT,
+                area,
+                color,
+            );
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_buffer(&mut self.buffer, BWRBIT, color);
+        }
+        Ok(())
+    }
 }
 
 /// For use with embedded_grahics
@@ -118,10 +248,7 @@ impl<
     > OriginDimensions for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
 {
     fn size(&self) -> Size {
-        match self.rotation {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
-        }
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -133,11 +260,76 @@ impl<
         COLOR: ColorType + PixelColor,
     > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
 {
+    /// Width in pixel when display is not rotated
+    pub const WIDTH: u32 = WIDTH;
+
+    /// Height in pixel when display is not rotated
+    pub const HEIGHT: u32 = HEIGHT;
+
+    /// Length of the internal buffer in bytes
+    pub const BUFFER_LEN: usize = BYTECOUNT;
+
+    /// Create a display with every bit cleared, see [`Default`]
+    ///
+    /// Being a `const fn`, it can be used to place the (possibly large) buffer in a `static`.
+    // inline is necessary here to allow heap allocation via Box on stack limited programs
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            // default color must be 0 for every bit in a pixel to make this work everywere
+            buffer: [0u8; BYTECOUNT],
+            rotation: DisplayRotation::Rotate0,
+            policy: ChromaticPolicy::Normal,
+            _color: PhantomData,
+        }
+    }
+
     /// get internal buffer to use it (to draw in epd)
     pub fn buffer(&self) -> &[u8] {
         &self.buffer
     }
 
+    /// A display drawing into `buffer` with `rotation`, e.g. a frame received from
+    /// another thread, see [`into_buffer`](Self::into_buffer)
+    pub const fn from_buffer(buffer: [u8; BYTECOUNT], rotation: DisplayRotation) -> Self {
+        Self {
+            buffer,
+            rotation,
+            policy: ChromaticPolicy::Normal,
+            _color: PhantomData,
+        }
+    }
+
+    /// The frame without the display around it
+    ///
+    /// Rendering and talking to the panel can be split over two threads, the frame is
+    /// sent to the thread owning the driver (the drivers are `Send` if their spi device,
+    /// pins and delay are):
+    ///
+    #[cfg_attr(feature = "epd4in2", doc = "```rust")]
+    #[cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+    /// use embedded_graphics::{prelude::*, primitives::{Circle, PrimitiveStyle}};
+    /// use epd_waveshare::{color::Color, epd4in2::Display4in2};
+    /// use std::{sync::mpsc, thread};
+    ///
+    /// let (frames, received) = mpsc::channel();
+    /// let renderer = thread::spawn(move || {
+    ///     let mut display = Display4in2::default();
+    ///     let _ = Circle::new(Point::new(10, 10), 50)
+    ///         .into_styled(PrimitiveStyle::with_fill(Color::Black))
+    ///         .draw(&mut display);
+    ///     frames.send(display.into_buffer()).unwrap();
+    /// });
+    ///
+    /// // the spi thread, e.g. `epd.update_and_display_frame(&mut spi, &frame, &mut delay)`
+    /// let frame = received.recv().unwrap();
+    /// assert_eq!(frame.len(), Display4in2::BUFFER_LEN);
+    /// renderer.join().unwrap();
+    ///```
+    pub fn into_buffer(self) -> [u8; BYTECOUNT] {
+        self.buffer
+    }
+
     /// Set the display rotation.
     ///
     /// This only concerns future drawing made to it. Anything aready drawn
@@ -152,22 +344,183 @@ impl<
     }
 
     /// Set a specific pixel color on this display
-    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
-        set_pixel(
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then.
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) -> bool {
+        set_pixel_with_policy(
             &mut self.buffer,
             WIDTH,
             HEIGHT,
             self.rotation,
             BWRBIT,
+            self.policy,
             pixel,
+        )
+    }
+
+    /// Offset of native row `y` in the buffer, or in each plane of a tri-color buffer
+    pub const fn row_offset(y: u32) -> usize {
+        y as usize * line_bytes(WIDTH, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+    }
+
+    /// The rows of the buffer, e.g. to post-process a finished frame byte wise
+    ///
+    /// Rows are in the native orientation of the panel whatever the
+    /// [rotation](Self::set_rotation) is, every row has the length of a line of the
+    /// controller, padding bits included. Tri-color buffers yield the rows of the
+    /// black/white plane followed by those of the chromatic plane.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.buffer.chunks_exact(row_stride::<COLOR>(WIDTH))
+    }
+
+    /// Mutable [rows](Self::rows), changes are sent with the next frame update
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        self.buffer.chunks_exact_mut(row_stride::<COLOR>(WIDTH))
+    }
+
+    /// Fill a rectangle given in native (unrotated) coordinates with `color`
+    ///
+    /// The fast path for areas already known in native coordinates, e.g. progress bars:
+    /// whole bytes are written for the middle of every line and only the bytes at both
+    /// ends are masked. The rotation is ignored, the rectangle is clipped to the display.
+    /// `fill_solid` converts its area and ends up here as well.
+    pub fn fill_rect_native(&mut self, rect: Rectangle, color: COLOR) {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_rect_native(&mut self.buffer, WIDTH, HEIGHT, BWRBIT, &rect, color);
+        }
+    }
+
+    /// Draw a 1 bit per pixel image scaled up by an integer factor
+    ///
+    /// Set bits of `src` are drawn with `fg`, cleared ones with `bg`. `scale` is clamped
+    /// to 1..=8, a scale of 1 is a plain blit. Rotation and clipping are honored like for
+    /// any other drawing, unrotated b/w displays take a faster byte wise path.
+    pub fn draw_scaled_bitmap(
+        &mut self,
+        src: &RawBitImage,
+        top_left: Point,
+        scale: u8,
+        fg: COLOR,
+        bg: COLOR,
+    ) {
+        draw_scaled_bitmap(
+            &mut self.buffer,
+            WIDTH,
+            HEIGHT,
+            self.rotation,
+            BWRBIT,
+            src,
+            top_left,
+            scale,
+            fg,
+            bg,
+        );
+    }
+
+    /// Draw an 8 bit grayscale image dithered to black and white
+    ///
+    /// `data` holds `width` bytes per line, 0 is black and 255 is white. Tricolor
+    /// displays only get black and white pixels, the chromatic color is never used.
+    ///
+    /// # Panics
+    ///
+    /// If the error buffer of [Dither::FloydSteinberg] is shorter than `width`.
+    pub fn draw_gray_image_dithered(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        top_left: Point,
+        algo: Dither,
+    ) where
+        COLOR: From<BinaryColor>,
+    {
+        draw_gray_image_dithered::<COLOR>(
+            &mut self.buffer,
+            WIDTH,
+            HEIGHT,
+            self.rotation,
+            BWRBIT,
+            GrayImage {
+                data,
+                width,
+                height,
+            },
+            top_left,
+            algo,
         );
     }
 }
 
+/// Some black/white specifics
+impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
+    Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+{
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        pixel_planes::<Color>(&self.buffer, WIDTH, HEIGHT, self.rotation, x, y).map(bw_color)
+    }
+
+    /// Inverts the whole buffer in place, black becomes white and vice versa
+    ///
+    /// Contrary to `set_inverted` of the SSD16xx based drivers this changes the
+    /// frame, which needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        self.buffer.iter_mut().for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(&mut self.buffer, 1, WIDTH, HEIGHT, transform)
+    }
+
+    /// Replaces the frame with an image array exported by Image2Lcd or the GxEPD tools
+    ///
+    /// `data` is in the orientation of the buffer, the rotation of the display isn't
+    /// applied. Returns `false` and leaves the frame as it is if `data` doesn't have the
+    /// length of the frame in `format`, see [assert_image2lcd_len] for a compile time
+    /// check.
+    pub fn from_image2lcd(&mut self, data: &[u8], format: Image2LcdFormat) -> bool {
+        image2lcd_into_buffer(data, format, WIDTH, HEIGHT, &mut self.buffer)
+    }
+
+    /// Writes the frame into `out` as an image array in `format`, the reverse of
+    /// [from_image2lcd](Self::from_image2lcd)
+    ///
+    /// Returns `false` if `out` is shorter than [Image2LcdFormat::len].
+    pub fn to_image2lcd(&self, out: &mut [u8], format: Image2LcdFormat) -> bool {
+        buffer_into_image2lcd(&self.buffer, WIDTH, HEIGHT, format, out)
+    }
+}
+
 /// Some Tricolor specifics
 impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
     Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor>
 {
+    /// Sets how [TriColor::Chromatic] is drawn from now on, see [ChromaticPolicy]
+    ///
+    /// Applies to everything drawn through [DrawTarget], `set_pixel` and
+    /// `fill_rect_native`. Anything already drawn stays as it is in the buffer, clear
+    /// the display to start over with an empty chromatic plane.
+    pub fn set_chromatic_policy(&mut self, policy: ChromaticPolicy) {
+        self.policy = policy;
+    }
+
+    /// Current [ChromaticPolicy]
+    pub fn chromatic_policy(&self) -> ChromaticPolicy {
+        self.policy
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        pixel_planes::<TriColor>(&self.buffer, WIDTH, HEIGHT, self.rotation, x, y).map(tri_color)
+    }
+
     /// get black/white internal buffer to use it (to draw in epd)
     pub fn bw_buffer(&self) -> &[u8] {
         &self.buffer[..self.buffer.len() / 2]
@@ -177,6 +530,107 @@ impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: u
     pub fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.buffer.len() / 2..]
     }
+
+    /// Native rows of the black/white plane, see [rows](Self::rows)
+    pub fn bw_rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.bw_buffer().chunks_exact(row_stride::<TriColor>(WIDTH))
+    }
+
+    /// Native rows of the chromatic plane, see [rows](Self::rows)
+    pub fn chromatic_rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.chromatic_buffer()
+            .chunks_exact(row_stride::<TriColor>(WIDTH))
+    }
+
+    /// Mutable [bw_rows](Self::bw_rows)
+    pub fn bw_rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let len = self.buffer.len() / 2;
+        self.buffer[..len].chunks_exact_mut(row_stride::<TriColor>(WIDTH))
+    }
+
+    /// Mutable [chromatic_rows](Self::chromatic_rows)
+    pub fn chromatic_rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let len = self.buffer.len() / 2;
+        self.buffer[len..].chunks_exact_mut(row_stride::<TriColor>(WIDTH))
+    }
+
+    /// Merges both planes into the b/w frame `out`, e.g. to preview the frame on a b/w panel
+    ///
+    /// Chromatic pixels are shown as `chromatic_as`, a [Color] or [ChromaticAs::Dithered],
+    /// whatever their black/white bit is. `out` is laid out like a b/w [Display] of the
+    /// same size. Panics if it is shorter than [bw_buffer](Self::bw_buffer).
+    ///
+    #[cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+    #[cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+    /// # use embedded_hal_mock::eh1::*;
+    /// # fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+    /// use embedded_graphics::{prelude::*, primitives::{Circle, PrimitiveStyle}};
+    /// use epd_waveshare::{buffer_len, epd4in2::*, graphics::ChromaticAs, prelude::*};
+    /// #
+    /// # let expectations = [];
+    /// # let mut spi = spi::Mock::new(&expectations);
+    /// # let expectations = [];
+    /// # let busy_in = pin::Mock::new(&expectations);
+    /// # let dc = pin::Mock::new(&expectations);
+    /// # let rst = pin::Mock::new(&expectations);
+    /// # let mut delay = delay::NoopDelay::new();
+    ///
+    /// // the frame of a 4.2" b/c panel
+    /// const LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    /// let mut frame = Display::<WIDTH, HEIGHT, false, { LEN * 2 }, TriColor>::default();
+    /// let _ = frame.clear(TriColor::White);
+    /// let _ = Circle::new(Point::new(100, 50), 200)
+    ///     .into_styled(PrimitiveStyle::with_fill(TriColor::Chromatic))
+    ///     .draw(&mut frame);
+    ///
+    /// // previewed on the b/w 4.2", the circle in a checkered pattern
+    /// let mut preview = [0u8; LEN];
+    /// frame.to_bw_buffer(&mut preview, ChromaticAs::Dithered);
+    /// let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+    /// epd.update_and_display_frame(&mut spi, &preview, &mut delay)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_bw_buffer(&self, out: &mut [u8], chromatic_as: impl Into<ChromaticAs>) {
+        merge_chromatic(
+            self.bw_buffer(),
+            self.chromatic_buffer(),
+            row_stride::<TriColor>(WIDTH),
+            out,
+            chromatic_as.into(),
+        );
+    }
+
+    /// Inverts the black/white plane in place, the chromatic plane is left as it is
+    ///
+    /// The frame needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        let len = self.buffer.len() / 2;
+        self.buffer[..len]
+            .iter_mut()
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(&mut self.buffer, 2, WIDTH, HEIGHT, transform)
+    }
+
+    /// Shows the b/w frame `new` with the pixels changed since the b/w frame `old` in
+    /// the chromatic color, e.g. to check which regions a partial refresh logic updates
+    ///
+    /// Both frames are laid out like a b/w [Display] of the same size. Changed pixels are
+    /// set like drawing [TriColor::Chromatic], the padding bits at the end of the rows are
+    /// never marked. Returns `false` and leaves the frame as it is if `old` or `new` is
+    /// shorter than [bw_buffer](Self::bw_buffer).
+    #[cfg(feature = "debug-tools")]
+    pub fn diff_overlay(&mut self, old: &[u8], new: &[u8]) -> bool {
+        diff_overlay_planes(&mut self.buffer, WIDTH, BWRBIT, old, new)
+    }
 }
 
 /// Same as `Display`, except that its characteristics are defined at runtime.
@@ -188,6 +642,8 @@ pub struct VarDisplay<'a, COLOR: ColorType + PixelColor> {
     bwrbit: bool,
     buffer: &'a mut [u8],
     rotation: DisplayRotation,
+    anchor: Option<Point>,
+    policy: ChromaticPolicy,
     _color: PhantomData<COLOR>,
 }
 
@@ -205,26 +661,46 @@ impl<'a, COLOR: ColorType + PixelColor> DrawTarget for VarDisplay<'a, COLOR> {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let Some(color) = color.with_chromatic_policy(self.policy) else {
+            return Ok(());
+        };
+        let size = self.buffer_size();
+        fill_solid(
+            &mut self.buffer[..size],
+            self.width,
+            self.height,
+            self.rotation,
+            self.bwrbit,
+            area,
+            color,
+        );
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let Some(color) = color.with_chromatic_policy(self.policy) else {
+            return Ok(());
+        };
+        let size = self.buffer_size();
+        fill_buffer(&mut self.buffer[..size], self.bwrbit, color);
+        Ok(())
+    }
 }
 
 /// For use with embedded_grahics
 impl<'a, COLOR: ColorType + PixelColor> OriginDimensions for VarDisplay<'a, COLOR> {
     fn size(&self) -> Size {
-        match self.rotation {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-                Size::new(self.width, self.height)
-            }
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-                Size::new(self.height, self.width)
-            }
-        }
+        rotated_size(self.width, self.height, self.rotation)
     }
 }
 
 /// Error found during usage of VarDisplay
 #[derive(Debug)]
 pub enum VarDisplayError {
-    /// The provided buffer was too small
+    /// The provided buffer was too small, see [`max_buffer_height`](crate::max_buffer_height)
+    /// for sizing a display to a buffer
     BufferTooSmall,
 }
 
@@ -239,19 +715,25 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
         buffer: &'a mut [u8],
         bwrbit: bool,
     ) -> Result<Self, VarDisplayError> {
-        let myself = Self {
+        // the same check as the const `assert_buffer_fits`
+        if !crate::buffer_fits(
+            width as usize,
+            height as usize,
+            COLOR::BITS_PER_PIXEL_PER_BUFFER * COLOR::BUFFER_COUNT,
+            buffer.len(),
+        ) {
+            return Err(VarDisplayError::BufferTooSmall);
+        }
+        Ok(Self {
             width,
             height,
             bwrbit,
             buffer,
             rotation: DisplayRotation::default(),
+            anchor: None,
+            policy: ChromaticPolicy::Normal,
             _color: PhantomData,
-        };
-        // enfore some constraints dynamicly
-        if myself.buffer_size() > myself.buffer.len() {
-            return Err(VarDisplayError::BufferTooSmall);
-        }
-        Ok(myself)
+        })
     }
 
     /// get the number of used bytes in the buffer
@@ -268,6 +750,24 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
         &self.buffer[..self.buffer_size()]
     }
 
+    /// Offset of native row `y` in the buffer, or in each plane of a tri-color buffer
+    pub fn row_offset(&self, y: u32) -> usize {
+        y as usize * line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+    }
+
+    /// The rows of the buffer, see [`Display::rows`]
+    ///
+    /// Rows are in native orientation whatever the [rotation](Self::set_rotation) is.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.buffer().chunks_exact(row_stride::<COLOR>(self.width))
+    }
+
+    /// Mutable [rows](Self::rows), changes are sent with the next frame update
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let len = self.buffer_size();
+        self.buffer[..len].chunks_exact_mut(row_stride::<COLOR>(self.width))
+    }
+
     /// Set the display rotation.
     ///
     /// This only concerns future drawing made to it. Anything aready drawn
@@ -281,22 +781,183 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
         self.rotation
     }
 
+    /// Places the buffer on the panel with its top left corner at `anchor`
+    ///
+    /// The anchor is given in native (unrotated) coordinates of the panel. An anchored b/w
+    /// display is sent as a partial update by [`FlushableDisplay::flush`], without an
+    /// anchor (default) the buffer has to cover the whole panel. Most controllers need
+    /// `x` and the width to be multiples of 8.
+    pub fn set_anchor(&mut self, anchor: Option<Point>) {
+        self.anchor = anchor;
+    }
+
+    /// Position of the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn anchor(&self) -> Option<Point> {
+        self.anchor
+    }
+
     /// Set a specific pixel color on this display
-    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then.
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) -> bool {
         let size = self.buffer_size();
-        set_pixel(
+        set_pixel_with_policy(
             &mut self.buffer[..size],
             self.width,
             self.height,
             self.rotation,
             self.bwrbit,
+            self.policy,
             pixel,
+        )
+    }
+
+    /// Fill a rectangle given in native (unrotated) coordinates with `color`
+    ///
+    /// The fast path for areas already known in native coordinates, e.g. progress bars:
+    /// whole bytes are written for the middle of every line and only the bytes at both
+    /// ends are masked. The rotation is ignored, the rectangle is clipped to the display.
+    /// `fill_solid` converts its area and ends up here as well.
+    pub fn fill_rect_native(&mut self, rect: Rectangle, color: COLOR) {
+        let Some(color) = color.with_chromatic_policy(self.policy) else {
+            return;
+        };
+        let size = self.buffer_size();
+        fill_rect_native(
+            &mut self.buffer[..size],
+            self.width,
+            self.height,
+            self.bwrbit,
+            &rect,
+            color,
+        );
+    }
+
+    /// Draw a 1 bit per pixel image scaled up by an integer factor
+    ///
+    /// Set bits of `src` are drawn with `fg`, cleared ones with `bg`. `scale` is clamped
+    /// to 1..=8, a scale of 1 is a plain blit. Rotation and clipping are honored like for
+    /// any other drawing, unrotated b/w displays take a faster byte wise path.
+    pub fn draw_scaled_bitmap(
+        &mut self,
+        src: &RawBitImage,
+        top_left: Point,
+        scale: u8,
+        fg: COLOR,
+        bg: COLOR,
+    ) {
+        let size = self.buffer_size();
+        draw_scaled_bitmap(
+            &mut self.buffer[..size],
+            self.width,
+            self.height,
+            self.rotation,
+            self.bwrbit,
+            src,
+            top_left,
+            scale,
+            fg,
+            bg,
+        );
+    }
+
+    /// Draw an 8 bit grayscale image dithered to black and white
+    ///
+    /// See [Display::draw_gray_image_dithered].
+    pub fn draw_gray_image_dithered(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        top_left: Point,
+        algo: Dither,
+    ) where
+        COLOR: From<BinaryColor>,
+    {
+        let size = self.buffer_size();
+        draw_gray_image_dithered::<COLOR>(
+            &mut self.buffer[..size],
+            self.width,
+            self.height,
+            self.rotation,
+            self.bwrbit,
+            GrayImage {
+                data,
+                width,
+                height,
+            },
+            top_left,
+            algo,
         );
     }
 }
 
+/// Some black/white specifics
+impl<'a> VarDisplay<'a, Color> {
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        pixel_planes::<Color>(self.buffer(), self.width, self.height, self.rotation, x, y)
+            .map(bw_color)
+    }
+
+    /// Inverts the whole buffer in place, black becomes white and vice versa
+    ///
+    /// Contrary to `set_inverted` of the SSD16xx based drivers this changes the
+    /// frame, which needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        let len = self.buffer_size();
+        self.buffer[..len]
+            .iter_mut()
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(self.buffer, 1, self.width, self.height, transform)
+    }
+
+    /// Replaces the frame with an image array exported by Image2Lcd or the GxEPD tools
+    ///
+    /// `data` is in the orientation of the buffer, the rotation of the display isn't
+    /// applied. Returns `false` and leaves the frame as it is if `data` doesn't have the
+    /// length of the frame in `format`, see [assert_image2lcd_len] for a compile time
+    /// check.
+    pub fn from_image2lcd(&mut self, data: &[u8], format: Image2LcdFormat) -> bool {
+        image2lcd_into_buffer(data, format, self.width, self.height, self.buffer)
+    }
+
+    /// Writes the frame into `out` as an image array in `format`, the reverse of
+    /// [from_image2lcd](Self::from_image2lcd)
+    ///
+    /// Returns `false` if `out` is shorter than [Image2LcdFormat::len].
+    pub fn to_image2lcd(&self, out: &mut [u8], format: Image2LcdFormat) -> bool {
+        buffer_into_image2lcd(self.buffer(), self.width, self.height, format, out)
+    }
+}
+
 /// Some Tricolor specifics
 impl<'a> VarDisplay<'a, TriColor> {
+    /// Sets how [TriColor::Chromatic] is drawn from now on, see [`Display::set_chromatic_policy`]
+    pub fn set_chromatic_policy(&mut self, policy: ChromaticPolicy) {
+        self.policy = policy;
+    }
+
+    /// Current [ChromaticPolicy]
+    pub fn chromatic_policy(&self) -> ChromaticPolicy {
+        self.policy
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        pixel_planes::<TriColor>(self.buffer(), self.width, self.height, self.rotation, x, y)
+            .map(tri_color)
+    }
+
     /// get black/white internal buffer to use it (to draw in epd)
     pub fn bw_buffer(&self) -> &[u8] {
         &self.buffer[..self.buffer_size() / 2]
@@ -306,147 +967,5515 @@ impl<'a> VarDisplay<'a, TriColor> {
     pub fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.buffer_size() / 2..self.buffer_size()]
     }
-}
 
-// This is a function to share code between `Display` and `VarDisplay`
-// It sets a specific pixel in a buffer to a given color.
-// The big number of parameters is due to the fact that it is an internal function to both
-// strctures.
-fn set_pixel<COLOR: ColorType + PixelColor>(
-    buffer: &mut [u8],
-    width: u32,
-    height: u32,
-    rotation: DisplayRotation,
-    bwrbit: bool,
-    pixel: Pixel<COLOR>,
+    /// Native rows of the black/white plane, see [`Display::rows`]
+    pub fn bw_rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.bw_buffer()
+            .chunks_exact(row_stride::<TriColor>(self.width))
+    }
+
+    /// Native rows of the chromatic plane, see [`Display::rows`]
+    pub fn chromatic_rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.chromatic_buffer()
+            .chunks_exact(row_stride::<TriColor>(self.width))
+    }
+
+    /// Mutable [bw_rows](Self::bw_rows)
+    pub fn bw_rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let len = self.buffer_size() / 2;
+        self.buffer[..len].chunks_exact_mut(row_stride::<TriColor>(self.width))
+    }
+
+    /// Mutable [chromatic_rows](Self::chromatic_rows)
+    pub fn chromatic_rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let len = self.buffer_size();
+        self.buffer[len / 2..len].chunks_exact_mut(row_stride::<TriColor>(self.width))
+    }
+
+    /// Merges both planes into the b/w frame `out`, see [`Display::to_bw_buffer`]
+    pub fn to_bw_buffer(&self, out: &mut [u8], chromatic_as: impl Into<ChromaticAs>) {
+        merge_chromatic(
+            self.bw_buffer(),
+            self.chromatic_buffer(),
+            row_stride::<TriColor>(self.width),
+            out,
+            chromatic_as.into(),
+        );
+    }
+
+    /// Inverts the black/white plane in place, the chromatic plane is left as it is
+    ///
+    /// The frame needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        let len = self.buffer_size() / 2;
+        self.buffer[..len]
+            .iter_mut()
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(self.buffer, 2, self.width, self.height, transform)
+    }
+
+    /// Highlights the changes from `old` to `new`, see [`Display::diff_overlay`]
+    #[cfg(feature = "debug-tools")]
+    pub fn diff_overlay(&mut self, old: &[u8], new: &[u8]) -> bool {
+        let len = self.buffer_size();
+        diff_overlay_planes(&mut self.buffer[..len], self.width, self.bwrbit, old, new)
+    }
+}
+
+impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
+    /// Size, rotation and anchor of the display, to attach another buffer later on
+    pub fn config(&self) -> VarDisplayConfig {
+        VarDisplayConfig {
+            width: self.width,
+            height: self.height,
+            bwrbit: self.bwrbit,
+            rotation: self.rotation,
+            anchor: self.anchor,
+            policy: self.policy,
+        }
+    }
+}
+
+/// The settings of a [`VarDisplay`] without its buffer
+///
+/// A `VarDisplay` borrows its buffer, which makes it hard to keep in a long lived
+/// struct. The config can be stored instead and cheaply attached to the buffer
+/// whenever something is drawn, keeping the rotation and anchor between frames.
+#[derive(Clone, Copy)]
+pub struct VarDisplayConfig {
+    width: u32,
+    height: u32,
+    bwrbit: bool,
+    rotation: DisplayRotation,
+    anchor: Option<Point>,
+    policy: ChromaticPolicy,
+}
+
+impl VarDisplayConfig {
+    /// Parameters are the same as for [`VarDisplay::new`]
+    pub const fn new(width: u32, height: u32, bwrbit: bool) -> Self {
+        Self {
+            width,
+            height,
+            bwrbit,
+            rotation: DisplayRotation::Rotate0,
+            anchor: None,
+            policy: ChromaticPolicy::Normal,
+        }
+    }
+
+    /// Number of bytes a buffer of `COLOR` needs for this display, `usize::MAX` if that
+    /// overflows
+    pub const fn buffer_len<COLOR: ColorType>(&self) -> usize {
+        match crate::frame_len(
+            self.width as usize,
+            self.height as usize,
+            COLOR::BITS_PER_PIXEL_PER_BUFFER * COLOR::BUFFER_COUNT,
+        ) {
+            Some(len) => len,
+            None => usize::MAX,
+        }
+    }
+
+    /// Set the rotation used by attached displays, see [`VarDisplay::set_rotation`]
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Set the position on the panel, see [`VarDisplay::set_anchor`]
+    pub fn set_anchor(&mut self, anchor: Option<Point>) {
+        self.anchor = anchor;
+    }
+
+    /// Position of the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn anchor(&self) -> Option<Point> {
+        self.anchor
+    }
+
+    /// Draws into `buffer` with these settings
+    ///
+    /// The buffer keeps its content, changes of the rotation or anchor on the returned
+    /// display can be stored again with [`VarDisplay::config`].
+    pub fn attach<'a, COLOR: ColorType + PixelColor>(
+        &self,
+        buffer: &'a mut [u8],
+    ) -> Result<VarDisplay<'a, COLOR>, VarDisplayError> {
+        let mut display = VarDisplay::new(self.width, self.height, buffer, self.bwrbit)?;
+        display.rotation = self.rotation;
+        display.anchor = self.anchor;
+        display.policy = self.policy;
+        Ok(display)
+    }
+}
+
+/// Same as [`VarDisplay`], except that it owns its buffer of `N` bytes
+///
+/// The size is still chosen at runtime and checked against `N`, so the display can be
+/// embedded in a struct without borrowing a buffer. Anything not covered here is
+/// available on the borrowed view of [`OwnedVarDisplay::display`].
+pub struct OwnedVarDisplay<const N: usize, COLOR: ColorType + PixelColor> {
+    config: VarDisplayConfig,
+    buffer: [u8; N],
+    _color: PhantomData<COLOR>,
+}
+
+impl<const N: usize, COLOR: ColorType + PixelColor> OwnedVarDisplay<N, COLOR> {
+    /// A display with every bit cleared
+    ///
+    /// Parameters are the same as for [`VarDisplay::new`]. Being a `const fn`, it can be
+    /// used to place the display in a `static`.
+    pub const fn new(width: u32, height: u32, bwrbit: bool) -> Result<Self, VarDisplayError> {
+        let config = VarDisplayConfig::new(width, height, bwrbit);
+        if config.buffer_len::<COLOR>() > N {
+            return Err(VarDisplayError::BufferTooSmall);
+        }
+        Ok(Self {
+            config,
+            buffer: [0u8; N],
+            _color: PhantomData,
+        })
+    }
+
+    /// The buffer borrowed as [`VarDisplay`] with the same settings
+    ///
+    /// Changes of the rotation or anchor through it are not kept.
+    pub fn display(&mut self) -> VarDisplay<'_, COLOR> {
+        VarDisplay {
+            width: self.config.width,
+            height: self.config.height,
+            bwrbit: self.config.bwrbit,
+            buffer: &mut self.buffer,
+            rotation: self.config.rotation,
+            anchor: self.config.anchor,
+            policy: self.config.policy,
+            _color: PhantomData,
+        }
+    }
+
+    /// Size, rotation and anchor of the display
+    pub fn config(&self) -> VarDisplayConfig {
+        self.config
+    }
+
+    /// get internal buffer to use it (to draw in epd)
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer[..self.config.buffer_len::<COLOR>()]
+    }
+
+    /// Set the display rotation, see [`VarDisplay::set_rotation`]
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.config.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.config.rotation
+    }
+
+    /// Places the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn set_anchor(&mut self, anchor: Option<Point>) {
+        self.config.anchor = anchor;
+    }
+
+    /// Position of the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn anchor(&self) -> Option<Point> {
+        self.config.anchor
+    }
+
+    /// Set a specific pixel color on this display
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then.
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) -> bool {
+        self.display().set_pixel(pixel)
+    }
+}
+
+/// Some black/white specifics
+impl<const N: usize> OwnedVarDisplay<N, Color> {
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        pixel_planes::<Color>(
+            self.buffer(),
+            self.config.width,
+            self.config.height,
+            self.config.rotation,
+            x,
+            y,
+        )
+        .map(bw_color)
+    }
+}
+
+/// Some Tricolor specifics
+impl<const N: usize> OwnedVarDisplay<N, TriColor> {
+    /// Sets how [TriColor::Chromatic] is drawn from now on, see [`Display::set_chromatic_policy`]
+    pub fn set_chromatic_policy(&mut self, policy: ChromaticPolicy) {
+        self.config.policy = policy;
+    }
+
+    /// Current [ChromaticPolicy]
+    pub fn chromatic_policy(&self) -> ChromaticPolicy {
+        self.config.policy
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        pixel_planes::<TriColor>(
+            self.buffer(),
+            self.config.width,
+            self.config.height,
+            self.config.rotation,
+            x,
+            y,
+        )
+        .map(tri_color)
+    }
+
+    /// get black/white internal buffer to use it (to draw in epd)
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.buffer()[..self.config.buffer_len::<TriColor>() / 2]
+    }
+
+    /// get chromatic internal buffer to use it (to draw in epd)
+    pub fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer()[self.config.buffer_len::<TriColor>() / 2..]
+    }
+}
+
+/// For use with embedded_grahics
+impl<const N: usize, COLOR: ColorType + PixelColor> DrawTarget for OwnedVarDisplay<N, COLOR> {
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display().draw_iter(pixels)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display().fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display().clear(color)
+    }
+}
+
+/// For use with embedded_grahics
+impl<const N: usize, COLOR: ColorType + PixelColor> OriginDimensions for OwnedVarDisplay<N, COLOR> {
+    fn size(&self) -> Size {
+        rotated_size(self.config.width, self.config.height, self.config.rotation)
+    }
+}
+
+/// Same as [`OwnedVarDisplay`], except that its buffer is allocated on the heap
+///
+/// Meant for hosts (e.g. a Raspberry Pi) which pick the panel at runtime, e.g. from a
+/// config file. Drawing goes through the borrowed view of [`DynDisplay::display`], so
+/// the buffer is byte for byte the one of a [`Display`] of the same size.
+#[cfg(feature = "alloc")]
+pub struct DynDisplay<COLOR: ColorType + PixelColor> {
+    config: VarDisplayConfig,
+    buffer: alloc::vec::Vec<u8>,
+    _color: PhantomData<COLOR>,
+}
+
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> DynDisplay<COLOR> {
+    /// A display with every bit cleared
+    ///
+    /// Parameters are the same as for [`VarDisplay::new`], the buffer is sized for them.
+    pub fn new(width: u32, height: u32, bwrbit: bool) -> Self {
+        let config = VarDisplayConfig::new(width, height, bwrbit);
+        Self {
+            config,
+            buffer: alloc::vec![0u8; config.buffer_len::<COLOR>()],
+            _color: PhantomData,
+        }
+    }
+
+    /// A display of the size of the panel of `EPD`
+    pub fn for_panel<EPD, SPI, BUSY, DC, RST, DELAY>(bwrbit: bool) -> Self
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        Self::new(EPD::WIDTH, EPD::HEIGHT, bwrbit)
+    }
+
+    /// The buffer borrowed as [`VarDisplay`] with the same settings
+    ///
+    /// Changes of the rotation or anchor through it are not kept.
+    pub fn display(&mut self) -> VarDisplay<'_, COLOR> {
+        VarDisplay {
+            width: self.config.width,
+            height: self.config.height,
+            bwrbit: self.config.bwrbit,
+            buffer: &mut self.buffer,
+            rotation: self.config.rotation,
+            anchor: self.config.anchor,
+            policy: self.config.policy,
+            _color: PhantomData,
+        }
+    }
+
+    /// Size, rotation and anchor of the display
+    pub fn config(&self) -> VarDisplayConfig {
+        self.config
+    }
+
+    /// get internal buffer to use it (to draw in epd)
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Set the display rotation, see [`VarDisplay::set_rotation`]
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.config.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.config.rotation
+    }
+
+    /// Places the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn set_anchor(&mut self, anchor: Option<Point>) {
+        self.config.anchor = anchor;
+    }
+
+    /// Position of the buffer on the panel, see [`VarDisplay::set_anchor`]
+    pub fn anchor(&self) -> Option<Point> {
+        self.config.anchor
+    }
+
+    /// Set a specific pixel color on this display
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then.
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) -> bool {
+        self.display().set_pixel(pixel)
+    }
+}
+
+/// Some black/white specifics
+#[cfg(feature = "alloc")]
+impl DynDisplay<Color> {
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        pixel_planes::<Color>(
+            &self.buffer,
+            self.config.width,
+            self.config.height,
+            self.config.rotation,
+            x,
+            y,
+        )
+        .map(bw_color)
+    }
+
+    /// Inverts the whole buffer in place, see [`VarDisplay::invert`]
+    pub fn invert(&mut self) {
+        self.display().invert();
+    }
+
+    /// Transforms the finished frame in place, see [`Display::transform_buffer`]
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        self.display().transform_buffer(transform)
+    }
+
+    /// Replaces the frame with an image array, see [`Display::from_image2lcd`]
+    pub fn from_image2lcd(&mut self, data: &[u8], format: Image2LcdFormat) -> bool {
+        self.display().from_image2lcd(data, format)
+    }
+
+    /// Writes the frame as an image array, see [`Display::to_image2lcd`]
+    pub fn to_image2lcd(&self, out: &mut [u8], format: Image2LcdFormat) -> bool {
+        buffer_into_image2lcd(
+            &self.buffer,
+            self.config.width,
+            self.config.height,
+            format,
+            out,
+        )
+    }
+}
+
+/// Some Tricolor specifics
+#[cfg(feature = "alloc")]
+impl DynDisplay<TriColor> {
+    /// Sets how [TriColor::Chromatic] is drawn from now on, see [`Display::set_chromatic_policy`]
+    pub fn set_chromatic_policy(&mut self, policy: ChromaticPolicy) {
+        self.config.policy = policy;
+    }
+
+    /// Current [ChromaticPolicy]
+    pub fn chromatic_policy(&self) -> ChromaticPolicy {
+        self.config.policy
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        pixel_planes::<TriColor>(
+            &self.buffer,
+            self.config.width,
+            self.config.height,
+            self.config.rotation,
+            x,
+            y,
+        )
+        .map(tri_color)
+    }
+
+    /// get black/white internal buffer to use it (to draw in epd)
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.buffer[..self.buffer.len() / 2]
+    }
+
+    /// get chromatic internal buffer to use it (to draw in epd)
+    pub fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer[self.buffer.len() / 2..]
+    }
+
+    /// Merges both planes into the b/w frame `out`, see [`Display::to_bw_buffer`]
+    pub fn to_bw_buffer(&self, out: &mut [u8], chromatic_as: impl Into<ChromaticAs>) {
+        merge_chromatic(
+            self.bw_buffer(),
+            self.chromatic_buffer(),
+            row_stride::<TriColor>(self.config.width),
+            out,
+            chromatic_as.into(),
+        );
+    }
+
+    /// Inverts the black/white plane in place, see [`VarDisplay::invert`]
+    pub fn invert(&mut self) {
+        self.display().invert();
+    }
+
+    /// Transforms the finished frame in place, see [`Display::transform_buffer`]
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        self.display().transform_buffer(transform)
+    }
+}
+
+/// For use with embedded_grahics
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> DrawTarget for DynDisplay<COLOR> {
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display().draw_iter(pixels)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.display().fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.display().clear(color)
+    }
+}
+
+/// For use with embedded_grahics
+#[cfg(feature = "alloc")]
+impl<COLOR: ColorType + PixelColor> OriginDimensions for DynDisplay<COLOR> {
+    fn size(&self) -> Size {
+        rotated_size(self.config.width, self.config.height, self.config.rotation)
+    }
+}
+
+/// Same as `Display`, except that the buffer is a `&'static mut` array provided at
+/// construction instead of being owned.
+///
+/// This allows to place the buffer in a `static` (e.g. with `cortex_m::singleton!`),
+/// draw into it and hand it to a DMA capable spi with [`StaticDisplay::release`]
+/// without any copy.
+pub struct StaticDisplay<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+    COLOR: ColorType + PixelColor,
+> {
+    buffer: &'static mut [u8; BYTECOUNT],
+    rotation: DisplayRotation,
+    policy: ChromaticPolicy,
+    _color: PhantomData<COLOR>,
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    > DrawTarget for StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.set_pixel(pixel);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_solid(
+                self.buffer,
+                WIDTH,
+                HEIGHT,
+                self.rotation,
+                BWRBIT,
+                area,
+                color,
+            );
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_buffer(self.buffer, BWRBIT, color);
+        }
+        Ok(())
+    }
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    > OriginDimensions for StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    > StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    /// Uses `buffer` as display buffer, its content is kept
+    pub fn new(buffer: &'static mut [u8; BYTECOUNT]) -> Self {
+        Self {
+            buffer,
+            rotation: DisplayRotation::default(),
+            policy: ChromaticPolicy::Normal,
+            _color: PhantomData,
+        }
+    }
+
+    /// Gives the buffer back, e.g. to pass it to a DMA transfer
+    ///
+    /// Afterwards it can be drawn into again by creating a new `StaticDisplay` from it.
+    pub fn release(self) -> &'static mut [u8; BYTECOUNT] {
+        self.buffer
+    }
+
+    /// get internal buffer to use it (to draw in epd)
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    /// Set the display rotation.
+    ///
+    /// This only concerns future drawing made to it. Anything aready drawn
+    /// stays as it is in the buffer.
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Set a specific pixel color on this display
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then.
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) -> bool {
+        set_pixel_with_policy(
+            self.buffer,
+            WIDTH,
+            HEIGHT,
+            self.rotation,
+            BWRBIT,
+            self.policy,
+            pixel,
+        )
+    }
+
+    /// Fill a rectangle given in native (unrotated) coordinates with `color`
+    ///
+    /// The fast path for areas already known in native coordinates, e.g. progress bars:
+    /// whole bytes are written for the middle of every line and only the bytes at both
+    /// ends are masked. The rotation is ignored, the rectangle is clipped to the display.
+    /// `fill_solid` converts its area and ends up here as well.
+    pub fn fill_rect_native(&mut self, rect: Rectangle, color: COLOR) {
+        if let Some(color) = color.with_chromatic_policy(self.policy) {
+            fill_rect_native(self.buffer, WIDTH, HEIGHT, BWRBIT, &rect, color);
+        }
+    }
+}
+
+/// Some black/white specifics
+impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
+    StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+{
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
+        pixel_planes::<Color>(self.buffer, WIDTH, HEIGHT, self.rotation, x, y).map(bw_color)
+    }
+
+    /// Inverts the whole buffer in place, black becomes white and vice versa
+    ///
+    /// Contrary to `set_inverted` of the SSD16xx based drivers this changes the
+    /// frame, which needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        self.buffer.iter_mut().for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(&mut self.buffer[..], 1, WIDTH, HEIGHT, transform)
+    }
+
+    /// Replaces the frame with an image array exported by Image2Lcd or the GxEPD tools
+    ///
+    /// `data` is in the orientation of the buffer, the rotation of the display isn't
+    /// applied. Returns `false` and leaves the frame as it is if `data` doesn't have the
+    /// length of the frame in `format`, see [assert_image2lcd_len] for a compile time
+    /// check.
+    pub fn from_image2lcd(&mut self, data: &[u8], format: Image2LcdFormat) -> bool {
+        image2lcd_into_buffer(data, format, WIDTH, HEIGHT, &mut self.buffer[..])
+    }
+
+    /// Writes the frame into `out` as an image array in `format`, the reverse of
+    /// [from_image2lcd](Self::from_image2lcd)
+    ///
+    /// Returns `false` if `out` is shorter than [Image2LcdFormat::len].
+    pub fn to_image2lcd(&self, out: &mut [u8], format: Image2LcdFormat) -> bool {
+        buffer_into_image2lcd(&self.buffer[..], WIDTH, HEIGHT, format, out)
+    }
+}
+
+/// Some Tricolor specifics
+impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
+    StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor>
+{
+    /// Sets how [TriColor::Chromatic] is drawn from now on, see [`Display::set_chromatic_policy`]
+    pub fn set_chromatic_policy(&mut self, policy: ChromaticPolicy) {
+        self.policy = policy;
+    }
+
+    /// Current [ChromaticPolicy]
+    pub fn chromatic_policy(&self) -> ChromaticPolicy {
+        self.policy
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        pixel_planes::<TriColor>(self.buffer, WIDTH, HEIGHT, self.rotation, x, y).map(tri_color)
+    }
+
+    /// get black/white internal buffer to use it (to draw in epd)
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.buffer[..BYTECOUNT / 2]
+    }
+
+    /// get chromatic internal buffer to use it (to draw in epd)
+    pub fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer[BYTECOUNT / 2..]
+    }
+
+    /// Inverts the black/white plane in place, the chromatic plane is left as it is
+    ///
+    /// The frame needs to be transmitted again to be shown.
+    pub fn invert(&mut self) {
+        let len = BYTECOUNT / 2;
+        self.buffer[..len]
+            .iter_mut()
+            .for_each(|byte| *byte = !*byte);
+    }
+
+    /// Transforms the finished frame in place, e.g. for a panel mounted upside down
+    ///
+    /// Contrary to [set_rotation](Self::set_rotation) this moves what is already drawn.
+    /// Returns `false` and leaves the frame as it is for a 90 or 270 degrees rotation of
+    /// a display which isn't square, see [rotate90_into] for those.
+    pub fn transform_buffer(&mut self, transform: BufferTransform) -> bool {
+        transform_planes(&mut self.buffer[..], 2, WIDTH, HEIGHT, transform)
+    }
+}
+
+/// A tri-color display keeping only a band of rows of the chromatic plane
+///
+/// For interfaces using the chromatic color in a fixed area only, e.g. a red banner:
+/// the black/white plane covers the whole panel, the chromatic plane only the native
+/// rows `chromatic_start..chromatic_start + CH_ROWS`. The rest of the chromatic plane
+/// is sent as background by [`WaveshareThreeColorDisplay::update_color_frame_banded`]
+/// (or [`flush`](FlushableDisplay::flush)).
+///
+/// Chromatic pixels drawn outside of the band are silently drawn black.
+///
+/// - WIDTH, HEIGHT, BWRBIT: the same as for [`Display`]
+/// - CH_ROWS: number of native rows of the chromatic band
+/// - BYTECOUNT: `buffer_len(WIDTH, HEIGHT + CH_ROWS)`, see [crate::buffer_len]
+///
+/// ```rust
+/// use embedded_graphics::{prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+/// use epd_waveshare::{buffer_len, color::TriColor, graphics::PartialChromaticDisplay};
+///
+/// // a 2.9" (B) with red in the top 40 rows, 5.3 instead of 9.5 KiB
+/// let mut display =
+///     PartialChromaticDisplay::<128, 296, 40, false, { buffer_len(128, 296 + 40) }>::new(0);
+/// let _ = Rectangle::new(Point::zero(), Size::new(128, 40))
+///     .into_styled(PrimitiveStyle::with_fill(TriColor::Chromatic))
+///     .draw(&mut display);
+/// assert_eq!(display.chromatic_buffer().data.len(), 40 * 16);
+/// // outside of the band
+/// let _ = Pixel(Point::new(0, 100), TriColor::Chromatic).draw(&mut display);
+/// assert_eq!(display.get_pixel(0, 100), Some(TriColor::Black));
+/// ```
+pub struct PartialChromaticDisplay<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const CH_ROWS: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+> {
+    /// The black/white plane followed by the rows of the chromatic band
+    buffer: [u8; BYTECOUNT],
+    chromatic_start: u32,
+    rotation: DisplayRotation,
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const CH_ROWS: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+    > DrawTarget for PartialChromaticDisplay<WIDTH, HEIGHT, CH_ROWS, BWRBIT, BYTECOUNT>
+{
+    type Color = TriColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.set_pixel(pixel);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let (_, outside) = byte_bits(Self::band_color(color, false), BWRBIT, 0, 8);
+        let (_, inside) = byte_bits(color, BWRBIT, 0, 8);
+        let (bw, band) = self.buffer.split_at_mut(Self::PLANE_LEN);
+        bw.fill(outside as u8);
+        let start = Self::row_offset(self.chromatic_start);
+        bw[start..start + band.len()].fill(inside as u8);
+        band.fill((inside >> 8) as u8);
+        Ok(())
+    }
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const CH_ROWS: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+    > OriginDimensions for PartialChromaticDisplay<WIDTH, HEIGHT, CH_ROWS, BWRBIT, BYTECOUNT>
+{
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const CH_ROWS: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+    > PartialChromaticDisplay<WIDTH, HEIGHT, CH_ROWS, BWRBIT, BYTECOUNT>
+{
+    /// Width in pixel when display is not rotated
+    pub const WIDTH: u32 = WIDTH;
+
+    /// Height in pixel when display is not rotated
+    pub const HEIGHT: u32 = HEIGHT;
+
+    /// Length of the internal buffer in bytes
+    pub const BUFFER_LEN: usize = BYTECOUNT;
+
+    /// Length of the black/white plane, the full chromatic plane would take as much
+    const PLANE_LEN: usize = Self::row_offset(HEIGHT);
+
+    /// Create a display with the chromatic band starting at native row `chromatic_start`
+    ///
+    /// Every bit is cleared like for [`Display::new`].
+    ///
+    /// # Panics
+    ///
+    /// If the band reaches beyond the panel. A `BYTECOUNT` other than
+    /// `buffer_len(WIDTH, HEIGHT + CH_ROWS)` fails to compile.
+    // inline is necessary here to allow heap allocation via Box on stack limited programs
+    #[inline(always)]
+    pub const fn new(chromatic_start: u32) -> Self {
+        const {
+            assert!(
+                CH_ROWS <= HEIGHT && BYTECOUNT == Self::row_offset(HEIGHT + CH_ROWS),
+                "BYTECOUNT must be buffer_len(WIDTH, HEIGHT + CH_ROWS)"
+            )
+        };
+        assert!(
+            chromatic_start <= HEIGHT - CH_ROWS,
+            "the chromatic band reaches beyond the panel"
+        );
+        Self {
+            buffer: [0u8; BYTECOUNT],
+            chromatic_start,
+            rotation: DisplayRotation::Rotate0,
+        }
+    }
+
+    /// First native row of the chromatic band
+    pub fn chromatic_start(&self) -> u32 {
+        self.chromatic_start
+    }
+
+    /// Offset of native row `y` in the black/white plane
+    pub const fn row_offset(y: u32) -> usize {
+        y as usize * line_bytes(WIDTH, 1)
+    }
+
+    /// Set the display rotation.
+    ///
+    /// This only concerns future drawing made to it. Anything aready drawn
+    /// stays as it is in the buffer. The band stays at the same native rows.
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Set a specific pixel color on this display
+    ///
+    /// The point is given in rotated coordinates. Returns `false` if it is outside of the
+    /// display, nothing is drawn then. A chromatic pixel outside of the band is drawn
+    /// black.
+    pub fn set_pixel(&mut self, pixel: Pixel<TriColor>) -> bool {
+        let Pixel(point, color) = pixel;
+        let Some((index, x)) = pixel_index::<TriColor>(WIDTH, HEIGHT, self.rotation, point) else {
+            return false;
+        };
+        let band = self.band_index(index);
+        let (mask, bits) = Self::band_color(color, band.is_some()).bitmask(BWRBIT, x);
+        self.buffer[index] = self.buffer[index] & mask | (bits & 0xFF) as u8;
+        if let Some(index) = band {
+            self.buffer[index] = self.buffer[index] & mask | (bits >> 8) as u8;
+        }
+        true
+    }
+
+    /// Color of the pixel at `x`, `y` in rotated coordinates, `None` outside of the display
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        let point = Point::new(x.try_into().ok()?, y.try_into().ok()?);
+        let (index, x) = pixel_index::<TriColor>(WIDTH, HEIGHT, self.rotation, point)?;
+        let bit = 0x80 >> (x % 8);
+        let mut planes = u8::from(self.buffer[index] & bit != 0);
+        if let Some(index) = self.band_index(index) {
+            planes |= u8::from(self.buffer[index] & bit != 0) << 1;
+        }
+        Some(tri_color(planes))
+    }
+
+    /// The black/white plane of the whole panel
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.buffer[..Self::PLANE_LEN]
+    }
+
+    /// The chromatic band and the native row it starts at
+    pub fn chromatic_buffer(&self) -> ChromaticBand<'_> {
+        ChromaticBand {
+            data: &self.buffer[Self::PLANE_LEN..],
+            start_row: self.chromatic_start,
+        }
+    }
+
+    /// Index of the chromatic byte of the black/white byte at `index`, `None` outside
+    /// of the band
+    fn band_index(&self, index: usize) -> Option<usize> {
+        let start = Self::row_offset(self.chromatic_start);
+        let offset = index.checked_sub(start)?;
+        (offset < BYTECOUNT - Self::PLANE_LEN).then_some(Self::PLANE_LEN + offset)
+    }
+
+    /// `color` as drawn inside or outside of the band
+    fn band_color(color: TriColor, in_band: bool) -> TriColor {
+        match color {
+            TriColor::Chromatic if !in_band => TriColor::Black,
+            color => color,
+        }
+    }
+}
+
+/// Two [Display]s for drawing the next frame while the current one is shown
+///
+/// Draw into the [back](DoubleBuffered::back) buffer, [swap](DoubleBuffered::swap) and send
+/// the new front buffer with [flush](DoubleBuffered::flush). After a swap the back buffer holds
+/// the frame from before, not the one just shown, so redraw it completely.
+///
+/// For displays with [QuickRefresh], [swap_and_flush_quick](DoubleBuffered::swap_and_flush_quick)
+/// passes the previous frame as old and the new one as new frame, there is no need to keep a
+/// copy of the shown frame around.
+pub struct DoubleBuffered<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+    COLOR: ColorType + PixelColor,
+> {
+    front: Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>,
+    back: Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>,
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    > Default for DoubleBuffered<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    // inline is necessary here to allow heap allocation via Box on stack limited programs
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor,
+    > DoubleBuffered<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    /// Two displays with every bit cleared, see [Display::new]
+    // inline is necessary here to allow heap allocation via Box on stack limited programs
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            front: Display::new(),
+            back: Display::new(),
+        }
+    }
+
+    /// The buffer to draw the next frame into
+    pub fn back(&mut self) -> &mut Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR> {
+        &mut self.back
+    }
+
+    /// The buffer sent by the last or next flush
+    pub fn front(&self) -> &Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR> {
+        &self.front
+    }
+
+    /// Makes the back buffer the front buffer and the other way round
+    pub fn swap(&mut self) {
+        core::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Sets the rotation of both buffers
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.front.set_rotation(rotation);
+        self.back.set_rotation(rotation);
+    }
+
+    /// Sends the front buffer and displays it
+    pub fn flush<SPI, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        epd.update_and_display_frame(spi, self.front.buffer(), delay)
+    }
+
+    /// Swaps the buffers and displays the new front buffer with a quick refresh
+    ///
+    /// The previous front buffer is sent as old frame, so this expects that the
+    /// previous frame was shown with a flush of this pair. Controllers still holding
+    /// it (see [`QuickRefresh::promote_displayed_frame_to_old`]) only get the new frame.
+    pub fn swap_and_flush_quick<SPI, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        EPD: QuickRefresh<SPI, BUSY, DC, RST, DELAY>,
+    {
+        self.swap();
+        if !epd.promote_displayed_frame_to_old(spi, delay)? {
+            epd.update_old_frame(spi, self.back.buffer(), delay)?;
+        }
+        epd.update_new_frame(spi, self.front.buffer(), delay)?;
+        epd.display_new_frame(spi, delay)
+    }
+}
+
+/// Several displays drawn as one surface, e.g. four 4.2" panels arranged 2x2 as a 800x600 wall
+///
+/// The tiles are given row by row, `tiles[row][col]`, and all need the size of the first
+/// one in their current rotation. Every tile keeps its own rotation, so a panel mounted
+/// upside down is set to [`DisplayRotation::Rotate180`] and still shows its part of the
+/// surface upright. Drawing is clipped at the tile boundaries, each tile is then sent to
+/// its own driver with [`TiledSurface::for_each_tile`].
+pub struct TiledSurface<const COLS: usize, const ROWS: usize, D> {
+    tiles: [[D; COLS]; ROWS],
+    tile_size: Size,
+}
+
+/// Error found during creation of a [`TiledSurface`]
+#[derive(Debug)]
+pub enum TiledSurfaceError {
+    /// A tile differs in size from the first one, e.g. because of its rotation
+    TileSizeMismatch,
+}
+
+impl<const COLS: usize, const ROWS: usize, D: OriginDimensions> TiledSurface<COLS, ROWS, D> {
+    /// Combines the `tiles`, given as `tiles[row][col]`, to one surface
+    pub fn new(tiles: [[D; COLS]; ROWS]) -> Result<Self, TiledSurfaceError> {
+        let tile_size = tiles
+            .iter()
+            .flatten()
+            .next()
+            .map_or(Size::zero(), |tile| tile.size());
+        if tiles.iter().flatten().any(|tile| tile.size() != tile_size) {
+            return Err(TiledSurfaceError::TileSizeMismatch);
+        }
+        Ok(Self { tiles, tile_size })
+    }
+
+    /// Size of every tile on the surface
+    pub fn tile_size(&self) -> Size {
+        self.tile_size
+    }
+
+    /// The tile in column `col` and row `row`
+    ///
+    /// # Panics
+    ///
+    /// If `col` or `row` is out of range
+    pub fn tile(&self, col: usize, row: usize) -> &D {
+        &self.tiles[row][col]
+    }
+
+    /// The tile in column `col` and row `row`, e.g. to change its rotation
+    ///
+    /// A rotation swapping width and height moves parts of the tile off the surface.
+    ///
+    /// # Panics
+    ///
+    /// If `col` or `row` is out of range
+    pub fn tile_mut(&mut self, col: usize, row: usize) -> &mut D {
+        &mut self.tiles[row][col]
+    }
+
+    /// Calls `f` with the column, row and display of every tile, row by row
+    pub fn for_each_tile<F: FnMut(usize, usize, &D)>(&self, mut f: F) {
+        for (row, tiles) in self.tiles.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                f(col, row, tile);
+            }
+        }
+    }
+
+    /// Like [`TiledSurface::for_each_tile`], stopping at the first error of `f`
+    ///
+    /// Meant for flushing every tile to its driver.
+    pub fn try_for_each_tile<E, F: FnMut(usize, usize, &D) -> Result<(), E>>(
+        &self,
+        mut f: F,
+    ) -> Result<(), E> {
+        for (row, tiles) in self.tiles.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                f(col, row, tile)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Top left corner of the tile in column `col` and row `row` on the surface
+    fn tile_origin(&self, col: usize, row: usize) -> Point {
+        Point::new(
+            (col as u32 * self.tile_size.width) as i32,
+            (row as u32 * self.tile_size.height) as i32,
+        )
+    }
+}
+
+impl<const COLS: usize, const ROWS: usize, D> DrawTarget for TiledSurface<COLS, ROWS, D>
+where
+    D: DrawTarget + OriginDimensions,
+{
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let Size { width, height } = self.tile_size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let col = (point.x as u32 / width) as usize;
+            let row = (point.y as u32 / height) as usize;
+            if col < COLS && row < ROWS {
+                let local = point - self.tile_origin(col, row);
+                self.tiles[row][col].draw_iter(core::iter::once(Pixel(local, color)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let origin = self.tile_origin(col, row);
+                let part = area.intersection(&Rectangle::new(origin, self.tile_size));
+                if !part.is_zero_sized() {
+                    let local = Rectangle::new(part.top_left - origin, part.size);
+                    self.tiles[row][col].fill_solid(&local, color)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.tiles
+            .iter_mut()
+            .flatten()
+            .try_for_each(|tile| tile.clear(color))
+    }
+}
+
+impl<const COLS: usize, const ROWS: usize, D> OriginDimensions for TiledSurface<COLS, ROWS, D> {
+    fn size(&self) -> Size {
+        Size::new(
+            self.tile_size.width * COLS as u32,
+            self.tile_size.height * ROWS as u32,
+        )
+    }
+}
+
+/// A display buffer which knows how to send itself to the driver `EPD`
+///
+/// Generic code can flush any buffer flavour without knowing it: b/w buffers are sent
+/// as one frame, tri-color buffers with both planes and anchored [`VarDisplay`]s as
+/// partial update, see [`VarDisplay::set_anchor`]. Every flush ends with a refresh.
+///
+/// The size of [`Display`] and [`StaticDisplay`] is checked against the driver at compile
+/// time, a [`VarDisplay`] without anchor has to match the size of the panel.
+pub trait FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Sends the buffer to `epd` and displays it
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+/// Fails to compile if the buffer doesn't have the size of the panel of `EPD`
+macro_rules! assert_panel_size {
+    ($epd:ty, $width:expr, $height:expr) => {
+        const {
+            assert!(
+                $width == <$epd>::WIDTH && $height == <$epd>::HEIGHT,
+                "the size of the display buffer doesn't match the panel"
+            )
+        }
+    };
+}
+
+fn flush_tri_color<SPI, BUSY, DC, RST, DELAY, EPD>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    bw: &[u8],
+    chromatic: &[u8],
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    epd.update_color_frame(spi, delay, bw, chromatic)?;
+    epd.display_frame(spi, delay)
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        SPI,
+        BUSY,
+        DC,
+        RST,
+        DELAY,
+        EPD,
+    > FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert_panel_size!(EPD, WIDTH, HEIGHT);
+        epd.update_and_display_frame(spi, self.buffer(), delay)
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        SPI,
+        BUSY,
+        DC,
+        RST,
+        DELAY,
+        EPD,
+    > FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert_panel_size!(EPD, WIDTH, HEIGHT);
+        flush_tri_color(epd, spi, delay, self.bw_buffer(), self.chromatic_buffer())
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        SPI,
+        BUSY,
+        DC,
+        RST,
+        DELAY,
+        EPD,
+    > FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert_panel_size!(EPD, WIDTH, HEIGHT);
+        epd.update_and_display_frame(spi, self.buffer(), delay)
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        SPI,
+        BUSY,
+        DC,
+        RST,
+        DELAY,
+        EPD,
+    > FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for StaticDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, TriColor>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert_panel_size!(EPD, WIDTH, HEIGHT);
+        flush_tri_color(epd, spi, delay, self.bw_buffer(), self.chromatic_buffer())
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const CH_ROWS: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        SPI,
+        BUSY,
+        DC,
+        RST,
+        DELAY,
+        EPD,
+    > FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for PartialChromaticDisplay<WIDTH, HEIGHT, CH_ROWS, BWRBIT, BYTECOUNT>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert_panel_size!(EPD, WIDTH, HEIGHT);
+        epd.update_color_frame_banded(spi, delay, self.bw_buffer(), self.chromatic_buffer())?;
+        epd.display_frame(spi, delay)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, EPD> FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for VarDisplay<'_, Color>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        flush_var(epd, spi, delay, &self.config(), self.buffer())
+    }
+}
+
+impl<const N: usize, SPI, BUSY, DC, RST, DELAY, EPD>
+    FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY> for OwnedVarDisplay<N, Color>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        flush_var(epd, spi, delay, &self.config, self.buffer())
+    }
+}
+
+/// Sends a b/w buffer of a runtime sized display, partially if it is anchored
+fn flush_var<SPI, BUSY, DC, RST, DELAY, EPD>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    config: &VarDisplayConfig,
+    buffer: &[u8],
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    match config.anchor {
+        Some(anchor) => {
+            epd.update_partial_frame(
+                spi,
+                delay,
+                buffer,
+                anchor.x as u32,
+                anchor.y as u32,
+                config.width,
+                config.height,
+            )?;
+            epd.display_frame(spi, delay)
+        }
+        None => {
+            debug_assert!(
+                config.width == EPD::WIDTH && config.height == EPD::HEIGHT,
+                "the size of the display buffer doesn't match the panel"
+            );
+            epd.update_and_display_frame(spi, buffer, delay)
+        }
+    }
+}
+
+/// The tri-color drivers have no partial updates of both planes, the anchor is ignored
+impl<SPI, BUSY, DC, RST, DELAY, EPD> FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for VarDisplay<'_, TriColor>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        debug_assert!(
+            self.width == EPD::WIDTH && self.height == EPD::HEIGHT,
+            "the size of the display buffer doesn't match the panel"
+        );
+        flush_tri_color(epd, spi, delay, self.bw_buffer(), self.chromatic_buffer())
+    }
+}
+
+/// The tri-color drivers have no partial updates of both planes, the anchor is ignored
+impl<const N: usize, SPI, BUSY, DC, RST, DELAY, EPD>
+    FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY> for OwnedVarDisplay<N, TriColor>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        debug_assert!(
+            self.config.width == EPD::WIDTH && self.config.height == EPD::HEIGHT,
+            "the size of the display buffer doesn't match the panel"
+        );
+        flush_tri_color(epd, spi, delay, self.bw_buffer(), self.chromatic_buffer())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<SPI, BUSY, DC, RST, DELAY, EPD> FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for DynDisplay<Color>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        flush_var(epd, spi, delay, &self.config, self.buffer())
+    }
+}
+
+/// The tri-color drivers have no partial updates of both planes, the anchor is ignored
+#[cfg(feature = "alloc")]
+impl<SPI, BUSY, DC, RST, DELAY, EPD> FlushableDisplay<EPD, SPI, BUSY, DC, RST, DELAY>
+    for DynDisplay<TriColor>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>,
+{
+    fn flush(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        debug_assert!(
+            self.config.width == EPD::WIDTH && self.config.height == EPD::HEIGHT,
+            "the size of the display buffer doesn't match the panel"
+        );
+        flush_tri_color(epd, spi, delay, self.bw_buffer(), self.chromatic_buffer())
+    }
+}
+
+/// A packed 1 bit per pixel image
+///
+/// Rows are stored MSB first and padded to full bytes, the same layout as a b/w
+/// display buffer (and as most QR code or icon generators output it).
+#[derive(Clone, Copy, Debug)]
+pub struct RawBitImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> RawBitImage<'a> {
+    /// Creates a new image, the height is derived from the length of `data`
+    pub fn new(data: &'a [u8], width: u32) -> Self {
+        let stride = line_bytes(width, 1);
+        let height = data.len().checked_div(stride).unwrap_or(0) as u32;
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Width of the image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes of the given row
+    fn row(&self, y: u32) -> &'a [u8] {
+        let stride = line_bytes(self.width, 1);
+        &self.data[y as usize * stride..(y as usize + 1) * stride]
+    }
+
+    /// Whether the bit of the given pixel is set
+    pub fn bit(&self, x: u32, y: u32) -> bool {
+        self.row(y)[x as usize / 8] & (0x80 >> (x % 8)) != 0
+    }
+
+    /// The set pixels of the image at `top_left` in `color`, cleared ones are left out
+    ///
+    /// Draw them on any [DrawTarget] with `draw_iter`, which takes care of the rotation
+    /// and clipping of the target.
+    pub fn pixels<C: PixelColor + 'a>(
+        &self,
+        top_left: Point,
+        color: C,
+    ) -> impl Iterator<Item = Pixel<C>> + 'a {
+        let image = *self;
+        (0..image.height).flat_map(move |y| {
+            (0..image.width)
+                .filter(move |&x| image.bit(x, y))
+                .map(move |x| Pixel(top_left + Point::new(x as i32, y as i32), color))
+        })
+    }
+}
+
+// This is a function to share code between `Display` and `VarDisplay`
+// It sets a specific pixel in a buffer to a given color.
+// The big number of parameters is due to the fact that it is an internal function to both
+// strctures.
+//
+// Returns false without changing anything if the pixel is out of range.
+fn set_pixel<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    pixel: Pixel<COLOR>,
+) -> bool {
+    let Pixel(point, color) = pixel;
+    let Some((index, x)) = pixel_index::<COLOR>(width, height, rotation, point) else {
+        return false;
+    };
+    let (mask, bits) = color.bitmask(bwrbit, x);
+
+    if COLOR::BUFFER_COUNT == 2 {
+        // split buffer is for tricolor displays that use 2 buffer for 2 bits per pixel
+        buffer[index] = buffer[index] & mask | (bits & 0xFF) as u8;
+        let index = index + buffer.len() / 2;
+        buffer[index] = buffer[index] & mask | (bits >> 8) as u8;
+    } else {
+        buffer[index] = buffer[index] & mask | bits as u8;
+    }
+    true
+}
+
+// Shared by the displays with a `ChromaticPolicy`
+//
+// `set_pixel` with the color the policy draws instead, a pixel the policy drops is
+// left as it is. Returns false if the pixel is out of range.
+#[allow(clippy::too_many_arguments)]
+fn set_pixel_with_policy<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    policy: ChromaticPolicy,
+    pixel: Pixel<COLOR>,
+) -> bool {
+    let Pixel(point, color) = pixel;
+    match color.with_chromatic_policy(policy) {
+        Some(color) => set_pixel(buffer, width, height, rotation, bwrbit, Pixel(point, color)),
+        None => pixel_index::<COLOR>(width, height, rotation, point).is_some(),
+    }
+}
+
+// Converts `point` from rotated to native coordinates of a display of `width` x `height`
+// pixels
+//
+// Mirroring uses the width in pixels, not the padded width of the lines in the buffer
+// (e.g. 122 and not 128 for the 2.13"), which only comes in when indexing the buffer.
+fn native_point(width: u32, height: u32, rotation: DisplayRotation, point: Point) -> Point {
+    // as i32 = never use more than 2 billion pixel per line or per column
+    match rotation {
+        DisplayRotation::Rotate0 => point,
+        DisplayRotation::Rotate90 => Point::new(width as i32 - 1 - point.y, point.x),
+        DisplayRotation::Rotate180 => {
+            Point::new(width as i32 - 1 - point.x, height as i32 - 1 - point.y)
+        }
+        DisplayRotation::Rotate270 => Point::new(point.y, height as i32 - 1 - point.x),
+    }
+}
+
+// Shared by `set_pixel` and `pixel_planes`
+//
+// Converts `point` from rotated to native coordinates and returns the index of its byte
+// in the (first) buffer and its native x coordinate, `None` if it is out of range.
+fn pixel_index<COLOR: ColorType>(
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    point: Point,
+) -> Option<(usize, u32)> {
+    let Point { x, y } = native_point(width, height, rotation, point);
+
+    // Out of range check
+    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
+        return None;
+    }
+
+    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
+        + y as usize * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+    Some((index, x as u32))
+}
+
+// Shared by the `get_pixel` of the b/w and tricolor displays, the inverse of `set_pixel`
+//
+// Returns the bit of the pixel at `x`, `y` (in rotated coordinates) of the black/white
+// plane in bit 0 and the one of the chromatic plane (if any) in bit 1.
+fn pixel_planes<COLOR: ColorType>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    x: u32,
+    y: u32,
+) -> Option<u8> {
+    let point = Point::new(x.try_into().ok()?, y.try_into().ok()?);
+    let (index, x) = pixel_index::<COLOR>(width, height, rotation, point)?;
+    let bit = 0x80 >> (x % 8);
+    let mut planes = u8::from(buffer[index] & bit != 0);
+    if COLOR::BUFFER_COUNT == 2 {
+        planes |= u8::from(buffer[index + buffer.len() / 2] & bit != 0) << 1;
+    }
+    Some(planes)
+}
+
+fn bw_color(planes: u8) -> Color {
+    if planes & 1 != 0 {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
+// Chromatic wins, whatever the black/white bit is (see BWRBIT of `Display`)
+fn tri_color(planes: u8) -> TriColor {
+    if planes & 2 != 0 {
+        TriColor::Chromatic
+    } else if planes & 1 != 0 {
+        TriColor::White
+    } else {
+        TriColor::Black
+    }
+}
+
+/// Mask excluding the pixels `first..last` of a byte and the bits setting them to `color`,
+/// see [ColorType::bitmask]
+fn byte_bits<COLOR: ColorType>(color: COLOR, bwrbit: bool, first: u32, last: u32) -> (u8, u16) {
+    (first..last).fold((0xFF, 0), |(mask, bits), pos| {
+        let (pixel_mask, pixel_bits) = color.bitmask(bwrbit, pos);
+        (mask & pixel_mask, bits | pixel_bits)
+    })
+}
+
+// Shared between `Display` and `VarDisplay`
+//
+// Sets every byte of every buffer to `color`, including the padding bits at the end
+// of the lines.
+fn fill_buffer<COLOR: ColorType + PixelColor>(buffer: &mut [u8], bwrbit: bool, color: COLOR) {
+    if buffer.is_empty() {
+        return;
+    }
+    let pixels_per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+    let (_, bits) = byte_bits(color, bwrbit, 0, pixels_per_byte);
+    let plane_len = buffer.len() / COLOR::BUFFER_COUNT;
+    for (plane, bytes) in buffer.chunks_mut(plane_len).enumerate() {
+        bytes.fill((bits >> (8 * plane)) as u8);
+    }
+}
+
+// Shared between `Display` and `VarDisplay`
+//
+// Fills `area` (in rotated coordinates) with `color`, the same as drawing every pixel
+// with `set_pixel`. The area is converted to native coordinates and filled with
+// `fill_rect_native`.
+#[allow(clippy::too_many_arguments)]
+fn fill_solid<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    area: &Rectangle,
+    color: COLOR,
+) {
+    let size = rotated_size(width, height, rotation);
+    let area = area.intersection(&Rectangle::new(Point::zero(), size));
+    let Some(bottom_right) = area.bottom_right() else {
+        return;
+    };
+
+    let native = |point| native_point(width, height, rotation, point);
+    let rect = Rectangle::with_corners(native(area.top_left), native(bottom_right));
+    fill_rect_native(buffer, width, height, bwrbit, &rect, color);
+}
+
+// Shared between `Display` and `VarDisplay`
+//
+// Fills `rect` (in native coordinates, clipped to the display) with `color`. Every line
+// is a run of whole bytes with masked partial bytes at both ends.
+fn fill_rect_native<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    bwrbit: bool,
+    rect: &Rectangle,
+    color: COLOR,
+) {
+    let rect = rect.intersection(&Rectangle::new(Point::zero(), Size::new(width, height)));
+    if rect.is_zero_sized() {
+        return;
+    }
+    let (x0, y0) = (rect.top_left.x as u32, rect.top_left.y as u32);
+    let (x1, y1) = (x0 + rect.size.width, y0 + rect.size.height);
+
+    let pixels_per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+    let stride = line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+    let plane_len = buffer.len() / COLOR::BUFFER_COUNT;
+    let (_, full) = byte_bits(color, bwrbit, 0, pixels_per_byte);
+
+    // partial bytes at the start and the end of a line
+    let head = x0.next_multiple_of(pixels_per_byte).min(x1);
+    let tail = (x1 / pixels_per_byte * pixels_per_byte).max(head);
+    let edges = [(x0, head), (tail, x1)];
+
+    for y in y0..y1 {
+        let line = y as usize * stride;
+        for plane in 0..COLOR::BUFFER_COUNT {
+            let offset = line + plane * plane_len;
+            let value = (full >> (8 * plane)) as u8;
+            buffer[offset + (head / pixels_per_byte) as usize
+                ..offset + (tail / pixels_per_byte) as usize]
+                .fill(value);
+            for (start, end) in edges {
+                if start == end {
+                    continue;
+                }
+                let (mask, bits) = byte_bits(
+                    color,
+                    bwrbit,
+                    start % pixels_per_byte,
+                    (end - 1) % pixels_per_byte + 1,
+                );
+                let byte = &mut buffer[offset + (start / pixels_per_byte) as usize];
+                *byte = *byte & mask | (bits >> (8 * plane)) as u8;
+            }
+        }
+    }
+}
+
+/// Horizontal doubling of a byte, the most common scale for QR codes
+const DOUBLE_BITS: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut bit = 0;
+        while bit < 8 {
+            if i & (0x80 >> bit) != 0 {
+                table[i] |= 0xC000 >> (bit * 2);
+            }
+            bit += 1;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// Expands every bit of `byte` to `scale` bits, MSB aligned in the result
+fn expand_bits(byte: u8, scale: u32) -> u64 {
+    if scale == 2 {
+        return (DOUBLE_BITS[byte as usize] as u64) << 48;
+    }
+    let run = u64::MAX << (64 - scale);
+    (0..8)
+        .filter(|bit| byte & (0x80 >> bit) != 0)
+        .fold(0, |acc, bit| acc | run >> (bit * scale))
+}
+
+/// Writes the `count` most significant bits of `bits` to `row` starting at pixel `x`,
+/// clipping everything outside of `0..width`
+fn write_bits(row: &mut [u8], width: u32, x: i32, mut bits: u64, count: u32) {
+    let mut count = count as i64;
+    let mut x = x as i64;
+    if x < 0 {
+        let skip = -x;
+        if skip >= count {
+            return;
+        }
+        bits <<= skip;
+        count -= skip;
+        x = 0;
+    }
+    let count = count.min(width as i64 - x);
+    if count <= 0 {
+        return;
+    }
+    let (mut x, mut count) = (x as u32, count as u32);
+    while count > 0 {
+        let offset = x % 8;
+        let take = (8 - offset).min(count);
+        let mask = ((0xFF00u16 >> take) as u8) >> offset;
+        let value = ((bits >> 56) as u8) >> offset;
+        let byte = &mut row[x as usize / 8];
+        *byte = *byte & !mask | value & mask;
+        bits <<= take;
+        count -= take;
+        x += take;
+    }
+}
+
+// Shared between `Display` and `VarDisplay`
+//
+// Blits `src` with every pixel scaled to a `scale` x `scale` square (1 to 8).
+// Unrotated single plane 1 bit displays take a fast path which expands whole source
+// bytes at once (with a lookup table for the common doubling), everything else goes
+// through `set_pixel` so rotation and clipping behave exactly like other drawings.
+#[allow(clippy::too_many_arguments)]
+fn draw_scaled_bitmap<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    src: &RawBitImage,
+    top_left: Point,
+    scale: u8,
+    fg: COLOR,
+    bg: COLOR,
+) {
+    let scale = u32::from(scale.clamp(1, 8));
+
+    let fast_path = matches!(rotation, DisplayRotation::Rotate0)
+        && COLOR::BITS_PER_PIXEL_PER_BUFFER == 1
+        && COLOR::BUFFER_COUNT == 1;
+
+    if !fast_path {
+        for sy in 0..src.height() {
+            for sx in 0..src.width() {
+                let color = if src.bit(sx, sy) { fg } else { bg };
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let point = top_left
+                            + Point::new((sx * scale + dx) as i32, (sy * scale + dy) as i32);
+                        set_pixel(buffer, width, height, rotation, bwrbit, Pixel(point, color));
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    // bit values of the colors within a byte
+    let fg_bits = if fg.bitmask(bwrbit, 0).1 != 0 {
+        u64::MAX
+    } else {
+        0
+    };
+    let bg_bits = if bg.bitmask(bwrbit, 0).1 != 0 {
+        u64::MAX
+    } else {
+        0
+    };
+    let stride = line_bytes(width, 1);
+
+    for sy in 0..src.height() {
+        let row = src.row(sy);
+        for dy in 0..scale {
+            let y = top_left.y as i64 + (sy * scale + dy) as i64;
+            if y < 0 || y >= height as i64 {
+                continue;
+            }
+            let dest = &mut buffer[y as usize * stride..(y as usize + 1) * stride];
+            for (index, &byte) in row.iter().enumerate() {
+                let pixels = (src.width() - index as u32 * 8).min(8);
+                let bits = expand_bits(byte, scale);
+                let bits = bits & fg_bits | !bits & bg_bits;
+                let x = top_left.x as i64 + (index as u32 * 8 * scale) as i64;
+                if x >= width as i64 {
+                    break;
+                }
+                write_bits(dest, width, x as i32, bits, pixels * scale);
+            }
+        }
+    }
+}
+
+/// Size of an ordered dithering matrix
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bayer {
+    /// 4x4 matrix, 17 levels of gray
+    X4,
+    /// 8x8 matrix, 65 levels of gray
+    X8,
+}
+
+/// Dithering algorithm for [Display::draw_gray_image_dithered]
+#[derive(Debug)]
+pub enum Dither<'a> {
+    /// Ordered dithering with a Bayer matrix, regular patterns but no memory needed
+    Ordered(Bayer),
+    /// Floyd-Steinberg error diffusion, looks best for photos
+    ///
+    /// Needs a buffer for the error of one line, at least as long as the image is wide.
+    FloydSteinberg(&'a mut [i16]),
+}
+
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// 8 bit grayscale image, `width` bytes per line
+struct GrayImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+// Shared between `Display` and `VarDisplay`
+//
+// Dithers `image` to black and white, every pixel goes through `set_pixel` so rotation
+// and clipping behave like for other drawings. The matrix of ordered dithering is
+// aligned to the image, not to the display.
+#[allow(clippy::too_many_arguments)]
+fn draw_gray_image_dithered<COLOR: ColorType + PixelColor + From<BinaryColor>>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    image: GrayImage,
+    top_left: Point,
+    algo: Dither,
+) {
+    let mut draw = |x: u32, y: u32, white: bool| {
+        let color = if white {
+            BinaryColor::Off
+        } else {
+            BinaryColor::On
+        };
+        let point = top_left + Point::new(x as i32, y as i32);
+        set_pixel(
+            buffer,
+            width,
+            height,
+            rotation,
+            bwrbit,
+            Pixel(point, COLOR::from(color)),
+        );
+    };
+    let lines = image
+        .data
+        .chunks(image.width.max(1) as usize)
+        .take(image.height as usize)
+        .zip(0..);
+
+    match algo {
+        Dither::Ordered(bayer) => {
+            for (line, y) in lines {
+                for (&gray, x) in line.iter().zip(0..) {
+                    // threshold in the middle of every matrix step
+                    let white = match bayer {
+                        Bayer::X4 => {
+                            let step = BAYER_4X4[y as usize % 4][x as usize % 4] as u32;
+                            gray as u32 * 16 >= step * 256 + 128
+                        }
+                        Bayer::X8 => {
+                            let step = BAYER_8X8[y as usize % 8][x as usize % 8] as u32;
+                            gray as u32 * 64 >= step * 256 + 128
+                        }
+                    };
+                    draw(x, y, white);
+                }
+            }
+        }
+        Dither::FloydSteinberg(errors) => {
+            // errors[x] is the error diffused to pixel x of the current line, the parts
+            // for the next line are written behind the current pixel
+            let errors = &mut errors[..image.width as usize];
+            errors.fill(0);
+            for (line, y) in lines {
+                let mut right = 0i16;
+                let (mut below_left, mut below) = (0i16, 0i16);
+                for (&gray, x) in line.iter().zip(0..) {
+                    let value = gray as i16 + errors[x as usize] + right;
+                    let white = value >= 128;
+                    let error = value - if white { 255 } else { 0 };
+                    draw(x, y, white);
+
+                    right = error * 7 / 16;
+                    if x > 0 {
+                        errors[x as usize - 1] = below_left + error * 3 / 16;
+                    }
+                    below_left = below + error * 5 / 16;
+                    below = error / 16;
+                }
+                if let Some(last) = errors.last_mut() {
+                    *last = below_left;
+                }
+            }
+        }
+    }
+}
+
+/// Splits `text` into lines of at most `max_chars` characters, see [wrap_lines]
+#[derive(Clone, Debug)]
+pub struct WrapLines<'a> {
+    rest: Option<&'a str>,
+    max_chars: usize,
+    marker: Option<char>,
+}
+
+/// Word wraps `text` into lines of at most `max_chars` characters, e.g. for a monospace font
+///
+/// Lines are broken at whitespace, which is dropped at the end of a line and at the
+/// start of a wrapped line. Words longer than a line are split, `'\n'` always starts a
+/// new line. A trailing `'\n'` doesn't add an empty line.
+pub fn wrap_lines(text: &str, max_chars: usize) -> WrapLines<'_> {
+    WrapLines::new(text, max_chars, None)
+}
+
+impl<'a> WrapLines<'a> {
+    /// `marker` characters are kept in the lines but don't take up space
+    fn new(text: &'a str, max_chars: usize, marker: Option<char>) -> Self {
+        Self {
+            rest: (!text.is_empty()).then_some(text),
+            max_chars,
+            marker,
+        }
+    }
+}
+
+impl<'a> Iterator for WrapLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.max_chars == 0 {
+            return None;
+        }
+        let rest = self.rest?;
+        let (paragraph, after) = match rest.split_once('\n') {
+            Some((paragraph, after)) => (paragraph, (!after.is_empty()).then_some(after)),
+            None => (rest, None),
+        };
+
+        let overflow = paragraph
+            .char_indices()
+            .filter(|&(_, c)| Some(c) != self.marker)
+            .nth(self.max_chars);
+        let Some((overflow, c)) = overflow else {
+            self.rest = after;
+            return Some(paragraph.trim_end());
+        };
+
+        let cut = if c.is_whitespace() {
+            overflow
+        } else {
+            paragraph[..overflow]
+                .rfind(char::is_whitespace)
+                .filter(|&space| !paragraph[..space].trim_end().is_empty())
+                // a single word longer than the line
+                .unwrap_or(overflow)
+        };
+        let remainder = paragraph[cut..].trim_start();
+        self.rest = if remainder.is_empty() {
+            after
+        } else {
+            Some(&rest[paragraph.len() - remainder.len()..])
+        };
+        Some(paragraph[..cut].trim_end())
+    }
+}
+
+/// Prefix of `line` with `count` characters, not counting `marker`
+#[cfg(feature = "text")]
+fn take_chars(line: &str, count: usize, marker: Option<char>) -> &str {
+    let end = line
+        .char_indices()
+        .filter(|&(_, c)| Some(c) != marker)
+        .nth(count)
+        .map_or(line.len(), |(index, _)| index);
+    &line[..end]
+}
+
+/// Result of drawing a [TextBox]
+#[cfg(feature = "text")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextBoxLayout {
+    /// Number of lines drawn
+    pub lines: u32,
+    /// Whether the text didn't fit, the last line ends with "..." then
+    pub truncated: bool,
+}
+
+/// Word wrapped text in a rectangle, with a monospace font
+///
+/// The text is wrapped like [wrap_lines] and truncated with "..." if it has more lines
+/// than fit into the box. With [emphasis](TextBox::with_emphasis) a marker character
+/// switches between the normal and the emphasis color, e.g. black and red on
+/// [TriColor] displays. Needs the `text` feature.
+///
+/// ```rust
+/// use embedded_graphics::{mono_font::ascii::FONT_6X10, prelude::*, primitives::Rectangle};
+/// use epd_waveshare::{color::TriColor, epd2in13bc::Display2in13bc, graphics::TextBox};
+///
+/// let mut display = Display2in13bc::default();
+/// let bounds = Rectangle::new(Point::new(4, 4), Size::new(96, 60));
+/// let layout = TextBox::new("Some *important* words", bounds, &FONT_6X10, TriColor::Black)
+///     .with_emphasis('*', TriColor::Chromatic)
+///     .draw(&mut display)
+///     .unwrap();
+/// assert!(!layout.truncated);
+/// ```
+#[cfg(feature = "text")]
+#[derive(Clone, Copy, Debug)]
+pub struct TextBox<'a, C> {
+    text: &'a str,
+    bounds: Rectangle,
+    font: &'a MonoFont<'a>,
+    color: C,
+    emphasis: Option<(char, C)>,
+    align: HAlign,
+}
+
+#[cfg(feature = "text")]
+impl<'a, C: PixelColor> TextBox<'a, C> {
+    /// Text drawn with `font` in `color` into `bounds`
+    pub fn new(text: &'a str, bounds: Rectangle, font: &'a MonoFont<'a>, color: C) -> Self {
+        Self {
+            text,
+            bounds,
+            font,
+            color,
+            emphasis: None,
+            align: HAlign::Left,
+        }
+    }
+
+    /// Aligns every line horizontally in the box, left by default
+    pub fn with_alignment(mut self, align: HAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Every `marker` in the text toggles between the normal and the `emphasis` color
+    ///
+    /// The markers themselves aren't drawn.
+    pub fn with_emphasis(mut self, marker: char, emphasis: C) -> Self {
+        self.emphasis = Some((marker, emphasis));
+        self
+    }
+
+    /// Characters fitting into one line
+    fn max_chars(&self) -> usize {
+        let spacing = self.font.character_spacing;
+        ((self.bounds.size.width + spacing) / (self.font.character_size.width + spacing)) as usize
+    }
+
+    /// Size of a line with `chars` characters
+    fn line_size(&self, chars: usize) -> Size {
+        let spacing = self.font.character_spacing;
+        let width =
+            (chars as u32 * (self.font.character_size.width + spacing)).saturating_sub(spacing);
+        Size::new(width, self.font.character_size.height)
+    }
+
+    /// Draws the text, returning how many lines were drawn and whether it was truncated
+    pub fn draw<D: DrawTarget<Color = C>>(
+        &self,
+        target: &mut D,
+    ) -> Result<TextBoxLayout, D::Error> {
+        let marker = self.emphasis.map(|(marker, _)| marker);
+        let max_chars = self.max_chars();
+        let max_lines = self.bounds.size.height / self.font.character_size.height;
+        let mut lines = WrapLines::new(self.text, max_chars, marker).peekable();
+        let mut emphasized = false;
+        let mut layout = TextBoxLayout {
+            lines: 0,
+            truncated: false,
+        };
+
+        while layout.lines < max_lines {
+            let Some(mut line) = lines.next() else {
+                break;
+            };
+            let last = layout.lines + 1 == max_lines;
+            layout.truncated = last && lines.peek().is_some();
+            if layout.truncated {
+                line = take_chars(line, max_chars.saturating_sub(3), marker).trim_end();
+            }
+
+            let row = Rectangle::new(
+                self.bounds.top_left
+                    + Point::new(0, (layout.lines * self.font.character_size.height) as i32),
+                Size::new(self.bounds.size.width, self.font.character_size.height),
+            );
+            let mut chars = line.chars().filter(|&c| Some(c) != marker).count();
+            if layout.truncated {
+                chars += 3.min(max_chars);
+            }
+            let mut position = align_in(&row, self.line_size(chars), self.align, VAlign::Top);
+            let mut segments = line.split(|c| Some(c) == marker).peekable();
+            while let Some(segment) = segments.next() {
+                position = self.draw_segment(target, segment, position, emphasized)?;
+                if segments.peek().is_some() {
+                    emphasized = !emphasized;
+                }
+            }
+            if layout.truncated {
+                let dots = &"..."[..3.min(max_chars)];
+                self.draw_segment(target, dots, position, emphasized)?;
+            }
+            layout.lines += 1;
+        }
+        Ok(layout)
+    }
+
+    fn draw_segment<D: DrawTarget<Color = C>>(
+        &self,
+        target: &mut D,
+        segment: &str,
+        position: Point,
+        emphasized: bool,
+    ) -> Result<Point, D::Error> {
+        let color = match self.emphasis {
+            Some((_, emphasis)) if emphasized => emphasis,
+            _ => self.color,
+        };
+        let style = MonoTextStyle::new(self.font, color);
+        Text::with_baseline(segment, position, style, Baseline::Top).draw(target)
+    }
+}
+
+/// A sparkline of `u16` samples, e.g. the sensor readings of the last hours
+///
+/// Every sample is drawn as a column of one pixel width from the bottom of `bounds`:
+/// the smallest sample as a single pixel, the largest one filling the height. With more
+/// samples than columns only the newest (last) ones are shown.
+///
+#[cfg_attr(feature = "epd2in9_v2", doc = "```rust")]
+#[cfg_attr(not(feature = "epd2in9_v2"), doc = "```ignore")]
+/// use embedded_graphics::{prelude::*, primitives::Rectangle};
+/// use epd_waveshare::{color::Color, epd2in9_v2::Display2in9, graphics::Sparkline};
+///
+/// let mut display = Display2in9::default();
+/// display.clear(Color::White).unwrap();
+/// let bounds = Rectangle::new(Point::new(10, 10), Size::new(4, 8));
+/// Sparkline::new(&[20, 25, 30, 20], bounds, Color::Black)
+///     .draw(&mut display)
+///     .unwrap();
+/// assert_eq!(display.get_pixel(12, 10), Some(Color::Black));
+/// assert_eq!(display.get_pixel(13, 16), Some(Color::White));
+/// assert_eq!(display.get_pixel(13, 17), Some(Color::Black));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Sparkline<'a, C> {
+    samples: &'a [u16],
+    bounds: Rectangle,
+    color: C,
+}
+
+impl<'a, C: PixelColor> Sparkline<'a, C> {
+    /// Plots `samples` into `bounds` with `color`
+    pub fn new(samples: &'a [u16], bounds: Rectangle, color: C) -> Self {
+        Self {
+            samples,
+            bounds,
+            color,
+        }
+    }
+
+    /// Draws the columns, the rest of `bounds` is left as it is
+    pub fn draw<D: DrawTarget<Color = C>>(&self, target: &mut D) -> Result<(), D::Error> {
+        let Size { width, height } = self.bounds.size;
+        if height == 0 {
+            return Ok(());
+        }
+        let samples = &self.samples[self.samples.len().saturating_sub(width as usize)..];
+        let (min, max) = samples
+            .iter()
+            .fold((u16::MAX, u16::MIN), |(min, max), &sample| {
+                (min.min(sample), max.max(sample))
+            });
+        let range = u64::from(max.saturating_sub(min)).max(1);
+        for (column, &sample) in samples.iter().enumerate() {
+            let scaled = u64::from(sample - min) * u64::from(height - 1) / range;
+            let length = scaled as u32 + 1;
+            let top_left =
+                self.bounds.top_left + Point::new(column as i32, (height - length) as i32);
+            target.fill_solid(&Rectangle::new(top_left, Size::new(1, length)), self.color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Longest row [RowRenderer::stream_to] renders, in bytes (1024 pixels)
+const MAX_ROW_BYTES: usize = 128;
+
+/// A draw command of a [RowRenderer], in native coordinates of the panel
+#[derive(Clone, Copy, Debug)]
+pub enum RowCommand<'a> {
+    /// Fills `area` with `color`
+    Fill {
+        /// Filled rectangle
+        area: Rectangle,
+        /// Color of the rectangle
+        color: Color,
+    },
+    /// Draws the set pixels of `image` in `color`, cleared ones are left as they are like
+    /// with [RawBitImage::pixels]
+    Bitmap {
+        /// The image
+        image: RawBitImage<'a>,
+        /// Top left corner of the image
+        top_left: Point,
+        /// Color of the set pixels
+        color: Color,
+    },
+    /// Draws one line of `text` in `color` without background, like an embedded-graphics
+    /// `Text` with [Baseline::Top] and a [MonoTextStyle] without background color.
+    /// Needs the `text` feature.
+    #[cfg(feature = "text")]
+    Text {
+        /// The text, `'\n'` doesn't start a new line
+        text: &'a str,
+        /// Top left corner of the first character
+        top_left: Point,
+        /// Monospace font of the text
+        font: &'a MonoFont<'a>,
+        /// Color of the glyphs
+        color: Color,
+    },
+}
+
+/// Renders simple b/w scenes row by row while they are sent, without any frame buffer
+///
+/// Up to `N` [commands](RowCommand) are drawn in the order they were pushed on top of
+/// the background, each row is rendered into a scratch row on the stack just before it
+/// is sent with [RowStreaming]. This suits screens of a few text lines, rectangles and
+/// icons on controllers with too little RAM for a frame buffer. There is no rotation,
+/// the commands are in the native coordinates of the panel.
+///
+/// ```rust
+/// use embedded_graphics::{prelude::*, primitives::Rectangle};
+/// use epd_waveshare::{color::Color, graphics::*};
+///
+/// let mut scene = RowRenderer::<4>::new(Color::White);
+/// scene.fill(Rectangle::new(Point::new(0, 2), Size::new(16, 1)), Color::Black)
+///     .unwrap();
+///
+/// let mut row = [0; 2];
+/// scene.render_row(2, 16, &mut row);
+/// assert_eq!(row, [0x00, 0x00]);
+/// scene.render_row(3, 16, &mut row);
+/// assert_eq!(row, [0xFF, 0xFF]);
+/// ```
+#[derive(Clone)]
+pub struct RowRenderer<'a, const N: usize> {
+    background: Color,
+    commands: [Option<RowCommand<'a>>; N],
+    len: usize,
+}
+
+impl<'a, const N: usize> RowRenderer<'a, N> {
+    /// Creates an empty scene of `background`
+    pub fn new(background: Color) -> Self {
+        Self {
+            background,
+            commands: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Adds `command` on top of the previous ones, gives it back if all `N` are in use
+    pub fn push(&mut self, command: RowCommand<'a>) -> Result<(), RowCommand<'a>> {
+        let Some(slot) = self.commands.get_mut(self.len) else {
+            return Err(command);
+        };
+        *slot = Some(command);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Adds a [RowCommand::Fill]
+    pub fn fill(&mut self, area: Rectangle, color: Color) -> Result<(), RowCommand<'a>> {
+        self.push(RowCommand::Fill { area, color })
+    }
+
+    /// Adds a [RowCommand::Bitmap]
+    pub fn bitmap(
+        &mut self,
+        image: RawBitImage<'a>,
+        top_left: Point,
+        color: Color,
+    ) -> Result<(), RowCommand<'a>> {
+        self.push(RowCommand::Bitmap {
+            image,
+            top_left,
+            color,
+        })
+    }
+
+    /// Adds a [RowCommand::Text]
+    #[cfg(feature = "text")]
+    pub fn text(
+        &mut self,
+        text: &'a str,
+        top_left: Point,
+        font: &'a MonoFont<'a>,
+        color: Color,
+    ) -> Result<(), RowCommand<'a>> {
+        self.push(RowCommand::Text {
+            text,
+            top_left,
+            font,
+            color,
+        })
+    }
+
+    /// Number of commands
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no commands, the scene is only background
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all commands
+    pub fn clear(&mut self) {
+        self.commands = [None; N];
+        self.len = 0;
+    }
+
+    /// Renders row `y` of a panel `width` pixels wide into `row`, which needs at least
+    /// [line_bytes](crate::line_bytes) of the width
+    ///
+    /// The result is the same as the row of a [Display] drawn with the commands.
+    pub fn render_row(&self, y: u32, width: u32, row: &mut [u8]) {
+        let row = &mut row[..line_bytes(width, 1)];
+        row.fill(byte_bits(self.background, false, 0, 8).1 as u8);
+        let y = y as i64;
+
+        for command in self.commands.iter().flatten() {
+            match *command {
+                RowCommand::Fill { area, color } => {
+                    let top = i64::from(area.top_left.y);
+                    if y < top || y >= top + i64::from(area.size.height) {
+                        continue;
+                    }
+                    let x0 = i64::from(area.top_left.x).max(0);
+                    let x1 = (i64::from(area.top_left.x) + i64::from(area.size.width))
+                        .min(i64::from(width));
+                    for x in (x0..x1).step_by(64) {
+                        let count = (x1 - x).min(64) as u32;
+                        paint_bits(row, width, x, u64::MAX, count, color);
+                    }
+                }
+                RowCommand::Bitmap {
+                    image,
+                    top_left,
+                    color,
+                } => {
+                    let line = y - i64::from(top_left.y);
+                    if line < 0 || line >= i64::from(image.height()) {
+                        continue;
+                    }
+                    for (index, &byte) in image.row(line as u32).iter().enumerate() {
+                        let x = i64::from(top_left.x) + index as i64 * 8;
+                        let count = (image.width() - index as u32 * 8).min(8);
+                        paint_bits(row, width, x, u64::from(byte) << 56, count, color);
+                    }
+                }
+                #[cfg(feature = "text")]
+                RowCommand::Text {
+                    text,
+                    top_left,
+                    font,
+                    color,
+                } => {
+                    let line = y - i64::from(top_left.y);
+                    if line < 0 || line >= i64::from(font.character_size.height) {
+                        continue;
+                    }
+                    paint_text_row(row, width, text, top_left.x, line as u32, font, color);
+                }
+            }
+        }
+    }
+
+    /// Sends the scene to `epd` row by row, it is shown with the next `display_frame`
+    ///
+    /// Fails to compile for panels wider than 1024 pixels.
+    pub fn stream_to<SPI, BUSY, DC, RST, DELAY, EPD>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>
+            + RowStreaming<SPI, BUSY, DC, RST, DELAY>,
+    {
+        const {
+            assert!(
+                line_bytes(EPD::WIDTH, 1) <= MAX_ROW_BYTES,
+                "the panel is too wide for the row scratch buffer"
+            )
+        }
+        let mut scratch = [0; MAX_ROW_BYTES];
+        let row = &mut scratch[..line_bytes(EPD::WIDTH, 1)];
+        epd.update_frame_rows(spi, delay, row, |y, row| {
+            self.render_row(y, EPD::WIDTH, row)
+        })
+    }
+}
+
+/// Sets the pixels of the set bits among the `count` most significant bits of `bits` to
+/// `color`, starting at pixel `x` of `row` and clipped to `0..width`
+fn paint_bits(row: &mut [u8], width: u32, x: i64, mut bits: u64, count: u32, color: Color) {
+    let mut count = i64::from(count);
+    let mut x = x;
+    if x < 0 {
+        let skip = -x;
+        if skip >= count {
+            return;
+        }
+        bits <<= skip;
+        count -= skip;
+        x = 0;
+    }
+    let count = count.min(i64::from(width) - x);
+    if count <= 0 {
+        return;
+    }
+    let set = color.bitmask(false, 0).1 != 0;
+    let (mut x, mut count) = (x as u32, count as u32);
+    while count > 0 {
+        let offset = x % 8;
+        let take = (8 - offset).min(count);
+        let mask = ((0xFF00u16 >> take) as u8) >> offset & ((bits >> 56) as u8) >> offset;
+        let byte = &mut row[x as usize / 8];
+        *byte = if set { *byte | mask } else { *byte & !mask };
+        bits <<= take;
+        count -= take;
+        x += take;
+    }
+}
+
+/// Paints row `line` of the glyphs of `text`, see [RowCommand::Text]
+#[cfg(feature = "text")]
+fn paint_text_row(
+    row: &mut [u8],
+    width: u32,
+    text: &str,
+    left: i32,
+    line: u32,
+    font: &MonoFont,
+    color: Color,
 ) {
-    let Pixel(point, color) = pixel;
+    use embedded_graphics::image::GetPixel;
+
+    let Size {
+        width: char_width,
+        height: char_height,
+    } = font.character_size;
+    if char_width == 0 || font.image.size().width < char_width {
+        return;
+    }
+    let glyphs_per_row = font.image.size().width / char_width;
+    let advance = i64::from(char_width + font.character_spacing);
+
+    for (index, c) in text.chars().enumerate() {
+        let x = i64::from(left) + index as i64 * advance;
+        if x >= i64::from(width) {
+            break;
+        }
+        if x + i64::from(char_width) <= 0 {
+            continue;
+        }
+        // the glyphs are laid out in rows of `glyphs_per_row` in the font image
+        let glyph = font.glyph_mapping.index(c) as u32;
+        let glyph_x = glyph % glyphs_per_row * char_width;
+        let glyph_y = (glyph / glyphs_per_row * char_height + line) as i32;
+        for start in (0..char_width).step_by(64) {
+            let count = (char_width - start).min(64);
+            let bits = (0..count)
+                .filter(|dx| {
+                    let point = Point::new((glyph_x + start + dx) as i32, glyph_y);
+                    font.image.pixel(point) == Some(BinaryColor::On)
+                })
+                .fold(0u64, |bits, dx| bits | 1 << (63 - dx));
+            paint_bits(row, width, x + i64::from(start), bits, count, color);
+        }
+    }
+}
+
+/// How chromatic pixels show up on a b/w frame, see [Display::to_bw_buffer]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChromaticAs {
+    /// Black pixels
+    Black,
+    /// White pixels
+    White,
+    /// A checkered pattern, to tell them apart from black and white areas
+    Dithered,
+}
+
+impl From<Color> for ChromaticAs {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => ChromaticAs::Black,
+            Color::White => ChromaticAs::White,
+        }
+    }
+}
+
+/// Merges the planes `bw` and `chromatic` of a tri-color frame with rows of `stride`
+/// bytes into `out`, chromatic wins over the black/white bit like on the panels
+fn merge_chromatic(
+    bw: &[u8],
+    chromatic: &[u8],
+    stride: usize,
+    out: &mut [u8],
+    chromatic_as: ChromaticAs,
+) {
+    let out = &mut out[..bw.len()];
+    let rows = out.chunks_mut(stride).zip(bw.chunks(stride));
+    for (y, ((out, bw), chromatic)) in rows.zip(chromatic.chunks(stride)).enumerate() {
+        let fill = match chromatic_as {
+            ChromaticAs::Black => 0x00,
+            ChromaticAs::White => 0xFF,
+            // white on even pixels of even rows
+            ChromaticAs::Dithered if y % 2 == 0 => 0xAA,
+            ChromaticAs::Dithered => 0x55,
+        };
+        for ((out, bw), chromatic) in out.iter_mut().zip(bw).zip(chromatic) {
+            *out = bw & !chromatic | fill & chromatic;
+        }
+    }
+}
+
+/// Writes `new` into the b/w half of `planes` and marks the bits differing from `old`
+/// in the chromatic half, see [Display::diff_overlay]
+#[cfg(feature = "debug-tools")]
+fn diff_overlay_planes(
+    planes: &mut [u8],
+    width: u32,
+    bwrbit: bool,
+    old: &[u8],
+    new: &[u8],
+) -> bool {
+    let (bw, chromatic) = planes.split_at_mut(planes.len() / 2);
+    if old.len() < bw.len() || new.len() < bw.len() {
+        return false;
+    }
+    let stride = row_stride::<TriColor>(width);
+    // the pixels in the last byte of a row, without the padding
+    let last = match width % 8 {
+        0 => 0xFF,
+        rest => 0xFF << (8 - rest),
+    };
+    // the b/w bit of a chromatic pixel, bitmask of the first pixel of a byte
+    let (_, bits) = TriColor::Chromatic.bitmask(bwrbit, 0);
+    let bw_set = bits & 0x80 != 0;
+    let rows = bw.chunks_mut(stride).zip(chromatic.chunks_mut(stride));
+    for ((bw, chromatic), (old, new)) in rows.zip(old.chunks(stride).zip(new.chunks(stride))) {
+        let bytes = bw.iter_mut().zip(chromatic.iter_mut());
+        for (x, ((bw, chromatic), (old, new))) in bytes.zip(old.iter().zip(new)).enumerate() {
+            let pixels = if x + 1 == stride { last } else { 0xFF };
+            let changed = (old ^ new) & pixels;
+            *chromatic = changed;
+            *bw = if bw_set {
+                new | changed
+            } else {
+                new & !changed
+            };
+        }
+    }
+    true
+}
+
+/// Transformation of a finished frame, see [Display::transform_buffer]
+///
+/// Rotations are clockwise, like [DisplayRotation].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferTransform {
+    /// Rotate by 90 degrees clockwise, only possible in place if the frame is square
+    Rotate90,
+    /// Rotate by 180 degrees
+    Rotate180,
+    /// Rotate by 270 degrees clockwise, only possible in place if the frame is square
+    Rotate270,
+    /// Mirror left and right
+    FlipHorizontal,
+    /// Mirror top and bottom
+    FlipVertical,
+}
+
+/// Rotates a packed 1 bit per pixel frame of `width` x `height` pixels by 180 degrees
+///
+/// Rows are padded to whole bytes like in the buffers of the displays, the padding bits
+/// are cleared. Panics if `buf` is shorter than the frame.
+pub fn rotate180_in_place(buf: &mut [u8], width: u32, height: u32) {
+    let frame = &mut buf[..crate::buffer_len(width as usize, height as usize)];
+    frame.reverse();
+    frame
+        .iter_mut()
+        .for_each(|byte| *byte = byte.reverse_bits());
+    // the padding moved to the start of every row
+    for row in frame.chunks_exact_mut(line_bytes(width, 1)) {
+        drop_padding(row, width);
+    }
+}
+
+/// Mirrors a packed 1 bit per pixel frame of `width` x `height` pixels left to right
+///
+/// See [rotate180_in_place] for the layout.
+pub fn flip_horizontal_in_place(buf: &mut [u8], width: u32, height: u32) {
+    let frame = &mut buf[..crate::buffer_len(width as usize, height as usize)];
+    for row in frame.chunks_exact_mut(line_bytes(width, 1)) {
+        row.reverse();
+        row.iter_mut().for_each(|byte| *byte = byte.reverse_bits());
+        drop_padding(row, width);
+    }
+}
+
+/// Mirrors a packed 1 bit per pixel frame of `width` x `height` pixels top to bottom
+///
+/// See [rotate180_in_place] for the layout.
+pub fn flip_vertical_in_place(buf: &mut [u8], width: u32, height: u32) {
+    let line = line_bytes(width, 1);
+    let frame = &mut buf[..line * height as usize];
+    let (top, bottom) = frame.split_at_mut(line * (height as usize / 2));
+    let bottom_rows = bottom.rchunks_exact_mut(line);
+    for (upper, lower) in top.chunks_exact_mut(line).zip(bottom_rows) {
+        upper.swap_with_slice(lower);
+    }
+}
+
+/// Rotates a packed 1 bit per pixel frame of `width` x `height` pixels by 90 degrees
+/// clockwise into `dst`, which is `height` pixels wide and `width` pixels high
+///
+/// See [rotate180_in_place] for the layout. Panics if `src` or `dst` are shorter than
+/// their frame.
+pub fn rotate90_into(src: &[u8], width: u32, height: u32, dst: &mut [u8]) {
+    transpose_into(src, width, height, dst);
+    flip_horizontal_in_place(dst, height, width);
+}
+
+/// Rotates a packed 1 bit per pixel frame of `width` x `height` pixels by 270 degrees
+/// clockwise into `dst`, which is `height` pixels wide and `width` pixels high
+///
+/// See [rotate90_into].
+pub fn rotate270_into(src: &[u8], width: u32, height: u32, dst: &mut [u8]) {
+    transpose_into(src, width, height, dst);
+    flip_vertical_in_place(dst, height, width);
+}
+
+/// How [downscale_into] picks the color of a destination pixel from the source pixels it
+/// covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalePolicy {
+    /// The source pixel in the middle, fastest but drops lines thinner than the ratio
+    NearestNeighbor,
+    /// Black if any of the source pixels is, keeps lines of 1 pixel at the cost of bolder
+    /// text
+    BlackWins,
+    /// The color of most of the source pixels, black on a tie
+    Majority,
+}
+
+/// Scales a packed 1 bit per pixel frame of `src_w` x `src_h` pixels to `dst_w` x `dst_h`
+/// pixels into `dst`, e.g. a frame rendered for the 7.5" to the 4.2"
+///
+/// Every destination pixel covers `src_w / dst_w` x `src_h / dst_h` source pixels, ratios
+/// which aren't whole are stepped in 16.16 fixed point. A cleared bit is black like in the
+/// b/w buffers, see [rotate180_in_place] for the layout. Scaling up repeats pixels. Panics
+/// if `src` or `dst` are shorter than their frame.
+pub fn downscale_into(
+    src: &[u8],
+    src_w: u32,
+    src_h: u32,
+    dst: &mut [u8],
+    dst_w: u32,
+    dst_h: u32,
+    policy: ScalePolicy,
+) {
+    let (src_size, dst_size) = (Size::new(src_w, src_h), Size::new(dst_w, dst_h));
+    scale_plane(src, src_size, dst, dst_size, policy, false);
+}
+
+/// Scales the frame of a tricolor display like [downscale_into], one plane at a time
+///
+/// `src` and `dst` hold the b/w plane followed by the chromatic plane, like the buffer of a
+/// [Display] with [TriColor]. On the chromatic plane `policy` treats a set bit like black,
+/// so [ScalePolicy::BlackWins] keeps thin chromatic lines too.
+pub fn downscale_tri_color_into(
+    src: &[u8],
+    src_w: u32,
+    src_h: u32,
+    dst: &mut [u8],
+    dst_w: u32,
+    dst_h: u32,
+    policy: ScalePolicy,
+) {
+    let (src_len, dst_len) = (
+        crate::buffer_len(src_w as usize, src_h as usize),
+        crate::buffer_len(dst_w as usize, dst_h as usize),
+    );
+    let (src_size, dst_size) = (Size::new(src_w, src_h), Size::new(dst_w, dst_h));
+    let (src_bw, src_chromatic) = src[..2 * src_len].split_at(src_len);
+    let (dst_bw, dst_chromatic) = dst[..2 * dst_len].split_at_mut(dst_len);
+    scale_plane(src_bw, src_size, dst_bw, dst_size, policy, false);
+    scale_plane(
+        src_chromatic,
+        src_size,
+        dst_chromatic,
+        dst_size,
+        policy,
+        true,
+    );
+}
+
+// Shared by `downscale_into` and `downscale_tri_color_into`
+//
+// `ink` is the bit value which wins with `BlackWins` and on a tie with `Majority`.
+fn scale_plane(
+    src: &[u8],
+    src_size: Size,
+    dst: &mut [u8],
+    dst_size: Size,
+    policy: ScalePolicy,
+    ink: bool,
+) {
+    let (src_line, dst_line) = (line_bytes(src_size.width, 1), line_bytes(dst_size.width, 1));
+    let src = &src[..src_line * src_size.height as usize];
+    let dst = &mut dst[..dst_line * dst_size.height as usize];
+    if dst.is_empty() {
+        return;
+    }
+    assert!(src_size.width > 0 && src_size.height > 0);
+    let step_x = scale_step(src_size.width, dst_size.width);
+    let step_y = scale_step(src_size.height, dst_size.height);
+    let pixel =
+        |x: u32, y: u32| src[y as usize * src_line + x as usize / 8] & (0x80 >> (x % 8)) != 0;
+
+    for (dy, row) in (0..).zip(dst.chunks_exact_mut(dst_line)) {
+        let rows = scale_span(dy, step_y, src_size.height);
+        row.fill(0);
+        for dx in 0..dst_size.width {
+            let columns = scale_span(dx, step_x, src_size.width);
+            let set = match policy {
+                ScalePolicy::NearestNeighbor => pixel(
+                    (columns.start + columns.end) / 2,
+                    (rows.start + rows.end) / 2,
+                ),
+                ScalePolicy::BlackWins => {
+                    let mut area = rows
+                        .clone()
+                        .flat_map(|y| columns.clone().map(move |x| (x, y)));
+                    if area.any(|(x, y)| pixel(x, y) == ink) {
+                        ink
+                    } else {
+                        !ink
+                    }
+                }
+                ScalePolicy::Majority => {
+                    let inked = rows
+                        .clone()
+                        .flat_map(|y| columns.clone().map(move |x| (x, y)))
+                        .filter(|&(x, y)| pixel(x, y) == ink)
+                        .count() as u32;
+                    let total = columns.len() as u32 * rows.len() as u32;
+                    (2 * inked >= total) == ink
+                }
+            };
+            if set {
+                row[dx as usize / 8] |= 0x80 >> (dx % 8);
+            }
+        }
+    }
+}
+
+/// Source pixels per destination pixel in 16.16 fixed point, rounded up so the last
+/// destination pixel reaches the end of the source
+fn scale_step(src: u32, dst: u32) -> u64 {
+    (u64::from(src) << 16).div_ceil(u64::from(dst))
+}
+
+/// The source pixels covered by destination pixel `d`, at least one
+fn scale_span(d: u32, step: u64, src: u32) -> core::ops::Range<u32> {
+    let start = ((u64::from(d) * step) >> 16).min(u64::from(src) - 1) as u32;
+    let end = ((u64::from(d + 1) * step) >> 16).min(u64::from(src)) as u32;
+    start..end.max(start + 1)
+}
+
+/// Shifts the pixels of `row` to the start, over the padding left by reversing it
+fn drop_padding(row: &mut [u8], width: u32) {
+    let pad = (row.len() as u32 * 8 - width) as u8;
+    if pad == 0 {
+        return;
+    }
+    for i in 0..row.len() {
+        let next = row.get(i + 1).map_or(0, |byte| byte >> (8 - pad));
+        row[i] = (row[i] << pad) | next;
+    }
+}
+
+/// Mirrors `src` of `width` x `height` pixels along its diagonal into `dst`, one block of
+/// 8x8 pixels at a time
+fn transpose_into(src: &[u8], width: u32, height: u32, dst: &mut [u8]) {
+    let (src_line, dst_line) = (line_bytes(width, 1), line_bytes(height, 1));
+    assert!(src.len() >= src_line * height as usize);
+    assert!(dst.len() >= dst_line * width as usize);
+    for by in 0..dst_line {
+        for bx in 0..src_line {
+            let block = transpose_block(gather_block(src, src_line, height, bx, by));
+            scatter_block(dst, dst_line, width, by, bx, block);
+        }
+    }
+}
+
+/// Transposes a square frame of `size` x `size` pixels in place, see [transpose_into]
+fn transpose_in_place(buf: &mut [u8], size: u32) {
+    let line = line_bytes(size, 1);
+    for by in 0..line {
+        for bx in by..line {
+            let upper = transpose_block(gather_block(buf, line, size, bx, by));
+            let lower = transpose_block(gather_block(buf, line, size, by, bx));
+            scatter_block(buf, line, size, by, bx, upper);
+            scatter_block(buf, line, size, bx, by, lower);
+        }
+    }
+}
+
+/// The bytes of column `bx` of the rows `8 * by..8 * by + 8`, zeros below the frame
+fn gather_block(buf: &[u8], line: usize, height: u32, bx: usize, by: usize) -> [u8; 8] {
+    let mut block = [0; 8];
+    for (i, byte) in block.iter_mut().enumerate() {
+        let y = by * 8 + i;
+        if y < height as usize {
+            *byte = buf[y * line + bx];
+        }
+    }
+    block
+}
+
+/// Inverse of [gather_block], rows below the frame are dropped
+fn scatter_block(buf: &mut [u8], line: usize, height: u32, bx: usize, by: usize, block: [u8; 8]) {
+    for (i, byte) in block.into_iter().enumerate() {
+        let y = by * 8 + i;
+        if y < height as usize {
+            buf[y * line + bx] = byte;
+        }
+    }
+}
+
+/// Transposes 8x8 pixels, the first row in the first byte and the first column in the
+/// highest bit (Hacker's Delight, 7-3)
+fn transpose_block(block: [u8; 8]) -> [u8; 8] {
+    let mut x = u64::from_be_bytes(block);
+    let t = (x ^ (x >> 7)) & 0x00AA_00AA_00AA_00AA;
+    x ^= t ^ (t << 7);
+    let t = (x ^ (x >> 14)) & 0x0000_CCCC_0000_CCCC;
+    x ^= t ^ (t << 14);
+    let t = (x ^ (x >> 28)) & 0x0000_0000_F0F0_F0F0;
+    x ^= t ^ (t << 28);
+    x.to_be_bytes()
+}
+
+// Shared by the `transform_buffer` of the b/w and tricolor displays
+//
+// Applies `transform` to each of the `planes` of `buffer`, returns `false` without
+// touching it if the frame can't be rotated in place.
+fn transform_planes(
+    buffer: &mut [u8],
+    planes: usize,
+    width: u32,
+    height: u32,
+    transform: BufferTransform,
+) -> bool {
+    let rotates = matches!(
+        transform,
+        BufferTransform::Rotate90 | BufferTransform::Rotate270
+    );
+    if rotates && width != height {
+        return false;
+    }
+    let len = crate::buffer_len(width as usize, height as usize);
+    for plane in buffer[..planes * len].chunks_exact_mut(len) {
+        match transform {
+            BufferTransform::Rotate90 => {
+                transpose_in_place(plane, width);
+                flip_horizontal_in_place(plane, width, height);
+            }
+            BufferTransform::Rotate180 => rotate180_in_place(plane, width, height),
+            BufferTransform::Rotate270 => {
+                transpose_in_place(plane, width);
+                flip_vertical_in_place(plane, width, height);
+            }
+            BufferTransform::FlipHorizontal => flip_horizontal_in_place(plane, width, height),
+            BufferTransform::FlipVertical => flip_vertical_in_place(plane, width, height),
+        }
+    }
+    true
+}
+
+/// Order of the pixels in an image array of Image2Lcd or the GxEPD tools
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Image2LcdScan {
+    /// Row by row from the top, a byte holds 8 pixels next to each other and every
+    /// row is padded to whole bytes, like the buffers of the displays
+    #[default]
+    Horizontal,
+    /// Column by column from the left, a byte holds 8 pixels on top of each other and
+    /// every column is padded to whole bytes
+    Vertical,
+}
+
+/// Layout of a 1 bit per pixel image array exported by Image2Lcd or the GxEPD tools,
+/// see [Display::from_image2lcd]
+///
+/// The default is the layout of the displays: horizontal scan, the first pixel in
+/// the most significant bit and a set bit for white. Image2Lcd calls a set bit for
+/// black "color reverse", GxEPD bitmaps usually have it as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Image2LcdFormat {
+    /// Order of the pixels
+    pub scan: Image2LcdScan,
+    /// The first pixel of a byte is in its least significant bit
+    pub lsb_first: bool,
+    /// A set bit is black instead of white
+    pub inverted: bool,
+}
+
+impl Image2LcdFormat {
+    /// Bytes of an image array of `width` x `height` pixels in this layout
+    pub const fn len(self, width: u32, height: u32) -> usize {
+        match self.scan {
+            Image2LcdScan::Horizontal => crate::buffer_len(width as usize, height as usize),
+            Image2LcdScan::Vertical => crate::buffer_len(height as usize, width as usize),
+        }
+    }
+
+    /// Byte and bit mask of the pixel at `x`, `y`
+    fn locate(self, width: u32, height: u32, x: u32, y: u32) -> (usize, u8) {
+        let (index, bit) = match self.scan {
+            Image2LcdScan::Horizontal => {
+                (y as usize * line_bytes(width, 1) + x as usize / 8, x % 8)
+            }
+            Image2LcdScan::Vertical => (x as usize * line_bytes(height, 1) + y as usize / 8, y % 8),
+        };
+        let mask = if self.lsb_first {
+            1 << bit
+        } else {
+            0x80 >> bit
+        };
+        (index, mask)
+    }
+}
+
+/// Panics if an image array of `len` bytes doesn't have the size of a `width` x
+/// `height` image in `format`, which shears the picture
+///
+/// Meant for a compile time check of a pasted array:
+///
+///```rust
+/// use epd_waveshare::graphics::{assert_image2lcd_len, Image2LcdFormat, Image2LcdScan};
+///
+/// const FORMAT: Image2LcdFormat = Image2LcdFormat {
+///     scan: Image2LcdScan::Vertical,
+///     lsb_first: false,
+///     inverted: true,
+/// };
+/// const LOGO: [u8; 2 * 12] = [0x00; 2 * 12];
+/// const _: () = assert_image2lcd_len(12, 16, FORMAT, LOGO.len());
+///```
+pub const fn assert_image2lcd_len(width: u32, height: u32, format: Image2LcdFormat, len: usize) {
+    if format.len(width, height) != len {
+        panic!("assert_image2lcd_len: the array doesn't have the length of width x height");
+    }
+}
+
+// Shared by the `from_image2lcd` of the b/w displays
+//
+// Writes the pixels of `data` into the frame `buffer`, the padding of its rows is
+// kept. Returns `false` without touching it if `data` has the wrong length.
+fn image2lcd_into_buffer(
+    data: &[u8],
+    format: Image2LcdFormat,
+    width: u32,
+    height: u32,
+    buffer: &mut [u8],
+) -> bool {
+    if data.len() != format.len(width, height) {
+        return false;
+    }
+    let line = line_bytes(width, 1);
+    for y in 0..height {
+        for x in 0..width {
+            let (index, mask) = format.locate(width, height, x, y);
+            let white = (data[index] & mask != 0) != format.inverted;
+            let byte = &mut buffer[y as usize * line + x as usize / 8];
+            let bit = 0x80 >> (x % 8);
+            if white {
+                *byte |= bit;
+            } else {
+                *byte &= !bit;
+            }
+        }
+    }
+    true
+}
+
+// Shared by the `to_image2lcd` of the b/w displays
+//
+// The reverse of `image2lcd_into_buffer`, the padding bits of `out` are cleared.
+fn buffer_into_image2lcd(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    format: Image2LcdFormat,
+    out: &mut [u8],
+) -> bool {
+    let len = format.len(width, height);
+    if out.len() < len {
+        return false;
+    }
+    out[..len].fill(0);
+    let line = line_bytes(width, 1);
+    for y in 0..height {
+        for x in 0..width {
+            let white = buffer[y as usize * line + x as usize / 8] & (0x80 >> (x % 8)) != 0;
+            if white != format.inverted {
+                let (index, mask) = format.locate(width, height, x, y);
+                out[index] |= mask;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::*;
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        // example definition taken from epd1in54
+        let display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        assert_eq!(display.buffer().len(), 5000);
+    }
+
+    // driver side consts, checked against the runtime values
+    #[cfg(all(feature = "epd2in13bc", feature = "epd4in2"))]
+    fn driver_sizes<SPI, BUSY, DC, RST, DELAY, EPD>(epd: &EPD) -> (u32, u32, usize)
+    where
+        SPI: embedded_hal::spi::SpiDevice,
+        BUSY: embedded_hal::digital::InputPin,
+        DC: embedded_hal::digital::OutputPin,
+        RST: embedded_hal::digital::OutputPin,
+        DELAY: embedded_hal::delay::DelayNs,
+        EPD: crate::traits::WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        assert_eq!(EPD::WIDTH, epd.width());
+        assert_eq!(EPD::HEIGHT, epd.height());
+        (EPD::WIDTH, EPD::HEIGHT, EPD::BUFFER_LEN)
+    }
+
+    #[test]
+    #[cfg(all(feature = "epd2in13bc", feature = "epd4in2"))]
+    fn graphics_const_sizes() {
+        use crate::epd2in13bc::{Display2in13bc, Epd2in13bc};
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Bus, BusyPin, Delay, Pin};
+
+        static BW: Display4in2 = Display4in2::new();
+        static TRI: Display2in13bc = Display2in13bc::new();
+
+        assert_eq!(BW.buffer().len(), Display4in2::BUFFER_LEN);
+        assert_eq!(
+            BW.size(),
+            Size::new(Display4in2::WIDTH, Display4in2::HEIGHT)
+        );
+        assert_eq!(TRI.buffer().len(), Display2in13bc::BUFFER_LEN);
+        assert_eq!(
+            TRI.size(),
+            Size::new(Display2in13bc::WIDTH, Display2in13bc::HEIGHT)
+        );
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let (width, height, len) = driver_sizes(&epd);
+        assert_eq!(BW.size(), Size::new(width, height));
+        assert_eq!(BW.buffer().len(), len);
+
+        let epd = Epd2in13bc::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let (width, height, len) = driver_sizes(&epd);
+        assert_eq!(TRI.size(), Size::new(width, height));
+        assert_eq!(TRI.buffer().len(), len);
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        for &byte in display.buffer() {
+            assert_eq!(byte, 0);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_0() {
+        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(
+            buffer[0],
+            Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite)
+        );
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, 0);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_90() {
+        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        let _ = Line::new(Point::new(0, 192), Point::new(0, 199))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(
+            buffer[0],
+            Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite)
+        );
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, 0);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_180() {
+        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate180);
+        let _ = Line::new(Point::new(192, 199), Point::new(199, 199))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        extern crate std;
+        std::println!("{:?}", buffer);
+
+        assert_eq!(
+            buffer[0],
+            Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite)
+        );
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, 0);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_270() {
+        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate270);
+        let _ = Line::new(Point::new(199, 0), Point::new(199, 7))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        extern crate std;
+        std::println!("{:?}", buffer);
+
+        assert_eq!(
+            buffer[0],
+            Color::Black.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite)
+        );
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, 0);
+        }
+    }
+
+    // the rotated top left and bottom right corners end up at the native corners, also
+    // for widths which aren't a multiple of 8, without touching the padding bits
+    fn check_corners<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize>() {
+        let (right, bottom) = (WIDTH - 1, HEIGHT - 1);
+        let expected = [
+            [(0, 0), (right, bottom)],
+            [(right, 0), (0, bottom)],
+            [(right, bottom), (0, 0)],
+            [(0, bottom), (right, 0)],
+        ];
+        for (rotation, corners) in ROTATIONS.into_iter().zip(expected) {
+            let mut display = Display::<WIDTH, HEIGHT, false, BYTECOUNT, Color>::default();
+            display.set_rotation(rotation);
+            let far = display.bounding_box().bottom_right().unwrap();
+            assert!(display.set_pixel(Pixel(Point::zero(), Color::White)));
+            assert!(display.set_pixel(Pixel(far, Color::White)));
+
+            let stride = line_bytes(WIDTH, 1) as u32;
+            let buffer = display.buffer();
+            for (x, y) in corners {
+                assert_ne!(buffer[(y * stride + x / 8) as usize] & 0x80 >> (x % 8), 0);
+            }
+            assert_eq!(buffer.iter().map(|b| b.count_ones()).sum::<u32>(), 2);
+
+            // a full fill sets exactly the pixels, not the padding
+            display
+                .fill_solid(&display.bounding_box(), Color::White)
+                .unwrap();
+            let pixels = display.buffer().iter().map(|b| b.count_ones()).sum::<u32>();
+            assert_eq!(pixels, WIDTH * HEIGHT);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_corners() {
+        // 2.13" with 122 pixels in 16 bytes per line
+        check_corners::<122, 250, { 16 * 250 }>();
+        // 1.54" (C) with 152 pixels in 19 bytes per line
+        check_corners::<152, 152, { 19 * 152 }>();
+        check_corners::<200, 200, { 200 * 200 / 8 }>();
+        check_corners::<128, 296, { 128 * 296 / 8 }>();
+    }
+
+    // per pixel reference for the bitmap blit
+    fn reference_blit(
+        display: &mut Display<200, 200, false, { 200 * 200 / 8 }, Color>,
+        src: &RawBitImage,
+        top_left: Point,
+        scale: u32,
+    ) {
+        for y in 0..src.height() * scale {
+            for x in 0..src.width() * scale {
+                let color = if src.bit(x / scale, y / scale) {
+                    Color::Black
+                } else {
+                    Color::White
+                };
+                display.set_pixel(Pixel(top_left + Point::new(x as i32, y as i32), color));
+            }
+        }
+    }
+
+    const PATTERN: [u8; 10] = [
+        0b1010_0110,
+        0b1000_0000,
+        0b0111_0001,
+        0b0100_0000,
+        0b1100_1100,
+        0b1100_0000,
+        0b0000_0000,
+        0b0000_0000,
+        0b1111_1111,
+        0b1100_0000,
+    ];
+
+    #[test]
+    fn scaled_bitmap_matches_reference() {
+        let src = RawBitImage::new(&PATTERN, 10);
+        assert_eq!(src.height(), 5);
+
+        for scale in 1..=8u8 {
+            // the last position clips at the right and bottom edge
+            for top_left in [
+                Point::new(0, 0),
+                Point::new(13, 7),
+                Point::new(-5, -3),
+                Point::new(190, 185),
+            ] {
+                let mut fast = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+                let mut expected = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+                fast.draw_scaled_bitmap(&src, top_left, scale, Color::Black, Color::White);
+                reference_blit(&mut expected, &src, top_left, scale as u32);
+                assert_eq!(
+                    fast.buffer(),
+                    expected.buffer(),
+                    "scale {} at {:?}",
+                    scale,
+                    top_left
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scaled_bitmap_rotated() {
+        let src = RawBitImage::new(&PATTERN, 10);
+        let mut fast = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        let mut expected = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
+        fast.set_rotation(DisplayRotation::Rotate90);
+        expected.set_rotation(DisplayRotation::Rotate90);
+        fast.draw_scaled_bitmap(&src, Point::new(180, 3), 3, Color::Black, Color::White);
+        reference_blit(&mut expected, &src, Point::new(180, 3), 3);
+        assert_eq!(fast.buffer(), expected.buffer());
+    }
+
+    const ROTATIONS: [DisplayRotation; 4] = [
+        DisplayRotation::Rotate0,
+        DisplayRotation::Rotate90,
+        DisplayRotation::Rotate180,
+        DisplayRotation::Rotate270,
+    ];
+
+    // small xorshift, good enough for test rectangles
+    fn next(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    // compares the byte wise fills against drawing every single pixel, on a buffer
+    // with random content to catch wrong edge masks
+    fn check_fill_solid<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + PixelColor + core::fmt::Debug,
+    >(
+        colors: &[COLOR],
+    ) {
+        let mut state = 0x1234_5678;
+        for rotation in ROTATIONS {
+            for _ in 0..200 {
+                let mut fast = Display::<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>::new();
+                for byte in fast.buffer.iter_mut() {
+                    *byte = next(&mut state) as u8;
+                }
+                let mut expected = Display::<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>::new();
+                expected.buffer = fast.buffer;
+                fast.set_rotation(rotation);
+                expected.set_rotation(rotation);
+
+                let size = fast.size();
+                let area = Rectangle::new(
+                    Point::new(
+                        (next(&mut state) % (size.width + 20)) as i32 - 10,
+                        (next(&mut state) % (size.height + 20)) as i32 - 10,
+                    ),
+                    Size::new(
+                        next(&mut state) % (size.width + 10),
+                        next(&mut state) % (size.height + 10),
+                    ),
+                );
+                let color = colors[next(&mut state) as usize % colors.len()];
+
+                fast.fill_solid(&area, color).unwrap();
+                expected
+                    .draw_iter(area.points().map(|point| Pixel(point, color)))
+                    .unwrap();
+                assert_eq!(fast.buffer(), expected.buffer(), "{:?} {:?}", area, color);
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rect_native_matches_pixels() {
+        let tri = [TriColor::Black, TriColor::White, TriColor::Chromatic];
+        // every start offset within a byte, the rotation is ignored
+        for x in 0..8 {
+            for width in 0..20 {
+                for (i, &color) in tri.iter().enumerate() {
+                    let mut fast = Display::<45, 30, false, { 6 * 30 * 2 }, TriColor>::new();
+                    fast.buffer.iter_mut().enumerate().for_each(|(j, byte)| {
+                        *byte = (j * 37 + i) as u8;
+                    });
+                    let mut expected = Display::<45, 30, false, { 6 * 30 * 2 }, TriColor>::new();
+                    expected.buffer = fast.buffer;
+                    fast.set_rotation(DisplayRotation::Rotate90);
+
+                    let rect = Rectangle::new(Point::new(x + 8, 3), Size::new(width, 4));
+                    fast.fill_rect_native(rect, color);
+                    expected
+                        .draw_iter(rect.points().map(|point| Pixel(point, color)))
+                        .unwrap();
+                    assert_eq!(fast.buffer(), expected.buffer(), "{:?} {:?}", rect, color);
+                }
+            }
+        }
+
+        // clipped at the edges
+        let mut fast = Display::<45, 30, false, { 6 * 30 }, Color>::new();
+        let mut expected = Display::<45, 30, false, { 6 * 30 }, Color>::new();
+        let rect = Rectangle::new(Point::new(-5, 25), Size::new(60, 10));
+        fast.fill_rect_native(rect, Color::White);
+        expected
+            .draw_iter(rect.points().map(|point| Pixel(point, Color::White)))
+            .unwrap();
+        assert_eq!(fast.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn fill_rect_native_writes_whole_bytes() {
+        // a 200x12 progress bar from x = 3 spans bytes 0..=25 of each line, per pixel
+        // drawing would write these 26 bytes 200 times instead of once per line
+        let mut display = Display::<400, 20, false, { 50 * 20 }, Color>::new();
+        display.fill_rect_native(
+            Rectangle::new(Point::new(3, 4), Size::new(200, 12)),
+            Color::White,
+        );
+        let mut touched = display
+            .buffer()
+            .iter()
+            .enumerate()
+            .filter(|(_, &byte)| byte != 0);
+        assert_eq!(touched.clone().count(), 12 * 26);
+        assert!(touched.all(|(i, _)| (4..16).contains(&(i / 50)) && i % 50 <= 25));
+        // only the edge bytes are partial
+        assert_eq!(display.buffer()[4 * 50], 0x1F);
+        assert_eq!(display.buffer()[4 * 50 + 1], 0xFF);
+        assert_eq!(display.buffer()[4 * 50 + 25], 0xE0);
+    }
+
+    #[test]
+    fn fill_solid_matches_pixels() {
+        check_fill_solid::<45, 30, false, { 6 * 30 }, Color>(&[Color::Black, Color::White]);
+        let tri = [TriColor::Black, TriColor::White, TriColor::Chromatic];
+        check_fill_solid::<45, 30, false, { 6 * 30 * 2 }, TriColor>(&tri);
+        check_fill_solid::<45, 30, true, { 6 * 30 * 2 }, TriColor>(&tri);
+        check_fill_solid::<45, 30, false, { 23 * 30 }, OctColor>(&[
+            OctColor::Black,
+            OctColor::Green,
+            OctColor::HiZ,
+        ]);
+        check_fill_solid::<45, 30, false, { 12 * 30 }, QuadColor>(&[
+            QuadColor::Black,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ]);
+    }
+
+    #[test]
+    fn clear_matches_pixels() {
+        fn check<const BWRBIT: bool, COLOR: ColorType + PixelColor>(color: COLOR) {
+            let mut fast = Display::<48, 16, BWRBIT, { 48 * 16 / 4 }, COLOR>::new();
+            let mut expected = Display::<48, 16, BWRBIT, { 48 * 16 / 4 }, COLOR>::new();
+            fast.buffer = [0x5A; 48 * 16 / 4];
+            expected.buffer = fast.buffer;
+            fast.clear(color).unwrap();
+            let area = expected.bounding_box();
+            expected
+                .draw_iter(area.points().map(|point| Pixel(point, color)))
+                .unwrap();
+            assert_eq!(fast.buffer(), expected.buffer());
+        }
+        check::<false, _>(QuadColor::Yellow);
+        check::<false, _>(TriColor::Chromatic);
+        check::<true, _>(TriColor::Chromatic);
+        check::<false, _>(TriColor::White);
+
+        let mut buffer = [0u8; 6 * 30 * 2];
+        let mut display = VarDisplay::<TriColor>::new(45, 30, &mut buffer, false).unwrap();
+        display.clear(TriColor::Chromatic).unwrap();
+        assert!(display.bw_buffer().iter().all(|&byte| byte == 0xFF));
+        assert!(display.chromatic_buffer().iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn ordered_dither_ramp() {
+        let image: [u8; 16 * 4] = core::array::from_fn(|i| (i % 16 * 17) as u8);
+        let mut display = Display::<16, 4, false, { 2 * 4 }, Color>::new();
+        display.draw_gray_image_dithered(&image, 16, 4, Point::zero(), Dither::Ordered(Bayer::X4));
+        assert_eq!(
+            display.buffer(),
+            &[0x0A, 0xFF, 0x05, 0x5F, 0x2A, 0xBF, 0x01, 0x57]
+        );
+
+        let image: [u8; 32 * 8] = core::array::from_fn(|i| (i % 32 * 255 / 31) as u8);
+        let mut display = Display::<32, 8, false, { 4 * 8 }, Color>::new();
+        display.draw_gray_image_dithered(&image, 32, 8, Point::zero(), Dither::Ordered(Bayer::X8));
+        #[rustfmt::skip]
+        assert_eq!(
+            display.buffer(),
+            &[
+                0x0A, 0xAA, 0xEF, 0xFF, 0x00, 0x45, 0x55, 0xDF, 0x02, 0xAA, 0xBB, 0xFF, 0x00, 0x11, 0x55, 0x77,
+                0x0A, 0xAA, 0xAF, 0xFF, 0x00, 0x05, 0x55, 0x5F, 0x02, 0xAA, 0xAB, 0xFF, 0x00, 0x01, 0x55, 0x57,
+            ]
+        );
+
+        // tricolor displays get the same black and white pixels
+        let image: [u8; 16 * 4] = core::array::from_fn(|i| (i % 16 * 17) as u8);
+        let mut display = Display::<16, 4, true, { 2 * 4 * 2 }, TriColor>::new();
+        display.clear(TriColor::Chromatic).unwrap();
+        display.draw_gray_image_dithered(&image, 16, 4, Point::zero(), Dither::Ordered(Bayer::X4));
+        assert_eq!(
+            display.bw_buffer(),
+            &[0x0A, 0xFF, 0x05, 0x5F, 0x2A, 0xBF, 0x01, 0x57]
+        );
+        assert!(display.chromatic_buffer().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn floyd_steinberg_dither() {
+        let mut errors = [0i16; 16];
+        let mut display = Display::<16, 16, false, { 2 * 16 }, Color>::new();
+
+        // mid gray ends up as half of the pixels
+        display.draw_gray_image_dithered(
+            &[128; 16 * 16],
+            16,
+            16,
+            Point::zero(),
+            Dither::FloydSteinberg(&mut errors),
+        );
+        let white: u32 = display.buffer().iter().map(|byte| byte.count_ones()).sum();
+        assert!((120..=136).contains(&white), "{} white pixels", white);
+
+        // pure colors stay pure, even with leftovers in the error buffer
+        errors.fill(1000);
+        display.draw_gray_image_dithered(
+            &[255; 16 * 16],
+            16,
+            16,
+            Point::zero(),
+            Dither::FloydSteinberg(&mut errors),
+        );
+        assert!(display.buffer().iter().all(|&byte| byte == 0xFF));
+        display.draw_gray_image_dithered(
+            &[0; 16 * 16],
+            16,
+            16,
+            Point::zero(),
+            Dither::FloydSteinberg(&mut errors),
+        );
+        assert!(display.buffer().iter().all(|&byte| byte == 0x00));
+    }
+
+    #[test]
+    fn dither_rotated_and_clipped() {
+        let image: [u8; 16 * 4] = core::array::from_fn(|i| (i % 16 * 17) as u8);
+        let mut reference = Display::<16, 4, false, { 2 * 4 }, Color>::new();
+        reference.draw_gray_image_dithered(
+            &image,
+            16,
+            4,
+            Point::zero(),
+            Dither::Ordered(Bayer::X4),
+        );
+
+        for rotation in ROTATIONS {
+            let top_left = Point::new(-3, 5);
+            let mut display = Display::<8, 16, false, 16, Color>::new();
+            let mut expected = Display::<8, 16, false, 16, Color>::new();
+            display.set_rotation(rotation);
+            expected.set_rotation(rotation);
+            display.draw_gray_image_dithered(&image, 16, 4, top_left, Dither::Ordered(Bayer::X4));
+            let pixels = (0..4).flat_map(|y| (0..16).map(move |x| (x, y)));
+            let pixels = pixels.map(|(x, y)| {
+                let white = reference.buffer()[y * 2 + x / 8] & (0x80 >> (x % 8)) != 0;
+                let color = if white { Color::White } else { Color::Black };
+                Pixel(top_left + Point::new(x as i32, y as i32), color)
+            });
+            expected.draw_iter(pixels).unwrap();
+            assert_eq!(display.buffer(), expected.buffer());
+        }
+    }
+
+    // independent reference of the buffer layout of b/w and tricolor displays:
+    // rotation mapping, MSB first bits, bw plane followed by the chromatic plane
+    fn reference_set(
+        buffer: &mut [u8],
+        (width, height): (u32, u32),
+        rotation: DisplayRotation,
+        bwrbit: bool,
+        point: Point,
+        color: TriColor,
+    ) {
+        let (x, y) = match rotation {
+            DisplayRotation::Rotate0 => (point.x, point.y),
+            DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
+            DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
+            DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
+        };
+        if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+            return;
+        }
+        let plane = buffer.len() / 2;
+        let index = y as usize * width.div_ceil(8) as usize + x as usize / 8;
+        let bit = 0x80 >> (x % 8);
+        let (bw, chromatic) = match color {
+            TriColor::Black => (false, false),
+            TriColor::White => (true, false),
+            TriColor::Chromatic => (!bwrbit, true),
+        };
+        for (index, set) in [(index, bw), (index + plane, chromatic)] {
+            if set {
+                buffer[index] |= bit;
+            } else {
+                buffer[index] &= !bit;
+            }
+        }
+    }
+
+    // the generic color encoding has to stay bit identical to the buffer layout the
+    // drivers expect
+    #[test]
+    fn color_encoding_matches_reference() {
+        let mut state = 0x0bad_cafe;
+        for rotation in ROTATIONS {
+            for bwrbit in [false, true] {
+                let mut buffer = [0u8; 3 * 20 * 2];
+                let mut tri = VarDisplay::<TriColor>::new(21, 20, &mut buffer, bwrbit).unwrap();
+                let mut bw = Display::<21, 20, false, { 3 * 20 }, Color>::new();
+                let mut expected_tri = [0u8; 3 * 20 * 2];
+                let mut expected_bw = [0u8; 3 * 20 * 2];
+                tri.set_rotation(rotation);
+                bw.set_rotation(rotation);
+
+                for _ in 0..500 {
+                    let point = Point::new(
+                        (next(&mut state) % 26) as i32 - 2,
+                        (next(&mut state) % 26) as i32 - 2,
+                    );
+                    let color = [TriColor::Black, TriColor::White, TriColor::Chromatic]
+                        [next(&mut state) as usize % 3];
+                    tri.set_pixel(Pixel(point, color));
+                    reference_set(&mut expected_tri, (21, 20), rotation, bwrbit, point, color);
+
+                    let color = if color == TriColor::White {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    bw.set_pixel(Pixel(point, color));
+                    let color = if color == Color::White {
+                        TriColor::White
+                    } else {
+                        TriColor::Black
+                    };
+                    reference_set(&mut expected_bw, (21, 20), rotation, false, point, color);
+                }
+                assert_eq!(tri.buffer(), &expected_tri[..]);
+                assert_eq!(bw.buffer(), &expected_bw[..3 * 20]);
+            }
+        }
+    }
+
+    #[test]
+    fn double_buffered_feeds_quick_refresh() {
+        use crate::mock::{Bus, BusyPin, Delay, Pin, Spi};
+        use embedded_hal::spi::ErrorKind;
+        extern crate std;
+        use std::vec::Vec;
+
+        #[derive(Default)]
+        struct Recorder(Vec<(&'static str, Vec<u8>)>);
+
+        impl QuickRefresh<Spi, BusyPin, Pin, Pin, Delay> for Recorder {
+            fn update_old_frame(
+                &mut self,
+                _: &mut Spi,
+                buffer: &[u8],
+                _: &mut Delay,
+            ) -> Result<(), Error<ErrorKind>> {
+                self.0.push(("old", buffer.to_vec()));
+                Ok(())
+            }
+
+            fn update_new_frame(
+                &mut self,
+                _: &mut Spi,
+                buffer: &[u8],
+                _: &mut Delay,
+            ) -> Result<(), Error<ErrorKind>> {
+                self.0.push(("new", buffer.to_vec()));
+                Ok(())
+            }
+
+            fn display_new_frame(
+                &mut self,
+                _: &mut Spi,
+                _: &mut Delay,
+            ) -> Result<(), Error<ErrorKind>> {
+                self.0.push(("display", Vec::new()));
+                Ok(())
+            }
+
+            fn update_and_display_new_frame(
+                &mut self,
+                _: &mut Spi,
+                _: &[u8],
+                _: &mut Delay,
+            ) -> Result<(), Error<ErrorKind>> {
+                unimplemented!()
+            }
+
+            fn update_partial_old_frame(
+                &mut self,
+                _: &mut Spi,
+                _: &mut Delay,
+                _: &[u8],
+                _: u32,
+                _: u32,
+                _: u32,
+                _: u32,
+            ) -> Result<(), Error<ErrorKind>> {
+                unimplemented!()
+            }
+
+            fn update_partial_new_frame(
+                &mut self,
+                _: &mut Spi,
+                _: &mut Delay,
+                _: &[u8],
+                _: u32,
+                _: u32,
+                _: u32,
+                _: u32,
+            ) -> Result<(), Error<ErrorKind>> {
+                unimplemented!()
+            }
+
+            fn clear_partial_frame(
+                &mut self,
+                _: &mut Spi,
+                _: &mut Delay,
+                _: u32,
+                _: u32,
+                _: u32,
+                _: u32,
+            ) -> Result<(), Error<ErrorKind>> {
+                unimplemented!()
+            }
+        }
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Recorder::default();
+        let mut frames = DoubleBuffered::<16, 2, false, 4, Color>::new();
+
+        frames.back().clear(Color::White).unwrap();
+        frames
+            .swap_and_flush_quick(&mut epd, &mut spi, &mut delay)
+            .unwrap();
+        frames
+            .back()
+            .set_pixel(Pixel(Point::new(0, 0), Color::White));
+        frames
+            .swap_and_flush_quick(&mut epd, &mut spi, &mut delay)
+            .unwrap();
+
+        assert_eq!(
+            epd.0,
+            [
+                ("old", std::vec![0x00; 4]),
+                ("new", std::vec![0xFF; 4]),
+                ("display", Vec::new()),
+                ("old", std::vec![0xFF; 4]),
+                ("new", std::vec![0x80, 0x00, 0x00, 0x00]),
+                ("display", Vec::new()),
+            ]
+        );
+        assert_eq!(frames.front().buffer(), [0x80, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    #[cfg(feature = "epd4in2")]
+    fn double_buffered_flush_sends_front() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Bus, Delay, Pin};
+        extern crate std;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut frames = std::boxed::Box::new(DoubleBuffered::<
+            400,
+            300,
+            false,
+            { Display4in2::BUFFER_LEN },
+            Color,
+        >::new());
+
+        frames.back().clear(Color::White).unwrap();
+        frames.swap();
+        bus.clear();
+        frames.flush(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x13).unwrap(), frames.front().buffer());
+    }
+
+    #[test]
+    fn layout_follows_rotation() {
+        // an asymmetric panel with asymmetric content, so any mixed up axis shows
+        let mut display = Display::<296, 128, false, { 37 * 128 }, Color>::default();
+        let content = Size::new(100, 20);
+        for rotation in ROTATIONS {
+            display.set_rotation(rotation);
+            let size = match rotation {
+                DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(296, 128),
+                DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(128, 296),
+            };
+            assert_eq!(display.drawable_size(), size);
+            let free = size - content;
+            assert_eq!(
+                display.centered_origin(content),
+                Point::new(free.width as i32 / 2, free.height as i32 / 2)
+            );
+            assert_eq!(
+                display.aligned_origin(content, HAlign::Left, VAlign::Top),
+                Point::zero()
+            );
+            assert_eq!(
+                display.aligned_origin(content, HAlign::Right, VAlign::Bottom),
+                Point::new(free.width as i32, free.height as i32)
+            );
+            // too large content is clamped to the top left corner in that dimension
+            let wide = Size::new(400, 20);
+            assert_eq!(
+                display.centered_origin(wide),
+                Point::new(0, (size.height - 20) as i32 / 2)
+            );
+
+            // the content ends up centered on the panel as well
+            display.clear(Color::White).unwrap();
+            Rectangle::new(display.centered_origin(content), content)
+                .into_styled(PrimitiveStyle::with_fill(Color::Black))
+                .draw(&mut display)
+                .unwrap();
+            let black = |x: u32, y: u32| {
+                display.buffer()[(y * 37 + x / 8) as usize] & (0x80 >> (x % 8)) == 0
+            };
+            let column = |x: u32| (0..128).any(|y| black(x, y));
+            let row = |y: u32| (0..296).any(|x| black(x, y));
+            let left = (0..296).find(|&x| column(x)).unwrap();
+            let right = 295 - (0..296).rev().find(|&x| column(x)).unwrap();
+            let top = (0..128).find(|&y| row(y)).unwrap();
+            let bottom = 127 - (0..128).rev().find(|&y| row(y)).unwrap();
+            assert!(left.abs_diff(right) <= 1 && top.abs_diff(bottom) <= 1);
+        }
+
+        let mut buffer = [0u8; 37 * 128];
+        let mut display = VarDisplay::<Color>::new(296, 128, &mut buffer, false).unwrap();
+        display.set_rotation(DisplayRotation::Rotate270);
+        assert_eq!(display.centered_origin(content), Point::new(14, 138));
+    }
+
+    #[test]
+    fn owned_var_display_checks_size() {
+        assert!(OwnedVarDisplay::<16, Color>::new(16, 8, false).is_ok());
+        assert!(matches!(
+            OwnedVarDisplay::<15, Color>::new(16, 8, false),
+            Err(VarDisplayError::BufferTooSmall)
+        ));
+        // two planes
+        assert!(OwnedVarDisplay::<16, TriColor>::new(16, 8, false).is_err());
+        let display = OwnedVarDisplay::<40, TriColor>::new(16, 8, false).unwrap();
+        assert_eq!(display.buffer().len(), 32);
+        assert_eq!(display.bw_buffer().len(), 16);
+        assert_eq!(display.chromatic_buffer().len(), 16);
+
+        const STATIC: Result<OwnedVarDisplay<16, Color>, VarDisplayError> =
+            OwnedVarDisplay::new(16, 8, false);
+        assert!(STATIC.is_ok());
+    }
+
+    #[test]
+    fn var_display_shares_the_const_check() {
+        let mut buffer = [0u8; 12 * 32];
+        crate::assert_buffer_fits(96, 32, buffer.len());
+        assert!(VarDisplay::<Color>::new(96, 32, &mut buffer, false).is_ok());
+        let height = crate::max_buffer_height(96, buffer.len()) as u32;
+        assert!(VarDisplay::<Color>::new(96, height + 1, &mut buffer, false).is_err());
+
+        // an absurd size is refused instead of overflowing
+        assert!(VarDisplay::<OctColor>::new(u32::MAX, u32::MAX, &mut buffer, false).is_err());
+        assert!(OwnedVarDisplay::<16, OctColor>::new(u32::MAX, u32::MAX, false).is_err());
+    }
+
+    #[test]
+    fn owned_var_display_draws_like_var_display() {
+        let line = Line::new(Point::new(1, 2), Point::new(6, 14))
+            .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1));
+        let area = Rectangle::new(Point::new(3, 0), Size::new(4, 5));
+
+        let mut owned = OwnedVarDisplay::<40, TriColor>::new(16, 8, true).unwrap();
+        owned.set_rotation(DisplayRotation::Rotate270);
+        owned.clear(TriColor::White).unwrap();
+        line.draw(&mut owned).unwrap();
+        owned.fill_solid(&area, TriColor::Black).unwrap();
+
+        let mut buffer = [0u8; 32];
+        let mut borrowed = VarDisplay::<TriColor>::new(16, 8, &mut buffer, true).unwrap();
+        borrowed.set_rotation(DisplayRotation::Rotate270);
+        borrowed.clear(TriColor::White).unwrap();
+        line.draw(&mut borrowed).unwrap();
+        borrowed.fill_solid(&area, TriColor::Black).unwrap();
+
+        assert_eq!(owned.size(), borrowed.size());
+        assert_eq!(owned.buffer(), borrowed.buffer());
+    }
 
-    // final coordinates
-    let (x, y) = match rotation {
-        // as i32 = never use more than 2 billion pixel per line or per column
-        DisplayRotation::Rotate0 => (point.x, point.y),
-        DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
-        DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
-        DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
-    };
+    #[test]
+    fn rows_are_native() {
+        let mut display = Display::<16, 4, false, 8, Color>::default();
+        display.set_rotation(DisplayRotation::Rotate180);
+        assert_eq!(display.rows().count(), 4);
+        assert_eq!(Display::<16, 4, false, 8, Color>::row_offset(3), 6);
 
-    // Out of range check
-    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
-        // don't do anything in case of out of range
-        return;
+        let before: [_; 16] = core::array::from_fn(|x| display.get_pixel(x as u32, 1));
+        let row = display.rows_mut().nth(2).unwrap();
+        row.iter_mut().for_each(|byte| *byte = !*byte);
+        // native row 2 is row 1 upside down, all of it changed and nothing else
+        for x in 0..16 {
+            assert_ne!(display.get_pixel(x, 1), before[x as usize]);
+            assert_eq!(display.get_pixel(x, 0), display.get_pixel(x, 2));
+        }
+        assert_eq!(display.rows().nth(2), Some(&[0xFF, 0xFF][..]));
+
+        // the planes don't overlap, the rest of the buffer isn't used
+        let mut buffer = [0u8; 2 * 2 * 3 + 5];
+        let mut tri = VarDisplay::<TriColor>::new(16, 3, &mut buffer, false).unwrap();
+        assert_eq!(tri.row_offset(2), 4);
+        assert_eq!(tri.rows().count(), 6);
+        tri.chromatic_rows_mut().nth(1).unwrap().fill(0xFF);
+        tri.bw_rows_mut().nth(2).unwrap().fill(0x0F);
+        assert_eq!(tri.get_pixel(11, 1), Some(TriColor::Chromatic));
+        assert_eq!(tri.bw_buffer(), &[0, 0, 0, 0, 0x0F, 0x0F]);
+        assert_eq!(tri.chromatic_buffer(), &[0, 0, 0xFF, 0xFF, 0, 0]);
     }
 
-    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
-        + y as usize * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
-    let (mask, bits) = color.bitmask(bwrbit, x as u32);
+    #[test]
+    fn tri_color_to_bw_buffer() {
+        // bwrbit set, chromatic pixels have a black bit below them
+        let mut display = Display::<16, 2, true, 8, TriColor>::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::Black));
+        display.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(8, 0), Size::new(8, 2)),
+                TriColor::Chromatic,
+            )
+            .unwrap();
+        display.set_pixel(Pixel(Point::new(3, 1), TriColor::Black));
+        assert_eq!(display.bw_buffer()[0] & 0x40, 0);
 
-    if COLOR::BUFFER_COUNT == 2 {
-        // split buffer is for tricolor displays that use 2 buffer for 2 bits per pixel
-        buffer[index] = buffer[index] & mask | (bits & 0xFF) as u8;
-        let index = index + buffer.len() / 2;
-        buffer[index] = buffer[index] & mask | (bits >> 8) as u8;
-    } else {
-        buffer[index] = buffer[index] & mask | bits as u8;
+        for chromatic_as in [
+            ChromaticAs::Black,
+            ChromaticAs::White,
+            ChromaticAs::Dithered,
+        ] {
+            let mut out = [0x33; 5];
+            display.to_bw_buffer(&mut out, chromatic_as);
+            assert_eq!(out[4], 0x33);
+            let bw = VarDisplay::<Color>::new(16, 2, &mut out, false).unwrap();
+            for (x, y) in (0..16).flat_map(|x| (0..2).map(move |y| (x, y))) {
+                let expected = match display.get_pixel(x, y).unwrap() {
+                    TriColor::Black => Color::Black,
+                    TriColor::White => Color::White,
+                    TriColor::Chromatic => match chromatic_as {
+                        ChromaticAs::Black => Color::Black,
+                        ChromaticAs::White => Color::White,
+                        ChromaticAs::Dithered if (x + y) % 2 == 0 => Color::White,
+                        ChromaticAs::Dithered => Color::Black,
+                    },
+                };
+                assert_eq!(
+                    bw.get_pixel(x, y),
+                    Some(expected),
+                    "{chromatic_as:?} {x} {y}"
+                );
+            }
+        }
+
+        // a color converts to the plain mappings
+        let mut from_color = [0; 4];
+        display.to_bw_buffer(&mut from_color, Color::White);
+        let mut from_variant = [0; 4];
+        display.to_bw_buffer(&mut from_variant, ChromaticAs::White);
+        assert_eq!(from_color, from_variant);
+        assert_eq!(from_color, [0x7F, 0xFF, 0xEF, 0xFF]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::color::*;
-    use embedded_graphics::{
-        prelude::*,
-        primitives::{Line, PrimitiveStyle},
-    };
+    #[cfg(all(feature = "alloc", feature = "epd4in2", feature = "epd2in13bc"))]
+    fn draw_scene<D: DrawTarget>(
+        target: &mut D,
+        background: D::Color,
+        ink: D::Color,
+        fill: D::Color,
+    ) where
+        D::Error: core::fmt::Debug,
+    {
+        target.clear(background).unwrap();
+        Line::new(Point::new(3, 7), Point::new(90, 61))
+            .into_styled(PrimitiveStyle::with_stroke(ink, 3))
+            .draw(target)
+            .unwrap();
+        target
+            .fill_solid(&Rectangle::new(Point::new(11, 5), Size::new(21, 13)), fill)
+            .unwrap();
+    }
 
-    // test buffer length
+    #[cfg(all(feature = "alloc", feature = "epd4in2", feature = "epd2in13bc"))]
     #[test]
-    fn graphics_size() {
-        // example definition taken from epd1in54
-        let display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        assert_eq!(display.buffer().len(), 5000);
+    fn dyn_display_matches_static_display() {
+        use crate::epd2in13bc::{Display2in13bc, HEIGHT as BC_HEIGHT, WIDTH as BC_WIDTH};
+        use crate::epd4in2::{Display4in2, HEIGHT, WIDTH};
+
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            let mut fixed = Display4in2::default();
+            let mut dynamic = DynDisplay::<Color>::new(WIDTH, HEIGHT, false);
+            fixed.set_rotation(rotation);
+            dynamic.set_rotation(rotation);
+            draw_scene(&mut fixed, Color::White, Color::Black, Color::Black);
+            draw_scene(&mut dynamic, Color::White, Color::Black, Color::Black);
+            assert_eq!(fixed.size(), dynamic.size());
+            assert_eq!(fixed.buffer(), dynamic.buffer());
+            assert_eq!(fixed.get_pixel(12, 6), dynamic.get_pixel(12, 6));
+
+            let mut fixed = Display2in13bc::default();
+            let mut dynamic = DynDisplay::<TriColor>::new(BC_WIDTH, BC_HEIGHT, true);
+            fixed.set_rotation(rotation);
+            dynamic.set_rotation(rotation);
+            draw_scene(
+                &mut fixed,
+                TriColor::White,
+                TriColor::Chromatic,
+                TriColor::Black,
+            );
+            draw_scene(
+                &mut dynamic,
+                TriColor::White,
+                TriColor::Chromatic,
+                TriColor::Black,
+            );
+            assert_eq!(fixed.bw_buffer(), dynamic.bw_buffer());
+            assert_eq!(fixed.chromatic_buffer(), dynamic.chromatic_buffer());
+            assert_eq!(fixed.get_pixel(40, 30), dynamic.get_pixel(40, 30));
+        }
     }
 
-    // test default background color on all bytes
     #[test]
-    fn graphics_default() {
-        let display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        for &byte in display.buffer() {
-            assert_eq!(byte, 0);
+    fn var_display_config_keeps_rotation() {
+        let mut buffer = [0u8; 16];
+        let mut config = VarDisplayConfig::new(16, 8, false);
+        config.set_rotation(DisplayRotation::Rotate90);
+        config.set_anchor(Some(Point::new(8, 0)));
+
+        let mut display = config.attach::<Color>(&mut buffer).unwrap();
+        assert!(matches!(display.rotation(), DisplayRotation::Rotate90));
+        assert_eq!(display.size(), Size::new(8, 16));
+        display.set_pixel(Pixel(Point::new(0, 15), Color::White));
+        display.set_rotation(DisplayRotation::Rotate180);
+        let config = display.config();
+
+        // re-attached with the rotation changed on the display, the content stays
+        let display = config.attach::<Color>(&mut buffer).unwrap();
+        assert!(matches!(display.rotation(), DisplayRotation::Rotate180));
+        assert_eq!(display.anchor(), Some(Point::new(8, 0)));
+        assert_eq!(display.buffer()[0], 0x80);
+
+        let mut small = [0u8; 15];
+        assert!(config.attach::<Color>(&mut small).is_err());
+    }
+
+    type Tile = Display<16, 8, false, 16, Color>;
+
+    /// Tiles of a 2x2 surface drawn with `draw`, the bottom right one mounted upside down
+    fn tiled(draw: impl Fn(&mut TiledSurface<2, 2, Tile>)) -> TiledSurface<2, 2, Tile> {
+        let mut surface = TiledSurface::new([
+            [Tile::default(), Tile::default()],
+            [Tile::default(), Tile::default()],
+        ])
+        .unwrap();
+        surface
+            .tile_mut(1, 1)
+            .set_rotation(DisplayRotation::Rotate180);
+        assert_eq!(surface.size(), Size::new(32, 16));
+        draw(&mut surface);
+        surface
+    }
+
+    #[test]
+    fn tiled_surface_routes_pixels() {
+        let line = Line::new(Point::new(-3, 2), Point::new(40, 13));
+        let frame = Rectangle::new(Point::new(2, 3), Size::new(27, 11));
+        let style = PrimitiveStyle::with_stroke(Color::White, 1);
+        let surface = tiled(|surface| {
+            line.into_styled(style).draw(surface).unwrap();
+            frame.into_styled(style).draw(surface).unwrap();
+        });
+
+        // every tile holds its segment, drawn in its own rotation
+        surface.for_each_tile(|col, row, tile| {
+            let offset = -Point::new(col as i32 * 16, row as i32 * 8);
+            let mut expected = Tile::default();
+            expected.set_rotation(tile.rotation());
+            line.translate(offset)
+                .into_styled(style)
+                .draw(&mut expected)
+                .unwrap();
+            frame
+                .translate(offset)
+                .into_styled(style)
+                .draw(&mut expected)
+                .unwrap();
+            assert_eq!(tile.buffer(), expected.buffer(), "tile {col}, {row}");
+            assert!(tile.buffer().iter().any(|&byte| byte != 0));
+        });
+    }
+
+    #[test]
+    fn tiled_surface_clips_fills() {
+        let area = Rectangle::new(Point::new(10, 6), Size::new(30, 4));
+        let surface = tiled(|surface| surface.fill_solid(&area, Color::White).unwrap());
+
+        surface.for_each_tile(|col, row, tile| {
+            let mut expected = Tile::default();
+            expected.set_rotation(tile.rotation());
+            expected
+                .fill_solid(
+                    &area.translate(-Point::new(col as i32 * 16, row as i32 * 8)),
+                    Color::White,
+                )
+                .unwrap();
+            assert_eq!(tile.buffer(), expected.buffer(), "tile {col}, {row}");
+        });
+        // the area reaches into every tile
+        surface.for_each_tile(|_, _, tile| assert!(tile.buffer().iter().any(|&byte| byte != 0)));
+
+        let mut rotated = Tile::default();
+        rotated.set_rotation(DisplayRotation::Rotate90);
+        assert!(matches!(
+            TiledSurface::new([[Tile::default(), rotated]]),
+            Err(TiledSurfaceError::TileSizeMismatch)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "epd4in2")]
+    fn flush_bw_display() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Bus, Delay, Pin};
+        extern crate std;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut display = std::boxed::Box::new(Display4in2::new());
+        display.set_pixel(Pixel(Point::new(9, 1), Color::White));
+        bus.clear();
+
+        display.flush(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x13).unwrap(), display.buffer());
+        assert_eq!(bus.count(0x12), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "epd2in13bc")]
+    fn flush_tri_color_display() {
+        use crate::epd2in13bc::{Display2in13bc, Epd2in13bc};
+        use crate::mock::{Bus, Delay, Pin};
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd2in13bc::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut display = Display2in13bc::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(1, 0), TriColor::Black));
+        bus.clear();
+
+        display.flush(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x10, 0x13, 0x12]);
+        assert_eq!(bus.data_after(0x10).unwrap(), display.bw_buffer());
+        assert_eq!(bus.data_after(0x13).unwrap(), display.chromatic_buffer());
+    }
+
+    #[test]
+    #[cfg(feature = "epd4in2")]
+    fn flush_var_display() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Bus, Delay, Pin};
+        extern crate std;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+
+        // without anchor the whole panel is sent
+        let mut buffer = std::boxed::Box::new([0u8; Display4in2::BUFFER_LEN]);
+        let display = VarDisplay::<Color>::new(400, 300, &mut buffer[..], false).unwrap();
+        bus.clear();
+        display.flush(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(bus.data_after(0x13).unwrap(), display.buffer());
+        assert_eq!(bus.count(0x91), 0);
+
+        // an anchored window is a partial update
+        let mut buffer = [0u8; 2 * 8];
+        let mut display = VarDisplay::<Color>::new(16, 8, &mut buffer, false).unwrap();
+        display.clear(Color::White).unwrap();
+        display.set_anchor(Some(Point::new(8, 16)));
+        bus.clear();
+        display.flush(&mut epd, &mut spi, &mut delay).unwrap();
+        assert_eq!(bus.commands(), [0x91, 0x90, 0x13, 0x92, 0x12]);
+        assert_eq!(
+            bus.data_after(0x90).unwrap(),
+            [0, 8, 0, 23, 0, 16, 0, 23, 0x01]
+        );
+        assert_eq!(bus.data_after(0x13).unwrap(), [0xFF; 16]);
+    }
+
+    #[test]
+    #[cfg(feature = "epd4in2")]
+    fn static_display_matches_display() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Bus, BusyPin, Delay, Pin};
+        use embedded_graphics::primitives::Circle;
+
+        extern crate std;
+        let buffer: &'static mut [u8; Display4in2::BUFFER_LEN] =
+            std::boxed::Box::leak(std::boxed::Box::new([0; Display4in2::BUFFER_LEN]));
+        let mut display =
+            StaticDisplay::<400, 300, false, { Display4in2::BUFFER_LEN }, Color>::new(buffer);
+        let mut expected = Display4in2::new();
+
+        for rotation in ROTATIONS {
+            display.set_rotation(rotation);
+            expected.set_rotation(rotation);
+            let circle = Circle::new(Point::new(20, 30), 100)
+                .into_styled(PrimitiveStyle::with_stroke(Color::White, 3));
+            circle.draw(&mut display).unwrap();
+            circle.draw(&mut expected).unwrap();
+            display
+                .fill_solid(
+                    &Rectangle::new(Point::new(150, 5), Size::new(33, 7)),
+                    Color::White,
+                )
+                .unwrap();
+            expected
+                .fill_solid(
+                    &Rectangle::new(Point::new(150, 5), Size::new(33, 7)),
+                    Color::White,
+                )
+                .unwrap();
         }
+        assert_eq!(display.buffer(), expected.buffer());
+
+        // the released buffer goes to the display unchanged
+        let buffer: &'static [u8] = display.release();
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+        epd.update_frame_static(&mut spi, buffer, &mut delay)
+            .unwrap();
+        assert_eq!(bus.data_after(0x13).as_deref(), Some(expected.buffer()));
     }
 
     #[test]
-    fn graphics_rotation_0() {
-        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
-            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-            .draw(&mut display);
+    #[cfg(all(feature = "epd2in13bc", feature = "epd4in2"))]
+    fn invert_flips_bw_plane() {
+        use crate::epd2in13bc::Display2in13bc;
+        use crate::epd4in2::Display4in2;
 
-        let buffer = display.buffer();
+        let mut display = Display4in2::default();
+        display.set_pixel(Pixel(Point::new(0, 0), Color::White));
+        display.invert();
+        assert_eq!(display.buffer()[0], 0x7F);
+        assert!(display.buffer()[1..].iter().all(|&b| b == 0xFF));
+        display.invert();
+        assert_eq!(display.buffer()[0], 0x80);
 
-        assert_eq!(buffer[0], Color::Black.get_byte_value());
+        let mut display = Display2in13bc::default();
+        display.set_pixel(Pixel(Point::new(0, 0), TriColor::White));
+        display.set_pixel(Pixel(Point::new(1, 0), TriColor::Chromatic));
+        let bw = display.bw_buffer().to_vec();
+        let chromatic = display.chromatic_buffer().to_vec();
+        display.invert();
+        assert!(display.bw_buffer().iter().zip(&bw).all(|(a, b)| *a == !*b));
+        assert_eq!(display.chromatic_buffer(), chromatic);
 
-        for &byte in buffer.iter().skip(1) {
-            assert_eq!(byte, 0);
+        let mut buffer = [0u8; 8];
+        let mut display = VarDisplay::<Color>::new(8, 4, &mut buffer, false).unwrap();
+        display.invert();
+        assert_eq!(display.buffer(), [0xFF; 4]);
+    }
+
+    fn assert_wraps(text: &str, max_chars: usize, expected: &[&str]) {
+        let mut lines = wrap_lines(text, max_chars);
+        for line in expected {
+            assert_eq!(lines.next(), Some(*line));
         }
+        assert_eq!(lines.next(), None);
     }
 
     #[test]
-    fn graphics_rotation_90() {
-        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        display.set_rotation(DisplayRotation::Rotate90);
-        let _ = Line::new(Point::new(0, 192), Point::new(0, 199))
-            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-            .draw(&mut display);
+    fn wrap_lines_at_whitespace() {
+        assert_wraps("the quick brown fox", 9, &["the quick", "brown fox"]);
+        assert_wraps("abc def", 3, &["abc", "def"]);
+        assert_wraps("a  lot   of    space", 5, &["a", "lot", "of", "space"]);
+        assert_wraps("  indented", 10, &["  indented"]);
+    }
 
-        let buffer = display.buffer();
+    #[test]
+    fn wrap_lines_edge_cases() {
+        // a word longer than the line is split
+        assert_wraps("abcdefghij kl", 4, &["abcd", "efgh", "ij", "kl"]);
+        // trailing whitespace doesn't make an empty line
+        assert_wraps("hello world   ", 5, &["hello", "world"]);
+        // newlines start new lines, the trailing one doesn't add a line
+        assert_wraps("one\n\ntwo three\n", 5, &["one", "", "two", "three"]);
+        assert_wraps("wrapped   \nnext", 7, &["wrapped", "next"]);
+        assert_wraps("", 5, &[]);
+        assert_wraps("no room", 0, &[]);
+    }
 
-        assert_eq!(buffer[0], Color::Black.get_byte_value());
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_box_truncates_with_ellipsis() {
+        use embedded_graphics::{mono_font::ascii::FONT_6X10, text::Text};
 
-        for &byte in buffer.iter().skip(1) {
-            assert_eq!(byte, 0);
+        // 5 characters and 2 lines fit
+        let bounds = Rectangle::new(Point::new(2, 1), Size::new(30, 20));
+        let mut display = Display::<40, 24, false, { 5 * 24 }, Color>::default();
+        let layout = TextBox::new("one two three four", bounds, &FONT_6X10, Color::White)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(
+            layout,
+            TextBoxLayout {
+                lines: 2,
+                truncated: true
+            }
+        );
+
+        let mut expected = Display::<40, 24, false, { 5 * 24 }, Color>::default();
+        let style = MonoTextStyle::new(&FONT_6X10, Color::White);
+        for (text, y) in [("one", 1), ("tw...", 11)] {
+            Text::with_baseline(text, Point::new(2, y), style, Baseline::Top)
+                .draw(&mut expected)
+                .unwrap();
         }
+        assert_eq!(display.buffer(), expected.buffer());
+
+        let layout = TextBox::new("fits", bounds, &FONT_6X10, Color::White)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(
+            layout,
+            TextBoxLayout {
+                lines: 1,
+                truncated: false
+            }
+        );
     }
 
+    #[cfg(feature = "text")]
     #[test]
-    fn graphics_rotation_180() {
-        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        display.set_rotation(DisplayRotation::Rotate180);
-        let _ = Line::new(Point::new(192, 199), Point::new(199, 199))
-            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-            .draw(&mut display);
+    fn text_box_alignment() {
+        use embedded_graphics::{mono_font::ascii::FONT_6X10, text::Text};
 
-        let buffer = display.buffer();
+        // "ab" is 12 pixels wide, the truncated second line 30
+        let bounds = Rectangle::new(Point::new(2, 1), Size::new(33, 20));
+        let style = MonoTextStyle::new(&FONT_6X10, Color::White);
+        for (align, first, second) in [
+            (HAlign::Left, 2, 2),
+            (HAlign::Center, 12, 3),
+            (HAlign::Right, 23, 5),
+        ] {
+            let mut display = Display::<40, 24, false, { 5 * 24 }, Color>::default();
+            TextBox::new("ab cdefgh ij", bounds, &FONT_6X10, Color::White)
+                .with_alignment(align)
+                .draw(&mut display)
+                .unwrap();
+
+            let mut expected = Display::<40, 24, false, { 5 * 24 }, Color>::default();
+            Text::with_baseline("ab", Point::new(first, 1), style, Baseline::Top)
+                .draw(&mut expected)
+                .unwrap();
+            Text::with_baseline("cd...", Point::new(second, 11), style, Baseline::Top)
+                .draw(&mut expected)
+                .unwrap();
+            assert_eq!(display.buffer(), expected.buffer(), "{:?}", align);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_box_emphasis_over_lines() {
+        use embedded_graphics::{mono_font::ascii::FONT_6X10, text::Text};
+
+        // 7 characters per line, the markers don't count
+        let bounds = Rectangle::new(Point::zero(), Size::new(42, 24));
+        let mut display = Display::<64, 24, false, { 8 * 24 * 2 }, TriColor>::default();
+        TextBox::new("a *bb cc d*", bounds, &FONT_6X10, TriColor::Black)
+            .with_emphasis('*', TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+
+        // the emphasis continues on the second line
+        let mut expected = Display::<64, 24, false, { 8 * 24 * 2 }, TriColor>::default();
+        let black = MonoTextStyle::new(&FONT_6X10, TriColor::Black);
+        let red = MonoTextStyle::new(&FONT_6X10, TriColor::Chromatic);
+        Text::with_baseline("a ", Point::zero(), black, Baseline::Top)
+            .draw(&mut expected)
+            .unwrap();
+        Text::with_baseline("bb cc", Point::new(12, 0), red, Baseline::Top)
+            .draw(&mut expected)
+            .unwrap();
+        Text::with_baseline("d", Point::new(0, 10), red, Baseline::Top)
+            .draw(&mut expected)
+            .unwrap();
+        assert_eq!(display.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn pixel_round_trip() {
+        for rotation in ROTATIONS {
+            // a width which isn't a multiple of 8, with padding bits
+            let mut buffer = [0u8; 3 * 10];
+            let mut display = Display::<20, 10, false, 30, Color>::default();
+            let mut var = VarDisplay::<Color>::new(20, 10, &mut buffer, false).unwrap();
+            display.set_rotation(rotation);
+            var.set_rotation(rotation);
+            let Size { width, height } = display.size();
+            for (x, y) in (0..width).flat_map(|x| (0..height).map(move |y| (x, y))) {
+                let point = Point::new(x as i32, y as i32);
+                assert!(display.set_pixel(Pixel(point, Color::White)));
+                assert_eq!(display.get_pixel(x, y), Some(Color::White));
+                assert!(var.set_pixel(Pixel(point, Color::White)));
+                assert_eq!(var.get_pixel(x, y), Some(Color::White));
+                // fill_solid converts the rotation on its own, it sets the same bit
+                let mut drawn = Display::<20, 10, false, 30, Color>::default();
+                drawn.set_rotation(rotation);
+                drawn
+                    .fill_solid(&Rectangle::new(point, Size::new(1, 1)), Color::White)
+                    .unwrap();
+                assert_eq!(drawn.get_pixel(x, y), Some(Color::White));
+                assert_eq!(
+                    drawn.buffer().iter().map(|b| b.count_ones()).sum::<u32>(),
+                    1
+                );
+            }
+            // every pixel was set exactly once, the padding bits stay cleared
+            let set: u32 = display.buffer().iter().map(|b| b.count_ones()).sum();
+            assert_eq!(set, 200);
+            assert_eq!(var.buffer(), display.buffer());
+        }
+    }
+
+    #[test]
+    fn pixel_bounds() {
+        for rotation in ROTATIONS {
+            let mut display = Display::<20, 10, false, 30, Color>::default();
+            display.set_rotation(rotation);
+            let Size { width, height } = display.size();
+            for (x, y) in [(width, 0), (0, height), (width, height), (u32::MAX, 0)] {
+                assert_eq!(display.get_pixel(x, y), None);
+            }
+            for point in [
+                Point::new(-1, 0),
+                Point::new(0, -1),
+                Point::new(width as i32, 0),
+            ] {
+                assert!(!display.set_pixel(Pixel(point, Color::White)));
+            }
+            assert!(display.buffer().iter().all(|&b| b == 0));
+            let corner = Point::new(width as i32 - 1, height as i32 - 1);
+            assert!(display.set_pixel(Pixel(corner, Color::White)));
+        }
+
+        let mut owned = OwnedVarDisplay::<30, Color>::new(20, 10, false).unwrap();
+        assert_eq!(owned.get_pixel(19, 9), Some(Color::Black));
+        assert_eq!(owned.get_pixel(20, 9), None);
+        assert!(owned.set_pixel(Pixel(Point::new(19, 9), Color::White)));
+        assert_eq!(owned.get_pixel(19, 9), Some(Color::White));
+    }
+
+    #[test]
+    fn tri_color_pixel_round_trip() {
+        for rotation in ROTATIONS {
+            let mut display = Display::<16, 8, false, 32, TriColor>::default();
+            let mut bwrbit = Display::<16, 8, true, 32, TriColor>::default();
+            display.set_rotation(rotation);
+            bwrbit.set_rotation(rotation);
+            let colors = [TriColor::White, TriColor::Chromatic, TriColor::Black];
+            for (i, point) in display.bounding_box().points().enumerate() {
+                let color = colors[i % 3];
+                assert!(display.set_pixel(Pixel(point, color)));
+                assert!(bwrbit.set_pixel(Pixel(point, color)));
+            }
+            for (i, point) in display.bounding_box().points().enumerate() {
+                let (x, y) = (point.x as u32, point.y as u32);
+                assert_eq!(display.get_pixel(x, y), Some(colors[i % 3]));
+                assert_eq!(bwrbit.get_pixel(x, y), Some(colors[i % 3]));
+            }
+            assert_eq!(display.get_pixel(16, 16), None);
+        }
+    }
+
+    #[test]
+    fn chromatic_policies() {
+        // chromatic band with black drawn over its middle, chromatic pixels on the
+        // second row, on 16 x 2 pixels
+        fn scene<D: DrawTarget<Color = TriColor>>(display: &mut D) {
+            let _ = display.clear(TriColor::White);
+            let _ = display.fill_solid(
+                &Rectangle::new(Point::zero(), Size::new(8, 1)),
+                TriColor::Chromatic,
+            );
+            let _ = display.draw_iter((2..6).map(|x| Pixel(Point::new(x, 0), TriColor::Black)));
+            let _ =
+                display.draw_iter((8..12).map(|x| Pixel(Point::new(x, 1), TriColor::Chromatic)));
+        }
+        let expected = [
+            (
+                ChromaticPolicy::Normal,
+                [0xC3, 0xFF, 0xFF, 0xFF],
+                [0xC3, 0x00, 0x00, 0xF0],
+            ),
+            (
+                ChromaticPolicy::RenderAsBlack,
+                [0x00, 0xFF, 0xFF, 0x0F],
+                [0x00; 4],
+            ),
+            (ChromaticPolicy::Drop, [0xC3, 0xFF, 0xFF, 0xFF], [0x00; 4]),
+        ];
+
+        for (policy, bw, chromatic) in expected {
+            let mut display = Display::<16, 2, false, 8, TriColor>::default();
+            display.set_chromatic_policy(policy);
+            scene(&mut display);
+            assert_eq!(display.bw_buffer(), bw, "{:?}", policy);
+            assert_eq!(display.chromatic_buffer(), chromatic, "{:?}", policy);
+
+            // the borrowed displays keep the policy of their config
+            let mut owned = OwnedVarDisplay::<8, TriColor>::new(16, 2, false).unwrap();
+            owned.set_chromatic_policy(policy);
+            scene(&mut owned);
+            assert_eq!(owned.buffer(), display.buffer(), "{:?}", policy);
+            let mut buffer = [0u8; 8];
+            let mut var = owned.config().attach::<TriColor>(&mut buffer).unwrap();
+            assert_eq!(var.chromatic_policy(), policy);
+            scene(&mut var);
+            assert_eq!(var.buffer(), display.buffer(), "{:?}", policy);
+        }
+
+        // a dropped clear keeps the frame, dropped pixels are still in range
+        let mut display = Display::<16, 2, false, 8, TriColor>::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_chromatic_policy(ChromaticPolicy::Drop);
+        display.clear(TriColor::Chromatic).unwrap();
+        display.fill_rect_native(
+            Rectangle::new(Point::zero(), Size::new(4, 2)),
+            TriColor::Chromatic,
+        );
+        assert!(display.set_pixel(Pixel(Point::new(15, 1), TriColor::Chromatic)));
+        assert!(!display.set_pixel(Pixel(Point::new(16, 1), TriColor::Chromatic)));
+        assert_eq!(display.buffer(), [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn partial_chromatic_display_matches_display() {
+        // 20 x 12 with the chromatic band on the native rows 4..7
+        type Banded = PartialChromaticDisplay<20, 12, 3, false, { 3 * 15 }>;
+        type Full = Display<20, 12, false, { 3 * 24 }, TriColor>;
+        let in_band =
+            |point: Point, rotation| (4..7).contains(&native_point(20, 12, rotation, point).y);
+
+        for rotation in ROTATIONS {
+            let mut banded = Banded::new(4);
+            let mut full = Full::default();
+            banded.set_rotation(rotation);
+            full.set_rotation(rotation);
+            banded.clear(TriColor::White).unwrap();
+            full.clear(TriColor::White).unwrap();
+            let colors = [TriColor::Chromatic, TriColor::Black, TriColor::White];
+            for (i, point) in full.bounding_box().points().enumerate() {
+                let color = match colors[i % 3] {
+                    TriColor::Chromatic if !in_band(point, rotation) => TriColor::Black,
+                    color => color,
+                };
+                assert!(banded.set_pixel(Pixel(point, colors[i % 3])));
+                assert!(full.set_pixel(Pixel(point, color)));
+            }
+            assert_eq!(banded.bw_buffer(), full.bw_buffer());
+            let band = banded.chromatic_buffer();
+            assert_eq!(band.start_row, 4);
+            assert_eq!(band.data, &full.chromatic_buffer()[4 * 3..7 * 3]);
+            for point in full.bounding_box().points() {
+                let (x, y) = (point.x as u32, point.y as u32);
+                assert_eq!(banded.get_pixel(x, y), full.get_pixel(x, y));
+            }
+            assert!(!banded.set_pixel(Pixel(Point::new(-1, 0), TriColor::Black)));
+        }
+
+        // chromatic is black outside of the band
+        let mut banded = Banded::new(9);
+        banded.clear(TriColor::Chromatic).unwrap();
+        assert_eq!(banded.get_pixel(0, 8), Some(TriColor::Black));
+        assert_eq!(banded.get_pixel(19, 9), Some(TriColor::Chromatic));
+        assert_eq!(banded.get_pixel(0, 11), Some(TriColor::Chromatic));
+        assert_eq!(banded.chromatic_buffer().data, [0xFF; 9]);
+        // BWRBIT false sets the black/white bit of chromatic pixels
+        assert_eq!(banded.bw_buffer()[..27], [0x00; 27]);
+        assert_eq!(banded.bw_buffer()[27..], [0xFF; 9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "beyond the panel")]
+    fn partial_chromatic_band_inside_panel() {
+        PartialChromaticDisplay::<20, 12, 3, false, { 3 * 15 }>::new(10);
+    }
+
+    #[test]
+    fn sparkline_columns() {
+        type Small = Display<8, 8, false, 8, Color>;
+        let bounds = Rectangle::new(Point::new(1, 2), Size::new(4, 5));
+        // bit y is set for the white pixels of column x
+        let column = |display: &Small, x| {
+            (0..8)
+                .filter(|&y| display.get_pixel(x, y) == Some(Color::White))
+                .fold(0u8, |bits, y| bits | 1 << y)
+        };
+
+        // only the last 4 samples fit, 10 is the minimum and 50 the maximum
+        let mut display = Small::default();
+        Sparkline::new(&[0, 60, 10, 50, 30, 20], bounds, Color::White)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(column(&display, 0), 0);
+        assert_eq!(column(&display, 1), 0b0100_0000);
+        assert_eq!(column(&display, 2), 0b0111_1100);
+        assert_eq!(column(&display, 3), 0b0111_0000);
+        assert_eq!(column(&display, 4), 0b0110_0000);
+        assert_eq!(column(&display, 5), 0);
+
+        // equal samples are a line at the bottom
+        let mut display = Small::default();
+        Sparkline::new(&[7, 7], bounds, Color::White)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(column(&display, 1), 0b0100_0000);
+        assert_eq!(column(&display, 2), 0b0100_0000);
+        assert_eq!(column(&display, 3), 0);
+    }
+
+    #[test]
+    fn row_renderer_capacity() {
+        let mut scene = RowRenderer::<1>::new(Color::White);
+        assert!(scene.is_empty());
+        let area = Rectangle::new(Point::zero(), Size::new(8, 8));
+        scene.fill(area, Color::Black).unwrap();
+        assert!(matches!(
+            scene.fill(area, Color::White),
+            Err(RowCommand::Fill {
+                color: Color::White,
+                ..
+            })
+        ));
+        assert_eq!(scene.len(), 1);
+        scene.clear();
+        assert!(scene.is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "epd7in5_v2", feature = "text"))]
+    fn row_renderer_matches_display() {
+        use crate::epd7in5_v2::{Display7in5, Epd7in5, HEIGHT, WIDTH};
+        use crate::mock::{Bus, Delay, Pin};
+        use embedded_graphics::{
+            mono_font::{ascii::FONT_10X20, ascii::FONT_6X10, MonoTextStyle},
+            text::{Baseline, Text},
+        };
+
+        // an 11 pixels wide icon, with padding bits set to catch them leaking
+        let icon_data = [0xF0, 0x3F, 0x81, 0xE0, 0xAA, 0xBF];
+        let icon = RawBitImage::new(&icon_data, 11);
+
+        let mut scene = RowRenderer::<8>::new(Color::White);
+        let banner = Rectangle::new(Point::new(-5, 3), Size::new(811, 30));
+        let hole = Rectangle::new(Point::new(13, 10), Size::new(77, 9));
+        scene.fill(banner, Color::Black).unwrap();
+        scene.fill(hole, Color::White).unwrap();
+        scene
+            .text("Hello, 24 KB!", Point::new(7, 8), &FONT_10X20, Color::White)
+            .unwrap();
+        scene
+            .text(
+                "cut off at the edge",
+                Point::new(720, 40),
+                &FONT_6X10,
+                Color::Black,
+            )
+            .unwrap();
+        scene
+            .text("negative", Point::new(-9, 60), &FONT_6X10, Color::Black)
+            .unwrap();
+        scene.bitmap(icon, Point::new(3, 14), Color::Black).unwrap();
+        scene
+            .bitmap(icon, Point::new(795, 477), Color::Black)
+            .unwrap();
+
+        // the same commands drawn into a full buffer
+        let mut display = Display7in5::default();
+        display.clear(Color::White).unwrap();
+        display.fill_solid(&banner, Color::Black).unwrap();
+        display.fill_solid(&hole, Color::White).unwrap();
+        let text = |text, position, font, color| {
+            Text::with_baseline(
+                text,
+                position,
+                MonoTextStyle::new(font, color),
+                Baseline::Top,
+            )
+        };
+        text("Hello, 24 KB!", Point::new(7, 8), &FONT_10X20, Color::White)
+            .draw(&mut display)
+            .unwrap();
+        text(
+            "cut off at the edge",
+            Point::new(720, 40),
+            &FONT_6X10,
+            Color::Black,
+        )
+        .draw(&mut display)
+        .unwrap();
+        text("negative", Point::new(-9, 60), &FONT_6X10, Color::Black)
+            .draw(&mut display)
+            .unwrap();
+        display
+            .draw_iter(icon.pixels(Point::new(3, 14), Color::Black))
+            .unwrap();
+        display
+            .draw_iter(icon.pixels(Point::new(795, 477), Color::Black))
+            .unwrap();
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        bus.clear();
+        scene.stream_to(&mut epd, &mut spi, &mut delay).unwrap();
+
+        assert_eq!(bus.commands().last(), Some(&0x13));
+        let streamed = bus.data_after(0x13).unwrap();
+        assert_eq!(
+            streamed.len(),
+            crate::buffer_len(WIDTH as usize, HEIGHT as usize)
+        );
+        assert!(streamed == display.buffer());
+    }
 
+    fn frame_pixel(buf: &[u8], width: u32, x: u32, y: u32) -> bool {
+        let line = crate::line_bytes(width);
+        buf[(y * line + x / 8) as usize] & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Fills `buf` with noise, the padding bits are set too to catch shifts by the padding
+    fn fill_noise(buf: &mut [u8]) {
+        let mut state = 0x2545_F491_u32;
+        for byte in buf {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *byte = state as u8;
+        }
+    }
+
+    /// Checks every pixel of `dst` (`dst_width` wide) against the pixel of `src` at the
+    /// position given by `source`
+    fn assert_mapped(
+        src: &[u8],
+        width: u32,
+        dst: &[u8],
+        dst_width: u32,
+        dst_height: u32,
+        source: impl Fn(u32, u32) -> (u32, u32),
+    ) {
+        for y in 0..dst_height {
+            for x in 0..dst_width {
+                let (sx, sy) = source(x, y);
+                assert_eq!(
+                    frame_pixel(dst, dst_width, x, y),
+                    frame_pixel(src, width, sx, sy),
+                    "pixel {x}, {y}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_transforms_match_pixels() {
         extern crate std;
-        std::println!("{:?}", buffer);
+        // 2.13", 1.54" c and a frame with a partial block at the bottom
+        for (w, h) in [(122, 250), (152, 152), (152, 21)] {
+            let mut src = std::vec![0; crate::buffer_len(w as usize, h as usize)];
+            fill_noise(&mut src);
 
-        assert_eq!(buffer[0], Color::Black.get_byte_value());
+            let mut dst = src.clone();
+            rotate180_in_place(&mut dst, w, h);
+            assert_mapped(&src, w, &dst, w, h, |x, y| (w - 1 - x, h - 1 - y));
 
-        for &byte in buffer.iter().skip(1) {
-            assert_eq!(byte, 0);
+            let mut dst = src.clone();
+            flip_horizontal_in_place(&mut dst, w, h);
+            assert_mapped(&src, w, &dst, w, h, |x, y| (w - 1 - x, y));
+
+            let mut dst = src.clone();
+            flip_vertical_in_place(&mut dst, w, h);
+            assert_mapped(&src, w, &dst, w, h, |x, y| (x, h - 1 - y));
+
+            let mut dst = std::vec![0xFF; crate::buffer_len(h as usize, w as usize)];
+            rotate90_into(&src, w, h, &mut dst);
+            assert_mapped(&src, w, &dst, h, w, |x, y| (y, h - 1 - x));
+
+            let mut dst = std::vec![0xFF; crate::buffer_len(h as usize, w as usize)];
+            rotate270_into(&src, w, h, &mut dst);
+            assert_mapped(&src, w, &dst, h, w, |x, y| (w - 1 - y, x));
+        }
+    }
+
+    /// Per pixel reference for `downscale_into`, the source area of a destination pixel
+    /// stepped in 16.16 fixed point as documented
+    fn reference_downscale(
+        src: &[u8],
+        (src_w, src_h): (u32, u32),
+        (dst_w, dst_h): (u32, u32),
+        policy: ScalePolicy,
+        ink: bool,
+        x: u32,
+        y: u32,
+    ) -> bool {
+        let span = |d: u32, src: u32, dst: u32| {
+            let step = ((src as u64) << 16).div_ceil(dst as u64);
+            let start = ((d as u64 * step) >> 16).min(src as u64 - 1) as u32;
+            let end = (((d as u64 + 1) * step) >> 16).min(src as u64) as u32;
+            (start, end.max(start + 1))
+        };
+        let (x0, x1) = span(x, src_w, dst_w);
+        let (y0, y1) = span(y, src_h, dst_h);
+        let (mut inked, mut total) = (0, 0);
+        for sy in y0..y1 {
+            for sx in x0..x1 {
+                total += 1;
+                if frame_pixel(src, src_w, sx, sy) == ink {
+                    inked += 1;
+                }
+            }
+        }
+        match policy {
+            ScalePolicy::NearestNeighbor => frame_pixel(src, src_w, (x0 + x1) / 2, (y0 + y1) / 2),
+            ScalePolicy::BlackWins => (inked > 0) == ink,
+            ScalePolicy::Majority => (2 * inked >= total) == ink,
         }
     }
 
     #[test]
-    fn graphics_rotation_270() {
-        let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
-        display.set_rotation(DisplayRotation::Rotate270);
-        let _ = Line::new(Point::new(199, 0), Point::new(199, 7))
-            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-            .draw(&mut display);
+    fn downscale_matches_reference() {
+        extern crate std;
+        let policies = [
+            ScalePolicy::NearestNeighbor,
+            ScalePolicy::BlackWins,
+            ScalePolicy::Majority,
+        ];
+        // 7.5" to 4.2", 3:2, a destination width which isn't a multiple of 8 and scaling up
+        for (src_size, dst_size) in [
+            ((800, 480), (400, 300)),
+            ((150, 90), (100, 60)),
+            ((16, 16), (13, 10)),
+            ((122, 250), (61, 125)),
+            ((10, 7), (21, 9)),
+        ] {
+            let ((src_w, src_h), (dst_w, dst_h)) = (src_size, dst_size);
+            let src_len = crate::buffer_len(src_w as usize, src_h as usize);
+            let dst_len = crate::buffer_len(dst_w as usize, dst_h as usize);
+            let mut src = std::vec![0; 2 * src_len];
+            fill_noise(&mut src);
+            for policy in policies {
+                let mut dst = std::vec![0xFF; dst_len];
+                downscale_into(&src, src_w, src_h, &mut dst, dst_w, dst_h, policy);
 
-        let buffer = display.buffer();
+                let mut tri_color = std::vec![0xFF; 2 * dst_len];
+                downscale_tri_color_into(&src, src_w, src_h, &mut tri_color, dst_w, dst_h, policy);
+                assert_eq!(tri_color[..dst_len], dst[..]);
+
+                let chromatic = &src[src_len..];
+                for y in 0..dst_h {
+                    for x in 0..dst_w {
+                        let bw = reference_downscale(&src, src_size, dst_size, policy, false, x, y);
+                        assert_eq!(frame_pixel(&dst, dst_w, x, y), bw, "{policy:?} {x}, {y}");
+                        let red =
+                            reference_downscale(chromatic, src_size, dst_size, policy, true, x, y);
+                        let got = frame_pixel(&tri_color[dst_len..], dst_w, x, y);
+                        assert_eq!(got, red, "{policy:?} chromatic {x}, {y}");
+                    }
+                }
+                // the padding is cleared
+                let line = crate::line_bytes(dst_w) as usize;
+                if dst_w % 8 != 0 {
+                    let pad = 0xFF >> (dst_w % 8);
+                    assert!(dst.chunks(line).all(|row| row[line - 1] & pad == 0));
+                }
+            }
+        }
+    }
 
+    #[test]
+    fn black_wins_keeps_thin_lines() {
         extern crate std;
-        std::println!("{:?}", buffer);
+        let (w, h) = (800, 480);
+        let mut src = std::vec![0xFF; crate::buffer_len(w, h)];
+        // a vertical and a horizontal line of 1 pixel, off the middle of the 2x2 areas
+        for y in 0..h {
+            src[y * 100 + 100 / 8] &= !(0x80 >> (100 % 8));
+        }
+        src[300 * 100..301 * 100].fill(0);
+
+        let mut dst = std::vec![0; crate::buffer_len(400, 240)];
+        downscale_into(&src, 800, 480, &mut dst, 400, 240, ScalePolicy::BlackWins);
+        for i in 0..240 {
+            assert!(!frame_pixel(&dst, 400, 50, i));
+            assert!(frame_pixel(&dst, 400, 49, i) || i == 150);
+        }
+        for i in 0..400 {
+            assert!(!frame_pixel(&dst, 400, i, 150));
+            assert!(frame_pixel(&dst, 400, i, 149) || i == 50);
+        }
 
-        assert_eq!(buffer[0], Color::Black.get_byte_value());
+        // while the middle pixel is the white one next to them
+        downscale_into(
+            &src,
+            800,
+            480,
+            &mut dst,
+            400,
+            240,
+            ScalePolicy::NearestNeighbor,
+        );
+        assert!(dst.iter().all(|&byte| byte == 0xFF));
+    }
 
-        for &byte in buffer.iter().skip(1) {
-            assert_eq!(byte, 0);
+    #[test]
+    fn display_transform_buffer() {
+        let mut display = Display::<152, 152, true, { 152 * 152 / 8 * 2 }, TriColor>::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(1, 0), TriColor::Black));
+        display.set_pixel(Pixel(Point::new(0, 2), TriColor::Chromatic));
+
+        assert!(display.transform_buffer(BufferTransform::Rotate90));
+        assert_eq!(display.get_pixel(151, 1), Some(TriColor::Black));
+        assert_eq!(display.get_pixel(149, 0), Some(TriColor::Chromatic));
+        assert!(display.transform_buffer(BufferTransform::Rotate270));
+        assert_eq!(display.get_pixel(1, 0), Some(TriColor::Black));
+        assert_eq!(display.get_pixel(0, 2), Some(TriColor::Chromatic));
+
+        assert!(display.transform_buffer(BufferTransform::Rotate180));
+        assert_eq!(display.get_pixel(150, 151), Some(TriColor::Black));
+        assert_eq!(display.get_pixel(151, 149), Some(TriColor::Chromatic));
+
+        // the 2.13" isn't square
+        let mut display = Display::<122, 250, false, { 16 * 250 }, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        assert!(!display.transform_buffer(BufferTransform::Rotate90));
+        assert_eq!(display.get_pixel(0, 0), Some(Color::Black));
+        assert!(display.transform_buffer(BufferTransform::FlipHorizontal));
+        assert_eq!(display.get_pixel(0, 0), Some(Color::White));
+        assert_eq!(display.get_pixel(121, 0), Some(Color::Black));
+    }
+
+    #[test]
+    fn image2lcd_formats() {
+        // 10 x 3 pixels, black at (0, 0), (9, 0), (1, 1) and (2, 2)
+        let native = [0x7F, 0x80, 0xBF, 0xC0, 0xDF, 0xC0];
+        let format = |scan, lsb_first, inverted| Image2LcdFormat {
+            scan,
+            lsb_first,
+            inverted,
+        };
+        let horizontal = Image2LcdScan::Horizontal;
+        let vertical = Image2LcdScan::Vertical;
+        let cases: [(Image2LcdFormat, &[u8]); 8] = [
+            (format(horizontal, false, false), &native),
+            (
+                format(horizontal, false, true),
+                &[0x80, 0x40, 0x40, 0x00, 0x20, 0x00],
+            ),
+            (
+                format(horizontal, true, false),
+                &[0xFE, 0x01, 0xFD, 0x03, 0xFB, 0x03],
+            ),
+            (
+                format(horizontal, true, true),
+                &[0x01, 0x02, 0x02, 0x00, 0x04, 0x00],
+            ),
+            (
+                format(vertical, false, false),
+                &[0x60, 0xA0, 0xC0, 0xE0, 0xE0, 0xE0, 0xE0, 0xE0, 0xE0, 0x60],
+            ),
+            (
+                format(vertical, false, true),
+                &[0x80, 0x40, 0x20, 0, 0, 0, 0, 0, 0, 0x80],
+            ),
+            (
+                format(vertical, true, false),
+                &[0x06, 0x05, 0x03, 0x07, 0x07, 0x07, 0x07, 0x07, 0x07, 0x06],
+            ),
+            (
+                format(vertical, true, true),
+                &[0x01, 0x02, 0x04, 0, 0, 0, 0, 0, 0, 0x01],
+            ),
+        ];
+        for (format, data) in cases {
+            assert_eq!(format.len(10, 3), data.len());
+            let mut display = Display::<10, 3, false, 6, Color>::default();
+            assert!(display.from_image2lcd(data, format), "{format:?}");
+            assert_eq!(display.buffer(), native, "{format:?}");
+
+            let mut out = [0xFF; 10];
+            assert!(display.to_image2lcd(&mut out, format));
+            assert_eq!(&out[..data.len()], data, "{format:?}");
+        }
+
+        // a sheared picture is refused
+        let mut display = Display::<10, 3, false, 6, Color>::default();
+        assert!(!display.from_image2lcd(&[0; 5], Image2LcdFormat::default()));
+        assert!(!display.to_image2lcd(&mut [0; 5], Image2LcdFormat::default()));
+        assert_eq!(display.buffer(), [0; 6]);
+
+        // the variable sized display has the same layout
+        let mut buffer = [0u8; 6];
+        let mut display = VarDisplay::<Color>::new(10, 3, &mut buffer, false).unwrap();
+        let inverted = format(vertical, true, true);
+        assert!(display.from_image2lcd(&[0x01, 0x02, 0x04, 0, 0, 0, 0, 0, 0, 0x01], inverted));
+        assert_eq!(display.buffer(), native);
+    }
+
+    #[cfg(feature = "debug-tools")]
+    #[test]
+    fn diff_overlay_marks_changed_pixels() {
+        // 10 x 3 pixels, the last 6 bits of every row are padding
+        let old = [0xFF, 0xC0, 0x0F, 0x00, 0xAA, 0x80];
+        // (0, 0), (9, 0), (4, 1) and (1, 2) changed, the padding of each row too
+        let new = [0x7F, 0xBF, 0x07, 0x3F, 0xEA, 0x81];
+        let changed = [0x80, 0x40, 0x08, 0x00, 0x40, 0x00];
+
+        let mut display = Display::<10, 3, false, 12, TriColor>::default();
+        assert!(display.diff_overlay(&old, &new));
+        assert_eq!(display.chromatic_buffer(), changed);
+        // chromatic pixels are white in the b/w plane without the BWRBIT
+        let bw: [u8; 6] = core::array::from_fn(|i| new[i] | changed[i]);
+        assert_eq!(display.bw_buffer(), bw);
+        assert_eq!(display.get_pixel(0, 0), Some(TriColor::Chromatic));
+        assert_eq!(display.get_pixel(1, 0), Some(TriColor::White));
+        assert_eq!(display.get_pixel(8, 0), Some(TriColor::White));
+
+        let mut display = Display::<10, 3, true, 12, TriColor>::default();
+        assert!(display.diff_overlay(&old, &new));
+        assert_eq!(display.chromatic_buffer(), changed);
+        let bw: [u8; 6] = core::array::from_fn(|i| new[i] & !changed[i]);
+        assert_eq!(display.bw_buffer(), bw);
+        assert_eq!(display.get_pixel(4, 1), Some(TriColor::Chromatic));
+
+        // the same as drawing the chromatic pixels on the new frame
+        let mut drawn = Display::<10, 3, true, 12, TriColor>::default();
+        drawn
+            .bw_rows_mut()
+            .flatten()
+            .zip(new)
+            .for_each(|(bw, new)| *bw = new);
+        for (x, y) in [(0, 0), (9, 0), (4, 1), (1, 2)] {
+            drawn.set_pixel(Pixel(Point::new(x, y), TriColor::Chromatic));
         }
+        assert_eq!(display.buffer(), drawn.buffer());
+
+        // frames shorter than a plane are refused, 16 x 3 pixels without padding
+        let mut buffer = [0u8; 12];
+        let mut display = VarDisplay::<TriColor>::new(16, 3, &mut buffer, false).unwrap();
+        assert!(!display.diff_overlay(&old[..5], &new));
+        assert_eq!(display.buffer(), [0; 12]);
+        assert!(display.diff_overlay(&old, &new));
+        let changed: [u8; 6] = core::array::from_fn(|i| old[i] ^ new[i]);
+        assert_eq!(display.chromatic_buffer(), changed);
     }
 }
@@ -0,0 +1,339 @@
+//! 1 bit per pixel bitmaps of the icons, rows MSB first, see `RawBitImage`
+//!
+//! The battery is only the outline, the level is filled in when drawing.
+
+#[rustfmt::skip]
+pub(crate) const BATTERY_16: [u8; 32] = [
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0xFF, 0xFC,
+    0x80, 0x04,
+    0x80, 0x04,
+    0x80, 0x07,
+    0x80, 0x07,
+    0x80, 0x07,
+    0x80, 0x07,
+    0x80, 0x04,
+    0x80, 0x04,
+    0xFF, 0xFC,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const BATTERY_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0xFF, 0xFF, 0xF8,
+    0xFF, 0xFF, 0xF8,
+    0xC0, 0x00, 0x18,
+    0xC0, 0x00, 0x18,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x1F,
+    0xC0, 0x00, 0x18,
+    0xC0, 0x00, 0x18,
+    0xFF, 0xFF, 0xF8,
+    0xFF, 0xFF, 0xF8,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const BELL_16: [u8; 32] = [
+    0x01, 0x80,
+    0x07, 0xE0,
+    0x0F, 0xF0,
+    0x1F, 0xF8,
+    0x1F, 0xF8,
+    0x1F, 0xF8,
+    0x1F, 0xF8,
+    0x1F, 0xF8,
+    0x3F, 0xFC,
+    0x3F, 0xFC,
+    0x7F, 0xFE,
+    0xFF, 0xFF,
+    0x00, 0x00,
+    0x03, 0xC0,
+    0x01, 0x80,
+    0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const BELL_24: [u8; 72] = [
+    0x00, 0x18, 0x00,
+    0x00, 0x18, 0x00,
+    0x00, 0xFF, 0x00,
+    0x03, 0xFF, 0xC0,
+    0x07, 0xFF, 0xE0,
+    0x0F, 0xFF, 0xF0,
+    0x0F, 0xFF, 0xF0,
+    0x0F, 0xFF, 0xF0,
+    0x0F, 0xFF, 0xF0,
+    0x0F, 0xFF, 0xF0,
+    0x0F, 0xFF, 0xF0,
+    0x1F, 0xFF, 0xF8,
+    0x1F, 0xFF, 0xF8,
+    0x3F, 0xFF, 0xFC,
+    0x7F, 0xFF, 0xFE,
+    0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF,
+    0x00, 0x00, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x18, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const SYNC_16: [u8; 32] = [
+    0x00, 0x00,
+    0x00, 0x10,
+    0x00, 0x08,
+    0x00, 0x04,
+    0x7F, 0xFE,
+    0x00, 0x04,
+    0x00, 0x08,
+    0x00, 0x10,
+    0x08, 0x00,
+    0x10, 0x00,
+    0x20, 0x00,
+    0x7F, 0xFE,
+    0x20, 0x00,
+    0x10, 0x00,
+    0x08, 0x00,
+    0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const SYNC_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x60,
+    0x00, 0x00, 0x30,
+    0x00, 0x00, 0x18,
+    0x7F, 0xFF, 0xFC,
+    0x7F, 0xFF, 0xFE,
+    0x00, 0x00, 0x0C,
+    0x00, 0x00, 0x18,
+    0x00, 0x00, 0x30,
+    0x00, 0x00, 0x60,
+    0x06, 0x00, 0x00,
+    0x0C, 0x00, 0x00,
+    0x18, 0x00, 0x00,
+    0x30, 0x00, 0x00,
+    0x7F, 0xFF, 0xFE,
+    0x3F, 0xFF, 0xFE,
+    0x18, 0x00, 0x00,
+    0x0C, 0x00, 0x00,
+    0x06, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_OFF_16: [u8; 32] = [
+    0x00, 0x00,
+    0x6F, 0xF0,
+    0x77, 0xFC,
+    0x38, 0x0E,
+    0xDC, 0x03,
+    0x8E, 0xE1,
+    0x07, 0x70,
+    0x1B, 0xB8,
+    0x31, 0xCC,
+    0x02, 0xE0,
+    0x07, 0x70,
+    0x0C, 0x38,
+    0x00, 0x1C,
+    0x01, 0x8E,
+    0x03, 0xC6,
+    0x01, 0x80,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_OFF_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x60, 0x00, 0x00,
+    0x70, 0xFF, 0x00,
+    0x39, 0xFF, 0xE0,
+    0x1C, 0x00, 0xF8,
+    0x4E, 0x00, 0x1E,
+    0xE7, 0x00, 0x07,
+    0x83, 0x9F, 0x01,
+    0x01, 0xC3, 0xE0,
+    0x0C, 0xE0, 0x70,
+    0x18, 0x70, 0x18,
+    0x00, 0x38, 0x00,
+    0x00, 0x1C, 0x00,
+    0x00, 0xCE, 0x00,
+    0x01, 0xC7, 0x00,
+    0x03, 0x03, 0x80,
+    0x00, 0x01, 0xC0,
+    0x00, 0x00, 0xE0,
+    0x00, 0x3C, 0x70,
+    0x00, 0x7E, 0x38,
+    0x00, 0x7E, 0x1C,
+    0x00, 0x3C, 0x0E,
+    0x00, 0x00, 0x06,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_LOW_16: [u8; 32] = [
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x03, 0xC0,
+    0x07, 0xE0,
+    0x0C, 0x30,
+    0x00, 0x00,
+    0x01, 0x80,
+    0x03, 0xC0,
+    0x01, 0x80,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_LOW_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0xFF, 0x00,
+    0x01, 0xC3, 0x80,
+    0x03, 0x00, 0xC0,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_MEDIUM_16: [u8; 32] = [
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x00, 0x00,
+    0x07, 0xE0,
+    0x0F, 0xF0,
+    0x1C, 0x38,
+    0x30, 0x0C,
+    0x03, 0xC0,
+    0x07, 0xE0,
+    0x0C, 0x30,
+    0x00, 0x00,
+    0x01, 0x80,
+    0x03, 0xC0,
+    0x01, 0x80,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_MEDIUM_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0xFF, 0x00,
+    0x07, 0xC3, 0xE0,
+    0x0E, 0x00, 0x70,
+    0x18, 0x00, 0x18,
+    0x00, 0x00, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0xFF, 0x00,
+    0x01, 0xC3, 0x80,
+    0x03, 0x00, 0xC0,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_HIGH_16: [u8; 32] = [
+    0x00, 0x00,
+    0x0F, 0xF0,
+    0x3F, 0xFC,
+    0x70, 0x0E,
+    0xC0, 0x03,
+    0x87, 0xE1,
+    0x0F, 0xF0,
+    0x1C, 0x38,
+    0x30, 0x0C,
+    0x03, 0xC0,
+    0x07, 0xE0,
+    0x0C, 0x30,
+    0x00, 0x00,
+    0x01, 0x80,
+    0x03, 0xC0,
+    0x01, 0x80,
+];
+
+#[rustfmt::skip]
+pub(crate) const WIFI_HIGH_24: [u8; 72] = [
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0xFF, 0x00,
+    0x07, 0xFF, 0xE0,
+    0x1F, 0x00, 0xF8,
+    0x78, 0x00, 0x1E,
+    0xE0, 0x00, 0x07,
+    0x80, 0xFF, 0x01,
+    0x07, 0xC3, 0xE0,
+    0x0E, 0x00, 0x70,
+    0x18, 0x00, 0x18,
+    0x00, 0x00, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0xFF, 0x00,
+    0x01, 0xC3, 0x80,
+    0x03, 0x00, 0xC0,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x7E, 0x00,
+    0x00, 0x3C, 0x00,
+    0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00,
+];
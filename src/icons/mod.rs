@@ -0,0 +1,327 @@
+//! Built-in status icons (battery, Wi-Fi, bell and sync) in 16x16 and 24x24 pixels
+//!
+//! The icons are 1 bit per pixel bitmaps, so the whole set takes less than 1 KiB
+//! of flash. They are drawn with [`RawBitImage`] onto any [`DrawTarget`], only
+//! the set pixels are drawn so the background shows through.
+//!
+//! ```rust
+//! use embedded_graphics_core::prelude::*;
+//! use epd_waveshare::{color::Color, epd4in2::Display4in2, icons::*};
+//!
+//! let mut display = Display4in2::default();
+//! draw_icon(&mut display, Icon::BatteryLevel(80), Point::new(0, 0), Color::Black).unwrap();
+//! draw_icon_sized(&mut display, Icon::WifiHigh, IconSize::Large, Point::new(20, 0), Color::Black)
+//!     .unwrap();
+//! ```
+
+mod bitmaps;
+use self::bitmaps::*;
+
+use crate::graphics::RawBitImage;
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+/// Size of the icons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IconSize {
+    /// 16x16 pixels
+    #[default]
+    Small,
+    /// 24x24 pixels
+    Large,
+}
+
+impl IconSize {
+    /// Width and height in pixels
+    pub const fn pixels(self) -> u32 {
+        match self {
+            IconSize::Small => 16,
+            IconSize::Large => 24,
+        }
+    }
+}
+
+/// The available icons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    /// Battery filled according to its charge in percent, values above 100 are shown as full
+    BatteryLevel(u8),
+    /// Bell, e.g. for alarms or notifications
+    Bell,
+    /// Arrows in both directions
+    Sync,
+    /// Wi-Fi with a slash through it
+    WifiOff,
+    /// Wi-Fi with one bar
+    WifiLow,
+    /// Wi-Fi with two bars
+    WifiMedium,
+    /// Wi-Fi with three bars
+    WifiHigh,
+}
+
+impl Icon {
+    /// Bitmap of the icon, only the outline for the battery
+    fn bitmap(self, size: IconSize) -> &'static [u8] {
+        match (self, size) {
+            (Icon::BatteryLevel(_), IconSize::Small) => &BATTERY_16,
+            (Icon::BatteryLevel(_), IconSize::Large) => &BATTERY_24,
+            (Icon::Bell, IconSize::Small) => &BELL_16,
+            (Icon::Bell, IconSize::Large) => &BELL_24,
+            (Icon::Sync, IconSize::Small) => &SYNC_16,
+            (Icon::Sync, IconSize::Large) => &SYNC_24,
+            (Icon::WifiOff, IconSize::Small) => &WIFI_OFF_16,
+            (Icon::WifiOff, IconSize::Large) => &WIFI_OFF_24,
+            (Icon::WifiLow, IconSize::Small) => &WIFI_LOW_16,
+            (Icon::WifiLow, IconSize::Large) => &WIFI_LOW_24,
+            (Icon::WifiMedium, IconSize::Small) => &WIFI_MEDIUM_16,
+            (Icon::WifiMedium, IconSize::Large) => &WIFI_MEDIUM_24,
+            (Icon::WifiHigh, IconSize::Small) => &WIFI_HIGH_16,
+            (Icon::WifiHigh, IconSize::Large) => &WIFI_HIGH_24,
+        }
+    }
+}
+
+/// Area inside the battery outline which is filled for a full battery
+const fn battery_fill(size: IconSize) -> Rectangle {
+    match size {
+        IconSize::Small => Rectangle::new(Point::new(2, 5), Size::new(10, 6)),
+        IconSize::Large => Rectangle::new(Point::new(3, 8), Size::new(15, 8)),
+    }
+}
+
+/// Draw a 16x16 icon with its top left corner at `top_left`
+pub fn draw_icon<D, C>(
+    display: &mut D,
+    icon: Icon,
+    top_left: Point,
+    color: C,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = C>,
+    C: PixelColor,
+{
+    draw_icon_sized(display, icon, IconSize::Small, top_left, color)
+}
+
+/// Draw an icon of the given size with its top left corner at `top_left`
+pub fn draw_icon_sized<D, C>(
+    display: &mut D,
+    icon: Icon,
+    size: IconSize,
+    top_left: Point,
+    color: C,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = C>,
+    C: PixelColor,
+{
+    let image = RawBitImage::new(icon.bitmap(size), size.pixels());
+    display.draw_iter(image.pixels(top_left, color))?;
+
+    if let Icon::BatteryLevel(level) = icon {
+        let full = battery_fill(size);
+        // rounded to the nearest pixel
+        let width = (full.size.width * u32::from(level.min(100)) + 50) / 100;
+        let area = Rectangle::new(top_left + full.top_left, Size::new(width, full.size.height));
+        display.fill_solid(&area, color)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_len;
+    use crate::color::Color;
+    use crate::graphics::{Display, DisplayRotation};
+
+    type Display24 = Display<24, 24, false, { buffer_len(24, 24) }, Color>;
+
+    fn white_display() -> Display24 {
+        let mut display = Display24::default();
+        display.clear(Color::White).unwrap();
+        display
+    }
+
+    /// Compares the first `art` rows of the display with the picture, `#` is black
+    fn assert_art(display: &Display24, art: &str) {
+        for (y, row) in art
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .enumerate()
+        {
+            for (x, pixel) in row.chars().enumerate() {
+                let byte = display.buffer()[y * 3 + x / 8];
+                let black = byte & (0x80 >> (x % 8)) == 0;
+                assert_eq!(black, pixel == '#', "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn bell_small() {
+        let mut display = white_display();
+        draw_icon(&mut display, Icon::Bell, Point::zero(), Color::Black).unwrap();
+        assert_art(
+            &display,
+            "
+            .......##...............
+            .....######.............
+            ....########............
+            ...##########...........
+            ...##########...........
+            ...##########...........
+            ...##########...........
+            ...##########...........
+            ..############..........
+            ..############..........
+            .##############.........
+            ################........
+            ........................
+            ......####..............
+            .......##...............
+            ........................
+            ",
+        );
+    }
+
+    #[test]
+    fn wifi_high_large() {
+        let mut display = white_display();
+        draw_icon_sized(
+            &mut display,
+            Icon::WifiHigh,
+            IconSize::Large,
+            Point::zero(),
+            Color::Black,
+        )
+        .unwrap();
+        assert_art(
+            &display,
+            "
+            ........................
+            ........................
+            ........########........
+            .....##############.....
+            ...#####........#####...
+            .####..............####.
+            ###..................###
+            #.......########.......#
+            .....#####....#####.....
+            ....###..........###....
+            ...##..............##...
+            ........................
+            .........######.........
+            ........########........
+            .......###....###.......
+            ......##........##......
+            ........................
+            ........................
+            ..........####..........
+            .........######.........
+            .........######.........
+            ..........####..........
+            ........................
+            ........................
+            ",
+        );
+    }
+
+    #[test]
+    fn battery_fills_proportionally() {
+        let mut display = white_display();
+        draw_icon(
+            &mut display,
+            Icon::BatteryLevel(50),
+            Point::zero(),
+            Color::Black,
+        )
+        .unwrap();
+        assert_art(
+            &display,
+            "
+            ................
+            ................
+            ................
+            ##############..
+            #............#..
+            #.#####......#..
+            #.#####......###
+            #.#####......###
+            #.#####......###
+            #.#####......###
+            #.#####......#..
+            #............#..
+            ##############..
+            ................
+            ",
+        );
+
+        let mut display = white_display();
+        draw_icon_sized(
+            &mut display,
+            Icon::BatteryLevel(200),
+            IconSize::Large,
+            Point::zero(),
+            Color::Black,
+        )
+        .unwrap();
+        assert_art(
+            &display,
+            "
+            ........................
+            ........................
+            ........................
+            ........................
+            ........................
+            #####################...
+            #####################...
+            ##.................##...
+            ##.###############.##...
+            ##.###############.#####
+            ##.###############.#####
+            ##.###############.#####
+            ##.###############.#####
+            ##.###############.#####
+            ##.###############.#####
+            ##.###############.##...
+            ##.................##...
+            #####################...
+            #####################...
+            ........................
+            ",
+        );
+    }
+
+    #[test]
+    fn icons_are_rotated_and_clipped() {
+        // drawn half outside of the top left corner, the bottom right quarter remains
+        let mut display = white_display();
+        draw_icon(&mut display, Icon::Bell, Point::new(-8, -8), Color::Black).unwrap();
+        assert_art(
+            &display,
+            "
+            ######..................
+            ######..................
+            #######.................
+            ########................
+            ........................
+            ##......................
+            #.......................
+            ........................
+            ",
+        );
+
+        // upside down in the bottom right corner
+        let mut display = white_display();
+        display.set_rotation(DisplayRotation::Rotate180);
+        draw_icon(&mut display, Icon::Bell, Point::zero(), Color::Black).unwrap();
+        let buffer = display.buffer();
+        // the clapper is above the rim now
+        assert_eq!(buffer[9 * 3 + 1], 0xFE);
+        assert_eq!(buffer[9 * 3 + 2], 0x7F);
+        assert_eq!(buffer[12 * 3 + 1], 0);
+        assert_eq!(buffer[12 * 3 + 2], 0);
+    }
+}
@@ -0,0 +1,388 @@
+//! Drawing RGB images, e.g. from `tinybmp` or `tinytga`, onto b/w and tri-color displays
+//!
+//! The image crates decode to RGB colors, which don't convert to [Color] or [TriColor].
+//! [QuantizedImage] wraps any [ImageDrawable] with [Rgb888] or [Rgb565] pixels and maps
+//! them with a [Quantizer]: saturated pixels with a hue in the chromatic window become
+//! [TriColor::Chromatic], all others black or white by their luminance, optionally with
+//! ordered dithering. Needs the `images` feature.
+//!
+//! [Rgb565]: embedded_graphics_core::pixelcolor::Rgb565
+//!
+//! ```rust
+//! use embedded_graphics::{image::{Image, ImageRaw}, pixelcolor::Rgb888, prelude::*};
+//! use epd_waveshare::{color::TriColor, epd2in13bc::Display2in13bc, images::*};
+//!
+//! // 2x2 pixels: red, dark gray, light gray and white, e.g. a `tinybmp::Bmp<Rgb888>`
+//! let data = [255, 0, 0, 64, 64, 64, 200, 200, 200, 255, 255, 255];
+//! let raw = ImageRaw::<Rgb888>::new(&data, 2);
+//!
+//! let mut display = Display2in13bc::default();
+//! let image = QuantizedImage::<_, TriColor>::new(&raw, Quantizer::default());
+//! Image::new(&image, Point::new(10, 10)).draw(&mut display).unwrap();
+//! assert_eq!(display.get_pixel(10, 10), Some(TriColor::Chromatic));
+//! assert_eq!(display.get_pixel(11, 10), Some(TriColor::Black));
+//! assert_eq!(display.get_pixel(10, 11), Some(TriColor::White));
+//! ```
+
+use crate::color::{Color, TriColor};
+use core::marker::PhantomData;
+use embedded_graphics_core::{
+    image::ImageDrawable,
+    pixelcolor::{Rgb888, RgbColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+/// 4x4 Bayer matrix of the ordered dithering
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Maps RGB pixels to black, white and chromatic
+///
+/// A pixel is chromatic if its saturation and brightness (HSV) reach the minimums and its
+/// hue lies in the chromatic window, by default the reds of the usual red panels. All
+/// other pixels are black if their luminance is below the threshold, white otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quantizer {
+    threshold: u8,
+    hue: (u16, u16),
+    min_saturation: u8,
+    min_brightness: u8,
+    dithering: bool,
+}
+
+impl Default for Quantizer {
+    /// Threshold at half the luminance, reds from 330° to 30° with at least 50 %
+    /// saturation and 40 % brightness, no dithering
+    fn default() -> Self {
+        Self {
+            threshold: 128,
+            hue: (330, 30),
+            min_saturation: 128,
+            min_brightness: 102,
+            dithering: false,
+        }
+    }
+}
+
+impl Quantizer {
+    /// Pixels with a luminance below `threshold` are black
+    pub fn with_threshold(mut self, threshold: u8) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Hues from `start` to `end` in degrees can be chromatic, the window wraps around at
+    /// 360°, e.g. 330 to 30 for red or 40 to 70 for yellow
+    pub fn with_chromatic_hue(mut self, start: u16, end: u16) -> Self {
+        self.hue = (start % 360, end % 360);
+        self
+    }
+
+    /// Minimum saturation of chromatic pixels, 0 to 255
+    pub fn with_min_saturation(mut self, saturation: u8) -> Self {
+        self.min_saturation = saturation;
+        self
+    }
+
+    /// Minimum brightness (the largest channel) of chromatic pixels, darker ones are
+    /// black or white like unsaturated ones
+    pub fn with_min_brightness(mut self, brightness: u8) -> Self {
+        self.min_brightness = brightness;
+        self
+    }
+
+    /// Dithers black and white with a 4x4 Bayer matrix instead of a hard threshold
+    pub fn with_dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// The black or white of `color` at `point`, which only matters for dithering
+    pub fn color(&self, color: Rgb888, point: Point) -> Color {
+        let (r, g, b) = (
+            u32::from(color.r()),
+            u32::from(color.g()),
+            u32::from(color.b()),
+        );
+        let luminance = ((77 * r + 150 * g + 29 * b) >> 8) as i32;
+        let mut threshold = i32::from(self.threshold);
+        if self.dithering {
+            let level = BAYER[point.y.rem_euclid(4) as usize][point.x.rem_euclid(4) as usize];
+            threshold += i32::from(level) * 16 + 8 - 128;
+        }
+        if luminance < threshold {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    /// The black, white or chromatic of `color` at `point`
+    pub fn tri_color(&self, color: Rgb888, point: Point) -> TriColor {
+        if self.is_chromatic(color) {
+            return TriColor::Chromatic;
+        }
+        match self.color(color, point) {
+            Color::Black => TriColor::Black,
+            Color::White => TriColor::White,
+        }
+    }
+
+    fn is_chromatic(&self, color: Rgb888) -> bool {
+        let (r, g, b) = (
+            i32::from(color.r()),
+            i32::from(color.g()),
+            i32::from(color.b()),
+        );
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        if max == 0 || max < i32::from(self.min_brightness) {
+            return false;
+        }
+        if delta * 255 / max < i32::from(self.min_saturation) || delta == 0 {
+            return false;
+        }
+        let hue = if max == r {
+            60 * (g - b) / delta
+        } else if max == g {
+            120 + 60 * (b - r) / delta
+        } else {
+            240 + 60 * (r - g) / delta
+        }
+        .rem_euclid(360) as u16;
+        let (start, end) = self.hue;
+        if start <= end {
+            (start..=end).contains(&hue)
+        } else {
+            hue >= start || hue <= end
+        }
+    }
+}
+
+/// Display colors an RGB pixel can be quantized to, [Color] and [TriColor]
+pub trait Quantize: PixelColor {
+    /// `color` at `point` mapped by `quantizer`
+    fn quantize(quantizer: &Quantizer, color: Rgb888, point: Point) -> Self;
+}
+
+impl Quantize for Color {
+    fn quantize(quantizer: &Quantizer, color: Rgb888, point: Point) -> Self {
+        quantizer.color(color, point)
+    }
+}
+
+impl Quantize for TriColor {
+    fn quantize(quantizer: &Quantizer, color: Rgb888, point: Point) -> Self {
+        quantizer.tri_color(color, point)
+    }
+}
+
+/// An RGB image drawn in the colors `C` of a display, see the [module](self) documentation
+///
+/// Wraps any [ImageDrawable] with pixels converting to [Rgb888], e.g. `Rgb565` ones, and
+/// is drawn with an `Image` of embedded-graphics like the wrapped image. The dithering
+/// pattern follows the display coordinates.
+pub struct QuantizedImage<'a, I, C> {
+    image: &'a I,
+    quantizer: Quantizer,
+    _color: PhantomData<C>,
+}
+
+impl<'a, I, C> QuantizedImage<'a, I, C>
+where
+    I: ImageDrawable,
+    I::Color: Into<Rgb888>,
+    C: Quantize,
+{
+    /// Wraps `image`, whose pixels are mapped with `quantizer`
+    pub fn new(image: &'a I, quantizer: Quantizer) -> Self {
+        Self {
+            image,
+            quantizer,
+            _color: PhantomData,
+        }
+    }
+}
+
+impl<I, C> OriginDimensions for QuantizedImage<'_, I, C>
+where
+    I: ImageDrawable,
+{
+    fn size(&self) -> Size {
+        self.image.size()
+    }
+}
+
+impl<I, C> ImageDrawable for QuantizedImage<'_, I, C>
+where
+    I: ImageDrawable,
+    I::Color: Into<Rgb888>,
+    C: Quantize,
+{
+    type Color = C;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.image.draw(&mut Quantizing {
+            target,
+            quantizer: &self.quantizer,
+            _color: PhantomData,
+        })
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.image.draw_sub_image(
+            &mut Quantizing {
+                target,
+                quantizer: &self.quantizer,
+                _color: PhantomData,
+            },
+            area,
+        )
+    }
+}
+
+/// Draw target in the colors `S` of the wrapped image, drawing into `target`
+struct Quantizing<'a, D, S> {
+    target: &'a mut D,
+    quantizer: &'a Quantizer,
+    _color: PhantomData<S>,
+}
+
+impl<D, S> OriginDimensions for Quantizing<'_, D, S>
+where
+    D: DrawTarget,
+{
+    fn size(&self) -> Size {
+        self.target.bounding_box().size
+    }
+}
+
+impl<D, S> DrawTarget for Quantizing<'_, D, S>
+where
+    D: DrawTarget,
+    D::Color: Quantize,
+    S: PixelColor + Into<Rgb888>,
+{
+    type Color = S;
+    type Error = D::Error;
+
+    fn draw_iter<P>(&mut self, pixels: P) -> Result<(), Self::Error>
+    where
+        P: IntoIterator<Item = Pixel<S>>,
+    {
+        let quantizer = self.quantizer;
+        self.target
+            .draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+                Pixel(point, D::Color::quantize(quantizer, color.into(), point))
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizer_maps_synthetic_pixels() {
+        let quantizer = Quantizer::default();
+        let tri = |r, g, b| quantizer.tri_color(Rgb888::new(r, g, b), Point::zero());
+        let bw = |r, g, b| quantizer.color(Rgb888::new(r, g, b), Point::zero());
+
+        // pure red is chromatic, on a b/w display it is dark enough for black
+        assert_eq!(tri(255, 0, 0), TriColor::Chromatic);
+        assert_eq!(bw(255, 0, 0), Color::Black);
+        // a bit off is still red, also across 0°
+        assert_eq!(tri(220, 40, 30), TriColor::Chromatic);
+        assert_eq!(tri(220, 30, 60), TriColor::Chromatic);
+        // dark red is too dark for the red pigment
+        assert_eq!(tri(80, 0, 0), TriColor::Black);
+        // light gray has no saturation
+        assert_eq!(tri(192, 192, 192), TriColor::White);
+        assert_eq!(bw(192, 192, 192), Color::White);
+        assert_eq!(tri(100, 100, 100), TriColor::Black);
+        // other hues aren't chromatic
+        assert_eq!(tri(0, 0, 255), TriColor::Black);
+        assert_eq!(tri(255, 255, 0), TriColor::White);
+
+        // configurable
+        let yellow = Quantizer::default().with_chromatic_hue(40, 70);
+        assert_eq!(
+            yellow.tri_color(Rgb888::new(255, 255, 0), Point::zero()),
+            TriColor::Chromatic
+        );
+        assert_eq!(
+            yellow.tri_color(Rgb888::new(255, 0, 0), Point::zero()),
+            TriColor::Black
+        );
+        let dark = Quantizer::default()
+            .with_min_brightness(64)
+            .with_min_saturation(200)
+            .with_threshold(60);
+        assert_eq!(
+            dark.tri_color(Rgb888::new(80, 0, 0), Point::zero()),
+            TriColor::Chromatic
+        );
+        assert_eq!(
+            dark.tri_color(Rgb888::new(100, 100, 100), Point::zero()),
+            TriColor::White
+        );
+    }
+
+    #[test]
+    fn dithering_follows_luminance() {
+        let quantizer = Quantizer::default().with_dithering(true);
+        let white_pixels = |gray: u8| {
+            (0..4)
+                .flat_map(|y| (0..4).map(move |x| Point::new(x, y)))
+                .filter(|&point| {
+                    quantizer.color(Rgb888::new(gray, gray, gray), point) == Color::White
+                })
+                .count()
+        };
+        assert_eq!(white_pixels(0), 0);
+        assert_eq!(white_pixels(64), 4);
+        assert_eq!(white_pixels(128), 8);
+        assert_eq!(white_pixels(255), 16);
+        // gray doesn't become chromatic
+        assert_eq!(
+            quantizer.tri_color(Rgb888::new(128, 128, 128), Point::new(0, 0)),
+            TriColor::White
+        );
+    }
+
+    #[test]
+    fn draws_rgb565_images() {
+        use crate::graphics::Display;
+        use embedded_graphics::{
+            image::{Image, ImageRaw},
+            pixelcolor::Rgb565,
+        };
+
+        // red, white, black and dark gray, big endian
+        let data = [0xF8, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x42, 0x08];
+        let raw = ImageRaw::<Rgb565>::new(&data, 2);
+
+        let mut display = Display::<16, 4, false, 8, Color>::default();
+        let image = QuantizedImage::<_, Color>::new(&raw, Quantizer::default());
+        Image::new(&image, Point::new(3, 1))
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.get_pixel(3, 1), Some(Color::Black));
+        assert_eq!(display.get_pixel(4, 1), Some(Color::White));
+        assert_eq!(display.get_pixel(3, 2), Some(Color::Black));
+        assert_eq!(display.get_pixel(4, 2), Some(Color::Black));
+
+        let mut tri = Display::<16, 4, false, 16, TriColor>::default();
+        tri.clear(TriColor::White).unwrap();
+        let image = QuantizedImage::<_, TriColor>::new(&raw, Quantizer::default());
+        Image::new(&image, Point::new(3, 1)).draw(&mut tri).unwrap();
+        assert_eq!(tri.get_pixel(3, 1), Some(TriColor::Chromatic));
+        assert_eq!(tri.get_pixel(4, 1), Some(TriColor::White));
+    }
+}
@@ -1,12 +1,65 @@
-use crate::traits::Command;
+use crate::busy::{
+    BusyCheck, BusyMode, BusyPolarity, BusyTimings, Operation, RefreshLimits, RefreshReport,
+    SelfTestReport, WaitOutcome, SELF_TEST_ASSERT_US, SELF_TEST_POLL_US, SELF_TEST_TIMEOUT_US,
+};
+#[cfg(feature = "checksum")]
+use crate::checksum::TransferChecksum;
+#[cfg(feature = "checksum")]
+use crate::color::Plane;
+use crate::error::{self, Context, Error};
+use crate::frame::FrameData;
+use crate::policy::{RefreshCounters, RefreshKind};
+use crate::reset::ResetConfig;
+use crate::traits::{Command, DataLen};
+use crate::update_sequence::UpdateSequence;
+use crate::voltage::DrivingVoltages;
 use core::marker::PhantomData;
-use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+use core::ops::Range;
+use embedded_hal::{
+    delay::*,
+    digital::*,
+    spi::{Operation as SpiOperation, SpiDevice},
+};
+
+/// Longest time the SSD16xx controllers take for a software reset
+#[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+const SW_RESET_US: u32 = 10_000;
+
+/// Pause before a write is retried, see [DisplayInterface::set_retry]
+const RETRY_PAUSE_NS: u32 = 100_000;
+
+/// Steps of a cancellable wait with [BusyMode::FixedDelay], `cancel` is asked before each
+const CANCEL_STEP_US: u32 = 1_000;
+
+/// Writes of one spi transaction of [DisplayInterface::data_vectored], more parts take
+/// another CS assertion
+#[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+const VECTORED_WRITES: usize = 32;
+
+/// Bytes of data combined into one write with the `write-combining` feature, see
+/// [DisplayInterface::data]
+#[cfg(feature = "write-combining")]
+pub(crate) const WRITE_COMBINE_LEN: usize = 64;
+/// Without the `write-combining` feature the data is written as the drivers pass it
+#[cfg(not(feature = "write-combining"))]
+pub(crate) const WRITE_COMBINE_LEN: usize = 0;
 
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
 /// SINGLE_BYTE_WRITE defines if a data block is written bytewise
 /// or blockwise to the spi device
-pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool> {
+///
+/// COMBINE_LEN is the size of the staging buffer of the write combining, 0 (the
+/// default without the `write-combining` feature) writes the data as it comes.
+pub struct DisplayInterface<
+    SPI,
+    BUSY,
+    DC,
+    RST,
+    DELAY,
+    const SINGLE_BYTE_WRITE: bool,
+    const COMBINE_LEN: usize = { WRITE_COMBINE_LEN },
+> {
     /// SPI
     _spi: PhantomData<SPI>,
     /// DELAY
@@ -19,10 +72,61 @@ pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_
     rst: RST,
     /// number of ms the idle loop should sleep on
     delay_us: u32,
+    /// CRC-16 over the data sent since the last command and per plane
+    #[cfg(feature = "checksum")]
+    checksum: TransferChecksum,
+    /// Set when a spi write failed, cleared by a reset
+    poisoned: bool,
+    /// Largest number of bytes written to the spi device at once
+    max_transfer_size: Option<usize>,
+    /// Further attempts of a failed command write, see [DisplayInterface::set_retry]
+    retry_attempts: u8,
+    /// Whether to poll the busy pin or wait fixed times
+    busy_mode: BusyMode,
+    /// Worst case times for [BusyMode::FixedDelay]
+    busy_timings: BusyTimings,
+    /// Last started operation, decides how long the next wait takes with [BusyMode::FixedDelay]
+    operation: Option<Operation>,
+    /// VCOM register value overriding the default of the driver, see [crate::vcom]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    vcom: Option<u8>,
+    /// Consecutive polls reading idle before a wait returns
+    idle_polls: u8,
+    /// Polls reading busy since [DisplayInterface::reset_busy_stats]
+    busy_polls: u32,
+    /// Idle readings followed by a busy one within a wait since the last reset
+    glitches: u32,
+    /// Ticks per second of the refresh guard, 0 while it is disabled
+    guard_ticks_per_second: u32,
+    /// Last time passed to [DisplayInterface::note_time]
+    now_ticks: u64,
+    /// Time of the last refresh passing the guard
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    last_refresh_ticks: Option<u64>,
+    /// Lets the next refreshes pass the guard
+    refresh_forced: bool,
+    /// Refreshes started, see [DisplayInterface::count_refresh]
+    counters: RefreshCounters,
+    /// Operation and instance id attached to failed transfers
+    context: Context,
+    /// Whether the busy pin signals busy with the opposite level than the driver expects
+    busy_inverted: bool,
+    /// Reset sequence overriding the default of the driver, see [crate::reset]
+    reset_config: Option<ResetConfig>,
+    /// Display update sequence overriding the ones of the driver, see [crate::update_sequence]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    update_sequence: Option<UpdateSequence>,
+    /// Driving voltages overriding the defaults of the driver, see [crate::voltage]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    driving_voltages: Option<DrivingVoltages>,
+    /// Data bytes waiting to be written together, see [DisplayInterface::data]
+    staging: [u8; COMBINE_LEN],
+    /// Number of bytes in `staging`
+    staged: usize,
 }
 
-impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>
-    DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>
+impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool, const COMBINE_LEN: usize>
+    DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE, COMBINE_LEN>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
@@ -43,15 +147,269 @@ where
             dc,
             rst,
             delay_us,
+            #[cfg(feature = "checksum")]
+            checksum: TransferChecksum::new(),
+            poisoned: false,
+            max_transfer_size: None,
+            retry_attempts: 0,
+            busy_mode: BusyMode::Pin,
+            busy_timings: BusyTimings::default(),
+            operation: None,
+            vcom: None,
+            idle_polls: 1,
+            busy_polls: 0,
+            glitches: 0,
+            guard_ticks_per_second: 0,
+            now_ticks: 0,
+            last_refresh_ticks: None,
+            refresh_forced: false,
+            counters: RefreshCounters::default(),
+            // the drivers initialise the display right after creating the interface
+            context: Context::new(error::Operation::Init),
+            busy_inverted: false,
+            reset_config: None,
+            update_sequence: None,
+            driving_voltages: None,
+            staging: [0; COMBINE_LEN],
+            staged: 0,
+        }
+    }
+
+    /// Sends a command (with its parameters) up to `attempts` more times when a write
+    /// fails, 0 (the default) gives up at the first error
+    ///
+    /// The retries are meant for a bus shared with other devices, where a write
+    /// occasionally fails. Every attempt is a transaction of its own, so the spi device
+    /// deasserts CS in between, after a pause of [RETRY_PAUSE_NS]. Resending the
+    /// command restarts the parameter list in the controller.
+    ///
+    /// Data of a variable length, i.e. frames written into the RAM of the controller,
+    /// is never retried: the controller already moved its address counter for the
+    /// bytes it received. A failure there poisons the interface like without retries.
+    pub(crate) fn set_retry(&mut self, attempts: u8) {
+        self.retry_attempts = attempts;
+    }
+
+    /// Overrides the display update sequences of the driver, `None` restores them
+    ///
+    /// Returns [Error::InvalidUpdateSequence] for sequences the controller can't run.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_update_sequence(
+        &mut self,
+        sequence: Option<UpdateSequence>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if sequence.is_some_and(|sequence| !sequence.is_valid()) {
+            return Err(Error::InvalidUpdateSequence);
+        }
+        self.update_sequence = sequence;
+        Ok(())
+    }
+
+    /// Value of `DisplayUpdateControl2` for a refresh, `default` unless overridden
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn update_sequence_or(&self, default: u8) -> u8 {
+        self.update_sequence.map_or(default, UpdateSequence::bits)
+    }
+
+    /// Limits the number of bytes written to the spi device in one go
+    ///
+    /// Longer data is split into several writes. `None` (the default) leaves the
+    /// data in one piece, apart from the 4096 byte limit always applied on linux.
+    pub(crate) fn set_max_transfer_size(&mut self, max_transfer_size: Option<usize>) {
+        // a limit of 0 would never make progress
+        self.max_transfer_size = max_transfer_size.map(|size| size.max(1));
+    }
+
+    /// Selects how to wait for the controller, `timings` are used for [BusyMode::FixedDelay]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_busy_mode(&mut self, busy_mode: BusyMode, timings: BusyTimings) {
+        self.busy_mode = busy_mode;
+        self.busy_timings = timings;
+    }
+
+    /// Current busy mode
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn busy_mode(&self) -> BusyMode {
+        self.busy_mode
+    }
+
+    /// Uses `polarity` for the busy pin of a driver expecting busy low if `is_busy_low`
+    pub(crate) fn set_busy_polarity(&mut self, polarity: BusyPolarity, is_busy_low: bool) {
+        self.busy_inverted = polarity.is_busy_low() != is_busy_low;
+    }
+
+    /// Polarity of the busy pin used by a driver expecting busy low if `is_busy_low`
+    pub(crate) fn busy_polarity(&self, is_busy_low: bool) -> BusyPolarity {
+        BusyPolarity::from_busy_low(is_busy_low != self.busy_inverted)
+    }
+
+    /// Overrides the reset sequence of the driver
+    pub(crate) fn set_reset_config(&mut self, config: Option<ResetConfig>) {
+        self.reset_config = config;
+    }
+
+    /// Reset sequence to use, `default` unless overridden
+    pub(crate) fn reset_config_or(&self, default: ResetConfig) -> ResetConfig {
+        self.reset_config.unwrap_or(default)
+    }
+
+    /// Overrides the driving voltages the driver sends in its init
+    ///
+    /// The drivers check the voltages of their controller family beforehand.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_driving_voltages(&mut self, voltages: Option<DrivingVoltages>) {
+        self.driving_voltages = voltages;
+    }
+
+    /// Parameters of `GateDrivingVoltage` and `SourceDrivingVoltage` of the SSD16xx
+    /// controllers, `default` unless overridden
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn ssd16xx_voltages_or(&self, default: [u8; 4]) -> [u8; 4] {
+        self.driving_voltages
+            .and_then(|voltages| voltages.ssd16xx.registers().ok())
+            .unwrap_or(default)
+    }
+
+    /// Parameters of `PowerSetting` of the UC81xx controllers, `default` unless overridden
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn uc81xx_power_setting_or(&self, default: [u8; 5]) -> [u8; 5] {
+        self.driving_voltages
+            .and_then(|voltages| voltages.uc81xx.registers().ok())
+            .unwrap_or(default)
+    }
+
+    /// Overrides the VCOM register value the driver sends in its init
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_vcom(&mut self, register: Option<u8>) {
+        self.vcom = register;
+    }
+
+    /// VCOM register value to send, `default` unless overridden
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn vcom_or(&self, default: u8) -> u8 {
+        self.vcom.unwrap_or(default)
+    }
+
+    /// Number of consecutive polls which need to read idle before a wait returns
+    ///
+    /// 1 (the default) returns at the first idle reading. The polls are `delay_us`
+    /// apart, 0 is treated as 1.
+    pub(crate) fn set_idle_debounce(&mut self, polls: u8) {
+        self.idle_polls = polls.max(1);
+    }
+
+    /// Enables the refresh guard with the time in `ticks_per_second`, 0 disables it
+    pub(crate) fn set_refresh_guard(&mut self, ticks_per_second: u32) {
+        self.guard_ticks_per_second = ticks_per_second;
+    }
+
+    /// Current time for the refresh guard
+    pub(crate) fn note_time(&mut self, now_ticks: u64) {
+        self.now_ticks = now_ticks;
+    }
+
+    /// Lets refreshes pass the guard while set
+    pub(crate) fn set_refresh_forced(&mut self, forced: bool) {
+        self.refresh_forced = forced;
+    }
+
+    /// Checks the refresh guard before a refresh, which may happen once every
+    /// `min_interval_s` seconds
+    ///
+    /// Returns [Error::RefreshTooSoon] if the last refresh passing the guard is more
+    /// recent, otherwise the refresh is noted.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn guard_refresh(&mut self, min_interval_s: u32) -> Result<(), Error<SPI::Error>> {
+        if self.guard_ticks_per_second == 0 {
+            return Ok(());
+        }
+        let min_interval = u64::from(min_interval_s) * u64::from(self.guard_ticks_per_second);
+        match self.last_refresh_ticks {
+            Some(last)
+                if !self.refresh_forced && self.now_ticks.saturating_sub(last) < min_interval =>
+            {
+                Err(Error::RefreshTooSoon)
+            }
+            _ => {
+                self.last_refresh_ticks = Some(self.now_ticks);
+                Ok(())
+            }
         }
     }
 
+    /// Counts a refresh of the whole panel, `quick` with the quick LUT
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn count_refresh(&mut self, quick: bool) {
+        self.counters.count(if quick {
+            RefreshKind::Quick
+        } else {
+            RefreshKind::Full
+        });
+    }
+
+    /// Counts a refresh of a window of the panel
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn count_partial_refresh(&mut self) {
+        self.counters.count_partial();
+    }
+
+    /// Refreshes counted so far
+    pub(crate) fn refresh_counters(&self) -> RefreshCounters {
+        self.counters
+    }
+
+    /// Continues counting from `counters`
+    pub(crate) fn restore_refresh_counters(&mut self, counters: RefreshCounters) {
+        self.counters = counters;
+    }
+
+    /// Marks `operation` as the one of the driver, failing transfers report it until
+    /// the next one. Returns the operation it interrupts.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_operation(
+        &mut self,
+        operation: error::Operation,
+    ) -> Option<error::Operation> {
+        self.context.operation.replace(operation)
+    }
+
+    /// Goes back to `operation` after a nested one, e.g. a LUT upload during init,
+    /// completed
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn restore_operation(&mut self, operation: Option<error::Operation>) {
+        self.context.operation = operation;
+    }
+
+    /// Id of the display reported with failing transfers
+    pub(crate) fn set_instance_id(&mut self, id: u8) {
+        self.context.instance = Some(id);
+    }
+
+    /// Marks `operation` as started, the next wait takes its worst case time with
+    /// [BusyMode::FixedDelay]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn start_operation(&mut self, operation: Operation) {
+        self.operation = Some(operation);
+    }
+
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
+    /// The command byte is retried, see [DisplayInterface::set_retry].
+    pub(crate) fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.flush(spi)?;
+        self.retrying(spi, |interface, spi| interface.send_cmd(spi, command))
+    }
+
+    fn send_cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), Error<SPI::Error>> {
         // low for commands
         let _ = self.dc.set_low();
+        #[cfg(feature = "checksum")]
+        self.checksum.start(command.plane());
 
         // Transfer the command over spi
         self.write(spi, &[command.address()])
@@ -60,68 +418,457 @@ where
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    ///
+    /// With write combining (COMBINE_LEN > 0) the bytes SINGLE_BYTE_WRITE would write
+    /// one by one, and data shorter than COMBINE_LEN, are collected in a staging buffer
+    /// and written COMBINE_LEN at a time, saving the setup time of every write. The
+    /// controller receives the same byte stream. Everything staged is written before
+    /// this returns, so no data waits across a busy wait or delay.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.data_combined(spi, data)?;
+        self.flush(spi)
+    }
+
+    /// Same as [DisplayInterface::data], but the last bytes may stay staged for more
+    /// data of the same command, e.g. the rows of a frame
+    ///
+    /// The caller writes them with [DisplayInterface::flush] before it returns, the
+    /// next command, parameter list or read writes them at the latest.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data_combined(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
         // high for data
         let _ = self.dc.set_high();
 
-        if SINGLE_BYTE_WRITE {
+        #[cfg(feature = "checksum")]
+        self.checksum.feed(data);
+
+        if COMBINE_LEN > 0 && (SINGLE_BYTE_WRITE || data.len() < COMBINE_LEN) {
+            self.stage(spi, data)?;
+        } else if SINGLE_BYTE_WRITE {
             for val in data.iter().copied() {
                 // Transfer data one u8 at a time over spi
                 self.write(spi, &[val])?;
             }
         } else {
+            self.flush(spi)?;
             self.write(spi, data)?;
         }
 
         Ok(())
     }
 
+    /// Writes the staged data bytes, see [DisplayInterface::data]
+    pub(crate) fn flush(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        if self.staged == 0 {
+            return Ok(());
+        }
+        let (staging, len) = (self.staging, self.staged);
+        self.staged = 0;
+        self.write(spi, &staging[..len])
+    }
+
+    /// Appends `data` to the staged bytes, writing them whenever the buffer is full
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn stage(&mut self, spi: &mut SPI, mut data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        while !data.is_empty() {
+            let len = (COMBINE_LEN - self.staged).min(data.len());
+            self.staging[self.staged..self.staged + len].copy_from_slice(&data[..len]);
+            self.staged += len;
+            data = &data[len..];
+            if self.staged == COMBINE_LEN {
+                self.flush(spi)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends `val` `repetitions` times to the staged bytes, like [DisplayInterface::stage]
+    fn stage_repeated(
+        &mut self,
+        spi: &mut SPI,
+        val: u8,
+        mut repetitions: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        while repetitions > 0 {
+            let len = (COMBINE_LEN - self.staged).min(repetitions as usize);
+            self.staging[self.staged..self.staged + len].fill(val);
+            self.staged += len;
+            repetitions -= len as u32;
+            if self.staged == COMBINE_LEN {
+                self.flush(spi)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the bytes `range` of `frame` like [DisplayInterface::data] sends a buffer
+    ///
+    /// A frame in parts goes out like [DisplayInterface::data_vectored] where `data`
+    /// writes a buffer at once. A frame reporting its progress is sent in
+    /// [chunks](FrameData::chunks), each written before it is reported.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data_frame(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        range: Range<usize>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if frame.reports_progress() {
+            for chunk in frame.chunks(range) {
+                self.data(spi, chunk)?;
+                frame.sent(chunk.len());
+            }
+            return Ok(());
+        }
+        let len = range.len();
+        if let Some(data) = frame.slice(range.clone()) {
+            self.data(spi, data)?;
+        } else if COMBINE_LEN > 0 || SINGLE_BYTE_WRITE {
+            for piece in frame.pieces(range) {
+                self.data_combined(spi, piece)?;
+            }
+            self.flush(spi)?;
+        } else {
+            self.data_vectored(spi, frame.pieces(range))?;
+        }
+        frame.sent(len);
+        Ok(())
+    }
+
+    /// Sends the bytes `range` of `frame` like [DisplayInterface::data_block] sends a
+    /// buffer, see [DisplayInterface::data_frame]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data_block_frame(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        range: Range<usize>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if frame.reports_progress() {
+            for chunk in frame.chunks(range) {
+                self.data_block(spi, chunk)?;
+                frame.sent(chunk.len());
+            }
+            return Ok(());
+        }
+        let len = range.len();
+        match frame.slice(range.clone()) {
+            Some(data) => self.data_block(spi, data)?,
+            None => self.data_vectored(spi, frame.pieces(range))?,
+        }
+        frame.sent(len);
+        Ok(())
+    }
+
+    /// [DisplayInterface::cmd_with_data] for the bytes `range` of `frame`
+    ///
+    /// Frame data is never retried, see [DisplayInterface::set_retry].
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn cmd_with_frame<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        frame: &mut FrameData<'_>,
+        range: Range<usize>,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !command.expected_data_len().accepts(range.len()) {
+            return Err(Error::InvalidDataLength);
+        }
+        self.cmd(spi, command)?;
+        self.data_block_frame(spi, frame, range)
+    }
+
     /// Basic function for sending [Commands](Command) and the data belonging to it.
     ///
-    /// TODO: directly use ::write? cs wouldn't needed to be changed twice than
+    /// The whole parameter list goes out in one write, see [DisplayInterface::data_block].
+    /// As the spi device owns CS and DC can't change within one of its transactions,
+    /// the command byte takes a CS assertion of its own. Returns
+    /// [Error::InvalidDataLength] without sending anything if `data` doesn't have the
+    /// [expected length](Command::expected_data_len) of the command.
+    ///
+    /// Parameter lists of a known length are retried together with their command,
+    /// data of a variable length only is sent once, see [DisplayInterface::set_retry].
     pub(crate) fn cmd_with_data<T: Command>(
         &mut self,
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.cmd(spi, command)?;
-        self.data(spi, data)
+    ) -> Result<(), Error<SPI::Error>> {
+        let expected = command.expected_data_len();
+        if !expected.accepts(data.len()) {
+            return Err(Error::InvalidDataLength);
+        }
+        self.flush(spi)?;
+        if expected == DataLen::Variable {
+            self.cmd(spi, command)?;
+            return self.data_block(spi, data);
+        }
+        self.retrying(spi, |interface, spi| {
+            interface.send_cmd(spi, command)?;
+            interface.data_block(spi, data)
+        })
+    }
+
+    /// Sends `data` in one write, so within a single CS assertion
+    ///
+    /// Unlike [DisplayInterface::data] this ignores `SINGLE_BYTE_WRITE`, meant for
+    /// parameter lists which some controllers don't like to be interrupted. Only the
+    /// transfer size limit splits it.
+    pub(crate) fn data_block(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.flush(spi)?;
+        // high for data
+        let _ = self.dc.set_high();
+
+        #[cfg(feature = "checksum")]
+        self.checksum.feed(data);
+        self.write(spi, data)
+    }
+
+    /// Sends `parts` as if they were one buffer in one write, see [DisplayInterface::data_block]
+    ///
+    /// The parts go out as consecutive writes of one spi transaction, so CS stays
+    /// asserted from the first to the last byte like for a contiguous buffer. The
+    /// transfer size limit splits the transaction at the same bytes as a contiguous
+    /// buffer, and every [VECTORED_WRITES] writes start another one.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data_vectored<'p>(
+        &mut self,
+        spi: &mut SPI,
+        parts: impl Iterator<Item = &'p [u8]> + Clone,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.flush(spi)?;
+        // high for data
+        let _ = self.dc.set_high();
+
+        #[cfg(feature = "checksum")]
+        for part in parts.clone() {
+            self.checksum.feed(part);
+        }
+        if self.poisoned {
+            return Err(Error::NeedsReset(self.context));
+        }
+        let result = self.write_vectored(spi, parts);
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
     }
 
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
+    ///
+    /// Combined into writes of COMBINE_LEN bytes with write combining, see
+    /// [DisplayInterface::data].
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
     pub(crate) fn data_x_times(
         &mut self,
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.data_x_times_combined(spi, val, repetitions)?;
+        self.flush(spi)
+    }
+
+    /// Same as [DisplayInterface::data_x_times], leaving the last bytes staged like
+    /// [DisplayInterface::data_combined]
+    fn data_x_times_combined(
+        &mut self,
+        spi: &mut SPI,
+        val: u8,
+        repetitions: u32,
+    ) -> Result<(), Error<SPI::Error>> {
         // high for data
         let _ = self.dc.set_high();
-        // Transfer data (u8) over spi
+        #[cfg(feature = "checksum")]
+        self.checksum.feed_repeated(val, repetitions);
+        if COMBINE_LEN > 0 {
+            return self.stage_repeated(spi, val, repetitions);
+        }
+        // Transfer data (u8) over spi, one byte at a time stays below any transfer limit
         for _ in 0..repetitions {
             self.write(spi, &[val])?;
         }
         Ok(())
     }
 
+    /// Sends a plane of which only `band` is kept, `before` and `after` bytes of
+    /// `background` around it, see [crate::traits::ChromaticBand::fill_lens]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn data_band(
+        &mut self,
+        spi: &mut SPI,
+        band: &[u8],
+        background: u8,
+        (before, after): (u32, u32),
+    ) -> Result<(), Error<SPI::Error>> {
+        self.data_x_times_combined(spi, background, before)?;
+        self.data_combined(spi, band)?;
+        self.data_x_times_combined(spi, background, after)?;
+        self.flush(spi)
+    }
+
+    /// Reads `out.len()` bytes the controller returns for the last command
+    ///
+    /// The controllers only send data on their bidirectional data line (DIN/SDA), which
+    /// has to be connected to MISO as well, e.g. with a resistor between MOSI and DIN.
+    /// The Waveshare boards only connect it to MOSI. A failed read poisons the driver
+    /// like a failed write.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn read(&mut self, spi: &mut SPI, out: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        self.flush(spi)?;
+        if self.poisoned {
+            return Err(Error::NeedsReset(self.context));
+        }
+        // high for data
+        let _ = self.dc.set_high();
+        let context = self.context;
+        let result = spi.read(out).map_err(|e| Error::Spi(e, context));
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    /// CRC-16 over all data bytes sent since the last command
+    ///
+    /// See [crate::checksum] for details.
+    #[cfg(feature = "checksum")]
+    pub(crate) fn checksum(&self) -> u16 {
+        self.checksum.current()
+    }
+
+    /// Overrides the checksum, e.g. to keep the one of a frame across a trailing command
+    #[cfg(feature = "checksum")]
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn set_checksum(&mut self, checksum: u16) {
+        self.checksum.set_current(checksum);
+    }
+
+    /// CRC-16 over the data last sent to `plane` of a tri-color controller
+    #[cfg(feature = "checksum")]
+    pub(crate) fn plane_checksum(&self, plane: Plane) -> u16 {
+        self.checksum.plane(plane)
+    }
+
+    /// Returns [DisplayInterface::checksum] and resets it and the plane checksums
+    #[cfg(feature = "checksum")]
+    pub(crate) fn take_checksum(&mut self) -> u16 {
+        self.checksum.take()
+    }
+
+    /// Runs `send` again after a failed spi write, up to the configured number of
+    /// retries, see [DisplayInterface::set_retry]
+    fn retrying<F>(&mut self, spi: &mut SPI, mut send: F) -> Result<(), Error<SPI::Error>>
+    where
+        F: FnMut(&mut Self, &mut SPI) -> Result<(), Error<SPI::Error>>,
+    {
+        if self.poisoned {
+            return Err(Error::NeedsReset(self.context));
+        }
+        let mut retries = self.retry_attempts;
+        loop {
+            match send(self, spi) {
+                Err(Error::Spi(..)) if retries > 0 => {
+                    retries -= 1;
+                    // the command is sent again from its start, which resynchronises
+                    // the controller
+                    self.poisoned = false;
+                    // CS toggles around the pause, a failure of the pause shows in the retry
+                    let _ = spi.transaction(&mut [SpiOperation::DelayNs(RETRY_PAUSE_NS)]);
+                }
+                result => return result,
+            }
+        }
+    }
+
     // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        // transfer spi data
+    //
+    // A failed write may leave the controller in the middle of a transfer, so all
+    // following writes are refused until the device was reset.
+    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        if self.poisoned {
+            return Err(Error::NeedsReset(self.context));
+        }
+        let result = self.write_chunks(spi, data);
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    /// Longest spi transfer
+    fn transfer_limit(&self) -> Option<usize> {
         // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
         // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
         if cfg!(target_os = "linux") {
-            for data_chunk in data.chunks(4096) {
-                spi.write(data_chunk)?;
-            }
-            Ok(())
+            Some(self.max_transfer_size.map_or(4096, |size| size.min(4096)))
         } else {
-            spi.write(data)
+            self.max_transfer_size
+        }
+    }
+
+    fn write_chunks(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // transfer spi data
+        let limit = self.transfer_limit();
+        let context = self.context;
+        match limit {
+            Some(limit) => {
+                for data_chunk in data.chunks(limit) {
+                    spi.write(data_chunk).map_err(|e| Error::Spi(e, context))?;
+                }
+                Ok(())
+            }
+            None => spi.write(data).map_err(|e| Error::Spi(e, context)),
         }
     }
 
+    /// Writes `parts` in transactions of up to [VECTORED_WRITES] writes and the transfer
+    /// limit, a part reaching beyond the limit is split
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn write_vectored<'p>(
+        &mut self,
+        spi: &mut SPI,
+        parts: impl Iterator<Item = &'p [u8]>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let limit = self.transfer_limit();
+        let context = self.context;
+        let mut writes: [SpiOperation<'_, u8>; VECTORED_WRITES] =
+            core::array::from_fn(|_| SpiOperation::Write(&[]));
+        let (mut len, mut bytes) = (0, 0);
+        for part in parts {
+            let mut rest = part;
+            while !rest.is_empty() {
+                let room = limit.map_or(rest.len(), |limit| limit - bytes);
+                let (write, next) = rest.split_at(room.min(rest.len()));
+                writes[len] = SpiOperation::Write(write);
+                len += 1;
+                bytes += write.len();
+                rest = next;
+                if len == VECTORED_WRITES || Some(bytes) == limit {
+                    spi.transaction(&mut writes[..len])
+                        .map_err(|e| Error::Spi(e, context))?;
+                    (len, bytes) = (0, 0);
+                }
+            }
+        }
+        if len > 0 {
+            spi.transaction(&mut writes[..len])
+                .map_err(|e| Error::Spi(e, context))?;
+        }
+        Ok(())
+    }
+
     /// Waits until device isn't busy anymore (busy == HIGH)
     ///
     /// This is normally handled by the more complicated commands themselves,
@@ -134,8 +881,47 @@ where
     ///  - FALSE for epd2in9, epd1in54 (for all Display Type A ones?)
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
     pub(crate) fn wait_until_idle(&mut self, delay: &mut DELAY, is_busy_low: bool) {
-        while self.is_busy(is_busy_low) {
+        if self.wait_fixed_delay(delay) {
+            return;
+        }
+        let _ = self.poll_until_idle(delay, is_busy_low, &|| false);
+    }
+
+    /// Same as `wait_until_idle`, but gives up as soon as `cancel` returns true
+    ///
+    /// `cancel` is called before every poll of the busy pin, or every
+    /// [CANCEL_STEP_US] of a [BusyMode::FixedDelay] wait. A cancelled wait leaves
+    /// everything as it was, a later wait still waits for the same operation.
+    pub(crate) fn wait_until_idle_cancellable(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        cancel: &dyn Fn() -> bool,
+    ) -> WaitOutcome {
+        if self.busy_mode == BusyMode::FixedDelay {
+            return self.wait_fixed_delay_cancellable(delay, cancel);
+        }
+        self.operation = None;
+        self.poll_until_idle(delay, is_busy_low, cancel)
+    }
+
+    /// Polls the busy pin every `delay_us` until it is idle or `cancel` returns true
+    fn poll_until_idle(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        cancel: &dyn Fn() -> bool,
+    ) -> WaitOutcome {
+        let mut idle = 0;
+        loop {
+            if cancel() {
+                return WaitOutcome::Cancelled;
+            }
+            if self.confirmed_idle(is_busy_low, &mut idle) {
+                return WaitOutcome::Idle;
+            }
             // This has been removed and added many time :
             // - it is faster to not have it
             // - it is complicated to pass the delay everywhere all the time
@@ -149,24 +935,157 @@ where
     }
 
     /// Same as `wait_until_idle` for device needing a command to probe Busy pin
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
     pub(crate) fn wait_until_idle_with_cmd<T: Command>(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         is_busy_low: bool,
         status_command: T,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.wait_fixed_delay(delay) {
+            return Ok(());
+        }
+        let _ = self.poll_with_cmd(spi, delay, is_busy_low, status_command, &|| false)?;
+        Ok(())
+    }
+
+    /// Same as `wait_until_idle_with_cmd`, but gives up as soon as `cancel` returns true,
+    /// see [wait_until_idle_cancellable](Self::wait_until_idle_cancellable)
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn wait_until_idle_with_cmd_cancellable<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        status_command: T,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        if self.busy_mode == BusyMode::FixedDelay {
+            return Ok(self.wait_fixed_delay_cancellable(delay, cancel));
+        }
+        self.operation = None;
+        self.poll_with_cmd(spi, delay, is_busy_low, status_command, cancel)
+    }
+
+    /// Sends `status_command` before every poll of the busy pin
+    ///
+    /// The checksum of the data sent before is kept, the status commands don't transfer any.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn poll_with_cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        status_command: T,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        #[cfg(feature = "checksum")]
+        let checksum = self.checksum;
+        let outcome = self.poll_status(spi, delay, is_busy_low, status_command, cancel);
+        #[cfg(feature = "checksum")]
+        {
+            self.checksum = checksum;
+        }
+        outcome
+    }
+
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn poll_status<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        status_command: T,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
         self.cmd(spi, status_command)?;
         if self.delay_us > 0 {
             delay.delay_us(self.delay_us);
         }
-        while self.is_busy(is_busy_low) {
+        let mut idle = 0;
+        loop {
+            if cancel() {
+                return Ok(WaitOutcome::Cancelled);
+            }
+            if self.confirmed_idle(is_busy_low, &mut idle) {
+                return Ok(WaitOutcome::Idle);
+            }
             self.cmd(spi, status_command)?;
             if self.delay_us > 0 {
                 delay.delay_us(self.delay_us);
             }
         }
-        Ok(())
+    }
+
+    /// Polls the busy pin once, true after enough consecutive idle readings
+    ///
+    /// `idle` counts the idle readings so far, a busy one starts over. This filters
+    /// short glitches of the busy line, see [DisplayInterface::set_idle_debounce].
+    fn confirmed_idle(&mut self, is_busy_low: bool, idle: &mut u8) -> bool {
+        if self.is_busy(is_busy_low) {
+            self.busy_polls = self.busy_polls.saturating_add(1);
+            if *idle > 0 {
+                self.glitches = self.glitches.saturating_add(1);
+            }
+            *idle = 0;
+            false
+        } else {
+            *idle += 1;
+            *idle >= self.idle_polls
+        }
+    }
+
+    /// Starts counting the busy polls and glitches of the following waits again
+    pub(crate) fn reset_busy_stats(&mut self) {
+        self.busy_polls = 0;
+        self.glitches = 0;
+    }
+
+    /// Report of the waits since [DisplayInterface::reset_busy_stats], see [RefreshReport]
+    pub(crate) fn refresh_report(&self, limits: &RefreshLimits, quick: bool) -> RefreshReport {
+        RefreshReport::new(self.busy_polls, self.delay_us, self.glitches, limits, quick)
+    }
+
+    /// [wait_fixed_delay](Self::wait_fixed_delay) in steps of [CANCEL_STEP_US], asking
+    /// `cancel` before each
+    ///
+    /// A cancelled wait keeps the operation, the next wait takes its whole worst case
+    /// time again.
+    fn wait_fixed_delay_cancellable(
+        &mut self,
+        delay: &mut DELAY,
+        cancel: &dyn Fn() -> bool,
+    ) -> WaitOutcome {
+        let duration = self.busy_timings.duration(self.operation);
+        let mut waited = 0;
+        while waited < duration {
+            if cancel() {
+                return WaitOutcome::Cancelled;
+            }
+            let step = (duration - waited).min(CANCEL_STEP_US);
+            delay.delay_us(step);
+            waited += step;
+        }
+        self.operation = None;
+        WaitOutcome::Idle
+    }
+
+    /// Waits the worst case time of the last started operation in [BusyMode::FixedDelay]
+    ///
+    /// Returns false in [BusyMode::Pin], where the pin needs to be polled instead.
+    fn wait_fixed_delay(&mut self, delay: &mut DELAY) -> bool {
+        let operation = self.operation.take();
+        match self.busy_mode {
+            BusyMode::Pin => false,
+            BusyMode::FixedDelay => {
+                let duration = self.busy_timings.duration(operation);
+                if duration > 0 {
+                    delay.delay_us(duration);
+                }
+                true
+            }
+        }
     }
 
     /// Checks if device is still busy
@@ -183,6 +1102,7 @@ where
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
     pub(crate) fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        let is_busy_low = is_busy_low != self.busy_inverted;
         (is_busy_low && self.busy.is_low().unwrap_or(false))
             || (!is_busy_low && self.busy.is_high().unwrap_or(false))
     }
@@ -193,16 +1113,953 @@ where
     ///
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
-    /// properly with 2ms
-    pub(crate) fn reset(&mut self, delay: &mut DELAY, initial_delay: u32, duration: u32) {
-        let _ = self.rst.set_high();
-        delay.delay_us(initial_delay);
-
-        let _ = self.rst.set_low();
-        delay.delay_us(duration);
-        let _ = self.rst.set_high();
-        //TODO: the upstream libraries always sleep for 200ms here
-        // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
-        delay.delay_us(200_000);
+    /// properly with 2ms. The drivers pass their timing as `default`, which is used unless
+    /// overridden with [DisplayInterface::set_reset_config].
+    pub(crate) fn reset(&mut self, delay: &mut DELAY, default: ResetConfig) {
+        let config = self.reset_config_or(default);
+        self.pulse_reset(delay, &config, config.pulses.max(1));
+        self.set_reset_line(&config, false);
+        self.poisoned = false;
+        self.operation = Some(Operation::Init);
+        delay.delay_us(config.post_us);
+    }
+
+    /// Releases the reset line for `pre_us` and asserts it for `pulse_us`, `pulses` times
+    fn pulse_reset(&mut self, delay: &mut DELAY, config: &ResetConfig, pulses: u8) {
+        for _ in 0..pulses {
+            self.set_reset_line(config, false);
+            delay.delay_us(config.pre_us);
+            self.set_reset_line(config, true);
+            delay.delay_us(config.pulse_us);
+        }
+    }
+
+    /// Drives the reset line to the level holding the controller in reset if `asserted`
+    fn set_reset_line(&mut self, config: &ResetConfig, asserted: bool) {
+        let _ = if asserted == config.active_low {
+            self.rst.set_low()
+        } else {
+            self.rst.set_high()
+        };
+    }
+
+    /// Aborts a refresh of an UC81xx style controller with a hardware reset, then powers it off
+    ///
+    /// The busy pin is not waited for. Further transfers return [`Error::NeedsReset`]
+    /// until the device is reset again by the initialisation.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn abort_with_reset<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        reset: ResetConfig,
+        power_off: T,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.reset(delay, reset);
+        let result = self.cmd(spi, power_off);
+        self.poison();
+        result
+    }
+
+    /// Aborts a refresh of an SSD16xx style controller with a software reset, then sends it
+    /// into deep sleep mode 1
+    ///
+    /// Only the software reset is waited for with a fixed delay, not the busy pin. Further
+    /// transfers return [`Error::NeedsReset`] until the device is reset again by the
+    /// initialisation.
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn abort_with_sw_reset<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        sw_reset: T,
+        deep_sleep: T,
+    ) -> Result<(), Error<SPI::Error>> {
+        // the abort has to reach the controller even after a failed transfer
+        self.poisoned = false;
+        let result = self.cmd(spi, sw_reset).and_then(|()| {
+            delay.delay_us(SW_RESET_US);
+            self.cmd_with_data(spi, deep_sleep, &[0x01])
+        });
+        self.poison();
+        result
+    }
+
+    /// Checks the reaction of the busy pin to a hardware reset and to `busy_command`,
+    /// see [crate::busy]
+    ///
+    /// The reset follows `default` like [DisplayInterface::reset], the pin is watched from
+    /// the start of the last pulse. Afterwards further transfers return
+    /// [`Error::NeedsReset`] until the device is reset again by the initialisation.
+    pub(crate) fn self_test<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        default: ResetConfig,
+        busy_command: T,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        let config = self.reset_config_or(default);
+        self.pulse_reset(delay, &config, config.pulses.max(1) - 1);
+        self.set_reset_line(&config, false);
+        delay.delay_us(config.pre_us);
+        self.set_reset_line(&config, true);
+        // levels seen since the reset, indexed by whether they read busy
+        let mut seen = [false; 2];
+        for _ in 0..config.pulse_us.div_ceil(SELF_TEST_POLL_US) {
+            let busy = self.is_busy(is_busy_low);
+            seen[usize::from(busy)] = true;
+            delay.delay_us(SELF_TEST_POLL_US);
+        }
+        let in_reset = seen[1];
+        self.set_reset_line(&config, false);
+        self.poisoned = false;
+        let mut reset = self.watch_busy(delay, is_busy_low, &mut seen);
+        // the pin settles at the idle level, once it changed at all
+        let settled_busy = reset.asserted && !reset.released;
+        let configured = self.busy_polarity(is_busy_low);
+        let detected = match (seen, settled_busy) {
+            ([true, true], false) => Some(configured),
+            ([true, true], true) => Some(BusyPolarity::from_busy_low(!configured.is_busy_low())),
+            _ => None,
+        };
+        if in_reset && !reset.asserted {
+            // busy only while the reset line was low
+            reset = BusyCheck {
+                asserted: true,
+                released: true,
+                busy_us: 0,
+            };
+        }
+
+        // a line stuck at the busy level isn't waited for a second time
+        let mut result = Ok(());
+        let mut command = BusyCheck::default();
+        if reset.released || !reset.asserted {
+            result = self.cmd(spi, busy_command);
+            command = self.watch_busy(delay, is_busy_low, &mut [false; 2]);
+        }
+        self.poison();
+        result?;
+        Ok(
+            SelfTestReport::new(reset, command, configured.is_busy_low())
+                .with_busy_polarity(detected, configured),
+        )
+    }
+
+    /// Watches the busy pin becoming busy and idle again, giving up after
+    /// [SELF_TEST_ASSERT_US] and [SELF_TEST_TIMEOUT_US]
+    ///
+    /// Marks the levels read in `seen`, indexed by whether they read busy.
+    fn watch_busy(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        seen: &mut [bool; 2],
+    ) -> BusyCheck {
+        let mut check = BusyCheck::default();
+        let mut elapsed = 0;
+        loop {
+            let busy = self.is_busy(is_busy_low);
+            seen[usize::from(busy)] = true;
+            check.asserted |= busy;
+            if check.asserted && !busy {
+                check.released = true;
+                check.busy_us = elapsed;
+                return check;
+            }
+            let limit = if check.asserted {
+                SELF_TEST_TIMEOUT_US
+            } else {
+                SELF_TEST_ASSERT_US
+            };
+            if elapsed >= limit {
+                if check.asserted {
+                    check.busy_us = elapsed;
+                }
+                return check;
+            }
+            delay.delay_us(SELF_TEST_POLL_US);
+            elapsed += SELF_TEST_POLL_US;
+        }
+    }
+
+    /// Refuses further transfers until the next reset
+    pub(crate) fn poison(&mut self) {
+        self.poisoned = true;
+        self.operation = None;
+    }
+}
+
+/// The parts of a [DisplayInterface] which don't depend on how it writes data
+///
+/// Lets the methods of [DriverControl](crate::traits::DriverControl) work with the
+/// interface of every driver, whatever its const parameters.
+pub trait Connection<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+{
+    /// See [DisplayInterface::new]
+    fn new(busy: BUSY, dc: DC, rst: RST, delay_us: Option<u32>) -> Self;
+    /// See [DisplayInterface::checksum]
+    #[cfg(feature = "checksum")]
+    fn checksum(&self) -> u16;
+    /// See [DisplayInterface::plane_checksum]
+    #[cfg(feature = "checksum")]
+    fn plane_checksum(&self, plane: Plane) -> u16;
+    /// See [DisplayInterface::take_checksum]
+    #[cfg(feature = "checksum")]
+    fn take_checksum(&mut self) -> u16;
+    /// See [DisplayInterface::set_max_transfer_size]
+    fn set_max_transfer_size(&mut self, max_transfer_size: Option<usize>);
+    /// See [DisplayInterface::set_retry]
+    fn set_retry(&mut self, attempts: u8);
+    /// See [DisplayInterface::set_idle_debounce]
+    fn set_idle_debounce(&mut self, polls: u8);
+    /// See [DisplayInterface::set_busy_polarity]
+    fn set_busy_polarity(&mut self, polarity: BusyPolarity, is_busy_low: bool);
+    /// See [DisplayInterface::busy_polarity]
+    fn busy_polarity(&self, is_busy_low: bool) -> BusyPolarity;
+    /// See [DisplayInterface::set_reset_config]
+    fn set_reset_config(&mut self, config: Option<ResetConfig>);
+    /// See [DisplayInterface::reset_config_or]
+    fn reset_config_or(&self, default: ResetConfig) -> ResetConfig;
+    /// See [DisplayInterface::set_refresh_guard]
+    fn set_refresh_guard(&mut self, ticks_per_second: u32);
+    /// See [DisplayInterface::note_time]
+    fn note_time(&mut self, now_ticks: u64);
+    /// See [DisplayInterface::set_refresh_forced]
+    fn set_refresh_forced(&mut self, forced: bool);
+    /// See [DisplayInterface::refresh_counters]
+    fn refresh_counters(&self) -> RefreshCounters;
+    /// See [DisplayInterface::restore_refresh_counters]
+    fn restore_refresh_counters(&mut self, counters: RefreshCounters);
+    /// See [DisplayInterface::set_instance_id]
+    fn set_instance_id(&mut self, id: u8);
+    /// See [DisplayInterface::reset_busy_stats]
+    fn reset_busy_stats(&mut self);
+    /// See [DisplayInterface::refresh_report]
+    fn refresh_report(&self, limits: &RefreshLimits, quick: bool) -> RefreshReport;
+    /// See [DisplayInterface::is_busy]
+    fn is_busy(&mut self, is_busy_low: bool) -> bool;
+    /// See [DisplayInterface::wait_until_idle_cancellable]
+    fn wait_until_idle_cancellable(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        cancel: &dyn Fn() -> bool,
+    ) -> WaitOutcome;
+    /// See [DisplayInterface::self_test]
+    fn self_test<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        default: ResetConfig,
+        busy_command: T,
+    ) -> Result<SelfTestReport, Error<SPI::Error>>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool, const COMBINE_LEN: usize>
+    Connection<SPI, BUSY, DC, RST, DELAY>
+    for DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE, COMBINE_LEN>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    fn new(busy: BUSY, dc: DC, rst: RST, delay_us: Option<u32>) -> Self {
+        DisplayInterface::new(busy, dc, rst, delay_us)
+    }
+
+    #[cfg(feature = "checksum")]
+    fn checksum(&self) -> u16 {
+        DisplayInterface::checksum(self)
+    }
+
+    #[cfg(feature = "checksum")]
+    fn plane_checksum(&self, plane: Plane) -> u16 {
+        DisplayInterface::plane_checksum(self, plane)
+    }
+
+    #[cfg(feature = "checksum")]
+    fn take_checksum(&mut self) -> u16 {
+        DisplayInterface::take_checksum(self)
+    }
+
+    fn set_max_transfer_size(&mut self, max_transfer_size: Option<usize>) {
+        DisplayInterface::set_max_transfer_size(self, max_transfer_size);
+    }
+
+    fn set_retry(&mut self, attempts: u8) {
+        DisplayInterface::set_retry(self, attempts);
+    }
+
+    fn set_idle_debounce(&mut self, polls: u8) {
+        DisplayInterface::set_idle_debounce(self, polls);
+    }
+
+    fn set_busy_polarity(&mut self, polarity: BusyPolarity, is_busy_low: bool) {
+        DisplayInterface::set_busy_polarity(self, polarity, is_busy_low);
+    }
+
+    fn busy_polarity(&self, is_busy_low: bool) -> BusyPolarity {
+        DisplayInterface::busy_polarity(self, is_busy_low)
+    }
+
+    fn set_reset_config(&mut self, config: Option<ResetConfig>) {
+        DisplayInterface::set_reset_config(self, config);
+    }
+
+    fn reset_config_or(&self, default: ResetConfig) -> ResetConfig {
+        DisplayInterface::reset_config_or(self, default)
+    }
+
+    fn set_refresh_guard(&mut self, ticks_per_second: u32) {
+        DisplayInterface::set_refresh_guard(self, ticks_per_second);
+    }
+
+    fn note_time(&mut self, now_ticks: u64) {
+        DisplayInterface::note_time(self, now_ticks);
+    }
+
+    fn set_refresh_forced(&mut self, forced: bool) {
+        DisplayInterface::set_refresh_forced(self, forced);
+    }
+
+    fn refresh_counters(&self) -> RefreshCounters {
+        DisplayInterface::refresh_counters(self)
+    }
+
+    fn restore_refresh_counters(&mut self, counters: RefreshCounters) {
+        DisplayInterface::restore_refresh_counters(self, counters);
+    }
+
+    fn set_instance_id(&mut self, id: u8) {
+        DisplayInterface::set_instance_id(self, id);
+    }
+
+    fn reset_busy_stats(&mut self) {
+        DisplayInterface::reset_busy_stats(self);
+    }
+
+    fn refresh_report(&self, limits: &RefreshLimits, quick: bool) -> RefreshReport {
+        DisplayInterface::refresh_report(self, limits, quick)
+    }
+
+    fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        DisplayInterface::is_busy(self, is_busy_low)
+    }
+
+    fn wait_until_idle_cancellable(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        cancel: &dyn Fn() -> bool,
+    ) -> WaitOutcome {
+        DisplayInterface::wait_until_idle_cancellable(self, delay, is_busy_low, cancel)
+    }
+
+    fn self_test<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        default: ResetConfig,
+        busy_command: T,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        DisplayInterface::self_test(self, spi, delay, is_busy_low, default, busy_command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Bus, BusyPin, DcPin, Delay, Pin, Spi};
+    use crate::traits::DataLen;
+
+    #[derive(Clone, Copy)]
+    enum Command {
+        WriteRam,
+        WriteLut,
+    }
+
+    impl crate::traits::Command for Command {
+        fn address(self) -> u8 {
+            match self {
+                Command::WriteRam => 0x24,
+                Command::WriteLut => 0x32,
+            }
+        }
+
+        fn expected_data_len(self) -> DataLen {
+            match self {
+                Command::WriteRam => DataLen::Variable,
+                Command::WriteLut => DataLen::Exact(44),
+            }
+        }
+    }
+
+    // without write combining, which has tests of its own
+    type Interface = DisplayInterface<Spi, BusyPin, DcPin, Pin, Delay, false, 0>;
+
+    #[test]
+    fn idle_debounce_filters_glitches() {
+        // busy is low, a glitch reads idle for two polls in the middle of the refresh
+        let script = [false, false, true, true, false, false];
+
+        // the old behaviour returns at the glitch with the refresh still running
+        let bus = Bus::new();
+        let busy = BusyPin::scripted(&script, true);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+        interface.wait_until_idle(&mut Delay::new(), true);
+        assert_eq!(busy.reads(), 3);
+
+        let busy = BusyPin::scripted(&script, true);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+        interface.set_idle_debounce(3);
+        interface.wait_until_idle(&mut Delay::new(), true);
+        // the glitch, the rest of the refresh and three idle polls
+        assert_eq!(busy.reads(), script.len() + 3);
+
+        // the status command is sent before every poll
+        let mut spi = bus.spi();
+        let busy = BusyPin::scripted(&script, true);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+        interface.set_idle_debounce(3);
+        interface
+            .wait_until_idle_with_cmd(&mut spi, &mut Delay::new(), true, Command::WriteRam)
+            .unwrap();
+        assert_eq!(busy.reads(), script.len() + 3);
+        assert_eq!(bus.count(0x24), script.len() + 3);
+    }
+
+    #[test]
+    fn cancelled_wait_stops_after_n_polls() {
+        // busy is low, the refresh takes five polls
+        let bus = Bus::new();
+        let busy = BusyPin::scripted(&[false; 5], true);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+
+        let polls = core::cell::Cell::new(0);
+        let cancel = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 3
+        };
+        let outcome = interface.wait_until_idle_cancellable(&mut Delay::new(), true, &cancel);
+        assert_eq!(outcome, WaitOutcome::Cancelled);
+        assert_eq!(busy.reads(), 3);
+
+        // the refresh keeps running, a later wait sees the rest of it
+        assert!(interface.is_busy(true));
+        interface.wait_until_idle(&mut Delay::new(), true);
+        assert_eq!(busy.reads(), 6);
+        assert_eq!(
+            interface.wait_until_idle_cancellable(&mut Delay::new(), true, &|| false),
+            WaitOutcome::Idle
+        );
+    }
+
+    #[test]
+    fn cmd_with_data_checks_the_length() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = Interface::new(BusyPin::new(false), bus.dc(), Pin::new(), None);
+
+        assert_eq!(
+            interface.cmd_with_data(&mut spi, Command::WriteLut, &[0; 42]),
+            Err(Error::InvalidDataLength)
+        );
+        assert!(bus.commands().is_empty());
+
+        interface
+            .cmd_with_data(&mut spi, Command::WriteLut, &[0; 44])
+            .unwrap();
+        interface
+            .cmd_with_data(&mut spi, Command::WriteRam, &[0; 100])
+            .unwrap();
+        assert_eq!(bus.commands(), [0x32, 0x24]);
+    }
+
+    #[test]
+    fn refresh_guard_intervals() {
+        let bus = Bus::new();
+        let mut interface = Interface::new(BusyPin::new(false), bus.dc(), Pin::new(), None);
+
+        // disabled by default
+        assert_eq!(interface.guard_refresh(180), Ok(()));
+        assert_eq!(interface.guard_refresh(180), Ok(()));
+
+        // milliseconds, the first refresh always passes
+        interface.set_refresh_guard(1000);
+        interface.note_time(5_000);
+        assert_eq!(interface.guard_refresh(180), Ok(()));
+        interface.note_time(184_999);
+        assert_eq!(interface.guard_refresh(180), Err(Error::RefreshTooSoon));
+        interface.note_time(185_000);
+        assert_eq!(interface.guard_refresh(180), Ok(()));
+
+        // a forced refresh restarts the interval
+        interface.note_time(200_000);
+        interface.set_refresh_forced(true);
+        assert_eq!(interface.guard_refresh(180), Ok(()));
+        interface.set_refresh_forced(false);
+        interface.note_time(370_000);
+        assert_eq!(interface.guard_refresh(180), Err(Error::RefreshTooSoon));
+
+        // a clock going backwards doesn't let refreshes through
+        interface.note_time(0);
+        assert_eq!(interface.guard_refresh(180), Err(Error::RefreshTooSoon));
+    }
+
+    #[test]
+    fn refresh_report_flags() {
+        // 2 s typically and 4 s at most, polled every 100 ms
+        let limits = RefreshLimits::new(2_000_000, 4_000_000).with_quick(300_000, 1_000_000);
+        let report = |busy_polls: usize, quick: bool| {
+            let bus = Bus::new();
+            let busy = BusyPin::scripted(&[false; 64][..busy_polls], true);
+            let mut interface = Interface::new(busy, bus.dc(), Pin::new(), Some(100_000));
+            interface.wait_until_idle(&mut Delay::new(), true);
+            interface.refresh_report(&limits, quick)
+        };
+
+        let typical = report(15, false);
+        assert_eq!(typical.busy_polls, 15);
+        assert_eq!(typical.busy_us, 1_500_000);
+        assert!(!typical.over_typical && !typical.over_max);
+        let slow = report(30, false);
+        assert!(slow.over_typical && !slow.over_max);
+        let failing = report(41, false);
+        assert!(failing.over_typical && failing.over_max);
+        // a quick refresh has its own limits
+        assert!(report(15, true).over_max);
+
+        // with a debounce the glitch is seen, the counts start over after a reset
+        let bus = Bus::new();
+        let busy = BusyPin::scripted(&[false, true, false, false, true, false], true);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(100_000));
+        interface.set_idle_debounce(2);
+        interface.wait_until_idle(&mut Delay::new(), true);
+        let glitchy = interface.refresh_report(&limits, false);
+        assert_eq!((glitchy.busy_polls, glitchy.glitches), (4, 2));
+        interface.reset_busy_stats();
+        interface.wait_until_idle(&mut Delay::new(), true);
+        assert_eq!(
+            interface.refresh_report(&limits, false),
+            RefreshReport::default()
+        );
+    }
+
+    #[test]
+    fn retry_resends_commands() {
+        let lut = [0x11; 44];
+        let (write_ram, write_lut) = (0x24, 0x32);
+
+        // without retries the first failure poisons the interface
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = Interface::new(BusyPin::new(false), bus.dc(), Pin::new(), None);
+        bus.fail_after(1);
+        assert!(matches!(
+            interface.cmd_with_data(&mut spi, Command::WriteLut, &lut),
+            Err(Error::Spi(..))
+        ));
+        assert!(matches!(
+            interface.cmd(&mut spi, Command::WriteRam),
+            Err(Error::NeedsReset(_))
+        ));
+
+        // the parameters failed, the command is sent again in a new transaction
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = Interface::new(BusyPin::new(false), bus.dc(), Pin::new(), None);
+        interface.set_retry(2);
+        bus.fail_after(1);
+        interface
+            .cmd_with_data(&mut spi, Command::WriteLut, &lut)
+            .unwrap();
+        assert_eq!(bus.commands(), [write_lut, write_lut]);
+        assert_eq!(bus.data_after_nth(write_lut, 1).unwrap(), lut);
+        // command, failed parameters, pause, command, parameters
+        assert_eq!(bus.cs_assertions(), 5);
+        #[cfg(feature = "checksum")]
+        assert_eq!(interface.checksum(), crate::checksum::crc16(&lut));
+
+        // a failed command byte alone
+        bus.clear();
+        bus.fail_after(0);
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        interface.data(&mut spi, &[0xFF; 4]).unwrap();
+        assert_eq!(bus.commands(), [write_ram]);
+
+        // frame data is not retried
+        bus.clear();
+        bus.fail_after(1);
+        assert!(matches!(
+            interface.cmd_with_data(&mut spi, Command::WriteRam, &[0xFF; 64]),
+            Err(Error::Spi(..))
+        ));
+        assert_eq!(bus.commands(), [write_ram]);
+        assert!(matches!(
+            interface.cmd(&mut spi, Command::WriteRam),
+            Err(Error::NeedsReset(_))
+        ));
+    }
+
+    #[test]
+    fn max_transfer_size_splits_data() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = Interface::new(BusyPin::new(false), bus.dc(), Pin::new(), None);
+        interface.set_max_transfer_size(Some(4 * 1024));
+
+        let frame: [u8; 100 * 1024] = core::array::from_fn(|i| (i % 251) as u8);
+        interface
+            .cmd_with_data(&mut spi, Command::WriteRam, &frame)
+            .unwrap();
+
+        // one write for the command, ceil(100 / 4) for the data
+        let sizes = bus.write_sizes();
+        assert_eq!(sizes.len(), 1 + 25);
+        assert!(sizes[1..].iter().all(|&size| size == 4 * 1024));
+        assert_eq!(bus.data_after(0x24).as_deref(), Some(&frame[..]));
+
+        // uneven split keeps the remainder in the last write
+        bus.clear();
+        interface.set_max_transfer_size(Some(3000));
+        interface.data(&mut spi, &[0xAB; 10_000]).unwrap();
+        assert_eq!(&bus.write_sizes()[26..], &[3000, 3000, 3000, 1000]);
+
+        interface.data_x_times(&mut spi, 0xFF, 5).unwrap();
+        assert!(bus.write_sizes()[30..].iter().all(|&size| size == 1));
+    }
+
+    #[test]
+    fn parameter_list_in_one_cs_assertion() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = DisplayInterface::<_, _, _, _, Delay, true, 0>::new(
+            BusyPin::new(false),
+            bus.dc(),
+            Pin::new(),
+            None,
+        );
+
+        // the command and its whole parameter list, even with single byte writes
+        interface
+            .cmd_with_data(&mut spi, Command::WriteRam, &[0x01, 0x02, 0x03, 0x04])
+            .unwrap();
+        assert_eq!(bus.cs_assertions(), 2);
+        assert_eq!(bus.data_after(0x24).as_deref(), Some(&[1, 2, 3, 4][..]));
+
+        // plain data still goes out byte by byte
+        bus.clear();
+        interface.data(&mut spi, &[0x01, 0x02, 0x03, 0x04]).unwrap();
+        assert_eq!(bus.cs_assertions(), 4);
+    }
+
+    #[test]
+    fn vectored_data_as_contiguous() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = DisplayInterface::<_, _, _, _, Delay, true, 0>::new(
+            BusyPin::new(false),
+            bus.dc(),
+            Pin::new(),
+            None,
+        );
+        let frame: [u8; 10_000] = core::array::from_fn(|i| (i % 251) as u8);
+
+        // a few parts in one cs assertion, even with single byte writes
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        bus.clear();
+        interface
+            .data_vectored(&mut spi, [&frame[..7], &[], &frame[7..100]].into_iter())
+            .unwrap();
+        assert_eq!(bus.cs_assertions(), 1);
+
+        // split at the limit across parts like the contiguous data
+        interface.set_max_transfer_size(Some(3000));
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        interface
+            .data_vectored(
+                &mut spi,
+                [&frame[..1], &frame[1..5001], &frame[5001..]].into_iter(),
+            )
+            .unwrap();
+        assert_eq!(bus.data_after(0x24).as_deref(), Some(&frame[..]));
+        bus.clear();
+        interface
+            .data_vectored(&mut spi, [&frame[..2999], &frame[2999..]].into_iter())
+            .unwrap();
+        assert_eq!(bus.cs_assertions(), 4);
+
+        // at most VECTORED_WRITES parts per cs assertion
+        bus.clear();
+        let parts: [&[u8]; 40] = core::array::from_fn(|i| &frame[i..i + 1]);
+        interface
+            .data_vectored(&mut spi, parts.into_iter())
+            .unwrap();
+        assert_eq!(bus.cs_assertions(), 2);
+    }
+
+    #[test]
+    fn write_combining_stages_data() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = DisplayInterface::<_, _, _, _, Delay, true, 8>::new(
+            BusyPin::new(false),
+            bus.dc(),
+            Pin::new(),
+            None,
+        );
+        let frame: [u8; 20] = core::array::from_fn(|i| i as u8);
+
+        // single byte writes are combined, the rest is written before data returns
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        interface.data(&mut spi, &frame).unwrap();
+        assert_eq!(bus.write_sizes(), [1, 8, 8, 4]);
+        assert_eq!(bus.data_after(0x24).as_deref(), Some(&frame[..]));
+
+        // consecutive calls share the staging buffer up to the next command
+        bus.clear();
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        for row in frame.chunks(3) {
+            interface.data_combined(&mut spi, row).unwrap();
+        }
+        interface.data_x_times(&mut spi, 0xFF, 5).unwrap();
+        assert_eq!(bus.write_sizes()[4..], [1, 8, 8, 8, 1]);
+        let mut expected = frame.to_vec();
+        expected.extend([0xFF; 5]);
+        assert_eq!(bus.data_after(0x24), Some(expected));
+
+        // the next command writes what is still staged first
+        bus.clear();
+        interface.data_combined(&mut spi, &[1, 2]).unwrap();
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        assert_eq!(bus.write_sizes()[9..], [2, 1]);
+    }
+
+    #[test]
+    fn write_combining_keeps_blocks() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut interface = DisplayInterface::<_, _, _, _, Delay, false, 8>::new(
+            BusyPin::new(false),
+            bus.dc(),
+            Pin::new(),
+            None,
+        );
+
+        // short data is combined, longer data written as is after the staged bytes
+        interface.cmd(&mut spi, Command::WriteRam).unwrap();
+        interface.data_combined(&mut spi, &[1, 2, 3]).unwrap();
+        interface.data_combined(&mut spi, &[4, 5]).unwrap();
+        interface.data(&mut spi, &[6; 20]).unwrap();
+        assert_eq!(bus.write_sizes(), [1, 5, 20]);
+
+        // reads come after the staged bytes
+        bus.clear();
+        interface.data_combined(&mut spi, &[1, 2]).unwrap();
+        interface.read(&mut spi, &mut [0; 2]).unwrap();
+        assert_eq!(bus.write_sizes()[3..], [2]);
+    }
+
+    #[test]
+    fn self_test_diagnoses() {
+        use crate::busy::{LikelyFault, SELF_TEST_ASSERT_US};
+        // the busy pin is polled 10 times while the reset line is low
+        let reset_polls = [false; 10];
+        let never_asserted = [false; (SELF_TEST_ASSERT_US / SELF_TEST_POLL_US) as usize + 1];
+        let run = |script: &[&[bool]], level: bool| {
+            let bus = Bus::new();
+            let busy = BusyPin::new(level);
+            for part in script {
+                busy.push(part);
+            }
+            let mut interface = Interface::new(busy, bus.dc(), Pin::new(), None);
+            let report = interface
+                .self_test(
+                    &mut bus.spi(),
+                    &mut Delay::new(),
+                    false,
+                    ResetConfig::new(10_000, 1_000),
+                    Command::WriteRam,
+                )
+                .unwrap();
+            // poisoned until the driver resets the controller again
+            assert!(interface.cmd(&mut bus.spi(), Command::WriteRam).is_err());
+            assert_eq!(
+                bus.count(0x24),
+                usize::from(report.reset.released || !report.reset.asserted)
+            );
+            report
+        };
+
+        let report = run(&[&reset_polls, &[true, true, false], &[true, false]], false);
+        assert_eq!(report.fault, None);
+        assert!(report.passed());
+        assert_eq!(report.reset.busy_us, 2 * SELF_TEST_POLL_US);
+        assert_eq!(report.command.busy_us, SELF_TEST_POLL_US);
+        assert_eq!(report.ram_read_back, None);
+
+        // busy only while the reset line is low
+        let report = run(&[&[true; 10], &never_asserted, &[true, false]], false);
+        assert!(report.reset.passed());
+        assert!(report.passed());
+
+        let report = run(&[], true);
+        assert_eq!(report.fault, Some(LikelyFault::BusyStuckHigh));
+        assert!(!report.reset.released);
+        assert!(!report.command.asserted);
+        assert_eq!(run(&[], false).fault, Some(LikelyFault::BusyStuckLow));
+
+        let report = run(&[&reset_polls, &never_asserted, &[true, false]], false);
+        assert_eq!(report.fault, Some(LikelyFault::NoResetResponse));
+        let report = run(&[&reset_polls, &[true, false]], false);
+        assert_eq!(report.fault, Some(LikelyFault::NoCommandResponse));
+
+        assert_eq!(
+            report.with_read_back(false).fault,
+            Some(LikelyFault::NoCommandResponse)
+        );
+        let report = run(&[&reset_polls, &[true, false], &[true, false]], false);
+        assert_eq!(report.with_read_back(true).fault, None);
+        assert_eq!(
+            report.with_read_back(false).fault,
+            Some(LikelyFault::RamMismatch)
+        );
+    }
+
+    #[test]
+    fn busy_polarity_override() {
+        // a clone module holding the pin high while busy, for a driver expecting busy low
+        let script = [true, true, false];
+        let bus = Bus::new();
+        let busy = BusyPin::scripted(&script, false);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+        assert_eq!(interface.busy_polarity(true), BusyPolarity::ActiveLow);
+        interface.wait_until_idle(&mut Delay::new(), true);
+        assert_eq!(busy.reads(), 1);
+
+        let busy = BusyPin::scripted(&script, false);
+        let mut interface = Interface::new(busy.clone(), bus.dc(), Pin::new(), Some(0));
+        interface.set_busy_polarity(BusyPolarity::ActiveHigh, true);
+        assert_eq!(interface.busy_polarity(true), BusyPolarity::ActiveHigh);
+        interface.wait_until_idle(&mut Delay::new(), true);
+        assert_eq!(busy.reads(), 3);
+        assert!(!interface.is_busy(true));
+
+        // back to the default of the driver
+        interface.set_busy_polarity(BusyPolarity::ActiveLow, true);
+        assert!(interface.is_busy(true));
+    }
+
+    #[test]
+    fn reset_config_levels() {
+        extern crate std;
+        use std::vec;
+        let default = ResetConfig::new(10_000, 2_000);
+        let reset = |config: Option<ResetConfig>| {
+            let (rst, delay) = (Pin::new(), Delay::new());
+            let busy = BusyPin::new(false);
+            let mut interface = Interface::new(busy, Bus::new().dc(), rst.clone(), None);
+            interface.set_reset_config(config);
+            interface.reset(&mut delay.clone(), default);
+            (rst.levels(), delay.delays())
+        };
+
+        // released, pulled low and released again
+        let delays = vec![10_000, 2_000, 200_000];
+        assert_eq!(reset(None), (vec![true, false, true], delays.clone()));
+
+        // an inverting transistor between the pin and the panel
+        let inverted = ResetConfig {
+            active_low: false,
+            ..default
+        };
+        assert_eq!(reset(Some(inverted)), (vec![false, true, false], delays));
+
+        let triple = ResetConfig {
+            pulses: 3,
+            post_us: 20_000,
+            ..default
+        };
+        assert_eq!(
+            reset(Some(triple)),
+            (
+                vec![true, false, true, false, true, false, true],
+                vec![10_000, 2_000, 10_000, 2_000, 10_000, 2_000, 20_000]
+            )
+        );
+        let none = ResetConfig {
+            pulses: 0,
+            ..default
+        };
+        assert_eq!(reset(Some(none)), reset(None));
+
+        // the self test pulses the same way, watching the last pulse
+        let rst = Pin::new();
+        let busy = BusyPin::new(true);
+        let mut interface = Interface::new(busy, Bus::new().dc(), rst.clone(), None);
+        interface.set_reset_config(Some(ResetConfig {
+            active_low: false,
+            ..triple
+        }));
+        let _ = interface.self_test(
+            &mut Bus::new().spi(),
+            &mut Delay::new(),
+            true,
+            default,
+            Command::WriteRam,
+        );
+        assert_eq!(rst.levels(), [false, true, false, true, false, true, false]);
+    }
+
+    #[test]
+    fn self_test_detects_busy_polarity() {
+        use crate::busy::LikelyFault;
+        let run = |script: &[&[bool]], level: bool, polarity: BusyPolarity| {
+            let bus = Bus::new();
+            let busy = BusyPin::new(level);
+            for part in script {
+                busy.push(part);
+            }
+            let mut interface = Interface::new(busy, bus.dc(), Pin::new(), None);
+            interface.set_busy_polarity(polarity, true);
+            interface
+                .self_test(
+                    &mut bus.spi(),
+                    &mut Delay::new(),
+                    true,
+                    ResetConfig::new(10_000, 1_000),
+                    Command::WriteRam,
+                )
+                .unwrap()
+        };
+        // genuine module: idle high, busy low after the reset and the command
+        let genuine: [&[bool]; 3] = [&[true; 10], &[false, false, true], &[false, true]];
+        let report = run(&genuine, true, BusyPolarity::ActiveLow);
+        assert_eq!(report.busy_polarity, Some(BusyPolarity::ActiveLow));
+        assert_eq!(report.fault, None);
+
+        // the clone pulses high after the reset and settles low
+        let clone: [&[bool]; 2] = [&[false; 10], &[true, true]];
+        let report = run(&clone, false, BusyPolarity::ActiveLow);
+        assert_eq!(report.busy_polarity, Some(BusyPolarity::ActiveHigh));
+        assert_eq!(report.fault, Some(LikelyFault::InvertedBusyPolarity));
+
+        let clone: [&[bool]; 3] = [&[false; 10], &[true, true, false], &[true, false]];
+        let report = run(&clone, false, BusyPolarity::ActiveHigh);
+        assert_eq!(report.busy_polarity, Some(BusyPolarity::ActiveHigh));
+        assert!(report.passed());
+
+        // a line which never changes tells nothing
+        let report = run(&[], true, BusyPolarity::ActiveLow);
+        assert_eq!(report.busy_polarity, None);
+        assert_eq!(report.fault, Some(LikelyFault::BusyStuckHigh));
     }
 }
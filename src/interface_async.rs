@@ -0,0 +1,177 @@
+use crate::error::{self, Context, Error};
+use crate::reset::ResetConfig;
+use crate::traits::Command;
+use core::marker::PhantomData;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+/// The connection interface of the async drivers
+///
+/// The same transfers as [DisplayInterface](crate::interface::DisplayInterface), but the
+/// spi writes and delays are awaited and the busy pin is waited for with [Wait]
+/// instead of being polled. The busy modes, retries and transfer size limits of the
+/// blocking interface aren't supported.
+///
+/// SINGLE_BYTE_WRITE defines if a data block is written bytewise
+/// or blockwise to the spi device
+pub(crate) struct AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool> {
+    /// SPI
+    _spi: PhantomData<SPI>,
+    /// DELAY
+    _delay: PhantomData<DELAY>,
+    /// Busy pin, waited for until it shows the idle level
+    busy: BUSY,
+    /// Data/Command Control Pin (High for data, Low for command)
+    dc: DC,
+    /// Pin for Resetting
+    rst: RST,
+    /// Set when a spi write failed, cleared by a reset
+    poisoned: bool,
+    /// Operation attached to failed transfers
+    context: Context,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>
+    AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>
+where
+    SPI: SpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates a new `AsyncDisplayInterface` struct
+    pub(crate) fn new(busy: BUSY, dc: DC, rst: RST) -> Self {
+        AsyncDisplayInterface {
+            _spi: PhantomData,
+            _delay: PhantomData,
+            busy,
+            dc,
+            rst,
+            poisoned: false,
+            // the drivers initialise the display right after creating the interface
+            context: Context::new(error::Operation::Init),
+        }
+    }
+
+    /// Marks `operation` as the one of the driver, failing transfers report it until
+    /// the next one
+    pub(crate) fn set_operation(&mut self, operation: error::Operation) {
+        self.context.operation = Some(operation);
+    }
+
+    /// Sends a command without parameters, see [DisplayInterface::cmd](crate::interface::DisplayInterface::cmd)
+    pub(crate) async fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), Error<SPI::Error>> {
+        // low for commands
+        let _ = self.dc.set_low();
+        self.write(spi, &[command.address()]).await
+    }
+
+    /// Sends data, one byte per write with SINGLE_BYTE_WRITE
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) async fn data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        // high for data
+        let _ = self.dc.set_high();
+        if SINGLE_BYTE_WRITE {
+            for val in data.iter().copied() {
+                self.write(spi, &[val]).await?;
+            }
+            Ok(())
+        } else {
+            self.write(spi, data).await
+        }
+    }
+
+    /// Sends a command and its parameters, the parameters in one write
+    ///
+    /// Returns [Error::InvalidDataLength] without sending anything if `data` doesn't
+    /// have the [expected length](Command::expected_data_len) of the command, like
+    /// [DisplayInterface::cmd_with_data](crate::interface::DisplayInterface::cmd_with_data).
+    pub(crate) async fn cmd_with_data<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        if !command.expected_data_len().accepts(data.len()) {
+            return Err(Error::InvalidDataLength);
+        }
+        self.cmd(spi, command).await?;
+        // high for data
+        let _ = self.dc.set_high();
+        self.write(spi, data).await
+    }
+
+    /// Sends the same byte of data `repetitions` times, one byte per write
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) async fn data_x_times(
+        &mut self,
+        spi: &mut SPI,
+        val: u8,
+        repetitions: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        // high for data
+        let _ = self.dc.set_high();
+        for _ in 0..repetitions {
+            self.write(spi, &[val]).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits until the busy pin shows the idle level
+    ///
+    /// `is_busy_low` like for [DisplayInterface::wait_until_idle](crate::interface::DisplayInterface::wait_until_idle).
+    /// An error of the pin ends the wait, just as a failed read of the pin counts as
+    /// idle for the blocking drivers.
+    pub(crate) async fn wait_until_idle(&mut self, is_busy_low: bool) {
+        let _ = if is_busy_low {
+            self.busy.wait_for_high().await
+        } else {
+            self.busy.wait_for_low().await
+        };
+    }
+
+    /// Resets the device with `config`, see [DisplayInterface::reset](crate::interface::DisplayInterface::reset)
+    pub(crate) async fn reset(&mut self, delay: &mut DELAY, config: ResetConfig) {
+        for _ in 0..config.pulses.max(1) {
+            self.set_reset_line(&config, false);
+            delay.delay_us(config.pre_us).await;
+            self.set_reset_line(&config, true);
+            delay.delay_us(config.pulse_us).await;
+        }
+        self.set_reset_line(&config, false);
+        self.poisoned = false;
+        delay.delay_us(config.post_us).await;
+    }
+
+    /// Drives the reset line to the level holding the controller in reset if `asserted`
+    fn set_reset_line(&mut self, config: &ResetConfig, asserted: bool) {
+        let _ = if asserted == config.active_low {
+            self.rst.set_low()
+        } else {
+            self.rst.set_high()
+        };
+    }
+
+    // A failed write may leave the controller in the middle of a transfer, so all
+    // following writes are refused until the device was reset.
+    async fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        if self.poisoned {
+            return Err(Error::NeedsReset(self.context));
+        }
+        let context = self.context;
+        let result = spi.write(data).await.map_err(|e| Error::Spi(e, context));
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+}
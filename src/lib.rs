@@ -10,9 +10,16 @@
 //!
 //! # Example
 //!
-//!```rust, no_run
+#![cfg_attr(
+    all(feature = "epd1in54", feature = "graphics"),
+    doc = "```rust, no_run"
+)]
+#![cfg_attr(
+    not(all(feature = "epd1in54", feature = "graphics")),
+    doc = "```ignore"
+)]
 //!# use embedded_hal_mock::eh1::*;
-//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -63,52 +70,165 @@
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "graphics")]
+pub mod animation;
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
+#[cfg(all(feature = "linux", unix))]
+pub mod linux;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "calibration")]
+pub mod calibration;
+
+#[cfg(feature = "delay-adapter")]
+pub mod delay;
+
+mod frame;
 mod traits;
 
+pub mod busy;
+pub mod checksum;
 pub mod color;
+pub mod command_log;
+pub mod error;
+pub mod panels;
+pub mod policy;
+pub use error::Error;
+pub mod reset;
+pub mod shadow;
+pub mod update_sequence;
+pub mod vcom;
+pub mod voltage;
 
 /// Interface for the physical connection between display and the controlling device
 mod interface;
+/// Async version of the interface for the drivers of the `async` feature
+#[cfg(all(feature = "async", any(feature = "epd4in2", feature = "epd2in9_v2")))]
+mod interface_async;
 
+#[cfg(feature = "epd13in3k")]
+pub mod epd13in3k;
+#[cfg(feature = "epd1in54")]
 pub mod epd1in54;
+#[cfg(feature = "epd1in54_v2")]
 pub mod epd1in54_v2;
+#[cfg(feature = "epd1in54b")]
 pub mod epd1in54b;
+#[cfg(feature = "epd1in54c")]
 pub mod epd1in54c;
+#[cfg(feature = "epd2in13")]
 pub mod epd2in13_v2;
+#[cfg(feature = "epd2in13b_v4")]
+pub mod epd2in13b_v4;
+#[cfg(feature = "epd2in13bc")]
 pub mod epd2in13bc;
+#[cfg(feature = "epd2in13d")]
+pub mod epd2in13d;
+#[cfg(feature = "epd2in15g")]
+pub mod epd2in15g;
+#[cfg(feature = "epd2in36g")]
+pub mod epd2in36g;
+#[cfg(feature = "epd2in66b")]
 pub mod epd2in66b;
+#[cfg(feature = "epd2in7_v2")]
+pub mod epd2in7_v2;
+#[cfg(feature = "epd2in7b")]
 pub mod epd2in7b;
+#[cfg(feature = "epd2in9")]
 pub mod epd2in9;
+#[cfg(all(feature = "epd2in9", feature = "epd2in9_v2"))]
+pub mod epd2in9_any;
+#[cfg(feature = "epd2in9_v2")]
 pub mod epd2in9_v2;
+#[cfg(feature = "epd2in9b_v3")]
+pub mod epd2in9b_v3;
+#[cfg(feature = "epd2in9bc")]
 pub mod epd2in9bc;
+#[cfg(feature = "epd2in9d")]
 pub mod epd2in9d;
+#[cfg(feature = "epd2in9g")]
+pub mod epd2in9g;
+#[cfg(feature = "epd3in52")]
+pub mod epd3in52;
+#[cfg(feature = "epd3in7")]
 pub mod epd3in7;
+#[cfg(feature = "epd4in2")]
 pub mod epd4in2;
+#[cfg(feature = "epd5in65f")]
 pub mod epd5in65f;
+#[cfg(feature = "epd5in79")]
+pub mod epd5in79;
+#[cfg(feature = "epd5in83_v2")]
 pub mod epd5in83_v2;
+#[cfg(feature = "epd5in83b_v2")]
 pub mod epd5in83b_v2;
+#[cfg(feature = "epd7in5")]
 pub mod epd7in5;
+#[cfg(feature = "epd7in5_hd")]
 pub mod epd7in5_hd;
+#[cfg(feature = "epd7in5_v2")]
 pub mod epd7in5_v2;
+#[cfg(feature = "epd7in5b_v2")]
 pub mod epd7in5b_v2;
+#[cfg(feature = "epd7in5b_v2")]
 pub use epd7in5b_v2 as epd7in5b_v3;
 
+#[cfg(feature = "graphics")]
+pub mod test_pattern;
+
+#[cfg(feature = "icons")]
+pub mod icons;
+
+#[cfg(feature = "images")]
+pub mod images;
+
+#[cfg(any(
+    feature = "epd1in54",
+    feature = "epd1in54_v2",
+    feature = "epd2in9",
+    feature = "epd2in9_v2"
+))]
 pub(crate) mod type_a;
 
+#[cfg(any(feature = "epd2in15g", feature = "epd2in9g"))]
+pub(crate) mod g_series;
+
+#[cfg(any(feature = "epd1in54_v2", feature = "epd2in13", feature = "epd2in9_v2"))]
+pub(crate) mod ssd16xx;
+
+#[cfg(any(feature = "epd2in9bc", feature = "epd4in2"))]
+pub(crate) mod uc81xx;
+
+#[cfg(test)]
+pub(crate) mod mock;
+
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::busy::{BusyMode, BusyPolarity};
+    pub use crate::color::{Color, OctColor, Plane, PlaneConvention, QuadColor, TriColor};
+    pub use crate::reset::ResetConfig;
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        ChromaticBand, DriverControl, PeriodicUpdateOptions, QuickRefresh, RamBank, RefreshLut,
+        RowStreaming, WaveshareDisplay, WaveshareThreeColorDisplay,
     };
+    pub use crate::update_sequence::UpdateSequence;
 
     pub use crate::SPI_MODE;
 
+    #[cfg(feature = "async")]
+    pub use crate::traits::AsyncWaveshareDisplay;
+
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::{Display, DisplayLayout, DisplayRotation, FlushableDisplay};
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation};
+    pub use crate::traits::RegionRefresh;
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
@@ -120,8 +240,101 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] ^
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
+///
+/// Panics if the length overflows `usize`. Used for the `BYTECOUNT` of a `Display`
+/// alias this is a compile error with that message instead of an opaque const
+/// evaluation failure:
+///
+///```rust, compile_fail
+/// // a typo'd height
+/// let buffer = [0u8; epd_waveshare::buffer_len(800, usize::MAX / 10)];
+///```
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    match frame_len(width, height, 1) {
+        Some(len) => len,
+        None => panic!("buffer_len: the frame size overflows usize, check width and height"),
+    }
+}
+
+/// Buffer length of a tri-color frame, a black/white plane followed by a chromatic
+/// plane of [buffer_len] each
+///
+/// Panics like [buffer_len] if the length overflows.
+pub const fn tri_buffer_len(width: usize, height: usize) -> usize {
+    match buffer_len(width, height).checked_mul(2) {
+        Some(len) => len,
+        None => panic!("tri_buffer_len: the frame size overflows usize, check width and height"),
+    }
+}
+
+/// Panics if a 1 bpp frame of `width` x `height` doesn't fit into `len` bytes
+///
+/// Meant for a compile time check of a buffer sized by hand, `VarDisplay::new` does the
+/// same check at runtime:
+///
+///```rust
+/// use epd_waveshare::assert_buffer_fits;
+///
+/// const WINDOW: [u8; 12 * 32] = [0xFF; 12 * 32];
+/// const _: () = assert_buffer_fits(96, 32, WINDOW.len());
+///```
+pub const fn assert_buffer_fits(width: usize, height: usize, len: usize) {
+    if !buffer_fits(width, height, 1, len) {
+        panic!("assert_buffer_fits: the buffer is too small for width x height");
+    }
+}
+
+/// Largest height of a 1 bpp frame of `width` pixels fitting into `len` bytes, e.g. to
+/// size a window for a given buffer
+///
+/// A `width` of 0 fits any height, `usize::MAX` is returned.
+pub const fn max_buffer_height(width: usize, len: usize) -> usize {
+    match width.div_ceil(8) {
+        0 => usize::MAX,
+        bytes => len / bytes,
+    }
+}
+
+/// Largest width of a 1 bpp frame of `height` rows fitting into `len` bytes, a multiple
+/// of 8 as rows are padded to whole bytes
+///
+/// A `height` of 0 fits any width, `usize::MAX` is returned.
+pub const fn max_buffer_width(height: usize, len: usize) -> usize {
+    match height {
+        0 => usize::MAX,
+        _ => (len / height).saturating_mul(8),
+    }
+}
+
+/// Bytes of a frame with `bits_per_pixel`, rows padded to whole bytes, `None` on an
+/// overflow
+pub(crate) const fn frame_len(width: usize, height: usize, bits_per_pixel: usize) -> Option<usize> {
+    match width.checked_mul(bits_per_pixel) {
+        Some(bits) => bits.div_ceil(8).checked_mul(height),
+        None => None,
+    }
+}
+
+/// Whether a frame with `bits_per_pixel` fits into `len` bytes, shared by the const
+/// checks and the runtime checks of the displays
+pub(crate) const fn buffer_fits(
+    width: usize,
+    height: usize,
+    bits_per_pixel: usize,
+    len: usize,
+) -> bool {
+    match frame_len(width, height, bits_per_pixel) {
+        Some(needed) => needed <= len,
+        None => false,
+    }
+}
+
+/// Bytes of one row of `width` pixels, the last byte is padded if width is
+/// not divisible by 8 (e.g. 16 bytes for the 122 pixels of the 2.13")
+///
+/// Use it for every row stride and frame size instead of `width / 8`.
+pub const fn line_bytes(width: u32) -> u32 {
+    width.div_ceil(8)
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
@@ -132,3 +345,92 @@ pub const SPI_MODE: Mode = Mode {
     phase: Phase::CaptureOnFirstTransition,
     polarity: Polarity::IdleLow,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // evaluated at compile time
+    const _: () = assert!(buffer_len(122, 250) == 4000);
+    const _: () = assert!(tri_buffer_len(122, 250) == 8000);
+    const _: () = assert_buffer_fits(122, 250, 4000);
+    const _: () = assert!(max_buffer_height(122, 4000) == 250);
+    const _: () = assert!(max_buffer_width(250, 4000) == 128);
+
+    #[test]
+    fn buffer_fits_checks_overflow() {
+        assert!(buffer_fits(16, 8, 1, 16));
+        assert!(!buffer_fits(16, 8, 1, 15));
+        assert!(buffer_fits(16, 8, 4, 64));
+        assert!(buffer_fits(0, 8, 1, 0));
+        assert_eq!(frame_len(usize::MAX, 1, 2), None);
+        assert_eq!(frame_len(800, usize::MAX, 1), None);
+        assert!(!buffer_fits(800, usize::MAX, 1, usize::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows")]
+    fn buffer_len_panics_on_overflow() {
+        let height = core::hint::black_box(usize::MAX / 10);
+        buffer_len(800, height);
+    }
+
+    #[test]
+    fn max_dimensions_round_trip() {
+        for (width, height) in [(1, 1), (122, 250), (800, 480), (13, 7)] {
+            let len = buffer_len(width, height);
+            assert_eq!(max_buffer_height(width, len), height);
+            let max_width = max_buffer_width(height, len);
+            assert!(max_width >= width && max_width.is_multiple_of(8));
+            assert_eq!(buffer_len(max_width, height), len);
+            assert!(buffer_len(max_width + 1, height) > len);
+        }
+        assert_eq!(max_buffer_height(0, 10), usize::MAX);
+        assert_eq!(max_buffer_width(0, 10), usize::MAX);
+    }
+
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn assert_send<T: Send>() {}
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    fn assert_sync<T: Sync>() {}
+
+    // a driver can be moved to the thread talking to the panel when its spi device,
+    // pins and delay can, and the buffers to and from the rendering thread
+    #[test]
+    #[allow(unused_imports)]
+    fn drivers_and_buffers_are_send() {
+        use embedded_hal_mock::eh1::{delay::NoopDelay, pin::Mock as Pin, spi::Mock as Spi};
+        #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+        type Spi8 = Spi<u8>;
+
+        #[cfg(feature = "epd4in2")]
+        assert_send::<epd4in2::Epd4in2<Spi8, Pin, Pin, Pin, NoopDelay>>();
+        #[cfg(feature = "epd1in54")]
+        assert_send::<epd1in54::Epd1in54<Spi8, Pin, Pin, Pin, NoopDelay>>();
+        #[cfg(feature = "epd2in9bc")]
+        assert_send::<epd2in9bc::Epd2in9bc<Spi8, Pin, Pin, Pin, NoopDelay>>();
+        #[cfg(feature = "epd7in5b_v2")]
+        assert_send::<epd7in5b_v2::Epd7in5<Spi8, Pin, Pin, Pin, NoopDelay>>();
+        #[cfg(feature = "epd2in15g")]
+        assert_send::<epd2in15g::Epd2in15g<Spi8, Pin, Pin, Pin, NoopDelay>>();
+        #[cfg(any(feature = "epd2in13_v2", feature = "epd2in13_v3"))]
+        assert_send::<epd2in13_v2::Epd2in13<Spi8, Pin, Pin, Pin, NoopDelay>>();
+
+        #[cfg(all(feature = "graphics", feature = "epd4in2"))]
+        {
+            use crate::epd4in2::Display4in2;
+            assert_send::<Display4in2>();
+            assert_sync::<Display4in2>();
+        }
+        #[cfg(feature = "graphics")]
+        {
+            use crate::color::{Color, TriColor};
+            use crate::graphics::{OwnedVarDisplay, StaticDisplay, VarDisplay};
+            assert_send::<StaticDisplay<16, 8, false, 16, Color>>();
+            assert_send::<VarDisplay<'static, Color>>();
+            assert_sync::<VarDisplay<'static, TriColor>>();
+            assert_send::<OwnedVarDisplay<64, Color>>();
+            assert_sync::<OwnedVarDisplay<64, Color>>();
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Helpers to set up the displays on linux boards like the Raspberry Pi
+//!
+//! Only available with the `linux` feature, which needs `std` and pulls in
+//! [linux-embedded-hal](https://crates.io/crates/linux-embedded-hal).
+//! Activate spi and gpio in `raspi-config` first. Exporting sysfs pins usually
+//! needs root, see <https://github.com/rust-embedded/rust-sysfs-gpio/issues/5>.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!use epd_waveshare::{epd4in2::*, linux::*, prelude::*};
+//!
+//!# fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!let mut spi = spidev_default("/dev/spidev0.0")?;
+//!let pins = LinuxEpdPins::waveshare_hat().export()?;
+//!let mut delay = Delay;
+//!
+//!let mut epd = Epd4in2::new(&mut spi, pins.busy, pins.dc, pins.rst, &mut delay, None)?;
+//!epd.clear_frame(&mut spi, &mut delay)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+extern crate std;
+
+use core::fmt;
+use linux_embedded_hal::{
+    spidev::{SpiModeFlags, SpidevOptions},
+    sysfs_gpio::Direction,
+    SysfsPin, SysfsPinError,
+};
+use std::path::Path;
+
+pub use linux_embedded_hal::{Delay, SPIError, SpidevDevice};
+
+/// Spi clock used by [spidev_default], all displays handle 4 MHz
+pub const DEFAULT_SPI_SPEED_HZ: u32 = 4_000_000;
+
+/// Opens and configures a spidev device with the settings recommended for the displays
+///
+/// 8 bits per word, spi mode 0 and [DEFAULT_SPI_SPEED_HZ]. The chip select of the
+/// device (e.g. CE0 for `/dev/spidev0.0`) is driven by the kernel.
+pub fn spidev_default<P: AsRef<Path>>(path: P) -> Result<SpidevDevice, SPIError> {
+    let mut spi = SpidevDevice::open(path)?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(DEFAULT_SPI_SPEED_HZ)
+        .mode(SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options)?;
+    Ok(spi)
+}
+
+/// Builder for the sysfs gpio pins of a display, numbered by BCM gpio
+///
+/// Starts with the wiring of the Waveshare e-Paper HAT and driver boards,
+/// change single pins for other wirings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinuxEpdPins {
+    cs: Option<u64>,
+    busy: u64,
+    dc: u64,
+    rst: u64,
+}
+
+/// The exported pins, ready to be passed to the `new` of the drivers
+pub struct EpdPins {
+    /// Manually driven chip select, only if one was configured
+    pub cs: Option<SysfsPin>,
+    /// Busy input
+    pub busy: SysfsPin,
+    /// Data/Command output
+    pub dc: SysfsPin,
+    /// Reset output
+    pub rst: SysfsPin,
+}
+
+impl Default for LinuxEpdPins {
+    fn default() -> Self {
+        Self::waveshare_hat()
+    }
+}
+
+impl LinuxEpdPins {
+    /// Pins of the Waveshare e-Paper HAT: busy 24, dc 25, rst 17, chip select by spidev
+    pub fn waveshare_hat() -> Self {
+        Self {
+            cs: None,
+            busy: 24,
+            dc: 25,
+            rst: 17,
+        }
+    }
+
+    /// Drives the chip select from this gpio instead of leaving it to spidev
+    pub fn cs(mut self, pin: u64) -> Self {
+        self.cs = Some(pin);
+        self
+    }
+
+    /// Sets the busy input
+    pub fn busy(mut self, pin: u64) -> Self {
+        self.busy = pin;
+        self
+    }
+
+    /// Sets the data/command output
+    pub fn dc(mut self, pin: u64) -> Self {
+        self.dc = pin;
+        self
+    }
+
+    /// Sets the reset output
+    pub fn rst(mut self, pin: u64) -> Self {
+        self.rst = pin;
+        self
+    }
+
+    /// Exports all pins and sets their directions, the outputs start high
+    pub fn export(self) -> Result<EpdPins, SysfsPinError> {
+        let cs = match self.cs {
+            Some(pin) => Some(export(pin, Direction::High)?),
+            None => None,
+        };
+        Ok(EpdPins {
+            cs,
+            busy: export(self.busy, Direction::In)?,
+            dc: export(self.dc, Direction::High)?,
+            rst: export(self.rst, Direction::High)?,
+        })
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for crate::Error<E> {}
+
+fn export(number: u64, direction: Direction) -> Result<SysfsPin, SysfsPinError> {
+    let pin = SysfsPin::new(number);
+    pin.0.export()?;
+    // udev needs a moment to apply the permissions of the new pin
+    while !pin.0.is_exported() {}
+    pin.0.set_direction(direction)?;
+    Ok(pin)
+}
@@ -0,0 +1,351 @@
+//! Recording test doubles for the driver tests
+//!
+//! The SPI device and the DC pin share a [`Bus`] so that every write can be
+//! classified as command or data, just like the controller sees it.
+//! Consecutive data writes are merged into a single [`Transfer::Data`] entry,
+//! which makes the recorded traffic independent of `SINGLE_BYTE_WRITE` and of
+//! any chunking done by the interface.
+
+#![allow(dead_code)]
+
+extern crate std;
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{ErrorType as PinErrorType, InputPin, OutputPin},
+    spi::{ErrorKind, ErrorType as SpiErrorType, Operation, SpiDevice},
+};
+use std::{collections::VecDeque, rc::Rc, vec::Vec};
+
+/// A single transfer as seen by the controller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Transfer {
+    Command(u8),
+    Data(Vec<u8>),
+    /// Bytes read from the controller
+    Read(Vec<u8>),
+}
+
+#[derive(Default)]
+struct BusState {
+    dc_high: bool,
+    transfers: Vec<Transfer>,
+    /// Number of calls to `SpiDevice::write` so far
+    writes: usize,
+    /// Number of spi transactions so far, each one asserts CS once
+    transactions: usize,
+    /// Length of every successful write so far
+    write_sizes: Vec<usize>,
+    /// Make the n-th write (0-based) fail
+    fail_at: Option<usize>,
+    /// Number of recorded transfers at every read of a bus attached [`BusyPin`]
+    busy_reads: Vec<usize>,
+    /// Bytes returned by the next spi reads, zeros once empty
+    replies: VecDeque<u8>,
+}
+
+/// Shared state between [`Spi`] and the DC [`Pin`]
+#[derive(Clone, Default)]
+pub(crate) struct Bus(Rc<RefCell<BusState>>);
+
+impl Bus {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn spi(&self) -> Spi {
+        Spi(self.clone())
+    }
+
+    pub(crate) fn dc(&self) -> DcPin {
+        DcPin(self.clone())
+    }
+
+    /// A busy pin always reading `level` whose reads are ordered with the transfers
+    pub(crate) fn busy(&self, level: bool) -> BusyPin {
+        let pin = BusyPin::new(level);
+        pin.0.borrow_mut().bus = Some(self.clone());
+        pin
+    }
+
+    /// Let the n-th spi write (counted from now on) fail
+    pub(crate) fn fail_after(&self, writes: usize) {
+        let mut state = self.0.borrow_mut();
+        state.fail_at = Some(state.writes + writes);
+    }
+
+    /// Let the controller answer the next spi reads with `bytes`
+    pub(crate) fn reply(&self, bytes: &[u8]) {
+        self.0.borrow_mut().replies.extend(bytes);
+    }
+
+    /// Number of spi writes so far
+    pub(crate) fn writes(&self) -> usize {
+        self.0.borrow().writes
+    }
+
+    /// Number of spi transactions, i.e. CS low/high pairs, since the last [`Bus::clear`]
+    pub(crate) fn cs_assertions(&self) -> usize {
+        self.0.borrow().transactions
+    }
+
+    /// Length of every successful spi write so far, not reset by [`Bus::clear`]
+    pub(crate) fn write_sizes(&self) -> Vec<usize> {
+        self.0.borrow().write_sizes.clone()
+    }
+
+    /// Forget everything recorded so far
+    pub(crate) fn clear(&self) {
+        let mut state = self.0.borrow_mut();
+        state.transfers.clear();
+        state.busy_reads.clear();
+        state.transactions = 0;
+    }
+
+    pub(crate) fn transfers(&self) -> Vec<Transfer> {
+        self.0.borrow().transfers.clone()
+    }
+
+    /// All command bytes in the order they were sent
+    pub(crate) fn commands(&self) -> Vec<u8> {
+        self.0
+            .borrow()
+            .transfers
+            .iter()
+            .filter_map(|t| match t {
+                Transfer::Command(c) => Some(*c),
+                Transfer::Data(_) | Transfer::Read(_) => None,
+            })
+            .collect()
+    }
+
+    /// Data sent right after the n-th occurrence (0-based) of `command`
+    pub(crate) fn data_after_nth(&self, command: u8, n: usize) -> Option<Vec<u8>> {
+        let state = self.0.borrow();
+        let position = state
+            .transfers
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| **t == Transfer::Command(command))
+            .nth(n)?
+            .0;
+        match state.transfers.get(position + 1) {
+            Some(Transfer::Data(data)) => Some(data.clone()),
+            _ => Some(Vec::new()),
+        }
+    }
+
+    /// Data sent right after the first occurrence of `command`
+    pub(crate) fn data_after(&self, command: u8) -> Option<Vec<u8>> {
+        self.data_after_nth(command, 0)
+    }
+
+    /// Whether the busy pin was read right after the first occurrence of `command`,
+    /// before anything else was transferred
+    pub(crate) fn waited_after(&self, command: u8) -> bool {
+        let state = self.0.borrow();
+        state
+            .transfers
+            .iter()
+            .position(|t| *t == Transfer::Command(command))
+            .is_some_and(|position| state.busy_reads.contains(&(position + 1)))
+    }
+
+    /// Number of recorded transfers at every read of the busy pin
+    pub(crate) fn busy_reads(&self) -> Vec<usize> {
+        self.0.borrow().busy_reads.clone()
+    }
+
+    /// Number of times `command` was sent
+    pub(crate) fn count(&self, command: u8) -> usize {
+        self.commands().iter().filter(|&&c| c == command).count()
+    }
+}
+
+/// Recording spi device
+pub(crate) struct Spi(Bus);
+
+impl SpiErrorType for Spi {
+    type Error = ErrorKind;
+}
+
+impl SpiDevice for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        let mut state = self.0 .0.borrow_mut();
+        state.transactions += 1;
+        for op in operations {
+            if let Operation::Read(bytes) = op {
+                for byte in bytes.iter_mut() {
+                    *byte = state.replies.pop_front().unwrap_or(0);
+                }
+                state.transfers.push(Transfer::Read(bytes.to_vec()));
+            }
+            if let Operation::Write(bytes) = op {
+                if state.fail_at == Some(state.writes) {
+                    state.fail_at = None;
+                    state.writes += 1;
+                    return Err(ErrorKind::Other);
+                }
+                state.writes += 1;
+                state.write_sizes.push(bytes.len());
+                if state.dc_high {
+                    if let Some(Transfer::Data(data)) = state.transfers.last_mut() {
+                        data.extend_from_slice(bytes);
+                    } else {
+                        state.transfers.push(Transfer::Data(bytes.to_vec()));
+                    }
+                } else {
+                    for byte in bytes.iter() {
+                        state.transfers.push(Transfer::Command(*byte));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Data/Command pin which tells the [`Bus`] how to classify writes
+pub(crate) struct DcPin(Bus);
+
+impl PinErrorType for DcPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for DcPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().dc_high = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0 .0.borrow_mut().dc_high = true;
+        Ok(())
+    }
+}
+
+/// Output pin recording every level it was set to
+#[derive(Clone, Default)]
+pub(crate) struct Pin(Rc<RefCell<Vec<bool>>>);
+
+impl Pin {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn levels(&self) -> Vec<bool> {
+        self.0.borrow().clone()
+    }
+}
+
+impl PinErrorType for Pin {
+    type Error = Infallible;
+}
+
+impl OutputPin for Pin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(true);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct BusyState {
+    script: VecDeque<bool>,
+    level: bool,
+    reads: usize,
+    bus: Option<Bus>,
+}
+
+/// Busy pin returning a scripted sequence of levels, then a fixed level
+#[derive(Clone, Default)]
+pub(crate) struct BusyPin(Rc<RefCell<BusyState>>);
+
+impl BusyPin {
+    /// A pin which always reads `level`
+    pub(crate) fn new(level: bool) -> Self {
+        Self::scripted(&[], level)
+    }
+
+    /// A pin which reads the levels of `script` first and `level` afterwards
+    pub(crate) fn scripted(script: &[bool], level: bool) -> Self {
+        BusyPin(Rc::new(RefCell::new(BusyState {
+            script: script.iter().copied().collect(),
+            level,
+            reads: 0,
+            bus: None,
+        })))
+    }
+
+    /// Append levels to the script
+    pub(crate) fn push(&self, script: &[bool]) {
+        self.0.borrow_mut().script.extend(script.iter().copied());
+    }
+
+    /// Number of times the level was read
+    pub(crate) fn reads(&self) -> usize {
+        self.0.borrow().reads
+    }
+
+    fn read(&self) -> bool {
+        let mut state = self.0.borrow_mut();
+        state.reads += 1;
+        if let Some(bus) = &state.bus {
+            let mut bus = bus.0.borrow_mut();
+            let transfers = bus.transfers.len();
+            bus.busy_reads.push(transfers);
+        }
+        let level = state.level;
+        state.script.pop_front().unwrap_or(level)
+    }
+}
+
+impl PinErrorType for BusyPin {
+    type Error = Infallible;
+}
+
+impl InputPin for BusyPin {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.read())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.read())
+    }
+}
+
+/// Delay recording every requested duration in us
+#[derive(Clone, Default)]
+pub(crate) struct Delay(Rc<RefCell<Vec<u32>>>);
+
+impl Delay {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// All delays in us
+    pub(crate) fn delays(&self) -> Vec<u32> {
+        self.0.borrow().clone()
+    }
+}
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.0.borrow_mut().push(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.borrow_mut().push(ms.saturating_mul(1000));
+    }
+}
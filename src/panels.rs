@@ -0,0 +1,89 @@
+//! Capabilities of the supported panels
+//!
+//! Every driver module has a `CAPABILITIES` const, [SUPPORTED_PANELS] collects the ones of
+//! the enabled panel features, e.g. for a hardware picker:
+//!
+//!```rust
+//! use epd_waveshare::panels::SUPPORTED_PANELS;
+//!
+//! for panel in SUPPORTED_PANELS.iter().filter(|panel| panel.partial) {
+//!     assert!(panel.width > 0 && panel.height > 0);
+//! }
+//!```
+//!
+//! The matrix of all panels is checked in as `tests/golden/support_matrix.md`.
+
+/// Size and refresh capabilities of a panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelInfo {
+    /// Name of the driver module, e.g. `"epd4in2"`
+    pub name: &'static str,
+    /// Width of the display
+    pub width: u32,
+    /// Height of the display
+    pub height: u32,
+    /// Number of colors the panel can show, including black and white
+    pub colors: u8,
+    /// `update_partial_frame` writes a window instead of the whole frame
+    pub partial: bool,
+    /// The driver has a quick refresh, via `RefreshLut::Quick` or `QuickRefresh`
+    pub quick_refresh: bool,
+}
+
+macro_rules! registry {
+    ($($feature:literal => $module:ident,)*) => {
+        /// Capabilities of all panels enabled by the crate features, sorted by name
+        pub const SUPPORTED_PANELS: &[PanelInfo] = &[
+            $(
+                #[cfg(feature = $feature)]
+                crate::$module::CAPABILITIES,
+            )*
+        ];
+    };
+}
+
+registry! {
+    "epd13in3k" => epd13in3k,
+    "epd1in54" => epd1in54,
+    "epd1in54_v2" => epd1in54_v2,
+    "epd1in54b" => epd1in54b,
+    "epd1in54c" => epd1in54c,
+    "epd2in13" => epd2in13_v2,
+    "epd2in13b_v4" => epd2in13b_v4,
+    "epd2in13bc" => epd2in13bc,
+    "epd2in13d" => epd2in13d,
+    "epd2in15g" => epd2in15g,
+    "epd2in36g" => epd2in36g,
+    "epd2in66b" => epd2in66b,
+    "epd2in7_v2" => epd2in7_v2,
+    "epd2in7b" => epd2in7b,
+    "epd2in9" => epd2in9,
+    "epd2in9_v2" => epd2in9_v2,
+    "epd2in9b_v3" => epd2in9b_v3,
+    "epd2in9bc" => epd2in9bc,
+    "epd2in9d" => epd2in9d,
+    "epd2in9g" => epd2in9g,
+    "epd3in52" => epd3in52,
+    "epd3in7" => epd3in7,
+    "epd4in2" => epd4in2,
+    "epd5in65f" => epd5in65f,
+    "epd5in79" => epd5in79,
+    "epd5in83_v2" => epd5in83_v2,
+    "epd5in83b_v2" => epd5in83b_v2,
+    "epd7in5" => epd7in5,
+    "epd7in5_hd" => epd7in5_hd,
+    "epd7in5_v2" => epd7in5_v2,
+    "epd7in5b_v2" => epd7in5b_v2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_sorted_and_unique() {
+        for pair in SUPPORTED_PANELS.windows(2) {
+            assert!(pair[0].name < pair[1].name, "{:?}", pair);
+        }
+    }
+}
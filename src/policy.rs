@@ -0,0 +1,459 @@
+//! Scheduling full refreshes in between quick refreshes
+//!
+//! Quick (partial) refreshes leave some ghosting behind, which a full refresh
+//! cleans up again. [`RefreshPolicy`] decides for every refresh which of both is
+//! due: a full refresh is forced after a number of quick refreshes, after some
+//! time or when the temperature changed, as the waveforms depend on it.
+//!
+//! The crate has no clock, the time is passed in by the caller as ticks of any
+//! unit (e.g. seconds or milliseconds since boot) with every [`RefreshHint`].
+//!
+//! The policy can be used standalone with [`RefreshPolicy::next_refresh`], or
+//! together with a driver with [`WaveshareDisplay::display_with_policy`], which
+//! refreshes with the [selected LUT](WaveshareDisplay::display_frame_with).
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd4in2::*, policy::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let frame = [0u8; 15000];
+//!# let seconds_since_boot = || 0;
+//!
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!
+//!// a full refresh after 10 quick ones or at least every 10 minutes
+//!let mut policy = RefreshPolicy::new()
+//!    .with_max_quick_refreshes(10)
+//!    .with_max_elapsed(600);
+//!
+//!epd.update_frame(&mut spi, &frame, &mut delay)?;
+//!let hint = RefreshHint::new(seconds_since_boot());
+//!epd.display_with_policy(&mut spi, &mut delay, &mut policy, hint)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # Refresh guard
+//!
+//! Refreshing a panel too often shortens its lifetime, the datasheets recommend at
+//! least 180 s between full refreshes. Every driver module has this interval as
+//! `MIN_REFRESH_INTERVAL` in seconds. The opt-in refresh guard of the drivers makes a
+//! scheduling bug fail instead of wearing out the panel:
+//!
+//! - `set_refresh_guard(ticks_per_second)` enables it, the crate has no clock
+//! - `note_time(now_ticks)` passes in the time of a monotonic clock
+//! - `display_frame` (and everything refreshing through it) then returns
+//!   [`Error::RefreshTooSoon`](crate::Error::RefreshTooSoon) without sending anything
+//!   if the last refresh is less than `MIN_REFRESH_INTERVAL` ago
+//! - `force_display_frame` refreshes anyway
+//!
+//! Quick refreshes aren't limited by the guard.
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd4in2::*, prelude::*, Error};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let millis_since_boot = || 0;
+//!
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!epd.set_refresh_guard(1000);
+//!
+//!epd.note_time(millis_since_boot());
+//!match epd.display_frame(&mut spi, &mut delay) {
+//!    Err(Error::RefreshTooSoon) => { /* try again later */ }
+//!    result => result?,
+//!}
+//!# Ok(())
+//!# }
+//!```
+
+use crate::traits::RefreshLut;
+#[cfg(doc)]
+use crate::traits::WaveshareDisplay;
+
+/// Kind of a refresh decided by a [`RefreshPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshKind {
+    /// Full refresh, cleaning up the ghosting
+    Full,
+    /// Quick (partial) refresh
+    Quick,
+}
+
+impl From<RefreshKind> for RefreshLut {
+    fn from(kind: RefreshKind) -> Self {
+        match kind {
+            RefreshKind::Full => RefreshLut::Full,
+            RefreshKind::Quick => RefreshLut::Quick,
+        }
+    }
+}
+
+/// Refreshes done by a driver, for estimating the wear of the panel
+///
+/// Panels are rated for a limited number of refreshes. Every driver counts the
+/// refreshes it starts, see `refresh_counters` of the driver. The counters live in
+/// RAM only: persist them, e.g. to flash, and seed them back with
+/// `restore_refresh_counters` after a reboot. They saturate at `u32::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RefreshCounters {
+    /// Refreshes of the whole panel with the full LUT
+    pub full: u32,
+    /// Refreshes of the whole panel with the quick LUT
+    pub quick: u32,
+    /// Refreshes of a window of the panel
+    pub partial: u32,
+}
+
+impl RefreshCounters {
+    /// All refreshes, saturating at `u32::MAX`
+    pub fn total(&self) -> u32 {
+        self.full
+            .saturating_add(self.quick)
+            .saturating_add(self.partial)
+    }
+
+    /// Counts a refresh of the whole panel
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn count(&mut self, kind: RefreshKind) {
+        let counter = match kind {
+            RefreshKind::Full => &mut self.full,
+            RefreshKind::Quick => &mut self.quick,
+        };
+        *counter = counter.saturating_add(1);
+    }
+
+    /// Counts a refresh of a window
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    pub(crate) fn count_partial(&mut self) {
+        self.partial = self.partial.saturating_add(1);
+    }
+}
+
+/// State of the application at a refresh, see [`RefreshPolicy::next_refresh`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshHint {
+    /// Current time in ticks of the unit used for [`RefreshPolicy::with_max_elapsed`]
+    pub now: u64,
+    /// Ambient temperature in °C, if known
+    pub temperature: Option<i16>,
+    /// The application asks for a full refresh, e.g. because the whole screen changed
+    pub full: bool,
+}
+
+impl RefreshHint {
+    /// Hint at time `now` without temperature
+    pub const fn new(now: u64) -> Self {
+        RefreshHint {
+            now,
+            temperature: None,
+            full: false,
+        }
+    }
+
+    /// Adds the ambient temperature in °C
+    pub const fn with_temperature(mut self, temperature: i16) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Asks for a full refresh regardless of the policy
+    pub const fn with_full(mut self) -> Self {
+        self.full = true;
+        self
+    }
+}
+
+/// Decides when a full refresh is due in between quick refreshes
+///
+/// Every threshold is optional, without any the policy only refreshes fully for
+/// the first refresh, after [`force_full`](RefreshPolicy::force_full) and when a
+/// [hint](RefreshHint::with_full) asks for it. The first refresh is always a full
+/// one, as the content of the panel is unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RefreshPolicy {
+    max_quick_refreshes: Option<u32>,
+    max_elapsed: Option<u64>,
+    max_temperature_delta: Option<u16>,
+    quick_refreshes: u32,
+    /// time and temperature of the last full refresh, `None` if one is due
+    last_full: Option<(u64, Option<i16>)>,
+}
+
+impl RefreshPolicy {
+    /// Policy without thresholds
+    pub const fn new() -> Self {
+        RefreshPolicy {
+            max_quick_refreshes: None,
+            max_elapsed: None,
+            max_temperature_delta: None,
+            quick_refreshes: 0,
+            last_full: None,
+        }
+    }
+
+    /// Forces a full refresh after `count` quick refreshes in a row
+    pub const fn with_max_quick_refreshes(mut self, count: u32) -> Self {
+        self.max_quick_refreshes = Some(count);
+        self
+    }
+
+    /// Forces a full refresh once `ticks` passed since the last one
+    pub const fn with_max_elapsed(mut self, ticks: u64) -> Self {
+        self.max_elapsed = Some(ticks);
+        self
+    }
+
+    /// Forces a full refresh once the temperature differs by at least `delta` °C from
+    /// the one at the last full refresh
+    ///
+    /// Only hints with a temperature are taken into account.
+    pub const fn with_max_temperature_delta(mut self, delta: u16) -> Self {
+        self.max_temperature_delta = Some(delta);
+        self
+    }
+
+    /// Quick refreshes since the last full refresh
+    pub fn quick_refreshes(&self) -> u32 {
+        self.quick_refreshes
+    }
+
+    /// Makes the next refresh a full one, e.g. after waking the display up
+    pub fn force_full(&mut self) {
+        self.last_full = None;
+    }
+
+    /// Decides the kind of the next refresh and counts it as done
+    pub fn next_refresh(&mut self, hint: RefreshHint) -> RefreshKind {
+        if self.full_due(&hint) {
+            // without a temperature in the hint the last known one stays the reference
+            let temperature = hint
+                .temperature
+                .or(self.last_full.and_then(|(_, temperature)| temperature));
+            self.quick_refreshes = 0;
+            self.last_full = Some((hint.now, temperature));
+            RefreshKind::Full
+        } else {
+            self.quick_refreshes += 1;
+            RefreshKind::Quick
+        }
+    }
+
+    fn full_due(&self, hint: &RefreshHint) -> bool {
+        let Some((last_time, last_temperature)) = self.last_full else {
+            return true;
+        };
+        let count_due = self
+            .max_quick_refreshes
+            .is_some_and(|max| self.quick_refreshes >= max);
+        // a clock going backwards doesn't trigger a full refresh
+        let time_due = self
+            .max_elapsed
+            .is_some_and(|max| hint.now.saturating_sub(last_time) >= max);
+        let temperature_due = match (
+            self.max_temperature_delta,
+            last_temperature,
+            hint.temperature,
+        ) {
+            (Some(max), Some(last), Some(now)) => now.abs_diff(last) >= max,
+            _ => false,
+        };
+        hint.full || count_due || time_due || temperature_due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_refresh_is_full() {
+        let mut policy = RefreshPolicy::new();
+        assert_eq!(policy.next_refresh(RefreshHint::new(0)), RefreshKind::Full);
+        for now in 1..100 {
+            assert_eq!(
+                policy.next_refresh(RefreshHint::new(now)),
+                RefreshKind::Quick
+            );
+        }
+        assert_eq!(policy.quick_refreshes(), 99);
+
+        policy.force_full();
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(100)),
+            RefreshKind::Full
+        );
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(101).with_full()),
+            RefreshKind::Full
+        );
+        assert_eq!(policy.quick_refreshes(), 0);
+    }
+
+    #[test]
+    fn quick_refresh_count() {
+        let mut policy = RefreshPolicy::new().with_max_quick_refreshes(3);
+        let kinds = [
+            RefreshKind::Full,
+            RefreshKind::Quick,
+            RefreshKind::Quick,
+            RefreshKind::Quick,
+            RefreshKind::Full,
+            RefreshKind::Quick,
+        ];
+        for kind in kinds {
+            assert_eq!(policy.next_refresh(RefreshHint::new(0)), kind);
+        }
+
+        // a requested full refresh starts counting anew
+        policy.next_refresh(RefreshHint::new(0));
+        policy.next_refresh(RefreshHint::new(0).with_full());
+        for _ in 0..3 {
+            assert_eq!(policy.next_refresh(RefreshHint::new(0)), RefreshKind::Quick);
+        }
+        assert_eq!(policy.next_refresh(RefreshHint::new(0)), RefreshKind::Full);
+    }
+
+    #[test]
+    fn elapsed_ticks() {
+        let mut policy = RefreshPolicy::new().with_max_elapsed(60);
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1000)),
+            RefreshKind::Full
+        );
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1030)),
+            RefreshKind::Quick
+        );
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1059)),
+            RefreshKind::Quick
+        );
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1060)),
+            RefreshKind::Full
+        );
+        // measured from the last full refresh
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1100)),
+            RefreshKind::Quick
+        );
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(1120)),
+            RefreshKind::Full
+        );
+        // the clock was reset, e.g. by a reboot without resetting the policy
+        assert_eq!(policy.next_refresh(RefreshHint::new(5)), RefreshKind::Quick);
+    }
+
+    #[test]
+    fn temperature_delta() {
+        let mut policy = RefreshPolicy::new().with_max_temperature_delta(5);
+        let at = |temperature| RefreshHint::new(0).with_temperature(temperature);
+        assert_eq!(policy.next_refresh(at(20)), RefreshKind::Full);
+        assert_eq!(policy.next_refresh(at(24)), RefreshKind::Quick);
+        assert_eq!(policy.next_refresh(at(16)), RefreshKind::Quick);
+        assert_eq!(policy.next_refresh(RefreshHint::new(0)), RefreshKind::Quick);
+        assert_eq!(policy.next_refresh(at(15)), RefreshKind::Full);
+        assert_eq!(policy.next_refresh(at(19)), RefreshKind::Quick);
+        assert_eq!(policy.next_refresh(at(-3)), RefreshKind::Full);
+        assert_eq!(
+            policy.next_refresh(RefreshHint::new(0).with_full()),
+            RefreshKind::Full
+        );
+        assert_eq!(policy.next_refresh(at(2)), RefreshKind::Full);
+    }
+
+    #[cfg(feature = "epd4in2")]
+    #[test]
+    fn display_with_policy_forces_full_refresh() {
+        use crate::epd4in2::{command::Command, Epd4in2};
+        use crate::mock::{Bus, BusyPin, Delay, Pin};
+        use crate::traits::WaveshareDisplay;
+
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            BusyPin::new(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+            .unwrap();
+        let mut policy = RefreshPolicy::new().with_max_quick_refreshes(2);
+
+        let vcom = Command::LutForVcom as u8;
+        let mut refresh = |now| {
+            bus.clear();
+            let kind = epd
+                .display_with_policy(&mut spi, &mut delay, &mut policy, RefreshHint::new(now))
+                .unwrap();
+            assert_eq!(bus.count(Command::DisplayRefresh as u8), 1);
+            (kind, bus.count(vcom))
+        };
+        // the full refresh uploads the full LUT and restores the quick one
+        assert_eq!(refresh(0), (RefreshKind::Full, 2));
+        assert_eq!(refresh(1), (RefreshKind::Quick, 0));
+        assert_eq!(refresh(2), (RefreshKind::Quick, 0));
+        assert_eq!(refresh(3), (RefreshKind::Full, 2));
+        assert_eq!(refresh(4), (RefreshKind::Quick, 0));
+        assert_eq!(epd.refresh_lut(), Some(RefreshLut::Quick));
+    }
+
+    #[test]
+    fn refresh_counters_saturate() {
+        let mut counters = RefreshCounters {
+            full: u32::MAX - 1,
+            quick: 1,
+            partial: u32::MAX,
+        };
+        counters.count(RefreshKind::Full);
+        counters.count(RefreshKind::Full);
+        counters.count(RefreshKind::Quick);
+        counters.count_partial();
+        assert_eq!(
+            counters,
+            RefreshCounters {
+                full: u32::MAX,
+                quick: 2,
+                partial: u32::MAX
+            }
+        );
+        assert_eq!(counters.total(), u32::MAX);
+        assert_eq!(
+            RefreshCounters {
+                full: 1,
+                quick: 2,
+                partial: 3
+            }
+            .total(),
+            6
+        );
+    }
+}
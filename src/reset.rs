@@ -0,0 +1,70 @@
+//! Timing and polarity of the hardware reset
+//!
+//! Every driver resets the controller in its initialisation and `wake_up` with the
+//! `RESET_CONFIG` of its module: the reset line is released for `pre_us`, pulled low
+//! for `pulse_us` and released again, followed by `post_us` for the controller to
+//! start. Boards driving the line through an inverting transistor need
+//! `active_low: false`, some panels only come up reliably after a cold boot with
+//! several pulses:
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd4in2::*, prelude::*, reset::ResetConfig};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let reset = ResetConfig {
+//!    active_low: false,
+//!    pulses: 3,
+//!    ..RESET_CONFIG
+//!};
+//!let mut epd =
+//!    Epd4in2::new_with_reset_config(&mut spi, busy_in, dc, rst, &mut delay, None, reset)?;
+//!# Ok(())
+//!# }
+//!```
+
+/// Hardware reset sequence, see [crate::reset]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetConfig {
+    /// Whether the controller is held in reset by a low line, `false` for an inverted one
+    pub active_low: bool,
+    /// Number of reset pulses, 0 is treated as 1
+    pub pulses: u8,
+    /// Time the line is released before every pulse
+    pub pre_us: u32,
+    /// Duration of every pulse
+    pub pulse_us: u32,
+    /// Time the controller gets after the last pulse
+    pub post_us: u32,
+}
+
+impl ResetConfig {
+    /// A single active low pulse of `pulse_us` after `pre_us`, followed by 200ms
+    pub const fn new(pre_us: u32, pulse_us: u32) -> Self {
+        Self {
+            active_low: true,
+            pulses: 1,
+            pre_us,
+            pulse_us,
+            //TODO: the upstream libraries always sleep for 200ms here
+            // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
+            post_us: 200_000,
+        }
+    }
+}
+
+/// The 10ms pulse most of the panels use
+impl Default for ResetConfig {
+    fn default() -> Self {
+        Self::new(10_000, 10_000)
+    }
+}
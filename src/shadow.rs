@@ -0,0 +1,634 @@
+//! Keeping a copy of the frame last sent to the display
+//!
+//! E-paper keeps its image without power, so the application often can't tell
+//! what is shown right now. [ShadowedEpd] wraps a driver and records every frame
+//! and partial window passed to it in a shadow buffer, e.g. to upload it for
+//! remote diagnostics. The RAM for the shadow is only spent when the wrapper is used.
+//!
+//! Partial windows are merged at byte granularity like the controllers do: the
+//! lowest 3 bits of x are ignored and every row of the window takes
+//! [line_bytes(width)](crate::line_bytes) bytes.
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd4in2", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd4in2"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd4in2::*, prelude::*, shadow::ShadowedEpd};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!# let buffer = [0u8; 400 / 8 * 300];
+//!
+//!let epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!let mut epd = ShadowedEpd::<_, { 400 / 8 * 300 }>::wrap(epd);
+//!epd.update_and_display_frame(&mut spi, &buffer, &mut delay)?;
+//!
+//!// what is on the screen now
+//!let frame: &[u8] = epd.shadow();
+//!# Ok(())
+//!# }
+//!```
+
+use crate::color::{Color, Plane, PlaneConvention, TriColor};
+use crate::error::Error;
+use crate::line_bytes;
+use crate::traits::{
+    ChromaticBand, QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+
+/// A driver recording the last frame sent to it, see the [module documentation](self)
+///
+/// `N` is the size of the shadow: the frame buffer length of the black/white
+/// plane, or twice that for three color displays, which keep the chromatic
+/// plane in the second half (like the `TriColor` display buffers).
+pub struct ShadowedEpd<E, const N: usize> {
+    epd: E,
+    shadow: [u8; N],
+}
+
+impl<E, const N: usize> ShadowedEpd<E, N> {
+    /// Wraps `epd`, the shadow starts with all bits cleared
+    ///
+    /// # Panics
+    ///
+    /// If `N` is neither the length of one plane of the display nor of two.
+    pub fn wrap<SPI, BUSY, DC, RST, DELAY>(epd: E) -> Self
+    where
+        SPI: SpiDevice,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayNs,
+        E: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        let plane = plane_len(E::WIDTH, E::HEIGHT);
+        assert!(N == plane || N == 2 * plane);
+        Self {
+            epd,
+            shadow: [0; N],
+        }
+    }
+
+    /// The frame sent last, with the chromatic plane in the second half on three color displays
+    pub fn shadow(&self) -> &[u8] {
+        &self.shadow
+    }
+
+    /// The wrapped driver
+    pub fn inner(&self) -> &E {
+        &self.epd
+    }
+
+    /// The wrapped driver, transfers through it bypass the shadow
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.epd
+    }
+
+    /// Returns the wrapped driver, dropping the shadow
+    pub fn into_inner(self) -> E {
+        self.epd
+    }
+
+    fn plane_mut(&mut self, chromatic: bool, width: u32, height: u32) -> &mut [u8] {
+        let plane = plane_len(width, height);
+        match chromatic {
+            false => &mut self.shadow[..plane],
+            // without a second plane there is nothing to record
+            true => &mut self.shadow[plane..],
+        }
+    }
+}
+
+fn plane_len(width: u32, height: u32) -> usize {
+    line_bytes(width) as usize * height as usize
+}
+
+/// Copies the window `buffer` at (x, y) into a plane of `width` pixels
+///
+/// x is rounded down to a multiple of 8 like the controllers do, rows or bytes
+/// outside of the plane are dropped.
+fn merge_window(
+    plane: &mut [u8],
+    width: u32,
+    buffer: &[u8],
+    x: u32,
+    y: u32,
+    window_width: u32,
+    window_height: u32,
+) {
+    let stride = line_bytes(width) as usize;
+    let window_stride = line_bytes(window_width) as usize;
+    let first = (x / 8) as usize;
+    if stride == 0 || window_stride == 0 || first >= stride {
+        return;
+    }
+    let bytes = window_stride.min(stride - first);
+    let rows = buffer.chunks(window_stride).take(window_height as usize);
+    for (row, src) in (y as usize..).zip(rows) {
+        let Some(dst) = plane.get_mut(row * stride + first..row * stride + first + bytes) else {
+            break;
+        };
+        let bytes = bytes.min(src.len());
+        dst[..bytes].copy_from_slice(&src[..bytes]);
+    }
+}
+
+/// Fills the window at (x, y) of a plane of `width` pixels with `value`
+fn fill_window(
+    plane: &mut [u8],
+    width: u32,
+    value: u8,
+    x: u32,
+    y: u32,
+    window_width: u32,
+    window_height: u32,
+) {
+    let stride = line_bytes(width) as usize;
+    let first = (x / 8) as usize;
+    if first >= stride {
+        return;
+    }
+    let bytes = (line_bytes(window_width) as usize).min(stride - first);
+    for row in y as usize..(y + window_height) as usize {
+        let Some(dst) = plane.get_mut(row * stride + first..row * stride + first + bytes) else {
+            break;
+        };
+        dst.fill(value);
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, E, const N: usize> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for ShadowedEpd<E, N>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    E: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    type DisplayColor = Color;
+    const WIDTH: u32 = E::WIDTH;
+    const HEIGHT: u32 = E::HEIGHT;
+    const BUFFER_LEN: usize = E::BUFFER_LEN;
+
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        E::new(spi, busy, dc, rst, delay, delay_us).map(Self::wrap)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.sleep(spi, delay)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.wake_up(spi, delay)
+    }
+
+    fn recover(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.recover(spi, delay)
+    }
+
+    /// Aborts the refresh, the shadow keeps the frame which was being refreshed
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.abort_refresh(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.epd.set_background_color(color)
+    }
+
+    fn background_color(&self) -> &Color {
+        self.epd.background_color()
+    }
+
+    fn width(&self) -> u32 {
+        self.epd.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.epd.height()
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_frame(spi, buffer, delay)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+
+    fn update_frame_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        progress: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_frame_with_progress(spi, buffer, delay, progress)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+
+    fn update_frame_vectored(
+        &mut self,
+        spi: &mut SPI,
+        parts: &[&[u8]],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_frame_vectored(spi, parts, delay)?;
+        let mut start = 0;
+        for part in parts {
+            let len = part.len().min(N - start);
+            self.shadow[start..start + len].copy_from_slice(&part[..len]);
+            start += len;
+        }
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_partial_frame(spi, delay, buffer, x, y, width, height)?;
+        let plane = self.plane_mut(false, E::WIDTH, E::HEIGHT);
+        merge_window(plane, E::WIDTH, buffer, x, y, width, height);
+        Ok(())
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.display_frame(spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_and_display_frame(spi, buffer, delay)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+
+    /// Clears the frame, afterwards the whole shadow holds the byte of the background color
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.epd.clear_frame(spi, delay)?;
+        // the shadow holds the frames as they are passed in, in the layout of `Display`
+        let value = self
+            .epd
+            .background_color()
+            .fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite);
+        self.shadow.fill(value);
+        Ok(())
+    }
+
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.clear_region(spi, delay, x, y, width, height)?;
+        let value = self
+            .epd
+            .background_color()
+            .fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite);
+        let plane = self.plane_mut(false, E::WIDTH, E::HEIGHT);
+        fill_window(plane, E::WIDTH, value, x, y, width, height);
+        Ok(())
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.set_lut(spi, delay, refresh_rate)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.wait_until_idle(spi, delay)
+    }
+
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        self.epd.refresh_lut()
+    }
+
+    fn display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.display_frame_with(spi, delay, lut)
+    }
+
+    fn update_and_display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_and_display_frame_with(spi, buffer, delay, lut)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+}
+
+impl<E, const N: usize> ShadowedEpd<E, N> {
+    /// Records a full frame, a buffer holding both planes fills the whole shadow
+    fn record_frame(&mut self, buffer: &[u8]) {
+        let len = buffer.len().min(N);
+        self.shadow[..len].copy_from_slice(&buffer[..len]);
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, E, const N: usize>
+    WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY> for ShadowedEpd<E, N>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    E: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_color_frame(spi, delay, black, chromatic)?;
+        self.record_plane(false, E::WIDTH, E::HEIGHT, black);
+        self.record_plane(true, E::WIDTH, E::HEIGHT, chromatic);
+        Ok(())
+    }
+
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_achromatic_frame(spi, delay, black)?;
+        self.record_plane(false, E::WIDTH, E::HEIGHT, black);
+        Ok(())
+    }
+
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_chromatic_frame(spi, delay, chromatic)?;
+        self.record_plane(true, E::WIDTH, E::HEIGHT, chromatic);
+        Ok(())
+    }
+
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_color_frame_banded(spi, delay, black, chromatic)?;
+        self.record_plane(false, E::WIDTH, E::HEIGHT, black);
+        self.record_band(E::WIDTH, E::HEIGHT, chromatic);
+        Ok(())
+    }
+}
+
+impl<E, const N: usize> ShadowedEpd<E, N> {
+    fn record_plane(&mut self, chromatic: bool, width: u32, height: u32, buffer: &[u8]) {
+        let plane = self.plane_mut(chromatic, width, height);
+        let len = buffer.len().min(plane.len());
+        plane[..len].copy_from_slice(&buffer[..len]);
+    }
+
+    /// Records the chromatic plane of a band, without chromatic pixels around it
+    fn record_band(&mut self, width: u32, height: u32, band: ChromaticBand<'_>) {
+        let Some((before, _)) = band.fill_lens(width, height) else {
+            return;
+        };
+        let plane = self.plane_mut(true, width, height);
+        plane.fill(TriColor::White.fill_byte(Plane::Chromatic, PlaneConvention::OneIsChromatic));
+        if let Some(rows) = plane.get_mut(before as usize..) {
+            let len = band.data.len().min(rows.len());
+            rows[..len].copy_from_slice(&band.data[..len]);
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, E, const N: usize> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for ShadowedEpd<E, N>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+    E: QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+        + WaveshareDisplay<SPI, BUSY, DC, RST, DELAY, DisplayColor = Color>,
+{
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_old_frame(spi, buffer, delay)
+    }
+
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_new_frame(spi, buffer, delay)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.display_new_frame(spi, delay)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd.update_and_display_new_frame(spi, buffer, delay)?;
+        self.record_frame(buffer);
+        Ok(())
+    }
+
+    fn promote_displayed_frame_to_old(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, Error<SPI::Error>> {
+        self.epd.promote_displayed_frame_to_old(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_partial_old_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .update_partial_new_frame(spi, delay, buffer, x, y, width, height)?;
+        let plane = self.plane_mut(false, E::WIDTH, E::HEIGHT);
+        merge_window(plane, E::WIDTH, buffer, x, y, width, height);
+        Ok(())
+    }
+
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.epd
+            .clear_partial_frame(spi, delay, x, y, width, height)?;
+        let value = self
+            .epd
+            .background_color()
+            .fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite);
+        let plane = self.plane_mut(false, E::WIDTH, E::HEIGHT);
+        fill_window(plane, E::WIDTH, value, x, y, width, height);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "epd4in2"))]
+mod tests {
+    use super::*;
+    use crate::epd4in2::{Epd4in2, HEIGHT, WIDTH};
+    use crate::mock::{Bus, Delay, Pin};
+
+    const LEN: usize = 400 / 8 * 300;
+
+    #[test]
+    fn merge_window_ignores_low_x_bits() {
+        // 20 pixels wide, 3 bytes per row
+        let mut plane = [0u8; 3 * 4];
+        // x = 13 is written at byte 1, the window is clipped at the row end
+        merge_window(&mut plane, 20, &[0xAA, 0xBB, 0xCC, 0xDD], 13, 2, 16, 2);
+        assert_eq!(plane, [0, 0, 0, 0, 0, 0, 0, 0xAA, 0xBB, 0, 0xCC, 0xDD]);
+    }
+
+    #[test]
+    fn shadow_follows_partial_updates() {
+        let bus = Bus::new();
+        let mut spi = bus.spi();
+        let mut delay = Delay::new();
+        let epd = Epd4in2::new(
+            &mut spi,
+            bus.busy(true),
+            bus.dc(),
+            Pin::new(),
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        let mut epd = ShadowedEpd::<_, LEN>::wrap(epd);
+        let stride = line_bytes(WIDTH) as usize;
+
+        let mut expected = [0u8; LEN];
+        for (i, byte) in expected.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        epd.update_frame(&mut spi, &expected, &mut delay).unwrap();
+        assert_eq!(epd.shadow(), expected);
+
+        // 16x3 window at (80, 10) and 24x2 window at (203, 298), the second
+        // one starts at byte 25 like on the controller
+        let first = [0xF0; 2 * 3];
+        let second = [0x0F; 3 * 2];
+        epd.update_partial_frame(&mut spi, &mut delay, &first, 80, 10, 16, 3)
+            .unwrap();
+        epd.update_partial_frame(&mut spi, &mut delay, &second, 203, 298, 24, 2)
+            .unwrap();
+
+        for row in 10..13 {
+            expected[row * stride + 10..row * stride + 12].fill(0xF0);
+        }
+        for row in 298..HEIGHT as usize {
+            expected[row * stride + 25..row * stride + 28].fill(0x0F);
+        }
+        assert_eq!(epd.shadow(), expected);
+
+        epd.clear_frame(&mut spi, &mut delay).unwrap();
+        assert!(epd.shadow().iter().all(|&byte| byte == 0xFF));
+    }
+}
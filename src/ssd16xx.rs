@@ -0,0 +1,101 @@
+//! Shared parts of the SSD16xx based drivers, see
+//! [fast full refreshes](crate::update_sequence#fast-full-refreshes)
+//!
+//! # References
+//!
+//! - [Waveshare C driver 2.9" V2 (`EPD_2IN9_V2_Init_Fast`)](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in9_V2.c)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::busy::Operation;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::traits::{self, DataLen};
+use crate::update_sequence::UpdateSequence;
+
+/// Temperature register value of 100 °C, the integer part is in the first byte
+pub(crate) const FAST_TEMPERATURE: [u8; 2] = [0x64, 0x00];
+
+/// Loads the LUT for the temperature in the register
+const LOAD_LUT: UpdateSequence = UpdateSequence::ENABLE_CLOCK
+    .union(UpdateSequence::LOAD_LUT)
+    .union(UpdateSequence::DISABLE_CLOCK);
+/// Reads the temperature sensor into the register
+const LOAD_TEMPERATURE: UpdateSequence = UpdateSequence::ENABLE_CLOCK
+    .union(UpdateSequence::LOAD_TEMPERATURE)
+    .union(UpdateSequence::DISABLE_CLOCK);
+
+/// The commands of the fast refresh, the same on all SSD16xx controllers
+#[derive(Copy, Clone)]
+enum Command {
+    TemperatureSensorWrite = 0x1A,
+    MasterActivation = 0x20,
+    DisplayUpdateControl2 = 0x22,
+}
+
+impl traits::Command for Command {
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::TemperatureSensorWrite => DataLen::Exact(2),
+            Command::MasterActivation => DataLen::Exact(0),
+            Command::DisplayUpdateControl2 => DataLen::Exact(1),
+        }
+    }
+}
+
+/// Full refresh with the LUT of [FAST_TEMPERATURE], see
+/// [fast full refreshes](crate::update_sequence#fast-full-refreshes)
+///
+/// Waits for the refresh and reads the real temperature again afterwards, the LUT of
+/// the driver has to be uploaded again by the caller.
+pub(crate) fn display_frame_fast<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    is_busy_low: bool,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::TemperatureSensorWrite, &FAST_TEMPERATURE)?;
+    activate(interface, spi, LOAD_LUT)?;
+    interface.wait_until_idle(delay, is_busy_low);
+
+    activate(interface, spi, UpdateSequence::DISPLAY_ONLY)?;
+    interface.count_refresh(false);
+    interface.start_operation(Operation::FullRefresh);
+    interface.wait_until_idle(delay, is_busy_low);
+
+    activate(interface, spi, LOAD_TEMPERATURE)?;
+    interface.wait_until_idle(delay, is_busy_low);
+    Ok(())
+}
+
+/// Runs `sequence` without waiting for it
+fn activate<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    sequence: UpdateSequence,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence.bits()])?;
+    interface.cmd(spi, Command::MasterActivation)
+}
@@ -0,0 +1,295 @@
+//! Test cards for bringing up new displays
+//!
+//! Every test card only uses solid rectangles, so the rendering is deterministic and
+//! doesn't depend on any font or drawing code outside of this crate.
+//!
+#![cfg_attr(feature = "epd2in13bc", doc = "```rust")]
+#![cfg_attr(not(feature = "epd2in13bc"), doc = "```ignore")]
+//! use epd_waveshare::{epd2in13bc::Display2in13bc, test_pattern::*};
+//!
+//! let mut display = Display2in13bc::default();
+//! draw_test_card(&mut display, TestCard::CornerMarkers).unwrap();
+//! ```
+
+use crate::color::{Color, OctColor, QuadColor, TriColor};
+use crate::graphics::DisplayRotation;
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+
+/// Colors used by the test cards
+pub trait TestCardColor: PixelColor {
+    /// Black
+    const BLACK: Self;
+    /// White
+    const WHITE: Self;
+    /// The additional color of the display, black on b/w displays
+    const CHROMATIC: Self;
+}
+
+impl TestCardColor for Color {
+    const BLACK: Self = Color::Black;
+    const WHITE: Self = Color::White;
+    const CHROMATIC: Self = Color::Black;
+}
+
+impl TestCardColor for TriColor {
+    const BLACK: Self = TriColor::Black;
+    const WHITE: Self = TriColor::White;
+    const CHROMATIC: Self = TriColor::Chromatic;
+}
+
+impl TestCardColor for OctColor {
+    const BLACK: Self = OctColor::Black;
+    const WHITE: Self = OctColor::White;
+    const CHROMATIC: Self = OctColor::Red;
+}
+
+impl TestCardColor for QuadColor {
+    const BLACK: Self = QuadColor::Black;
+    const WHITE: Self = QuadColor::White;
+    const CHROMATIC: Self = QuadColor::Red;
+}
+
+/// Color of a [TestCard::FullField]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fill {
+    /// Black
+    Black,
+    /// White
+    White,
+    /// The additional color of the display, black on b/w displays
+    Chromatic,
+}
+
+/// The available test cards
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestCard {
+    /// The whole display in one color
+    FullField(Fill),
+    /// Black and white squares with the given edge length in pixels
+    Checkerboard(u32),
+    /// A one pixel border with L shaped markers in the corners, the top left corner
+    /// additionally gets a chromatic square to tell the origin
+    CornerMarkers,
+    /// "R0", "R90", "R180" and "R270" along the edges, each one rotated like the display
+    /// would be for reading it
+    TextRotations,
+}
+
+/// Draws the given test card onto the whole `display`
+pub fn draw_test_card<C, D>(display: &mut D, variant: TestCard) -> Result<(), D::Error>
+where
+    C: TestCardColor,
+    D: DrawTarget<Color = C>,
+{
+    let area = display.bounding_box();
+    match variant {
+        TestCard::FullField(fill) => display.clear(match fill {
+            Fill::Black => C::BLACK,
+            Fill::White => C::WHITE,
+            Fill::Chromatic => C::CHROMATIC,
+        }),
+        TestCard::Checkerboard(cell) => {
+            let cell = cell.max(1);
+            display.clear(C::WHITE)?;
+            for row in 0..area.size.height.div_ceil(cell) {
+                for column in (row % 2..area.size.width.div_ceil(cell)).step_by(2) {
+                    let top_left = Point::new((column * cell) as i32, (row * cell) as i32);
+                    display
+                        .fill_solid(&Rectangle::new(top_left, Size::new(cell, cell)), C::BLACK)?;
+                }
+            }
+            Ok(())
+        }
+        TestCard::CornerMarkers => draw_corner_markers(display, area.size),
+        TestCard::TextRotations => draw_text_rotations(display, area.size),
+    }
+}
+
+fn draw_corner_markers<C, D>(display: &mut D, size: Size) -> Result<(), D::Error>
+where
+    C: TestCardColor,
+    D: DrawTarget<Color = C>,
+{
+    let (width, height) = (size.width as i32, size.height as i32);
+    let length = (size.width.min(size.height) / 4).max(1);
+    let thickness = (length / 4).max(1);
+
+    display.clear(C::WHITE)?;
+
+    // border
+    for (top_left, size) in [
+        (Point::zero(), Size::new(size.width, 1)),
+        (Point::new(0, height - 1), Size::new(size.width, 1)),
+        (Point::zero(), Size::new(1, size.height)),
+        (Point::new(width - 1, 0), Size::new(1, size.height)),
+    ] {
+        display.fill_solid(&Rectangle::new(top_left, size), C::BLACK)?;
+    }
+
+    // L shaped markers, their legs pointing along the edges
+    let (length, thickness) = (length as i32, thickness as i32);
+    for (x, y) in [
+        (2, 2),
+        (width - 3, 2),
+        (2, height - 3),
+        (width - 3, height - 3),
+    ] {
+        let horizontal_x = if x == 2 { x } else { x - length + 1 };
+        let vertical_y = if y == 2 { y } else { y - length + 1 };
+        let leg_x = if x == 2 { x } else { x - thickness + 1 };
+        let leg_y = if y == 2 { y } else { y - thickness + 1 };
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(horizontal_x, leg_y),
+                Size::new(length as u32, thickness as u32),
+            ),
+            C::BLACK,
+        )?;
+        display.fill_solid(
+            &Rectangle::new(
+                Point::new(leg_x, vertical_y),
+                Size::new(thickness as u32, length as u32),
+            ),
+            C::BLACK,
+        )?;
+    }
+
+    // origin
+    let offset = 2 + 2 * thickness;
+    display.fill_solid(
+        &Rectangle::new(
+            Point::new(offset, offset),
+            Size::new(thickness as u32 * 2, thickness as u32 * 2),
+        ),
+        C::CHROMATIC,
+    )
+}
+
+/// 3x5 pixel glyphs, one row per byte with the leftmost pixel in bit 2
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        _ => [0; 5],
+    }
+}
+
+fn draw_text_rotations<C, D>(display: &mut D, size: Size) -> Result<(), D::Error>
+where
+    C: TestCardColor,
+    D: DrawTarget<Color = C>,
+{
+    let (width, height) = (size.width as i32, size.height as i32);
+    let scale = (size.width.min(size.height) / 64).max(1);
+    let margin = scale as i32;
+
+    display.clear(C::WHITE)?;
+
+    // maps a pixel of the text (u along the reading direction, v downwards) to the display
+    let position = |rotation, u, v| match rotation {
+        DisplayRotation::Rotate0 => Point::new(margin + u, margin + v),
+        DisplayRotation::Rotate90 => Point::new(width - 1 - margin - v, margin + u),
+        DisplayRotation::Rotate180 => Point::new(width - 1 - margin - u, height - 1 - margin - v),
+        DisplayRotation::Rotate270 => Point::new(margin + v, height - 1 - margin - u),
+    };
+    let labels = [
+        ("R0", DisplayRotation::Rotate0),
+        ("R90", DisplayRotation::Rotate90),
+        ("R180", DisplayRotation::Rotate180),
+        ("R270", DisplayRotation::Rotate270),
+    ];
+
+    let scale_i = scale as i32;
+    for (text, rotation) in labels {
+        for (index, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    let u = (index as i32 * 4 + column) * scale_i;
+                    let v = row as i32 * scale_i;
+                    // opposite corners of the scaled pixel, in any rotation
+                    let a = position(rotation, u, v);
+                    let b = position(rotation, u + scale_i - 1, v + scale_i - 1);
+                    let top_left = Point::new(a.x.min(b.x), a.y.min(b.y));
+                    display
+                        .fill_solid(&Rectangle::new(top_left, Size::new(scale, scale)), C::BLACK)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::VarDisplay;
+
+    #[test]
+    fn checkerboard() {
+        let mut buffer = [0u8; 2 * 4];
+        let mut display = VarDisplay::<Color>::new(16, 4, &mut buffer, false).unwrap();
+        draw_test_card(&mut display, TestCard::Checkerboard(2)).unwrap();
+        assert_eq!(
+            display.buffer(),
+            &[0x33, 0x33, 0x33, 0x33, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+    }
+
+    #[test]
+    fn full_field_chromatic() {
+        let mut buffer = [0u8; 2 * 4 * 2];
+        let mut display = VarDisplay::<TriColor>::new(16, 4, &mut buffer, true).unwrap();
+        draw_test_card(&mut display, TestCard::FullField(Fill::Chromatic)).unwrap();
+        assert!(display.bw_buffer().iter().all(|&byte| byte == 0x00));
+        assert!(display.chromatic_buffer().iter().all(|&byte| byte == 0xFF));
+    }
+
+    // locks the rendering down, a changed test card has to be a deliberate change
+    #[test]
+    fn corner_markers_golden() {
+        #[rustfmt::skip]
+        const BW: [u8; 4 * 24] = [
+            0x00, 0x00, 0x00, 0x00, 0x7F, 0xFF, 0xFF, 0xFE, 0x40, 0xFF, 0xFF, 0x02, 0x5F, 0xFF, 0xFF, 0xFA,
+            0x53, 0xFF, 0xFF, 0xFA, 0x53, 0xFF, 0xFF, 0xFA, 0x5F, 0xFF, 0xFF, 0xFA, 0x5F, 0xFF, 0xFF, 0xFA,
+            0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE,
+            0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE, 0x7F, 0xFF, 0xFF, 0xFE,
+            0x5F, 0xFF, 0xFF, 0xFA, 0x5F, 0xFF, 0xFF, 0xFA, 0x5F, 0xFF, 0xFF, 0xFA, 0x5F, 0xFF, 0xFF, 0xFA,
+            0x5F, 0xFF, 0xFF, 0xFA, 0x40, 0xFF, 0xFF, 0x02, 0x7F, 0xFF, 0xFF, 0xFE, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut chromatic = [0u8; 4 * 24];
+        chromatic[16] = 0x0C;
+        chromatic[20] = 0x0C;
+
+        let mut buffer = [0u8; 4 * 24 * 2];
+        let mut display = VarDisplay::<TriColor>::new(32, 24, &mut buffer, true).unwrap();
+        draw_test_card(&mut display, TestCard::CornerMarkers).unwrap();
+        assert_eq!(display.bw_buffer(), &BW);
+        assert_eq!(display.chromatic_buffer(), &chromatic);
+    }
+
+    #[test]
+    fn text_rotations_golden() {
+        #[rustfmt::skip]
+        const BW: [u8; 4 * 24] = [
+            0xFF, 0xFF, 0xFF, 0xFF, 0x98, 0xFF, 0xFF, 0xC1, 0xAA, 0xFF, 0xFF, 0xF5, 0x9A, 0xFF, 0xFF, 0xCB,
+            0xAA, 0xFF, 0xFF, 0xFF, 0xA8, 0xFF, 0xFF, 0xD1, 0xFF, 0xFF, 0xFF, 0xD5, 0xFF, 0xFF, 0xFF, 0xC1,
+            0x83, 0xFF, 0xFF, 0xFF, 0xBB, 0xFF, 0xFF, 0xC1, 0x83, 0xFF, 0xFF, 0xDD, 0xFF, 0xFF, 0xFF, 0xC1,
+            0x9F, 0xFF, 0xFF, 0xFF, 0xA3, 0xFF, 0xFF, 0xFF, 0xBF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x8B, 0xFF, 0xFF, 0xFF, 0xAB, 0xFF, 0xFF, 0xFF, 0xA3, 0xFF, 0x11, 0x15, 0xFF, 0xFF, 0x55, 0xB5,
+            0xD3, 0xFF, 0x51, 0xB9, 0xAF, 0xFF, 0x55, 0x95, 0x83, 0xFF, 0x11, 0xB9, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+
+        let mut buffer = [0u8; 4 * 24];
+        let mut display = VarDisplay::<Color>::new(32, 24, &mut buffer, false).unwrap();
+        draw_test_card(&mut display, TestCard::TextRotations).unwrap();
+        assert_eq!(display.buffer(), &BW);
+    }
+}
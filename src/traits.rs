@@ -1,10 +1,57 @@
+use crate::busy::{BusyPolarity, RefreshLimits, RefreshReport, SelfTestReport, WaitOutcome};
+use crate::color::Plane;
+use crate::error::Error;
+use crate::frame::FrameData;
+use crate::interface::Connection;
+use crate::policy::{RefreshCounters, RefreshHint, RefreshKind, RefreshPolicy};
+use crate::reset::ResetConfig;
 use core::marker::Sized;
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::primitives::Rectangle;
 use embedded_hal::{delay::*, digital::*, spi::SpiDevice};
+#[cfg(feature = "async")]
+use embedded_hal_async::{
+    delay::DelayNs as AsyncDelayNs, digital::Wait, spi::SpiDevice as AsyncSpiDevice,
+};
 
 /// All commands need to have this trait which gives the address of the command
 /// which needs to be send via SPI with activated CommandsPin (Data/Command Pin in CommandMode)
-pub(crate) trait Command: Copy {
+pub trait Command: Copy {
     fn address(self) -> u8;
+
+    /// Number of parameter bytes the controller expects after the command, checked by
+    /// `cmd_with_data`
+    fn expected_data_len(self) -> DataLen {
+        DataLen::Variable
+    }
+
+    /// Plane of a tri-color controller the data after the command is written to,
+    /// for the per plane checksums
+    fn plane(self) -> Option<Plane> {
+        None
+    }
+}
+
+/// Parameter length of a [Command], from the datasheet of the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataLen {
+    /// Exactly this number of bytes
+    Exact(usize),
+    /// Up to this number of bytes, e.g. for optional trailing parameters
+    Max(usize),
+    /// Anything, e.g. frame data or parameters depending on the panel
+    Variable,
+}
+
+impl DataLen {
+    /// Whether `len` bytes are a valid parameter list
+    pub(crate) fn accepts(self, len: usize) -> bool {
+        match self {
+            DataLen::Exact(expected) => len == expected,
+            DataLen::Max(max) => len <= max,
+            DataLen::Variable => true,
+        }
+    }
 }
 
 /// Seperates the different LUT for the Display Refresh process
@@ -16,9 +63,80 @@ pub enum RefreshLut {
     /// The quick LUT where not the full refresh sequence is followed.
     /// This might lead to some
     Quick,
+    /// A further waveform of the controller, selected by a driver specific index
+    ///
+    /// See the `set_lut` of the drivers for the supported indices. Drivers
+    /// without further waveforms use the [full](RefreshLut::Full) one.
+    Custom(u8),
+}
+
+/// Options of [`WaveshareDisplay::periodic_update`]
+///
+/// The default is a full refresh without assuming anything about the controller and
+/// without deep sleep afterwards.
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+pub struct PeriodicUpdateOptions {
+    /// Refresh with the full LUT, otherwise with the quick one where supported
+    pub full_refresh: bool,
+    /// Put the display into deep sleep after the refresh instead of only powering it
+    /// off, for long intervals between updates
+    pub deep_sleep_after: bool,
+    /// The controller was neither reset nor without power since the driver last
+    /// configured it, e.g. the driver was kept in retained memory over the MCU sleep
+    ///
+    /// The drivers then skip as much of the initialisation as their controller allows.
+    /// A deep sleep of a previous update is taken into account by the drivers.
+    pub assume_config_retained: bool,
+}
+
+/// RAM of the SSD16xx controllers written by the `write_ram_window` of their drivers
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+pub enum RamBank {
+    /// The black/white RAM holding the frame shown by the next refresh
+    #[default]
+    New,
+    /// The RAM holding the shown frame, which quick refreshes compare the new one with
+    Old,
+}
+
+/// The rows of the chromatic plane kept in RAM by a display with a chromatic band
+///
+/// The rest of the plane is transmitted as background by
+/// [`WaveshareThreeColorDisplay::update_color_frame_banded`], see
+/// `graphics::PartialChromaticDisplay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChromaticBand<'a> {
+    /// Native rows `start_row..` of the chromatic plane, whole rows of the panel
+    pub data: &'a [u8],
+    /// First native row of the panel covered by `data`
+    pub start_row: u32,
+}
+
+impl ChromaticBand<'_> {
+    /// Number of background bytes before and after the band in the chromatic plane of a
+    /// `width` x `height` panel, `None` if the band doesn't fit into it
+    ///
+    /// Both together with the band are exactly the bytes of the full plane.
+    pub fn fill_lens(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let stride = crate::line_bytes(width);
+        let len = u32::try_from(self.data.len()).ok()?;
+        if stride == 0 || len % stride != 0 {
+            return None;
+        }
+        let before = self.start_row.checked_mul(stride)?;
+        let after = stride
+            .checked_mul(height)?
+            .checked_sub(before)?
+            .checked_sub(len)?;
+        Some((before, after))
+    }
 }
 
-pub(crate) trait InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+/// Driver internals used by the default methods of [DriverControl]
+///
+/// Not reexported, which keeps [DriverControl] implemented by the drivers of this crate only.
+pub trait InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     BUSY: InputPin,
@@ -26,6 +144,32 @@ where
     RST: OutputPin,
     DELAY: DelayNs,
 {
+    /// Connection interface of the driver
+    type Interface: Connection<SPI, BUSY, DC, RST, DELAY>;
+    /// Command set of the controller
+    type Command: Command;
+
+    /// Whether the busy pin of the genuine Waveshare module is low while busy
+    const IS_BUSY_LOW: bool;
+    /// Hardware reset sequence of the panel
+    const RESET_CONFIG: ResetConfig;
+    /// Typical and maximum refresh times of the panel
+    const REFRESH_LIMITS: RefreshLimits;
+    /// Command making the controller busy for [DriverControl::self_test]
+    const SELF_TEST_COMMAND: Self::Command;
+
+    /// Creates the driver with its defaults around `interface`, without initialising
+    /// the device
+    fn from_interface(interface: Self::Interface) -> Self
+    where
+        Self: Sized;
+
+    /// Connection interface of the driver
+    fn interface(&self) -> &Self::Interface;
+
+    /// Connection interface of the driver
+    fn interface_mut(&mut self) -> &mut Self::Interface;
+
     /// This initialises the EPD and powers it up
     ///
     /// This function is already called from
@@ -36,7 +180,48 @@ where
     /// This function calls [reset](WaveshareDisplay::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Starts the refresh of [display_frame](WaveshareDisplay::display_frame) without
+    /// waiting for it
+    ///
+    /// The default is `display_frame` itself, for the drivers of which it returns right
+    /// after starting the refresh.
+    fn begin_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.display_frame(spi, delay)
+    }
+
+    /// Waits for the refresh started by [begin_display_frame](Self::begin_display_frame)
+    /// until `cancel` returns true
+    fn wait_until_idle_cancellable(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        Ok(self
+            .interface_mut()
+            .wait_until_idle_cancellable(delay, Self::IS_BUSY_LOW, cancel))
+    }
+
+    /// Completes the refresh started by [begin_display_frame](Self::begin_display_frame)
+    /// once the panel is idle again, nothing to do for most drivers
+    fn finish_display_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Ok(())
+    }
+
+    /// Whether the next [display_frame](WaveshareDisplay::display_frame) is a quick refresh
+    fn is_quick_refresh(&self) -> bool {
+        self.refresh_lut() == Some(RefreshLut::Quick)
+    }
 }
 
 /// Functions to interact with three color panels
@@ -58,7 +243,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the black/white data of the display.
     ///
@@ -68,7 +253,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the chromatic data of the display.
     ///
@@ -79,7 +264,66 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Transmit a black/white frame and a chromatic band of it
+    ///
+    /// Like [`update_color_frame`](Self::update_color_frame), but only `chromatic.data`
+    /// is sent of the chromatic plane. The RAM before and after the band is filled with
+    /// the [background color](WaveshareDisplay::set_background_color), so the controller
+    /// receives as many bytes as for the full plane.
+    ///
+    /// Fails with [`Error::InvalidWindow`] without sending anything if the band doesn't
+    /// fit into the panel.
+    fn update_color_frame_banded(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: ChromaticBand<'_>,
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Transmit both planes given in parts, like
+    /// [`update_frame_vectored`](WaveshareDisplay::update_frame_vectored)
+    ///
+    /// `black` and `chromatic` are the parts of the planes of
+    /// [`update_color_frame`](Self::update_color_frame), the controller receives the
+    /// same bytes as from it with the joined planes. Returns
+    /// [`Error::InvalidDataLength`] without sending anything unless each adds up to a
+    /// full plane.
+    fn update_color_frame_vectored(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[&[u8]],
+        chromatic: &[&[u8]],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_color_frame_from(
+            spi,
+            delay,
+            &mut FrameData::from_parts(black),
+            &mut FrameData::from_parts(chromatic),
+        )
+    }
+
+    /// Transmits both planes from a [FrameData] each, the drivers of this crate implement
+    /// [`update_color_frame`](Self::update_color_frame) and
+    /// [`update_color_frame_vectored`](Self::update_color_frame_vectored) with it
+    ///
+    /// The default only takes planes in one part each.
+    #[doc(hidden)]
+    fn update_color_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &mut FrameData<'_>,
+        chromatic: &mut FrameData<'_>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match (black.as_slice(), chromatic.as_slice()) {
+            (Some(black), Some(chromatic)) => self.update_color_frame(spi, delay, black, chromatic),
+            _ => Err(Error::Unsupported),
+        }
+    }
 }
 
 /// All the functions to interact with the EPDs
@@ -88,9 +332,13 @@ where
 ///
 /// # Example
 ///
-///```rust, no_run
+#[cfg_attr(
+    all(feature = "epd4in2", feature = "graphics"),
+    doc = "```rust, no_run"
+)]
+#[cfg_attr(not(all(feature = "epd4in2", feature = "graphics")), doc = "```ignore")]
 ///# use embedded_hal_mock::eh1::*;
-///# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+///# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -136,6 +384,15 @@ where
 {
     /// The Color Type used by the Display
     type DisplayColor;
+    /// Width of the display in pixels
+    const WIDTH: u32;
+
+    /// Height of the display in pixels
+    const HEIGHT: u32;
+
+    /// Length in bytes of the buffer of a full size `Display` for this display
+    const BUFFER_LEN: usize;
+
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
     /// `delay_us` is the number of us the idle loop should sleep on.
@@ -150,21 +407,46 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized;
 
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Wakes the device up from sleep
     ///
     /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Resets and reinitialises the device after a failed transfer
+    ///
+    /// After a spi error all further transfers return [`Error::NeedsReset`]
+    /// as the controller may still expect the rest of the interrupted transfer.
+    /// This brings the device back into a known state, the frame needs to be sent again.
+    fn recover(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wake_up(spi, delay)
+    }
+
+    /// Stops driving the panel as fast as the controller allows, e.g. in the middle of a refresh
+    ///
+    /// UC81xx style controllers are reset with the reset pin and powered off, SSD16xx
+    /// style ones get a software reset and are sent into deep sleep. The busy pin is
+    /// not waited for.
+    ///
+    /// The image on the panel is undefined afterwards. All further transfers return
+    /// [`Error::NeedsReset`] until the display is initialised again with
+    /// [`recover`](WaveshareDisplay::recover) or [`wake_up`](WaveshareDisplay::wake_up),
+    /// the frame needs to be sent again then.
+    fn abort_refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
+    ///
+    /// Every fill the driver synthesizes afterwards uses it: `clear_frame`,
+    /// [clear_region](WaveshareDisplay::clear_region) and the planes `update_frame` fills
+    /// on its own, e.g. the chromatic one of the tri-color panels.
     fn set_background_color(&mut self, color: Self::DisplayColor);
 
     /// Get current background color
@@ -182,13 +464,86 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Transmit a full frame from a `'static` buffer to the SRAM of the EPD
+    ///
+    /// The same as [update_frame](WaveshareDisplay::update_frame), for buffers which are
+    /// passed around between the drawing code and a DMA capable spi, e.g. the one of a
+    /// [`StaticDisplay`](crate::graphics::StaticDisplay).
+    fn update_frame_static(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &'static [u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Transmit a full frame like [update_frame](WaveshareDisplay::update_frame), reporting the progress
+    ///
+    /// `progress` is called with the number of bytes of `buffer` sent so far and its
+    /// length, about every 1 KB and always with `(buffer.len(), buffer.len())` at the
+    /// end. The numbers never go down, drivers sending the frame twice report the
+    /// first transfer.
+    fn update_frame_with_progress<F: FnMut(usize, usize)>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        mut progress: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut frame = FrameData::new(buffer).with_progress(&mut progress);
+        self.update_frame_from(spi, &mut frame, delay)?;
+        frame.finish();
+        Ok(())
+    }
+
+    /// Transmit a full frame given in parts to the SRAM of the EPD, e.g. the pieces of a
+    /// ring buffer, without copying them into one buffer first
+    ///
+    /// The parts are sent in order as one buffer, they may be split anywhere, also within
+    /// a row or into single bytes, the controller receives the same bytes as from
+    /// [update_frame](WaveshareDisplay::update_frame) with the joined buffer. Where that
+    /// writes the buffer at once, the parts are written within the same CS assertions.
+    /// Returns [`Error::InvalidDataLength`] without sending anything unless the parts
+    /// add up to a full frame.
+    fn update_frame_vectored(
+        &mut self,
+        spi: &mut SPI,
+        parts: &[&[u8]],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame_from(spi, &mut FrameData::from_parts(parts), delay)
+    }
+
+    /// Transmits a full frame from a [FrameData], the drivers of this crate implement
+    /// [update_frame](WaveshareDisplay::update_frame) and the progress and vectored
+    /// variants with it
+    ///
+    /// The default only takes frames in one part and reports their end.
+    #[doc(hidden)]
+    fn update_frame_from(
+        &mut self,
+        spi: &mut SPI,
+        frame: &mut FrameData<'_>,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let buffer = frame.as_slice().ok_or(Error::Unsupported)?;
+        self.update_frame(spi, buffer, delay)?;
+        frame.sent(buffer.len());
+        Ok(())
+    }
 
     /// Transmits partial data to the SRAM of the EPD
     ///
     /// (x,y) is the top left corner
     ///
-    /// BUFFER needs to be of size: width / 8 * height !
+    /// BUFFER needs to be of size: [line_bytes(width)](crate::line_bytes) * height !
+    ///
+    /// Returns [`Error::InvalidWindow`] without sending anything if the window is empty
+    /// or doesn't fit on the panel, e.g. for coordinates wrapped around from a negative
+    /// value.
     #[allow(clippy::too_many_arguments)]
     fn update_partial_frame(
         &mut self,
@@ -199,25 +554,106 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Same as [`update_partial_frame`](WaveshareDisplay::update_partial_frame), with the
+    /// window given as `Rectangle` in native (unrotated) panel coordinates
+    ///
+    /// Returns [`Error::InvalidWindow`] without sending anything if `area` is empty,
+    /// starts at a negative coordinate or doesn't fit on the panel.
+    #[cfg(feature = "graphics")]
+    fn update_partial_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+    ) -> Result<(), Error<SPI::Error>> {
+        let (x, y, width, height) = partial_window(&area, self.width(), self.height())?;
+        self.update_partial_frame(spi, delay, buffer, x, y, width, height)
+    }
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
-    /// Provide a combined update&display and save some time (skipping a busy check in between)
+    /// Combined [update_frame](WaveshareDisplay::update_frame) and
+    /// [display_frame](WaveshareDisplay::display_frame)
+    ///
+    /// Waits for the refresh (and checks the refresh guard) exactly like `display_frame`.
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Clears the window at (x, y) of the frame buffer on the EPD with the background color
+    ///
+    /// Like [clear_frame](WaveshareDisplay::clear_frame) for a part of the panel, x and the
+    /// width are rounded to multiples of 8 by the controllers. Returns
+    /// [`Error::Unsupported`] without sending anything unless the driver can clear
+    /// windows, which Epd4in2, Epd1in54 V2, Epd2in13d and Epd2in9bc do with their
+    /// [`clear_partial_frame`](QuickRefresh::clear_partial_frame).
+    fn clear_region(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let _ = (spi, delay, x, y, width, height);
+        Err(Error::Unsupported)
+    }
+
+    /// Clears the frame buffer on the EPD except for `keep`, which is sent from `full_frame`
+    ///
+    /// Meant for persistent parts of a layout, e.g. a header: everything around `keep` is
+    /// cleared with [clear_region](WaveshareDisplay::clear_region) in up to four windows,
+    /// then the rows of `keep` are transmitted again from `full_frame`, a buffer of the
+    /// whole panel. `keep` is widened to whole bytes, so rather more than less is kept.
+    ///
+    /// Returns [`Error::InvalidWindow`] for a `keep` outside of the panel like
+    /// [`update_partial_frame_rect`](WaveshareDisplay::update_partial_frame_rect) and
+    /// [`Error::Unsupported`] for drivers which can't clear windows and
+    /// [`Error::InvalidDataLength`] if `full_frame` is shorter than a full frame, in all
+    /// cases without sending anything.
+    #[cfg(feature = "graphics")]
+    fn clear_frame_except(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        keep: Rectangle,
+        full_frame: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let stride = crate::line_bytes(self.width()) as usize;
+        if full_frame.len() < stride * self.height() as usize {
+            return Err(Error::InvalidDataLength);
+        }
+        let ((x, y, width, height), around) = clear_windows(&keep, self.width(), self.height())?;
+        for (x, y, width, height) in around.into_iter().flatten() {
+            self.clear_region(spi, delay, x, y, width, height)?;
+        }
+
+        let rows = &full_frame[y as usize * stride..(y + height) as usize * stride];
+        if width == self.width() {
+            return self.update_partial_frame(spi, delay, rows, x, y, width, height);
+        }
+        // the rows of the window aren't adjacent in `full_frame`, they are sent one by one
+        let (first, bytes) = ((x / 8) as usize, crate::line_bytes(width) as usize);
+        for (row, line) in (y..).zip(rows.chunks_exact(stride)) {
+            self.update_partial_frame(spi, delay, &line[first..first + bytes], x, row, width, 1)?;
+        }
+        Ok(())
+    }
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -232,12 +668,114 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Wait until the display has stopped processing data
     ///
     /// You can call this to make sure a frame is displayed before goin further
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// The currently selected LUT
+    ///
+    /// `None` for displays which don't support switching between LUTs.
+    fn refresh_lut(&self) -> Option<RefreshLut> {
+        None
+    }
+
+    /// Displays the frame data from SRAM using `lut` for this refresh only
+    ///
+    /// E.g. a single full refresh to clean up ghosting in between quick refreshes.
+    /// The previously selected LUT is restored afterwards, also if the refresh failed.
+    /// Nothing is uploaded if `lut` is already selected.
+    ///
+    /// Displays without selectable LUTs just [display the frame](WaveshareDisplay::display_frame).
+    fn display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        match self.refresh_lut() {
+            Some(previous) if previous != lut => {
+                let result = self
+                    .set_lut(spi, delay, Some(lut))
+                    .and_then(|()| self.display_frame(spi, delay));
+                // the LUT must not change while the refresh is still running
+                let restore = self
+                    .wait_until_idle(spi, delay)
+                    .and_then(|()| self.set_lut(spi, delay, Some(previous)));
+                result.and(restore)
+            }
+            _ => self.display_frame(spi, delay),
+        }
+    }
+
+    /// Combined [update_frame](WaveshareDisplay::update_frame) and
+    /// [display_frame_with](WaveshareDisplay::display_frame_with)
+    fn update_and_display_frame_with(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame_with(spi, delay, lut)
+    }
+
+    /// Shows `buffer` after waking up the display, e.g. every few minutes from the MCU
+    /// deep sleep, and leaves it in the lowest power state afterwards
+    ///
+    /// Drivers knowing their controller only resend what it lost since the last update,
+    /// see [`PeriodicUpdateOptions::assume_config_retained`]. The generic version is
+    /// conservative: it always [wakes up](WaveshareDisplay::wake_up) the display with a
+    /// complete initialisation, refreshes with the [full](RefreshLut::Full) LUT for
+    /// this refresh only if `full_refresh` is set (otherwise with the selected one) and
+    /// waits for the refresh to finish or puts the display to
+    /// [sleep](WaveshareDisplay::sleep).
+    fn periodic_update(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        opts: PeriodicUpdateOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.wake_up(spi, delay)?;
+        self.update_frame(spi, buffer, delay)?;
+        if opts.full_refresh {
+            self.display_frame_with(spi, delay, RefreshLut::Full)?;
+        } else {
+            self.display_frame(spi, delay)?;
+        }
+        if opts.deep_sleep_after {
+            self.sleep(spi, delay)
+        } else {
+            self.wait_until_idle(spi, delay)
+        }
+    }
+
+    /// Displays the frame data from SRAM with the refresh `policy` decides on
+    ///
+    /// A [full](crate::policy::RefreshKind::Full) refresh uses the full LUT for this
+    /// refresh only, see [display_frame_with](WaveshareDisplay::display_frame_with),
+    /// so select the quick LUT beforehand. If the refresh fails, the next one is a full
+    /// one. Returns the kind of the refresh.
+    fn display_with_policy(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        policy: &mut RefreshPolicy,
+        hint: RefreshHint,
+    ) -> Result<RefreshKind, Error<SPI::Error>> {
+        let kind = policy.next_refresh(hint);
+        self.display_frame_with(spi, delay, kind.into())
+            .inspect_err(|_| policy.force_full())?;
+        Ok(kind)
+    }
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -249,9 +787,13 @@ where
 ///
 /// (todo: Example ommitted due to CI failures.)
 /// Example:
-///```rust, no_run
+#[cfg_attr(
+    all(feature = "epd4in2", feature = "graphics"),
+    doc = "```rust, no_run"
+)]
+#[cfg_attr(not(all(feature = "epd4in2", feature = "graphics")), doc = "```ignore")]
 ///# use embedded_hal_mock::eh1::*;
-///# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+///# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -271,7 +813,7 @@ where
 ///# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
 ///let (x, y, frame_width, frame_height) = (20, 40, 80,80);
 ///
-///let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 80 / 8 * 80];
+///let mut buffer = [DEFAULT_BACKGROUND_COLOR.fill_byte(Plane::BlackWhite, PlaneConvention::OneIsWhite); 80 / 8 * 80];
 ///let mut display = VarDisplay::new(frame_width, frame_height, &mut buffer,false).unwrap();
 ///
 ///epd.update_partial_old_frame(&mut spi, &mut delay, display.buffer(), x, y, frame_width, frame_height)
@@ -299,7 +841,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -307,10 +849,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -318,9 +864,56 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Makes the frame shown by the last refresh the old frame of the next quick
+    /// refresh, without sending it again
+    ///
+    /// Returns `false` if the controller doesn't hold the shown frame as old frame, it
+    /// then has to be sent with [`update_old_frame`](Self::update_old_frame) as before.
+    ///
+    /// SSD16xx controllers (e.g. of the 1.54" V2 and 2.9" V2) copy the new frame into the
+    /// old frame RAM at the end of a quick refresh. Between quick refreshes
+    /// [`update_and_display_new_frame`](Self::update_and_display_new_frame) is then enough,
+    /// and the previous frame doesn't need to be kept in the MCU RAM. After a full
+    /// refresh the copy isn't done, so the first quick refresh still needs the old frame.
+    fn promote_displayed_frame_to_old(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<bool, Error<SPI::Error>> {
+        Ok(false)
+    }
+
+    /// Displays `buffer` after briefly showing its inverse, against the ghosting built up
+    /// by long runs of quick refreshes
+    ///
+    /// The inverse is written into `scratch` and shown with a short compensation LUT,
+    /// which moves every pixel once and resets the particle positions. The real frame is
+    /// then shown with the quick LUT, afterwards the LUT selected before is set again.
+    /// Meant to be called every few dozen quick refreshes instead of a full refresh.
+    ///
+    /// <div class="warning">The whole display visibly flashes once in its inverted
+    /// colors before the frame appears.</div>
+    ///
+    /// Returns [`Error::InvalidDataLength`] if `scratch` is shorter than `buffer` and
+    /// [`Error::Unsupported`] unless the driver supports it, in both cases without sending
+    /// anything. Only the 4.2" driver does so far.
+    fn display_with_ghost_compensation(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let _ = (spi, delay, buffer, scratch);
+        Err(Error::Unsupported)
+    }
 
     /// Updates the old frame for a portion of the display.
+    ///
+    /// Refuses windows off the panel like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame).
     #[allow(clippy::too_many_arguments)]
     fn update_partial_old_frame(
         &mut self,
@@ -331,9 +924,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame for a portion of the display.
+    ///
+    /// Refuses windows off the panel like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame).
     #[allow(clippy::too_many_arguments)]
     fn update_partial_new_frame(
         &mut self,
@@ -344,10 +940,13 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
+    ///
+    /// Refuses windows off the panel like
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame).
     fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -356,5 +955,642 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Same as [`update_partial_old_frame`](QuickRefresh::update_partial_old_frame), with
+    /// the window given as `Rectangle`, see
+    /// [`WaveshareDisplay::update_partial_frame_rect`]
+    #[cfg(feature = "graphics")]
+    fn update_partial_old_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        let (x, y, width, height) = partial_window(&area, self.width(), self.height())?;
+        self.update_partial_old_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// Same as [`update_partial_new_frame`](QuickRefresh::update_partial_new_frame), with
+    /// the window given as `Rectangle`, see
+    /// [`WaveshareDisplay::update_partial_frame_rect`]
+    #[cfg(feature = "graphics")]
+    fn update_partial_new_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        let (x, y, width, height) = partial_window(&area, self.width(), self.height())?;
+        self.update_partial_new_frame(spi, delay, buffer, x, y, width, height)
+    }
+
+    /// Same as [`clear_partial_frame`](QuickRefresh::clear_partial_frame), with the window
+    /// given as `Rectangle`, see [`WaveshareDisplay::update_partial_frame_rect`]
+    #[cfg(feature = "graphics")]
+    fn clear_partial_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        area: Rectangle,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+    {
+        let (x, y, width, height) = partial_window(&area, self.width(), self.height())?;
+        self.clear_partial_frame(spi, delay, x, y, width, height)
+    }
+}
+
+/// Refreshing a single window with its own LUT, e.g. a quickly updated clock with the
+/// quick LUT next to a photo which is only refreshed with the full LUT
+///
+/// The LUT can't be uploaded during a partial update, so this takes care of the order:
+/// any open partial session is closed, the LUT uploaded if it differs from the selected
+/// one, `buffer` written into the window at `area` and refreshed. The previously
+/// selected LUT is restored afterwards (after the refresh finished), also if the refresh
+/// failed.
+///
+/// Implemented by the UC81xx drivers with a quick LUT (Epd4in2 and Epd2in13d).
+#[cfg(feature = "graphics")]
+pub trait RegionRefresh<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Writes `buffer` into the window at `area` and refreshes it using `lut` for this
+    /// refresh only
+    ///
+    /// `area` is given in native (unrotated) panel coordinates, see
+    /// [`WaveshareDisplay::update_partial_frame_rect`].
+    fn refresh_region_with_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        area: Rectangle,
+        lut: RefreshLut,
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+/// Converts `area` to the loose x, y, width and height of the partial updates, checked
+/// against a panel of `width` x `height` pixels
+///
+/// The controllers write whole bytes, so the window is widened to byte boundaries
+/// horizontally. That still has to fit into the padded rows of the RAM.
+#[cfg(feature = "graphics")]
+pub(crate) fn partial_window<E>(
+    area: &Rectangle,
+    width: u32,
+    height: u32,
+) -> Result<(u32, u32, u32, u32), Error<E>> {
+    let (Ok(x), Ok(y)) = (
+        u32::try_from(area.top_left.x),
+        u32::try_from(area.top_left.y),
+    ) else {
+        return Err(Error::InvalidWindow);
+    };
+    let (w, h) = (area.size.width, area.size.height);
+    check_window(x, y, w, h, width, height)?;
+    Ok((x, y, w, h))
+}
+
+/// Checks the loose window `x`, `y`, `w` x `h` like [partial_window]
+///
+/// Every driver runs this before the first command of a partial update, so none of the
+/// coordinate math behind it can overflow.
+#[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+pub(crate) fn check_window<E>(
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), Error<E>> {
+    if w == 0 || h == 0 {
+        return Err(Error::InvalidWindow);
+    }
+    let (Some(end_x), Some(end_y)) = (x.checked_add(w), y.checked_add(h)) else {
+        return Err(Error::InvalidWindow);
+    };
+    if end_x > width || end_y > height || crate::line_bytes(end_x) > crate::line_bytes(width) {
+        return Err(Error::InvalidWindow);
+    }
+    Ok(())
+}
+
+/// The window kept by [`WaveshareDisplay::clear_frame_except`] and the ones cleared
+/// around it, above, below, left and right of it as far as they aren't empty
+///
+/// `keep` is checked like in [partial_window] and widened to byte boundaries, the
+/// end is clipped to the panel though.
+#[cfg(feature = "graphics")]
+#[allow(clippy::type_complexity)]
+pub(crate) fn clear_windows<E>(
+    keep: &Rectangle,
+    width: u32,
+    height: u32,
+) -> Result<((u32, u32, u32, u32), [Option<(u32, u32, u32, u32)>; 4]), Error<E>> {
+    let (x, y, w, h) = partial_window(keep, width, height)?;
+    let (start, end) = (x & !0x07, (x + w).next_multiple_of(8).min(width));
+    let bottom = y + h;
+    let window = |x, y, w, h| (w > 0 && h > 0).then_some((x, y, w, h));
+    Ok((
+        (start, y, end - start, h),
+        [
+            window(0, 0, width, y),
+            window(0, bottom, width, height - bottom),
+            window(0, y, start, h),
+            window(end, y, width - end, h),
+        ],
+    ))
+}
+
+/// Sending a full b/w frame row by row, without a buffer of the whole frame
+///
+/// The frame is generated while it is transmitted: `fill_row` is called for every row
+/// from the top with its index and `row`, which it has to overwrite completely with the
+/// [line_bytes](crate::line_bytes) of the row, in the layout of a b/w display buffer.
+/// Afterwards the row is sent and `row` reused for the next one.
+///
+/// Implemented by the drivers which transmit their frame in chunks (Epd4in2, Epd2in9 V2
+/// and Epd7in5 V2), see [`RowRenderer`](crate::graphics::RowRenderer) for drawing
+/// simple scenes this way.
+pub trait RowStreaming<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Transmits a full frame to the SRAM of the EPD, generated by `fill_row`
+    ///
+    /// `row` has to hold exactly one row of the panel.
+    fn update_frame_rows<F: FnMut(u32, &mut [u8])>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        row: &mut [u8],
+        fill_row: F,
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+/// Settings and diagnostics shared by all drivers
+///
+/// Implemented by every driver of this crate, on top of its connection interface.
+pub trait DriverControl<SPI, BUSY, DC, RST, DELAY>:
+    InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    /// Creates the driver like [new](WaveshareDisplay::new), resetting the controller
+    /// with `reset` instead of the default of the panel
+    ///
+    /// See [`DriverControl::set_reset_config`].
+    fn new_with_reset_config(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+        reset: ResetConfig,
+    ) -> Result<Self, Error<SPI::Error>>
+    where
+        Self: Sized,
+    {
+        let mut interface = Self::Interface::new(busy, dc, rst, delay_us);
+        interface.set_reset_config(Some(reset));
+
+        let mut epd = Self::from_interface(interface);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    /// CRC-16 over the data sent after the most recent command
+    ///
+    /// After a frame transfer this is the checksum of the last transmitted buffer,
+    /// see [crate::checksum]. On tri-color displays that is the chromatic plane,
+    /// [DriverControl::plane_checksum] has the one of each plane.
+    #[cfg(feature = "checksum")]
+    fn last_transfer_checksum(&self) -> u16 {
+        self.interface().checksum()
+    }
+
+    /// Returns [DriverControl::last_transfer_checksum] and resets it, along with the
+    /// plane checksums
+    ///
+    /// A checksum taken without any data sent in between is [crate::checksum::CRC16_INIT].
+    #[cfg(feature = "checksum")]
+    fn take_checksum(&mut self) -> u16 {
+        self.interface_mut().take_checksum()
+    }
+
+    /// CRC-16 over the data last sent to `plane` of a tri-color display
+    ///
+    /// Covers the bytes as the controller received them, e.g. the 2 bit per pixel
+    /// black/white plane of the Epd1in54b. Always [crate::checksum::CRC16_INIT] on
+    /// black/white displays.
+    #[cfg(feature = "checksum")]
+    fn plane_checksum(&self, plane: Plane) -> u16 {
+        self.interface().plane_checksum(plane)
+    }
+
+    /// Splits spi writes into chunks of at most `max_transfer_size` bytes
+    ///
+    /// Needed for spi drivers with a limited transfer size (e.g. DMA on ESP32),
+    /// `None` sends every buffer in one write (default).
+    fn set_max_transfer_size(&mut self, max_transfer_size: Option<usize>) {
+        self.interface_mut()
+            .set_max_transfer_size(max_transfer_size);
+    }
+
+    /// Sends a command with its parameters up to `attempts` more times when a spi
+    /// write fails, e.g. on a bus shared with an SD card
+    ///
+    /// Every attempt asserts CS again after a short pause. Frame data is never
+    /// retried, a failure there needs a `recover` like without retries. 0 (the
+    /// default) gives up at the first error.
+    fn set_retry(&mut self, attempts: u8) {
+        self.interface_mut().set_retry(attempts);
+    }
+
+    /// Number of consecutive idle readings of the busy pin before a wait returns
+    ///
+    /// Filters short glitches of the busy line, see [crate::busy]. 1 (the default)
+    /// returns at the first idle reading.
+    fn set_idle_debounce(&mut self, polls: u8) {
+        self.interface_mut().set_idle_debounce(polls);
+    }
+
+    /// Sets the level of the busy pin signalling busy, e.g. for clone modules with an
+    /// inverted busy line, see [crate::busy]
+    ///
+    /// Defaults to the level of the genuine Waveshare module.
+    fn set_busy_polarity(&mut self, polarity: BusyPolarity) {
+        self.interface_mut()
+            .set_busy_polarity(polarity, Self::IS_BUSY_LOW);
+    }
+
+    /// Level of the busy pin signalling busy
+    fn busy_polarity(&self) -> BusyPolarity {
+        self.interface().busy_polarity(Self::IS_BUSY_LOW)
+    }
+
+    /// Overrides the hardware reset sequence, e.g. for an inverted reset line, see
+    /// [crate::reset]
+    ///
+    /// Used from the next `wake_up` on, [DriverControl::new_with_reset_config] already
+    /// initialises with it.
+    fn set_reset_config(&mut self, config: ResetConfig) {
+        self.interface_mut().set_reset_config(Some(config));
+    }
+
+    /// Current hardware reset sequence, the `RESET_CONFIG` of the panel unless overridden
+    fn reset_config(&self) -> ResetConfig {
+        self.interface().reset_config_or(Self::RESET_CONFIG)
+    }
+
+    /// Displays the frame like [WaveshareDisplay::display_frame] and reports how long
+    /// the panel was busy, compared with the `REFRESH_LIMITS` of the panel, see
+    /// [crate::busy]
+    fn display_frame_checked(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<RefreshReport, Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface_mut().reset_busy_stats();
+        self.begin_display_frame(spi, delay)?;
+        self.wait_until_idle(spi, delay)?;
+        self.finish_display_frame(spi, delay)?;
+        let quick = self.is_quick_refresh();
+        Ok(self
+            .interface()
+            .refresh_report(&Self::REFRESH_LIMITS, quick))
+    }
+
+    /// Same as [display_frame](WaveshareDisplay::display_frame), but stops waiting for
+    /// the refresh as soon as `cancel` returns true, see
+    /// [cancelling a refresh](crate::busy#cancelling-a-refresh)
+    fn display_frame_cancellable(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cancel: &dyn Fn() -> bool,
+    ) -> Result<WaitOutcome, Error<SPI::Error>> {
+        self.begin_display_frame(spi, delay)?;
+        let outcome = InternalWiAdditions::wait_until_idle_cancellable(self, spi, delay, cancel)?;
+        if outcome == WaitOutcome::Idle {
+            self.finish_display_frame(spi, delay)?;
+        }
+        Ok(outcome)
+    }
+
+    /// Whether the busy pin reads busy, e.g. during a refresh of which the wait was
+    /// cancelled
+    fn is_busy(&mut self) -> bool {
+        self.interface_mut().is_busy(Self::IS_BUSY_LOW)
+    }
+
+    /// Checks the wiring of the busy, reset and DC line, see [crate::busy]
+    ///
+    /// Resets the controller, afterwards the driver needs a `recover` (or `wake_up`).
+    fn self_test(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<SelfTestReport, Error<SPI::Error>> {
+        self.interface_mut().self_test(
+            spi,
+            delay,
+            Self::IS_BUSY_LOW,
+            Self::RESET_CONFIG,
+            Self::SELF_TEST_COMMAND,
+        )
+    }
+
+    /// Enables the refresh guard with a clock of `ticks_per_second`, see [crate::policy]
+    ///
+    /// Afterwards full refreshes less than the `MIN_REFRESH_INTERVAL` of the panel apart
+    /// return [Error::RefreshTooSoon], the time is passed in with `note_time`. Quick
+    /// refreshes aren't limited. 0 (the default) disables the guard.
+    fn set_refresh_guard(&mut self, ticks_per_second: u32) {
+        self.interface_mut().set_refresh_guard(ticks_per_second);
+    }
+
+    /// Current time of a monotonic clock for the refresh guard, in the ticks given to
+    /// `set_refresh_guard`
+    fn note_time(&mut self, now_ticks: u64) {
+        self.interface_mut().note_time(now_ticks);
+    }
+
+    /// Displays the frame like [WaveshareDisplay::display_frame], ignoring the refresh guard
+    fn force_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface_mut().set_refresh_forced(true);
+        let result = self.display_frame(spi, delay);
+        self.interface_mut().set_refresh_forced(false);
+        result
+    }
+
+    /// Refreshes started since the driver was created, see [RefreshCounters]
+    fn refresh_counters(&self) -> RefreshCounters {
+        self.interface().refresh_counters()
+    }
+
+    /// Continues counting refreshes from `counters`, e.g. persisted before a reboot
+    fn restore_refresh_counters(&mut self, counters: RefreshCounters) {
+        self.interface_mut().restore_refresh_counters(counters);
+    }
+
+    /// Tags the errors of this display with `id`, see [Context](crate::error::Context)
+    fn set_instance_id(&mut self, id: u8) {
+        self.interface_mut().set_instance_id(id);
+    }
+}
+
+impl<T, SPI, BUSY, DC, RST, DELAY> DriverControl<SPI, BUSY, DC, RST, DELAY> for T
+where
+    T: InternalWiAdditions<SPI, BUSY, DC, RST, DELAY>,
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+}
+
+/// Async counterpart of [WaveshareDisplay] on embedded-hal-async
+///
+/// The spi transfers and delays are awaited, and instead of polling the busy pin the
+/// driver awaits its edge with [Wait], so an async executor can run other tasks
+/// during the seconds of a refresh. The drivers send exactly the same bytes as their
+/// blocking versions.
+///
+/// Only the basic full refreshes are covered so far, implemented by
+/// [`Epd4in2Async`](crate::epd4in2::Epd4in2Async) and
+/// [`Epd2in9Async`](crate::epd2in9_v2::Epd2in9Async) behind the `async` feature.
+#[cfg(feature = "async")]
+// the futures aren't `Send`, which doesn't matter on the usual single core executors
+#[allow(async_fn_in_trait)]
+pub trait AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: AsyncSpiDevice,
+    BUSY: Wait,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: AsyncDelayNs,
+{
+    /// The Color Type used by the Display
+    type DisplayColor;
+    /// Width of the display in pixels
+    const WIDTH: u32;
+
+    /// Height of the display in pixels
+    const HEIGHT: u32;
+
+    /// Creates a new driver like [WaveshareDisplay::new] and initialises the device
+    ///
+    /// There is no idle loop to configure, the busy pin is awaited.
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>>
+    where
+        Self: Sized;
+
+    /// Let the device enter deep-sleep mode to save power
+    async fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Wakes the device up from sleep and initialises it again
+    async fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
+
+    /// Sets the background color, see [WaveshareDisplay::set_background_color]
+    fn set_background_color(&mut self, color: Self::DisplayColor);
+
+    /// Get current background color
+    fn background_color(&self) -> &Self::DisplayColor;
+
+    /// Transmit a full frame to the SRAM of the EPD
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Displays the frame data from SRAM, see [WaveshareDisplay::display_frame]
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Combined [update_frame](AsyncWaveshareDisplay::update_frame) and
+    /// [display_frame](AsyncWaveshareDisplay::display_frame)
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay).await?;
+        self.display_frame(spi, delay).await
+    }
+
+    /// Waits until the device isn't busy anymore
+    async fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+#[cfg(all(test, feature = "graphics"))]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::prelude::{Point, Size};
+
+    fn window(x: i32, y: i32, width: u32, height: u32) -> Result<(u32, u32, u32, u32), Error<()>> {
+        partial_window(
+            &Rectangle::new(Point::new(x, y), Size::new(width, height)),
+            122,
+            250,
+        )
+    }
+
+    #[test]
+    fn chromatic_band_fill_lens() {
+        let band = |rows: usize, start_row: u32| {
+            ChromaticBand {
+                data: &[0; 16 * 250][..16 * rows],
+                start_row,
+            }
+            .fill_lens(122, 250)
+        };
+        // before, band and after add up to the 16 * 250 bytes of the plane
+        assert_eq!(band(250, 0), Some((0, 0)));
+        assert_eq!(band(5, 10), Some((160, 3760)));
+        assert_eq!(band(5, 245), Some((3920, 0)));
+        assert_eq!(band(0, 250), Some((4000, 0)));
+        assert_eq!(band(5, 246), None);
+        assert_eq!(band(1, u32::MAX), None);
+        // not whole rows
+        let partial_row = ChromaticBand {
+            data: &[0; 20],
+            start_row: 0,
+        };
+        assert_eq!(partial_row.fill_lens(122, 250), None);
+    }
+
+    #[test]
+    fn partial_window_checks_area() {
+        assert_eq!(window(0, 0, 122, 250), Ok((0, 0, 122, 250)));
+        assert_eq!(window(8, 16, 24, 5), Ok((8, 16, 24, 5)));
+        // the last byte of a row widened to 128 pixels stays in the padded RAM
+        assert_eq!(window(120, 249, 2, 1), Ok((120, 249, 2, 1)));
+        assert_eq!(window(3, 0, 7, 1), Ok((3, 0, 7, 1)));
+
+        assert_eq!(window(0, 0, 0, 10), Err(Error::InvalidWindow));
+        assert_eq!(window(0, 0, 10, 0), Err(Error::InvalidWindow));
+        assert_eq!(window(-8, 0, 16, 1), Err(Error::InvalidWindow));
+        assert_eq!(window(0, -1, 16, 1), Err(Error::InvalidWindow));
+        assert_eq!(window(120, 0, 3, 1), Err(Error::InvalidWindow));
+        assert_eq!(window(0, 200, 8, 51), Err(Error::InvalidWindow));
+        assert_eq!(window(0, 0, 123, 1), Err(Error::InvalidWindow));
+        // no wrap around for huge sizes
+        assert_eq!(window(8, 0, u32::MAX, 1), Err(Error::InvalidWindow));
+        assert_eq!(window(0, 8, 8, u32::MAX - 4), Err(Error::InvalidWindow));
+    }
+
+    #[test]
+    fn clear_windows_surround_keep() {
+        let windows = |x, y, width, height| {
+            clear_windows::<()>(
+                &Rectangle::new(Point::new(x, y), Size::new(width, height)),
+                122,
+                250,
+            )
+        };
+
+        // the kept window grows to whole bytes on both sides
+        assert_eq!(
+            windows(13, 20, 30, 10),
+            Ok((
+                (8, 20, 40, 10),
+                [
+                    Some((0, 0, 122, 20)),
+                    Some((0, 30, 122, 220)),
+                    Some((0, 20, 8, 10)),
+                    Some((48, 20, 74, 10)),
+                ]
+            ))
+        );
+        // top left corner
+        assert_eq!(
+            windows(0, 0, 16, 5),
+            Ok((
+                (0, 0, 16, 5),
+                [None, Some((0, 5, 122, 245)), None, Some((16, 0, 106, 5))]
+            ))
+        );
+        // the right edge isn't a multiple of 8, the end is clipped to the panel
+        assert_eq!(
+            windows(115, 0, 7, 250),
+            Ok((
+                (112, 0, 10, 250),
+                [None, None, Some((0, 0, 112, 250)), None]
+            ))
+        );
+        assert_eq!(
+            windows(100, 240, 3, 10),
+            Ok((
+                (96, 240, 8, 10),
+                [
+                    Some((0, 0, 122, 240)),
+                    None,
+                    Some((0, 240, 96, 10)),
+                    Some((104, 240, 18, 10)),
+                ]
+            ))
+        );
+        // full rows and the whole panel
+        assert_eq!(
+            windows(0, 100, 122, 50),
+            Ok((
+                (0, 100, 122, 50),
+                [Some((0, 0, 122, 100)), Some((0, 150, 122, 100)), None, None]
+            ))
+        );
+        assert_eq!(windows(0, 0, 122, 250), Ok(((0, 0, 122, 250), [None; 4])));
+
+        assert_eq!(windows(-1, 0, 8, 8), Err(Error::InvalidWindow));
+        assert_eq!(windows(120, 0, 8, 8), Err(Error::InvalidWindow));
+    }
 }
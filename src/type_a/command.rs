@@ -1,6 +1,6 @@
 //! SPI Commands for the Waveshare 2.9" and 1.54" E-Ink Display
 
-use crate::traits;
+use crate::traits::{self, DataLen};
 
 /// Epd1in54 and EPD2IN9 commands
 ///
@@ -9,7 +9,7 @@ use crate::traits;
 /// For more infos about the addresses and what they are doing look into the pdfs
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
-pub(crate) enum Command {
+pub enum Command {
     /// Driver Output control
     ///     3 Databytes:
     ///     A[7:0]
@@ -55,8 +55,14 @@ pub(crate) enum Command {
 
     WriteRam2 = 0x26,
 
+    /// The first byte read afterwards is a dummy byte
+    ReadRam = 0x27,
+
     WriteVcomRegister = 0x2C,
 
+    /// Not supported by the IL3820 of the first panel versions
+    UserIdRead = 0x2E,
+
     WriteLutRegister = 0x32,
 
     WriteOtpSelection = 0x37,
@@ -85,6 +91,35 @@ impl traits::Command for Command {
     fn address(self) -> u8 {
         self as u8
     }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::SwReset | Command::MasterActivation | Command::Nop => DataLen::Exact(0),
+            Command::GateDrivingVoltage
+            | Command::DeepSleepMode
+            | Command::DataEntryModeSetting
+            | Command::TemperatureSensorSelection
+            | Command::DisplayUpdateControl2
+            | Command::WriteVcomRegister
+            | Command::SetDummyLinePeriod
+            | Command::SetGateLineWidth
+            | Command::BorderWaveformControl
+            | Command::WriteLutRegisterEnd
+            | Command::SetRamXAddressCounter => DataLen::Exact(1),
+            Command::TemperatureSensorControl
+            | Command::SetRamXAddressStartEndPosition
+            | Command::SetRamYAddressCounter => DataLen::Exact(2),
+            Command::DriverOutputControl | Command::SourceDrivingVoltage => DataLen::Exact(3),
+            Command::SetRamYAddressStartEndPosition => DataLen::Exact(4),
+            Command::WriteOtpSelection => DataLen::Exact(10),
+            Command::GateScanStartPosition | Command::DisplayUpdateControl1 => DataLen::Max(2),
+            Command::BoosterSoftStartControl => DataLen::Max(4),
+            // 30 bytes on the SSD1608, 153 on the SSD1680/1681, the 2.9" V2 sends the
+            // voltages following the partial LUT along
+            Command::WriteLutRegister => DataLen::Max(159),
+            _ => DataLen::Variable,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +135,18 @@ mod tests {
 
         assert_eq!(Command::Nop.address(), 0xFF);
     }
+
+    #[test]
+    fn wrong_data_len() {
+        assert!(Command::WriteLutRegister.expected_data_len().accepts(30));
+        assert!(!Command::WriteLutRegister.expected_data_len().accepts(160));
+        assert!(Command::SetRamYAddressStartEndPosition
+            .expected_data_len()
+            .accepts(4));
+        assert!(!Command::SetRamYAddressStartEndPosition
+            .expected_data_len()
+            .accepts(2));
+        assert!(Command::WriteOtpSelection.expected_data_len().accepts(10));
+        assert!(!Command::WriteOtpSelection.expected_data_len().accepts(7));
+    }
 }
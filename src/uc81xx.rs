@@ -0,0 +1,247 @@
+//! Shared parts of the quick refreshes of the UC81xx based drivers
+//!
+//! In b/w mode the controllers drive a pixel from its value in the RAM written with
+//! DataStartTransmission1 (old frame) to the one in the RAM written with
+//! DataStartTransmission2 (new frame). Inside `PartialIn`/`PartialOut` both transfers
+//! only cover the window set with `PartialWindow`, whose parameters differ between the
+//! controllers in the width of the coordinates, see [Coordinates].
+//!
+//! # References
+//!
+//! - [Ben Krasnows partial Refresh tips](https://benkrasnow.blogspot.de/2017/10/fast-partial-refresh-on-42-e-paper.html)
+
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{InputPin, OutputPin},
+    spi::SpiDevice,
+};
+
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::traits::{self, DataLen};
+
+/// The commands of the quick refreshes, the same on all UC81xx controllers
+#[derive(Copy, Clone)]
+enum Command {
+    DataStartTransmission1 = 0x10,
+    DataStartTransmission2 = 0x13,
+    PartialWindow = 0x90,
+    PartialIn = 0x91,
+    PartialOut = 0x92,
+}
+
+impl traits::Command for Command {
+    fn address(self) -> u8 {
+        self as u8
+    }
+
+    fn expected_data_len(self) -> DataLen {
+        match self {
+            Command::PartialIn | Command::PartialOut => DataLen::Exact(0),
+            // the frames and both encodings of the window
+            Command::DataStartTransmission1
+            | Command::DataStartTransmission2
+            | Command::PartialWindow => DataLen::Variable,
+        }
+    }
+}
+
+/// RAM of one of both frames of a quick refresh
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Frame {
+    /// Written with DataStartTransmission1, the b/w RAM on tri-color panels
+    Old,
+    /// Written with DataStartTransmission2, the chromatic RAM on tri-color panels
+    New,
+}
+
+impl Frame {
+    fn command(self) -> Command {
+        match self {
+            Frame::Old => Command::DataStartTransmission1,
+            Frame::New => Command::DataStartTransmission2,
+        }
+    }
+}
+
+/// Encoding of the coordinates in the `PartialWindow` parameters
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Coordinates {
+    /// x in one byte, y in 9 bits (UC8151, panels up to 256 px wide)
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    Byte,
+    /// x and y in two bytes each (UC8176)
+    #[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+    Word,
+}
+
+/// Parameters of `PartialWindow` for one window
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PartialWindow {
+    params: [u8; 9],
+    len: usize,
+}
+
+impl PartialWindow {
+    /// The window of `width` x `height` at (x, y)
+    ///
+    /// x is rounded down to the byte, the window ends on the last pixel of a byte.
+    pub(crate) fn new(coordinates: Coordinates, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let x_start = x & !0x07;
+        let x_end = (x_start + width - 1) | 0x07;
+        let y_end = y + height - 1;
+        // gates scan inside and outside of the window
+        let scan = 0x01;
+        match coordinates {
+            Coordinates::Byte => PartialWindow {
+                params: [
+                    x_start as u8,
+                    x_end as u8,
+                    (y >> 8) as u8 & 0x01,
+                    y as u8,
+                    (y_end >> 8) as u8 & 0x01,
+                    y_end as u8,
+                    scan,
+                    0,
+                    0,
+                ],
+                len: 7,
+            },
+            Coordinates::Word => PartialWindow {
+                params: [
+                    (x >> 8) as u8,
+                    x_start as u8,
+                    (x_end >> 8) as u8,
+                    x_end as u8,
+                    (y >> 8) as u8,
+                    y as u8,
+                    (y_end >> 8) as u8,
+                    y_end as u8,
+                    scan,
+                ],
+                len: 9,
+            },
+        }
+    }
+
+    /// The parameters as sent after `PartialWindow`
+    pub(crate) fn params(&self) -> &[u8] {
+        &self.params[..self.len]
+    }
+}
+
+/// Writes `buffer` into the RAM of `frame`
+pub(crate) fn write_frame<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    frame: Frame,
+    buffer: &[u8],
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd_with_data(spi, frame.command(), buffer)
+}
+
+/// Fills `len` bytes of the RAM of `frame` with `byte`
+pub(crate) fn fill_frame<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    frame: Frame,
+    byte: u8,
+    len: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd(spi, frame.command())?;
+    interface.data_x_times(spi, byte, len)
+}
+
+/// Enters the partial mode, the following frame transfers only cover `window`
+///
+/// The parameters of the window are sent in one CS assertion.
+pub(crate) fn enter_partial<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    window: &PartialWindow,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd(spi, Command::PartialIn)?;
+    interface.cmd_with_data(spi, Command::PartialWindow, window.params())
+}
+
+/// Leaves the partial mode again
+pub(crate) fn leave_partial<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    interface.cmd(spi, Command::PartialOut)
+}
+
+/// Fills `window` in both frames with `byte` between entering and leaving the partial
+/// mode
+#[cfg_attr(not(feature = "all-displays"), allow(dead_code))]
+pub(crate) fn clear_partial_frames<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>(
+    interface: &mut DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    spi: &mut SPI,
+    window: &PartialWindow,
+    byte: u8,
+    len: u32,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    enter_partial(interface, spi, window)?;
+    fill_frame(interface, spi, Frame::Old, byte, len)?;
+    fill_frame(interface, spi, Frame::New, byte, len)?;
+    leave_partial(interface, spi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_window_encodings() {
+        // y ends at 279, above 8 bits
+        assert_eq!(
+            PartialWindow::new(Coordinates::Byte, 120, 200, 8, 80).params(),
+            [0x78, 0x7F, 0x00, 0xC8, 0x01, 0x17, 0x01]
+        );
+        assert_eq!(
+            PartialWindow::new(Coordinates::Word, 296, 200, 8, 80).params(),
+            [0x01, 0x28, 0x01, 0x2F, 0x00, 0xC8, 0x01, 0x17, 0x01]
+        );
+        // x is rounded down to the byte, the end follows the rounded start
+        assert_eq!(
+            PartialWindow::new(Coordinates::Byte, 13, 0, 16, 1).params(),
+            [8, 23, 0, 0, 0, 0, 0x01]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Steps of a display update on the SSD16xx controllers
+//!
+//! `MasterActivation` (0x20) runs the steps selected with `DisplayUpdateControl2`
+//! (0x22) in the order of [`UpdateSequence`]: enabling the clock and the analog
+//! block, loading the temperature and the LUT for it, displaying and disabling the
+//! analog block and the clock again. Every SSD16xx driver sending the option uses
+//! one which works for its panel. `set_update_sequence` replaces it for all
+//! refreshes, e.g. to save the time of the temperature reading.
+//!
+//! # Known combinations
+//!
+//! - [`UpdateSequence::FULL_WITH_TEMP`] (0xF7) runs every step, the default of most
+//!   controllers
+//! - [`UpdateSequence::FAST_NO_TEMP`] (0xD7) loads the LUT for the temperature read
+//!   by the last update, saving about 30 ms
+//! - [`UpdateSequence::DISPLAY_ONLY`] (0xC7) displays with the LUT loaded before,
+//!   e.g. a custom one written to the LUT register
+//! - 0xC4 displays like `DISPLAY_ONLY` but leaves the clock and the analog block
+//!   running, the type A drivers use it for their quick refreshes
+//!
+//! Drivers with a display mode 2 refresh (0x0F on the 2.9" V2) keep it for their
+//! quick refreshes, as it updates the old frame RAM they rely on.
+//!
+//! # Rejected combinations
+//!
+//! The datasheets only describe sequences which enable the analog block after the
+//! clock and disable it before. The drivers refuse with
+//! [`Error::InvalidUpdateSequence`](crate::Error::InvalidUpdateSequence):
+//!
+//! - an empty sequence, `MasterActivation` would have nothing to do
+//! - enabling the analog block without enabling the clock
+//! - disabling the clock while the analog block enabled by the sequence keeps running
+//! - display mode 2 without displaying
+//!
+//! # Fast full refreshes
+//!
+//! The controllers pick the waveform from the OTP by the value in their temperature
+//! register, and the waveforms for hot panels are a lot shorter. The "Fast" examples
+//! of Waveshare write a fake temperature of 100 °C into the register before loading
+//! the LUT, which makes a full refresh take about half as long (~1 s instead of ~2 s
+//! on the 1.54" V2).
+//!
+//! `display_frame_fast` on the 1.54" V2, 2.13" V2/V3 and 2.9" V2 writes the fake
+//! temperature, loads the LUT for it, refreshes with it and reads the real
+//! temperature again afterwards. The driver then uploads its own LUT again, so the
+//! following refreshes aren't affected. The short waveform drives the pixels less
+//! long, the contrast is slightly lower than after a normal full refresh, and it
+//! doesn't clear ghosting as thoroughly.
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd2in13", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd2in13"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd2in13_v2::*, prelude::*, update_sequence::UpdateSequence};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd2in13::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// the room temperature doesn't change between the refreshes
+//!epd.set_update_sequence(Some(UpdateSequence::FAST_NO_TEMP))?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use core::ops::BitOr;
+
+/// Steps run by `MasterActivation`, the value of `DisplayUpdateControl2` (0x22)
+///
+/// Combine the steps with `|`, see [crate::update_sequence].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateSequence(u8);
+
+impl UpdateSequence {
+    /// Enable the clock signal (oscillator)
+    pub const ENABLE_CLOCK: Self = UpdateSequence(0x80);
+    /// Enable the analog block (charge pump)
+    pub const ENABLE_ANALOG: Self = UpdateSequence(0x40);
+    /// Read the temperature sensor
+    pub const LOAD_TEMPERATURE: Self = UpdateSequence(0x20);
+    /// Load the LUT of the OTP for the temperature
+    pub const LOAD_LUT: Self = UpdateSequence(0x10);
+    /// Display with display mode 2 instead of 1, needs [`DISPLAY`](Self::DISPLAY)
+    pub const DISPLAY_MODE_2: Self = UpdateSequence(0x08);
+    /// Drive the panel with the RAM content
+    pub const DISPLAY: Self = UpdateSequence(0x04);
+    /// Disable the analog block
+    pub const DISABLE_ANALOG: Self = UpdateSequence(0x02);
+    /// Disable the clock signal
+    pub const DISABLE_CLOCK: Self = UpdateSequence(0x01);
+
+    /// Every step with display mode 1 (0xF7)
+    pub const FULL_WITH_TEMP: Self = UpdateSequence(0xF7);
+    /// Every step but the temperature reading (0xD7)
+    pub const FAST_NO_TEMP: Self = UpdateSequence(0xD7);
+    /// Display with the loaded LUT and power down afterwards (0xC7)
+    pub const DISPLAY_ONLY: Self = UpdateSequence(0xC7);
+
+    /// Sequence of the raw register value
+    pub const fn from_bits(bits: u8) -> Self {
+        UpdateSequence(bits)
+    }
+
+    /// Raw register value
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Both the steps of `self` and of `other`
+    pub const fn union(self, other: Self) -> Self {
+        UpdateSequence(self.0 | other.0)
+    }
+
+    /// Whether all steps of `other` are part of the sequence
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the controller can run the sequence, see
+    /// [rejected combinations](crate::update_sequence#rejected-combinations)
+    pub const fn is_valid(self) -> bool {
+        let analog_without_clock =
+            self.contains(Self::ENABLE_ANALOG) && !self.contains(Self::ENABLE_CLOCK);
+        let clock_off_analog_on = self.contains(Self::ENABLE_ANALOG)
+            && self.contains(Self::DISABLE_CLOCK)
+            && !self.contains(Self::DISABLE_ANALOG);
+        let mode_2_without_display =
+            self.contains(Self::DISPLAY_MODE_2) && !self.contains(Self::DISPLAY);
+        self.0 != 0 && !analog_without_clock && !clock_off_analog_on && !mode_2_without_display
+    }
+}
+
+impl BitOr for UpdateSequence {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_the_documented_values() {
+        let all = UpdateSequence::ENABLE_CLOCK
+            | UpdateSequence::ENABLE_ANALOG
+            | UpdateSequence::LOAD_TEMPERATURE
+            | UpdateSequence::LOAD_LUT
+            | UpdateSequence::DISPLAY
+            | UpdateSequence::DISABLE_ANALOG
+            | UpdateSequence::DISABLE_CLOCK;
+        assert_eq!(all, UpdateSequence::FULL_WITH_TEMP);
+        assert_eq!(
+            UpdateSequence::FULL_WITH_TEMP.bits() & !UpdateSequence::LOAD_TEMPERATURE.bits(),
+            UpdateSequence::FAST_NO_TEMP.bits()
+        );
+        assert!(!UpdateSequence::DISPLAY_ONLY.contains(UpdateSequence::LOAD_LUT));
+        for preset in [
+            UpdateSequence::FULL_WITH_TEMP,
+            UpdateSequence::FAST_NO_TEMP,
+            UpdateSequence::DISPLAY_ONLY,
+        ] {
+            assert!(preset.is_valid());
+        }
+    }
+
+    #[test]
+    fn validity() {
+        // the sequences sent by the drivers
+        for bits in [0xC0, 0xC4, 0xC7, 0xCF, 0x0F, 0xB1, 0xF7, 0xFF, 0x91] {
+            assert!(UpdateSequence::from_bits(bits).is_valid(), "{:#04x}", bits);
+        }
+        // empty, analog without clock, clock off under analog, mode 2 without display
+        for bits in [0x00, 0x44, 0xC5, 0x88] {
+            assert!(!UpdateSequence::from_bits(bits).is_valid(), "{:#04x}", bits);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Conversion of VCOM voltages to the register values of the controllers
+//!
+//! Every panel is measured in the factory and its VCOM voltage is printed on the
+//! flex cable (e.g. `-2.05V`). The drivers use a typical value by default, the
+//! printed one can be set with `set_vcom` or `new_with_vcom` in millivolts
+//! against ghosting and a low contrast. Values outside of the range of a
+//! controller are clamped to the nearest supported voltage.
+
+/// Most negative VCOM of the UC81xx (and IL03xx) controllers, -3.0V
+pub const UC81XX_MIN_MILLIVOLTS: i16 = -3000;
+/// Least negative VCOM of the UC81xx (and IL03xx) controllers, -0.1V
+pub const UC81XX_MAX_MILLIVOLTS: i16 = -100;
+
+/// Most negative VCOM of the SSD16xx controllers, -3.0V
+pub const SSD16XX_MIN_MILLIVOLTS: i16 = -3000;
+/// Least negative VCOM of the SSD16xx controllers, -0.2V
+pub const SSD16XX_MAX_MILLIVOLTS: i16 = -200;
+
+/// Value of the `VcmDcSetting` (0x82) register of the UC81xx controllers
+///
+/// The register counts down from -0.1V in steps of 50mV, so 0x12 is -1.0V.
+pub const fn uc81xx_register(millivolts: i16) -> u8 {
+    let mv = clamp(millivolts, UC81XX_MIN_MILLIVOLTS, UC81XX_MAX_MILLIVOLTS);
+    ((UC81XX_MAX_MILLIVOLTS - mv) / 50) as u8
+}
+
+/// Value of the `WriteVcomRegister` (0x2C) register of the SSD16xx controllers
+///
+/// The register counts down from 0V in steps of 25mV, starting at 0x08 (-0.2V).
+pub const fn ssd16xx_register(millivolts: i16) -> u8 {
+    let mv = clamp(millivolts, SSD16XX_MIN_MILLIVOLTS, SSD16XX_MAX_MILLIVOLTS);
+    (-mv / 25) as u8
+}
+
+const fn clamp(value: i16, min: i16, max: i16) -> i16 {
+    if value < min {
+        min
+    } else if value > max {
+        max
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uc81xx_conversion() {
+        // the defaults of the drivers
+        assert_eq!(uc81xx_register(-1000), 0x12);
+        assert_eq!(uc81xx_register(-2100), 0x28);
+        assert_eq!(uc81xx_register(-2050), 0x27);
+        // boundaries
+        assert_eq!(uc81xx_register(-100), 0x00);
+        assert_eq!(uc81xx_register(-3000), 0x3A);
+        // out of range
+        assert_eq!(uc81xx_register(0), 0x00);
+        assert_eq!(uc81xx_register(-4000), 0x3A);
+        assert_eq!(uc81xx_register(i16::MAX), 0x00);
+        assert_eq!(uc81xx_register(i16::MIN), 0x3A);
+    }
+
+    #[test]
+    fn ssd16xx_conversion() {
+        assert_eq!(ssd16xx_register(-1000), 0x28);
+        assert_eq!(ssd16xx_register(-2050), 0x52);
+        // boundaries
+        assert_eq!(ssd16xx_register(-200), 0x08);
+        assert_eq!(ssd16xx_register(-3000), 0x78);
+        // out of range
+        assert_eq!(ssd16xx_register(0), 0x08);
+        assert_eq!(ssd16xx_register(-4000), 0x78);
+        assert_eq!(ssd16xx_register(i16::MAX), 0x08);
+        assert_eq!(ssd16xx_register(i16::MIN), 0x78);
+    }
+}
@@ -0,0 +1,367 @@
+//! Driving voltages of the panels, e.g. for a stronger drive in the cold
+//!
+//! The particles of e-paper move slower at low temperatures, at about -15 °C the
+//! default voltages of the drivers leave faint images. The drivers send fixed
+//! gate and source driving voltages in their init, `set_driving_voltages` replaces
+//! them with a [DrivingVoltages] which the next `init`/`wake_up` (and every one
+//! after it) sends instead. `None` restores the defaults of the driver.
+//!
+//! [DrivingVoltages] has the voltages of both controller families, every driver
+//! uses the ones of its controller:
+//!
+//! - [Ssd16xxVoltages] for the `GateDrivingVoltage` (0x03) and `SourceDrivingVoltage`
+//!   (0x04) commands of the SSD16xx controllers, used by Epd2in13 (V2/V3) and
+//!   Epd2in9 (V2)
+//! - [Uc81xxVoltages] for the `PowerSetting` (0x01) command of the UC81xx
+//!   controllers, used by Epd2in9d and Epd2in13d
+//!
+//! Voltages outside of the range of a controller, or between two of its steps, are
+//! refused with [`Error::InvalidVoltage`](crate::Error::InvalidVoltage) naming the
+//! [Rail], unlike the VCOM voltages of [crate::vcom] which are clamped: a wrong
+//! driving voltage can damage the panel.
+//!
+//! # Presets
+//!
+//! - [`VoltagePreset::Default`] the voltages of the Waveshare examples
+//! - [`VoltagePreset::ColdWeather`] the highest source and gate voltages, for panels
+//!   below about 0 °C
+//! - [`VoltagePreset::HighContrast`] slightly raised source voltages for a deeper
+//!   black at room temperature
+//!
+//! Higher voltages stress the panel more, use the presets only where needed.
+//!
+//! # Example
+//!
+#![cfg_attr(feature = "epd2in13", doc = "```rust, no_run")]
+#![cfg_attr(not(feature = "epd2in13"), doc = "```ignore")]
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), epd_waveshare::Error<embedded_hal::spi::ErrorKind>> {
+//!use epd_waveshare::{epd2in13_v2::*, prelude::*, voltage::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
+//!
+//!let mut epd = Epd2in13::new(&mut spi, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// the outdoor unit is below freezing
+//!epd.set_driving_voltages(Some(VoltagePreset::ColdWeather.into()))?;
+//!epd.wake_up(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+/// A driving voltage of the panel, named in [`Error::InvalidVoltage`](crate::Error::InvalidVoltage)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rail {
+    /// Positive gate voltage, VGH (and VGL on the UC81xx controllers)
+    Vgh,
+    /// First positive source voltage, VSH1
+    Vsh1,
+    /// Second positive source voltage, VSH2
+    Vsh2,
+    /// Negative source voltage, VSL
+    Vsl,
+    /// Positive source voltage of the UC81xx controllers, VDH
+    Vdh,
+    /// Negative source voltage of the UC81xx controllers, VDL
+    Vdl,
+    /// Source voltage of the red pixels of the UC81xx controllers, VDHR
+    Vdhr,
+}
+
+/// Driving voltages of the SSD16xx controllers in millivolts
+///
+/// The gate driving command of these controllers only takes VGH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ssd16xxVoltages {
+    /// Gate high voltage, 10 V to 21 V in steps of 500 mV
+    pub vgh: u16,
+    /// Source high voltage 1, 2.4 V to 8.8 V in steps of 100 mV or 9 V to 18 V in steps of 200 mV
+    pub vsh1: u16,
+    /// Source high voltage 2, in the same range as `vsh1`
+    pub vsh2: u16,
+    /// Source low voltage, -9 V to -18 V in steps of 500 mV
+    pub vsl: i16,
+}
+
+impl Ssd16xxVoltages {
+    /// Parameters of `GateDrivingVoltage` (first byte) and `SourceDrivingVoltage`
+    pub const fn registers(&self) -> Result<[u8; 4], Rail> {
+        let vgh = match ssd16xx_gate_register(self.vgh) {
+            Some(register) => register,
+            None => return Err(Rail::Vgh),
+        };
+        let vsh1 = match ssd16xx_vsh_register(self.vsh1) {
+            Some(register) => register,
+            None => return Err(Rail::Vsh1),
+        };
+        let vsh2 = match ssd16xx_vsh_register(self.vsh2) {
+            Some(register) => register,
+            None => return Err(Rail::Vsh2),
+        };
+        let vsl = match ssd16xx_vsl_register(self.vsl) {
+            Some(register) => register,
+            None => return Err(Rail::Vsl),
+        };
+        Ok([vgh, vsh1, vsh2, vsl])
+    }
+}
+
+/// Driving voltages of the UC81xx controllers in millivolts
+///
+/// Sent as the `PowerSetting` parameters, with the internal DC/DC converters enabled
+/// for the source and gate voltages like the drivers do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uc81xxVoltages {
+    /// Gate high voltage, VGL is its negative, 13 V to 16 V in steps of 1 V
+    pub vgh: u16,
+    /// Source high voltage, 2.4 V to 11 V in steps of 200 mV
+    pub vdh: u16,
+    /// Source low voltage, -2.4 V to -11 V in steps of 200 mV
+    pub vdl: i16,
+    /// Source voltage of the red pixels, 2.4 V to 11 V in steps of 200 mV
+    pub vdhr: u16,
+}
+
+impl Uc81xxVoltages {
+    /// Parameters of `PowerSetting`
+    pub const fn registers(&self) -> Result<[u8; 5], Rail> {
+        if self.vgh < 13_000 || self.vgh > 16_000 || !self.vgh.is_multiple_of(1000) {
+            return Err(Rail::Vgh);
+        }
+        let vdh = match uc81xx_source_register(self.vdh) {
+            Some(register) => register,
+            None => return Err(Rail::Vdh),
+        };
+        // -i16::MIN doesn't fit, it is out of range anyway
+        let vdl = match uc81xx_source_register(self.vdl.unsigned_abs()) {
+            Some(register) if self.vdl < 0 => register,
+            _ => return Err(Rail::Vdl),
+        };
+        let vdhr = match uc81xx_source_register(self.vdhr) {
+            Some(register) => register,
+            None => return Err(Rail::Vdhr),
+        };
+        // VDS_EN and VDG_EN, VGHL_LV counts down from 16 V
+        let vghl = ((16_000 - self.vgh) / 1000) as u8;
+        Ok([0x03, vghl, vdh, vdl, vdhr])
+    }
+}
+
+/// Driving voltages for the drivers of both controller families, see the
+/// [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrivingVoltages {
+    /// Used by the SSD16xx drivers
+    pub ssd16xx: Ssd16xxVoltages,
+    /// Used by the UC81xx drivers
+    pub uc81xx: Uc81xxVoltages,
+}
+
+/// Named combinations of [DrivingVoltages], see the [module documentation](self#presets)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoltagePreset {
+    /// The voltages of the Waveshare examples
+    #[default]
+    Default,
+    /// The highest source and gate voltages, for panels below about 0 °C
+    ColdWeather,
+    /// Raised source voltages for a deeper black at room temperature
+    HighContrast,
+}
+
+impl DrivingVoltages {
+    /// The voltages of `preset`
+    pub const fn preset(preset: VoltagePreset) -> Self {
+        match preset {
+            VoltagePreset::Default => DrivingVoltages {
+                ssd16xx: Ssd16xxVoltages {
+                    vgh: 20_000,
+                    vsh1: 15_000,
+                    vsh2: 5_000,
+                    vsl: -15_000,
+                },
+                uc81xx: Uc81xxVoltages {
+                    vgh: 16_000,
+                    vdh: 11_000,
+                    vdl: -11_000,
+                    vdhr: 3_000,
+                },
+            },
+            VoltagePreset::ColdWeather => DrivingVoltages {
+                ssd16xx: Ssd16xxVoltages {
+                    vgh: 21_000,
+                    vsh1: 18_000,
+                    vsh2: 5_000,
+                    vsl: -18_000,
+                },
+                // the defaults are the highest voltages already
+                uc81xx: Uc81xxVoltages {
+                    vgh: 16_000,
+                    vdh: 11_000,
+                    vdl: -11_000,
+                    vdhr: 3_000,
+                },
+            },
+            VoltagePreset::HighContrast => DrivingVoltages {
+                ssd16xx: Ssd16xxVoltages {
+                    vgh: 20_000,
+                    vsh1: 16_000,
+                    vsh2: 5_000,
+                    vsl: -16_000,
+                },
+                uc81xx: Uc81xxVoltages {
+                    vgh: 16_000,
+                    vdh: 11_000,
+                    vdl: -11_000,
+                    vdhr: 3_000,
+                },
+            },
+        }
+    }
+}
+
+impl Default for DrivingVoltages {
+    fn default() -> Self {
+        Self::preset(VoltagePreset::Default)
+    }
+}
+
+impl From<VoltagePreset> for DrivingVoltages {
+    fn from(preset: VoltagePreset) -> Self {
+        Self::preset(preset)
+    }
+}
+
+/// `GateDrivingVoltage` of the SSD16xx controllers, 0x03 is 10 V and every step 500 mV more
+const fn ssd16xx_gate_register(millivolts: u16) -> Option<u8> {
+    if millivolts < 10_000 || millivolts > 21_000 || !millivolts.is_multiple_of(500) {
+        return None;
+    }
+    Some(0x03 + ((millivolts - 10_000) / 500) as u8)
+}
+
+/// VSH1/VSH2 of the SSD16xx controllers
+///
+/// 0x8E is 2.4 V counting up in steps of 100 mV to 8.8 V, 0x23 is 9 V counting up in
+/// steps of 200 mV to 18 V.
+const fn ssd16xx_vsh_register(millivolts: u16) -> Option<u8> {
+    match millivolts {
+        2_400..=8_800 if millivolts.is_multiple_of(100) => {
+            Some(0x8E + ((millivolts - 2_400) / 100) as u8)
+        }
+        9_000..=18_000 if millivolts.is_multiple_of(200) => {
+            Some(0x23 + ((millivolts - 9_000) / 200) as u8)
+        }
+        _ => None,
+    }
+}
+
+/// VSL of the SSD16xx controllers, 0x1A is -9 V and every second value 500 mV lower
+const fn ssd16xx_vsl_register(millivolts: i16) -> Option<u8> {
+    match millivolts {
+        -18_000..=-9_000 if millivolts % 500 == 0 => {
+            Some(0x1A + ((-9_000 - millivolts) / 500) as u8 * 2)
+        }
+        _ => None,
+    }
+}
+
+/// VDH/VDL/VDHR of the UC81xx controllers, 0x00 is 2.4 V and every step 200 mV more
+const fn uc81xx_source_register(millivolts: u16) -> Option<u8> {
+    if millivolts < 2_400 || millivolts > 11_000 || !millivolts.is_multiple_of(200) {
+        return None;
+    }
+    Some(((millivolts - 2_400) / 200) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssd16xx_gate_range() {
+        assert_eq!(ssd16xx_gate_register(10_000), Some(0x03));
+        assert_eq!(ssd16xx_gate_register(19_000), Some(0x15));
+        assert_eq!(ssd16xx_gate_register(20_000), Some(0x17));
+        assert_eq!(ssd16xx_gate_register(21_000), Some(0x19));
+        assert_eq!(ssd16xx_gate_register(9_500), None);
+        assert_eq!(ssd16xx_gate_register(21_500), None);
+        assert_eq!(ssd16xx_gate_register(15_100), None);
+    }
+
+    #[test]
+    fn ssd16xx_source_ranges() {
+        // both ends of the fine range
+        assert_eq!(ssd16xx_vsh_register(2_400), Some(0x8E));
+        assert_eq!(ssd16xx_vsh_register(5_000), Some(0xA8));
+        assert_eq!(ssd16xx_vsh_register(8_800), Some(0xCE));
+        // both ends of the coarse range
+        assert_eq!(ssd16xx_vsh_register(9_000), Some(0x23));
+        assert_eq!(ssd16xx_vsh_register(15_000), Some(0x41));
+        assert_eq!(ssd16xx_vsh_register(18_000), Some(0x50));
+        // in between the ranges, out of range or off the steps
+        assert_eq!(ssd16xx_vsh_register(8_900), None);
+        assert_eq!(ssd16xx_vsh_register(2_300), None);
+        assert_eq!(ssd16xx_vsh_register(18_200), None);
+        assert_eq!(ssd16xx_vsh_register(9_100), None);
+        assert_eq!(ssd16xx_vsh_register(2_450), None);
+
+        assert_eq!(ssd16xx_vsl_register(-9_000), Some(0x1A));
+        assert_eq!(ssd16xx_vsl_register(-15_000), Some(0x32));
+        assert_eq!(ssd16xx_vsl_register(-18_000), Some(0x3E));
+        assert_eq!(ssd16xx_vsl_register(-8_500), None);
+        assert_eq!(ssd16xx_vsl_register(-18_500), None);
+        assert_eq!(ssd16xx_vsl_register(-9_200), None);
+        assert_eq!(ssd16xx_vsl_register(i16::MIN), None);
+    }
+
+    #[test]
+    fn uc81xx_ranges() {
+        assert_eq!(uc81xx_source_register(2_400), Some(0x00));
+        assert_eq!(uc81xx_source_register(3_000), Some(0x03));
+        assert_eq!(uc81xx_source_register(11_000), Some(0x2B));
+        assert_eq!(uc81xx_source_register(2_200), None);
+        assert_eq!(uc81xx_source_register(11_200), None);
+        assert_eq!(uc81xx_source_register(5_100), None);
+
+        let mut voltages = DrivingVoltages::default().uc81xx;
+        // the bytes of the drivers
+        assert_eq!(voltages.registers(), Ok([0x03, 0x00, 0x2B, 0x2B, 0x03]));
+        voltages.vgh = 13_000;
+        assert_eq!(voltages.registers(), Ok([0x03, 0x03, 0x2B, 0x2B, 0x03]));
+        voltages.vgh = 17_000;
+        assert_eq!(voltages.registers(), Err(Rail::Vgh));
+        voltages.vgh = 16_000;
+        voltages.vdl = 11_000;
+        assert_eq!(voltages.registers(), Err(Rail::Vdl));
+        voltages.vdl = i16::MIN;
+        assert_eq!(voltages.registers(), Err(Rail::Vdl));
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for preset in [
+            VoltagePreset::Default,
+            VoltagePreset::ColdWeather,
+            VoltagePreset::HighContrast,
+        ] {
+            let voltages = DrivingVoltages::from(preset);
+            assert!(voltages.ssd16xx.registers().is_ok());
+            assert!(voltages.uc81xx.registers().is_ok());
+        }
+        // VGH, VSH1 and VSL of the 2.9" V2 waveform
+        assert_eq!(
+            DrivingVoltages::default().ssd16xx.registers(),
+            Ok([0x17, 0x41, 0xA8, 0x32])
+        );
+        let mut voltages = DrivingVoltages::default().ssd16xx;
+        voltages.vsh2 = 8_900;
+        assert_eq!(voltages.registers(), Err(Rail::Vsh2));
+    }
+}
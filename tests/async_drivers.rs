@@ -0,0 +1,32 @@
+//! The async drivers have to send exactly the same bytes as their blocking versions,
+//! see `common/mod.rs`
+
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::{record_async_scenario, record_basic_scenario, AsyncDelay, Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+
+#[cfg(feature = "epd4in2")]
+#[test]
+fn epd4in2() {
+    use epd_waveshare::epd4in2;
+
+    let blocking = record_basic_scenario::<epd4in2::Epd4in2<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    let asynch =
+        record_async_scenario::<epd4in2::Epd4in2Async<Spi, Busy, Dc, Rst, AsyncDelay>>(true);
+    assert_eq!(asynch, blocking);
+}
+
+#[cfg(feature = "epd2in9_v2")]
+#[test]
+fn epd2in9_v2() {
+    use epd_waveshare::epd2in9_v2;
+
+    let blocking =
+        record_basic_scenario::<epd2in9_v2::Epd2in9<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    let asynch =
+        record_async_scenario::<epd2in9_v2::Epd2in9Async<Spi, Busy, Dc, Rst, AsyncDelay>>(false);
+    assert_eq!(asynch, blocking);
+}
@@ -0,0 +1,421 @@
+//! Clears every driver with each of its background colors and compares the bytes of
+//! the fills with the table below
+//!
+//! `set_background_color` has to reach every fill a driver synthesizes: the whole panel
+//! with `clear_frame` and the [FILL_WINDOW](common::FILL_WINDOW) with `clear_region`.
+//! Each row lists the background color, the command and fill byte of every plane
+//! written by `clear_frame` and those of `clear_region`, `None` for the drivers which
+//! can't clear windows. The bytes are the ones on the wire in the plane conventions of
+//! the controllers, see [PlaneConvention](epd_waveshare::color::PlaneConvention).
+//!
+//! Epd5in65f is missing, its `clear_frame` waits for the busy line to toggle.
+
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports))]
+
+mod common;
+
+use common::{record_background_fills, Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+
+macro_rules! fills {
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, [$($row:expr),* $(,)?]) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::{color::*, $module};
+
+            for (background, frame, window) in [$($row),*] {
+                assert_eq!(
+                    record_background_fills::<$module::$epd<Spi, Busy, Dc, Rst, NoopDelay>>(
+                        $idle, background
+                    ),
+                    (frame, window),
+                    "{:?}",
+                    background
+                );
+            }
+        }
+    };
+}
+
+fills!(
+    "epd13in3k",
+    epd13in3k,
+    epd13in3k,
+    Epd13in3k,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0x26, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+    ]
+);
+fills!(
+    "epd1in54",
+    epd1in54,
+    epd1in54,
+    Epd1in54,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00)], None),
+    ]
+);
+fills!(
+    "epd1in54_v2",
+    epd1in54_v2,
+    epd1in54_v2,
+    Epd1in54,
+    false,
+    [
+        (
+            Color::White,
+            vec![(0x24, 0xFF), (0x26, 0xFF)],
+            Some(vec![(0x24, 0xFF), (0x26, 0xFF)])
+        ),
+        (
+            Color::Black,
+            vec![(0x24, 0x00), (0x26, 0x00)],
+            Some(vec![(0x24, 0x00), (0x26, 0x00)])
+        ),
+    ]
+);
+fills!(
+    "epd1in54b",
+    epd1in54b,
+    epd1in54b,
+    Epd1in54b,
+    true,
+    [
+        (Color::White, vec![(0x10, 0xFF), (0x13, 0xFF)], None),
+        (Color::Black, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+    ]
+);
+fills!(
+    "epd1in54c",
+    epd1in54c,
+    epd1in54c,
+    Epd1in54c,
+    true,
+    [
+        (Color::White, vec![(0x10, 0xFF), (0x13, 0xFF)], None),
+        (Color::Black, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+    ]
+);
+fills!(
+    "epd2in13",
+    epd2in13_v2,
+    epd2in13_v2,
+    Epd2in13,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0x26, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in13b_v4",
+    epd2in13b_v4,
+    epd2in13b_v4,
+    Epd2in13b,
+    false,
+    [
+        (TriColor::White, vec![(0x24, 0xFF), (0x26, 0x00)], None),
+        (TriColor::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+        (TriColor::Chromatic, vec![(0x24, 0x00), (0x26, 0xFF)], None),
+    ]
+);
+fills!(
+    "epd2in13bc",
+    epd2in13bc,
+    epd2in13bc,
+    Epd2in13bc,
+    true,
+    [
+        (TriColor::White, vec![(0x10, 0xFF), (0x13, 0xFF)], None),
+        (TriColor::Black, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+        (TriColor::Chromatic, vec![(0x10, 0x00), (0x13, 0x00)], None),
+    ]
+);
+// the old frame is the inverted new one
+fills!(
+    "epd2in13d",
+    epd2in13d,
+    epd2in13d,
+    Epd2in13d,
+    true,
+    [
+        (
+            Color::White,
+            vec![(0x10, 0x00), (0x13, 0xFF)],
+            Some(vec![(0x10, 0x00), (0x13, 0xFF)])
+        ),
+        (
+            Color::Black,
+            vec![(0x10, 0xFF), (0x13, 0x00)],
+            Some(vec![(0x10, 0xFF), (0x13, 0x00)])
+        ),
+    ]
+);
+fills!(
+    "epd2in15g",
+    epd2in15g,
+    epd2in15g,
+    Epd2in15g,
+    true,
+    [
+        (QuadColor::White, vec![(0x10, 0x55)], None),
+        (QuadColor::Black, vec![(0x10, 0x00)], None),
+        (QuadColor::Yellow, vec![(0x10, 0xAA)], None),
+        (QuadColor::Red, vec![(0x10, 0xFF)], None),
+    ]
+);
+fills!(
+    "epd2in36g",
+    epd2in36g,
+    epd2in36g,
+    Epd2in36g,
+    true,
+    [
+        (QuadColor::White, vec![(0x10, 0x55)], None),
+        (QuadColor::Black, vec![(0x10, 0x00)], None),
+        (QuadColor::Yellow, vec![(0x10, 0xAA)], None),
+        (QuadColor::Red, vec![(0x10, 0xFF)], None),
+    ]
+);
+// fills the RAMs with the test pattern commands instead, see its unit tests
+fills!(
+    "epd2in66b",
+    epd2in66b,
+    epd2in66b,
+    Epd2in66b,
+    false,
+    [
+        (TriColor::White, vec![], None),
+        (TriColor::Black, vec![], None),
+        (TriColor::Chromatic, vec![], None),
+    ]
+);
+fills!(
+    "epd2in7_v2",
+    epd2in7_v2,
+    epd2in7_v2,
+    Epd2in7,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0x26, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in7b",
+    epd2in7b,
+    epd2in7b,
+    Epd2in7b,
+    true,
+    [
+        (Color::White, vec![(0x10, 0x00), (0x13, 0x00)], None),
+        (Color::Black, vec![(0x10, 0xFF), (0x13, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in9",
+    epd2in9,
+    epd2in9,
+    Epd2in9,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in9_v2",
+    epd2in9_v2,
+    epd2in9_v2,
+    Epd2in9,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0x26, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in9b_v3",
+    epd2in9b_v3,
+    epd2in9b_v3,
+    Epd2in9b,
+    false,
+    [
+        (TriColor::White, vec![(0x24, 0xFF), (0x26, 0x00)], None),
+        (TriColor::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+        (TriColor::Chromatic, vec![(0x24, 0x00), (0x26, 0xFF)], None),
+    ]
+);
+// clear_frame fills the chromatic plane, windows are cleared in the b/w mode
+// of the quick refreshes with the old and the new frame
+fills!(
+    "epd2in9bc",
+    epd2in9bc,
+    epd2in9bc,
+    Epd2in9bc,
+    true,
+    [
+        (
+            Color::White,
+            vec![(0x10, 0xFF), (0x13, 0xFF)],
+            Some(vec![(0x10, 0xFF), (0x13, 0xFF)])
+        ),
+        (
+            Color::Black,
+            vec![(0x10, 0x00), (0x13, 0xFF)],
+            Some(vec![(0x10, 0x00), (0x13, 0x00)])
+        ),
+    ]
+);
+// the old frame is the inverted new one
+fills!(
+    "epd2in9d",
+    epd2in9d,
+    epd2in9d,
+    Epd2in9d,
+    false,
+    [
+        (Color::White, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+        (Color::Black, vec![(0x10, 0xFF), (0x13, 0x00)], None),
+    ]
+);
+fills!(
+    "epd2in9g",
+    epd2in9g,
+    epd2in9g,
+    Epd2in9g,
+    true,
+    [
+        (QuadColor::White, vec![(0x10, 0x55)], None),
+        (QuadColor::Black, vec![(0x10, 0x00)], None),
+        (QuadColor::Yellow, vec![(0x10, 0xAA)], None),
+        (QuadColor::Red, vec![(0x10, 0xFF)], None),
+    ]
+);
+fills!(
+    "epd3in52",
+    epd3in52,
+    epd3in52,
+    Epd3in52,
+    true,
+    [
+        (Color::White, vec![(0x13, 0xFF)], None),
+        (Color::Black, vec![(0x13, 0x00)], None),
+    ]
+);
+fills!(
+    "epd3in7",
+    epd3in7,
+    epd3in7,
+    EPD3in7,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00)], None),
+    ]
+);
+fills!(
+    "epd4in2",
+    epd4in2,
+    epd4in2,
+    Epd4in2,
+    true,
+    [
+        (
+            Color::White,
+            vec![(0x10, 0xFF), (0x13, 0xFF)],
+            Some(vec![(0x10, 0xFF), (0x13, 0xFF)])
+        ),
+        (
+            Color::Black,
+            vec![(0x10, 0x00), (0x13, 0x00)],
+            Some(vec![(0x10, 0x00), (0x13, 0x00)])
+        ),
+    ]
+);
+fills!(
+    "epd5in79",
+    epd5in79,
+    epd5in79,
+    Epd5in79,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0xA4, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0xA4, 0x00)], None),
+    ]
+);
+fills!(
+    "epd5in83_v2",
+    epd5in83_v2,
+    epd5in83_v2,
+    Epd5in83,
+    true,
+    [
+        (Color::White, vec![(0x10, 0xFF), (0x13, 0xFF)], None),
+        (Color::Black, vec![(0x10, 0x00), (0x13, 0x00)], None),
+    ]
+);
+fills!(
+    "epd5in83b_v2",
+    epd5in83b_v2,
+    epd5in83b_v2,
+    Epd5in83,
+    true,
+    [
+        (Color::White, vec![(0x10, 0xFF), (0x13, 0x00)], None),
+        (Color::Black, vec![(0x10, 0x00), (0x13, 0x00)], None),
+    ]
+);
+// two pixels per byte, 0x3 is white
+fills!(
+    "epd7in5",
+    epd7in5,
+    epd7in5,
+    Epd7in5,
+    true,
+    [
+        (Color::White, vec![(0x10, 0x33)], None),
+        (Color::Black, vec![(0x10, 0x00)], None),
+    ]
+);
+fills!(
+    "epd7in5_hd",
+    epd7in5_hd,
+    epd7in5_hd,
+    Epd7in5,
+    false,
+    [
+        (Color::White, vec![(0x24, 0xFF), (0x26, 0xFF)], None),
+        (Color::Black, vec![(0x24, 0x00), (0x26, 0x00)], None),
+    ]
+);
+// the old frame is the inverted new one
+fills!(
+    "epd7in5_v2",
+    epd7in5_v2,
+    epd7in5_v2,
+    Epd7in5,
+    true,
+    [
+        (Color::White, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+        (Color::Black, vec![(0x10, 0xFF), (0x13, 0x00)], None),
+    ]
+);
+fills!(
+    "epd7in5b_v2",
+    epd7in5b_v2,
+    epd7in5b_v2,
+    Epd7in5,
+    true,
+    [
+        (TriColor::White, vec![(0x10, 0xFF), (0x13, 0x00)], None),
+        (TriColor::Black, vec![(0x10, 0x00), (0x13, 0x00)], None),
+        (TriColor::Chromatic, vec![(0x10, 0x00), (0x13, 0xFF)], None),
+    ]
+);
@@ -0,0 +1,205 @@
+//! Runs `update_frame` on every driver and compares the checksum of the driver with the
+//! one of the frame as the controller received it, see `epd_waveshare::checksum`
+//!
+//! Run with `--features checksum`, trailing commands must not spoil the checksum of the
+//! frame.
+#![cfg(feature = "checksum")]
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports, dead_code))]
+
+mod common;
+
+use common::{test_frame, Bus, Busy, Dc, Rst, Spi};
+use embedded_hal::spi::ErrorKind;
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::checksum::{crc16, CRC16_INIT};
+use epd_waveshare::{prelude::*, Error};
+
+/// A new driver after running `update` on it
+fn updated<EPD, F>(idle: bool, update: F) -> EPD
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+    F: FnOnce(&mut EPD, &mut Spi, &mut NoopDelay) -> Result<(), Error<ErrorKind>>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    update(&mut epd, &mut spi, &mut delay).unwrap();
+    epd
+}
+
+/// The frame sent as it is
+fn plain(frame: &[u8]) -> Vec<u8> {
+    frame.to_vec()
+}
+
+/// Every bit flipped, like the Epd2in7b sends both planes
+fn inverted(frame: &[u8]) -> Vec<u8> {
+    frame.iter().map(|byte| !byte).collect()
+}
+
+/// Two bits per pixel, like the Epd1in54b sends the black/white plane
+fn two_bits_per_pixel(frame: &[u8]) -> Vec<u8> {
+    frame
+        .iter()
+        .flat_map(|&byte| {
+            let wide = (0..8).fold(0u16, |wide, bit| {
+                wide | (u16::from(byte >> bit & 1) * 0b11) << (2 * bit)
+            });
+            wide.to_be_bytes()
+        })
+        .collect()
+}
+
+/// Four bits per pixel, like the Epd7in5 sends the frame
+fn four_bits_per_pixel(frame: &[u8]) -> Vec<u8> {
+    frame
+        .iter()
+        .flat_map(|&byte| {
+            (0..4).map(move |pair| {
+                let pixel = |bit: u8| if byte & (0x80 >> bit) == 0 { 0x0 } else { 0x3 };
+                pixel(2 * pair) << 4 | pixel(2 * pair + 1)
+            })
+        })
+        .collect()
+}
+
+macro_rules! checksum {
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr) => {
+        checksum!($feature, $name, $module, $epd, $idle, rows 1, sent plain);
+    };
+    // frames of `rows` times the rows of a black/white one, for more bits per pixel
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, rows $rows:expr) => {
+        checksum!($feature, $name, $module, $epd, $idle, rows $rows, sent plain);
+    };
+    // `sent` gives the bytes the controller receives for a frame
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, sent $sent:expr) => {
+        checksum!($feature, $name, $module, $epd, $idle, rows 1, sent $sent);
+    };
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, rows $rows:expr, sent $sent:expr) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module::{$epd, HEIGHT, WIDTH};
+
+            let frame = test_frame(WIDTH, HEIGHT * $rows);
+            let epd = updated::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                epd.update_frame(spi, &frame, delay)
+            });
+            assert_eq!(epd.last_transfer_checksum(), crc16(&$sent(&frame)));
+        }
+    };
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, color) => {
+        checksum!($feature, $name, $module, $epd, $idle, color plain, plain);
+    };
+    // `color black, chromatic` give the bytes the controller receives for each plane
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, color $black:expr, $chromatic:expr) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module::{$epd, HEIGHT, WIDTH};
+
+            // the black/white frame, the chromatic plane is filled with the background
+            let black = test_frame(WIDTH, HEIGHT);
+            let epd = updated::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                epd.update_frame(spi, &black, delay)
+            });
+            let planes = [Plane::BlackWhite, Plane::Chromatic].map(|plane| epd.plane_checksum(plane));
+            assert_eq!(planes[0], crc16(&$black(&black)));
+            let last = epd.last_transfer_checksum();
+            assert!(last != CRC16_INIT && planes.contains(&last), "{last:04X}");
+
+            let chromatic: Vec<u8> = black.iter().map(|byte| byte.rotate_left(3)).collect();
+            let epd = updated::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                epd.update_color_frame(spi, delay, &black, &chromatic)
+            });
+            assert_eq!(epd.plane_checksum(Plane::BlackWhite), crc16(&$black(&black)));
+            assert_eq!(epd.plane_checksum(Plane::Chromatic), crc16(&$chromatic(&chromatic)));
+            assert_eq!(epd.last_transfer_checksum(), crc16(&$chromatic(&chromatic)));
+        }
+    };
+}
+
+checksum!("epd13in3k", epd13in3k, epd13in3k, Epd13in3k, false);
+checksum!("epd1in54", epd1in54, epd1in54, Epd1in54, false);
+checksum!("epd1in54_v2", epd1in54_v2, epd1in54_v2, Epd1in54, false);
+checksum!("epd1in54b", epd1in54b, epd1in54b, Epd1in54b, true, color two_bits_per_pixel, plain);
+checksum!("epd1in54c", epd1in54c, epd1in54c, Epd1in54c, true, color);
+checksum!("epd2in13", epd2in13_v2, epd2in13_v2, Epd2in13, false);
+checksum!(
+    "epd2in13b_v4",
+    epd2in13b_v4,
+    epd2in13b_v4,
+    Epd2in13b,
+    false,
+    color
+);
+checksum!(
+    "epd2in13bc",
+    epd2in13bc,
+    epd2in13bc,
+    Epd2in13bc,
+    true,
+    color
+);
+checksum!("epd2in13d", epd2in13d, epd2in13d, Epd2in13d, true);
+checksum!("epd2in15g", epd2in15g, epd2in15g, Epd2in15g, true, rows 2);
+checksum!("epd2in36g", epd2in36g, epd2in36g, Epd2in36g, true, rows 2);
+checksum!("epd2in66b", epd2in66b, epd2in66b, Epd2in66b, false, color);
+checksum!("epd2in7_v2", epd2in7_v2, epd2in7_v2, Epd2in7, false);
+checksum!("epd2in7b", epd2in7b, epd2in7b, Epd2in7b, true, color inverted, inverted);
+checksum!("epd2in9", epd2in9, epd2in9, Epd2in9, false);
+checksum!("epd2in9_v2", epd2in9_v2, epd2in9_v2, Epd2in9, false);
+checksum!(
+    "epd2in9b_v3",
+    epd2in9b_v3,
+    epd2in9b_v3,
+    Epd2in9b,
+    false,
+    color
+);
+checksum!("epd2in9bc", epd2in9bc, epd2in9bc, Epd2in9bc, true, color);
+checksum!("epd2in9d", epd2in9d, epd2in9d, Epd2in9d, false);
+checksum!("epd2in9g", epd2in9g, epd2in9g, Epd2in9g, true, rows 2);
+checksum!("epd3in52", epd3in52, epd3in52, Epd3in52, true);
+checksum!("epd3in7", epd3in7, epd3in7, EPD3in7, false);
+checksum!("epd4in2", epd4in2, epd4in2, Epd4in2, true);
+checksum!("epd5in65f", epd5in65f, epd5in65f, Epd5in65f, true, rows 4);
+checksum!("epd5in79", epd5in79, epd5in79, Epd5in79, false);
+checksum!("epd5in83_v2", epd5in83_v2, epd5in83_v2, Epd5in83, true);
+checksum!(
+    "epd5in83b_v2",
+    epd5in83b_v2,
+    epd5in83b_v2,
+    Epd5in83,
+    true,
+    color
+);
+checksum!("epd7in5", epd7in5, epd7in5, Epd7in5, true, sent four_bits_per_pixel);
+checksum!("epd7in5_hd", epd7in5_hd, epd7in5_hd, Epd7in5, false);
+checksum!("epd7in5_v2", epd7in5_v2, epd7in5_v2, Epd7in5, true);
+
+// `update_frame` of the Epd7in5 (B) V2 takes both planes in one buffer, like the
+// `update_color_frame` of the others
+#[cfg(feature = "epd7in5b_v2")]
+#[test]
+fn epd7in5b_v2() {
+    use epd_waveshare::epd7in5b_v2::{Epd7in5, HEIGHT, WIDTH};
+
+    let frame = test_frame(WIDTH, HEIGHT * 2);
+    let (black, chromatic) = frame.split_at(frame.len() / 2);
+    let epd = updated::<Epd7in5<Spi, Busy, Dc, Rst, NoopDelay>, _>(true, |epd, spi, delay| {
+        epd.update_frame(spi, &frame, delay)
+    });
+    assert_eq!(epd.plane_checksum(Plane::BlackWhite), crc16(black));
+    assert_eq!(epd.plane_checksum(Plane::Chromatic), crc16(chromatic));
+    assert_eq!(epd.last_transfer_checksum(), crc16(chromatic));
+
+    let epd = updated::<Epd7in5<Spi, Busy, Dc, Rst, NoopDelay>, _>(true, |epd, spi, delay| {
+        epd.update_color_frame(spi, delay, black, chromatic)
+    });
+    assert_eq!(epd.plane_checksum(Plane::BlackWhite), crc16(black));
+    assert_eq!(epd.plane_checksum(Plane::Chromatic), crc16(chromatic));
+    assert_eq!(epd.last_transfer_checksum(), crc16(chromatic));
+}
@@ -0,0 +1,708 @@
+//! Harness for the golden spi trace tests
+//!
+//! A scripted scenario is run against a recording spi device, and the resulting
+//! command/data stream is compared with a checked in trace in `tests/golden/`.
+//! Every command is written as one line together with the length and a FNV-1a hash
+//! of the data following it, so a trace stays short and readable in a diff.
+//!
+//! Run the tests with `UPDATE_GOLDEN=1` to (re)write the traces after a deliberate
+//! change of the sequences.
+
+// not every test crate uses everything, e.g. `features.rs` only the types
+#![allow(dead_code)]
+
+use std::{
+    convert::Infallible,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use embedded_hal::{
+    digital::{ErrorType as PinErrorType, InputPin, OutputPin},
+    spi::{ErrorKind, ErrorType as SpiErrorType, Operation, SpiDevice},
+};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::prelude::{QuickRefresh, RefreshLut, WaveshareDisplay};
+use epd_waveshare::Error;
+#[cfg(feature = "async")]
+use {
+    embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice as AsyncSpiDevice},
+    epd_waveshare::prelude::AsyncWaveshareDisplay,
+    std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    },
+};
+
+/// Delay of the async drivers which returns right away
+#[cfg(feature = "async")]
+pub struct AsyncDelay;
+
+#[cfg(feature = "async")]
+impl DelayNs for AsyncDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Polls `future` until it completes, nothing in the harness ever returns pending
+#[cfg(feature = "async")]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    dc_high: bool,
+    /// command (`None` for data sent before any command) and the data following it
+    transfers: Vec<(Option<u8>, Vec<u8>)>,
+    /// section name and the number of transfers before it started
+    sections: Vec<(&'static str, usize)>,
+    /// number of spi writes, a command or data transfer may take several
+    writes: usize,
+}
+
+/// Command and fill byte of every fill, see [Bus::fills]
+pub type Fills = Vec<(u8, u8)>;
+
+/// Shared state of the recording spi device and the DC pin
+#[derive(Clone, Default)]
+pub struct Bus(Arc<Mutex<State>>);
+
+impl Bus {
+    pub fn spi(&self) -> Spi {
+        Spi(self.clone())
+    }
+
+    pub fn dc(&self) -> Dc {
+        Dc(self.clone())
+    }
+
+    /// Starts a new named section of the trace
+    pub fn section(&self, name: &'static str) {
+        let mut state = self.0.lock().unwrap();
+        let start = state.transfers.len();
+        state.sections.push((name, start));
+    }
+
+    /// Number of spi writes so far
+    pub fn writes(&self) -> usize {
+        self.0.lock().unwrap().writes
+    }
+
+    /// Number of command and data bytes sent so far
+    pub fn bytes(&self) -> usize {
+        let state = self.0.lock().unwrap();
+        state
+            .transfers
+            .iter()
+            .map(|(command, data)| usize::from(command.is_some()) + data.len())
+            .sum()
+    }
+
+    /// Command and byte of every transfer of at least `min_len` equal data bytes so far
+    pub fn fills(&self, min_len: usize) -> Fills {
+        let state = self.0.lock().unwrap();
+        state
+            .transfers
+            .iter()
+            .filter_map(|(command, data)| match (command, data.first()) {
+                (Some(command), Some(&byte))
+                    if data.len() >= min_len && data.iter().all(|&b| b == byte) =>
+                {
+                    Some((*command, byte))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The compact trace of everything recorded so far
+    pub fn trace(&self) -> String {
+        let state = self.0.lock().unwrap();
+        let mut trace = String::new();
+        for (index, (command, data)) in state.transfers.iter().enumerate() {
+            for (name, _) in state.sections.iter().filter(|(_, start)| *start == index) {
+                writeln!(trace, "# {}", name).unwrap();
+            }
+            match command {
+                Some(command) => write!(trace, "{:02X}", command).unwrap(),
+                None => trace.push_str("--"),
+            }
+            if !data.is_empty() {
+                write!(trace, " len={} fnv={:08x}", data.len(), fnv1a(data)).unwrap();
+            }
+            trace.push('\n');
+        }
+        for (name, _) in state
+            .sections
+            .iter()
+            .filter(|(_, start)| *start == state.transfers.len())
+        {
+            writeln!(trace, "# {}", name).unwrap();
+        }
+        trace
+    }
+}
+
+/// 32 bit FNV-1a
+fn fnv1a(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Recording spi device
+pub struct Spi(Bus);
+
+impl SpiErrorType for Spi {
+    type Error = ErrorKind;
+}
+
+impl SpiDevice for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        let mut state = self.0 .0.lock().unwrap();
+        for op in operations {
+            if let Operation::Write(bytes) = op {
+                state.writes += 1;
+                if state.dc_high {
+                    match state.transfers.last_mut() {
+                        Some((_, data)) => data.extend_from_slice(bytes),
+                        None => state.transfers.push((None, bytes.to_vec())),
+                    }
+                } else {
+                    for byte in bytes.iter() {
+                        state.transfers.push((Some(*byte), Vec::new()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncSpiDevice for Spi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        SpiDevice::transaction(self, operations)
+    }
+}
+
+/// Data/Command pin telling the [`Bus`] how to classify writes
+pub struct Dc(Bus);
+
+impl PinErrorType for Dc {
+    type Error = Infallible;
+}
+
+impl OutputPin for Dc {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0 .0.lock().unwrap().dc_high = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0 .0.lock().unwrap().dc_high = true;
+        Ok(())
+    }
+}
+
+/// Busy pin which always reads the idle level of the display
+pub struct Busy(pub bool);
+
+impl PinErrorType for Busy {
+    type Error = Infallible;
+}
+
+impl InputPin for Busy {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.0)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.0)
+    }
+}
+
+/// The busy pin already shows the idle level, a wait for the other one would hang
+#[cfg(feature = "async")]
+impl Wait for Busy {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        assert!(self.0, "waiting for a level the busy pin never reaches");
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        assert!(!self.0, "waiting for a level the busy pin never reaches");
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        panic!("the busy pin never changes");
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        panic!("the busy pin never changes");
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        panic!("the busy pin never changes");
+    }
+}
+
+/// Reset pin
+pub struct Rst;
+
+impl PinErrorType for Rst {
+    type Error = Infallible;
+}
+
+impl OutputPin for Rst {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Runs new → clear → update → display → sleep and returns the trace
+///
+/// `idle` is the level of the busy pin while the display is idle.
+pub fn record_scenario<EPD>(idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    record_scenario_on::<EPD>(&Bus::default(), idle)
+}
+
+/// [record_scenario] on `bus`, e.g. to count its writes afterwards
+pub fn record_scenario_on<EPD>(bus: &Bus, idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+
+    bus.section("new");
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+
+    bus.section("clear_frame");
+    epd.clear_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("update_frame");
+    let frame = test_frame(EPD::WIDTH, EPD::HEIGHT);
+    epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+
+    bus.section("display_frame");
+    epd.display_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("sleep");
+    epd.sleep(&mut spi, &mut delay).unwrap();
+
+    bus.trace()
+}
+
+/// Runs new → update → display → sleep → wake_up, the part of [record_scenario] the
+/// async drivers have as well, and returns the trace
+pub fn record_basic_scenario<EPD>(idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+
+    bus.section("new");
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+
+    bus.section("update_frame");
+    epd.update_frame(&mut spi, &test_frame(EPD::WIDTH, EPD::HEIGHT), &mut delay)
+        .unwrap();
+
+    bus.section("display_frame");
+    epd.display_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("sleep");
+    epd.sleep(&mut spi, &mut delay).unwrap();
+
+    bus.section("wake_up");
+    epd.wake_up(&mut spi, &mut delay).unwrap();
+
+    bus.trace()
+}
+
+/// [record_basic_scenario] on an async driver
+#[cfg(feature = "async")]
+pub fn record_async_scenario<EPD>(idle: bool) -> String
+where
+    EPD: AsyncWaveshareDisplay<Spi, Busy, Dc, Rst, AsyncDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = AsyncDelay;
+
+    block_on(async {
+        bus.section("new");
+        let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay)
+            .await
+            .unwrap();
+
+        bus.section("update_frame");
+        epd.update_frame(&mut spi, &test_frame(EPD::WIDTH, EPD::HEIGHT), &mut delay)
+            .await
+            .unwrap();
+
+        bus.section("display_frame");
+        epd.display_frame(&mut spi, &mut delay).await.unwrap();
+
+        bus.section("sleep");
+        epd.sleep(&mut spi, &mut delay).await.unwrap();
+
+        bus.section("wake_up");
+        epd.wake_up(&mut spi, &mut delay).await.unwrap();
+    });
+
+    bus.trace()
+}
+
+/// A b/w frame without any regularity the controllers might hide
+pub fn test_frame(width: u32, height: u32) -> Vec<u8> {
+    (0..width.div_ceil(8) as usize * height as usize)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect()
+}
+
+/// Runs new → clear → update with `background` as background color and returns the trace
+///
+/// Pins down how the drivers encode the background color in the planes.
+pub fn record_background<EPD>(idle: bool, background: EPD::DisplayColor) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    epd.set_background_color(background);
+    bus.0.lock().unwrap().transfers.clear();
+
+    bus.section("clear_frame");
+    epd.clear_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("update_frame");
+    let frame = vec![0xA5; EPD::WIDTH.div_ceil(8) as usize * EPD::HEIGHT as usize];
+    epd.update_frame(&mut spi, &frame, &mut delay).unwrap();
+
+    bus.trace()
+}
+
+/// Window cleared by [record_background_fills], 2 bytes wide
+pub const FILL_WINDOW: (u32, u32, u32, u32) = (8, 8, 16, 8);
+
+/// Runs `clear_frame` and `clear_region` of the [FILL_WINDOW] with `background` as
+/// background color and returns the [fills](Bus::fills) of both
+///
+/// The fills of the window are `None` for drivers which can't clear windows, they have
+/// to return `Error::Unsupported` without sending anything.
+pub fn record_background_fills<EPD>(
+    idle: bool,
+    background: EPD::DisplayColor,
+) -> (Fills, Option<Fills>)
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let (x, y, width, height) = FILL_WINDOW;
+    let min_len = (width / 8 * height) as usize;
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    epd.set_background_color(background);
+    bus.0.lock().unwrap().transfers.clear();
+
+    epd.clear_frame(&mut spi, &mut delay).unwrap();
+    let frame = bus.fills(min_len);
+    bus.0.lock().unwrap().transfers.clear();
+
+    let window = match epd.clear_region(&mut spi, &mut delay, x, y, width, height) {
+        Ok(()) => Some(bus.fills(min_len)),
+        Err(Error::Unsupported) => {
+            assert_eq!(bus.trace(), "");
+            None
+        }
+        Err(error) => panic!("clear_region: {:?}", error),
+    };
+    (frame, window)
+}
+
+/// Runs `update` on a new driver and returns the trace of it, e.g. to compare two ways
+/// of sending the same frame
+pub fn record_update<EPD, F>(idle: bool, update: F) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+    F: FnOnce(&mut EPD, &mut Spi, &mut NoopDelay) -> Result<(), Error<ErrorKind>>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+
+    update(&mut epd, &mut spi, &mut delay).unwrap();
+    bus.trace()
+}
+
+/// `frame` split into parts at awkward places for the vectored updates: in the middle
+/// of the rows of `stride` bytes, rows shifted by a few bytes, with empty parts and
+/// into single bytes
+pub fn awkward_splits(frame: &[u8], stride: usize) -> Vec<Vec<&[u8]>> {
+    let (half, middle) = (stride / 2, frame.len() / 2 + 1);
+    let shifted = std::iter::once(&frame[..3]).chain(frame[3..].chunks(stride));
+    vec![
+        vec![&frame[..half], &frame[half..middle], &frame[middle..]],
+        shifted.collect(),
+        vec![&[], &frame[..middle], &[], &frame[middle..], &[]],
+        frame.chunks(1).collect(),
+    ]
+}
+
+/// Runs new → update → display → quick refresh → quick `display_frame` → full refresh
+/// and returns the trace
+///
+/// Pins down when the drivers switch between the LUTs of quick and full refreshes.
+pub fn record_quick_scenario<EPD>(idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>
+        + QuickRefresh<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+    let len = EPD::WIDTH.div_ceil(8) as usize * EPD::HEIGHT as usize;
+    let first = vec![0xF0; len];
+    let second = vec![0x0F; len];
+
+    bus.section("update_and_display_frame");
+    epd.update_and_display_frame(&mut spi, &first, &mut delay)
+        .unwrap();
+
+    bus.section("update_old_frame");
+    epd.update_old_frame(&mut spi, &first, &mut delay).unwrap();
+
+    bus.section("update_and_display_new_frame");
+    epd.update_and_display_new_frame(&mut spi, &second, &mut delay)
+        .unwrap();
+
+    bus.section("set_lut quick");
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+        .unwrap();
+
+    bus.section("update_and_display_frame quick");
+    epd.update_and_display_frame(&mut spi, &first, &mut delay)
+        .unwrap();
+
+    bus.section("set_lut full");
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Full))
+        .unwrap();
+
+    bus.section("update_and_display_frame full");
+    epd.update_and_display_frame(&mut spi, &second, &mut delay)
+        .unwrap();
+
+    bus.trace()
+}
+
+/// Runs new → partial old frame → partial new frame → display → partial clear and
+/// returns the trace
+///
+/// Pins down the partial window parameters and how the drivers enter and leave the
+/// partial mode around the frames.
+pub fn record_partial_scenario<EPD>(idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>
+        + QuickRefresh<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+    // a window off the byte grid, ending above 8 bits of y on the taller panels
+    let (x, y, width, height) = (13, 200, 40, 80);
+    let len = (width as usize).div_ceil(8) * height as usize;
+    let old = vec![0xF0; len];
+    let new = vec![0x0F; len];
+
+    bus.section("update_partial_old_frame");
+    epd.update_partial_old_frame(&mut spi, &mut delay, &old, x, y, width, height)
+        .unwrap();
+
+    bus.section("update_partial_new_frame");
+    epd.update_partial_new_frame(&mut spi, &mut delay, &new, x, y, width, height)
+        .unwrap();
+
+    bus.section("display_new_frame");
+    epd.display_new_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("clear_partial_frame");
+    epd.clear_partial_frame(&mut spi, &mut delay, x, y, width, height)
+        .unwrap();
+
+    bus.trace()
+}
+
+/// Runs new → quick `update_and_display_frame` → full `display_frame_with` and returns
+/// the trace
+///
+/// For drivers switching the LUT without [QuickRefresh], pins down what is sent for
+/// each of both LUTs.
+pub fn record_lut_scenario<EPD>(idle: bool) -> String
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+    let frame = vec![0xF0; EPD::WIDTH.div_ceil(8) as usize * EPD::HEIGHT as usize];
+
+    bus.section("set_lut quick");
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+        .unwrap();
+
+    bus.section("update_and_display_frame quick");
+    epd.update_and_display_frame(&mut spi, &frame, &mut delay)
+        .unwrap();
+
+    bus.section("display_frame_with full");
+    epd.display_frame_with(&mut spi, &mut delay, RefreshLut::Full)
+        .unwrap();
+
+    bus.trace()
+}
+
+/// Windows the drivers have to refuse on a panel of `width` x `height` pixels, e.g.
+/// coordinates wrapped around from negative values or sizes overflowing the end
+pub fn invalid_windows(width: u32, height: u32) -> Vec<(u32, u32, u32, u32)> {
+    vec![
+        (u32::MAX, 0, 8, 8),
+        (0, u32::MAX, 8, 8),
+        (u32::MAX - 3, u32::MAX - 3, 8, 8),
+        (0, 0, u32::MAX, 1),
+        (0, 0, 8, u32::MAX),
+        (8, 8, u32::MAX - 4, u32::MAX - 4),
+        (0, 0, 0, 8),
+        (0, 0, 8, 0),
+        (width, 0, 8, 1),
+        (0, height, 8, 1),
+        (0, 0, width + 8, 1),
+        (0, 0, 8, height + 1),
+    ]
+}
+
+/// Every partial update of the driver refuses the [invalid_windows] with
+/// `Error::InvalidWindow` before sending anything
+pub fn assert_rejects_windows<EPD>(idle: bool)
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+    let buffer = [0xFF; 16];
+
+    for (x, y, width, height) in invalid_windows(EPD::WIDTH, EPD::HEIGHT) {
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut delay, &buffer, x, y, width, height),
+            Err(Error::InvalidWindow),
+            "update_partial_frame({}, {}, {}, {})",
+            x,
+            y,
+            width,
+            height
+        );
+    }
+    assert_eq!(bus.trace(), "");
+}
+
+/// Like [assert_rejects_windows] for the partial methods of [QuickRefresh]
+pub fn assert_quick_rejects_windows<EPD>(idle: bool)
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>
+        + QuickRefresh<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = EPD::new(&mut spi, Busy(idle), bus.dc(), Rst, &mut delay, None).unwrap();
+    bus.0.lock().unwrap().transfers.clear();
+    let buffer = [0xFF; 16];
+
+    for (x, y, width, height) in invalid_windows(EPD::WIDTH, EPD::HEIGHT) {
+        let window = format!("({}, {}, {}, {})", x, y, width, height);
+        assert_eq!(
+            epd.update_partial_old_frame(&mut spi, &mut delay, &buffer, x, y, width, height),
+            Err(Error::InvalidWindow),
+            "update_partial_old_frame{}",
+            window
+        );
+        assert_eq!(
+            epd.update_partial_new_frame(&mut spi, &mut delay, &buffer, x, y, width, height),
+            Err(Error::InvalidWindow),
+            "update_partial_new_frame{}",
+            window
+        );
+        assert_eq!(
+            epd.clear_partial_frame(&mut spi, &mut delay, x, y, width, height),
+            Err(Error::InvalidWindow),
+            "clear_partial_frame{}",
+            window
+        );
+    }
+    assert_eq!(bus.trace(), "");
+}
+
+/// Compares `trace` with `tests/golden/<name>.trace`, or writes it with `UPDATE_GOLDEN` set
+pub fn assert_golden(name: &str, trace: &str) {
+    assert_golden_file(&format!("{}.trace", name), trace);
+}
+
+/// Compares `content` with `tests/golden/<file>`, or writes it with `UPDATE_GOLDEN` set
+pub fn assert_golden_file(file: &str, content: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", file]
+        .iter()
+        .collect();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, content).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing {}, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        golden == content,
+        "{} changed, run with UPDATE_GOLDEN=1 if this is deliberate\n--- golden\n{}\n--- actual\n{}",
+        file,
+        golden,
+        content
+    );
+}
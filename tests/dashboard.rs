@@ -0,0 +1,140 @@
+//! A weather station style dashboard on the 2.9" B/C, see `examples/dashboard.rs`
+//!
+//! A header in red and a black body are refreshed in three colors, the clock is
+//! refreshed quickly once a minute in a window of the black plane, and the policy
+//! forces a full refresh after 10 quick ones. The panel is powered off in between
+//! the refreshes and put to sleep at the end.
+
+#![cfg(all(feature = "epd2in9bc", feature = "graphics"))]
+
+mod common;
+
+use common::{assert_golden, Bus, Busy, Rst};
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, ascii::FONT_6X10, MonoTextStyle},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::{
+    color::Color,
+    epd2in9bc::{Display2in9bc, Epd2in9bc},
+    graphics::VarDisplay,
+    policy::{RefreshHint, RefreshKind, RefreshPolicy},
+    prelude::*,
+};
+
+/// Window of the clock, x and width are multiples of 8
+const CLOCK: Rectangle = Rectangle::new(Point::new(16, 120), Size::new(96, 32));
+
+fn draw_clock<D: DrawTarget<Color = Color>>(target: &mut D, origin: Point, minute: u32) {
+    let style = MonoTextStyle::new(&FONT_10X20, Color::Black);
+    let time = format!("12:{:02}", minute);
+    let _ =
+        Text::with_baseline(&time, origin + Point::new(8, 6), style, Baseline::Top).draw(target);
+}
+
+/// Both planes of the whole layout, the header is drawn on the chromatic one
+fn render(minute: u32) -> (Display2in9bc, Display2in9bc) {
+    let mut bw = Display2in9bc::default();
+    let mut chromatic = Display2in9bc::default();
+    bw.clear(Color::White).unwrap();
+    chromatic.clear(Color::White).unwrap();
+
+    let _ = Rectangle::new(Point::zero(), Size::new(128, 32))
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(&mut chromatic);
+    let header = MonoTextStyle::new(&FONT_10X20, Color::White);
+    let _ = Text::with_baseline("Weather", Point::new(8, 6), header, Baseline::Top)
+        .draw(&mut chromatic);
+
+    let body = MonoTextStyle::new(&FONT_6X10, Color::Black);
+    let _ =
+        Text::with_baseline("21 C  sunny", Point::new(8, 48), body, Baseline::Top).draw(&mut bw);
+    let _ =
+        Text::with_baseline("48 %  1013 hPa", Point::new(8, 64), body, Baseline::Top).draw(&mut bw);
+    draw_clock(&mut bw, CLOCK.top_left, minute);
+
+    (bw, chromatic)
+}
+
+/// The clock alone, as buffer of the window
+fn render_clock(minute: u32) -> Vec<u8> {
+    let mut buffer = vec![0; (CLOCK.size.width / 8 * CLOCK.size.height) as usize];
+    let mut window =
+        VarDisplay::<Color>::new(CLOCK.size.width, CLOCK.size.height, &mut buffer, false).unwrap();
+    window.clear(Color::White).unwrap();
+    draw_clock(&mut window, Point::zero(), minute);
+    window.buffer().to_vec()
+}
+
+#[test]
+fn dashboard() {
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+
+    bus.section("new");
+    let mut epd = Epd2in9bc::new(&mut spi, Busy(true), bus.dc(), Rst, &mut delay, None).unwrap();
+    epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))
+        .unwrap();
+    let mut policy = RefreshPolicy::new().with_max_quick_refreshes(10);
+    let (x, y) = (CLOCK.top_left.x as u32, CLOCK.top_left.y as u32);
+    let (width, height) = (CLOCK.size.width, CLOCK.size.height);
+
+    let mut kinds = Vec::new();
+    for minute in 0..=11 {
+        let kind = policy.next_refresh(RefreshHint::new(u64::from(minute) * 60));
+        kinds.push(kind);
+        match kind {
+            RefreshKind::Full => {
+                bus.section("full refresh");
+                let (bw, chromatic) = render(minute);
+                epd.update_color_frame(&mut spi, &mut delay, bw.buffer(), chromatic.buffer())
+                    .unwrap();
+                epd.display_frame(&mut spi, &mut delay).unwrap();
+            }
+            RefreshKind::Quick => {
+                bus.section("clock");
+                epd.update_partial_old_frame(
+                    &mut spi,
+                    &mut delay,
+                    &render_clock(minute - 1),
+                    x,
+                    y,
+                    width,
+                    height,
+                )
+                .unwrap();
+                epd.update_partial_new_frame(
+                    &mut spi,
+                    &mut delay,
+                    &render_clock(minute),
+                    x,
+                    y,
+                    width,
+                    height,
+                )
+                .unwrap();
+                epd.display_partial_frame(&mut spi, &mut delay, x, y, width, height)
+                    .unwrap();
+            }
+        }
+        bus.section("power_off");
+        epd.power_off(&mut spi, &mut delay).unwrap();
+    }
+
+    bus.section("sleep");
+    epd.sleep(&mut spi, &mut delay).unwrap();
+
+    let mut expected = vec![RefreshKind::Full];
+    expected.extend([RefreshKind::Quick; 10]);
+    expected.push(RefreshKind::Full);
+    assert_eq!(kinds, expected);
+
+    let counters = epd.refresh_counters();
+    assert_eq!((counters.full, counters.partial), (2, 10));
+
+    assert_golden("dashboard", &bus.trace());
+}
@@ -0,0 +1,79 @@
+//! One test per panel feature, each only compiled with its feature
+//!
+//! Check that a panel builds on its own with e.g.
+//! `cargo test --no-default-features --features epd4in2,graphics --test features`.
+
+mod common;
+
+use common::{Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::panels::{PanelInfo, SUPPORTED_PANELS};
+use epd_waveshare::prelude::WaveshareDisplay;
+
+/// The associated constants of the driver match the ones of its module
+#[allow(dead_code)]
+fn assert_driver<EPD>(name: &str, width: u32, height: u32, capabilities: PanelInfo)
+where
+    EPD: WaveshareDisplay<Spi, Busy, Dc, Rst, NoopDelay>,
+{
+    assert_eq!(capabilities.name, name);
+    assert_eq!((capabilities.width, capabilities.height), (width, height));
+    assert!(SUPPORTED_PANELS.contains(&capabilities));
+    assert_eq!(EPD::WIDTH, width);
+    assert_eq!(EPD::HEIGHT, height);
+    assert!(EPD::BUFFER_LEN >= epd_waveshare::buffer_len(width as usize, height as usize));
+}
+
+macro_rules! panel {
+    ($feature:literal, $module:ident, $epd:ident, $display:ident) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $module() {
+            use epd_waveshare::$module::*;
+
+            assert_driver::<$epd<Spi, Busy, Dc, Rst, NoopDelay>>(
+                stringify!($module),
+                WIDTH,
+                HEIGHT,
+                CAPABILITIES,
+            );
+            #[cfg(feature = "graphics")]
+            assert!(
+                $display::default().buffer().len()
+                    >= $epd::<Spi, Busy, Dc, Rst, NoopDelay>::BUFFER_LEN
+            );
+        }
+    };
+}
+
+panel!("epd13in3k", epd13in3k, Epd13in3k, Display13in3k);
+panel!("epd1in54", epd1in54, Epd1in54, Display1in54);
+panel!("epd1in54_v2", epd1in54_v2, Epd1in54, Display1in54);
+panel!("epd1in54b", epd1in54b, Epd1in54b, Display1in54b);
+panel!("epd1in54c", epd1in54c, Epd1in54c, Display1in54c);
+panel!("epd2in13", epd2in13_v2, Epd2in13, Display2in13);
+panel!("epd2in13b_v4", epd2in13b_v4, Epd2in13b, Display2in13b);
+panel!("epd2in13bc", epd2in13bc, Epd2in13bc, Display2in13bc);
+panel!("epd2in13d", epd2in13d, Epd2in13d, Display2in13d);
+panel!("epd2in15g", epd2in15g, Epd2in15g, Display2in15g);
+panel!("epd2in36g", epd2in36g, Epd2in36g, Display2in36g);
+panel!("epd2in66b", epd2in66b, Epd2in66b, Display2in66b);
+panel!("epd2in7_v2", epd2in7_v2, Epd2in7, Display2in7_v2);
+panel!("epd2in7b", epd2in7b, Epd2in7b, Display2in7b);
+panel!("epd2in9", epd2in9, Epd2in9, Display2in9);
+panel!("epd2in9_v2", epd2in9_v2, Epd2in9, Display2in9);
+panel!("epd2in9b_v3", epd2in9b_v3, Epd2in9b, Display2in9b_v3);
+panel!("epd2in9bc", epd2in9bc, Epd2in9bc, Display2in9bc);
+panel!("epd2in9d", epd2in9d, Epd2in9d, Display2in9d);
+panel!("epd2in9g", epd2in9g, Epd2in9g, Display2in9g);
+panel!("epd3in52", epd3in52, Epd3in52, Display3in52);
+panel!("epd3in7", epd3in7, EPD3in7, Display3in7);
+panel!("epd4in2", epd4in2, Epd4in2, Display4in2);
+panel!("epd5in65f", epd5in65f, Epd5in65f, Display5in65f);
+panel!("epd5in79", epd5in79, Epd5in79, Display5in79);
+panel!("epd5in83_v2", epd5in83_v2, Epd5in83, Display5in83);
+panel!("epd5in83b_v2", epd5in83b_v2, Epd5in83, Display5in83);
+panel!("epd7in5", epd7in5, Epd7in5, Display7in5);
+panel!("epd7in5_hd", epd7in5_hd, Epd7in5, Display7in5);
+panel!("epd7in5_v2", epd7in5_v2, Epd7in5, Display7in5);
+panel!("epd7in5b_v2", epd7in5b_v2, Epd7in5, Display7in5b);
@@ -0,0 +1,447 @@
+//! Replays a fixed scenario on the drivers and compares the spi traffic with the
+//! traces in `tests/golden/`, see `common/mod.rs`
+
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports))]
+
+mod common;
+
+use common::{
+    assert_golden, record_background, record_lut_scenario, record_partial_scenario,
+    record_quick_scenario, record_scenario, Bus, Busy, Dc, Rst, Spi,
+};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+
+#[cfg(feature = "epd4in2")]
+#[test]
+fn epd4in2() {
+    use epd_waveshare::epd4in2;
+
+    let trace = record_scenario::<epd4in2::Epd4in2<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd4in2", &trace);
+}
+
+// the window is sent with 10 bit coordinates
+#[cfg(feature = "epd13in3k")]
+#[test]
+fn epd13in3k() {
+    use epd_waveshare::epd13in3k;
+
+    let trace = record_scenario::<epd13in3k::Epd13in3k<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd13in3k", &trace);
+}
+
+#[cfg(feature = "epd2in13bc")]
+#[test]
+fn epd2in13bc() {
+    use epd_waveshare::epd2in13bc;
+
+    let trace = record_scenario::<epd2in13bc::Epd2in13bc<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd2in13bc", &trace);
+}
+
+// the red RAM is shown as is, unlike in the Waveshare examples
+#[cfg(feature = "epd2in13b_v4")]
+#[test]
+fn epd2in13b_v4() {
+    use epd_waveshare::epd2in13b_v4;
+
+    let trace = record_scenario::<epd2in13b_v4::Epd2in13b<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in13b_v4", &trace);
+}
+
+// the same controller as the 2.13" (B) V4 with 296 gates
+#[cfg(feature = "epd2in9b_v3")]
+#[test]
+fn epd2in9b_v3() {
+    use epd_waveshare::epd2in9b_v3;
+
+    let trace = record_scenario::<epd2in9b_v3::Epd2in9b<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in9b_v3", &trace);
+}
+
+// the controller of the 2.9" (B) V3 with 264 gates and both RAMs for black/white
+#[cfg(feature = "epd2in7_v2")]
+#[test]
+fn epd2in7_v2() {
+    use epd_waveshare::epd2in7_v2;
+
+    let trace = record_scenario::<epd2in7_v2::Epd2in7<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in7_v2", &trace);
+}
+
+#[cfg(feature = "epd2in7_v2")]
+#[test]
+fn epd2in7_v2_lut() {
+    use epd_waveshare::epd2in7_v2;
+
+    let trace = record_lut_scenario::<epd2in7_v2::Epd2in7<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in7_v2_lut", &trace);
+}
+
+// the 4-gray LUT is uploaded once and replaced by the OTP one for black/white again
+#[cfg(feature = "epd2in7_v2")]
+#[test]
+fn epd2in7_v2_gray() {
+    use epd_waveshare::epd2in7_v2::{Epd2in7, GRAY_BUFFER_LEN};
+    use epd_waveshare::prelude::WaveshareDisplay;
+
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+
+    bus.section("new");
+    let mut epd = Epd2in7::new(&mut spi, Busy(false), bus.dc(), Rst, &mut delay, None).unwrap();
+    let gray: Vec<u8> = (0..GRAY_BUFFER_LEN).map(|i| (i * 7 % 251) as u8).collect();
+
+    bus.section("update_frame_gray");
+    epd.update_frame_gray(&mut spi, &mut delay, &gray).unwrap();
+
+    bus.section("display_frame");
+    epd.display_frame(&mut spi, &mut delay).unwrap();
+
+    bus.section("update_frame_gray again");
+    epd.update_frame_gray(&mut spi, &mut delay, &gray).unwrap();
+
+    bus.section("update_frame");
+    epd.update_frame(&mut spi, &gray[..GRAY_BUFFER_LEN / 2], &mut delay)
+        .unwrap();
+
+    bus.section("display_frame");
+    epd.display_frame(&mut spi, &mut delay).unwrap();
+
+    assert_golden("epd2in7_v2_gray", &bus.trace());
+}
+
+#[cfg(feature = "epd1in54")]
+#[test]
+fn epd1in54() {
+    use epd_waveshare::epd1in54;
+
+    let trace = record_scenario::<epd1in54::Epd1in54<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd1in54", &trace);
+}
+
+// the black/white plane is sent with 2 bits per pixel
+#[cfg(feature = "epd1in54b")]
+#[test]
+fn epd1in54b() {
+    use epd_waveshare::epd1in54b;
+
+    let trace = record_scenario::<epd1in54b::Epd1in54b<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd1in54b", &trace);
+}
+
+// the sequences differ between both versions of the display
+#[cfg(feature = "epd2in13")]
+#[test]
+fn epd2in13_v2() {
+    use epd_waveshare::epd2in13_v2;
+
+    let trace = record_scenario::<epd2in13_v2::Epd2in13<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    let name = if cfg!(feature = "epd2in13_v2") {
+        "epd2in13_v2"
+    } else {
+        "epd2in13_v3"
+    };
+    assert_golden(name, &trace);
+}
+
+// both G series panels share the init, only the resolution differs
+#[cfg(feature = "epd2in15g")]
+#[test]
+fn epd2in15g() {
+    use epd_waveshare::epd2in15g;
+
+    let trace = record_scenario::<epd2in15g::Epd2in15g<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd2in15g", &trace);
+}
+
+#[cfg(feature = "epd2in9g")]
+#[test]
+fn epd2in9g() {
+    use epd_waveshare::epd2in9g;
+
+    let trace = record_scenario::<epd2in9g::Epd2in9g<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd2in9g", &trace);
+}
+
+// both versions of the 2.9" switch between the LUTs of quick and full refreshes
+#[cfg(feature = "epd2in9")]
+#[test]
+fn epd2in9_quick() {
+    use epd_waveshare::epd2in9;
+
+    let trace = record_quick_scenario::<epd2in9::Epd2in9<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in9_quick", &trace);
+}
+
+#[cfg(feature = "epd2in9_v2")]
+#[test]
+fn epd2in9_v2_quick() {
+    use epd_waveshare::epd2in9_v2;
+
+    let trace = record_quick_scenario::<epd2in9_v2::Epd2in9<Spi, Busy, Dc, Rst, NoopDelay>>(false);
+    assert_golden("epd2in9_v2_quick", &trace);
+}
+
+// the UC81xx controllers share the old/new frame RAMs and the partial window
+#[cfg(feature = "epd4in2")]
+#[test]
+fn epd4in2_quick() {
+    use epd_waveshare::epd4in2;
+
+    let trace = record_quick_scenario::<epd4in2::Epd4in2<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd4in2_quick", &trace);
+}
+
+#[cfg(feature = "epd4in2")]
+#[test]
+fn epd4in2_partial() {
+    use epd_waveshare::epd4in2;
+
+    let trace = record_partial_scenario::<epd4in2::Epd4in2<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd4in2_partial", &trace);
+}
+
+#[cfg(feature = "epd2in9bc")]
+#[test]
+fn epd2in9bc_quick() {
+    use epd_waveshare::epd2in9bc;
+
+    let trace = record_quick_scenario::<epd2in9bc::Epd2in9bc<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd2in9bc_quick", &trace);
+}
+
+#[cfg(feature = "epd2in9bc")]
+#[test]
+fn epd2in9bc_partial() {
+    use epd_waveshare::epd2in9bc;
+
+    let trace =
+        record_partial_scenario::<epd2in9bc::Epd2in9bc<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd2in9bc_partial", &trace);
+}
+
+// the LUT of the refresh is loaded before the auto sequence starts it
+#[cfg(feature = "epd3in52")]
+#[test]
+fn epd3in52() {
+    use epd_waveshare::epd3in52;
+
+    let trace = record_scenario::<epd3in52::Epd3in52<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd3in52", &trace);
+}
+
+#[cfg(feature = "epd3in52")]
+#[test]
+fn epd3in52_lut() {
+    use epd_waveshare::epd3in52;
+
+    let trace = record_lut_scenario::<epd3in52::Epd3in52<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd3in52_lut", &trace);
+}
+
+// the controller has to be powered off after every refresh
+#[cfg(feature = "epd7in5b_v2")]
+#[test]
+fn epd7in5b_v2() {
+    use epd_waveshare::epd7in5b_v2;
+
+    let trace = record_scenario::<epd7in5b_v2::Epd7in5<Spi, Busy, Dc, Rst, NoopDelay>>(true);
+    assert_golden("epd7in5b_v2", &trace);
+}
+
+/// Records clear and update with a non-default background color, this pins down
+/// how each driver encodes the background color in its planes
+macro_rules! background {
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, $color:expr) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::{color::*, $module};
+
+            let trace =
+                record_background::<$module::$epd<Spi, Busy, Dc, Rst, NoopDelay>>($idle, $color);
+            assert_golden(stringify!($name), &trace);
+        }
+    };
+}
+
+background!(
+    "epd1in54",
+    epd1in54_black,
+    epd1in54,
+    Epd1in54,
+    false,
+    Color::Black
+);
+background!(
+    "epd1in54_v2",
+    epd1in54_v2_black,
+    epd1in54_v2,
+    Epd1in54,
+    false,
+    Color::Black
+);
+background!(
+    "epd1in54b",
+    epd1in54b_black,
+    epd1in54b,
+    Epd1in54b,
+    true,
+    Color::Black
+);
+background!(
+    "epd1in54c",
+    epd1in54c_black,
+    epd1in54c,
+    Epd1in54c,
+    true,
+    Color::Black
+);
+background!(
+    "epd2in13",
+    epd2in13_v2_black,
+    epd2in13_v2,
+    Epd2in13,
+    false,
+    Color::Black
+);
+background!(
+    "epd2in13bc",
+    epd2in13bc_black,
+    epd2in13bc,
+    Epd2in13bc,
+    true,
+    TriColor::Black
+);
+background!(
+    "epd2in13bc",
+    epd2in13bc_chromatic,
+    epd2in13bc,
+    Epd2in13bc,
+    true,
+    TriColor::Chromatic
+);
+background!(
+    "epd2in13b_v4",
+    epd2in13b_v4_chromatic,
+    epd2in13b_v4,
+    Epd2in13b,
+    false,
+    TriColor::Chromatic
+);
+background!(
+    "epd2in13d",
+    epd2in13d_black,
+    epd2in13d,
+    Epd2in13d,
+    true,
+    Color::Black
+);
+background!(
+    "epd2in7_v2",
+    epd2in7_v2_black,
+    epd2in7_v2,
+    Epd2in7,
+    false,
+    Color::Black
+);
+background!(
+    "epd2in7b",
+    epd2in7b_black,
+    epd2in7b,
+    Epd2in7b,
+    true,
+    Color::Black
+);
+background!(
+    "epd2in9",
+    epd2in9_black,
+    epd2in9,
+    Epd2in9,
+    false,
+    Color::Black
+);
+background!(
+    "epd2in9_v2",
+    epd2in9_v2_black,
+    epd2in9_v2,
+    Epd2in9,
+    false,
+    Color::Black
+);
+background!(
+    "epd2in9b_v3",
+    epd2in9b_v3_chromatic,
+    epd2in9b_v3,
+    Epd2in9b,
+    false,
+    TriColor::Chromatic
+);
+background!(
+    "epd2in9bc",
+    epd2in9bc_black,
+    epd2in9bc,
+    Epd2in9bc,
+    true,
+    Color::Black
+);
+background!(
+    "epd3in7",
+    epd3in7_black,
+    epd3in7,
+    EPD3in7,
+    false,
+    Color::Black
+);
+background!(
+    "epd4in2",
+    epd4in2_black,
+    epd4in2,
+    Epd4in2,
+    true,
+    Color::Black
+);
+background!(
+    "epd5in79",
+    epd5in79_black,
+    epd5in79,
+    Epd5in79,
+    false,
+    Color::Black
+);
+background!(
+    "epd5in83_v2",
+    epd5in83_v2_black,
+    epd5in83_v2,
+    Epd5in83,
+    true,
+    Color::Black
+);
+background!(
+    "epd5in83b_v2",
+    epd5in83b_v2_black,
+    epd5in83b_v2,
+    Epd5in83,
+    true,
+    Color::Black
+);
+// the default background, which once filled the whole chromatic plane
+background!(
+    "epd5in83b_v2",
+    epd5in83b_v2_white,
+    epd5in83b_v2,
+    Epd5in83,
+    true,
+    Color::White
+);
+background!(
+    "epd7in5_hd",
+    epd7in5_hd_black,
+    epd7in5_hd,
+    Epd7in5,
+    false,
+    Color::Black
+);
@@ -0,0 +1,53 @@
+//! The panel registry covers every driver module and matches the checked in support matrix
+//!
+//! Both tests need all panels, update the matrix with
+//! `UPDATE_GOLDEN=1 cargo test --test panels`.
+
+#![cfg(feature = "all-displays")]
+
+mod common;
+
+use std::fmt::Write;
+use std::fs;
+
+use epd_waveshare::panels::SUPPORTED_PANELS;
+
+/// A new `src/epdXinY/` module fails here until it is added to the registry in `src/panels.rs`
+#[test]
+fn registry_covers_all_driver_modules() {
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+    let mut modules: Vec<String> = fs::read_dir(src)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("mod.rs").is_file())
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("epd"))
+        .collect();
+    modules.sort();
+
+    let registered: Vec<&str> = SUPPORTED_PANELS.iter().map(|panel| panel.name).collect();
+    assert_eq!(registered, modules);
+}
+
+#[test]
+fn support_matrix() {
+    let mut matrix = String::from(
+        "| Panel | Width | Height | Colors | Partial | Quick Refresh |\n\
+         | :--- | ---: | ---: | ---: | :---: | :---: |\n",
+    );
+    let mark = |supported| if supported { "✔" } else { "✕" };
+    for panel in SUPPORTED_PANELS {
+        writeln!(
+            matrix,
+            "| {} | {} | {} | {} | {} | {} |",
+            panel.name,
+            panel.width,
+            panel.height,
+            panel.colors,
+            mark(panel.partial),
+            mark(panel.quick_refresh)
+        )
+        .unwrap();
+    }
+    common::assert_golden_file("support_matrix.md", &matrix);
+}
@@ -0,0 +1,73 @@
+//! Feeds windows off the panel to the partial updates of every driver, e.g. from
+//! layout math wrapping around below 0, see `common::invalid_windows`
+//!
+//! The drivers have to refuse them with `Error::InvalidWindow` before sending anything,
+//! without overflowing in the coordinate math.
+
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports))]
+
+mod common;
+
+use common::{assert_quick_rejects_windows, assert_rejects_windows, Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+
+macro_rules! rejects {
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module;
+
+            assert_rejects_windows::<$module::$epd<Spi, Busy, Dc, Rst, NoopDelay>>($idle);
+        }
+    };
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, quick) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module;
+
+            assert_rejects_windows::<$module::$epd<Spi, Busy, Dc, Rst, NoopDelay>>($idle);
+            assert_quick_rejects_windows::<$module::$epd<Spi, Busy, Dc, Rst, NoopDelay>>($idle);
+        }
+    };
+}
+
+rejects!("epd13in3k", epd13in3k, epd13in3k, Epd13in3k, false);
+rejects!("epd1in54", epd1in54, epd1in54, Epd1in54, false);
+rejects!(
+    "epd1in54_v2",
+    epd1in54_v2,
+    epd1in54_v2,
+    Epd1in54,
+    false,
+    quick
+);
+rejects!("epd1in54b", epd1in54b, epd1in54b, Epd1in54b, true);
+rejects!("epd1in54c", epd1in54c, epd1in54c, Epd1in54c, true);
+rejects!("epd2in13", epd2in13_v2, epd2in13_v2, Epd2in13, false);
+rejects!("epd2in13b_v4", epd2in13b_v4, epd2in13b_v4, Epd2in13b, false);
+rejects!("epd2in13bc", epd2in13bc, epd2in13bc, Epd2in13bc, true);
+rejects!("epd2in13d", epd2in13d, epd2in13d, Epd2in13d, true, quick);
+rejects!("epd2in15g", epd2in15g, epd2in15g, Epd2in15g, true);
+rejects!("epd2in36g", epd2in36g, epd2in36g, Epd2in36g, true);
+rejects!("epd2in66b", epd2in66b, epd2in66b, Epd2in66b, false);
+rejects!("epd2in7b", epd2in7b, epd2in7b, Epd2in7b, true);
+rejects!("epd2in9", epd2in9, epd2in9, Epd2in9, false, quick);
+rejects!("epd2in9_v2", epd2in9_v2, epd2in9_v2, Epd2in9, false, quick);
+rejects!("epd2in9b_v3", epd2in9b_v3, epd2in9b_v3, Epd2in9b, false);
+rejects!("epd2in9bc", epd2in9bc, epd2in9bc, Epd2in9bc, true, quick);
+rejects!("epd2in9d", epd2in9d, epd2in9d, Epd2in9d, false);
+rejects!("epd2in9g", epd2in9g, epd2in9g, Epd2in9g, true);
+rejects!("epd3in52", epd3in52, epd3in52, Epd3in52, true);
+rejects!("epd3in7", epd3in7, epd3in7, EPD3in7, false);
+rejects!("epd4in2", epd4in2, epd4in2, Epd4in2, true, quick);
+rejects!("epd5in65f", epd5in65f, epd5in65f, Epd5in65f, true);
+rejects!("epd5in79", epd5in79, epd5in79, Epd5in79, false);
+rejects!("epd5in83_v2", epd5in83_v2, epd5in83_v2, Epd5in83, true);
+rejects!("epd5in83b_v2", epd5in83b_v2, epd5in83b_v2, Epd5in83, true);
+rejects!("epd7in5", epd7in5, epd7in5, Epd7in5, true);
+rejects!("epd7in5_hd", epd7in5_hd, epd7in5_hd, Epd7in5, false);
+rejects!("epd7in5_v2", epd7in5_v2, epd7in5_v2, Epd7in5, true);
+rejects!("epd7in5b_v2", epd7in5b_v2, epd7in5b_v2, Epd7in5, true);
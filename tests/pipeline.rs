@@ -0,0 +1,88 @@
+//! Rendering on one thread while another one owns the driver and talks to the panel
+//!
+//! The frames are moved between the threads with `Display::into_buffer`, the driver,
+//! its spi device and pins are moved into the flushing thread. The panel has to see
+//! exactly what it sees when everything runs on a single thread.
+
+#![cfg(all(feature = "epd4in2", feature = "graphics"))]
+
+mod common;
+
+use common::{Bus, Busy, Rst};
+use embedded_graphics::{
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::{
+    color::Color,
+    epd4in2::{Display4in2, Epd4in2},
+    prelude::*,
+};
+use std::{sync::mpsc, thread};
+
+const FRAMES: i32 = 3;
+
+fn render(frame: i32) -> Display4in2 {
+    let mut display = Display4in2::default();
+    display.clear(Color::White).unwrap();
+    let _ = Rectangle::new(Point::new(20 * frame, 10), Size::new(40, 30))
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(&mut display);
+    display
+}
+
+fn single_thread() -> String {
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = Epd4in2::new(&mut spi, Busy(true), bus.dc(), Rst, &mut delay, None).unwrap();
+
+    for frame in 0..FRAMES {
+        let display = render(frame);
+        epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)
+            .unwrap();
+    }
+    epd.sleep(&mut spi, &mut delay).unwrap();
+    bus.trace()
+}
+
+#[test]
+fn render_and_flush_threads() {
+    let bus = Bus::default();
+    let mut spi = bus.spi();
+    let mut delay = NoopDelay::new();
+    let mut epd = Epd4in2::new(&mut spi, Busy(true), bus.dc(), Rst, &mut delay, None).unwrap();
+
+    let (frames, received) = mpsc::sync_channel(1);
+    let renderer = thread::spawn(move || {
+        for frame in 0..FRAMES {
+            frames.send(render(frame).into_buffer()).unwrap();
+        }
+    });
+    let flusher = thread::spawn(move || {
+        let mut flushed = 0;
+        for frame in received {
+            let display = Display4in2::from_buffer(frame, DisplayRotation::Rotate0);
+            epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)
+                .unwrap();
+            flushed += 1;
+        }
+        epd.sleep(&mut spi, &mut delay).unwrap();
+        flushed
+    });
+
+    renderer.join().unwrap();
+    assert_eq!(flusher.join().unwrap(), FRAMES);
+    assert_eq!(bus.trace(), single_thread());
+}
+
+#[test]
+fn buffer_round_trip() {
+    let display = render(1);
+    let expected = display.buffer().to_vec();
+
+    let restored = Display4in2::from_buffer(display.into_buffer(), DisplayRotation::Rotate90);
+    assert_eq!(restored.buffer(), &expected[..]);
+    assert!(matches!(restored.rotation(), DisplayRotation::Rotate90));
+}
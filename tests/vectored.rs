@@ -0,0 +1,194 @@
+//! Sends frames split at awkward places with the vectored updates and compares the spi
+//! traffic with the one of the contiguous frame, see `common::awkward_splits`
+//!
+//! Every driver has to send exactly the bytes of `update_frame` (`update_color_frame`
+//! for both planes) and refuse parts which don't add up to a frame before sending
+//! anything.
+
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports))]
+
+mod common;
+
+use common::{awkward_splits, record_update, test_frame, Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use epd_waveshare::{line_bytes, prelude::*, Error};
+
+macro_rules! vectored {
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr) => {
+        vectored!($feature, $name, $module, $epd, $idle, rows 1);
+    };
+    // frames of `rows` times the rows of a black/white one, for more bits per pixel
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, rows $rows:expr) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module::{$epd, HEIGHT, WIDTH};
+
+            let stride = line_bytes(WIDTH) as usize;
+            let frame = test_frame(WIDTH, HEIGHT * $rows);
+            let contiguous = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                epd.update_frame(spi, &frame, delay)
+            });
+            for parts in awkward_splits(&frame, stride) {
+                let trace = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                    epd.update_frame_vectored(spi, &parts, delay)
+                });
+                assert_eq!(trace, contiguous, "{} parts", parts.len());
+            }
+
+            let short = [&frame[..stride], &frame[stride + 1..]];
+            let long = [&frame[..], &frame[..1]];
+            for parts in [&short[..], &long[..], &[]] {
+                let trace = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                    let result = epd.update_frame_vectored(spi, parts, delay);
+                    assert_eq!(result, Err(Error::InvalidDataLength));
+                    Ok(())
+                });
+                assert_eq!(trace, "");
+            }
+        }
+    };
+    ($feature:literal, $name:ident, $module:ident, $epd:ident, $idle:expr, color) => {
+        #[cfg(feature = $feature)]
+        #[test]
+        fn $name() {
+            use epd_waveshare::$module::{$epd, HEIGHT, WIDTH};
+
+            let stride = line_bytes(WIDTH) as usize;
+            let black = test_frame(WIDTH, HEIGHT);
+            let chromatic: Vec<u8> = black.iter().map(|byte| byte.rotate_left(3)).collect();
+            let contiguous = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                epd.update_color_frame(spi, delay, &black, &chromatic)
+            });
+            let splits = awkward_splits(&black, stride)
+                .into_iter()
+                .zip(awkward_splits(&chromatic, stride).into_iter().rev());
+            for (black_parts, chromatic_parts) in splits {
+                let trace = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                    epd.update_color_frame_vectored(spi, delay, &black_parts, &chromatic_parts)
+                });
+                assert_eq!(
+                    trace,
+                    contiguous,
+                    "{} and {} parts",
+                    black_parts.len(),
+                    chromatic_parts.len()
+                );
+            }
+
+            let trace = record_update::<$epd<Spi, Busy, Dc, Rst, NoopDelay>, _>($idle, |epd, spi, delay| {
+                let result =
+                    epd.update_color_frame_vectored(spi, delay, &[&black], &[&chromatic[1..]]);
+                assert_eq!(result, Err(Error::InvalidDataLength));
+                Ok(())
+            });
+            assert_eq!(trace, "");
+        }
+    };
+}
+
+vectored!("epd13in3k", epd13in3k, epd13in3k, Epd13in3k, false);
+vectored!("epd1in54", epd1in54, epd1in54, Epd1in54, false);
+vectored!("epd1in54_v2", epd1in54_v2, epd1in54_v2, Epd1in54, false);
+vectored!("epd1in54b", epd1in54b, epd1in54b, Epd1in54b, true);
+vectored!(
+    "epd1in54b",
+    epd1in54b_color,
+    epd1in54b,
+    Epd1in54b,
+    true,
+    color
+);
+vectored!("epd1in54c", epd1in54c, epd1in54c, Epd1in54c, true);
+vectored!(
+    "epd1in54c",
+    epd1in54c_color,
+    epd1in54c,
+    Epd1in54c,
+    true,
+    color
+);
+vectored!("epd2in13", epd2in13_v2, epd2in13_v2, Epd2in13, false);
+vectored!("epd2in13b_v4", epd2in13b_v4, epd2in13b_v4, Epd2in13b, false);
+vectored!(
+    "epd2in13b_v4",
+    epd2in13b_v4_color,
+    epd2in13b_v4,
+    Epd2in13b,
+    false,
+    color
+);
+vectored!("epd2in13bc", epd2in13bc, epd2in13bc, Epd2in13bc, true);
+vectored!(
+    "epd2in13bc",
+    epd2in13bc_color,
+    epd2in13bc,
+    Epd2in13bc,
+    true,
+    color
+);
+vectored!("epd2in13d", epd2in13d, epd2in13d, Epd2in13d, true);
+vectored!("epd2in15g", epd2in15g, epd2in15g, Epd2in15g, true, rows 2);
+vectored!("epd2in36g", epd2in36g, epd2in36g, Epd2in36g, true, rows 2);
+vectored!("epd2in66b", epd2in66b, epd2in66b, Epd2in66b, false);
+vectored!(
+    "epd2in66b",
+    epd2in66b_color,
+    epd2in66b,
+    Epd2in66b,
+    false,
+    color
+);
+vectored!("epd2in7_v2", epd2in7_v2, epd2in7_v2, Epd2in7, false);
+vectored!("epd2in7b", epd2in7b, epd2in7b, Epd2in7b, true);
+vectored!("epd2in7b", epd2in7b_color, epd2in7b, Epd2in7b, true, color);
+vectored!("epd2in9", epd2in9, epd2in9, Epd2in9, false);
+vectored!("epd2in9_v2", epd2in9_v2, epd2in9_v2, Epd2in9, false);
+vectored!("epd2in9b_v3", epd2in9b_v3, epd2in9b_v3, Epd2in9b, false);
+vectored!(
+    "epd2in9b_v3",
+    epd2in9b_v3_color,
+    epd2in9b_v3,
+    Epd2in9b,
+    false,
+    color
+);
+vectored!("epd2in9bc", epd2in9bc, epd2in9bc, Epd2in9bc, true);
+vectored!(
+    "epd2in9bc",
+    epd2in9bc_color,
+    epd2in9bc,
+    Epd2in9bc,
+    true,
+    color
+);
+vectored!("epd2in9d", epd2in9d, epd2in9d, Epd2in9d, false);
+vectored!("epd2in9g", epd2in9g, epd2in9g, Epd2in9g, true, rows 2);
+vectored!("epd3in52", epd3in52, epd3in52, Epd3in52, true);
+vectored!("epd3in7", epd3in7, epd3in7, EPD3in7, false);
+vectored!("epd4in2", epd4in2, epd4in2, Epd4in2, true);
+vectored!("epd5in65f", epd5in65f, epd5in65f, Epd5in65f, true, rows 4);
+vectored!("epd5in79", epd5in79, epd5in79, Epd5in79, false);
+vectored!("epd5in83_v2", epd5in83_v2, epd5in83_v2, Epd5in83, true);
+vectored!("epd5in83b_v2", epd5in83b_v2, epd5in83b_v2, Epd5in83, true);
+vectored!(
+    "epd5in83b_v2",
+    epd5in83b_v2_color,
+    epd5in83b_v2,
+    Epd5in83,
+    true,
+    color
+);
+vectored!("epd7in5", epd7in5, epd7in5, Epd7in5, true);
+vectored!("epd7in5_hd", epd7in5_hd, epd7in5_hd, Epd7in5, false);
+vectored!("epd7in5_v2", epd7in5_v2, epd7in5_v2, Epd7in5, true);
+vectored!("epd7in5b_v2", epd7in5b_v2, epd7in5b_v2, Epd7in5, true, rows 2);
+vectored!(
+    "epd7in5b_v2",
+    epd7in5b_v2_color,
+    epd7in5b_v2,
+    Epd7in5,
+    true,
+    color
+);
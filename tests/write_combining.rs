@@ -0,0 +1,51 @@
+//! The `write-combining` feature sends the same bytes as the golden traces in fewer
+//! writes, see `common/mod.rs`
+//!
+//! Run with and without `--features write-combining`, both check the number of writes.
+
+// every test needs its panel feature, see `features.rs`
+#![cfg_attr(not(feature = "all-displays"), allow(unused_imports))]
+
+mod common;
+
+use common::{assert_golden, record_scenario_on, Bus, Busy, Dc, Rst, Spi};
+use embedded_hal_mock::eh1::delay::NoopDelay;
+
+/// Checks the trace against the golden one and the writes on `bus` against `uncombined`
+/// or `combined`, depending on the feature
+#[allow(dead_code)]
+fn assert_writes(name: &str, bus: &Bus, trace: &str, (uncombined, combined): (usize, usize)) {
+    assert_golden(name, trace);
+    assert!(combined * 40 < uncombined);
+    let expected = if cfg!(feature = "write-combining") {
+        combined
+    } else {
+        uncombined
+    };
+    assert_eq!(bus.writes(), expected);
+}
+
+// the UC81xx controller, every data byte is a write of its own without combining
+#[cfg(feature = "epd4in2")]
+#[test]
+fn epd4in2() {
+    use epd_waveshare::epd4in2;
+
+    let bus = Bus::default();
+    let trace = record_scenario_on::<epd4in2::Epd4in2<Spi, Busy, Dc, Rst, NoopDelay>>(&bus, true);
+    assert_eq!(bus.bytes(), 60_263);
+    assert_writes("epd4in2", &bus, &trace, (45_048, 753));
+}
+
+// the SSD16xx controller, with parameter lists of several bytes
+#[cfg(feature = "epd1in54")]
+#[test]
+fn epd1in54() {
+    use epd_waveshare::epd1in54;
+
+    let bus = Bus::default();
+    let trace =
+        record_scenario_on::<epd1in54::Epd1in54<Spi, Busy, Dc, Rst, NoopDelay>>(&bus, false);
+    assert_eq!(bus.bytes(), 10_081);
+    assert_writes("epd1in54", &bus, &trace, (5_040, 119));
+}